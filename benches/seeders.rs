@@ -0,0 +1,106 @@
+//! Criterion benchmarks for seeder chains and body codecs, run with `cargo bench`.
+//!
+//! Codec benchmarks are only run when their feature is enabled, as in
+//! `cargo bench --features serde_json,csv`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use grazie::core::seeder::{BoxBody, Guard, Seeder};
+use grazie::http::HttpRequest;
+use grazie::query::Pagination;
+use std::hint::black_box;
+use tokio::runtime::Runtime;
+
+/// A seeder which accepts every request, so chains of it measure the chain itself.
+struct Pass;
+
+impl Seeder for Pass {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        input
+    }
+}
+
+fn request(uri: &str, body: &[u8]) -> HttpRequest<BoxBody> {
+    HttpRequest::builder().uri(uri).body(BoxBody::new(body.into())).unwrap()
+}
+
+fn runtime() -> Runtime {
+    tokio::runtime::Builder::new_current_thread().build().unwrap()
+}
+
+fn chains(c: &mut Criterion) {
+    let runtime = runtime();
+    let single = Pass;
+    let stack = Pass.then(Pass).then(Pass).then(Pass).then(Pass).then(Pass).then(Pass).then(Pass);
+
+    c.bench_function("chain/1", |b| {
+        b.to_async(&runtime).iter(|| async {
+            let mut request = request("/", b"");
+            matches!(single.seed(Guard::Accessible(&mut request)).await, Guard::Accessible(_))
+        })
+    });
+    c.bench_function("chain/8", |b| {
+        b.to_async(&runtime).iter(|| async {
+            let mut request = request("/", b"");
+            matches!(stack.seed(Guard::Accessible(&mut request)).await, Guard::Accessible(_))
+        })
+    });
+}
+
+fn queries(c: &mut Criterion) {
+    let paged = request("/orders?page=3&per_page=50&sort=-created", b"");
+    c.bench_function("query/pagination", |b| {
+        b.iter(|| Pagination::from_request(black_box(&paged)).is_ok())
+    });
+}
+
+#[cfg(feature = "serde_json")]
+fn json(c: &mut Criterion) {
+    let runtime = runtime();
+
+    let json = br#"{"id":7,"name":"Ada","tags":["a","b","c"],"active":true}"#;
+    let body = BoxBody::new(json.to_vec().into_boxed_slice());
+    c.bench_function("codec/json/open", |b| {
+        b.to_async(&runtime).iter(|| async {
+            black_box(&body).open_json::<serde_json::Value>().await
+        })
+    });
+
+    let value = serde_json::json!({
+        "id": 7,
+        "name": "Ada",
+        "tags": ["a", "b", "c"],
+        "active": true,
+    });
+    c.bench_function("codec/json/close", |b| {
+        b.to_async(&runtime).iter(|| async {
+            let mut body = BoxBody::empty();
+            body.close_json(black_box(&value)).await
+        })
+    });
+}
+
+#[cfg(not(feature = "serde_json"))]
+fn json(_: &mut Criterion) {}
+
+#[cfg(feature = "csv")]
+fn csv(c: &mut Criterion) {
+    let rows: Vec<(u32, String)> = (0..100).map(|id| (id, format!("row {id}"))).collect();
+    let csv = grazie::csv::Csv::new().headers(false);
+    let body = csv.encode(&rows).unwrap();
+
+    c.bench_function("codec/csv/encode (100 rows)", |b| {
+        b.iter(|| csv.encode(black_box(&rows)).is_ok())
+    });
+    c.bench_function("codec/csv/decode (100 rows)", |b| {
+        b.iter(|| csv.decode::<(u32, String)>(black_box(&body)).map(|rows| rows.len()))
+    });
+}
+
+#[cfg(not(feature = "csv"))]
+fn csv(_: &mut Criterion) {}
+
+criterion_group!(benches, chains, queries, json, csv);
+criterion_main!(benches);
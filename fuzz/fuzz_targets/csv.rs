@@ -0,0 +1,20 @@
+//! Decodes arbitrary bodies as CSV, and checks that whatever decodes survives a round trip through
+//! the encoder.
+
+#![no_main]
+
+use grazie::core::seeder::BoxBody;
+use grazie::csv::Csv;
+use libfuzzer_sys::fuzz_target;
+use std::collections::HashMap;
+
+fuzz_target!(|data: &[u8]| {
+    let body = BoxBody::new(data.into());
+    let _ = Csv::new().decode::<HashMap<String, String>>(&body);
+
+    let csv = Csv::new().headers(false);
+    let Ok(rows) = csv.decode::<Vec<String>>(&body) else { return; };
+
+    let encoded = csv.encode(&rows).expect("decoded rows encode");
+    assert_eq!(csv.decode::<Vec<String>>(&encoded).expect("encoded rows decode"), rows);
+});
@@ -0,0 +1,25 @@
+//! Parses arbitrary query strings as pagination, sorting and filters, which exercises the query
+//! string splitting and percent decoding every query parameter goes through.
+
+#![no_main]
+
+use grazie::core::seeder::BoxBody;
+use grazie::http::HttpRequest;
+use grazie::query::{Filter, Pagination, Sort};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|query: &str| {
+    let request = HttpRequest::builder().uri(format!("/?{query}")).body(BoxBody::empty());
+    let Ok(request) = request else { return; };
+
+    if let Ok(pagination) = Pagination::from_request(&request) {
+        assert!(pagination.page >= 1);
+        assert!((1..=100).contains(&pagination.per_page));
+    }
+
+    let _ = Sort::from_request(&request, &["created", "name"]);
+
+    if let Ok(filter) = Filter::from_request(&request, &["status", "owner"]) {
+        assert!(filter.iter().all(|(field, _)| field == "status" || field == "owner"));
+    }
+});
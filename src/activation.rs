@@ -0,0 +1,96 @@
+//! Socket activation, for taking over listening sockets bound by a service manager.
+//!
+//! systemd (and compatible managers) can bind a service's sockets itself and pass them to the
+//! service when it starts, following the `sd_listen_fds` protocol. This lets the service listen on
+//! privileged ports without running as root, and lets the manager hold on to connections while the
+//! service restarts.
+
+use std::env;
+use std::io;
+use std::net::TcpListener;
+use std::os::fd::{FromRawFd, RawFd};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The first file descriptor passed by socket activation.
+const LISTEN_FDS_START: RawFd = 3;
+
+/// Whether the passed sockets have already been taken.
+static TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// A listening socket passed by socket activation.
+#[derive(Debug)]
+pub struct ActivatedListener {
+    /// The name of the socket, from `FileDescriptorName=` in its systemd unit. Sockets without a
+    /// name are called `unknown`, as with `sd_listen_fds_with_names`.
+    pub name: String,
+
+    /// The listening socket.
+    pub listener: TcpListener,
+}
+
+/// Takes the listening sockets passed through `LISTEN_FDS`, in the order they were passed.
+///
+/// Returns an empty list if the process wasn't socket-activated, or if `LISTEN_PID` names a
+/// different process (such as the parent which was activated). The sockets can only be taken once;
+/// later calls return an empty list, so that no socket is ever owned twice.
+///
+/// Every passed socket is assumed to be a TCP listener, as configured with `ListenStream=`.
+pub fn listeners() -> io::Result<Vec<ActivatedListener>> {
+    let Some(count) = passed_fds()? else { return Ok(Vec::new()); };
+
+    if TAKEN.swap(true, Ordering::SeqCst) {
+        return Ok(Vec::new());
+    }
+
+    let names = env::var("LISTEN_FDNAMES").unwrap_or_default();
+    let mut names = names.split(':');
+
+    let listeners = (0..count)
+        .map(|index| {
+            let name = names.next().filter(|name| !name.is_empty()).unwrap_or("unknown");
+
+            // SAFETY: The service manager passes ownership of the descriptors starting at
+            // `LISTEN_FDS_START` to this process, and `TAKEN` ensures they're only wrapped once.
+            let listener = unsafe { TcpListener::from_raw_fd(LISTEN_FDS_START + index) };
+
+            ActivatedListener {
+                name: name.to_owned(),
+                listener,
+            }
+        })
+        .collect();
+
+    Ok(listeners)
+}
+
+/// Takes the passed listening socket with the provided name, closing none of the others.
+///
+/// Since sockets can only be taken once, this takes every passed socket, returning the rest
+/// alongside the named one.
+pub fn listener_named(name: &str) -> io::Result<(Option<TcpListener>, Vec<ActivatedListener>)> {
+    let mut listeners = listeners()?;
+
+    let named = listeners
+        .iter()
+        .position(|listener| listener.name == name)
+        .map(|index| listeners.remove(index).listener);
+
+    Ok((named, listeners))
+}
+
+/// Reads the number of sockets passed to this process, or `None` if none were.
+fn passed_fds() -> io::Result<Option<RawFd>> {
+    let (Ok(pid), Ok(fds)) = (env::var("LISTEN_PID"), env::var("LISTEN_FDS")) else {
+        return Ok(None);
+    };
+
+    if pid.trim().parse::<u32>().ok() != Some(std::process::id()) {
+        return Ok(None);
+    }
+
+    match fds.trim().parse::<RawFd>() {
+        Ok(0) => { Ok(None) }
+        Ok(count) if count > 0 => { Ok(Some(count)) }
+        _ => { Err(io::Error::new(io::ErrorKind::InvalidData, format!("invalid LISTEN_FDS value `{fds}`"))) }
+    }
+}
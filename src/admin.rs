@@ -0,0 +1,331 @@
+//! An optional admin/control socket, for issuing runtime commands to a running server.
+//!
+//! The admin socket listens on a loopback TCP port (or a Unix domain socket), separately from the
+//! `HttpServer`. It speaks a simple line-based protocol: every line sent by a client holds the
+//! admin token, a command, and the command's arguments, separated by whitespace. Lines longer
+//! than `MAX_LINE` bytes close the connection.
+//!
+//! Lines with the wrong token are answered only after a delay, one at a time across every
+//! connection, and a connection is closed after `MAX_FAILED_AUTH` of them.
+//!
+//! ```text
+//! > s3cr3t maintenance on
+//! < ok maintenance enabled
+//! > s3cr3t frobnicate
+//! < err unknown command `frobnicate`
+//! ```
+
+use crate::seeders::maintenance::MaintenanceHandle;
+use crate::seeders::pipeline::TraceLog;
+use crate::upstream::CanaryHandle;
+use crate::util::constant_time_eq;
+use std::collections::BTreeMap;
+use std::io;
+use std::net::SocketAddr;
+use std::str::SplitWhitespace;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tokio::sync::{Mutex, Notify};
+
+#[cfg(unix)]
+use std::path::Path;
+
+#[cfg(unix)]
+use tokio::net::UnixListener;
+
+/// The longest line, in bytes and without its line break, an admin client may send.
+pub const MAX_LINE: usize = 4096;
+
+/// How many lines with the wrong token a connection may send before it's closed.
+pub const MAX_FAILED_AUTH: u32 = 3;
+
+/// How long a line with the wrong token waits before it's answered, unless configured otherwise.
+pub const DEFAULT_AUTH_DELAY: Duration = Duration::from_secs(1);
+
+/// The result of running an admin command. `Ok` values are sent back to the client prefixed with
+/// `ok`, and `Err` values are sent back prefixed with `err`.
+pub type CommandResult = Result<String, String>;
+
+/// A command which can be run through the admin socket. The command receives the arguments which
+/// followed the command's name.
+type Command = Box<dyn Fn(&[&str]) -> CommandResult + Send + Sync>;
+
+/// The listener an `AdminServer` accepts connections on.
+enum AdminListener {
+    Tcp(TcpListener),
+
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+/// A control socket which runs token-authenticated commands against a running server.
+pub struct AdminServer {
+    listener: AdminListener,
+    token: String,
+    commands: BTreeMap<String, Command>,
+    auth_delay: Duration,
+    shutdown: Arc<Notify>,
+}
+
+impl AdminServer {
+    /// Binds a new `AdminServer` to a TCP address.
+    ///
+    /// The admin socket has no transport security, so this should only ever be bound to a loopback
+    /// address. Fails with `InvalidInput` if the token is empty or contains whitespace.
+    pub async fn bind<A: ToSocketAddrs>(
+        host: A,
+        token: impl Into<String>,
+    ) -> io::Result<AdminServer> {
+        let token = checked_token(token.into())?;
+        let listener = TcpListener::bind(host).await?;
+
+        Ok(AdminServer::from_listener(AdminListener::Tcp(listener), token))
+    }
+
+    /// Binds a new `AdminServer` to a Unix domain socket. Fails with `InvalidInput` if the token
+    /// is empty or contains whitespace.
+    #[cfg(unix)]
+    pub fn bind_unix(path: impl AsRef<Path>, token: impl Into<String>) -> io::Result<AdminServer> {
+        let token = checked_token(token.into())?;
+        let listener = UnixListener::bind(path)?;
+
+        Ok(AdminServer::from_listener(AdminListener::Unix(listener), token))
+    }
+
+    fn from_listener(listener: AdminListener, token: String) -> AdminServer {
+        let mut server = AdminServer {
+            listener,
+            token,
+            commands: BTreeMap::new(),
+            auth_delay: DEFAULT_AUTH_DELAY,
+            shutdown: Arc::new(Notify::new()),
+        };
+
+        let notify = server.shutdown.clone();
+        server.commands.insert("shutdown".to_owned(), Box::new(move |_| {
+            notify.notify_one();
+            Ok("shutting down".to_owned())
+        }));
+
+        server
+    }
+
+    /// Returns the local address of this admin socket, if it is bound to a TCP address.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        match &self.listener {
+            AdminListener::Tcp(listener) => { listener.local_addr().ok() }
+
+            #[cfg(unix)]
+            AdminListener::Unix(_) => { None }
+        }
+    }
+
+    /// Sets how long a line with the wrong token waits before it's answered. Failed attempts wait
+    /// one at a time, so this also bounds how quickly tokens can be guessed over many connections.
+    pub fn auth_delay(mut self, delay: Duration) -> AdminServer {
+        self.auth_delay = delay;
+        self
+    }
+
+    /// Registers a command, replacing any existing command with the same name.
+    pub fn command<F>(mut self, name: impl Into<String>, command: F) -> AdminServer
+    where
+        F: Fn(&[&str]) -> CommandResult + Send + Sync + 'static,
+    {
+        self.commands.insert(name.into(), Box::new(command));
+        self
+    }
+
+    /// Registers the `maintenance on|off|toggle|status` command, toggling the provided handle.
+    pub fn maintenance(self, handle: MaintenanceHandle) -> AdminServer {
+        self.command("maintenance", move |args| {
+            match args {
+                ["on"] => { handle.enable(); }
+                ["off"] => { handle.disable(); }
+                ["toggle"] => { handle.toggle(); }
+                ["status"] | [] => {}
+                _ => { return Err("usage: maintenance on|off|toggle|status".to_owned()); }
+            }
+
+            match handle.enabled() {
+                true => { Ok("maintenance enabled".to_owned()) }
+                false => { Ok("maintenance disabled".to_owned()) }
+            }
+        })
+    }
+
+    /// Registers the `canary <percent>|status` command, adjusting the provided canary weight.
+    pub fn canary(self, handle: CanaryHandle) -> AdminServer {
+        self.command("canary", move |args| {
+            match args {
+                [percent] if *percent != "status" => {
+                    match percent.trim_end_matches('%').parse::<u8>() {
+                        Ok(percent) if percent <= 100 => { handle.set(percent); }
+                        _ => { return Err("usage: canary <percent>|status".to_owned()); }
+                    }
+                }
+                ["status"] | [] => {}
+                _ => { return Err("usage: canary <percent>|status".to_owned()); }
+            }
+
+            Ok(format!("canary at {}%", handle.percent()))
+        })
+    }
+
+    /// Registers the `trace [n]` command, showing the pipeline trace of the `n`th most recent
+    /// request in the provided log (the most recent, by default).
+    pub fn traces(self, log: TraceLog) -> AdminServer {
+        self.command("trace", move |args| {
+            let index = match args {
+                [] => { 1 }
+                [n] => { n.parse::<usize>().ok().filter(|n| *n > 0).ok_or("usage: trace [n]")? }
+                _ => { return Err("usage: trace [n]".to_owned()); }
+            };
+
+            match log.recent().into_iter().nth(index - 1) {
+                Some(traced) => { Ok(format!("{}: {}", traced.request, traced.trace)) }
+                None => { Err("no such trace".to_owned()) }
+            }
+        })
+    }
+
+    /// Gets a `Notify` which is notified whenever the `shutdown` command is run. A notification is
+    /// kept until it's awaited, so a shutdown which arrives before anything awaits it isn't lost.
+    ///
+    /// The application is responsible for actually shutting down, for example by awaiting
+    /// `notified()` alongside `HttpServer::run` in a `select!`.
+    pub fn shutdown_signal(&self) -> Arc<Notify> {
+        self.shutdown.clone()
+    }
+
+    /// Runs the admin socket, serving connections until an I/O error occurs while accepting.
+    pub async fn run(self) -> io::Result<()> {
+        let commands = Arc::new(Commands {
+            token: self.token,
+            commands: self.commands,
+            auth_delay: self.auth_delay,
+            penalty: Mutex::new(()),
+        });
+
+        loop {
+            let commands = commands.clone();
+
+            match &self.listener {
+                AdminListener::Tcp(listener) => {
+                    let (stream, _) = listener.accept().await?;
+                    tokio::spawn(async move { commands.serve(stream).await });
+                }
+
+                #[cfg(unix)]
+                AdminListener::Unix(listener) => {
+                    let (stream, _) = listener.accept().await?;
+                    tokio::spawn(async move { commands.serve(stream).await });
+                }
+            }
+        }
+    }
+}
+
+/// Checks that an admin token can authenticate anything: that it's neither empty (which would
+/// match a line without a token) nor holding whitespace (which would never match).
+fn checked_token(token: String) -> io::Result<String> {
+    if token.is_empty() || token.contains(char::is_whitespace) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the admin token must be non-empty and free of whitespace",
+        ));
+    }
+
+    Ok(token)
+}
+
+/// The registered commands of a running `AdminServer`, shared between its connections.
+struct Commands {
+    token: String,
+    commands: BTreeMap<String, Command>,
+    auth_delay: Duration,
+    penalty: Mutex<()>,
+}
+
+impl Commands {
+    /// Serves a single admin connection, running one command per line. A line longer than
+    /// `MAX_LINE` is answered with an error, and closes the connection, as does the
+    /// `MAX_FAILED_AUTH`th line with the wrong token.
+    async fn serve<S: AsyncRead + AsyncWrite + Unpin>(&self, stream: S) -> io::Result<()> {
+        let mut reader = BufReader::new(stream);
+        let mut line = Vec::new();
+        let mut failures = 0;
+
+        loop {
+            line.clear();
+
+            // Reading at most a line break past the limit is enough to tell whether a line is too
+            // long, without buffering the rest of it.
+            let limit = MAX_LINE as u64 + 2;
+            if (&mut reader).take(limit).read_until(b'\n', &mut line).await? == 0 {
+                return Ok(());
+            }
+
+            let text = line.strip_suffix(b"\n").unwrap_or(&line);
+            let text = text.strip_suffix(b"\r").unwrap_or(text);
+            if text.len() > MAX_LINE {
+                reader.get_mut().write_all(b"err line too long\n").await?;
+                return Ok(());
+            }
+
+            let text = std::str::from_utf8(text)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+            let response = match self.authenticate(text) {
+                Some(words) => {
+                    match self.run(words) {
+                        Ok(output) => { format!("ok {output}\n") }
+                        Err(error) => { format!("err {error}\n") }
+                    }
+                }
+                None => {
+                    // Holding the penalty while waiting queues failed attempts from every
+                    // connection behind each other, so opening more connections doesn't help.
+                    let _penalty = self.penalty.lock().await;
+                    tokio::time::sleep(self.auth_delay).await;
+
+                    failures += 1;
+                    if failures >= MAX_FAILED_AUTH {
+                        reader.get_mut().write_all(b"err too many failed attempts\n").await?;
+                        return Ok(());
+                    }
+
+                    "err unauthorized\n".to_owned()
+                }
+            };
+
+            reader.get_mut().write_all(response.as_bytes()).await?;
+        }
+    }
+
+    /// Checks the token a command line starts with, returning the line's remaining words if it
+    /// matches.
+    fn authenticate<'l>(&self, line: &'l str) -> Option<SplitWhitespace<'l>> {
+        let mut words = line.split_whitespace();
+
+        let token = words.next().unwrap_or_default();
+        constant_time_eq(token.as_bytes(), self.token.as_bytes()).then_some(words)
+    }
+
+    /// Runs a single authenticated command line.
+    fn run(&self, mut words: SplitWhitespace<'_>) -> CommandResult {
+        let name = words.next().unwrap_or("help");
+        let args: Vec<&str> = words.collect();
+
+        if name == "help" {
+            let names: Vec<&str> = self.commands.keys().map(String::as_str).collect();
+            return Ok(names.join(" "));
+        }
+
+        match self.commands.get(name) {
+            Some(command) => { command(&args) }
+            None => { Err(format!("unknown command `{name}`")) }
+        }
+    }
+}
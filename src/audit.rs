@@ -0,0 +1,348 @@
+//! Structured audit logging, as append-only JSON lines.
+//!
+//! An `AuditLog` writes one JSON object per line, recording who did what, when, and how it turned
+//! out. Records are queued for a background writer, so writing never holds up a request, and the
+//! file is rotated once it grows too large or too old, keeping a fixed number of rotated files:
+//!
+//! ```no_run
+//! use grazie::audit::{AuditLog, AuditSeeder};
+//! use std::time::Duration;
+//!
+//! # async fn example() -> std::io::Result<()> {
+//! let log = AuditLog::builder("audit.jsonl")
+//!     .max_size(64 * 1024 * 1024)
+//!     .max_age(Duration::from_secs(24 * 60 * 60))
+//!     .keep(30)
+//!     .open()
+//!     .await?;
+//!
+//! let seeder = AuditSeeder::new(log)
+//!     .audit(true)
+//!     .route("/healthz", false);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Part of the `serde_json` feature.
+
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use serde::Serialize;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+/// How an audited request turned out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// The request succeeded.
+    Success,
+
+    /// The request was refused, with `401 Unauthorized` or `403 Forbidden`.
+    Denied,
+
+    /// The request failed with any other error status.
+    Failed,
+}
+
+impl Outcome {
+    /// Classifies a response status.
+    pub fn from_status(status: StatusCode) -> Outcome {
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => { Outcome::Denied }
+            status if status.is_client_error() || status.is_server_error() => { Outcome::Failed }
+            _ => { Outcome::Success }
+        }
+    }
+}
+
+/// A single audit record, written as one line of JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditRecord {
+    /// When the request was made, in milliseconds since the Unix epoch.
+    pub time: u64,
+
+    /// Who made the request, if they're known.
+    pub actor: Option<String>,
+
+    /// The request's method.
+    pub method: String,
+
+    /// The request's path.
+    pub route: String,
+
+    /// The response's status code.
+    pub status: u16,
+
+    /// How the request turned out.
+    pub outcome: Outcome,
+
+    /// How long the request took to handle, in milliseconds, if it's known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u64>,
+}
+
+/// Builds an `AuditLog`.
+#[derive(Debug, Clone)]
+pub struct AuditLogBuilder {
+    path: PathBuf,
+    max_size: Option<u64>,
+    max_age: Option<Duration>,
+    keep: usize,
+    capacity: usize,
+}
+
+impl AuditLogBuilder {
+    /// Rotates the log once it would grow past `max_size` bytes.
+    pub fn max_size(mut self, max_size: u64) -> AuditLogBuilder {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Rotates the log once it has been written to for `max_age`.
+    pub fn max_age(mut self, max_age: Duration) -> AuditLogBuilder {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Sets how many rotated files are kept, as `<path>.1` (the newest) through `<path>.<keep>`.
+    /// Defaults to 10.
+    pub fn keep(mut self, keep: usize) -> AuditLogBuilder {
+        self.keep = keep;
+        self
+    }
+
+    /// Sets how many records can be queued for the writer. Defaults to 1024.
+    pub fn capacity(mut self, capacity: usize) -> AuditLogBuilder {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Opens the log for appending, creating it if it doesn't exist, and spawns its writer task.
+    pub async fn open(self) -> io::Result<AuditLog> {
+        let file = append(&self.path).await?;
+        let size = file.metadata().await?.len();
+        let (sender, receiver) = mpsc::channel(self.capacity.max(1));
+
+        let writer = Writer {
+            file,
+            size,
+            opened: Instant::now(),
+            builder: self,
+        };
+        tokio::spawn(writer.run(receiver));
+
+        Ok(AuditLog {
+            sender,
+            dropped: Arc::new(AtomicU64::new(0)),
+        })
+    }
+}
+
+/// An append-only, rotating audit log.
+///
+/// `AuditLog`s are cheap to clone, and all clones write to the same file.
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    sender: mpsc::Sender<AuditRecord>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl AuditLog {
+    /// Starts building an `AuditLog` which writes to a file. By default, the log is never rotated.
+    pub fn builder(path: impl Into<PathBuf>) -> AuditLogBuilder {
+        AuditLogBuilder {
+            path: path.into(),
+            max_size: None,
+            max_age: None,
+            keep: 10,
+            capacity: 1024,
+        }
+    }
+
+    /// Queues a record for writing. When the queue is full, the record is dropped rather than
+    /// waiting for room.
+    pub fn record(&self, record: AuditRecord) {
+        if self.sender.try_send(record).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Gets the number of records dropped because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// The background task writing an `AuditLog`'s records.
+struct Writer {
+    file: File,
+    size: u64,
+    opened: Instant,
+    builder: AuditLogBuilder,
+}
+
+impl Writer {
+    async fn run(mut self, mut receiver: mpsc::Receiver<AuditRecord>) {
+        while let Some(record) = receiver.recv().await {
+            let Ok(mut line) = serde_json::to_vec(&record) else { continue; };
+            line.push(b'\n');
+
+            let too_large = self.builder.max_size.is_some_and(|max_size| self.size > 0 && self.size + line.len() as u64 > max_size);
+            let too_old = self.builder.max_age.is_some_and(|max_age| self.opened.elapsed() >= max_age);
+
+            if (too_large || too_old) && self.rotate().await.is_err() {
+                continue;
+            }
+
+            if self.file.write_all(&line).await.is_ok() {
+                self.size += line.len() as u64;
+            }
+        }
+
+        let _ = self.file.flush().await;
+    }
+
+    /// Shifts every rotated file along by one, dropping the oldest, and starts a new file.
+    async fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush().await?;
+        let path = &self.builder.path;
+
+        match self.builder.keep {
+            0 => { tokio::fs::remove_file(path).await?; }
+            keep => {
+                let _ = tokio::fs::remove_file(rotated(path, keep)).await;
+                for index in (1..keep).rev() {
+                    let _ = tokio::fs::rename(rotated(path, index), rotated(path, index + 1)).await;
+                }
+
+                tokio::fs::rename(path, rotated(path, 1)).await?;
+            }
+        }
+
+        self.file = append(path).await?;
+        self.size = 0;
+        self.opened = Instant::now();
+        Ok(())
+    }
+}
+
+async fn append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path).await
+}
+
+/// Gets the path of a rotated file, such as `audit.jsonl.1`.
+fn rotated(path: &Path, index: usize) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{index}"));
+    rotated.into()
+}
+
+/// Gets who made a request.
+type Actor = Box<dyn Fn(&HttpRequest<BoxBody>) -> Option<String> + Send + Sync>;
+
+/// When an audited request started, as an extension of the request.
+#[derive(Debug, Clone, Copy)]
+struct Started(Instant);
+
+/// A `Seeder` which audits requests to an `AuditLog`.
+///
+/// Whether a request is audited is decided by the longest matching route prefix, or by the default
+/// set through `audit` if none match. Since there's no response stage to the request chain, records
+/// are written by passing responses to `AuditSeeder::finish` once they've been created, which also
+/// covers requests rejected before this seeder ran.
+pub struct AuditSeeder {
+    log: AuditLog,
+    audit: bool,
+    routes: Vec<(String, bool)>,
+    actor: Actor,
+}
+
+impl AuditSeeder {
+    /// Constructs a new `AuditSeeder` writing to the provided log, which doesn't audit any routes.
+    pub fn new(log: AuditLog) -> AuditSeeder {
+        AuditSeeder {
+            log,
+            audit: false,
+            routes: Vec::new(),
+            actor: Box::new(|_| None),
+        }
+    }
+
+    /// Sets whether routes without their own setting are audited.
+    pub fn audit(mut self, audit: bool) -> AuditSeeder {
+        self.audit = audit;
+        self
+    }
+
+    /// Sets whether the routes under a path prefix are audited.
+    pub fn route(mut self, prefix: impl Into<String>, audit: bool) -> AuditSeeder {
+        self.routes.push((prefix.into(), audit));
+        self
+    }
+
+    /// Sets how the actor of a request is found, such as from its session.
+    pub fn actor<F>(mut self, actor: F) -> AuditSeeder
+    where
+        F: Fn(&HttpRequest<BoxBody>) -> Option<String> + Send + Sync + 'static,
+    {
+        self.actor = Box::new(actor);
+        self
+    }
+
+    /// Checks whether a path is audited.
+    pub fn audits(&self, path: &str) -> bool {
+        self.routes
+            .iter()
+            .filter(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.audit, |(_, audit)| *audit)
+    }
+
+    /// Records the response to a request, if its route is audited.
+    pub fn finish(&self, request: &HttpRequest<BoxBody>, response: &HttpResponse<BoxBody>) {
+        if !self.audits(request.uri().path()) {
+            return;
+        }
+
+        let started = request.extensions().get::<Started>().map(|started| started.0);
+        let time = SystemTime::now()
+            .checked_sub(started.map(|started| started.elapsed()).unwrap_or_default())
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+
+        self.log.record(AuditRecord {
+            time: time.as_millis() as u64,
+            actor: (self.actor)(request),
+            method: request.method().to_string(),
+            route: request.uri().path().to_owned(),
+            status: response.status().as_u16(),
+            outcome: Outcome::from_status(response.status()),
+            duration: started.map(|started| started.elapsed().as_millis() as u64),
+        });
+    }
+}
+
+impl Seeder for AuditSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        if self.audits(request.uri().path()) {
+            request.extensions_mut().insert(Started(Instant::now()));
+        }
+
+        Guard::Accessible(request)
+    }
+}
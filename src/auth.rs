@@ -0,0 +1,10 @@
+//! Authentication: server-side sessions, password logins, and signing users in through external
+//! providers.
+
+#[cfg(any(feature = "argon2", feature = "bcrypt"))]
+pub mod login;
+#[cfg(feature = "oauth")]
+pub mod oauth;
+#[cfg(any(feature = "argon2", feature = "bcrypt"))]
+pub mod password;
+pub mod session;
@@ -0,0 +1,158 @@
+//! Signing users in with a username and password from a login form.
+//!
+//! Part of the `argon2` and `bcrypt` features.
+
+use crate::auth::password;
+use crate::auth::session::SessionStore;
+use crate::core::seeder::{BoxBody, Rejection};
+use crate::http::header::{HeaderValue, CONTENT_TYPE, LOCATION, SET_COOKIE};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::task::blocking;
+use crate::util::query_pairs;
+use std::fmt::Display;
+use std::sync::Arc;
+
+/// A user's stored credentials, as found by a `LoginHandler`'s lookup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials<T> {
+    /// The user's password hash, as produced by `password::hash`.
+    pub hash: String,
+
+    /// The user, stored in their session once they've signed in.
+    pub user: T,
+}
+
+/// Signs users in from a form-encoded login form, starting a session for them.
+///
+/// Users are found through an async lookup by username, and their password is verified on the
+/// blocking pool. Unknown users take as long to reject as wrong passwords, so the two can't be
+/// told apart. Any session the request already carries is ended before the new one starts, so a
+/// session planted before sign-in can't be carried into it.
+pub struct LoginHandler<S, F> {
+    sessions: Arc<S>,
+    lookup: F,
+    username_field: String,
+    password_field: String,
+    landing: String,
+}
+
+impl<S, T, F, Fut> LoginHandler<S, F>
+where
+    S: SessionStore<T>,
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Option<Credentials<T>>>,
+{
+    /// Constructs a new `LoginHandler`, starting sessions in the provided store.
+    pub fn new(sessions: Arc<S>, lookup: F) -> LoginHandler<S, F> {
+        LoginHandler {
+            sessions,
+            lookup,
+            username_field: "username".to_owned(),
+            password_field: "password".to_owned(),
+            landing: "/".to_owned(),
+        }
+    }
+
+    /// Sets the names of the form's username and password fields. Defaults to `username` and
+    /// `password`.
+    pub fn fields(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> LoginHandler<S, F> {
+        self.username_field = username.into();
+        self.password_field = password.into();
+        self
+    }
+
+    /// Sets the path users are redirected to once they've signed in.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the path isn't a valid `Location` header value.
+    pub fn landing(mut self, path: impl Into<String>) -> LoginHandler<S, F> {
+        self.landing = path.into();
+        assert!(
+            HeaderValue::try_from(self.landing.as_str()).is_ok(),
+            "A landing path must be a valid header value!",
+        );
+        self
+    }
+
+    /// Handles a submitted login form, returning a `303 See Other` redirect to the landing path
+    /// which starts the user's session.
+    pub async fn handle(
+        &self,
+        request: &HttpRequest<BoxBody>,
+    ) -> Result<HttpResponse<BoxBody>, Rejection> {
+        let form = request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("application/x-www-form-urlencoded"));
+
+        if !form {
+            return Err(Rejection::new(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "unsupported_media_type",
+                "The login must be submitted as a form.",
+            ));
+        }
+
+        let body = std::str::from_utf8(request.body().raw_bytes()).unwrap_or_default();
+        let mut username = None;
+        let mut secret = None;
+
+        for (key, value) in query_pairs(body) {
+            if key == self.username_field {
+                username = Some(value);
+            } else if key == self.password_field {
+                secret = Some(value);
+            }
+        }
+
+        let (Some(username), Some(secret)) = (username, secret) else {
+            return Err(Rejection::new(
+                StatusCode::BAD_REQUEST,
+                "malformed_body",
+                "The login form is missing its username or password.",
+            ));
+        };
+
+        let credentials = (self.lookup)(username).await;
+        let hash = credentials.as_ref().map(|credentials| credentials.hash.clone());
+
+        let verified = blocking(move || match hash {
+            Some(hash) => { password::verify(&secret, &hash) }
+            None => {
+                password::verify_dummy(&secret);
+                false
+            }
+        })
+        .await
+        .map_err(unavailable)?;
+
+        let Some(credentials) = credentials.filter(|_| verified) else {
+            return Err(Rejection::new(
+                StatusCode::UNAUTHORIZED,
+                "invalid_credentials",
+                "The username or password is incorrect.",
+            ));
+        };
+
+        self.sessions.destroy(request).await.map_err(unavailable)?;
+        let session = self.sessions.create(credentials.user).await.map_err(unavailable)?;
+
+        Ok(HttpResponse::builder()
+            .status(StatusCode::SEE_OTHER)
+            .header(LOCATION, self.landing.as_str())
+            .header(SET_COOKIE, session)
+            .body(BoxBody::empty())
+            .unwrap())
+    }
+}
+
+/// Rejects a login which couldn't be checked or started with `503 Service Unavailable`.
+fn unavailable(error: impl Display) -> Rejection {
+    Rejection::new(StatusCode::SERVICE_UNAVAILABLE, "login_unavailable", error.to_string())
+}
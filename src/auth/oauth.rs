@@ -0,0 +1,622 @@
+//! Signing users in through OAuth 2.0 and OpenID Connect providers.
+//!
+//! `OAuthSeeder` implements the authorization code flow with PKCE. It serves two routes: the login
+//! route, which redirects the user to the provider, and the callback route, which the provider
+//! redirects back to. The callback exchanges the authorization code for tokens, and starts a
+//! session holding the user's `Identity`, which is attached to every later request of the session.
+//!
+//! grazie doesn't ship an HTTP client, so the token exchange itself is delegated to a
+//! `TokenExchange`, which posts a `TokenRequest` to the provider with a client of your choosing.
+//!
+//! Part of the `oauth` feature.
+
+use crate::auth::session::{cookie, is_cookie_path, set_cookie, token};
+use crate::auth::session::{MemorySessionStore, SessionStore};
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::{HeaderValue, LOCATION, SET_COOKIE};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::util::{base64_decode, base64url_encode, constant_time_eq, percent_encode, query_pairs};
+use crate::util::safe_return_path;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// The content type of a `TokenRequest`'s body.
+pub const FORM_URLENCODED: &str = "application/x-www-form-urlencoded";
+
+/// The name of the cookie which binds a login to the browser that started it.
+const STATE_COOKIE: &str = "grazie_oauth_state";
+
+/// How long a user has to complete a login at the provider.
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// A PKCE code verifier, and its `S256` challenge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pkce {
+    /// The verifier, which is kept secret until the token exchange.
+    pub verifier: String,
+
+    /// The challenge, which is sent with the authorization request.
+    pub challenge: String,
+}
+
+impl Pkce {
+    /// Generates a new, random verifier.
+    pub fn generate() -> Pkce {
+        Pkce::from_verifier(token())
+    }
+
+    /// Computes the challenge for an existing verifier.
+    pub fn from_verifier(verifier: impl Into<String>) -> Pkce {
+        let verifier = verifier.into();
+        let challenge = base64url_encode(&Sha256::digest(verifier.as_bytes()));
+
+        Pkce {
+            verifier,
+            challenge,
+        }
+    }
+}
+
+/// The endpoints and client credentials of an OAuth 2.0 provider.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OAuthProvider {
+    /// The URL users are sent to to sign in.
+    pub authorization_endpoint: String,
+
+    /// The URL authorization codes are exchanged for tokens at.
+    pub token_endpoint: String,
+
+    /// The client's ID, as registered with the provider.
+    pub client_id: String,
+
+    /// The client's secret, for confidential clients.
+    pub client_secret: Option<String>,
+
+    /// The URL of the callback route, as registered with the provider.
+    pub redirect_uri: String,
+
+    /// The scopes requested from the provider.
+    pub scopes: Vec<String>,
+}
+
+/// The endpoints of an OpenID Connect discovery document.
+#[derive(Deserialize)]
+struct Discovery {
+    authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+impl OAuthProvider {
+    /// Constructs a new `OAuthProvider`, requesting the `openid` scope.
+    pub fn new(
+        client_id: impl Into<String>,
+        authorization_endpoint: impl Into<String>,
+        token_endpoint: impl Into<String>,
+        redirect_uri: impl Into<String>,
+    ) -> OAuthProvider {
+        OAuthProvider {
+            authorization_endpoint: authorization_endpoint.into(),
+            token_endpoint: token_endpoint.into(),
+            client_id: client_id.into(),
+            client_secret: None,
+            redirect_uri: redirect_uri.into(),
+            scopes: vec!["openid".to_owned()],
+        }
+    }
+
+    /// Constructs a new `OAuthProvider` from an OpenID Connect discovery document, as served from
+    /// the provider's `/.well-known/openid-configuration`.
+    pub fn from_discovery(
+        document: &str,
+        client_id: impl Into<String>,
+        redirect_uri: impl Into<String>,
+    ) -> Result<OAuthProvider, serde_json::Error> {
+        let discovery: Discovery = serde_json::from_str(document)?;
+
+        Ok(OAuthProvider::new(
+            client_id,
+            discovery.authorization_endpoint,
+            discovery.token_endpoint,
+            redirect_uri,
+        ))
+    }
+
+    /// Sets the client's secret, for confidential clients.
+    pub fn client_secret(mut self, secret: impl Into<String>) -> OAuthProvider {
+        self.client_secret = Some(secret.into());
+        self
+    }
+
+    /// Requests another scope from the provider.
+    pub fn scope(mut self, scope: impl Into<String>) -> OAuthProvider {
+        self.scopes.push(scope.into());
+        self
+    }
+
+    /// Builds the URL which starts a login at the provider.
+    pub fn authorization_url(&self, state: &str, challenge: &str, nonce: &str) -> String {
+        let separator = match self.authorization_endpoint.contains('?') {
+            true => { '&' }
+            false => { '?' }
+        };
+
+        format!(
+            "{}{separator}response_type=code&client_id={}&redirect_uri={}&scope={}&state={}\
+            &nonce={}&code_challenge={}&code_challenge_method=S256",
+            self.authorization_endpoint,
+            percent_encode(&self.client_id),
+            percent_encode(&self.redirect_uri),
+            percent_encode(&self.scopes.join(" ")),
+            percent_encode(state),
+            percent_encode(nonce),
+            percent_encode(challenge),
+        )
+    }
+
+    /// Builds the request which exchanges an authorization code for tokens.
+    pub fn token_request(&self, code: &str, verifier: &str) -> TokenRequest {
+        let mut body = format!(
+            "grant_type=authorization_code&code={}&redirect_uri={}&client_id={}&code_verifier={}",
+            percent_encode(code),
+            percent_encode(&self.redirect_uri),
+            percent_encode(&self.client_id),
+            percent_encode(verifier),
+        );
+
+        if let Some(secret) = &self.client_secret {
+            body.push_str("&client_secret=");
+            body.push_str(&percent_encode(secret));
+        }
+
+        TokenRequest {
+            endpoint: self.token_endpoint.clone(),
+            body,
+        }
+    }
+}
+
+/// A request to a provider's token endpoint.
+///
+/// This should be sent as a `POST` to the endpoint, with a `Content-Type` of `FORM_URLENCODED`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenRequest {
+    /// The provider's token endpoint.
+    pub endpoint: String,
+
+    /// The form-encoded body of the request.
+    pub body: String,
+}
+
+/// The tokens returned by a provider's token endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct TokenResponse {
+    /// The access token, for calling the provider's APIs on the user's behalf.
+    pub access_token: String,
+
+    /// The type of the access token, usually `Bearer`.
+    pub token_type: String,
+
+    /// How many seconds the access token is valid for.
+    #[serde(default)]
+    pub expires_in: Option<u64>,
+
+    /// The refresh token, if the provider issued one.
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+
+    /// The OpenID Connect ID token, if the `openid` scope was granted.
+    #[serde(default)]
+    pub id_token: Option<String>,
+
+    /// The scopes granted, if they differ from those requested.
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+/// Exchanges an authorization code for tokens, by sending a `TokenRequest` to the provider.
+pub trait TokenExchange: Send + Sync + 'static {
+    /// Sends the request, returning the provider's tokens or a description of what went wrong.
+    fn exchange(
+        &self,
+        request: TokenRequest,
+    ) -> impl Future<Output = Result<TokenResponse, String>> + Send;
+}
+
+/// A signed-in user, attached to the requests of their session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    /// The user's subject identifier, from the ID token's `sub` claim.
+    pub subject: Option<String>,
+
+    /// The claims of the ID token, or `null` if no ID token was issued.
+    pub claims: serde_json::Value,
+
+    /// The tokens issued by the provider.
+    pub tokens: TokenResponse,
+}
+
+impl Identity {
+    /// Gets one of the ID token's claims, such as `email`.
+    pub fn claim(&self, name: &str) -> Option<&serde_json::Value> {
+        self.claims.get(name)
+    }
+}
+
+/// Decodes the claims of an ID token.
+///
+/// The token's signature isn't checked. The token was received directly from the provider's
+/// token endpoint, so (as allowed by OpenID Connect) its origin is established by the token
+/// endpoint's TLS certificate instead.
+fn id_token_claims(id_token: &str) -> Option<serde_json::Value> {
+    let payload = id_token.split('.').nth(1)?;
+
+    serde_json::from_slice(&base64_decode(payload)?).ok()
+}
+
+/// A login which has been started, but not yet completed at the callback.
+struct Pending {
+    verifier: String,
+    nonce: String,
+    return_to: HeaderValue,
+    started: Instant,
+}
+
+/// A `Seeder` which signs users in through an OAuth 2.0 or OpenID Connect provider, using the
+/// authorization code flow with PKCE.
+///
+/// Requests to the login route (`/oauth/login` by default) are redirected to the provider. A
+/// `return_to` query parameter sets the local path the user lands on once they've signed in, and
+/// is ignored unless it's a single local path.
+/// Requests to the callback route (`/oauth/callback` by default) complete the login, start a
+/// new session in place of any the request already carried, and redirect to the landing path.
+///
+/// Every other request of a session has the session's `Identity` attached as an extension. When
+/// login is required, requests without a session are redirected to the login route.
+///
+/// At most 1024 logins are in progress at once by default (see `OAuthSeeder::max_pending`), so
+/// that clients requesting the login route can't grow the set of pending logins without bound.
+///
+/// Sessions are kept in a `MemorySessionStore` unless another `SessionStore` is set.
+pub struct OAuthSeeder<X, S = MemorySessionStore<Identity>> {
+    provider: OAuthProvider,
+    exchange: X,
+    sessions: S,
+    pending: Mutex<HashMap<String, Pending>>,
+    max_pending: usize,
+    login_path: String,
+    callback_path: String,
+    landing: String,
+    require_login: bool,
+}
+
+impl<X: TokenExchange> OAuthSeeder<X> {
+    /// Constructs a new `OAuthSeeder`, keeping sessions for 8 hours without use.
+    pub fn new(provider: OAuthProvider, exchange: X) -> OAuthSeeder<X> {
+        OAuthSeeder {
+            provider,
+            exchange,
+            sessions: MemorySessionStore::new(Duration::from_secs(8 * 60 * 60)),
+            pending: Mutex::new(HashMap::new()),
+            max_pending: 1024,
+            login_path: "/oauth/login".to_owned(),
+            callback_path: "/oauth/callback".to_owned(),
+            landing: "/".to_owned(),
+            require_login: false,
+        }
+    }
+
+    /// Sets the store the sessions of signed-in users are kept in.
+    pub fn sessions<T: SessionStore<Identity>>(self, sessions: T) -> OAuthSeeder<X, T> {
+        OAuthSeeder {
+            provider: self.provider,
+            exchange: self.exchange,
+            sessions,
+            pending: self.pending,
+            max_pending: self.max_pending,
+            login_path: self.login_path,
+            callback_path: self.callback_path,
+            landing: self.landing,
+            require_login: self.require_login,
+        }
+    }
+}
+
+impl<X: TokenExchange, S: SessionStore<Identity>> OAuthSeeder<X, S> {
+    /// Sets the path of the login route.
+    pub fn login_path(mut self, path: impl Into<String>) -> OAuthSeeder<X, S> {
+        self.login_path = path.into();
+        self
+    }
+
+    /// Sets the path of the callback route. This must match the provider's redirect URI.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the path can't be used as a cookie's path, which the login's state cookie is
+    /// scoped to.
+    pub fn callback_path(mut self, path: impl Into<String>) -> OAuthSeeder<X, S> {
+        self.callback_path = path.into();
+        assert!(
+            is_cookie_path(&self.callback_path),
+            "A callback path must be a valid cookie path!",
+        );
+        self
+    }
+
+    /// Sets the path users land on after signing in, unless the login asked for another.
+    pub fn landing(mut self, path: impl Into<String>) -> OAuthSeeder<X, S> {
+        self.landing = path.into();
+        self
+    }
+
+    /// Sets how many logins can be in progress at once. Once this many are pending, new logins are
+    /// refused with `503 Service Unavailable` until earlier ones complete or expire.
+    pub fn max_pending(mut self, max_pending: usize) -> OAuthSeeder<X, S> {
+        self.max_pending = max_pending;
+        self
+    }
+
+    /// Sets whether requests without a session are redirected to the login route.
+    pub fn require_login(mut self, require_login: bool) -> OAuthSeeder<X, S> {
+        self.require_login = require_login;
+        self
+    }
+
+    /// Gets the store the sessions of signed-in users are kept in, such as for signing users out.
+    pub fn session_store(&self) -> &S {
+        &self.sessions
+    }
+
+    /// Starts a login, returning the redirect to the provider.
+    fn login(&self, request: &HttpRequest<BoxBody>) -> Result<HttpResponse<BoxBody>, Rejection> {
+        let return_to = request
+            .uri()
+            .query()
+            .and_then(|query| query_pairs(query).find(|(key, _)| key == "return_to"))
+            .and_then(|(_, path)| safe_return_path(&path))
+            .or_else(|| safe_return_path(&self.landing))
+            .unwrap_or_else(|| HeaderValue::from_static("/"));
+
+        let state = token();
+        let nonce = token();
+        let pkce = Pkce::generate();
+        let location = location(&self.provider.authorization_url(&state, &pkce.challenge, &nonce))?;
+
+        let now = Instant::now();
+        let mut pending = self.lock();
+        pending.retain(|_, login| now.duration_since(login.started) < LOGIN_TIMEOUT);
+
+        if pending.len() >= self.max_pending {
+            return Err(Rejection::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "too_many_logins",
+                "Too many logins are in progress. Try again later.",
+            ));
+        }
+
+        pending.insert(state.clone(), Pending {
+            verifier: pkce.verifier,
+            nonce,
+            return_to,
+            started: now,
+        });
+
+        let secure = self.sessions.is_secure();
+        let state_cookie =
+            set_cookie(STATE_COOKIE, &state, &self.callback_path, LOGIN_TIMEOUT, secure);
+
+        Ok(redirect(location, Some(state_cookie)))
+    }
+
+    /// Completes a login at the callback, returning the redirect to the landing path.
+    async fn callback(
+        &self,
+        request: &HttpRequest<BoxBody>,
+    ) -> Result<HttpResponse<BoxBody>, Rejection> {
+        let params: HashMap<String, String> =
+            request.uri().query().map(|query| query_pairs(query).collect()).unwrap_or_default();
+
+        if let Some(error) = params.get("error") {
+            return Err(Rejection::new(
+                StatusCode::UNAUTHORIZED,
+                "oauth_denied",
+                format!("The provider denied the login: {error}."),
+            ));
+        }
+
+        let invalid_state = || {
+            Rejection::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_oauth_state",
+                "The login expired or was started elsewhere.",
+            )
+        };
+
+        let state = params.get("state").ok_or_else(invalid_state)?;
+        let bound = cookie(request, STATE_COOKIE)
+            .is_some_and(|bound| constant_time_eq(bound.as_bytes(), state.as_bytes()));
+
+        // A callback without the browser's state cookie leaves the login pending, so that it can't
+        // be cancelled by someone who only knows its state.
+        if !bound {
+            return Err(invalid_state());
+        }
+
+        let pending = self.lock().remove(state).ok_or_else(invalid_state)?;
+
+        if pending.started.elapsed() >= LOGIN_TIMEOUT {
+            return Err(invalid_state());
+        }
+
+        let code = params.get("code").ok_or_else(|| {
+            Rejection::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_oauth_callback",
+                "The callback is missing its authorization code.",
+            )
+        })?;
+
+        let tokens = self.exchange
+            .exchange(self.provider.token_request(code, &pending.verifier))
+            .await
+            .map_err(|error| {
+                Rejection::new(StatusCode::BAD_GATEWAY, "oauth_exchange_failed", error)
+            })?;
+
+        let claims = match &tokens.id_token {
+            Some(id_token) => {
+                let claims = id_token_claims(id_token).ok_or_else(|| {
+                    Rejection::new(
+                        StatusCode::BAD_GATEWAY,
+                        "invalid_id_token",
+                        "The provider's ID token is malformed.",
+                    )
+                })?;
+
+                let nonce = claims.get("nonce").and_then(|nonce| nonce.as_str());
+                if nonce != Some(pending.nonce.as_str()) {
+                    return Err(Rejection::new(
+                        StatusCode::UNAUTHORIZED,
+                        "invalid_id_token",
+                        "The ID token was issued for another login.",
+                    ));
+                }
+
+                claims
+            }
+            None => { serde_json::Value::Null }
+        };
+
+        let identity = Identity {
+            subject: claims.get("sub").and_then(|sub| sub.as_str()).map(str::to_owned),
+            claims,
+            tokens,
+        };
+
+        // Any session the request already carries is ended first, so that one planted before the
+        // login can't be carried into it.
+        let unavailable = |error: S::Error| {
+            Rejection::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "session_unavailable",
+                error.to_string(),
+            )
+        };
+        self.sessions.destroy(request).await.map_err(unavailable)?;
+        let session = self.sessions.create(identity).await.map_err(unavailable)?;
+
+        let secure = self.sessions.is_secure();
+        let mut response = redirect(pending.return_to, Some(session));
+        response.headers_mut().append(
+            SET_COOKIE,
+            set_cookie(STATE_COOKIE, "", &self.callback_path, Duration::ZERO, secure),
+        );
+
+        Ok(response)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Pending>> {
+        self.pending.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Converts a redirect target into a `Location` value, refusing the request if it isn't a valid
+/// header value.
+fn location(target: &str) -> Result<HeaderValue, Rejection> {
+    HeaderValue::try_from(target).map_err(|_| {
+        Rejection::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "invalid_redirect",
+            "The redirect location isn't a valid header value.",
+        )
+    })
+}
+
+/// Builds a `302 Found` redirect, optionally setting a cookie.
+fn redirect(location: HeaderValue, cookie: Option<HeaderValue>) -> HttpResponse<BoxBody> {
+    let mut response = HttpResponse::new(BoxBody::empty());
+    *response.status_mut() = StatusCode::FOUND;
+    response.headers_mut().insert(LOCATION, location);
+
+    if let Some(cookie) = cookie {
+        response.headers_mut().append(SET_COOKIE, cookie);
+    }
+
+    response
+}
+
+/// Answers a request with a route's response and rejection, or with an empty response if the
+/// route refused it.
+fn answer<'a>(
+    request: &'a mut HttpRequest<BoxBody>,
+    outcome: Result<(HttpResponse<BoxBody>, Rejection), Rejection>,
+) -> Guard<'a, HttpRequest<BoxBody>> {
+    let (response, rejection) = match outcome {
+        Ok(answered) => { answered }
+        Err(rejection) => {
+            let response = HttpResponse::builder().status(rejection.status).body(BoxBody::empty());
+            (response.unwrap(), rejection)
+        }
+    };
+
+    Guard::Inaccessible {
+        request,
+        respondent: Respondent::Respond(Box::new(response)),
+        rejection,
+    }
+}
+
+impl<X: TokenExchange, S: SessionStore<Identity>> Seeder for OAuthSeeder<X, S> {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        let path = request.uri().path();
+
+        if path == self.login_path {
+            let login = self.login(request).map(|response| {
+                let message = "The request was redirected to sign in.";
+                (response, Rejection::new(StatusCode::FOUND, "redirected", message))
+            });
+
+            return answer(request, login);
+        }
+
+        if path == self.callback_path {
+            let callback = self.callback(request).await.map(|response| {
+                let message = "The login was completed.";
+                (response, Rejection::new(StatusCode::FOUND, "redirected", message))
+            });
+
+            return answer(request, callback);
+        }
+
+        // Requests are handled as signed out while the session store fails.
+        match self.sessions.get(request).await.ok().flatten() {
+            Some(identity) => {
+                request.extensions_mut().insert(identity);
+                Guard::Accessible(request)
+            }
+            None if self.require_login => {
+                let return_to = request.uri().path_and_query().map_or("/", |path| path.as_str());
+                let login = format!("{}?return_to={}", self.login_path, percent_encode(return_to));
+                let redirected = location(&login).map(|location| {
+                    let message = "The request requires signing in.";
+                    let rejection = Rejection::new(StatusCode::FOUND, "login_required", message);
+                    (redirect(location, None), rejection)
+                });
+
+                answer(request, redirected)
+            }
+            None => { Guard::Accessible(request) }
+        }
+    }
+}
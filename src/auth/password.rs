@@ -0,0 +1,104 @@
+//! Hashing and verifying passwords.
+//!
+//! New hashes use Argon2id when the `argon2` feature is enabled, and bcrypt otherwise. Hashes of
+//! either algorithm can be verified when its feature is enabled, so stored bcrypt hashes keep
+//! working while users are migrated over to Argon2id through `needs_rehash`.
+//!
+//! ```
+//! use grazie::auth::password;
+//!
+//! let hash = password::hash("correct horse battery staple").unwrap();
+//!
+//! assert!(password::verify("correct horse battery staple", &hash));
+//! assert!(!password::verify("Tr0ub4dor&3", &hash));
+//! ```
+//!
+//! Hashing is deliberately slow. From async code, run it on a blocking pool, such as through
+//! `grazie::task::blocking`.
+//!
+//! Part of the `argon2` and `bcrypt` features.
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::sync::OnceLock;
+
+/// An error from hashing a password.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswordError {
+    message: String,
+}
+
+impl Display for PasswordError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "couldn't hash password: {}", self.message)
+    }
+}
+
+impl Error for PasswordError {}
+
+/// Hashes a password with a random salt, returning the hash in its standard string format.
+#[cfg(feature = "argon2")]
+pub fn hash(password: &str) -> Result<String, PasswordError> {
+    use argon2::password_hash::rand_core::OsRng;
+    use argon2::password_hash::{PasswordHasher, SaltString};
+
+    argon2::Argon2::default()
+        .hash_password(password.as_bytes(), &SaltString::generate(&mut OsRng))
+        .map(|hash| hash.to_string())
+        .map_err(|error| PasswordError { message: error.to_string() })
+}
+
+/// Hashes a password with a random salt, returning the hash in its standard string format.
+///
+/// bcrypt only considers the first 72 bytes of a password.
+#[cfg(not(feature = "argon2"))]
+pub fn hash(password: &str) -> Result<String, PasswordError> {
+    bcrypt::hash(password, bcrypt::DEFAULT_COST).map_err(|error| PasswordError { message: error.to_string() })
+}
+
+/// Checks a password against a hash, in constant time with respect to the hash's contents.
+///
+/// Returns `false` for malformed hashes, and for hashes of algorithms which aren't enabled.
+pub fn verify(password: &str, hash: &str) -> bool {
+    if hash.starts_with("$argon2") {
+        #[cfg(feature = "argon2")]
+        {
+            use argon2::password_hash::{PasswordHash, PasswordVerifier};
+
+            return PasswordHash::new(hash)
+                .is_ok_and(|hash| argon2::Argon2::default().verify_password(password.as_bytes(), &hash).is_ok());
+        }
+    }
+
+    if hash.starts_with("$2") {
+        #[cfg(feature = "bcrypt")]
+        {
+            return bcrypt::verify(password, hash).unwrap_or(false);
+        }
+    }
+
+    false
+}
+
+/// Checks whether a hash should be replaced with a fresh one, because it uses a different
+/// algorithm than `hash` does now.
+///
+/// Rehash a user's password after they've signed in with it successfully.
+pub fn needs_rehash(hash: &str) -> bool {
+    match cfg!(feature = "argon2") {
+        true => { !hash.starts_with("$argon2id$") }
+        false => { !hash.starts_with("$2") }
+    }
+}
+
+/// Spends as long as verifying a real password would, for when there's no hash to check against.
+///
+/// Verifying against this when a user doesn't exist keeps login attempts for unknown users from
+/// being told apart by how quickly they fail.
+pub fn verify_dummy(password: &str) {
+    static DUMMY: OnceLock<Option<String>> = OnceLock::new();
+
+    if let Some(dummy) = DUMMY.get_or_init(|| hash("grazie dummy password").ok()) {
+        verify(password, dummy);
+    }
+}
@@ -0,0 +1,213 @@
+//! Server-side sessions, identified by a cookie.
+//!
+//! A `SessionStore` keeps each session's value, and hands the client an opaque, random session ID
+//! in a cookie. `MemorySessionStore` keeps sessions in memory:
+//!
+//! ```
+//! use grazie::auth::session::{MemorySessionStore, SessionStore};
+//! use std::time::Duration;
+//!
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! let sessions = MemorySessionStore::<String>::new(Duration::from_secs(3600));
+//! let set_cookie = sessions.create("alice".to_owned()).await.unwrap();
+//!
+//! assert!(set_cookie.to_str().unwrap().starts_with("grazie_session="));
+//! # });
+//! ```
+
+use crate::core::seeder::BoxBody;
+use crate::http::header::{HeaderValue, COOKIE};
+use crate::http::HttpRequest;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt::Display;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// The name of the session cookie, unless another is configured.
+pub const DEFAULT_COOKIE: &str = "grazie_session";
+
+/// Gets the value of a request's cookie.
+pub fn cookie<'a>(request: &'a HttpRequest<BoxBody>, name: &str) -> Option<&'a str> {
+    request
+        .headers()
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.trim_matches('"'))
+}
+
+/// Checks whether `name` can be used as a cookie's name, which must be a non-empty token.
+pub(crate) fn is_cookie_name(name: &str) -> bool {
+    let separator = |byte: u8| br#"()<>@,;:\"/[]?={}"#.contains(&byte);
+
+    !name.is_empty() && name.bytes().all(|byte| byte.is_ascii_graphic() && !separator(byte))
+}
+
+/// Checks whether `path` can be used as a cookie's `Path` attribute.
+#[cfg(feature = "oauth")]
+pub(crate) fn is_cookie_path(path: &str) -> bool {
+    path.starts_with('/') && path.bytes().all(|byte| byte.is_ascii_graphic() && byte != b';')
+}
+
+/// Formats a `Set-Cookie` value for a cookie which is only sent over HTTPS (unless `secure` is
+/// disabled), isn't readable from scripts, and isn't sent on cross-site subrequests.
+///
+/// Names and paths are checked with `is_cookie_name` and `is_cookie_path` when they're configured,
+/// and values are generated, so the result is always a valid header value.
+pub(crate) fn set_cookie(
+    name: &str,
+    value: &str,
+    path: &str,
+    max_age: Duration,
+    secure: bool,
+) -> HeaderValue {
+    let max_age = max_age.as_secs();
+    let mut cookie =
+        format!("{name}={value}; Path={path}; Max-Age={max_age}; HttpOnly; SameSite=Lax");
+    if secure {
+        cookie.push_str("; Secure");
+    }
+
+    HeaderValue::try_from(cookie).expect("cookie names, paths and values are checked beforehand")
+}
+
+/// Generates a random, URL-safe token with 256 bits of entropy, drawn from the operating system's
+/// CSPRNG.
+pub(crate) fn token() -> String {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).expect("the operating system's random number generator failed");
+
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Storage for sessions, identified by a cookie.
+pub trait SessionStore<T>: Send + Sync {
+    /// The error the store fails with.
+    type Error: Display + Send;
+
+    /// Starts a new session, returning the `Set-Cookie` value which hands it to the client.
+    fn create(&self, value: T) -> impl Future<Output = Result<HeaderValue, Self::Error>> + Send;
+
+    /// Gets the value of a request's session, refreshing its idle timeout.
+    fn get(
+        &self,
+        request: &HttpRequest<BoxBody>,
+    ) -> impl Future<Output = Result<Option<T>, Self::Error>> + Send;
+
+    /// Ends a request's session, returning the `Set-Cookie` value which clears it from the client.
+    fn destroy(
+        &self,
+        request: &HttpRequest<BoxBody>,
+    ) -> impl Future<Output = Result<HeaderValue, Self::Error>> + Send;
+
+    /// Checks whether the session cookie is only sent over HTTPS.
+    fn is_secure(&self) -> bool;
+}
+
+/// An in-memory `SessionStore`.
+///
+/// Sessions expire once they've gone unused for the store's idle timeout. Expired sessions are
+/// swept whenever a session is created.
+pub struct MemorySessionStore<T> {
+    sessions: Mutex<HashMap<String, (T, Instant)>>,
+    idle_timeout: Duration,
+    cookie: String,
+    secure: bool,
+}
+
+impl<T> MemorySessionStore<T> {
+    /// Constructs a new, empty `MemorySessionStore`, expiring sessions after `idle_timeout` without
+    /// use.
+    pub fn new(idle_timeout: Duration) -> MemorySessionStore<T> {
+        MemorySessionStore {
+            sessions: Mutex::new(HashMap::new()),
+            idle_timeout,
+            cookie: DEFAULT_COOKIE.to_owned(),
+            secure: true,
+        }
+    }
+
+    /// Sets the name of the session cookie. Defaults to `DEFAULT_COOKIE`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name isn't a valid cookie name.
+    pub fn cookie(mut self, name: impl Into<String>) -> MemorySessionStore<T> {
+        self.cookie = name.into();
+        assert!(is_cookie_name(&self.cookie), "A session cookie must have a valid name!");
+        self
+    }
+
+    /// Sets whether the session cookie is only sent over HTTPS. Defaults to `true`, and should
+    /// only be disabled for local development.
+    pub fn secure(mut self, secure: bool) -> MemorySessionStore<T> {
+        self.secure = secure;
+        self
+    }
+
+    /// Gets the name of the session cookie.
+    pub fn cookie_name(&self) -> &str {
+        &self.cookie
+    }
+
+    /// Gets the number of stored sessions, including any which have expired but not been swept.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Checks whether there are no stored sessions.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, (T, Instant)>> {
+        self.sessions.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<T: Clone + Send> SessionStore<T> for MemorySessionStore<T> {
+    type Error = Infallible;
+
+    async fn create(&self, value: T) -> Result<HeaderValue, Infallible> {
+        let id = token();
+        let now = Instant::now();
+
+        let mut sessions = self.lock();
+        sessions.retain(|_, (_, used)| now.duration_since(*used) < self.idle_timeout);
+        sessions.insert(id.clone(), (value, now));
+
+        Ok(set_cookie(&self.cookie, &id, "/", self.idle_timeout, self.secure))
+    }
+
+    async fn get(&self, request: &HttpRequest<BoxBody>) -> Result<Option<T>, Infallible> {
+        let Some(id) = cookie(request, &self.cookie) else { return Ok(None); };
+        let now = Instant::now();
+
+        let mut sessions = self.lock();
+        let Some((value, used)) = sessions.get_mut(id) else { return Ok(None); };
+
+        if now.duration_since(*used) >= self.idle_timeout {
+            sessions.remove(id);
+            return Ok(None);
+        }
+
+        *used = now;
+        Ok(Some(value.clone()))
+    }
+
+    async fn destroy(&self, request: &HttpRequest<BoxBody>) -> Result<HeaderValue, Infallible> {
+        if let Some(id) = cookie(request, &self.cookie) {
+            self.lock().remove(id);
+        }
+
+        Ok(set_cookie(&self.cookie, "", "/", Duration::ZERO, self.secure))
+    }
+
+    fn is_secure(&self) -> bool {
+        self.secure
+    }
+}
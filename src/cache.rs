@@ -0,0 +1,243 @@
+//! Typed `Cache-Control` policies for responses.
+//!
+//! `CachePolicy` builds well-formed `Cache-Control` header values, and `CacheDefaults` attaches a
+//! default policy to responses by path prefix, for responses which don't set one themselves.
+//!
+//! ```
+//! use grazie::cache::CachePolicy;
+//! use std::time::Duration;
+//!
+//! let policy = CachePolicy::new()
+//!     .public()
+//!     .max_age(Duration::from_secs(60))
+//!     .stale_while_revalidate(Duration::from_secs(30));
+//!
+//! assert_eq!(policy.to_string(), "public, max-age=60, stale-while-revalidate=30");
+//! ```
+
+use crate::core::seeder::BoxBody;
+use crate::http::header::{HeaderValue, CACHE_CONTROL};
+use crate::http::{HttpRequest, HttpResponse};
+use std::fmt;
+use std::time::Duration;
+
+/// Who a response may be cached by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Visibility {
+    Public,
+    Private,
+}
+
+/// A typed `Cache-Control` policy.
+///
+/// Setting `no_store` overrides every other directive, since a response which may not be stored
+/// can't be reused either.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CachePolicy {
+    visibility: Option<Visibility>,
+    no_cache: bool,
+    no_store: bool,
+    no_transform: bool,
+    must_revalidate: bool,
+    proxy_revalidate: bool,
+    immutable: bool,
+    max_age: Option<Duration>,
+    s_maxage: Option<Duration>,
+    stale_while_revalidate: Option<Duration>,
+    stale_if_error: Option<Duration>,
+}
+
+impl CachePolicy {
+    /// Constructs a new, empty `CachePolicy`.
+    pub fn new() -> CachePolicy {
+        CachePolicy::default()
+    }
+
+    /// Constructs a policy which forbids storing the response anywhere.
+    pub fn no_store() -> CachePolicy {
+        CachePolicy {
+            no_store: true,
+            ..CachePolicy::default()
+        }
+    }
+
+    /// Constructs a policy for fingerprinted assets, which may be cached anywhere for a year and
+    /// never revalidated.
+    pub fn immutable_asset() -> CachePolicy {
+        CachePolicy::new()
+            .public()
+            .max_age(Duration::from_secs(365 * 24 * 60 * 60))
+            .immutable()
+    }
+
+    /// Allows the response to be stored by shared caches. This replaces `private`.
+    pub fn public(mut self) -> CachePolicy {
+        self.visibility = Some(Visibility::Public);
+        self
+    }
+
+    /// Only allows the response to be stored by the client's private cache. This replaces
+    /// `public`.
+    pub fn private(mut self) -> CachePolicy {
+        self.visibility = Some(Visibility::Private);
+        self
+    }
+
+    /// Requires caches to revalidate the response before every reuse.
+    pub fn no_cache(mut self) -> CachePolicy {
+        self.no_cache = true;
+        self
+    }
+
+    /// Forbids intermediaries from transforming the response.
+    pub fn no_transform(mut self) -> CachePolicy {
+        self.no_transform = true;
+        self
+    }
+
+    /// Forbids reusing the response once it's stale without revalidating it.
+    pub fn must_revalidate(mut self) -> CachePolicy {
+        self.must_revalidate = true;
+        self
+    }
+
+    /// Forbids shared caches from reusing the response once it's stale without revalidating it.
+    pub fn proxy_revalidate(mut self) -> CachePolicy {
+        self.proxy_revalidate = true;
+        self
+    }
+
+    /// Marks the response as never changing while it's fresh.
+    pub fn immutable(mut self) -> CachePolicy {
+        self.immutable = true;
+        self
+    }
+
+    /// Sets how long the response stays fresh. This is sent in whole seconds.
+    pub fn max_age(mut self, max_age: Duration) -> CachePolicy {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Sets how long the response stays fresh in shared caches, overriding `max_age` there.
+    pub fn s_maxage(mut self, s_maxage: Duration) -> CachePolicy {
+        self.s_maxage = Some(s_maxage);
+        self
+    }
+
+    /// Sets how long a stale response may be reused while it's revalidated in the background.
+    pub fn stale_while_revalidate(mut self, duration: Duration) -> CachePolicy {
+        self.stale_while_revalidate = Some(duration);
+        self
+    }
+
+    /// Sets how long a stale response may be reused when revalidating it fails.
+    pub fn stale_if_error(mut self, duration: Duration) -> CachePolicy {
+        self.stale_if_error = Some(duration);
+        self
+    }
+
+    /// Formats this policy as a `Cache-Control` header value.
+    pub fn header_value(&self) -> HeaderValue {
+        HeaderValue::try_from(self.to_string()).unwrap()
+    }
+
+    /// Sets the `Cache-Control` header of a response to this policy.
+    pub fn apply(&self, response: &mut HttpResponse<BoxBody>) {
+        response.headers_mut().insert(CACHE_CONTROL, self.header_value());
+    }
+}
+
+impl fmt::Display for CachePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.no_store {
+            return f.write_str("no-store");
+        }
+
+        let mut directives: Vec<String> = Vec::new();
+
+        match self.visibility {
+            Some(Visibility::Public) => { directives.push("public".to_owned()); }
+            Some(Visibility::Private) => { directives.push("private".to_owned()); }
+            None => {}
+        }
+
+        for (set, directive) in [
+            (self.no_cache, "no-cache"),
+            (self.no_transform, "no-transform"),
+            (self.must_revalidate, "must-revalidate"),
+            (self.proxy_revalidate, "proxy-revalidate"),
+        ] {
+            if set {
+                directives.push(directive.to_owned());
+            }
+        }
+
+        for (duration, directive) in [
+            (self.max_age, "max-age"),
+            (self.s_maxage, "s-maxage"),
+            (self.stale_while_revalidate, "stale-while-revalidate"),
+            (self.stale_if_error, "stale-if-error"),
+        ] {
+            if let Some(duration) = duration {
+                directives.push(format!("{directive}={}", duration.as_secs()));
+            }
+        }
+
+        if self.immutable {
+            directives.push("immutable".to_owned());
+        }
+
+        f.write_str(&directives.join(", "))
+    }
+}
+
+/// Default `CachePolicy`s by path prefix, for responses which don't set their own.
+///
+/// The longest matching prefix wins.
+#[derive(Debug, Clone, Default)]
+pub struct CacheDefaults {
+    defaults: Vec<(String, CachePolicy)>,
+    fallback: Option<CachePolicy>,
+}
+
+impl CacheDefaults {
+    /// Constructs a new `CacheDefaults` without any policies.
+    pub fn new() -> CacheDefaults {
+        CacheDefaults::default()
+    }
+
+    /// Sets the default policy for paths starting with `prefix`.
+    pub fn prefix(mut self, prefix: impl Into<String>, policy: CachePolicy) -> CacheDefaults {
+        self.defaults.push((prefix.into(), policy));
+        self.defaults.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        self
+    }
+
+    /// Sets the default policy for paths matching no prefix.
+    pub fn fallback(mut self, policy: CachePolicy) -> CacheDefaults {
+        self.fallback = Some(policy);
+        self
+    }
+
+    /// Gets the default policy for a path.
+    pub fn policy_for(&self, path: &str) -> Option<&CachePolicy> {
+        self.defaults
+            .iter()
+            .find(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .map(|(_, policy)| policy)
+            .or(self.fallback.as_ref())
+    }
+
+    /// Applies the default policy for a request's path to its response, unless the response has
+    /// already set a `Cache-Control` header.
+    pub fn apply(&self, request: &HttpRequest<BoxBody>, response: &mut HttpResponse<BoxBody>) {
+        if response.headers().contains_key(CACHE_CONTROL) {
+            return;
+        }
+
+        if let Some(policy) = self.policy_for(request.uri().path()) {
+            policy.apply(response);
+        }
+    }
+}
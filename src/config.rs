@@ -0,0 +1,286 @@
+//! Typed application configuration, layered from files, the environment, and overrides.
+//!
+//! Layers are applied in the order they're added, with later layers overriding earlier ones
+//! key by key, so the usual order is defaults, then a file, then environment variables, then
+//! command line overrides:
+//!
+//! ```
+//! use grazie::config::Config;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Settings {
+//!     port: u16,
+//!     database: Database,
+//! }
+//!
+//! #[derive(Deserialize)]
+//! struct Database {
+//!     url: String,
+//!     pool: u32,
+//! }
+//!
+//! let settings: Config<Settings> = Config::builder()
+//!     .toml("port = 8080\n[database]\nurl = \"postgres://localhost/app\"\npool = 4").unwrap()
+//!     .env("APP")
+//!     .set("database.pool", "16")
+//!     .build()
+//!     .unwrap();
+//!
+//! assert_eq!(settings.port, 8080);
+//! assert_eq!(settings.database.pool, 16);
+//! ```
+//!
+//! Part of the `config` feature.
+
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// An error from loading configuration.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// A configuration file couldn't be read.
+    Io(PathBuf, std::io::Error),
+
+    /// A configuration file (or source) couldn't be parsed.
+    Parse(String, String),
+
+    /// The layered configuration doesn't match the configuration type.
+    Invalid(serde_json::Error),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(path, error) => {
+                write!(f, "couldn't read {}: {error}", path.display())
+            }
+            ConfigError::Parse(source, error) => { write!(f, "couldn't parse {source}: {error}") }
+            ConfigError::Invalid(error) => { write!(f, "invalid configuration: {error}") }
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io(_, error) => { Some(error) }
+            ConfigError::Parse(..) => { None }
+            ConfigError::Invalid(error) => { Some(error) }
+        }
+    }
+}
+
+/// Loaded configuration of type `T`.
+///
+/// `Config`s are cheap to clone, and all clones share the same value.
+#[derive(Debug)]
+pub struct Config<T> {
+    value: Arc<T>,
+}
+
+impl<T> Clone for Config<T> {
+    fn clone(&self) -> Config<T> {
+        Config {
+            value: self.value.clone(),
+        }
+    }
+}
+
+impl<T> Config<T> {
+    /// Wraps an already loaded configuration value.
+    pub fn new(value: T) -> Config<T> {
+        Config {
+            value: Arc::new(value),
+        }
+    }
+}
+
+impl Config<()> {
+    /// Starts building a configuration from layers.
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder {
+            root: Value::Object(Map::new()),
+        }
+    }
+}
+
+impl<T> Deref for Config<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+/// Builds a `Config` from layers.
+#[derive(Debug, Clone)]
+pub struct ConfigBuilder {
+    root: Value,
+}
+
+impl ConfigBuilder {
+    /// Layers serializable defaults.
+    pub fn defaults(
+        mut self,
+        defaults: impl serde::Serialize,
+    ) -> Result<ConfigBuilder, ConfigError> {
+        let defaults = serde_json::to_value(defaults).map_err(ConfigError::Invalid)?;
+        merge(&mut self.root, defaults);
+        Ok(self)
+    }
+
+    /// Layers a TOML document.
+    pub fn toml(mut self, source: &str) -> Result<ConfigBuilder, ConfigError> {
+        let layer = toml::from_str(source)
+            .map_err(|error| ConfigError::Parse("TOML".to_owned(), error.to_string()))?;
+        merge(&mut self.root, layer);
+        Ok(self)
+    }
+
+    /// Layers a JSON document.
+    pub fn json(mut self, source: &str) -> Result<ConfigBuilder, ConfigError> {
+        let layer = serde_json::from_str(source)
+            .map_err(|error| ConfigError::Parse("JSON".to_owned(), error.to_string()))?;
+        merge(&mut self.root, layer);
+        Ok(self)
+    }
+
+    /// Layers a TOML or JSON file, by its extension. Files with any other extension are read as
+    /// TOML.
+    pub fn file(self, path: impl AsRef<Path>) -> Result<ConfigBuilder, ConfigError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|error| ConfigError::Io(path.to_owned(), error))?;
+        let relabel = |error| match error {
+            ConfigError::Parse(_, error) => {
+                ConfigError::Parse(path.display().to_string(), error)
+            }
+            error => { error }
+        };
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => { self.json(&source).map_err(relabel) }
+            _ => { self.toml(&source).map_err(relabel) }
+        }
+    }
+
+    /// Layers a file if it exists, such as a local override file.
+    pub fn file_optional(self, path: impl AsRef<Path>) -> Result<ConfigBuilder, ConfigError> {
+        match path.as_ref().exists() {
+            true => { self.file(path) }
+            false => { Ok(self) }
+        }
+    }
+
+    /// Layers the environment variables starting with `<prefix>_`.
+    ///
+    /// The rest of the variable's name is lowercased into the key, with `__` separating nested
+    /// keys: `APP_DATABASE__URL` sets `database.url`.
+    pub fn env(self, prefix: &str) -> ConfigBuilder {
+        self.vars(prefix, std::env::vars())
+    }
+
+    /// Layers variables as `env` would, from the provided variables rather than the environment.
+    pub fn vars(
+        mut self,
+        prefix: &str,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> ConfigBuilder {
+        let prefix = format!("{prefix}_");
+
+        for (name, value) in vars {
+            let Some(key) = name.strip_prefix(&prefix) else { continue; };
+            let path: Vec<String> = key.split("__").map(str::to_ascii_lowercase).collect();
+            insert(&mut self.root, &path, scalar(&value));
+        }
+
+        self
+    }
+
+    /// Sets a single dotted key, such as `database.pool`.
+    ///
+    /// Values which parse as JSON scalars (numbers, booleans, and `null`) are set as them, and
+    /// any other value is set as a string.
+    pub fn set(mut self, key: &str, value: &str) -> ConfigBuilder {
+        let path: Vec<String> = key.split('.').map(str::to_owned).collect();
+        insert(&mut self.root, &path, scalar(value));
+        self
+    }
+
+    /// Layers command line overrides of the form `--set key=value` or `--set=key=value`, skipping
+    /// any other arguments.
+    pub fn args<S: AsRef<str>>(mut self, args: impl IntoIterator<Item = S>) -> ConfigBuilder {
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let pair = match arg.as_ref() {
+                "--set" => { args.next().map(|pair| pair.as_ref().to_owned()) }
+                arg => { arg.strip_prefix("--set=").map(str::to_owned) }
+            };
+
+            if let Some((key, value)) = pair.as_deref().and_then(|pair| pair.split_once('=')) {
+                self = self.set(key, value);
+            }
+        }
+
+        self
+    }
+
+    /// Deserializes the layered configuration.
+    pub fn build<T: DeserializeOwned>(self) -> Result<Config<T>, ConfigError> {
+        serde_json::from_value(self.root).map(Config::new).map_err(ConfigError::Invalid)
+    }
+}
+
+/// Parses an environment or override value, as a JSON scalar if it is one.
+fn scalar(value: &str) -> Value {
+    match serde_json::from_str::<Value>(value) {
+        Ok(parsed) if !parsed.is_object() && !parsed.is_array() && !parsed.is_string() => { parsed }
+        _ => { Value::String(value.to_owned()) }
+    }
+}
+
+/// Sets the value at a key path, creating (or replacing non-object values with) tables on the way.
+fn insert(root: &mut Value, path: &[String], value: Value) {
+    let Some((last, parents)) = path.split_last() else { return; };
+    let mut node = root;
+
+    for key in parents {
+        if !node.is_object() {
+            *node = Value::Object(Map::new());
+        }
+
+        node = node
+            .as_object_mut()
+            .unwrap()
+            .entry(key.clone())
+            .or_insert_with(|| Value::Object(Map::new()));
+    }
+
+    if !node.is_object() {
+        *node = Value::Object(Map::new());
+    }
+
+    node.as_object_mut().unwrap().insert(last.clone(), value);
+}
+
+/// Merges a layer into the configuration, key by key for tables, replacing any other values.
+fn merge(base: &mut Value, layer: Value) {
+    match (base, layer) {
+        (Value::Object(base), Value::Object(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(&key) {
+                    Some(existing) => { merge(existing, value); }
+                    None => { base.insert(key, value); }
+                }
+            }
+        }
+        (base, layer) => { *base = layer; }
+    }
+}
@@ -1 +1,2 @@
+pub mod encoding;
 pub mod seeder;
\ No newline at end of file
@@ -0,0 +1,88 @@
+use crate::http::header::{HeaderMap, CONTENT_ENCODING};
+use std::str::FromStr;
+
+#[cfg(feature = "compression")]
+use std::io::{self, Read};
+
+/// A content coding, as named by the `Content-Encoding` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContentEncoding {
+    /// No coding; the body is sent as-is.
+    Identity,
+
+    /// The `gzip` coding.
+    Gzip,
+
+    /// The `deflate` coding, which is zlib-wrapped DEFLATE data. Raw DEFLATE data, which some
+    /// clients send instead, is also accepted when decompressing.
+    Deflate,
+
+    /// The `br` (Brotli) coding.
+    Brotli,
+}
+
+impl ContentEncoding {
+    /// Gets the coding of a message from its `Content-Encoding` header, which is `Identity` if the
+    /// header is missing.
+    ///
+    /// Returns `None` if the coding isn't supported, or if several codings were applied.
+    pub fn from_headers(headers: &HeaderMap) -> Option<ContentEncoding> {
+        match headers.get(CONTENT_ENCODING) {
+            Some(encoding) => { encoding.to_str().ok()?.parse().ok() }
+            None => { Some(ContentEncoding::Identity) }
+        }
+    }
+
+    /// Gets the name of this coding, as used in the `Content-Encoding` header.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentEncoding::Identity => { "identity" }
+            ContentEncoding::Gzip => { "gzip" }
+            ContentEncoding::Deflate => { "deflate" }
+            ContentEncoding::Brotli => { "br" }
+        }
+    }
+
+    /// Decompresses data in this coding, failing if the decompressed data is larger than `limit`
+    /// bytes.
+    ///
+    /// Part of the `compression` feature.
+    #[cfg(feature = "compression")]
+    pub fn decompress(&self, data: &[u8], limit: usize) -> io::Result<Vec<u8>> {
+        match self {
+            ContentEncoding::Identity => { read_limited(data, limit) }
+            ContentEncoding::Gzip => { read_limited(flate2::read::MultiGzDecoder::new(data), limit) }
+            ContentEncoding::Deflate => {
+                read_limited(flate2::read::ZlibDecoder::new(data), limit)
+                    .or_else(|_| read_limited(flate2::read::DeflateDecoder::new(data), limit))
+            }
+            ContentEncoding::Brotli => { read_limited(brotli::Decompressor::new(data, 4096), limit) }
+        }
+    }
+}
+
+impl FromStr for ContentEncoding {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<ContentEncoding, ()> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "" | "identity" => { Ok(ContentEncoding::Identity) }
+            "gzip" | "x-gzip" => { Ok(ContentEncoding::Gzip) }
+            "deflate" => { Ok(ContentEncoding::Deflate) }
+            "br" => { Ok(ContentEncoding::Brotli) }
+            _ => { Err(()) }
+        }
+    }
+}
+
+/// Reads a stream to its end, failing once more than `limit` bytes have been read.
+#[cfg(feature = "compression")]
+fn read_limited(reader: impl Read, limit: usize) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    reader.take(limit as u64 + 1).read_to_end(&mut data)?;
+
+    match data.len() > limit {
+        true => { Err(io::Error::new(io::ErrorKind::InvalidData, "decompressed body exceeds the size limit")) }
+        false => { Ok(data) }
+    }
+}
@@ -1,7 +1,14 @@
 use std::any::Any;
 use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use std::pin::Pin;
 use std::sync::Arc;
 
+#[cfg(feature = "digest")]
+use std::sync::OnceLock;
+
+#[cfg(feature = "compression")]
+use crate::core::encoding::ContentEncoding;
+
 #[cfg(feature = "serde")]
 use serde::de::DeserializeOwned;
 
@@ -33,26 +40,27 @@ pub trait Unpacker {
 /// Holds the accessibility state of a route, as handled by a `Seeder` acting as a route guard
 /// any series of `Route`s/`Router`s.
 ///
-/// `Guard::Accessible(&HttpRequest<BoxBody>)` is used to pass along a successful route check to
-/// the `HttpServer`. This makes allows the request to access the requested resource.
+/// `Guard::Accessible(&mut HttpRequest<BoxBody>)` is used to pass along a successful route check
+/// to the `HttpServer`. This makes allows the request to access the requested resource. The guard
+/// holds a mutable borrow of the request, so a `Seeder` may transform the request (its headers,
+/// extensions, or body) before handing it along.
 ///
-/// `Guard::Inaccessible { reason, status_code }` is used to pass along a route check that was
-/// unsuccessful, containing the request body, reason, and status code to use for the rejected
-/// request. A `Seeder` can be used to catch `Inaccessible` requests and create a new response body.
-/// This can be used for things like providing error codes, error traces, messages, standardized
-/// API responses, and more.
+/// `Guard::Inaccessible { request, respondent, rejection }` is used to pass along a route check
+/// that was unsuccessful, containing the request, and a `Rejection` describing why (and with what
+/// status) the request was rejected. A `Seeder` can be used to catch `Inaccessible` requests and
+/// create a new response body. This can be used for things like providing error codes, error
+/// traces, messages, standardized API responses, and more.
 pub enum Guard<'a, T> {
     /// A successful Guard check was met, and the request chain will continue to the requested
     /// accessible route.
-    Accessible(&'a T),
+    Accessible(&'a mut T),
 
     /// An unsuccessful Guard check was met, and the request chain will only continue on to
     /// `Seeder`s which accept inaccessible guards.
     Inaccessible {
-        request: &'a T,
+        request: &'a mut T,
         respondent: Respondent,
-        reason: Option<&'static str>,
-        status_code: StatusCode,
+        rejection: Rejection,
     },
 }
 
@@ -74,7 +82,7 @@ impl<'a, T> Guard<'a, T> {
     }
 
     /// Unwraps this `Guard`, exposing an `Accessible` `Guard` value.
-    pub const fn unwrap(self) -> &'a T {
+    pub fn unwrap(self) -> &'a mut T {
         match self {
             Guard::Accessible(value) => { value }
             Guard::Inaccessible { .. } => { panic!("Called unwrap on an inaccessible guard!"); }
@@ -82,16 +90,71 @@ impl<'a, T> Guard<'a, T> {
     }
 }
 
+/// Describes why a request was rejected by a `Seeder`, carried by `Guard::Inaccessible`.
+///
+/// A `Rejection` holds enough structure for standardized error bodies (such as RFC 7807
+/// `application/problem+json` documents) to be generated from it, without each `Seeder` having
+/// to build its own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rejection {
+    /// A stable, machine-readable code for the rejection, such as `"maintenance"`.
+    pub code: String,
+
+    /// A human-readable message describing the rejection.
+    pub message: String,
+
+    /// The status code to respond to the rejected request with.
+    pub status: StatusCode,
+
+    /// Additional details about the rejection, which are serialized into error bodies.
+    ///
+    /// Part of the `serde_json` feature.
+    #[cfg(feature = "serde_json")]
+    pub details: Option<serde_json::Value>,
+}
+
+impl Rejection {
+    /// Constructs a new `Rejection`, without any details.
+    pub fn new(status: StatusCode, code: impl Into<String>, message: impl Into<String>) -> Rejection {
+        Rejection {
+            code: code.into(),
+            message: message.into(),
+            status,
+            #[cfg(feature = "serde_json")]
+            details: None,
+        }
+    }
+
+    /// Attaches details to this rejection.
+    ///
+    /// Part of the `serde_json` feature. Details which fail to serialize are left empty.
+    #[cfg(feature = "serde_json")]
+    pub fn details<S: Serialize>(mut self, details: S) -> Rejection {
+        self.details = serde_json::to_value(details).ok();
+        self
+    }
+}
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}): {}", self.status, self.code, self.message)
+    }
+}
+
+impl std::error::Error for Rejection {}
+
 /// Contains a `Respondent` for a `Guard::Inaccessible` result from a `Seeder` object.
 ///
 /// This enum holds the required action for the next `Seeder` which is handling the result from the
 /// `Seeder` which returned the `Guard::Inaccessible` result.
 pub enum Respondent {
-    /// Create a response directly back to the client.
-    Respond(HttpResponse<BoxBody>),
+    /// Create a response directly back to the client. The response is boxed, so that guards stay
+    /// small as they're passed along the request chain.
+    Respond(Box<HttpResponse<BoxBody>>),
 
-    /// Pass the current guard to a different seeder.
-    Reseed(Box<dyn SeederFactory>),
+    /// Pass the current request to a different seeder, created by the factory. A `Chain` runs the
+    /// reseeded request through the factory's seeder, up to the request's `ReseedLimit`.
+    Reseed(Box<dyn SeederFactory + Send>),
 
     /// Specifies some other option for handling this `Guard` result.
     Other(Box<dyn Any + Send>),
@@ -117,6 +180,10 @@ pub enum Respondent {
 pub struct BoxBody {
     /// The pointer to the heap-allocated HTTP request body.
     inner: Arc<[u8]>,
+
+    /// The SHA-256 digest of the body, computed the first time it's needed.
+    #[cfg(feature = "digest")]
+    sha256: OnceLock<[u8; 32]>,
 }
 
 impl BoxBody {
@@ -124,9 +191,16 @@ impl BoxBody {
     pub fn new(inner: Box<[u8]>) -> BoxBody {
         BoxBody {
             inner: Arc::from(inner),
+            #[cfg(feature = "digest")]
+            sha256: OnceLock::new(),
         }
     }
 
+    /// Constructs a new, empty box body.
+    pub fn empty() -> BoxBody {
+        BoxBody::new(Box::new([]))
+    }
+
     /// Attempts to open this `BoxBody`.
     ///
     /// This returns `Some(())` if the open is successful, otherwise, this returns
@@ -146,8 +220,8 @@ impl BoxBody {
     where
         B: Into<Box<[u8]>>,
     {
-        let new_body: Box<[u8]> = body.try_into().ok()?;
-        self.inner = Arc::from(new_body);
+        let new_body: Box<[u8]> = body.into();
+        self.replace(Arc::from(new_body));
 
         Some(())
     }
@@ -157,6 +231,69 @@ impl BoxBody {
         &self.inner
     }
 
+    /// Gets the SHA-256 digest of this `BoxBody`'s bytes.
+    ///
+    /// The digest is computed the first time it's requested and kept with the body until the body
+    /// is rewritten, so seeders and handlers which each need it only hash the body once.
+    ///
+    /// Part of the `digest` feature.
+    #[cfg(feature = "digest")]
+    pub fn sha256(&self) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
+
+        *self.sha256.get_or_init(|| Sha256::digest(&self.inner).into())
+    }
+
+    /// Replaces this `BoxBody`'s bytes, forgetting anything computed from the previous bytes.
+    fn replace(&mut self, inner: Arc<[u8]>) {
+        self.inner = inner;
+
+        #[cfg(feature = "digest")]
+        {
+            self.sha256 = OnceLock::new();
+        }
+    }
+
+    /// Decompresses this `BoxBody`'s bytes, which are in the provided coding, failing if they
+    /// decompress to more than `limit` bytes.
+    ///
+    /// Part of the `compression` feature.
+    #[cfg(feature = "compression")]
+    pub fn decompress(&self, encoding: ContentEncoding, limit: usize) -> std::io::Result<Vec<u8>> {
+        encoding.decompress(&self.inner, limit)
+    }
+
+    /// Attempts to open this `BoxBody` after decompressing it from the provided coding, such as
+    /// one read from the request's `Content-Encoding` header.
+    ///
+    /// Returns `None` if the body fails to decompress, decompresses to more than `limit` bytes, or
+    /// fails to convert.
+    ///
+    /// Part of the `compression` feature.
+    #[cfg(feature = "compression")]
+    pub fn open_compressed<B>(&self, encoding: ContentEncoding, limit: usize) -> Option<B>
+    where
+        B: for<'a> TryFrom<&'a [u8]>,
+    {
+        let data = self.decompress(encoding, limit).ok()?;
+
+        B::try_from(&data).ok()
+    }
+
+    /// Attempts to open this `BoxBody` as a JSON object, after decompressing it from the provided
+    /// coding.
+    ///
+    /// Part of the `compression` and `serde_json` features.
+    #[cfg(all(feature = "compression", feature = "serde_json"))]
+    pub async fn open_json_compressed<D>(&self, encoding: ContentEncoding, limit: usize) -> Option<D>
+    where
+        D: DeserializeOwned,
+    {
+        let data = self.decompress(encoding, limit).ok()?;
+
+        serde_json::from_slice::<D>(&data).ok()
+    }
+
     /// Attempts to open this `BoxBody` as a JSON object.
     ///
     /// Returns `Some(T)` for a successful read, and `None` for an unsuccessful read.
@@ -164,7 +301,7 @@ impl BoxBody {
     /// Part of the `serde_json` feature, this can only be done for types which implement
     /// the `DeserializeOwned` trait.
     #[cfg(feature = "serde_json")]
-    pub fn open_json<D>(&self) -> impl Future<Output = Option<D>> + Send
+    pub async fn open_json<D>(&self) -> Option<D>
     where
         D: DeserializeOwned,
     {
@@ -178,14 +315,14 @@ impl BoxBody {
     /// Part of the `serde_json` feature, this can only be done for types which implement
     /// the `Serialize` trait.
     #[cfg(feature = "serde_json")]
-    pub fn close_json<S>(&mut self, json: S) -> impl Future<Output = Option<()>> + Send
+    pub async fn close_json<S>(&mut self, json: S) -> Option<()>
     where
         S: Serialize,
     {
         match serde_json::to_vec(&json).ok() {
             Some(b) => {
                 let bx = b.into_boxed_slice();
-                self.inner = Arc::from(bx);
+                self.replace(Arc::from(bx));
 
                 Some(())
             }
@@ -193,6 +330,39 @@ impl BoxBody {
         }
     }
 
+    /// Attempts to open this `BoxBody` as CSV records, with a header row and comma delimiters.
+    ///
+    /// Returns `Some(Vec<T>)` for a successful read, and `None` for an unsuccessful read.
+    ///
+    /// Part of the `csv` feature, this can only be done for types which implement the
+    /// `DeserializeOwned` trait. Other dialects can be read with `grazie::csv::Csv::decode`.
+    #[cfg(feature = "csv")]
+    pub async fn open_csv<D>(&self) -> Option<Vec<D>>
+    where
+        D: DeserializeOwned,
+    {
+        crate::csv::Csv::new().decode(self).ok()
+    }
+
+    /// Attempts to write CSV records back into this `BoxBody`, with a header row and comma
+    /// delimiters.
+    ///
+    /// Returns `Some(())` for a successful write, and `None` for an unsuccessful write.
+    ///
+    /// Part of the `csv` feature, this can only be done for types which implement the
+    /// `Serialize` trait.
+    #[cfg(feature = "csv")]
+    pub async fn close_csv<S, I>(&mut self, records: I) -> Option<()>
+    where
+        S: Serialize,
+        I: IntoIterator<Item = S>,
+    {
+        let body = crate::csv::Csv::new().encode(records).ok()?;
+        self.replace(body.inner);
+
+        Some(())
+    }
+
     /// Attempts to open this `BoxBody` as an XML object.
     ///
     /// Returns `Some(())` for a successful write, and `None` for an unsuccessful write.
@@ -200,7 +370,7 @@ impl BoxBody {
     /// Part of the `serde_xml` feature, this can only be done for types which implement
     /// the `Serialize` trait.
     #[cfg(feature = "serde_xml")]
-    pub fn open_xml<D>(&self) -> impl Future<Output = Option<D>> + Send
+    pub async fn open_xml<D>(&self) -> Option<D>
     where
         D: DeserializeOwned,
     {
@@ -214,14 +384,14 @@ impl BoxBody {
     /// Part of the `serde_xml` feature, this can only be done for types which implement
     /// the `Serialize` trait.
     #[cfg(feature = "serde_xml")]
-    pub fn close_xml<S>(&mut self, xml: S) -> impl Future<Output = Option<()>> + Send
+    pub async fn close_xml<S>(&mut self, xml: S) -> Option<()>
     where
         S: Serialize,
     {
         match serde_xml_rs::to_string(&xml).ok() {
             Some(b) => {
                 let bx = b.into_bytes().into_boxed_slice();
-                self.inner = Arc::from(bx);
+                self.replace(Arc::from(bx));
 
                 Some(())
             }
@@ -230,6 +400,43 @@ impl BoxBody {
     }
 }
 
+/// The most times a request may be reseeded when it has no `ReseedLimit`.
+pub const DEFAULT_RESEED_LIMIT: usize = 8;
+
+/// The most times a request may be reseeded, across every `Chain` it runs through, before it's
+/// rejected with `508 Loop Detected`. Requests without one are limited to `DEFAULT_RESEED_LIMIT`.
+///
+/// `ReseedLimit` is also a `Seeder`, which sets itself as the limit of every request it sees, so
+/// it can be put at the front of a stack, as in `ReseedLimit(16).then(auth).then(router)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReseedLimit(pub usize);
+
+impl Seeder for ReseedLimit {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        match input {
+            Guard::Accessible(request) => {
+                request.extensions_mut().insert(*self);
+                Guard::Accessible(request)
+            }
+            Guard::Inaccessible { request, respondent, rejection } => {
+                request.extensions_mut().insert(*self);
+                Guard::Inaccessible { request, respondent, rejection }
+            }
+        }
+    }
+}
+
+/// The factories a request has been reseeded by, in order, as kept in its extensions.
+#[derive(Clone, Default)]
+struct Reseeds(Vec<&'static str>);
+
+/// The outcome of running a reseeded request through a `SeederFactory`'s seeder: `None` if the
+/// request was accepted, or the respondent and rejection it was rejected with.
+pub type Reseeded<'a> = Pin<Box<dyn Future<Output = Option<(Respondent, Rejection)>> + Send + 'a>>;
+
 /// Trait implemented on an object which may create any `Seeder` object.
 ///
 /// `SeederFactory` objects generally don't maintain instances of themselves, they should be a
@@ -243,6 +450,32 @@ pub trait SeederFactory: 'static  {
     fn create<T: Seeder>() -> T
     where
         Self: Sized;
+
+    /// Runs a request through a newly created `Seeder`, as asked for by a `Respondent::Reseed`.
+    ///
+    /// By default, the request is refused with `500 Internal Server Error`, rather than let
+    /// through without the seeder it was meant for.
+    fn reseed<'a>(self: Box<Self>, _request: &'a mut HttpRequest<BoxBody>) -> Reseeded<'a> {
+        Box::pin(async move {
+            let rejection = Rejection::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "reseed_unsupported",
+                "The request was reseeded through a factory which can't reseed it.",
+            );
+            let response = HttpResponse::builder()
+                .status(rejection.status)
+                .body(BoxBody::empty())
+                .unwrap();
+
+            Some((Respondent::Respond(Box::new(response)), rejection))
+        })
+    }
+
+    /// Names this factory in the diagnostics of a request which was reseeded too many times.
+    /// Defaults to the factory's type name.
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
 }
 
 /// Trait implemented on an object which implements some middleware functionality.
@@ -253,10 +486,122 @@ pub trait Seeder {
     /// When an HttpRequest is passed through the server into this `Seeder`, the `seed` method is
     /// invoked. The `seed` method accepts a guarded `HttpRequest` object, and, depending on this
     /// seeder's implementation, must reject or accept the `HttpRequest`. If the `HttpRequest` is
-    /// accepted, then this must return `Guard::Accessible(&HttpRequest)`.
-    fn seed(
-        &self,
-        input: Guard<&HttpRequest<BoxBody>>,
-    ) -> impl Future<Output = Guard<&HttpRequest<BoxBody>>> + Send;
+    /// accepted, then this must return `Guard::Accessible(&mut HttpRequest)`.
+    fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> impl Future<Output = Guard<'a, HttpRequest<BoxBody>>> + Send;
+
+    /// Chains another `Seeder` to run after this one, creating a single `Seeder` out of both.
+    ///
+    /// Chains can be nested to build ordered stacks of any length, such as
+    /// `auth.then(rate_limit).then(logger)`.
+    fn then<S: Seeder>(self, next: S) -> Chain<Self, S>
+    where
+        Self: Sized,
+    {
+        Chain {
+            first: self,
+            second: next,
+        }
+    }
+}
+
+/// Two `Seeder`s run one after the other, as created by `Seeder::then`.
+///
+/// The guard returned by the first `Seeder` is handed to the second, so a rejection by the first is
+/// only handled by a second `Seeder` which accepts inaccessible guards. A rejection with a
+/// `Respondent::Ignore` respondent is handed along as accessible.
+///
+/// A rejection by either `Seeder` with a `Respondent::Reseed` respondent runs the request through
+/// the factory's seeder, and the chain carries on with its outcome. Reseeds are counted on the
+/// request, so a request reseeded more times than its `ReseedLimit` allows is rejected with
+/// `508 Loop Detected`, rather than reseeded without end. The rejection names the factories which
+/// looped.
+pub struct Chain<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> Chain<A, B> {
+    /// Gets the `Seeder` which runs first.
+    pub fn first(&self) -> &A {
+        &self.first
+    }
+
+    /// Gets the `Seeder` which runs second.
+    pub fn second(&self) -> &B {
+        &self.second
+    }
+}
+
+impl<A: Seeder + Sync, B: Seeder + Sync> Seeder for Chain<A, B> {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let guard = match reseed(self.first.seed(input).await).await {
+            Guard::Inaccessible { request, respondent: Respondent::Ignore, .. } => { Guard::Accessible(request) }
+            guard => { guard }
+        };
+
+        reseed(self.second.seed(guard).await).await
+    }
+}
+
+/// Runs a guard's request through the factories of `Respondent::Reseed` respondents, for as long
+/// as they're returned and the request hasn't reached its `ReseedLimit`.
+async fn reseed<'a>(mut guard: Guard<'a, HttpRequest<BoxBody>>) -> Guard<'a, HttpRequest<BoxBody>> {
+    loop {
+        let (request, factory) = match guard {
+            Guard::Inaccessible { request, respondent: Respondent::Reseed(factory), .. } => {
+                (request, factory)
+            }
+            guard => { return guard; }
+        };
+
+        let limit = request.extensions().get::<ReseedLimit>().copied();
+        let limit = limit.map_or(DEFAULT_RESEED_LIMIT, |limit| limit.0);
+        let mut reseeds = request.extensions().get::<Reseeds>().cloned().unwrap_or_default();
+
+        if reseeds.0.len() >= limit {
+            let cycle = reseed_cycle(&reseeds.0, factory.name());
+            let rejection = Rejection::new(
+                StatusCode::LOOP_DETECTED,
+                "reseed_limit",
+                format!("The request was reseeded too many times, through {}.", cycle.join(" -> ")),
+            );
+            #[cfg(feature = "serde_json")]
+            let rejection = rejection.details(serde_json::json!({ "cycle": cycle }));
+            let response =
+                HttpResponse::builder().status(rejection.status).body(BoxBody::empty()).unwrap();
+
+            return Guard::Inaccessible {
+                request,
+                respondent: Respondent::Respond(Box::new(response)),
+                rejection,
+            };
+        }
+        reseeds.0.push(factory.name());
+        request.extensions_mut().insert(reseeds);
+
+        guard = match factory.reseed(&mut *request).await {
+            Some((respondent, rejection)) => {
+                Guard::Inaccessible { request, respondent, rejection }
+            }
+            None => { Guard::Accessible(request) }
+        };
+    }
+}
+
+/// Finds the factories a request looped through, given the factories it was reseeded by and the
+/// factory which would have reseeded it next: from that factory's last reseed onwards, or every
+/// reseed if it hasn't reseeded the request before.
+fn reseed_cycle(reseeds: &[&'static str], next: &'static str) -> Vec<&'static str> {
+    let start = reseeds.iter().rposition(|name| *name == next).unwrap_or(0);
+    let mut cycle = reseeds[start..].to_vec();
+    cycle.push(next);
+
+    cycle
 }
 
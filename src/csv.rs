@@ -0,0 +1,783 @@
+//! CSV encoding and decoding of records, for data exports and imports.
+//!
+//! Records are any `Serialize` or `Deserialize` type made up of flat fields, such as structs of
+//! strings and numbers. Struct (and map) records get a header row naming their fields, when
+//! encoding, and are matched to columns by those headers when decoding; tuple records are matched
+//! by position. Fields containing the delimiter, quotes, or line breaks are quoted:
+//!
+//! ```
+//! use grazie::csv::Csv;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Order {
+//!     id: u32,
+//!     customer: String,
+//!     total: Option<f64>,
+//! }
+//!
+//! let orders = [
+//!     Order { id: 1, customer: "Ada".into(), total: Some(9.5) },
+//!     Order { id: 2, customer: "Lovelace, Ada".into(), total: None },
+//! ];
+//!
+//! let body = Csv::new().encode(&orders).unwrap();
+//! assert_eq!(body.raw_bytes(), b"id,customer,total\r\n1,Ada,9.5\r\n2,\"Lovelace, Ada\",\r\n");
+//!
+//! let decoded: Vec<Order> = Csv::new().decode(&body).unwrap();
+//! assert_eq!(decoded, orders);
+//! ```
+//!
+//! Part of the `csv` feature.
+
+use crate::core::seeder::BoxBody;
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{DeserializeOwned, IntoDeserializer, Visitor};
+use serde::ser::{Impossible, SerializeMap, SerializeSeq, SerializeStruct, SerializeTuple};
+use serde::ser::SerializeTupleStruct;
+use serde::{Deserializer, Serialize, Serializer};
+use std::fmt::{Display, Formatter};
+use std::io;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// The media type of CSV documents.
+pub const TEXT_CSV: &str = "text/csv";
+
+/// An error encoding or decoding CSV.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvError {
+    /// The record the error was found in, counted from 1, if it's known. When decoding, the header
+    /// row is the first record.
+    pub record: Option<u64>,
+
+    /// A human-readable message describing the problem.
+    pub message: String,
+}
+
+impl CsvError {
+    fn new(message: impl Into<String>) -> CsvError {
+        CsvError {
+            record: None,
+            message: message.into(),
+        }
+    }
+
+    fn at(mut self, record: u64) -> CsvError {
+        self.record.get_or_insert(record);
+        self
+    }
+}
+
+impl Display for CsvError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.record {
+            Some(record) => { write!(f, "record {record}: {}", self.message) }
+            None => { write!(f, "{}", self.message) }
+        }
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+impl serde::ser::Error for CsvError {
+    fn custom<T: Display>(message: T) -> CsvError {
+        CsvError::new(message.to_string())
+    }
+}
+
+impl serde::de::Error for CsvError {
+    fn custom<T: Display>(message: T) -> CsvError {
+        CsvError::new(message.to_string())
+    }
+}
+
+/// The dialect records are encoded and decoded with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Csv {
+    delimiter: u8,
+    headers: bool,
+}
+
+impl Csv {
+    /// Constructs a new `Csv`, separating fields with commas and starting with a header row.
+    pub fn new() -> Csv {
+        Csv {
+            delimiter: b',',
+            headers: true,
+        }
+    }
+
+    /// Sets the byte fields are separated by, such as `b';'` or `b'\t'`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the delimiter isn't ASCII, or is a quote or line break.
+    pub fn delimiter(mut self, delimiter: u8) -> Csv {
+        assert!(
+            delimiter.is_ascii() && !matches!(delimiter, b'"' | b'\r' | b'\n'),
+            "A CSV delimiter must be an ASCII byte other than a quote or line break!"
+        );
+
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Sets whether documents start with a header row. When they don't, struct records are
+    /// matched to columns by position.
+    pub fn headers(mut self, headers: bool) -> Csv {
+        self.headers = headers;
+        self
+    }
+
+    /// Encodes records as a CSV body.
+    pub fn encode<T, I>(&self, records: I) -> Result<BoxBody, CsvError>
+    where
+        T: Serialize,
+        I: IntoIterator<Item = T>,
+    {
+        let mut body = Vec::new();
+        let mut first = true;
+
+        for (record, number) in records.into_iter().zip(1..) {
+            let (headers, fields) = fields(&record).map_err(|error| error.at(number))?;
+
+            if first && self.headers && !headers.is_empty() {
+                self.write_row(&mut body, &headers);
+            }
+
+            self.write_row(&mut body, &fields);
+            first = false;
+        }
+
+        Ok(BoxBody::new(body.into_boxed_slice()))
+    }
+
+    /// Decodes the records of a CSV body.
+    pub fn decode<T: DeserializeOwned>(&self, body: &BoxBody) -> Result<Vec<T>, CsvError> {
+        let text = std::str::from_utf8(body.raw_bytes())
+            .map_err(|_| CsvError::new("CSV must be UTF-8"))?;
+        let mut rows = self.parse(text)?.into_iter().zip(1..);
+
+        let headers = match self.headers {
+            true => { rows.next().map(|(headers, _)| headers) }
+            false => { None }
+        };
+
+        rows.map(|(row, number)| {
+            if let Some(headers) = &headers {
+                if row.len() != headers.len() {
+                    let message = format!(
+                        "found {} fields, but there are {} headers",
+                        row.len(),
+                        headers.len(),
+                    );
+                    return Err(CsvError::new(message).at(number));
+                }
+            }
+
+            T::deserialize(Row { headers: headers.as_deref(), fields: &row })
+                .map_err(|error| error.at(number))
+        })
+        .collect()
+    }
+
+    /// Creates a writer encoding records in this dialect.
+    pub fn writer<W: AsyncWrite + Unpin>(&self, inner: W) -> CsvWriter<W> {
+        CsvWriter {
+            csv: *self,
+            inner,
+            row: Vec::new(),
+            written: 0,
+        }
+    }
+
+    /// Writes a row of fields, quoting those which need it. A row of a single empty field is
+    /// quoted, so that it isn't read back as a blank line.
+    fn write_row(&self, out: &mut Vec<u8>, fields: &[String]) {
+        if let [field] = fields {
+            if field.is_empty() {
+                out.extend_from_slice(b"\"\"\r\n");
+                return;
+            }
+        }
+
+        for (index, field) in fields.iter().enumerate() {
+            if index > 0 {
+                out.push(self.delimiter);
+            }
+
+            let quoted = field
+                .bytes()
+                .any(|byte| byte == self.delimiter || matches!(byte, b'"' | b'\r' | b'\n'));
+            match quoted {
+                true => {
+                    out.push(b'"');
+                    out.extend_from_slice(field.replace('"', "\"\"").as_bytes());
+                    out.push(b'"');
+                }
+                false => { out.extend_from_slice(field.as_bytes()); }
+            }
+        }
+
+        out.extend_from_slice(b"\r\n");
+    }
+
+    /// Splits a document into rows of fields. Blank lines are skipped.
+    fn parse(&self, text: &str) -> Result<Vec<Vec<String>>, CsvError> {
+        let delimiter = self.delimiter as char;
+        let mut rows = Vec::new();
+        let mut row = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut started = false;
+        let mut chars = text.chars().peekable();
+
+        while let Some(char) = chars.next() {
+            match (quoted, char) {
+                (true, '"') if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                (true, '"') => { quoted = false; }
+                (true, char) => { field.push(char); }
+                (false, '"') if field.is_empty() => {
+                    quoted = true;
+                    started = true;
+                }
+                (false, char) if char == delimiter => {
+                    row.push(std::mem::take(&mut field));
+                    started = true;
+                }
+                (false, '\r') if chars.peek() == Some(&'\n') => {}
+                (false, '\n') => {
+                    if started || !field.is_empty() {
+                        row.push(std::mem::take(&mut field));
+                        rows.push(std::mem::take(&mut row));
+                    }
+                    started = false;
+                }
+                (false, char) => {
+                    field.push(char);
+                    started = true;
+                }
+            }
+        }
+
+        if quoted {
+            return Err(CsvError::new("unterminated quoted field").at(rows.len() as u64 + 1));
+        }
+        if started || !field.is_empty() {
+            row.push(field);
+            rows.push(row);
+        }
+
+        Ok(rows)
+    }
+}
+
+impl Default for Csv {
+    fn default() -> Csv {
+        Csv::new()
+    }
+}
+
+/// Writes records as CSV, one row at a time.
+pub struct CsvWriter<W> {
+    csv: Csv,
+    inner: W,
+    row: Vec<u8>,
+    written: u64,
+}
+
+impl<W: AsyncWrite + Unpin> CsvWriter<W> {
+    /// Writes a record as a row, preceded by the header row if it's the first record, waiting
+    /// until the writer has accepted it.
+    pub async fn write<T: Serialize>(&mut self, record: &T) -> io::Result<()> {
+        let (headers, fields) = fields(record).map_err(|error| {
+            io::Error::new(io::ErrorKind::InvalidData, error.at(self.written + 1))
+        })?;
+
+        self.row.clear();
+        if self.written == 0 && self.csv.headers && !headers.is_empty() {
+            self.csv.write_row(&mut self.row, &headers);
+        }
+        self.csv.write_row(&mut self.row, &fields);
+
+        self.inner.write_all(&self.row).await?;
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Flushes the underlying writer.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush().await
+    }
+
+    /// Gets the number of records written so far.
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// Unwraps the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Serializes a record into its headers (if it names its fields) and fields.
+fn fields<T: Serialize>(record: &T) -> Result<(Vec<String>, Vec<String>), CsvError> {
+    let mut serializer = RecordSerializer::default();
+    record.serialize(&mut serializer)?;
+
+    Ok((serializer.headers, serializer.fields))
+}
+
+/// Serializes a record into its fields.
+#[derive(Default)]
+struct RecordSerializer {
+    headers: Vec<String>,
+    fields: Vec<String>,
+}
+
+impl RecordSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CsvError> {
+        self.fields.push(value.serialize(FieldSerializer)?);
+        Ok(())
+    }
+}
+
+/// Implements the `Serializer` methods of a record which has a single field.
+macro_rules! single_field {
+    ($($method:ident($type:ty)),* $(,)?) => {
+        $(
+            fn $method(self, value: $type) -> Result<(), CsvError> {
+                self.push(&value)
+            }
+        )*
+    };
+}
+
+impl Serializer for &mut RecordSerializer {
+    type Ok = ();
+    type Error = CsvError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Impossible<(), CsvError>;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), CsvError>;
+
+    single_field! {
+        serialize_bool(bool), serialize_i8(i8), serialize_i16(i16), serialize_i32(i32),
+        serialize_i64(i64), serialize_u8(u8), serialize_u16(u16), serialize_u32(u32),
+        serialize_u64(u64), serialize_f32(f32), serialize_f64(f64), serialize_char(char),
+        serialize_str(&str),
+    }
+
+    fn serialize_bytes(self, _: &[u8]) -> Result<(), CsvError> {
+        Err(CsvError::new("bytes can't be written to CSV"))
+    }
+
+    fn serialize_none(self) -> Result<(), CsvError> {
+        self.push(&())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), CsvError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), CsvError> {
+        self.push(&())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), CsvError> {
+        self.push(&())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<(), CsvError> {
+        self.push(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), CsvError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), CsvError> {
+        Err(CsvError::new("enum variants with data can't be written to CSV"))
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self, CsvError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self, CsvError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self, CsvError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, CsvError> {
+        Err(CsvError::new("enum variants with data can't be written to CSV"))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self, CsvError> {
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self, CsvError> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, CsvError> {
+        Err(CsvError::new("enum variants with data can't be written to CSV"))
+    }
+}
+
+impl SerializeSeq for &mut RecordSerializer {
+    type Ok = ();
+    type Error = CsvError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CsvError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), CsvError> {
+        Ok(())
+    }
+}
+
+impl SerializeTuple for &mut RecordSerializer {
+    type Ok = ();
+    type Error = CsvError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CsvError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), CsvError> {
+        Ok(())
+    }
+}
+
+impl SerializeTupleStruct for &mut RecordSerializer {
+    type Ok = ();
+    type Error = CsvError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CsvError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), CsvError> {
+        Ok(())
+    }
+}
+
+impl SerializeMap for &mut RecordSerializer {
+    type Ok = ();
+    type Error = CsvError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), CsvError> {
+        self.headers.push(key.serialize(FieldSerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CsvError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), CsvError> {
+        Ok(())
+    }
+}
+
+impl SerializeStruct for &mut RecordSerializer {
+    type Ok = ();
+    type Error = CsvError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), CsvError> {
+        self.headers.push(key.to_owned());
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), CsvError> {
+        Ok(())
+    }
+}
+
+/// Serializes a single field into its text.
+struct FieldSerializer;
+
+/// Implements the `Serializer` methods of a field which is written as it's displayed.
+macro_rules! displayed {
+    ($($method:ident($type:ty)),* $(,)?) => {
+        $(
+            fn $method(self, value: $type) -> Result<String, CsvError> {
+                Ok(value.to_string())
+            }
+        )*
+    };
+}
+
+impl Serializer for FieldSerializer {
+    type Ok = String;
+    type Error = CsvError;
+    type SerializeSeq = Impossible<String, CsvError>;
+    type SerializeTuple = Impossible<String, CsvError>;
+    type SerializeTupleStruct = Impossible<String, CsvError>;
+    type SerializeTupleVariant = Impossible<String, CsvError>;
+    type SerializeMap = Impossible<String, CsvError>;
+    type SerializeStruct = Impossible<String, CsvError>;
+    type SerializeStructVariant = Impossible<String, CsvError>;
+
+    displayed! {
+        serialize_bool(bool), serialize_i8(i8), serialize_i16(i16), serialize_i32(i32),
+        serialize_i64(i64), serialize_u8(u8), serialize_u16(u16), serialize_u32(u32),
+        serialize_u64(u64), serialize_f32(f32), serialize_f64(f64), serialize_char(char),
+        serialize_str(&str),
+    }
+
+    fn serialize_bytes(self, _: &[u8]) -> Result<String, CsvError> {
+        Err(CsvError::new("bytes can't be written to CSV"))
+    }
+
+    fn serialize_none(self) -> Result<String, CsvError> {
+        Ok(String::new())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String, CsvError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<String, CsvError> {
+        Ok(String::new())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<String, CsvError> {
+        Ok(String::new())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<String, CsvError> {
+        Ok(variant.to_owned())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<String, CsvError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<String, CsvError> {
+        Err(nested())
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, CsvError> {
+        Err(nested())
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, CsvError> {
+        Err(nested())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, CsvError> {
+        Err(nested())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, CsvError> {
+        Err(nested())
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, CsvError> {
+        Err(nested())
+    }
+
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, CsvError> {
+        Err(nested())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, CsvError> {
+        Err(nested())
+    }
+}
+
+fn nested() -> CsvError {
+    CsvError::new("nested values can't be written to CSV")
+}
+
+/// Deserializes a record from a row, matching fields to headers if there are any.
+struct Row<'a> {
+    headers: Option<&'a [String]>,
+    fields: &'a [String],
+}
+
+impl<'de> Deserializer<'de> for Row<'_> {
+    type Error = CsvError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CsvError> {
+        match self.headers {
+            Some(headers) => {
+                let fields = self.fields.iter().map(|field| Field(field));
+                let fields = headers.iter().map(String::as_str).zip(fields);
+                let mut map = MapDeserializer::new(fields);
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            None => {
+                let mut seq = SeqDeserializer::new(self.fields.iter().map(|field| Field(field)));
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option
+        unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier
+        ignored_any
+    }
+}
+
+/// Deserializes a single field from its text.
+struct Field<'a>(&'a str);
+
+impl<'de> IntoDeserializer<'de, CsvError> for Field<'_> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Implements the `Deserializer` methods of a field which is parsed from its text.
+macro_rules! parsed {
+    ($($method:ident => $visit:ident),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CsvError> {
+                match self.0.trim().parse() {
+                    Ok(value) => { visitor.$visit(value) }
+                    Err(_) => {
+                        let kind = &stringify!($method)[12..];
+                        Err(CsvError::new(format!("`{}` isn't a valid {kind}", self.0)))
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for Field<'_> {
+    type Error = CsvError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CsvError> {
+        visitor.visit_str(self.0)
+    }
+
+    parsed! {
+        deserialize_bool => visit_bool, deserialize_i8 => visit_i8, deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32, deserialize_i64 => visit_i64, deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16, deserialize_u32 => visit_u32, deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32, deserialize_f64 => visit_f64,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CsvError> {
+        match self.0.is_empty() {
+            true => { visitor.visit_none() }
+            false => { visitor.visit_some(self) }
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CsvError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, CsvError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, CsvError> {
+        IntoDeserializer::<CsvError>::into_deserializer(self.0)
+            .deserialize_enum(name, variants, visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
@@ -0,0 +1,421 @@
+//! Lifecycle glue for database connection pools.
+//!
+//! `Database` wraps whichever pool the application uses (implementing `Pool` for it is a couple
+//! of lines) and ties it to the server's lifecycle: the pool's connectivity is verified before the
+//! server starts, with retries for databases which start alongside the application, and on
+//! shutdown the pool is closed once the requests using it have finished. Handlers reach the
+//! pool through the request's extensions, and pools which implement `Transactional` can run each
+//! request in its own transaction with `TxSeeder`. With the `postgres` or `sqlite` feature, `sqlx`
+//! pools implement both traits:
+//!
+//! ```no_run
+//! use grazie::db::{Database, Pool};
+//! use grazie::HttpServer;
+//! use std::time::Duration;
+//!
+//! struct Connections;
+//!
+//! impl Pool for Connections {
+//!     type Error = std::io::Error;
+//!
+//!     async fn ping(&self) -> Result<(), std::io::Error> {
+//!         Ok(())
+//!     }
+//!
+//!     async fn close(&self) {}
+//! }
+//!
+//! # async fn example() -> std::io::Result<()> {
+//! let database = Database::new(Connections).startup_attempts(5).retry_delay(Duration::from_secs(2));
+//! let server = database.register(HttpServer::new("127.0.0.1:8080").await?);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::{HttpRequest, HttpResponse, Method, StatusCode};
+use crate::HttpServer;
+use std::fmt::Display;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, MutexGuard, Notify};
+
+/// A connection pool, as managed by a `Database`.
+pub trait Pool: Send + Sync + 'static {
+    /// The error the pool fails with.
+    type Error: Display + Send;
+
+    /// Checks that the database can be reached, such as by running `SELECT 1`.
+    fn ping(&self) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Closes every connection in the pool.
+    fn close(&self) -> impl Future<Output = ()> + Send;
+}
+
+/// The state shared between the clones of a `Database`.
+struct Shared<P> {
+    pool: P,
+    checked_out: AtomicUsize,
+    returned: Notify,
+    healthy: AtomicBool,
+}
+
+/// A handle to a connection pool, shared by the server's lifecycle hooks and its requests.
+///
+/// Cloning a `Database` is cheap, and every clone refers to the same pool.
+pub struct Database<P> {
+    shared: Arc<Shared<P>>,
+    startup_attempts: u32,
+    retry_delay: Duration,
+    drain_timeout: Duration,
+}
+
+impl<P> Clone for Database<P> {
+    fn clone(&self) -> Database<P> {
+        Database {
+            shared: self.shared.clone(),
+            startup_attempts: self.startup_attempts,
+            retry_delay: self.retry_delay,
+            drain_timeout: self.drain_timeout,
+        }
+    }
+}
+
+impl<P: Pool> Database<P> {
+    /// Constructs a new `Database` over a pool. Connectivity is checked once at startup, and
+    /// shutdown waits up to 30 seconds for requests to finish with the pool.
+    pub fn new(pool: P) -> Database<P> {
+        Database {
+            shared: Arc::new(Shared {
+                pool,
+                checked_out: AtomicUsize::new(0),
+                returned: Notify::new(),
+                healthy: AtomicBool::new(false),
+            }),
+            startup_attempts: 1,
+            retry_delay: Duration::from_secs(1),
+            drain_timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Sets how many times connectivity is checked at startup before giving up.
+    pub fn startup_attempts(mut self, attempts: u32) -> Database<P> {
+        self.startup_attempts = attempts.max(1);
+        self
+    }
+
+    /// Sets how long to wait between connectivity checks at startup.
+    pub fn retry_delay(mut self, delay: Duration) -> Database<P> {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Sets how long shutdown waits for requests to finish with the pool before closing it anyway.
+    pub fn drain_timeout(mut self, timeout: Duration) -> Database<P> {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// Gets the pool, without counting towards the requests shutdown waits for.
+    pub fn pool(&self) -> &P {
+        &self.shared.pool
+    }
+
+    /// Checks the pool out for the length of a unit of work, such as a request. Shutdown waits
+    /// for every checkout to be dropped before closing the pool.
+    pub fn checkout(&self) -> Checkout<P> {
+        self.shared.checked_out.fetch_add(1, Ordering::SeqCst);
+
+        Checkout {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Gets how many checkouts are outstanding.
+    pub fn checked_out(&self) -> usize {
+        self.shared.checked_out.load(Ordering::SeqCst)
+    }
+
+    /// Pings the database, recording whether it's healthy.
+    pub async fn check(&self) -> Result<(), P::Error> {
+        let result = self.shared.pool.ping().await;
+        self.shared.healthy.store(result.is_ok(), Ordering::SeqCst);
+        result
+    }
+
+    /// Gets whether the last check found the database healthy.
+    pub fn healthy(&self) -> bool {
+        self.shared.healthy.load(Ordering::SeqCst)
+    }
+
+    /// Checks connectivity, retrying up to the configured number of attempts, and returning the
+    /// last error if every attempt fails.
+    pub async fn verify(&self) -> Result<(), P::Error> {
+        let mut attempt = 1;
+
+        loop {
+            match self.check().await {
+                Ok(()) => { return Ok(()); }
+                Err(error) if attempt >= self.startup_attempts => { return Err(error); }
+                Err(_) => {
+                    attempt += 1;
+                    tokio::time::sleep(self.retry_delay).await;
+                }
+            }
+        }
+    }
+
+    /// Waits for every checkout to be dropped (or the drain timeout to pass), then closes the
+    /// pool. Returns whether every checkout was dropped in time.
+    pub async fn drain(&self) -> bool {
+        let drained = tokio::time::timeout(self.drain_timeout, async {
+            loop {
+                let returned = self.shared.returned.notified();
+                if self.checked_out() == 0 {
+                    return;
+                }
+
+                returned.await;
+            }
+        })
+        .await
+        .is_ok();
+
+        self.shared.healthy.store(false, Ordering::SeqCst);
+        self.shared.pool.close().await;
+        drained
+    }
+
+    /// Registers the database's lifecycle with a server: connectivity is verified by a `database`
+    /// startup hook, and the pool is drained and closed by a `database` shutdown hook.
+    pub fn register(&self, server: HttpServer) -> HttpServer {
+        let startup = self.clone();
+        let shutdown = self.clone();
+
+        server
+            .on_start("database", move || {
+                let database = startup.clone();
+                async move { database.verify().await }
+            })
+            .on_shutdown("database", move || {
+                let database = shutdown.clone();
+                async move {
+                    match database.drain().await {
+                        true => { Ok(()) }
+                        false => { Err("closed the pool with requests still using it") }
+                    }
+                }
+            })
+    }
+}
+
+/// A checkout of a `Database`'s pool, which dereferences to the pool.
+pub struct Checkout<P> {
+    shared: Arc<Shared<P>>,
+}
+
+impl<P> Deref for Checkout<P> {
+    type Target = P;
+
+    fn deref(&self) -> &P {
+        &self.shared.pool
+    }
+}
+
+impl<P> Drop for Checkout<P> {
+    fn drop(&mut self) {
+        self.shared.checked_out.fetch_sub(1, Ordering::SeqCst);
+        self.shared.returned.notify_waiters();
+    }
+}
+
+/// Inserts a checkout of the pool into each request's extensions, so handlers can reach the
+/// pool as `request.extensions().get::<Arc<Checkout<P>>>()`. The checkout is returned when the
+/// request (and any clone of the `Arc`) is dropped.
+impl<P: Pool> Seeder for Database<P> {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        request.extensions_mut().insert(Arc::new(self.checkout()));
+
+        Guard::Accessible(request)
+    }
+}
+
+/// A pool which can run work in transactions.
+pub trait Transactional: Pool {
+    /// An open transaction.
+    type Transaction: Send + 'static;
+
+    /// Begins a transaction.
+    fn begin(&self) -> impl Future<Output = Result<Self::Transaction, Self::Error>> + Send;
+
+    /// Commits a transaction.
+    fn commit(&self, transaction: Self::Transaction) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Rolls a transaction back.
+    fn rollback(&self, transaction: Self::Transaction) -> impl Future<Output = Result<(), Self::Error>> + Send;
+}
+
+/// A request's transaction, as an extension of the request.
+///
+/// The transaction is `None` once it's been committed or rolled back.
+pub struct Transaction<T> {
+    inner: Arc<Mutex<Option<T>>>,
+}
+
+impl<T> Clone for Transaction<T> {
+    fn clone(&self) -> Transaction<T> {
+        Transaction {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Transaction<T> {
+    /// Locks the transaction, for running queries in it.
+    pub async fn lock(&self) -> MutexGuard<'_, Option<T>> {
+        self.inner.lock().await
+    }
+}
+
+/// How a `TxSeeder` finished a request's transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The transaction was committed.
+    Committed,
+
+    /// The transaction was rolled back.
+    RolledBack,
+
+    /// The request had no open transaction.
+    None,
+}
+
+/// A `Seeder` which begins a transaction for each request, and inserts it into the request's
+/// extensions as a `Transaction`.
+///
+/// Since there's no response stage to the request chain, transactions are finished by passing the
+/// request and its response to `TxSeeder::finish`: the transaction is committed if the response
+/// is a success (`2xx`), and rolled back otherwise. A request which is dropped without being
+/// finished drops its transaction, which most drivers roll back. Requests are refused with
+/// `503 Service Unavailable` if a transaction can't be begun.
+pub struct TxSeeder<P> {
+    database: Database<P>,
+    methods: Vec<Method>,
+}
+
+impl<P: Transactional> TxSeeder<P> {
+    /// Constructs a new `TxSeeder`, beginning transactions on the database for `POST`, `PUT`,
+    /// `PATCH` and `DELETE` requests.
+    pub fn new(database: Database<P>) -> TxSeeder<P> {
+        TxSeeder {
+            database,
+            methods: vec![Method::POST, Method::PUT, Method::PATCH, Method::DELETE],
+        }
+    }
+
+    /// Sets the methods whose requests run in a transaction.
+    pub fn methods(mut self, methods: impl IntoIterator<Item = Method>) -> TxSeeder<P> {
+        self.methods = methods.into_iter().collect();
+        self
+    }
+
+    /// Commits or rolls back a request's transaction, depending on its response's status.
+    pub async fn finish(&self, request: &HttpRequest<BoxBody>, response: &HttpResponse<BoxBody>) -> Result<Outcome, P::Error> {
+        let Some(transaction) = request.extensions().get::<Transaction<P::Transaction>>() else {
+            return Ok(Outcome::None);
+        };
+        let Some(transaction) = transaction.lock().await.take() else { return Ok(Outcome::None); };
+
+        let pool = self.database.pool();
+        match response.status().is_success() {
+            true => { pool.commit(transaction).await.map(|_| Outcome::Committed) }
+            false => { pool.rollback(transaction).await.map(|_| Outcome::RolledBack) }
+        }
+    }
+}
+
+impl<P: Transactional> Seeder for TxSeeder<P> {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        if !self.methods.contains(request.method()) {
+            return Guard::Accessible(request);
+        }
+
+        match self.database.pool().begin().await {
+            Ok(transaction) => {
+                request.extensions_mut().insert(Transaction {
+                    inner: Arc::new(Mutex::new(Some(transaction))),
+                });
+
+                Guard::Accessible(request)
+            }
+            Err(_) => {
+                let rejection = Rejection::new(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "transaction_unavailable",
+                    "A database transaction couldn't be begun.",
+                );
+                let response = HttpResponse::builder().status(rejection.status).body(BoxBody::empty()).unwrap();
+
+                Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Respond(Box::new(response)),
+                    rejection,
+                }
+            }
+        }
+    }
+}
+
+/// Pings by acquiring a connection and pinging it, so an exhausted pool counts as unhealthy.
+///
+/// Part of the `postgres` and `sqlite` features.
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+impl<DB: sqlx::Database> Pool for sqlx::Pool<DB> {
+    type Error = sqlx::Error;
+
+    async fn ping(&self) -> Result<(), sqlx::Error> {
+        use sqlx::Connection;
+
+        self.acquire().await?.ping().await
+    }
+
+    async fn close(&self) {
+        sqlx::Pool::close(self).await;
+    }
+}
+
+/// Part of the `postgres` and `sqlite` features.
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+impl<DB: sqlx::Database> Transactional for sqlx::Pool<DB> {
+    type Transaction = sqlx::Transaction<'static, DB>;
+
+    async fn begin(&self) -> Result<sqlx::Transaction<'static, DB>, sqlx::Error> {
+        sqlx::Pool::begin(self).await
+    }
+
+    async fn commit(&self, transaction: sqlx::Transaction<'static, DB>) -> Result<(), sqlx::Error> {
+        transaction.commit().await
+    }
+
+    async fn rollback(&self, transaction: sqlx::Transaction<'static, DB>) -> Result<(), sqlx::Error> {
+        transaction.rollback().await
+    }
+}
@@ -0,0 +1,206 @@
+//! Development-mode helpers, such as reloading browser pages when files change.
+//!
+//! These are only available behind the `dev` feature, and aren't intended for production use.
+
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::{HttpRequest, HttpResponse, Method};
+use crate::longpoll::LongPoll;
+use crate::seeders::html_transform::{HtmlTransformSeeder, Position};
+use crate::util::fnv1a;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+
+/// The path the live-reload client polls by default.
+pub const DEFAULT_ENDPOINT: &str = "/__grazie/livereload";
+
+/// The topic which changes are published to.
+const TOPIC: &str = "reload";
+
+/// Reloads browser pages whenever a watched directory changes.
+///
+/// `LiveReload` answers requests to its endpoint by long-polling until the watched directory
+/// changes, and provides a client snippet which reloads the page once that happens. The snippet
+/// is injected into HTML responses through `LiveReload::html_transform`.
+///
+/// Long-polling is used instead of a WebSocket, since the server has no WebSocket support. The
+/// client also reloads the page after reconnecting to the server, so restarting the server on a
+/// rebuild refreshes the browser too.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn example() {
+/// use grazie::dev::LiveReload;
+///
+/// let reload = LiveReload::new("./static");
+/// let _watcher = reload.watch();
+/// let _transform = reload.html_transform();
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct LiveReload {
+    dir: PathBuf,
+    endpoint: String,
+    interval: Duration,
+    timeout: Duration,
+    events: LongPoll<()>,
+}
+
+impl LiveReload {
+    /// Constructs a new `LiveReload`, watching the provided directory.
+    pub fn new(dir: impl Into<PathBuf>) -> LiveReload {
+        LiveReload {
+            dir: dir.into(),
+            endpoint: DEFAULT_ENDPOINT.to_owned(),
+            interval: Duration::from_millis(500),
+            timeout: Duration::from_secs(30),
+            events: LongPoll::new(1),
+        }
+    }
+
+    /// Sets the path which the client polls. Defaults to `DEFAULT_ENDPOINT`.
+    pub fn endpoint(mut self, endpoint: impl Into<String>) -> LiveReload {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    /// Sets how often the watched directory is scanned for changes. Defaults to 500 milliseconds.
+    pub fn interval(mut self, interval: Duration) -> LiveReload {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets how long a client's poll is held open before it's answered with `204 No Content`.
+    /// Defaults to 30 seconds.
+    pub fn poll_timeout(mut self, timeout: Duration) -> LiveReload {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Spawns a task which scans the watched directory every interval, and notifies clients when
+    /// it changes.
+    ///
+    /// The task runs until it's aborted. Directories and files which can't be read are skipped
+    /// over.
+    pub fn watch(&self) -> JoinHandle<()> {
+        let reload = self.clone();
+
+        tokio::spawn(async move {
+            let mut last = fingerprint(&reload.dir).await;
+            let mut interval = tokio::time::interval(reload.interval);
+
+            loop {
+                interval.tick().await;
+
+                let current = fingerprint(&reload.dir).await;
+                if current != last {
+                    last = current;
+                    reload.notify();
+                }
+            }
+        })
+    }
+
+    /// Notifies every polling client that they should reload.
+    pub fn notify(&self) {
+        self.events.publish(TOPIC, ());
+    }
+
+    /// Gets the client snippet, a `<script>` tag which reloads the page once notified.
+    pub fn snippet(&self) -> String {
+        format!(
+            concat!(
+                "<script>(function(){{",
+                "var lost=false;",
+                "function poll(){{fetch({endpoint:?},{{cache:\"no-store\"}})",
+                ".then(function(r){{if(lost||r.status===200){{location.reload();}}else{{poll();}}}})",
+                ".catch(function(){{lost=true;setTimeout(poll,1000);}});}}",
+                "poll();",
+                "}})();</script>",
+            ),
+            endpoint = self.endpoint,
+        )
+    }
+
+    /// Creates an `HtmlTransformSeeder` which injects the client snippet before `</body>`.
+    pub fn html_transform(&self) -> HtmlTransformSeeder {
+        HtmlTransformSeeder::new().inject(Position::BeforeClose("body".to_owned()), self.snippet().into_bytes())
+    }
+
+    /// Answers a request to the endpoint, once the watched directory changes or the poll times
+    /// out. Returns `None` for any other request.
+    pub async fn respond(&self, request: &HttpRequest<BoxBody>) -> Option<HttpResponse<BoxBody>> {
+        if request.method() != Method::GET || request.uri().path() != self.endpoint {
+            return None;
+        }
+
+        let response = self.events
+            .respond(TOPIC, None, self.timeout, |_| BoxBody::empty())
+            .await;
+
+        Some(response)
+    }
+}
+
+impl Seeder for LiveReload {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        let Some(response) = self.respond(request).await else {
+            return Guard::Accessible(request);
+        };
+
+        let status = response.status();
+        Guard::Inaccessible {
+            request,
+            respondent: Respondent::Respond(Box::new(response)),
+            rejection: Rejection::new(status, "live_reload", "Answered a live-reload poll."),
+        }
+    }
+}
+
+/// Computes a fingerprint of every file beneath a directory, from their paths, sizes, and
+/// modification times.
+///
+/// Entries which can't be read, such as files removed during the scan, are skipped, so that they
+/// don't fail the whole scan. The rest of a directory whose listing fails is skipped too.
+async fn fingerprint(dir: &Path) -> u64 {
+    let mut pending = vec![dir.to_path_buf()];
+    let mut fingerprint = 0u64;
+
+    while let Some(dir) = pending.pop() {
+        let Ok(mut entries) = tokio::fs::read_dir(&dir).await else { continue; };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let Ok(metadata) = entry.metadata().await else { continue; };
+
+            if metadata.is_dir() {
+                pending.push(entry.path());
+                continue;
+            }
+
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map(|modified| modified.as_nanos())
+                .unwrap_or_default();
+
+            let mut bytes = entry.path().to_string_lossy().into_owned().into_bytes();
+            bytes.extend_from_slice(&metadata.len().to_le_bytes());
+            bytes.extend_from_slice(&modified.to_le_bytes());
+
+            // Combine order-independently, since directory listings aren't ordered.
+            fingerprint = fingerprint.wrapping_add(fnv1a(&bytes));
+        }
+    }
+
+    fingerprint
+}
@@ -0,0 +1,69 @@
+//! SHA-256 digests of bodies, for integrity headers and content-addressed storage.
+//!
+//! A body's digest is computed once, by `BoxBody::sha256`, and kept with the body, so seeders
+//! which check it, handlers which deduplicate by it, and headers which carry it all share one
+//! hash of the bytes:
+//!
+//! ```
+//! use grazie::core::seeder::BoxBody;
+//! use grazie::digest::{content_address, repr_digest};
+//!
+//! let body = BoxBody::new(b"hello".to_vec().into_boxed_slice());
+//!
+//! assert_eq!(repr_digest(&body), "sha-256=:LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=:");
+//! assert_eq!(content_address(&body), "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824");
+//! ```
+//!
+//! Part of the `digest` feature.
+
+use crate::core::seeder::BoxBody;
+use crate::http::header::{HeaderName, HeaderValue};
+use crate::http::{HttpResponse, StatusCode};
+use crate::util::base64_encode;
+
+/// The RFC 9530 header carrying a digest of the selected representation.
+pub const REPR_DIGEST: HeaderName = HeaderName::from_static("repr-digest");
+
+/// The RFC 9530 header carrying a digest of the message content.
+pub const CONTENT_DIGEST: HeaderName = HeaderName::from_static("content-digest");
+
+/// The RFC 3230 header carrying a digest of the body, which RFC 9530 replaces.
+pub const DIGEST: HeaderName = HeaderName::from_static("digest");
+
+/// Formats a body's digest as an RFC 9530 `Repr-Digest` or `Content-Digest` header value.
+pub fn repr_digest(body: &BoxBody) -> HeaderValue {
+    HeaderValue::try_from(format!("sha-256=:{}:", base64_encode(&body.sha256()))).unwrap()
+}
+
+/// Formats a body's digest as an RFC 3230 `Digest` header value.
+pub fn legacy_digest(body: &BoxBody) -> HeaderValue {
+    HeaderValue::try_from(format!("sha-256={}", base64_encode(&body.sha256()))).unwrap()
+}
+
+/// Formats a body's digest as lowercase hex, for use as a key in content-addressed storage.
+pub fn content_address(body: &BoxBody) -> String {
+    body.sha256().iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Adds `Repr-Digest` and `Content-Digest` headers to a response, and a `Digest` header if
+/// `legacy` is set, for clients which predate RFC 9530. Headers the response already has are
+/// kept.
+///
+/// Partial content responses only get a `Content-Digest`, since their body isn't the whole
+/// representation.
+pub fn add_digest_headers(response: &mut HttpResponse<BoxBody>, legacy: bool) {
+    let value = repr_digest(response.body());
+    let partial = response.status() == StatusCode::PARTIAL_CONTENT;
+    let legacy = legacy.then(|| legacy_digest(response.body()));
+    let headers = response.headers_mut();
+
+    if !partial && !headers.contains_key(REPR_DIGEST) {
+        headers.insert(REPR_DIGEST, value.clone());
+    }
+    if !headers.contains_key(CONTENT_DIGEST) {
+        headers.insert(CONTENT_DIGEST, value);
+    }
+    if let Some(legacy) = legacy.filter(|_| !partial && !headers.contains_key(DIGEST)) {
+        headers.insert(DIGEST, legacy);
+    }
+}
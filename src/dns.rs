@@ -0,0 +1,232 @@
+//! DNS resolution for outbound connections.
+//!
+//! Outbound connections resolve hosts through a `Resolver`. The default `CachingResolver` keeps
+//! resolved addresses around for their TTL, so that repeated connections to the same host don't
+//! re-resolve on every request. `connect` then races the resolved addresses of both IP families
+//! against each other ("happy eyeballs", RFC 8305), so a broken IPv6 route doesn't stall a
+//! connection when IPv4 works.
+
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::task::JoinSet;
+use tokio::time::sleep;
+
+/// The addresses a host resolved to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolved {
+    /// The resolved socket addresses.
+    pub addrs: Vec<SocketAddr>,
+
+    /// How long the addresses may be cached for, if the resolver knows the records' TTL.
+    pub ttl: Option<Duration>,
+}
+
+/// Resolves host names into socket addresses.
+pub trait Resolver: Send + Sync {
+    /// Resolves a host and port into a set of socket addresses.
+    fn resolve(&self, host: &str, port: u16) -> impl Future<Output = io::Result<Resolved>> + Send;
+}
+
+/// A `Resolver` backed by the operating system's resolver.
+///
+/// The system resolver doesn't expose record TTLs, so its results never carry one.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    async fn resolve(&self, host: &str, port: u16) -> io::Result<Resolved> {
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await?.collect();
+
+        if addrs.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("`{host}` did not resolve to any address")));
+        }
+
+        Ok(Resolved { addrs, ttl: None })
+    }
+}
+
+/// A cached resolution.
+struct CacheEntry {
+    result: Result<Vec<SocketAddr>, io::ErrorKind>,
+    expires: Instant,
+}
+
+/// A `Resolver` which caches the results of another resolver.
+///
+/// Results are cached for their record TTL when the inner resolver provides one, otherwise for
+/// the default TTL, and never for longer than the maximum TTL. Failed resolutions are cached for
+/// the (much shorter) negative TTL, so that an unresolvable host isn't hammered.
+pub struct CachingResolver<R = SystemResolver> {
+    inner: R,
+    default_ttl: Duration,
+    max_ttl: Duration,
+    negative_ttl: Duration,
+    cache: Mutex<HashMap<(String, u16), CacheEntry>>,
+}
+
+impl CachingResolver<SystemResolver> {
+    /// Constructs a new `CachingResolver` over the system resolver.
+    pub fn new() -> CachingResolver<SystemResolver> {
+        CachingResolver::wrap(SystemResolver)
+    }
+}
+
+impl Default for CachingResolver<SystemResolver> {
+    fn default() -> CachingResolver<SystemResolver> {
+        CachingResolver::new()
+    }
+}
+
+impl<R: Resolver> CachingResolver<R> {
+    /// Constructs a new `CachingResolver` over the provided resolver.
+    ///
+    /// By default, results without a TTL are cached for 60 seconds, no result is cached for longer
+    /// than 5 minutes, and failures are cached for 5 seconds.
+    pub fn wrap(inner: R) -> CachingResolver<R> {
+        CachingResolver {
+            inner,
+            default_ttl: Duration::from_secs(60),
+            max_ttl: Duration::from_secs(300),
+            negative_ttl: Duration::from_secs(5),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sets how long results without a TTL are cached for.
+    pub fn default_ttl(mut self, ttl: Duration) -> CachingResolver<R> {
+        self.default_ttl = ttl;
+        self
+    }
+
+    /// Sets the longest any result is cached for.
+    pub fn max_ttl(mut self, ttl: Duration) -> CachingResolver<R> {
+        self.max_ttl = ttl;
+        self
+    }
+
+    /// Sets how long failed resolutions are cached for.
+    pub fn negative_ttl(mut self, ttl: Duration) -> CachingResolver<R> {
+        self.negative_ttl = ttl;
+        self
+    }
+
+    /// Gets the resolver this `CachingResolver` wraps.
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    /// Removes every cached result.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<(String, u16), CacheEntry>> {
+        self.cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<R: Resolver> Resolver for CachingResolver<R> {
+    async fn resolve(&self, host: &str, port: u16) -> io::Result<Resolved> {
+        let key = (host.to_owned(), port);
+        let now = Instant::now();
+
+        if let Some(entry) = self.lock().get(&key).filter(|entry| entry.expires > now) {
+            let ttl = Some(entry.expires - now);
+
+            return match &entry.result {
+                Ok(addrs) => { Ok(Resolved { addrs: addrs.clone(), ttl }) }
+                Err(kind) => { Err(io::Error::new(*kind, format!("`{host}` failed to resolve (cached)"))) }
+            };
+        }
+
+        let result = self.inner.resolve(host, port).await;
+
+        let (cached, ttl) = match &result {
+            Ok(resolved) => {
+                let ttl = resolved.ttl.unwrap_or(self.default_ttl).min(self.max_ttl);
+                (Ok(resolved.addrs.clone()), ttl)
+            }
+            Err(error) => { (Err(error.kind()), self.negative_ttl) }
+        };
+
+        self.lock().insert(key, CacheEntry {
+            result: cached,
+            expires: Instant::now() + ttl,
+        });
+
+        result
+    }
+}
+
+/// The delay between connection attempts recommended by RFC 8305.
+pub const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Connects to the first reachable of the provided addresses, racing attempts "happy eyeballs"
+/// style.
+///
+/// Addresses are interleaved by family (starting with the family of the first address), and a new
+/// attempt is started whenever the previous attempt fails, or after `attempt_delay` has passed
+/// without it connecting. The first attempt to connect wins, and the rest are cancelled.
+pub async fn connect(addrs: &[SocketAddr], attempt_delay: Duration) -> io::Result<TcpStream> {
+    let mut pending = interleave(addrs).into_iter();
+    let mut attempts = JoinSet::new();
+    let mut last_error = None;
+
+    loop {
+        if let Some(addr) = pending.next() {
+            attempts.spawn(TcpStream::connect(addr));
+        } else if attempts.is_empty() {
+            return Err(last_error.unwrap_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "no addresses to connect to")
+            }));
+        }
+
+        let result = tokio::select! {
+            result = attempts.join_next(), if !attempts.is_empty() => { result }
+            _ = sleep(attempt_delay), if pending.len() > 0 => { continue; }
+        };
+
+        match result {
+            Some(Ok(Ok(stream))) => {
+                attempts.abort_all();
+                return Ok(stream);
+            }
+            Some(Ok(Err(error))) => { last_error = Some(error); }
+            Some(Err(join_error)) => { last_error = Some(io::Error::other(join_error)); }
+            None => {}
+        }
+    }
+}
+
+/// Resolves a host through the provided resolver, and connects to it with `connect`.
+pub async fn resolve_and_connect<R: Resolver>(resolver: &R, host: &str, port: u16) -> io::Result<TcpStream> {
+    let resolved = resolver.resolve(host, port).await?;
+
+    connect(&resolved.addrs, CONNECTION_ATTEMPT_DELAY).await
+}
+
+/// Interleaves addresses by family, starting with the family of the first address.
+fn interleave(addrs: &[SocketAddr]) -> Vec<SocketAddr> {
+    let Some(first) = addrs.first() else { return Vec::new(); };
+
+    let (mut preferred, mut other): (Vec<SocketAddr>, Vec<SocketAddr>) = addrs
+        .iter()
+        .partition(|addr| addr.is_ipv6() == first.is_ipv6());
+    preferred.reverse();
+    other.reverse();
+
+    let mut interleaved = Vec::with_capacity(addrs.len());
+    while let Some(addr) = preferred.pop() {
+        interleaved.push(addr);
+        interleaved.extend(other.pop());
+    }
+    while let Some(addr) = other.pop() {
+        interleaved.push(addr);
+    }
+
+    interleaved
+}
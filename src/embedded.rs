@@ -0,0 +1,194 @@
+//! Serving of assets compiled into the binary.
+//!
+//! `EmbeddedFiles` serves files from memory (generally included with `include_bytes!`), for
+//! single-binary deployments which don't ship a filesystem alongside the executable. The
+//! `embed_files!` macro builds an `EmbeddedFiles` from a list of files relative to the calling
+//! source file.
+//!
+//! ```ignore
+//! let assets = grazie::embed_files!("/assets", {
+//!     "favicon.ico" => "../assets/favicon.ico",
+//!     "app.js" => "../assets/app.js",
+//! });
+//! ```
+
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::{HeaderValue, CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_NONE_MATCH};
+use crate::http::{HttpRequest, HttpResponse, Method, StatusCode};
+use crate::util::fnv1a;
+use std::collections::HashMap;
+
+/// Builds an `EmbeddedFiles` from a mount path and a list of `"served/path" => "included/path"`
+/// pairs. The included paths are resolved by `include_bytes!`, relative to the calling file.
+#[macro_export]
+macro_rules! embed_files {
+    ($mount:expr, { $($path:literal => $file:literal),* $(,)? }) => {
+        $crate::embedded::EmbeddedFiles::new($mount)
+            $(.file($path, include_bytes!($file)))*
+    };
+}
+
+/// A single embedded file.
+struct EmbeddedFile {
+    bytes: &'static [u8],
+    content_type: HeaderValue,
+    etag: HeaderValue,
+}
+
+/// A `Seeder` which serves files embedded in the binary under a mount path.
+///
+/// Files are served for `GET` and `HEAD` requests, with their `Content-Type` guessed from their
+/// extension, and a strong `ETag` computed from their contents. Requests with a matching
+/// `If-None-Match` header are answered with `304 Not Modified`. Requests for any path which isn't
+/// an embedded file pass straight through.
+pub struct EmbeddedFiles {
+    mount: String,
+    files: HashMap<String, EmbeddedFile>,
+    index: Option<String>,
+}
+
+impl EmbeddedFiles {
+    /// Constructs a new `EmbeddedFiles`, serving files under the provided mount path (such as
+    /// `/assets`, or `/` to serve from the root).
+    pub fn new(mount: &str) -> EmbeddedFiles {
+        EmbeddedFiles {
+            mount: mount.trim_end_matches('/').to_owned(),
+            files: HashMap::new(),
+            index: None,
+        }
+    }
+
+    /// Adds a file, served at `<mount>/<path>`.
+    pub fn file(self, path: &str, bytes: &'static [u8]) -> EmbeddedFiles {
+        let content_type = content_type(path);
+        self.file_with_type(path, bytes, content_type)
+    }
+
+    /// Adds a file with an explicit content type, served at `<mount>/<path>`.
+    pub fn file_with_type(mut self, path: &str, bytes: &'static [u8], content_type: &'static str) -> EmbeddedFiles {
+        let etag = format!("\"{:016x}\"", fnv1a(bytes));
+
+        self.files.insert(path.trim_start_matches('/').to_owned(), EmbeddedFile {
+            bytes,
+            content_type: HeaderValue::from_static(content_type),
+            etag: HeaderValue::try_from(etag).unwrap(),
+        });
+        self
+    }
+
+    /// Sets the file served for requests to a directory (a path ending in `/`), such as
+    /// `index.html`.
+    pub fn index(mut self, index: impl Into<String>) -> EmbeddedFiles {
+        self.index = Some(index.into());
+        self
+    }
+
+    /// Builds the response for a request, if it is for one of the embedded files.
+    pub fn respond(&self, request: &HttpRequest<BoxBody>) -> Option<HttpResponse<BoxBody>> {
+        if request.method() != Method::GET && request.method() != Method::HEAD {
+            return None;
+        }
+
+        let path = request.uri().path().strip_prefix(self.mount.as_str())?;
+        if !path.is_empty() && !path.starts_with('/') && !self.mount.is_empty() {
+            return None;
+        }
+
+        let mut path = path.trim_start_matches('/').to_owned();
+        if path.is_empty() || path.ends_with('/') {
+            path.push_str(self.index.as_deref()?);
+        }
+
+        let file = self.files.get(&path)?;
+
+        let not_modified = request
+            .headers()
+            .get_all(IF_NONE_MATCH)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|tag| {
+                let tag = tag.trim();
+                tag == "*" || tag.trim_start_matches("W/") == file.etag
+            });
+
+        if not_modified {
+            return Some(HttpResponse::builder()
+                .status(StatusCode::NOT_MODIFIED)
+                .header(ETAG, file.etag.clone())
+                .body(BoxBody::empty())
+                .unwrap());
+        }
+
+        let body = match request.method() == Method::HEAD {
+            true => { BoxBody::empty() }
+            false => { BoxBody::new(file.bytes.into()) }
+        };
+
+        Some(HttpResponse::builder()
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, file.content_type.clone())
+            .header(CONTENT_LENGTH, file.bytes.len())
+            .header(ETAG, file.etag.clone())
+            .body(body)
+            .unwrap())
+    }
+}
+
+impl Seeder for EmbeddedFiles {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        match self.respond(request) {
+            Some(response) => {
+                let status = response.status();
+
+                Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Respond(Box::new(response)),
+                    rejection: Rejection::new(status, "embedded_file", "Served an embedded file."),
+                }
+            }
+            None => { Guard::Accessible(request) }
+        }
+    }
+}
+
+/// Guesses the content type of a file from its extension, falling back to
+/// `application/octet-stream`.
+pub fn content_type(path: &str) -> &'static str {
+    let extension = path.rsplit_once('.').map(|(_, extension)| extension).unwrap_or_default();
+
+    match extension.to_ascii_lowercase().as_str() {
+        "html" | "htm" => { "text/html; charset=utf-8" }
+        "css" => { "text/css; charset=utf-8" }
+        "js" | "mjs" => { "text/javascript; charset=utf-8" }
+        "json" | "map" => { "application/json" }
+        "txt" => { "text/plain; charset=utf-8" }
+        "xml" => { "application/xml" }
+        "svg" => { "image/svg+xml" }
+        "png" => { "image/png" }
+        "jpg" | "jpeg" => { "image/jpeg" }
+        "gif" => { "image/gif" }
+        "webp" => { "image/webp" }
+        "avif" => { "image/avif" }
+        "ico" => { "image/x-icon" }
+        "woff" => { "font/woff" }
+        "woff2" => { "font/woff2" }
+        "ttf" => { "font/ttf" }
+        "otf" => { "font/otf" }
+        "wasm" => { "application/wasm" }
+        "pdf" => { "application/pdf" }
+        "webmanifest" => { "application/manifest+json" }
+        "mp4" => { "video/mp4" }
+        "webm" => { "video/webm" }
+        "mp3" => { "audio/mpeg" }
+        _ => { "application/octet-stream" }
+    }
+}
@@ -0,0 +1,373 @@
+//! Publishing of domain events to message brokers.
+//!
+//! Handlers publish `Event`s through a `Publisher`, which adapts whichever broker the application
+//! uses. `EventSeeder` makes the publisher available to every request as an `Events` extension,
+//! and can publish events of its own as requests arrive and complete:
+//!
+//! ```
+//! use grazie::events::{EventSeeder, MemoryPublisher};
+//!
+//! let publisher = MemoryPublisher::new();
+//! let seeder = EventSeeder::new(publisher.clone()).lifecycle("http");
+//! ```
+//!
+//! Besides the in-process publishers, there are adapters for NATS (the `nats` feature), AMQP
+//! brokers such as RabbitMQ (the `amqp` feature), and Kafka (the `kafka` feature).
+
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::{HttpRequest, HttpResponse};
+#[cfg(feature = "kafka")]
+use rskafka::client::partition::PartitionClient;
+#[cfg(feature = "kafka")]
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+use tokio::sync::mpsc;
+
+/// A message to be published.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    /// The subject (or topic, or routing key) the event is published to, such as `orders.created`.
+    pub subject: String,
+
+    /// Metadata about the event, sent as message headers by brokers which support them.
+    pub headers: Vec<(String, String)>,
+
+    /// The event's body.
+    pub payload: Vec<u8>,
+}
+
+impl Event {
+    /// Constructs a new `Event`, without any headers.
+    pub fn new(subject: impl Into<String>, payload: impl Into<Vec<u8>>) -> Event {
+        Event {
+            subject: subject.into(),
+            headers: Vec::new(),
+            payload: payload.into(),
+        }
+    }
+
+    /// Constructs a new `Event` with a JSON payload.
+    ///
+    /// Part of the `serde_json` feature.
+    #[cfg(feature = "serde_json")]
+    pub fn json<T: serde::Serialize>(
+        subject: impl Into<String>,
+        payload: &T,
+    ) -> Result<Event, serde_json::Error> {
+        Ok(Event::new(subject, serde_json::to_vec(payload)?).header("content-type", "application/json"))
+    }
+
+    /// Adds a header.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Event {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+/// Publishes events to a message broker.
+pub trait Publisher: Send + Sync + 'static {
+    /// The error publishing fails with.
+    type Error: Display + Send;
+
+    /// Publishes an event, completing once the broker has accepted it.
+    fn publish(&self, event: Event) -> impl Future<Output = Result<(), Self::Error>> + Send;
+}
+
+/// A `Publisher` which sends events on a channel, for consuming them in-process.
+#[derive(Debug, Clone)]
+pub struct ChannelPublisher(pub mpsc::Sender<Event>);
+
+impl Publisher for ChannelPublisher {
+    type Error = mpsc::error::SendError<Event>;
+
+    async fn publish(&self, event: Event) -> Result<(), mpsc::error::SendError<Event>> {
+        self.0.send(event).await
+    }
+}
+
+/// A `Publisher` which keeps every event in memory, for tests.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryPublisher {
+    events: Arc<Mutex<Vec<Event>>>,
+}
+
+impl MemoryPublisher {
+    /// Constructs a new, empty `MemoryPublisher`.
+    pub fn new() -> MemoryPublisher {
+        MemoryPublisher::default()
+    }
+
+    /// Gets every event published so far, in order.
+    pub fn events(&self) -> Vec<Event> {
+        self.lock().clone()
+    }
+
+    /// Takes every event published so far, leaving none behind.
+    pub fn take(&self) -> Vec<Event> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Event>> {
+        self.events.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Publisher for MemoryPublisher {
+    type Error = std::convert::Infallible;
+
+    async fn publish(&self, event: Event) -> Result<(), std::convert::Infallible> {
+        self.lock().push(event);
+        Ok(())
+    }
+}
+
+/// A `Publisher` which publishes events to a NATS server, with the event's subject.
+///
+/// Core NATS doesn't acknowledge messages, so publishing completes once the event has been
+/// flushed to the server.
+///
+/// Part of the `nats` feature.
+#[cfg(feature = "nats")]
+#[derive(Debug, Clone)]
+pub struct NatsPublisher(pub async_nats::Client);
+
+#[cfg(feature = "nats")]
+impl Publisher for NatsPublisher {
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn publish(&self, event: Event) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut headers = async_nats::HeaderMap::new();
+        for (name, value) in &event.headers {
+            headers.append(name.as_str(), value.as_str());
+        }
+
+        self.0.publish_with_headers(event.subject, headers, event.payload.into()).await?;
+        self.0.flush().await?;
+        Ok(())
+    }
+}
+
+/// A `Publisher` which publishes events to an exchange on an AMQP broker, such as RabbitMQ, with
+/// the event's subject as the routing key.
+///
+/// If the channel is in confirm mode, publishing waits for the broker to confirm the event, and
+/// fails if the broker refuses it.
+///
+/// Part of the `amqp` feature.
+#[cfg(feature = "amqp")]
+#[derive(Debug, Clone)]
+pub struct AmqpPublisher {
+    channel: lapin::Channel,
+    exchange: String,
+}
+
+#[cfg(feature = "amqp")]
+impl AmqpPublisher {
+    /// Constructs a new `AmqpPublisher`, publishing to an exchange over a channel.
+    pub fn new(channel: lapin::Channel, exchange: impl Into<String>) -> AmqpPublisher {
+        AmqpPublisher {
+            channel,
+            exchange: exchange.into(),
+        }
+    }
+}
+
+#[cfg(feature = "amqp")]
+impl Publisher for AmqpPublisher {
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn publish(&self, event: Event) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        use lapin::types::{AMQPValue, FieldTable};
+
+        let mut headers = FieldTable::default();
+        for (name, value) in event.headers {
+            headers.insert(name.into(), AMQPValue::LongString(value.into()));
+        }
+
+        let properties = lapin::BasicProperties::default().with_headers(headers);
+        let options = lapin::options::BasicPublishOptions::default();
+        let confirm = self.channel
+            .basic_publish(&self.exchange, &event.subject, options, &event.payload, properties)
+            .await?;
+
+        match confirm.await? {
+            lapin::publisher_confirm::Confirmation::Nack(_) => { Err("the broker refused the event".into()) }
+            _ => { Ok(()) }
+        }
+    }
+}
+
+/// A `Publisher` which produces events to Kafka, with the event's subject as the topic.
+///
+/// Events are produced to one partition of each topic, the first by default, so that they keep
+/// their order. Kafka doesn't allow repeated headers, so only the last of an event's headers with
+/// a name is sent.
+///
+/// Part of the `kafka` feature.
+#[cfg(feature = "kafka")]
+pub struct KafkaPublisher {
+    client: rskafka::client::Client,
+    partition: i32,
+    partitions: tokio::sync::Mutex<HashMap<String, Arc<PartitionClient>>>,
+}
+
+#[cfg(feature = "kafka")]
+impl KafkaPublisher {
+    /// Constructs a new `KafkaPublisher` over a client.
+    pub fn new(client: rskafka::client::Client) -> KafkaPublisher {
+        KafkaPublisher {
+            client,
+            partition: 0,
+            partitions: tokio::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sets the partition events are produced to.
+    pub fn partition(mut self, partition: i32) -> KafkaPublisher {
+        self.partition = partition;
+        self
+    }
+}
+
+#[cfg(feature = "kafka")]
+impl Publisher for KafkaPublisher {
+    type Error = rskafka::client::error::Error;
+
+    async fn publish(&self, event: Event) -> Result<(), rskafka::client::error::Error> {
+        use rskafka::client::partition::{Compression, UnknownTopicHandling};
+
+        let partition = {
+            let mut partitions = self.partitions.lock().await;
+            match partitions.get(&event.subject) {
+                Some(partition) => { partition.clone() }
+                None => {
+                    let partition = self.client
+                        .partition_client(event.subject.as_str(), self.partition, UnknownTopicHandling::Error)
+                        .await?;
+
+                    let partition = Arc::new(partition);
+                    partitions.insert(event.subject.clone(), partition.clone());
+                    partition
+                }
+            }
+        };
+
+        let now = std::time::UNIX_EPOCH.elapsed().unwrap_or_default().as_millis() as i64;
+        let record = rskafka::record::Record {
+            key: None,
+            value: Some(event.payload),
+            headers: event.headers.into_iter().map(|(name, value)| (name, value.into_bytes())).collect(),
+            timestamp: rskafka::chrono::DateTime::from_timestamp_millis(now).unwrap_or_default(),
+        };
+
+        partition.produce(vec![record], Compression::NoCompression).await?;
+        Ok(())
+    }
+}
+
+/// A handle to a publisher, as an extension of each request passing through an `EventSeeder`.
+pub struct Events<P> {
+    publisher: Arc<P>,
+}
+
+impl<P> Clone for Events<P> {
+    fn clone(&self) -> Events<P> {
+        Events {
+            publisher: self.publisher.clone(),
+        }
+    }
+}
+
+impl<P: Publisher> Events<P> {
+    /// Publishes an event.
+    pub async fn publish(&self, event: Event) -> Result<(), P::Error> {
+        self.publisher.publish(event).await
+    }
+
+    /// Gets the publisher.
+    pub fn publisher(&self) -> &P {
+        &self.publisher
+    }
+}
+
+/// When a request passed through an `EventSeeder`, as an extension of the request.
+#[derive(Debug, Clone, Copy)]
+struct Received(Instant);
+
+/// A `Seeder` which inserts an `Events` handle into each request's extensions, so handlers can
+/// publish domain events without building their own broker clients.
+///
+/// With `EventSeeder::lifecycle`, it also publishes a `<prefix>.received` event for each request,
+/// and (since there's no response stage to the request chain) a `<prefix>.completed` event for
+/// each request passed to `EventSeeder::finish` with its response. Lifecycle events have empty
+/// payloads, and describe the request in `method`, `path`, `status` and `elapsed_ms` headers.
+/// Failures to publish lifecycle events never fail the request.
+pub struct EventSeeder<P> {
+    events: Events<P>,
+    lifecycle: Option<String>,
+}
+
+impl<P: Publisher> EventSeeder<P> {
+    /// Constructs a new `EventSeeder` over a publisher, without lifecycle events.
+    pub fn new(publisher: P) -> EventSeeder<P> {
+        EventSeeder {
+            events: Events {
+                publisher: Arc::new(publisher),
+            },
+            lifecycle: None,
+        }
+    }
+
+    /// Publishes lifecycle events, under subjects starting with the provided prefix.
+    pub fn lifecycle(mut self, prefix: impl Into<String>) -> EventSeeder<P> {
+        self.lifecycle = Some(prefix.into());
+        self
+    }
+
+    /// Gets the handle inserted into requests, for publishing from outside of requests.
+    pub fn events(&self) -> Events<P> {
+        self.events.clone()
+    }
+
+    /// Publishes the `<prefix>.completed` event for a request, if lifecycle events are enabled.
+    pub async fn finish(&self, request: &HttpRequest<BoxBody>, response: &HttpResponse<BoxBody>) {
+        let Some(prefix) = &self.lifecycle else { return; };
+
+        let mut event = Event::new(format!("{prefix}.completed"), Vec::new())
+            .header("method", request.method().as_str())
+            .header("path", request.uri().path())
+            .header("status", response.status().as_str());
+        if let Some(Received(received)) = request.extensions().get::<Received>() {
+            event = event.header("elapsed_ms", received.elapsed().as_millis().to_string());
+        }
+
+        let _ = self.events.publish(event).await;
+    }
+}
+
+impl<P: Publisher> Seeder for EventSeeder<P> {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        request.extensions_mut().insert(self.events.clone());
+
+        if let Some(prefix) = &self.lifecycle {
+            request.extensions_mut().insert(Received(Instant::now()));
+
+            let event = Event::new(format!("{prefix}.received"), Vec::new())
+                .header("method", request.method().as_str())
+                .header("path", request.uri().path());
+            let _ = self.events.publish(event).await;
+        }
+
+        Guard::Accessible(request)
+    }
+}
@@ -0,0 +1,343 @@
+//! Runtime feature flags, with percentage rollouts.
+//!
+//! `FeatureFlags` holds each flag's rollout, from `0` (off for everyone) to `100` (on for
+//! everyone). Flags can be set at runtime, or loaded from a simple text format, with one
+//! `name = on`, `name = off`, or `name = 25%` pair per line, and `#` starting comments:
+//!
+//! ```
+//! use grazie::flags::FeatureFlags;
+//!
+//! let flags = FeatureFlags::new();
+//! flags.load("new_checkout = on\ndark_mode = 25%").unwrap();
+//!
+//! assert!(flags.is_enabled("new_checkout", "user-1"));
+//! assert!(!flags.is_enabled("legacy_reports", "user-1"));
+//! ```
+//!
+//! Partial rollouts bucket by a key, such as a user's ID, so that every evaluation for the same key
+//! agrees. A `FlagSeeder` evaluates every flag for each request, attaching the enabled `Flags`, and
+//! `RequireFlag` hides whatever it guards while its flag is disabled.
+
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::server::PeerAddr;
+use crate::util::fnv1a;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+
+/// An error from loading flags.
+#[derive(Debug)]
+pub enum FlagsError {
+    /// A flags file couldn't be read.
+    Io(PathBuf, std::io::Error),
+
+    /// A line isn't a `name = rollout` pair. Lines start from 1.
+    Parse(usize),
+}
+
+impl Display for FlagsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FlagsError::Io(path, error) => {
+                write!(f, "couldn't read {}: {error}", path.display())
+            }
+            FlagsError::Parse(line) => {
+                write!(f, "line {line} isn't a `name = on`, `off`, or `<percent>%` pair")
+            }
+        }
+    }
+}
+
+impl Error for FlagsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FlagsError::Io(_, error) => { Some(error) }
+            FlagsError::Parse(_) => { None }
+        }
+    }
+}
+
+/// A store of feature flags.
+///
+/// Stores are cheap to clone, and every clone shares the same flags, so a clone can be handed off
+/// to an admin endpoint while the original is used to evaluate requests.
+#[derive(Debug, Clone, Default)]
+pub struct FeatureFlags {
+    rollouts: Arc<RwLock<HashMap<String, u8>>>,
+}
+
+impl FeatureFlags {
+    /// Constructs a new `FeatureFlags` without any flags.
+    pub fn new() -> FeatureFlags {
+        FeatureFlags::default()
+    }
+
+    /// Loads the flags from a file, replacing every flag in the store.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<FeatureFlags, FlagsError> {
+        let flags = FeatureFlags::new();
+        flags.reload(path)?;
+        Ok(flags)
+    }
+
+    /// Sets a flag's rollout, as the percentage of keys it's enabled for. Rollouts above 100 are
+    /// clamped.
+    pub fn set(&self, name: impl Into<String>, percent: u8) {
+        self.write().insert(name.into(), percent.min(100));
+    }
+
+    /// Enables a flag for everyone.
+    pub fn enable(&self, name: impl Into<String>) {
+        self.set(name, 100);
+    }
+
+    /// Disables a flag for everyone.
+    pub fn disable(&self, name: impl Into<String>) {
+        self.set(name, 0);
+    }
+
+    /// Removes a flag, which then evaluates as disabled.
+    pub fn remove(&self, name: &str) {
+        self.write().remove(name);
+    }
+
+    /// Gets a flag's rollout, or `None` if there's no such flag.
+    pub fn rollout(&self, name: &str) -> Option<u8> {
+        self.read().get(name).copied()
+    }
+
+    /// Loads flags, replacing every flag in the store. The store is left untouched if any line is
+    /// malformed.
+    pub fn load(&self, source: &str) -> Result<(), FlagsError> {
+        let mut rollouts = HashMap::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (name, rollout) = line.split_once('=').ok_or(FlagsError::Parse(index + 1))?;
+            let percent = match rollout.trim() {
+                "on" | "true" => { 100 }
+                "off" | "false" => { 0 }
+                rollout => {
+                    rollout
+                        .strip_suffix('%')
+                        .and_then(|percent| percent.trim().parse::<u8>().ok())
+                        .filter(|percent| *percent <= 100)
+                        .ok_or(FlagsError::Parse(index + 1))?
+                }
+            };
+
+            match name.trim() {
+                "" => { return Err(FlagsError::Parse(index + 1)); }
+                name => { rollouts.insert(name.to_owned(), percent); }
+            }
+        }
+
+        *self.write() = rollouts;
+        Ok(())
+    }
+
+    /// Loads the flags from a file, replacing every flag in the store.
+    pub fn reload(&self, path: impl AsRef<Path>) -> Result<(), FlagsError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|error| FlagsError::Io(path.to_owned(), error))?;
+        self.load(&source)
+    }
+
+    /// Spawns a task which checks a flags file for changes every interval, and reloads the store
+    /// when it changes.
+    ///
+    /// The task runs until it's aborted. Files which can't be read or parsed are skipped over,
+    /// keeping the last flags which loaded.
+    pub fn watch(&self, path: impl Into<PathBuf>, interval: Duration) -> JoinHandle<()> {
+        let flags = self.clone();
+        let path = path.into();
+
+        tokio::spawn(async move {
+            let mut last: Option<SystemTime> = None;
+            let mut interval = tokio::time::interval(interval);
+
+            loop {
+                interval.tick().await;
+
+                let metadata = tokio::fs::metadata(&path).await;
+                let Ok(modified) = metadata.and_then(|metadata| metadata.modified()) else {
+                    continue;
+                };
+
+                if last != Some(modified) {
+                    if let Ok(source) = tokio::fs::read_to_string(&path).await {
+                        if flags.load(&source).is_ok() {
+                            last = Some(modified);
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    /// Checks whether a flag is enabled for a key.
+    ///
+    /// Keys are bucketed per flag, so that the same key doesn't land in every partial rollout at
+    /// once.
+    pub fn is_enabled(&self, name: &str, key: &str) -> bool {
+        self.rollout(name).is_some_and(|percent| enabled(name, percent, key))
+    }
+
+    /// Evaluates every flag for a key.
+    pub fn evaluate(&self, key: &str) -> Flags {
+        let enabled = self
+            .read()
+            .iter()
+            .filter(|(name, percent)| enabled(name, **percent, key))
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        Flags { enabled }
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, HashMap<String, u8>> {
+        self.rollouts.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, HashMap<String, u8>> {
+        self.rollouts.write().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Checks whether a key falls within a flag's rollout.
+fn enabled(name: &str, percent: u8, key: &str) -> bool {
+    match percent {
+        0 => { false }
+        100.. => { true }
+        percent => { fnv1a(format!("{name}:{key}").as_bytes()) % 100 < percent as u64 }
+    }
+}
+
+/// The flags enabled for a request, as an extension of the request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Flags {
+    enabled: HashSet<String>,
+}
+
+impl Flags {
+    /// Checks whether a flag is enabled.
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.enabled.contains(name)
+    }
+
+    /// Gets the names of every enabled flag.
+    pub fn enabled(&self) -> impl Iterator<Item = &str> {
+        self.enabled.iter().map(String::as_str)
+    }
+}
+
+/// Gets the key a request is bucketed by.
+type FlagKey = Box<dyn Fn(&HttpRequest<BoxBody>) -> Option<String> + Send + Sync>;
+
+/// A `Seeder` which evaluates every flag for each request, attaching the enabled `Flags`.
+///
+/// Requests are bucketed by their `PeerAddr`'s IP unless another key is provided, such as a user's
+/// ID. Requests without a key only have the flags which are on for everyone enabled.
+pub struct FlagSeeder {
+    flags: FeatureFlags,
+    key: FlagKey,
+}
+
+impl FlagSeeder {
+    /// Constructs a new `FlagSeeder`, evaluating the provided flags.
+    pub fn new(flags: FeatureFlags) -> FlagSeeder {
+        FlagSeeder {
+            flags,
+            key: Box::new(|request| {
+                request.extensions().get::<PeerAddr>().map(|peer| peer.0.ip().to_string())
+            }),
+        }
+    }
+
+    /// Sets how the key a request is bucketed by is found.
+    pub fn key<F>(mut self, key: F) -> FlagSeeder
+    where
+        F: Fn(&HttpRequest<BoxBody>) -> Option<String> + Send + Sync + 'static,
+    {
+        self.key = Box::new(key);
+        self
+    }
+}
+
+impl Seeder for FlagSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        let flags = match (self.key)(request) {
+            Some(key) => { self.flags.evaluate(&key) }
+            None => {
+                let enabled = self
+                    .flags
+                    .read()
+                    .iter()
+                    .filter(|(_, percent)| **percent >= 100)
+                    .map(|(name, _)| name.clone())
+                    .collect();
+
+                Flags { enabled }
+            }
+        };
+
+        request.extensions_mut().insert(flags);
+        Guard::Accessible(request)
+    }
+}
+
+/// A `Seeder` which answers requests with `404 Not Found` unless their `Flags` have a flag enabled,
+/// such as `RequireFlag("new_checkout")`.
+///
+/// Requests without `Flags` (because no `FlagSeeder` ran before this seeder) are treated as having
+/// every flag disabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequireFlag(pub &'static str);
+
+impl Seeder for RequireFlag {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        if request.extensions().get::<Flags>().is_some_and(|flags| flags.is_enabled(self.0)) {
+            return Guard::Accessible(request);
+        }
+
+        let response = HttpResponse::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(BoxBody::empty())
+            .unwrap();
+
+        Guard::Inaccessible {
+            request,
+            respondent: Respondent::Respond(Box::new(response)),
+            rejection: Rejection::new(
+                StatusCode::NOT_FOUND,
+                "flag_disabled",
+                "The feature isn't enabled.",
+            ),
+        }
+    }
+}
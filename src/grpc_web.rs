@@ -0,0 +1,386 @@
+//! gRPC-Web translation, for serving browser clients without a separate proxy.
+//!
+//! A `GrpcWebSeeder` decodes gRPC-Web requests, in both the binary (`application/grpc-web`) and
+//! base64 text (`application/grpc-web-text`) encodings, attaching the decoded `GrpcWebCall`. A
+//! `GrpcWeb` service then runs the call against a registered unary handler, and frames its reply
+//! (and its status, as a trailer frame) in the same encoding:
+//!
+//! ```
+//! use grazie::grpc_web::{Code, GrpcStatus, GrpcWeb};
+//!
+//! let service = GrpcWeb::new()
+//!     .unary("/echo.Echo/Say", |message: Vec<u8>| async move {
+//!         match message.is_empty() {
+//!             true => { Err(GrpcStatus::new(Code::InvalidArgument, "Nothing to say.")) }
+//!             false => { Ok(message) }
+//!         }
+//!     });
+//! ```
+//!
+//! Messages are passed to handlers as their raw, encoded bytes, so that any protobuf library can
+//! be used to decode them. Streaming calls and compressed messages aren't supported.
+
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::CONTENT_TYPE;
+use crate::http::{HttpRequest, HttpResponse, Method, StatusCode};
+use crate::util::{base64_decode, base64_encode};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::pin::Pin;
+
+/// The flag of a frame carrying a message.
+const DATA_FRAME: u8 = 0x00;
+
+/// The flag of a frame carrying trailers.
+const TRAILER_FRAME: u8 = 0x80;
+
+/// The flag set on frames carrying compressed messages.
+const COMPRESSED: u8 = 0x01;
+
+/// A gRPC status code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Code {
+    /// The call succeeded.
+    Ok = 0,
+
+    /// The call was cancelled, typically by the caller.
+    Cancelled = 1,
+
+    /// An unknown error.
+    Unknown = 2,
+
+    /// The client specified an invalid argument.
+    InvalidArgument = 3,
+
+    /// The deadline expired before the call could complete.
+    DeadlineExceeded = 4,
+
+    /// A requested entity wasn't found.
+    NotFound = 5,
+
+    /// An entity the client tried to create already exists.
+    AlreadyExists = 6,
+
+    /// The caller isn't permitted to make the call.
+    PermissionDenied = 7,
+
+    /// A resource, such as a quota, has been exhausted.
+    ResourceExhausted = 8,
+
+    /// The system isn't in the state the call requires.
+    FailedPrecondition = 9,
+
+    /// The call was aborted, typically by a concurrency conflict.
+    Aborted = 10,
+
+    /// The call was attempted past a valid range.
+    OutOfRange = 11,
+
+    /// The call isn't implemented or supported.
+    Unimplemented = 12,
+
+    /// An internal error.
+    Internal = 13,
+
+    /// The service is currently unavailable.
+    Unavailable = 14,
+
+    /// Unrecoverable data loss or corruption.
+    DataLoss = 15,
+
+    /// The call doesn't have valid credentials.
+    Unauthenticated = 16,
+}
+
+/// The status a call finished with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrpcStatus {
+    /// The status code.
+    pub code: Code,
+
+    /// A developer facing description of the status.
+    pub message: String,
+}
+
+impl GrpcStatus {
+    /// Constructs a new `GrpcStatus`.
+    pub fn new(code: Code, message: impl Into<String>) -> GrpcStatus {
+        GrpcStatus {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// Serializes this status as a trailer block.
+    fn trailers(&self) -> String {
+        let mut trailers = format!("grpc-status:{}\r\n", self.code as u8);
+
+        if !self.message.is_empty() {
+            trailers.push_str(&format!("grpc-message:{}\r\n", encode_message(&self.message)));
+        }
+
+        trailers
+    }
+}
+
+impl Display for GrpcStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "gRPC status {:?}: {}", self.code, self.message)
+    }
+}
+
+impl Error for GrpcStatus {}
+
+/// A decoded gRPC-Web call, as an extension of the request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrpcWebCall {
+    /// The path of the called method, such as `/echo.Echo/Say`.
+    pub method: String,
+
+    /// Whether the call used the base64 text encoding, which the reply must use too.
+    pub text: bool,
+
+    /// The call's request message, as its raw bytes.
+    pub message: Vec<u8>,
+}
+
+impl GrpcWebCall {
+    /// Decodes a gRPC-Web request, returning `None` if it isn't one, or an `Err` if it's malformed.
+    pub fn from_request(request: &HttpRequest<BoxBody>) -> Option<Result<GrpcWebCall, GrpcStatus>> {
+        if request.method() != Method::POST {
+            return None;
+        }
+
+        let content_type = request.headers().get(CONTENT_TYPE)?.to_str().ok()?;
+        let text = match content_type.split(';').next().unwrap_or_default().trim() {
+            "application/grpc-web-text" | "application/grpc-web-text+proto" => { true }
+            "application/grpc-web" | "application/grpc-web+proto" => { false }
+            _ => { return None; }
+        };
+
+        let body = match text {
+            true => {
+                match decode_text(request.body().raw_bytes()) {
+                    Some(body) => { Cow::Owned(body) }
+                    None => {
+                        let message = "Malformed base64 request.";
+                        return Some(Err(GrpcStatus::new(Code::InvalidArgument, message)));
+                    }
+                }
+            }
+            false => { Cow::Borrowed(request.body().raw_bytes()) }
+        };
+
+        Some(decode_frame(&body).map(|message| GrpcWebCall {
+            method: request.uri().path().to_owned(),
+            text,
+            message,
+        }))
+    }
+}
+
+/// A `Seeder` which decodes gRPC-Web requests, attaching their `GrpcWebCall`.
+///
+/// Malformed requests are answered with their status, and every other request passes through
+/// untouched.
+#[derive(Debug, Clone, Default)]
+pub struct GrpcWebSeeder;
+
+impl GrpcWebSeeder {
+    /// Constructs a new `GrpcWebSeeder`.
+    pub fn new() -> GrpcWebSeeder {
+        GrpcWebSeeder
+    }
+}
+
+impl Seeder for GrpcWebSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        match GrpcWebCall::from_request(request) {
+            Some(Ok(call)) => {
+                request.extensions_mut().insert(call);
+                Guard::Accessible(request)
+            }
+            Some(Err(status)) => {
+                let text = request
+                    .headers()
+                    .get(CONTENT_TYPE)
+                    .and_then(|content_type| content_type.to_str().ok())
+                    .is_some_and(|content_type| {
+                        content_type.starts_with("application/grpc-web-text")
+                    });
+
+                Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Respond(Box::new(reply(text, None, &status))),
+                    rejection: Rejection::new(
+                        StatusCode::BAD_REQUEST,
+                        "malformed_grpc_web",
+                        "The gRPC-Web request is malformed.",
+                    ),
+                }
+            }
+            None => { Guard::Accessible(request) }
+        }
+    }
+}
+
+/// The future a type-erased unary handler returns.
+type UnaryFuture = Pin<Box<dyn Future<Output = Result<Vec<u8>, GrpcStatus>> + Send>>;
+
+/// A type-erased unary handler.
+type Unary = Box<dyn Fn(Vec<u8>) -> UnaryFuture + Send + Sync>;
+
+/// A registry of unary gRPC methods, served over gRPC-Web.
+#[derive(Default)]
+pub struct GrpcWeb {
+    methods: HashMap<String, Unary>,
+}
+
+impl GrpcWeb {
+    /// Constructs a new `GrpcWeb` without any methods.
+    pub fn new() -> GrpcWeb {
+        GrpcWeb::default()
+    }
+
+    /// Registers a unary method under its path, such as `/echo.Echo/Say`.
+    pub fn unary<F, Fut>(mut self, method: impl Into<String>, handler: F) -> GrpcWeb
+    where
+        F: Fn(Vec<u8>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Vec<u8>, GrpcStatus>> + Send + 'static,
+    {
+        self.methods.insert(method.into(), Box::new(move |message| Box::pin(handler(message))));
+        self
+    }
+
+    /// Runs a call, returning its reply message.
+    pub async fn call(&self, call: GrpcWebCall) -> Result<Vec<u8>, GrpcStatus> {
+        match self.methods.get(&call.method) {
+            Some(handler) => { handler(call.message).await }
+            None => {
+                let message = format!("Unknown method `{}`.", call.method);
+                Err(GrpcStatus::new(Code::Unimplemented, message))
+            }
+        }
+    }
+
+    /// Handles a gRPC-Web request, using the `GrpcWebCall` attached by a `GrpcWebSeeder`, or
+    /// decoding the request if there isn't one.
+    ///
+    /// gRPC-Web replies always have a `200 OK` status, with the call's status in the trailers.
+    pub async fn respond(&self, request: &HttpRequest<BoxBody>) -> HttpResponse<BoxBody> {
+        let call = match request.extensions().get::<GrpcWebCall>() {
+            Some(call) => { Ok(call.clone()) }
+            None => {
+                GrpcWebCall::from_request(request).unwrap_or_else(|| {
+                    let message = "The request isn't a gRPC-Web request.";
+                    Err(GrpcStatus::new(Code::InvalidArgument, message))
+                })
+            }
+        };
+
+        let call = match call {
+            Ok(call) => { call }
+            Err(status) => { return reply(false, None, &status); }
+        };
+
+        let text = call.text;
+        match self.call(call).await {
+            Ok(message) => { reply(text, Some(&message), &GrpcStatus::new(Code::Ok, "")) }
+            Err(status) => { reply(text, None, &status) }
+        }
+    }
+}
+
+/// Frames a reply, with a message (if the call succeeded) and a trailer frame.
+fn reply(text: bool, message: Option<&[u8]>, status: &GrpcStatus) -> HttpResponse<BoxBody> {
+    let mut body = Vec::new();
+
+    if let Some(message) = message {
+        frame(&mut body, DATA_FRAME, message);
+    }
+
+    frame(&mut body, TRAILER_FRAME, status.trailers().as_bytes());
+
+    let (content_type, body) = match text {
+        true => { ("application/grpc-web-text+proto", base64_encode(&body).into_bytes()) }
+        false => { ("application/grpc-web+proto", body) }
+    };
+
+    let mut response = HttpResponse::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, content_type);
+
+    // Replies without a message are trailers-only, and carry their status in the headers too.
+    if message.is_none() {
+        response = response.header("grpc-status", (status.code as u8).to_string());
+    }
+
+    response.body(BoxBody::new(body.into())).unwrap()
+}
+
+/// Writes a length-prefixed frame.
+fn frame(body: &mut Vec<u8>, flags: u8, payload: &[u8]) {
+    body.push(flags);
+    body.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    body.extend_from_slice(payload);
+}
+
+/// Decodes the single message frame of a unary request.
+fn decode_frame(body: &[u8]) -> Result<Vec<u8>, GrpcStatus> {
+    let malformed = || GrpcStatus::new(Code::InvalidArgument, "Malformed request frame.");
+
+    let (header, rest) = body.split_at_checked(5).ok_or_else(malformed)?;
+    let length = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+
+    if header[0] & COMPRESSED != 0 {
+        return Err(GrpcStatus::new(Code::Unimplemented, "Compressed messages aren't supported."));
+    }
+
+    match header[0] == DATA_FRAME && rest.len() == length {
+        true => { Ok(rest.to_vec()) }
+        false => { Err(malformed()) }
+    }
+}
+
+/// Decodes a text encoded body, which may be several padded base64 chunks concatenated.
+fn decode_text(body: &[u8]) -> Option<Vec<u8>> {
+    let body = std::str::from_utf8(body).ok()?;
+    let mut decoded = Vec::new();
+    let mut rest = body.trim();
+
+    while !rest.is_empty() {
+        // Each chunk ends after its padding, if it has any.
+        let end = match rest.find('=') {
+            Some(padding) => {
+                padding + rest[padding..].bytes().take_while(|byte| *byte == b'=').count()
+            }
+            None => { rest.len() }
+        };
+
+        decoded.extend(base64_decode(&rest[..end])?);
+        rest = &rest[end..];
+    }
+
+    Some(decoded)
+}
+
+/// Percent-encodes a status message, as the `grpc-message` trailer requires.
+fn encode_message(message: &str) -> String {
+    message
+        .bytes()
+        .map(|byte| match byte {
+            b' '..=b'~' if byte != b'%' => { (byte as char).to_string() }
+            byte => { format!("%{byte:02X}") }
+        })
+        .collect()
+}
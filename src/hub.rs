@@ -0,0 +1,302 @@
+//! Utilities for fanning messages out across many long-lived client connections.
+//!
+//! A `Hub` manages a set of connected clients, the named rooms those clients have joined, and a
+//! bounded send queue for each client. The `Hub` is transport-agnostic: a connection task (such as
+//! a WebSocket writer) drains its client's `Connection`, and writes each message out to the socket.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+/// Uniquely identifies a client connected to a `Hub`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClientId(u64);
+
+/// Describes what a `Hub` should do when a client's send queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Drop the message for the slow client, keeping the client connected.
+    DropMessage,
+
+    /// Disconnect the slow client entirely.
+    Disconnect,
+}
+
+/// The result of sending a message to one or more clients.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Delivery {
+    /// The number of clients which the message was queued for.
+    pub delivered: usize,
+
+    /// The number of clients which the message could not be queued for, either because their
+    /// queue was full, or because they have disconnected.
+    pub dropped: usize,
+}
+
+/// The state of a single connected client.
+struct Client<M> {
+    sender: mpsc::Sender<M>,
+    rooms: HashSet<String>,
+}
+
+/// The shared state of a `Hub`.
+struct HubState<M> {
+    clients: HashMap<ClientId, Client<M>>,
+    rooms: HashMap<String, HashSet<ClientId>>,
+}
+
+impl<M> HubState<M> {
+    /// Removes a client, along with its membership in every room it joined.
+    fn remove(&mut self, id: ClientId) {
+        let Some(client) = self.clients.remove(&id) else { return; };
+
+        for room in client.rooms {
+            if let Some(members) = self.rooms.get_mut(&room) {
+                members.remove(&id);
+
+                if members.is_empty() {
+                    self.rooms.remove(&room);
+                }
+            }
+        }
+    }
+}
+
+/// Manages named rooms, broadcasts, and targeted sends across a set of client connections.
+///
+/// Every connected client owns a bounded queue of messages. Sends never wait on a slow client;
+/// instead, a full queue is handled according to the `Hub`'s `Overflow` policy. Clients which have
+/// dropped their `Connection` are removed from the `Hub` (and all of their rooms) automatically.
+///
+/// `Hub`s are cheap to clone, and all clones share the same set of clients and rooms.
+pub struct Hub<M> {
+    state: Arc<Mutex<HubState<M>>>,
+    next_id: Arc<AtomicU64>,
+    capacity: usize,
+    overflow: Overflow,
+}
+
+impl<M> Clone for Hub<M> {
+    fn clone(&self) -> Hub<M> {
+        Hub {
+            state: self.state.clone(),
+            next_id: self.next_id.clone(),
+            capacity: self.capacity,
+            overflow: self.overflow,
+        }
+    }
+}
+
+impl<M: Clone> Hub<M> {
+    /// Constructs a new `Hub`, where each client may have up to `capacity` messages queued.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Hub<M> {
+        assert!(capacity > 0, "A hub's queue capacity must be greater than zero!");
+
+        Hub {
+            state: Arc::new(Mutex::new(HubState {
+                clients: HashMap::new(),
+                rooms: HashMap::new(),
+            })),
+            next_id: Arc::new(AtomicU64::new(0)),
+            capacity,
+            overflow: Overflow::DropMessage,
+        }
+    }
+
+    /// Sets the policy used when a client's send queue is full.
+    pub fn overflow(mut self, overflow: Overflow) -> Hub<M> {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Connects a new client to this `Hub`.
+    ///
+    /// The returned `Connection` receives every message sent to this client. Dropping the
+    /// `Connection` disconnects the client.
+    pub fn connect(&self) -> Connection<M> {
+        let id = ClientId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let (sender, receiver) = mpsc::channel(self.capacity);
+
+        self.lock().clients.insert(id, Client {
+            sender,
+            rooms: HashSet::new(),
+        });
+
+        Connection {
+            id,
+            receiver,
+            state: self.state.clone(),
+        }
+    }
+
+    /// Disconnects a client, removing it from every room it has joined.
+    pub fn disconnect(&self, id: ClientId) {
+        self.lock().remove(id);
+    }
+
+    /// Adds a client to a room. Returns `false` if the client isn't connected.
+    pub fn join(&self, id: ClientId, room: &str) -> bool {
+        let mut state = self.lock();
+
+        let Some(client) = state.clients.get_mut(&id) else { return false; };
+        client.rooms.insert(room.to_owned());
+        state.rooms.entry(room.to_owned()).or_default().insert(id);
+
+        true
+    }
+
+    /// Removes a client from a room.
+    pub fn leave(&self, id: ClientId, room: &str) {
+        let mut state = self.lock();
+
+        if let Some(client) = state.clients.get_mut(&id) {
+            client.rooms.remove(room);
+        }
+
+        if let Some(members) = state.rooms.get_mut(room) {
+            members.remove(&id);
+
+            if members.is_empty() {
+                state.rooms.remove(room);
+            }
+        }
+    }
+
+    /// Queues a message for a single client.
+    pub fn send(&self, id: ClientId, message: M) -> Delivery {
+        let mut state = self.lock();
+        let mut delivery = Delivery::default();
+
+        self.deliver(&mut state, [id], message, &mut delivery);
+        delivery
+    }
+
+    /// Queues a message for every member of a room.
+    pub fn send_to(&self, room: &str, message: M) -> Delivery {
+        let mut state = self.lock();
+        let mut delivery = Delivery::default();
+
+        let members: Vec<ClientId> = match state.rooms.get(room) {
+            Some(members) => { members.iter().copied().collect() }
+            None => { return delivery; }
+        };
+
+        self.deliver(&mut state, members, message, &mut delivery);
+        delivery
+    }
+
+    /// Queues a message for every connected client.
+    pub fn broadcast(&self, message: M) -> Delivery {
+        let mut state = self.lock();
+        let mut delivery = Delivery::default();
+
+        let clients: Vec<ClientId> = state.clients.keys().copied().collect();
+
+        self.deliver(&mut state, clients, message, &mut delivery);
+        delivery
+    }
+
+    /// Gets the clients which are members of a room.
+    pub fn members(&self, room: &str) -> Vec<ClientId> {
+        self.lock()
+            .rooms
+            .get(room)
+            .map(|members| members.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Gets the names of all rooms with at least one member.
+    pub fn rooms(&self) -> Vec<String> {
+        self.lock().rooms.keys().cloned().collect()
+    }
+
+    /// Gets the number of connected clients.
+    pub fn len(&self) -> usize {
+        self.lock().clients.len()
+    }
+
+    /// Checks whether there are no connected clients.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Queues a message for each of the provided clients, cleaning up any client which has closed
+    /// its `Connection` (or overflowed, under `Overflow::Disconnect`).
+    fn deliver(
+        &self,
+        state: &mut HubState<M>,
+        ids: impl IntoIterator<Item = ClientId>,
+        message: M,
+        delivery: &mut Delivery,
+    ) {
+        let mut closed = Vec::new();
+
+        for id in ids {
+            let Some(client) = state.clients.get(&id) else {
+                delivery.dropped += 1;
+                continue;
+            };
+
+            match client.sender.try_send(message.clone()) {
+                Ok(()) => { delivery.delivered += 1; }
+                Err(TrySendError::Full(_)) => {
+                    delivery.dropped += 1;
+
+                    if self.overflow == Overflow::Disconnect {
+                        closed.push(id);
+                    }
+                }
+                Err(TrySendError::Closed(_)) => {
+                    delivery.dropped += 1;
+                    closed.push(id);
+                }
+            }
+        }
+
+        for id in closed {
+            state.remove(id);
+        }
+    }
+
+    /// Locks this `Hub`'s state. The lock is never held across an await point, so a poisoned lock
+    /// can only be caused by a panic within the `Hub` itself, which leaves the state consistent.
+    fn lock(&self) -> MutexGuard<'_, HubState<M>> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A client's connection to a `Hub`, receiving the messages queued for that client.
+///
+/// Dropping a `Connection` disconnects the client from the `Hub`.
+pub struct Connection<M> {
+    id: ClientId,
+    receiver: mpsc::Receiver<M>,
+    state: Arc<Mutex<HubState<M>>>,
+}
+
+impl<M> Connection<M> {
+    /// Gets the `ClientId` of this connection.
+    pub fn id(&self) -> ClientId {
+        self.id
+    }
+
+    /// Receives the next message queued for this client.
+    ///
+    /// Returns `None` once the client has been disconnected from the `Hub`, and all queued messages
+    /// have been received.
+    pub async fn recv(&mut self) -> Option<M> {
+        self.receiver.recv().await
+    }
+}
+
+impl<M> Drop for Connection<M> {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.remove(self.id);
+    }
+}
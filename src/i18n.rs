@@ -0,0 +1,157 @@
+//! Localized message catalogs.
+//!
+//! `Messages` holds a key-value catalog per locale. Catalogs are loaded from a simple text format,
+//! with one `key = message` pair per line, and `#` starting comments:
+//!
+//! ```
+//! use grazie::i18n::Messages;
+//!
+//! let messages = Messages::new("en")
+//!     .load("en", "greeting = Hello, {name}!\nfarewell = Goodbye.").unwrap()
+//!     .load("de", "greeting = Hallo, {name}!").unwrap();
+//!
+//! assert_eq!(messages.format("de-AT", "greeting", &[("name", "Ada")]), "Hallo, Ada!");
+//! assert_eq!(messages.format("de-AT", "farewell", &[]), "Goodbye.");
+//! ```
+//!
+//! Pair with `LocaleSeeder`, and look messages up for a request's negotiated locale through
+//! `Messages::for_request`.
+
+use crate::core::seeder::BoxBody;
+use crate::http::HttpRequest;
+use crate::seeders::locale::Locale;
+use crate::util::fill_placeholders;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// An error from loading a malformed catalog.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CatalogError {
+    /// The locale of the catalog.
+    pub locale: String,
+
+    /// The line the error is on, starting from 1.
+    pub line: usize,
+}
+
+impl Display for CatalogError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {} of the `{}` catalog isn't a `key = message` pair", self.line, self.locale)
+    }
+}
+
+impl Error for CatalogError {}
+
+/// Message catalogs for a set of locales.
+///
+/// Lookups fall back from the requested locale through its less specific forms (`de-AT`, then
+/// `de`), and finally to the default locale.
+#[derive(Debug, Clone)]
+pub struct Messages {
+    catalogs: HashMap<String, HashMap<String, String>>,
+    default: String,
+}
+
+impl Messages {
+    /// Constructs a new `Messages` without any catalogs, falling back to `default`.
+    pub fn new(default: impl Into<String>) -> Messages {
+        Messages {
+            catalogs: HashMap::new(),
+            default: default.into().to_ascii_lowercase(),
+        }
+    }
+
+    /// Adds a single message to a locale's catalog.
+    pub fn add(mut self, locale: &str, key: impl Into<String>, message: impl Into<String>) -> Messages {
+        self.catalogs.entry(locale.to_ascii_lowercase()).or_default().insert(key.into(), message.into());
+        self
+    }
+
+    /// Loads messages into a locale's catalog, replacing any with the same keys.
+    pub fn load(mut self, locale: &str, source: &str) -> Result<Messages, CatalogError> {
+        let catalog = self.catalogs.entry(locale.to_ascii_lowercase()).or_default();
+
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((key, message)) = line.split_once('=').filter(|(key, _)| !key.trim().is_empty()) else {
+                return Err(CatalogError {
+                    locale: locale.to_owned(),
+                    line: index + 1,
+                });
+            };
+
+            catalog.insert(key.trim().to_owned(), message.trim().replace("\\n", "\n"));
+        }
+
+        Ok(self)
+    }
+
+    /// Gets a message, falling back through less specific locales and then the default locale.
+    pub fn get(&self, locale: &str, key: &str) -> Option<&str> {
+        let mut locale = locale.to_ascii_lowercase();
+
+        loop {
+            if let Some(message) = self.catalogs.get(&locale).and_then(|catalog| catalog.get(key)) {
+                return Some(message);
+            }
+
+            match locale.rsplit_once('-') {
+                Some((shorter, _)) => { locale = shorter.to_owned(); }
+                None => { break; }
+            }
+        }
+
+        self.catalogs.get(&self.default).and_then(|catalog| catalog.get(key)).map(String::as_str)
+    }
+
+    /// Gets a message with its `{name}` placeholders filled in. Missing messages are returned as
+    /// their key, so that untranslated text is visible rather than blank.
+    pub fn format(&self, locale: &str, key: &str, args: &[(&str, &str)]) -> String {
+        let Some(message) = self.get(locale, key) else { return key.to_owned(); };
+
+        fill_placeholders(message, args, str::to_owned)
+    }
+
+    /// Gets the messages for a request's negotiated `Locale`, or the default locale if it has none.
+    pub fn for_request<'a>(&'a self, request: &HttpRequest<BoxBody>) -> Localized<'a> {
+        let locale = request
+            .extensions()
+            .get::<Locale>()
+            .map(|locale| locale.0.clone())
+            .unwrap_or_else(|| self.default.clone());
+
+        Localized {
+            messages: self,
+            locale,
+        }
+    }
+}
+
+/// The messages of a single locale.
+#[derive(Debug, Clone)]
+pub struct Localized<'a> {
+    messages: &'a Messages,
+    locale: String,
+}
+
+impl Localized<'_> {
+    /// Gets the locale messages are looked up in.
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// Gets a message, or its key if it's missing.
+    pub fn get(&self, key: &str) -> String {
+        self.messages.format(&self.locale, key, &[])
+    }
+
+    /// Gets a message with its `{name}` placeholders filled in, or its key if it's missing.
+    pub fn format(&self, key: &str, args: &[(&str, &str)]) -> String {
+        self.messages.format(&self.locale, key, args)
+    }
+}
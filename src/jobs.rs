@@ -0,0 +1,396 @@
+//! Background jobs, run on a schedule alongside the server.
+//!
+//! Apps register recurring and one-off jobs on `Jobs`, then start them all together. Each run is
+//! isolated, so a job which fails or panics is recorded in its `JobStatus` and runs again on its
+//! next scheduled time. On shutdown, no new runs are started, and runs already underway are
+//! awaited.
+
+use crate::seeders::catch_panic::{catch_panic, Panic};
+use crate::util::{civil_from_days, days_from_civil};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// A boxed run of a job.
+type JobFuture = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+
+/// A job's body, which creates a new run each time it's called.
+type JobFn = Arc<dyn Fn() -> JobFuture + Send + Sync>;
+
+/// A cron expression, such as `*/15 9-17 * * 1-5`, evaluated in UTC.
+///
+/// Expressions have five fields: minute (`0-59`), hour (`0-23`), day of month (`1-31`), month
+/// (`1-12`), and day of week (`0-7`, where both `0` and `7` are Sunday). Each field accepts `*`,
+/// single values, ranges (`a-b`), steps (`*/n` or `a-b/n`), and comma-separated lists of these.
+/// The aliases `@hourly`, `@daily`, `@weekly`, `@monthly`, and `@yearly` are also accepted.
+///
+/// As with standard cron, if both the day of month and the day of week are restricted, a day
+/// matching either one is run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Cron {
+    /// Gets the first time strictly after the provided time which matches this expression.
+    ///
+    /// Returns `None` if no matching time exists within the next few years, such as for
+    /// `0 0 31 2 *`.
+    pub fn next_after(&self, after: SystemTime) -> Option<SystemTime> {
+        let after = after.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        let mut time = (after / 60 + 1) * 60;
+
+        for _ in 0..100_000 {
+            let days = (time / 86_400) as i64;
+            let (year, month, day) = civil_from_days(days);
+
+            if self.months & (1 << month) == 0 {
+                let (year, month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+                time = days_from_civil(year, month, 1) as u64 * 86_400;
+                continue;
+            }
+
+            let weekday = (days + 4).rem_euclid(7) as u32;
+            let day_matches = self.days & (1 << day) != 0;
+            let weekday_matches = self.weekdays & (1 << weekday) != 0;
+
+            let matches = match (self.any_day, self.any_weekday) {
+                (false, false) => { day_matches || weekday_matches }
+                _ => { day_matches && weekday_matches }
+            };
+
+            if !matches {
+                time = (days as u64 + 1) * 86_400;
+                continue;
+            }
+
+            let hour = (time % 86_400) / 3_600;
+            if self.hours & (1 << hour) == 0 {
+                time = (time / 3_600 + 1) * 3_600;
+                continue;
+            }
+
+            let minute = (time % 3_600) / 60;
+            if self.minutes & (1 << minute) == 0 {
+                time += 60;
+                continue;
+            }
+
+            return Some(UNIX_EPOCH + Duration::from_secs(time));
+        }
+
+        None
+    }
+}
+
+impl FromStr for Cron {
+    type Err = String;
+
+    fn from_str(expression: &str) -> Result<Cron, String> {
+        let expression = match expression.trim() {
+            "@hourly" => { "0 * * * *" }
+            "@daily" | "@midnight" => { "0 0 * * *" }
+            "@weekly" => { "0 0 * * 0" }
+            "@monthly" => { "0 0 1 * *" }
+            "@yearly" | "@annually" => { "0 0 1 1 *" }
+            expression => { expression }
+        };
+
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!("expected 5 fields in cron expression, found {}", fields.len()));
+        };
+
+        let mut weekdays_mask = parse_field(weekdays, 0, 7)?;
+        if weekdays_mask & (1 << 7) != 0 {
+            weekdays_mask |= 1;
+        }
+
+        Ok(Cron {
+            minutes: parse_field(minutes, 0, 59)?,
+            hours: parse_field(hours, 0, 23)? as u32,
+            days: parse_field(days, 1, 31)? as u32,
+            months: parse_field(months, 1, 12)? as u16,
+            weekdays: (weekdays_mask & 0x7f) as u8,
+            any_day: days == "*",
+            any_weekday: weekdays == "*",
+        })
+    }
+}
+
+/// Parses a single cron field into a bitmask of the values it matches.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut mask = 0u64;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| format!("invalid step in `{part}`"))?;
+                (range, step)
+            }
+            None => { (part, 1) }
+        };
+
+        let parse = |value: &str| -> Result<u32, String> {
+            match value.parse::<u32>() {
+                Ok(value) if (min..=max).contains(&value) => { Ok(value) }
+                _ => { Err(format!("`{value}` is out of range {min}-{max}")) }
+            }
+        };
+
+        let (start, end) = match range {
+            "*" => { (min, max) }
+            range => {
+                match range.split_once('-') {
+                    Some((start, end)) => { (parse(start)?, parse(end)?) }
+                    None if step > 1 => { (parse(range)?, max) }
+                    None => { let value = parse(range)?; (value, value) }
+                }
+            }
+        };
+
+        if step == 0 || start > end {
+            return Err(format!("invalid cron field `{part}`"));
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+
+    Ok(mask)
+}
+
+/// When a job runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule {
+    /// Runs repeatedly, waiting this long between the end of one run and the start of the next.
+    Every(Duration),
+
+    /// Runs a single time, after this delay.
+    Once(Duration),
+
+    /// Runs at every time matching a cron expression.
+    Cron(Cron),
+}
+
+impl Schedule {
+    /// Gets the next time a job on this schedule should run, or `None` if it shouldn't run again.
+    fn next(&self, now: SystemTime, runs: u64) -> Option<SystemTime> {
+        match self {
+            Schedule::Every(period) => { Some(now + *period) }
+            Schedule::Once(delay) if runs == 0 => { Some(now + *delay) }
+            Schedule::Once(_) => { None }
+            Schedule::Cron(cron) => { cron.next_after(now) }
+        }
+    }
+}
+
+/// How a job's run ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobOutcome {
+    /// The run completed successfully.
+    Succeeded,
+
+    /// The run returned an error.
+    Failed(String),
+
+    /// The run panicked.
+    Panicked(Panic),
+}
+
+/// The state of a registered job, suitable for reporting through a health check.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JobStatus {
+    /// The number of times the job has run.
+    pub runs: u64,
+
+    /// The number of runs which failed or panicked.
+    pub failures: u64,
+
+    /// Whether the job is currently running.
+    pub running: bool,
+
+    /// When the most recent run started.
+    pub last_run: Option<SystemTime>,
+
+    /// How long the most recent run took.
+    pub last_duration: Option<Duration>,
+
+    /// How the most recent run ended.
+    pub last_outcome: Option<JobOutcome>,
+
+    /// When the job will next run, or `None` if it won't run again.
+    pub next_run: Option<SystemTime>,
+}
+
+/// A registered job.
+struct Job {
+    name: String,
+    schedule: Schedule,
+    run: JobFn,
+}
+
+/// A registry of background jobs, which are started together by `Jobs::start`.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn example() {
+/// use grazie::jobs::{Jobs, Schedule};
+/// use std::time::Duration;
+///
+/// let jobs = Jobs::new()
+///     .job("purge-sessions", Schedule::Every(Duration::from_secs(300)), || async {
+///         Ok::<(), String>(())
+///     })
+///     .job("nightly-report", Schedule::Cron("0 3 * * *".parse().unwrap()), || async {
+///         Ok::<(), String>(())
+///     })
+///     .start();
+///
+/// // ...
+/// jobs.shutdown().await;
+/// # }
+/// ```
+pub struct Jobs {
+    jobs: Vec<Job>,
+}
+
+impl Jobs {
+    /// Constructs a new, empty `Jobs`.
+    pub fn new() -> Jobs {
+        Jobs { jobs: Vec::new() }
+    }
+
+    /// Registers a job. `run` is called for every run, and the run fails if it returns an error.
+    pub fn job<F, Fut, E>(mut self, name: impl Into<String>, schedule: Schedule, run: F) -> Jobs
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Display,
+    {
+        let run: JobFn = Arc::new(move || {
+            let run = run();
+            Box::pin(async move { run.await.map_err(|error| error.to_string()) })
+        });
+
+        self.jobs.push(Job {
+            name: name.into(),
+            schedule,
+            run,
+        });
+        self
+    }
+
+    /// Starts every registered job, each on its own task.
+    pub fn start(self) -> JobsHandle {
+        let (shutdown, _) = watch::channel(false);
+        let statuses: Arc<Mutex<HashMap<String, JobStatus>>> = Arc::new(Mutex::new(HashMap::new()));
+
+        let tasks = self.jobs
+            .into_iter()
+            .map(|job| {
+                lock(&statuses).insert(job.name.clone(), JobStatus::default());
+                tokio::spawn(run_job(job, statuses.clone(), shutdown.subscribe()))
+            })
+            .collect();
+
+        JobsHandle {
+            shutdown,
+            statuses,
+            tasks: Mutex::new(tasks),
+        }
+    }
+}
+
+impl Default for Jobs {
+    fn default() -> Jobs {
+        Jobs::new()
+    }
+}
+
+/// Runs a job on its schedule until it has no further runs, or until shutdown.
+async fn run_job(job: Job, statuses: Arc<Mutex<HashMap<String, JobStatus>>>, mut shutdown: watch::Receiver<bool>) {
+    let mut runs = 0;
+
+    loop {
+        let next = job.schedule.next(SystemTime::now(), runs);
+        if let Some(status) = lock(&statuses).get_mut(&job.name) {
+            status.next_run = next;
+        }
+
+        let Some(next) = next else { return; };
+        let wait = next.duration_since(SystemTime::now()).unwrap_or_default();
+
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = shutdown.wait_for(|shutdown| *shutdown) => { return; }
+        }
+
+        if let Some(status) = lock(&statuses).get_mut(&job.name) {
+            status.running = true;
+            status.last_run = Some(SystemTime::now());
+        }
+
+        let started = Instant::now();
+        let outcome = match catch_panic((job.run)()).await {
+            Ok(Ok(())) => { JobOutcome::Succeeded }
+            Ok(Err(error)) => { JobOutcome::Failed(error) }
+            Err(panic) => { JobOutcome::Panicked(panic) }
+        };
+        runs += 1;
+
+        if let Some(status) = lock(&statuses).get_mut(&job.name) {
+            status.running = false;
+            status.runs = runs;
+            status.failures += u64::from(outcome != JobOutcome::Succeeded);
+            status.last_duration = Some(started.elapsed());
+            status.last_outcome = Some(outcome);
+        }
+    }
+}
+
+fn lock(statuses: &Mutex<HashMap<String, JobStatus>>) -> MutexGuard<'_, HashMap<String, JobStatus>> {
+    statuses.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// A handle to a set of started jobs.
+pub struct JobsHandle {
+    shutdown: watch::Sender<bool>,
+    statuses: Arc<Mutex<HashMap<String, JobStatus>>>,
+    tasks: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl JobsHandle {
+    /// Gets the status of a job.
+    pub fn status(&self, name: &str) -> Option<JobStatus> {
+        lock(&self.statuses).get(name).cloned()
+    }
+
+    /// Gets the status of every job.
+    pub fn statuses(&self) -> HashMap<String, JobStatus> {
+        lock(&self.statuses).clone()
+    }
+
+    /// Stops every job, waiting for any runs which are underway to finish.
+    pub async fn shutdown(&self) {
+        let _ = self.shutdown.send(true);
+
+        let tasks = std::mem::take(&mut *self.tasks.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+        for task in tasks {
+            let _ = task.await;
+        }
+    }
+}
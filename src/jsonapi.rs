@@ -0,0 +1,491 @@
+//! JSON:API documents, for APIs following the <https://jsonapi.org> conventions.
+//!
+//! `Document` is both the envelope responses are wrapped in (with their `data`, `included`
+//! resources, `meta`, and `links`) and what request bodies are parsed into. `ErrorDocument` turns
+//! a `Rejection` into JSON:API error objects, and `JsonApiSeeder` does so for every rejection in
+//! the chain:
+//!
+//! ```
+//! use grazie::jsonapi::{Document, Relationship, Resource};
+//! use serde_json::json;
+//!
+//! let article = Resource::new("articles", "1")
+//!     .attribute("title", "JSON:API paints my bikeshed!")
+//!     .relationship("author", Relationship::one("people", "9"));
+//!
+//! let author = Resource::new("people", "9").attribute("name", "Dan");
+//! let document = Document::single(article).include(author);
+//!
+//! assert_eq!(document.to_value(), json!({
+//!     "data": {
+//!         "type": "articles",
+//!         "id": "1",
+//!         "attributes": { "title": "JSON:API paints my bikeshed!" },
+//!         "relationships": { "author": { "data": { "type": "people", "id": "9" } } },
+//!     },
+//!     "included": [{ "type": "people", "id": "9", "attributes": { "name": "Dan" } }],
+//! }));
+//! ```
+//!
+//! Part of the `jsonapi` feature.
+
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// The media type of JSON:API documents.
+pub const JSON_API: &str = "application/vnd.api+json";
+
+/// Identifies a single resource, by its type and ID.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ResourceIdentifier {
+    /// The resource's type, such as `articles`.
+    #[serde(rename = "type")]
+    pub kind: String,
+
+    /// The resource's ID.
+    pub id: String,
+}
+
+/// The resources a relationship points at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Linkage {
+    /// A to-many relationship.
+    Many(Vec<ResourceIdentifier>),
+
+    /// A to-one relationship, which is `None` when it's empty.
+    One(Option<ResourceIdentifier>),
+}
+
+/// A relationship from one resource to others.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Relationship {
+    /// The resources the relationship points at.
+    pub data: Linkage,
+
+    /// Links related to the relationship.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub links: Option<Map<String, Value>>,
+
+    /// Non-standard information about the relationship.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+}
+
+impl Relationship {
+    /// Constructs a to-one relationship.
+    pub fn one(kind: impl Into<String>, id: impl Into<String>) -> Relationship {
+        let identifier = ResourceIdentifier { kind: kind.into(), id: id.into() };
+        Relationship::from(Linkage::One(Some(identifier)))
+    }
+
+    /// Constructs an empty to-one relationship.
+    pub fn none() -> Relationship {
+        Relationship::from(Linkage::One(None))
+    }
+
+    /// Constructs a to-many relationship, to resources of a single type.
+    pub fn many<I>(kind: impl Into<String>, ids: I) -> Relationship
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let kind = kind.into();
+        let identifiers = ids
+            .into_iter()
+            .map(|id| ResourceIdentifier { kind: kind.clone(), id: id.into() })
+            .collect();
+
+        Relationship::from(Linkage::Many(identifiers))
+    }
+}
+
+impl From<Linkage> for Relationship {
+    fn from(data: Linkage) -> Relationship {
+        Relationship {
+            data,
+            links: None,
+            meta: None,
+        }
+    }
+}
+
+/// A resource object.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Resource {
+    /// The resource's type, such as `articles`.
+    #[serde(rename = "type")]
+    pub kind: String,
+
+    /// The resource's ID, which may be missing from resources a client is creating.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    /// The resource's attributes.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub attributes: Map<String, Value>,
+
+    /// The resource's relationships to other resources.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub relationships: BTreeMap<String, Relationship>,
+
+    /// Links related to the resource.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub links: Option<Map<String, Value>>,
+
+    /// Non-standard information about the resource.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+}
+
+impl Resource {
+    /// Constructs a new `Resource`, without any attributes or relationships.
+    pub fn new(kind: impl Into<String>, id: impl Into<String>) -> Resource {
+        Resource {
+            kind: kind.into(),
+            id: Some(id.into()),
+            attributes: Map::new(),
+            relationships: BTreeMap::new(),
+            links: None,
+            meta: None,
+        }
+    }
+
+    /// Sets an attribute.
+    pub fn attribute(mut self, name: impl Into<String>, value: impl Into<Value>) -> Resource {
+        self.attributes.insert(name.into(), value.into());
+        self
+    }
+
+    /// Sets every field of a value as an attribute. Values which don't serialize to an object are
+    /// ignored.
+    pub fn attributes<T: Serialize>(mut self, value: &T) -> Resource {
+        if let Ok(Value::Object(attributes)) = serde_json::to_value(value) {
+            self.attributes.extend(attributes);
+        }
+
+        self
+    }
+
+    /// Sets a relationship.
+    pub fn relationship(mut self, name: impl Into<String>, relationship: Relationship) -> Resource {
+        self.relationships.insert(name.into(), relationship);
+        self
+    }
+
+    /// Sets a link, such as `self`.
+    pub fn link(mut self, name: impl Into<String>, href: impl Into<String>) -> Resource {
+        self.links.get_or_insert_with(Map::new).insert(name.into(), Value::String(href.into()));
+        self
+    }
+
+    /// Gets this resource's identifier, if it has an ID.
+    pub fn identifier(&self) -> Option<ResourceIdentifier> {
+        Some(ResourceIdentifier {
+            kind: self.kind.clone(),
+            id: self.id.clone()?,
+        })
+    }
+
+    /// Decodes the resource's attributes into a type.
+    ///
+    /// Attributes which don't fit the type are rejected with `422 Unprocessable Entity`.
+    pub fn decode_attributes<T: DeserializeOwned>(&self) -> Result<T, Rejection> {
+        serde_json::from_value(Value::Object(self.attributes.clone())).map_err(|error| {
+            let status = StatusCode::UNPROCESSABLE_ENTITY;
+            Rejection::new(status, "invalid_attributes", error.to_string())
+        })
+    }
+}
+
+/// The primary data of a document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PrimaryData {
+    /// A collection of resources.
+    Many(Vec<Resource>),
+
+    /// A single resource, which is `None` when it doesn't exist.
+    One(Option<Resource>),
+}
+
+/// A JSON:API document with primary data.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    /// The document's primary data.
+    pub data: PrimaryData,
+
+    /// Resources related to the primary data, included to save clients from fetching them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub included: Vec<Resource>,
+
+    /// Links related to the primary data, such as pagination links.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub links: Option<Map<String, Value>>,
+
+    /// Non-standard information about the document.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+}
+
+impl Document {
+    /// Constructs a document with a single resource as its primary data.
+    pub fn single(resource: Resource) -> Document {
+        Document::new(PrimaryData::One(Some(resource)))
+    }
+
+    /// Constructs a document with a collection of resources as its primary data.
+    pub fn collection(resources: impl IntoIterator<Item = Resource>) -> Document {
+        Document::new(PrimaryData::Many(resources.into_iter().collect()))
+    }
+
+    fn new(data: PrimaryData) -> Document {
+        Document {
+            data,
+            included: Vec::new(),
+            links: None,
+            meta: None,
+        }
+    }
+
+    /// Includes a related resource. Resources which have already been included are skipped.
+    pub fn include(mut self, resource: Resource) -> Document {
+        let included = resource.identifier().is_some_and(|identifier| {
+            self.included.iter().any(|existing| existing.identifier().as_ref() == Some(&identifier))
+        });
+
+        if !included {
+            self.included.push(resource);
+        }
+
+        self
+    }
+
+    /// Sets a link, such as `next`.
+    pub fn link(mut self, name: impl Into<String>, href: impl Into<String>) -> Document {
+        self.links.get_or_insert_with(Map::new).insert(name.into(), Value::String(href.into()));
+        self
+    }
+
+    /// Sets the document's meta object.
+    pub fn meta(mut self, meta: impl Into<Value>) -> Document {
+        self.meta = Some(meta.into());
+        self
+    }
+
+    /// Parses a request body as a document.
+    ///
+    /// Bodies which aren't JSON:API documents are rejected with `400 Bad Request`.
+    pub fn from_body(body: &BoxBody) -> Result<Document, Rejection> {
+        serde_json::from_slice(body.raw_bytes()).map_err(|error| {
+            Rejection::new(StatusCode::BAD_REQUEST, "malformed_document", error.to_string())
+        })
+    }
+
+    /// Gets the single resource of a request document, such as one creating or updating a
+    /// resource.
+    pub fn resource(&self) -> Option<&Resource> {
+        match &self.data {
+            PrimaryData::One(resource) => { resource.as_ref() }
+            PrimaryData::Many(_) => { None }
+        }
+    }
+
+    /// Converts the document into JSON.
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+
+    /// Creates a response with the document as its body.
+    pub fn into_response(self, status: StatusCode) -> HttpResponse<BoxBody> {
+        respond(status, &self)
+    }
+}
+
+/// Where in a request an error was found.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorSource {
+    /// A JSON pointer to the member of the request document which caused the error, such as
+    /// `/data/attributes/title`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pointer: Option<String>,
+
+    /// The query parameter which caused the error.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameter: Option<String>,
+}
+
+/// A JSON:API error object.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ErrorObject {
+    /// The HTTP status code, as a string.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+
+    /// A machine-readable code for the error.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+
+    /// A short, human-readable summary of the error.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    /// A human-readable explanation of this occurrence of the error.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+
+    /// Where in the request the error was found.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<ErrorSource>,
+
+    /// Non-standard information about the error.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+}
+
+/// A JSON:API document describing errors.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ErrorDocument {
+    /// The errors.
+    pub errors: Vec<ErrorObject>,
+}
+
+impl ErrorDocument {
+    /// Describes a rejection as error objects.
+    ///
+    /// Rejections from failed validations become one error per failed check, pointing at the
+    /// attribute which failed. Other rejections become a single error, with their details as its
+    /// meta object.
+    pub fn from_rejection(rejection: &Rejection) -> ErrorDocument {
+        let status = rejection.status.as_u16().to_string();
+        let title = rejection.status.canonical_reason().map(str::to_owned);
+
+        let fields = rejection
+            .details
+            .as_ref()
+            .and_then(|details| details.get("errors"))
+            .and_then(Value::as_object);
+        if let Some(fields) = fields.filter(|_| rejection.code == "validation_failed") {
+            let mut errors = Vec::new();
+
+            for (field, failures) in fields {
+                let field = field.replace('~', "~0").replace('/', "~1").replace('.', "/");
+                let pointer = format!("/data/attributes/{field}");
+
+                for failure in failures.as_array().into_iter().flatten() {
+                    errors.push(ErrorObject {
+                        status: Some(status.clone()),
+                        code: failure.get("code").and_then(Value::as_str).map(str::to_owned),
+                        title: title.clone(),
+                        detail: failure.get("message").and_then(Value::as_str).map(str::to_owned),
+                        source: Some(ErrorSource {
+                            pointer: Some(pointer.clone()),
+                            parameter: None,
+                        }),
+                        meta: None,
+                    });
+                }
+            }
+
+            return ErrorDocument { errors };
+        }
+
+        ErrorDocument {
+            errors: vec![ErrorObject {
+                status: Some(status),
+                code: Some(rejection.code.clone()),
+                title,
+                detail: Some(rejection.message.clone()),
+                source: None,
+                meta: rejection.details.clone(),
+            }],
+        }
+    }
+
+    /// Creates a response with the document as its body.
+    pub fn into_response(self, status: StatusCode) -> HttpResponse<BoxBody> {
+        respond(status, &self)
+    }
+}
+
+/// Creates a JSON:API response.
+fn respond<T: Serialize>(status: StatusCode, document: &T) -> HttpResponse<BoxBody> {
+    let body = serde_json::to_vec(document).unwrap_or_default();
+
+    HttpResponse::builder()
+        .status(status)
+        .header(CONTENT_TYPE, JSON_API)
+        .header(CONTENT_LENGTH, body.len())
+        .body(BoxBody::new(body.into_boxed_slice()))
+        .unwrap()
+}
+
+/// A `Seeder` for JSON:API endpoints, which rejects request bodies of other media types with
+/// `415 Unsupported Media Type`, and converts error rejections into JSON:API error documents.
+///
+/// Like `ProblemSeeder`, only error rejections which respond directly and without a body of their
+/// own are converted, and the response's other headers are kept. This should be placed after the
+/// `Seeder`s whose rejections it converts.
+pub struct JsonApiSeeder;
+
+impl Seeder for JsonApiSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let (request, respondent, rejection) = match input {
+            Guard::Inaccessible { request, respondent, rejection } => {
+                (request, respondent, rejection)
+            }
+            Guard::Accessible(request) => {
+                let content_type = request
+                    .headers()
+                    .get(CONTENT_TYPE)
+                    .and_then(|content_type| content_type.to_str().ok());
+
+                // The specification forbids media type parameters on JSON:API requests.
+                if request.body().raw_bytes().is_empty() || content_type == Some(JSON_API) {
+                    return Guard::Accessible(request);
+                }
+
+                let rejection = Rejection::new(
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    "unsupported_media_type",
+                    "Request bodies must be sent as application/vnd.api+json.",
+                );
+
+                let document = ErrorDocument::from_rejection(&rejection);
+                let response = document.into_response(rejection.status);
+                return Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Respond(Box::new(response)),
+                    rejection,
+                };
+            }
+        };
+
+        let error = rejection.status.is_client_error() || rejection.status.is_server_error();
+        let respondent = match respondent {
+            Respondent::Respond(response) if error && response.body().raw_bytes().is_empty() => {
+                let document = ErrorDocument::from_rejection(&rejection);
+                let mut document = document.into_response(rejection.status);
+
+                for (name, value) in response.headers() {
+                    if !document.headers().contains_key(name) {
+                        document.headers_mut().insert(name, value.clone());
+                    }
+                }
+
+                Respondent::Respond(Box::new(document))
+            }
+            respondent => { respondent }
+        };
+
+        Guard::Inaccessible { request, respondent, rejection }
+    }
+}
@@ -0,0 +1,274 @@
+//! JSON-RPC 2.0 dispatch.
+//!
+//! A `JsonRpc` registry maps method names to async handlers, whose params and results are typed
+//! through serde. Params may be sent by position (as an array, deserialized into a tuple) or by
+//! name (as an object, deserialized into a struct):
+//!
+//! ```
+//! use grazie::jsonrpc::{JsonRpc, RpcError};
+//!
+//! let rpc = JsonRpc::new()
+//!     .method("add", |(a, b): (i64, i64)| async move { Ok::<_, RpcError>(a + b) });
+//!
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! let response = rpc.handle(br#"{"jsonrpc":"2.0","method":"add","params":[2,3],"id":1}"#).await;
+//! assert_eq!(response.as_deref(), Some(r#"{"jsonrpc":"2.0","result":5,"id":1}"#));
+//! # });
+//! ```
+//!
+//! Batches and notifications are supported. Requests without an `id` are notifications, and
+//! are never answered, even when they fail.
+//!
+//! Part of the `serde_json` feature.
+
+use crate::core::seeder::BoxBody;
+use crate::http::header::CONTENT_TYPE;
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::pin::Pin;
+
+/// The JSON was malformed.
+pub const PARSE_ERROR: i64 = -32700;
+
+/// The JSON wasn't a valid request object.
+pub const INVALID_REQUEST: i64 = -32600;
+
+/// No method is registered with the requested name.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+/// The params didn't match what the method takes.
+pub const INVALID_PARAMS: i64 = -32602;
+
+/// The method failed internally.
+pub const INTERNAL_ERROR: i64 = -32603;
+
+/// A JSON-RPC error object.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RpcError {
+    /// The error's code. Codes from -32768 to -32000 are reserved by the specification.
+    pub code: i64,
+
+    /// A short description of the error.
+    pub message: String,
+
+    /// Additional information about the error.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    /// Constructs a new `RpcError`.
+    pub fn new(code: i64, message: impl Into<String>) -> RpcError {
+        RpcError {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// Constructs an `INVALID_PARAMS` error.
+    pub fn invalid_params(message: impl Into<String>) -> RpcError {
+        RpcError::new(INVALID_PARAMS, message)
+    }
+
+    /// Constructs an `INTERNAL_ERROR` error.
+    pub fn internal(message: impl Into<String>) -> RpcError {
+        RpcError::new(INTERNAL_ERROR, message)
+    }
+
+    /// Attaches additional information to this error.
+    pub fn data(mut self, data: impl Serialize) -> RpcError {
+        self.data = serde_json::to_value(data).ok();
+        self
+    }
+}
+
+impl Display for RpcError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "JSON-RPC error {}: {}", self.code, self.message)
+    }
+}
+
+impl Error for RpcError {}
+
+/// The future a type-erased method handler returns.
+type MethodFuture = Pin<Box<dyn Future<Output = Result<Value, RpcError>> + Send>>;
+
+/// A type-erased method handler.
+type Method = Box<dyn Fn(Value) -> MethodFuture + Send + Sync>;
+
+/// A registry of JSON-RPC 2.0 methods.
+#[derive(Default)]
+pub struct JsonRpc {
+    methods: HashMap<String, Method>,
+}
+
+impl JsonRpc {
+    /// Constructs a new `JsonRpc` without any methods.
+    pub fn new() -> JsonRpc {
+        JsonRpc::default()
+    }
+
+    /// Registers a method. Requests whose params don't deserialize into `P` are answered with
+    /// `INVALID_PARAMS`. Methods without params can take `()`.
+    pub fn method<P, R, F, Fut>(mut self, name: impl Into<String>, handler: F) -> JsonRpc
+    where
+        P: DeserializeOwned + Send + 'static,
+        R: Serialize + 'static,
+        F: Fn(P) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R, RpcError>> + Send + 'static,
+    {
+        let method: Method = Box::new(move |params| {
+            let params = match params {
+                // Omitted params stand in for an empty params list.
+                Value::Null => {
+                    serde_json::from_value::<P>(Value::Null)
+                        .or_else(|_| serde_json::from_value(json!([])))
+                }
+                params => { serde_json::from_value::<P>(params) }
+            };
+
+            match params {
+                Ok(params) => {
+                    let future = handler(params);
+
+                    Box::pin(async move {
+                        let result = future.await?;
+                        serde_json::to_value(result)
+                            .map_err(|error| RpcError::internal(error.to_string()))
+                    })
+                }
+                Err(error) => {
+                    let error = RpcError::invalid_params(error.to_string());
+                    Box::pin(async move { Err(error) })
+                }
+            }
+        });
+
+        self.methods.insert(name.into(), method);
+        self
+    }
+
+    /// Handles a request body, returning the response body, or `None` if there's nothing to
+    /// respond with (because the request was only notifications).
+    pub async fn handle(&self, body: &[u8]) -> Option<String> {
+        let response = match serde_json::from_slice::<Value>(body) {
+            Ok(Value::Array(batch)) if batch.is_empty() => {
+                let error = RpcError::new(INVALID_REQUEST, "Invalid Request");
+                serde_json::to_string(&error_response(Value::Null, error))
+            }
+            Ok(Value::Array(batch)) => {
+                let mut responses = Vec::with_capacity(batch.len());
+
+                for call in batch {
+                    responses.extend(self.call(call).await);
+                }
+
+                if responses.is_empty() {
+                    return None;
+                }
+
+                serde_json::to_string(&responses)
+            }
+            Ok(call) => { serde_json::to_string(&self.call(call).await?) }
+            Err(_) => {
+                let error = RpcError::new(PARSE_ERROR, "Parse error");
+                serde_json::to_string(&error_response(Value::Null, error))
+            }
+        };
+
+        response.ok()
+    }
+
+    /// Handles an HTTP request, responding with the JSON-RPC response, or `204 No Content` if
+    /// there's nothing to respond with.
+    pub async fn respond(&self, request: &HttpRequest<BoxBody>) -> HttpResponse<BoxBody> {
+        match self.handle(request.body().raw_bytes()).await {
+            Some(body) => {
+                HttpResponse::builder()
+                    .status(StatusCode::OK)
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(BoxBody::new(body.into_bytes().into()))
+                    .unwrap()
+            }
+            None => {
+                HttpResponse::builder()
+                    .status(StatusCode::NO_CONTENT)
+                    .body(BoxBody::empty())
+                    .unwrap()
+            }
+        }
+    }
+
+    /// Runs a single call, returning its response, or `None` for notifications.
+    async fn call(&self, call: Value) -> Option<Response> {
+        let Value::Object(mut call) = call else {
+            let error = RpcError::new(INVALID_REQUEST, "Invalid Request");
+            return Some(error_response(Value::Null, error));
+        };
+
+        let id = call.remove("id");
+        let valid_id = matches!(id, None | Some(Value::Null | Value::Number(_) | Value::String(_)));
+        let method = call.remove("method");
+
+        let version = call.get("jsonrpc").and_then(Value::as_str);
+        let (Some(Value::String(method)), true, Some("2.0")) = (method, valid_id, version) else {
+            let id = id.filter(|_| valid_id).unwrap_or(Value::Null);
+            return Some(error_response(id, RpcError::new(INVALID_REQUEST, "Invalid Request")));
+        };
+
+        let params = call.remove("params").unwrap_or(Value::Null);
+        if !matches!(params, Value::Null | Value::Array(_) | Value::Object(_)) {
+            let error = RpcError::new(INVALID_REQUEST, "Invalid Request");
+            return id.map(|id| error_response(id, error));
+        }
+
+        let result = match self.methods.get(&method) {
+            Some(handler) => { handler(params).await }
+            None => { Err(RpcError::new(METHOD_NOT_FOUND, "Method not found")) }
+        };
+
+        let id = id?;
+
+        Some(match result {
+            Ok(result) => {
+                Response {
+                    jsonrpc: "2.0",
+                    result: Some(result),
+                    error: None,
+                    id,
+                }
+            }
+            Err(error) => { error_response(id, error) }
+        })
+    }
+}
+
+/// A response object, with its members in the order the specification lists them.
+#[derive(Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+
+    id: Value,
+}
+
+/// Builds an error response object.
+fn error_response(id: Value, error: RpcError) -> Response {
+    Response {
+        jsonrpc: "2.0",
+        result: None,
+        error: Some(error),
+        id,
+    }
+}
@@ -1,14 +1,74 @@
 #[cfg(test)]
 mod tests;
 
+#[cfg(unix)]
+pub mod activation;
+pub mod admin;
+#[cfg(feature = "serde_json")]
+pub mod audit;
+pub mod auth;
+pub mod cache;
+#[cfg(feature = "config")]
+pub mod config;
 pub mod core;
+#[cfg(feature = "csv")]
+pub mod csv;
+pub mod db;
+#[cfg(feature = "dev")]
+pub mod dev;
+#[cfg(feature = "digest")]
+pub mod digest;
+pub mod dns;
+pub mod embedded;
+pub mod events;
+pub mod flags;
+pub mod grpc_web;
+pub mod hub;
+pub mod i18n;
+pub mod jobs;
+#[cfg(feature = "jsonapi")]
+pub mod jsonapi;
+#[cfg(feature = "serde_json")]
+pub mod jsonrpc;
+pub mod links;
+pub mod longpoll;
+pub mod mail;
+#[cfg(feature = "serde_json")]
+pub mod ndjson;
+pub mod outbox;
+pub mod pool;
+pub mod query;
+pub mod recorder;
+#[cfg(feature = "redis")]
+pub mod redis;
+pub mod retry;
+pub mod seeders;
 mod server;
+pub mod sniff;
+#[cfg(feature = "serde_xml")]
+pub mod soap;
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub mod sql;
+pub mod task;
+pub mod test;
+pub mod tunnel;
+pub mod tus;
+#[cfg(all(unix, feature = "upgrade"))]
+pub mod upgrade;
+pub mod upstream;
+mod util;
+pub mod validate;
+#[cfg(feature = "webhooks")]
+pub mod webhooks;
+pub mod wellknown;
 
 
 pub mod http {
+    pub use hyper::header;
+    pub use hyper::{Method, Uri};
     pub use hyper::StatusCode;
     pub use hyper::Request as HttpRequest;
     pub use hyper::Response as HttpResponse;
 }
 
-pub use crate::server::HttpServer;
\ No newline at end of file
+pub use crate::server::{HttpServer, LifecycleError, PeerAddr};
\ No newline at end of file
@@ -0,0 +1,189 @@
+//! Hypermedia links, as RFC 8288 `Link` headers and `_links` objects in JSON bodies.
+//!
+//! `Links` collects a resource's links once, so the same links can be sent in a `Link` header,
+//! embedded in a HAL-style `_links` object, or both. Hrefs are usually built from route templates
+//! with `expand`, so that links follow the same paths as the routes they point at:
+//!
+//! ```
+//! use grazie::links::{expand, Links};
+//!
+//! const ORDER: &str = "/orders/{id}";
+//!
+//! let links = Links::new()
+//!     .link("self", expand(ORDER, &[("id", "7")]))
+//!     .link("customer", expand("/customers/{id}", &[("id", "ada lovelace")]));
+//!
+//! assert_eq!(
+//!     links.to_header().to_str().unwrap(),
+//!     "</orders/7>; rel=\"self\", </customers/ada%20lovelace>; rel=\"customer\"",
+//! );
+//! ```
+
+use crate::http::header::HeaderValue;
+use crate::util::percent_encode;
+
+/// A single link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// The URI the link points at.
+    pub href: String,
+
+    /// The relation of the target to the linking resource, such as `next`.
+    pub rel: String,
+
+    /// A human-readable title for the link.
+    pub title: Option<String>,
+
+    /// The media type of the target, such as `application/json`.
+    pub media_type: Option<String>,
+}
+
+impl Link {
+    /// Constructs a new `Link`.
+    pub fn new(rel: impl Into<String>, href: impl Into<String>) -> Link {
+        Link {
+            href: href.into(),
+            rel: rel.into(),
+            title: None,
+            media_type: None,
+        }
+    }
+
+    /// Sets the link's title.
+    pub fn title(mut self, title: impl Into<String>) -> Link {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Sets the media type of the link's target.
+    pub fn media_type(mut self, media_type: impl Into<String>) -> Link {
+        self.media_type = Some(media_type.into());
+        self
+    }
+
+    /// Formats the link as a `Link` header value.
+    fn to_header_value(&self) -> String {
+        let mut value = format!("<{}>; rel=\"{}\"", self.href, quoted(&self.rel));
+
+        if let Some(title) = &self.title {
+            value.push_str(&format!("; title=\"{}\"", quoted(title)));
+        }
+        if let Some(media_type) = &self.media_type {
+            value.push_str(&format!("; type=\"{}\"", quoted(media_type)));
+        }
+
+        value
+    }
+}
+
+/// Escapes a `Link` header parameter's quoted string.
+fn quoted(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// The links of a resource.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Links {
+    links: Vec<Link>,
+}
+
+impl Links {
+    /// Constructs a new, empty `Links`.
+    pub fn new() -> Links {
+        Links::default()
+    }
+
+    /// Adds a link with a relation and target.
+    pub fn link(self, rel: impl Into<String>, href: impl Into<String>) -> Links {
+        self.with(Link::new(rel, href))
+    }
+
+    /// Adds a link.
+    pub fn with(mut self, link: Link) -> Links {
+        self.links.push(link);
+        self
+    }
+
+    /// Gets every link, in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = &Link> {
+        self.links.iter()
+    }
+
+    /// Checks whether no links have been added.
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty()
+    }
+
+    /// Formats the links as a `Link` header.
+    pub fn to_header(&self) -> HeaderValue {
+        let links: Vec<String> = self.links.iter().map(Link::to_header_value).collect();
+
+        // Control characters are the only characters header values can't hold.
+        HeaderValue::try_from(links.join(", ").replace(char::is_control, "")).unwrap()
+    }
+
+    /// Formats the links as a HAL-style `_links` object, keyed by relation. Relations with more
+    /// than one link get an array.
+    ///
+    /// Part of the `serde_json` feature.
+    #[cfg(feature = "serde_json")]
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut object = serde_json::Map::new();
+
+        for link in &self.links {
+            let mut value = serde_json::Map::new();
+            value.insert("href".into(), link.href.clone().into());
+            if let Some(title) = &link.title {
+                value.insert("title".into(), title.clone().into());
+            }
+            if let Some(media_type) = &link.media_type {
+                value.insert("type".into(), media_type.clone().into());
+            }
+
+            match object.remove(&link.rel) {
+                None => { object.insert(link.rel.clone(), value.into()); }
+                Some(serde_json::Value::Array(mut values)) => {
+                    values.push(value.into());
+                    object.insert(link.rel.clone(), values.into());
+                }
+                Some(existing) => { object.insert(link.rel.clone(), vec![existing, value.into()].into()); }
+            }
+        }
+
+        object.into()
+    }
+
+    /// Embeds the links in a JSON object as its `_links` member. Values other than objects are
+    /// left as they are.
+    ///
+    /// Part of the `serde_json` feature.
+    #[cfg(feature = "serde_json")]
+    pub fn embed(&self, value: &mut serde_json::Value) {
+        if let Some(object) = value.as_object_mut() {
+            object.insert("_links".into(), self.to_json());
+        }
+    }
+}
+
+/// Expands a route template such as `/orders/{id}`, percent-encoding each parameter's value.
+/// Placeholders without a parameter are left as they are.
+pub fn expand(template: &str, parameters: &[(&str, &str)]) -> String {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else { break; };
+        expanded.push_str(&rest[..start]);
+
+        let name = &rest[start + 1..end];
+        match parameters.iter().find(|(parameter, _)| *parameter == name) {
+            Some((_, value)) => { expanded.push_str(&percent_encode(value)); }
+            None => { expanded.push_str(&rest[start..=end]); }
+        }
+
+        rest = &rest[end + 1..];
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
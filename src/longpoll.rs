@@ -0,0 +1,201 @@
+//! A long-polling helper, for clients which can't hold open a WebSocket or an event stream.
+//!
+//! Handlers await new events on a topic up to a timeout. If an event is published in the
+//! meantime, the handler responds with it straight away; otherwise, the handler responds with
+//! `204 No Content`, and the client polls again.
+
+use crate::core::seeder::BoxBody;
+use crate::http::header::HeaderValue;
+use crate::http::{HttpResponse, StatusCode};
+use std::collections::{HashMap, VecDeque};
+use std::pin::pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::{timeout_at, Instant};
+
+/// The response header which holds the cursor of the newest event in a long-poll response.
+///
+/// Clients should pass this value back on their next poll, so that no events are missed between
+/// polls.
+pub const CURSOR_HEADER: &str = "x-poll-cursor";
+
+/// An event published to a `LongPoll` topic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event<E> {
+    /// The position of this event within its topic. Cursors increase with every published event.
+    pub cursor: u64,
+
+    /// The published event.
+    pub event: E,
+}
+
+/// A single topic, holding its most recently published events.
+struct Topic<E> {
+    events: VecDeque<Event<E>>,
+    next: u64,
+    notify: Arc<Notify>,
+}
+
+impl<E> Topic<E> {
+    fn new() -> Topic<E> {
+        Topic {
+            events: VecDeque::new(),
+            next: 0,
+            notify: Arc::new(Notify::new()),
+        }
+    }
+}
+
+/// A registry of topics which long-polling clients can await events on.
+///
+/// Each topic retains a bounded number of its most recent events, so that a client which
+/// reconnects with its last cursor receives whatever it missed in between polls. Topics are only
+/// created by publishing, so clients can't grow the registry by polling made-up topics.
+///
+/// `LongPoll`s are cheap to clone, and all clones share the same topics.
+pub struct LongPoll<E> {
+    topics: Arc<Mutex<HashMap<String, Topic<E>>>>,
+    created: Arc<Notify>,
+    retain: usize,
+}
+
+impl<E> Clone for LongPoll<E> {
+    fn clone(&self) -> LongPoll<E> {
+        LongPoll {
+            topics: self.topics.clone(),
+            created: self.created.clone(),
+            retain: self.retain,
+        }
+    }
+}
+
+impl<E: Clone> LongPoll<E> {
+    /// Constructs a new `LongPoll`, retaining up to `retain` events per topic.
+    pub fn new(retain: usize) -> LongPoll<E> {
+        LongPoll {
+            topics: Arc::new(Mutex::new(HashMap::new())),
+            created: Arc::new(Notify::new()),
+            retain: retain.max(1),
+        }
+    }
+
+    /// Publishes an event to a topic, waking every client awaiting the topic.
+    ///
+    /// Topics are created on their first publish. Returns the cursor of the published event.
+    pub fn publish(&self, topic: &str, event: E) -> u64 {
+        let mut topics = self.lock();
+        let topic = topics.entry(topic.to_owned()).or_insert_with(|| {
+            self.created.notify_waiters();
+            Topic::new()
+        });
+
+        let cursor = topic.next;
+        topic.next += 1;
+        topic.events.push_back(Event { cursor, event });
+
+        while topic.events.len() > self.retain {
+            topic.events.pop_front();
+        }
+
+        topic.notify.notify_waiters();
+        cursor
+    }
+
+    /// Awaits events on a topic published after the provided cursor, for up to `timeout`.
+    ///
+    /// If `after` is `None`, only events published after this call are returned. If events newer
+    /// than `after` have already been published, they are returned immediately. Returns an empty
+    /// list if the timeout expires without any new events.
+    ///
+    /// Polling a topic which hasn't been published to doesn't create it. Instead, the poll waits
+    /// for the topic's first events.
+    pub async fn poll(&self, topic: &str, after: Option<u64>, timeout: Duration) -> Vec<Event<E>> {
+        let deadline = Instant::now() + timeout;
+
+        // Every event of a topic which doesn't exist yet will be new to this poll.
+        let after = match self.lock().get(topic) {
+            Some(entry) => { after.or(entry.next.checked_sub(1)) }
+            None => { after }
+        };
+
+        loop {
+            let notify = match self.lock().get(topic) {
+                Some(entry) => { entry.notify.clone() }
+                None => { self.created.clone() }
+            };
+
+            let mut notified = pin!(notify.notified());
+            notified.as_mut().enable();
+
+            let events = self.events_after(topic, after);
+            if !events.is_empty() {
+                return events;
+            }
+
+            if timeout_at(deadline, notified).await.is_err() {
+                return Vec::new();
+            }
+        }
+    }
+
+    /// Awaits events on a topic, and packages them into a response.
+    ///
+    /// Responds with `204 No Content` if the timeout expires. Otherwise, the events are encoded
+    /// into the response body by `encode`, and the newest event's cursor is placed in the
+    /// `CURSOR_HEADER` header.
+    pub async fn respond<F>(
+        &self,
+        topic: &str,
+        after: Option<u64>,
+        timeout: Duration,
+        encode: F,
+    ) -> HttpResponse<BoxBody>
+    where
+        F: FnOnce(&[Event<E>]) -> BoxBody,
+    {
+        let events = self.poll(topic, after, timeout).await;
+
+        let Some(last) = events.last() else {
+            return HttpResponse::builder()
+                .status(StatusCode::NO_CONTENT)
+                .body(BoxBody::empty())
+                .unwrap();
+        };
+
+        HttpResponse::builder()
+            .status(StatusCode::OK)
+            .header(CURSOR_HEADER, HeaderValue::from(last.cursor))
+            .body(encode(&events))
+            .unwrap()
+    }
+
+    /// Gets the names of every known topic.
+    pub fn topics(&self) -> Vec<String> {
+        self.lock().keys().cloned().collect()
+    }
+
+    /// Removes a topic, along with its retained events.
+    ///
+    /// Clients currently awaiting the topic continue waiting until their timeout.
+    pub fn remove(&self, topic: &str) {
+        self.lock().remove(topic);
+    }
+
+    /// Gets the retained events on a topic which are newer than the provided cursor.
+    fn events_after(&self, topic: &str, after: Option<u64>) -> Vec<Event<E>> {
+        let topics = self.lock();
+
+        let Some(topic) = topics.get(topic) else { return Vec::new(); };
+
+        topic.events
+            .iter()
+            .filter(|event| after.is_none_or(|after| event.cursor > after))
+            .cloned()
+            .collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Topic<E>>> {
+        self.topics.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
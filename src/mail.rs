@@ -0,0 +1,411 @@
+//! Sending email, such as for signup and password reset flows.
+//!
+//! A `Mailer` renders messages from named templates and sends them through a `Transport`, either
+//! straight away or from a queue drained by a background job. Templates fill `{name}`
+//! placeholders, as `Messages::format` does, with values escaped in HTML bodies:
+//!
+//! ```
+//! use grazie::mail::{Mailer, MemoryTransport, Template};
+//!
+//! let mailer = Mailer::new(MemoryTransport::new())
+//!     .from("noreply@example.com")
+//!     .template("reset", Template::new("Reset your password", "Hi {name}, reset it at {link}."));
+//!
+//! let values = [("name", "Ada"), ("link", "https://example.com/r/1")];
+//! let message = mailer.render("reset", "ada@example.com", &values).unwrap();
+//! assert_eq!(message.text, "Hi Ada, reset it at https://example.com/r/1.");
+//! ```
+//!
+//! With the `lettre` feature, `SmtpTransport` sends messages to an SMTP relay.
+
+use crate::jobs::{Jobs, Schedule};
+use crate::util::fill_placeholders;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// An email message.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Message {
+    /// The sender's address, or `None` to use the mailer's default sender.
+    pub from: Option<String>,
+
+    /// The recipients' addresses.
+    pub to: Vec<String>,
+
+    /// The subject line.
+    pub subject: String,
+
+    /// The plain text body.
+    pub text: String,
+
+    /// The HTML body, sent as an alternative to the plain text body.
+    pub html: Option<String>,
+}
+
+impl Message {
+    /// Constructs a new plain text `Message` to a single recipient.
+    pub fn new(
+        to: impl Into<String>,
+        subject: impl Into<String>,
+        text: impl Into<String>,
+    ) -> Message {
+        Message {
+            from: None,
+            to: vec![to.into()],
+            subject: subject.into(),
+            text: text.into(),
+            html: None,
+        }
+    }
+
+    /// Adds a recipient.
+    pub fn to(mut self, to: impl Into<String>) -> Message {
+        self.to.push(to.into());
+        self
+    }
+
+    /// Sets the sender.
+    pub fn from(mut self, from: impl Into<String>) -> Message {
+        self.from = Some(from.into());
+        self
+    }
+
+    /// Sets the HTML body.
+    pub fn html(mut self, html: impl Into<String>) -> Message {
+        self.html = Some(html.into());
+        self
+    }
+}
+
+/// A message template, with `{name}` placeholders in its subject and bodies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    subject: String,
+    text: String,
+    html: Option<String>,
+}
+
+impl Template {
+    /// Constructs a new plain text `Template`.
+    pub fn new(subject: impl Into<String>, text: impl Into<String>) -> Template {
+        Template {
+            subject: subject.into(),
+            text: text.into(),
+            html: None,
+        }
+    }
+
+    /// Sets the HTML body. Values are HTML-escaped as they're filled in.
+    pub fn html(mut self, html: impl Into<String>) -> Template {
+        self.html = Some(html.into());
+        self
+    }
+
+    /// Renders a message to a recipient, filling placeholders in from `args`.
+    pub fn render(&self, to: impl Into<String>, args: &[(&str, &str)]) -> Message {
+        Message {
+            from: None,
+            to: vec![to.into()],
+            subject: fill_placeholders(&self.subject, args, str::to_owned),
+            text: fill_placeholders(&self.text, args, str::to_owned),
+            html: self.html.as_deref().map(|html| fill_placeholders(html, args, escape_html)),
+        }
+    }
+}
+
+/// Escapes text for use in HTML content and attributes.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Delivers messages, such as over SMTP or to a provider's HTTP API.
+pub trait Transport: Send + Sync + 'static {
+    /// The error delivery fails with.
+    type Error: Display + Send;
+
+    /// Delivers a message, completing once the server has accepted it.
+    fn send(&self, message: &Message) -> impl Future<Output = Result<(), Self::Error>> + Send;
+}
+
+/// A `Transport` which keeps every message in memory, for tests.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryTransport {
+    sent: Arc<Mutex<Vec<Message>>>,
+}
+
+impl MemoryTransport {
+    /// Constructs a new, empty `MemoryTransport`.
+    pub fn new() -> MemoryTransport {
+        MemoryTransport::default()
+    }
+
+    /// Gets every message sent so far, in order.
+    pub fn sent(&self) -> Vec<Message> {
+        lock(&self.sent).clone()
+    }
+}
+
+impl Transport for MemoryTransport {
+    type Error = std::convert::Infallible;
+
+    async fn send(&self, message: &Message) -> Result<(), std::convert::Infallible> {
+        lock(&self.sent).push(message.clone());
+        Ok(())
+    }
+}
+
+/// A `Transport` which sends messages to an SMTP server, through `lettre`.
+///
+/// Messages must have a sender, either their own or the mailer's default. Messages with an HTML
+/// body are sent as `multipart/alternative`, with the plain text body first.
+///
+/// Part of the `lettre` feature.
+#[cfg(feature = "lettre")]
+#[derive(Clone)]
+pub struct SmtpTransport {
+    transport: lettre::AsyncSmtpTransport<lettre::Tokio1Executor>,
+}
+
+#[cfg(feature = "lettre")]
+impl SmtpTransport {
+    /// Constructs a new `SmtpTransport` over a `lettre` transport, which sets the server, its
+    /// credentials and how the connection is secured.
+    pub fn new(transport: lettre::AsyncSmtpTransport<lettre::Tokio1Executor>) -> SmtpTransport {
+        SmtpTransport {
+            transport,
+        }
+    }
+
+    /// Constructs a new `SmtpTransport` to a relay, over a connection secured with `STARTTLS`.
+    pub fn relay(
+        host: &str,
+        username: &str,
+        password: &str,
+    ) -> Result<SmtpTransport, lettre::transport::smtp::Error> {
+        use lettre::transport::smtp::authentication::Credentials;
+
+        let credentials = Credentials::new(username.to_owned(), password.to_owned());
+        let transport = lettre::AsyncSmtpTransport::<lettre::Tokio1Executor>::starttls_relay(host)?;
+
+        Ok(SmtpTransport::new(transport.credentials(credentials).build()))
+    }
+}
+
+#[cfg(feature = "lettre")]
+impl Transport for SmtpTransport {
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn send(
+        &self,
+        message: &Message,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        use lettre::message::{MultiPart, SinglePart};
+        use lettre::AsyncTransport;
+
+        let Some(from) = &message.from else { return Err("the message has no sender".into()); };
+
+        let mut builder =
+            lettre::Message::builder().from(from.parse()?).subject(message.subject.as_str());
+        for to in &message.to {
+            builder = builder.to(to.parse()?);
+        }
+
+        let email = match &message.html {
+            Some(html) => {
+                let text = message.text.clone();
+                builder.multipart(MultiPart::alternative_plain_html(text, html.clone()))?
+            }
+            None => { builder.singlepart(SinglePart::plain(message.text.clone()))? }
+        };
+
+        self.transport.send(email).await?;
+        Ok(())
+    }
+}
+
+/// What happened to a message, as reported to delivery hooks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    /// The message was accepted for delivery.
+    Sent,
+
+    /// Delivery failed, with the transport's error. Queued messages are tried again.
+    Failed(String),
+
+    /// Delivery of a queued message failed on its last attempt, with the transport's error, and
+    /// the message was dropped from the queue.
+    Abandoned(String),
+}
+
+/// A hook, called with every message's delivery status.
+type DeliveryHook = Arc<dyn Fn(&Message, &DeliveryStatus) + Send + Sync>;
+
+/// Renders and sends email through a transport.
+///
+/// Cloning a `Mailer` is cheap, and every clone shares the same transport and queue.
+pub struct Mailer<T> {
+    transport: Arc<T>,
+    from: Option<String>,
+    templates: HashMap<String, Template>,
+    hooks: Vec<DeliveryHook>,
+    attempts: u32,
+    queue: Arc<Mutex<VecDeque<(Message, u32)>>>,
+}
+
+impl<T> Clone for Mailer<T> {
+    fn clone(&self) -> Mailer<T> {
+        Mailer {
+            transport: self.transport.clone(),
+            from: self.from.clone(),
+            templates: self.templates.clone(),
+            hooks: self.hooks.clone(),
+            attempts: self.attempts,
+            queue: self.queue.clone(),
+        }
+    }
+}
+
+impl<T: Transport> Mailer<T> {
+    /// Constructs a new `Mailer` over a transport, without templates, trying queued messages up
+    /// to 3 times.
+    pub fn new(transport: T) -> Mailer<T> {
+        Mailer {
+            transport: Arc::new(transport),
+            from: None,
+            templates: HashMap::new(),
+            hooks: Vec::new(),
+            attempts: 3,
+            queue: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    /// Sets the sender of messages which don't set their own.
+    pub fn from(mut self, from: impl Into<String>) -> Mailer<T> {
+        self.from = Some(from.into());
+        self
+    }
+
+    /// Registers a template under a name.
+    pub fn template(mut self, name: impl Into<String>, template: Template) -> Mailer<T> {
+        self.templates.insert(name.into(), template);
+        self
+    }
+
+    /// Sets how many times a queued message is tried before it's abandoned.
+    pub fn attempts(mut self, attempts: u32) -> Mailer<T> {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    /// Registers a hook, called with every message's delivery status, such as for recording
+    /// bounces or metrics.
+    pub fn on_delivery(
+        mut self,
+        hook: impl Fn(&Message, &DeliveryStatus) + Send + Sync + 'static,
+    ) -> Mailer<T> {
+        self.hooks.push(Arc::new(hook));
+        self
+    }
+
+    /// Gets the transport.
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Renders a registered template to a recipient, or returns `None` if there's no template
+    /// with the name.
+    pub fn render(
+        &self,
+        name: &str,
+        to: impl Into<String>,
+        args: &[(&str, &str)],
+    ) -> Option<Message> {
+        self.templates.get(name).map(|template| template.render(to, args))
+    }
+
+    /// Sends a message straight away.
+    pub async fn send(&self, message: Message) -> Result<(), T::Error> {
+        let message = self.addressed(message);
+        let result = self.transport.send(&message).await;
+
+        match &result {
+            Ok(()) => { self.report(&message, DeliveryStatus::Sent); }
+            Err(error) => { self.report(&message, DeliveryStatus::Failed(error.to_string())); }
+        }
+
+        result
+    }
+
+    /// Queues a message, to be sent by `Mailer::flush`.
+    pub fn queue(&self, message: Message) {
+        let message = self.addressed(message);
+        lock(&self.queue).push_back((message, 0));
+    }
+
+    /// Gets how many messages are queued.
+    pub fn queued(&self) -> usize {
+        lock(&self.queue).len()
+    }
+
+    /// Sends every queued message. Messages which fail are queued again until they've been tried
+    /// the configured number of times. Returns how many messages were sent.
+    pub async fn flush(&self) -> usize {
+        let batch = std::mem::take(&mut *lock(&self.queue));
+        let mut sent = 0;
+
+        for (message, tries) in batch {
+            match self.transport.send(&message).await {
+                Ok(()) => {
+                    sent += 1;
+                    self.report(&message, DeliveryStatus::Sent);
+                }
+                Err(error) if tries + 1 >= self.attempts => {
+                    self.report(&message, DeliveryStatus::Abandoned(error.to_string()));
+                }
+                Err(error) => {
+                    self.report(&message, DeliveryStatus::Failed(error.to_string()));
+                    lock(&self.queue).push_back((message, tries + 1));
+                }
+            }
+        }
+
+        sent
+    }
+
+    /// Registers a `mail-queue` job with `jobs`, flushing the queue every `period`.
+    pub fn schedule(&self, jobs: Jobs, period: Duration) -> Jobs {
+        let mailer = self.clone();
+
+        jobs.job("mail-queue", Schedule::Every(period), move || {
+            let mailer = mailer.clone();
+            async move {
+                mailer.flush().await;
+                Ok::<(), String>(())
+            }
+        })
+    }
+
+    fn addressed(&self, mut message: Message) -> Message {
+        if message.from.is_none() {
+            message.from = self.from.clone();
+        }
+
+        message
+    }
+
+    fn report(&self, message: &Message, status: DeliveryStatus) {
+        for hook in &self.hooks {
+            hook(message, &status);
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
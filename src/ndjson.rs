@@ -0,0 +1,189 @@
+//! Newline-delimited JSON (NDJSON, or JSON Lines), one value per line.
+//!
+//! `NdjsonWriter` and `NdjsonReader` encode and decode values one at a time over any tokio
+//! writer or reader, such as an upgraded connection, so that producers wait on slow consumers
+//! rather than buffering everything. `to_body` and `from_body` do the same for whole bodies, for
+//! bulk exports and ingests which fit in memory:
+//!
+//! ```
+//! use grazie::ndjson::{from_body, to_body};
+//!
+//! let body = to_body([1, 2, 3]).unwrap();
+//! assert_eq!(body.raw_bytes(), b"1\n2\n3\n");
+//!
+//! let numbers: Vec<u32> = from_body(&body).collect::<Result<_, _>>().unwrap();
+//! assert_eq!(numbers, vec![1, 2, 3]);
+//! ```
+//!
+//! Part of the `serde_json` feature.
+
+use crate::core::seeder::BoxBody;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+use std::io;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// The media type of NDJSON documents.
+pub const NDJSON: &str = "application/x-ndjson";
+
+/// An error reading NDJSON.
+#[derive(Debug)]
+pub enum NdjsonError {
+    /// The underlying reader failed.
+    Io(io::Error),
+
+    /// A line wasn't valid JSON for the expected type. Lines are counted from 1.
+    Json(u64, serde_json::Error),
+
+    /// A line was longer than the reader allows. Lines are counted from 1.
+    TooLong(u64),
+}
+
+impl Display for NdjsonError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NdjsonError::Io(error) => { write!(f, "failed to read NDJSON: {error}") }
+            NdjsonError::Json(line, error) => { write!(f, "invalid JSON on line {line}: {error}") }
+            NdjsonError::TooLong(line) => { write!(f, "line {line} is too long") }
+        }
+    }
+}
+
+impl std::error::Error for NdjsonError {}
+
+/// Writes values as NDJSON, one line at a time.
+pub struct NdjsonWriter<W> {
+    inner: W,
+    line: Vec<u8>,
+    written: u64,
+}
+
+impl<W: AsyncWrite + Unpin> NdjsonWriter<W> {
+    /// Constructs a new `NdjsonWriter` over a writer.
+    pub fn new(inner: W) -> NdjsonWriter<W> {
+        NdjsonWriter {
+            inner,
+            line: Vec::new(),
+            written: 0,
+        }
+    }
+
+    /// Writes a value as a single line, waiting until the writer has accepted it.
+    pub async fn write<T: Serialize>(&mut self, value: &T) -> io::Result<()> {
+        self.line.clear();
+        serde_json::to_writer(&mut self.line, value)?;
+        self.line.push(b'\n');
+
+        self.inner.write_all(&self.line).await?;
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Flushes the underlying writer.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush().await
+    }
+
+    /// Gets the number of values written so far.
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// Unwraps the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Reads NDJSON values, one line at a time. Blank lines are skipped.
+pub struct NdjsonReader<R> {
+    inner: R,
+    line: Vec<u8>,
+    number: u64,
+    max_line: usize,
+}
+
+impl<R: AsyncBufRead + Unpin> NdjsonReader<R> {
+    /// Constructs a new `NdjsonReader` over a reader, allowing lines of up to 1 MiB.
+    pub fn new(inner: R) -> NdjsonReader<R> {
+        NdjsonReader {
+            inner,
+            line: Vec::new(),
+            number: 0,
+            max_line: 1024 * 1024,
+        }
+    }
+
+    /// Sets the longest line allowed, in bytes.
+    pub fn max_line(mut self, max_line: usize) -> NdjsonReader<R> {
+        self.max_line = max_line;
+        self
+    }
+
+    /// Reads the next value, or `None` once the reader is exhausted.
+    pub async fn next<T: DeserializeOwned>(&mut self) -> Option<Result<T, NdjsonError>> {
+        loop {
+            self.line.clear();
+            self.number += 1;
+
+            // Reads are capped so that a line without an end can't grow without bound.
+            let mut limited = (&mut self.inner).take(self.max_line as u64 + 1);
+            match limited.read_until(b'\n', &mut self.line).await {
+                Ok(0) => { return None; }
+                Ok(_) => {}
+                Err(error) => { return Some(Err(NdjsonError::Io(error))); }
+            }
+
+            if self.line.last() != Some(&b'\n') && self.line.len() > self.max_line {
+                return Some(Err(NdjsonError::TooLong(self.number)));
+            }
+
+            match decode(&self.line, self.number) {
+                Some(value) => { return Some(value); }
+                None => { continue; }
+            }
+        }
+    }
+
+    /// Unwraps the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+/// Decodes a single line, or returns `None` if it's blank.
+fn decode<T: DeserializeOwned>(line: &[u8], number: u64) -> Option<Result<T, NdjsonError>> {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+    if line.iter().all(u8::is_ascii_whitespace) {
+        return None;
+    }
+
+    Some(serde_json::from_slice(line).map_err(|error| NdjsonError::Json(number, error)))
+}
+
+/// Encodes values as an NDJSON body.
+pub fn to_body<T, I>(values: I) -> Result<BoxBody, serde_json::Error>
+where
+    T: Serialize,
+    I: IntoIterator<Item = T>,
+{
+    let mut body = Vec::new();
+
+    for value in values {
+        serde_json::to_writer(&mut body, &value)?;
+        body.push(b'\n');
+    }
+
+    Ok(BoxBody::new(body.into_boxed_slice()))
+}
+
+/// Decodes the values of an NDJSON body, one line at a time. Blank lines are skipped.
+pub fn from_body<T: DeserializeOwned>(body: &BoxBody) -> impl Iterator<Item = Result<T, NdjsonError>> + '_ {
+    body.raw_bytes()
+        .split_inclusive(|byte| *byte == b'\n')
+        .zip(1..)
+        .filter_map(|(line, number)| decode(line, number))
+}
@@ -0,0 +1,188 @@
+//! The transactional outbox, for publishing events reliably alongside database writes.
+//!
+//! Publishing an event straight from a handler can lose it (if the broker is down after the
+//! transaction commits) or send one which never happened (if the transaction rolls back after
+//! publishing). An `Outbox` instead writes events into the request's transaction, so they're
+//! stored if and only if the request's other writes are, and a relay job publishes stored events
+//! afterwards. An event is only marked as published once its publisher has accepted it, so every
+//! event is delivered at least once, and may be delivered more than once if the relay is
+//! interrupted between publishing and marking. Consumers should deduplicate by the `outbox-id`
+//! header each relayed event carries.
+//!
+//! ```no_run
+//! # use grazie::db::Database;
+//! # use grazie::events::MemoryPublisher;
+//! # use grazie::jobs::Jobs;
+//! # use grazie::outbox::{Outbox, OutboxStore};
+//! # use std::time::Duration;
+//! # fn example<P: OutboxStore>(database: Database<P>) {
+//! let outbox = Outbox::new(database);
+//! let jobs = outbox.schedule(Jobs::new(), MemoryPublisher::new(), Duration::from_secs(1)).start();
+//! # }
+//! ```
+
+use crate::core::seeder::BoxBody;
+use crate::db::{Database, Transaction, Transactional};
+use crate::events::{Event, Publisher};
+use crate::http::HttpRequest;
+use crate::jobs::{Jobs, Schedule};
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The header carrying a relayed event's outbox ID, for consumers to deduplicate by.
+pub const OUTBOX_ID: &str = "outbox-id";
+
+/// An event stored in the outbox, waiting to be published.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredEvent {
+    /// The ID the store assigned to the event, in the order events were written.
+    pub id: u64,
+
+    /// The event.
+    pub event: Event,
+}
+
+/// A transactional pool with an outbox table.
+pub trait OutboxStore: Transactional {
+    /// Writes an event to the outbox, in a transaction.
+    fn insert(&self, transaction: &mut Self::Transaction, event: &Event) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Gets up to `limit` unpublished events, oldest first.
+    fn pending(&self, limit: usize) -> impl Future<Output = Result<Vec<StoredEvent>, Self::Error>> + Send;
+
+    /// Marks events as published, so they're no longer pending.
+    fn mark_published(&self, ids: &[u64]) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Creates the outbox table, if it doesn't exist yet. Does nothing by default, for stores
+    /// whose schema is managed elsewhere.
+    fn migrate(&self) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        async { Ok(()) }
+    }
+}
+
+/// The ways writing to or relaying from an outbox can fail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutboxError<E> {
+    /// The request has no open transaction to write to.
+    NoTransaction,
+
+    /// The store failed.
+    Store(E),
+
+    /// The publisher failed, with its error.
+    Publish(String),
+}
+
+impl<E: Display> Display for OutboxError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutboxError::NoTransaction => { write!(f, "the request has no open transaction") }
+            OutboxError::Store(error) => { write!(f, "the outbox store failed: {error}") }
+            OutboxError::Publish(error) => { write!(f, "publishing failed: {error}") }
+        }
+    }
+}
+
+/// A handle to an outbox, for writing events in requests' transactions and relaying them to a
+/// publisher.
+///
+/// Requests write to the transaction a `TxSeeder` begun for them, so the outbox should be used on
+/// requests the `TxSeeder` runs in a transaction.
+pub struct Outbox<P> {
+    database: Database<P>,
+    batch: usize,
+}
+
+impl<P> Clone for Outbox<P> {
+    fn clone(&self) -> Outbox<P> {
+        Outbox {
+            database: self.database.clone(),
+            batch: self.batch,
+        }
+    }
+}
+
+impl<P: OutboxStore> Outbox<P> {
+    /// Constructs a new `Outbox` over a database, relaying up to 100 events at a time.
+    pub fn new(database: Database<P>) -> Outbox<P> {
+        Outbox {
+            database,
+            batch: 100,
+        }
+    }
+
+    /// Sets how many events are relayed at a time.
+    pub fn batch(mut self, batch: usize) -> Outbox<P> {
+        self.batch = batch.max(1);
+        self
+    }
+
+    /// Creates the outbox table in the database, if it doesn't exist yet.
+    pub async fn migrate(&self) -> Result<(), OutboxError<P::Error>> {
+        self.database.pool().migrate().await.map_err(OutboxError::Store)
+    }
+
+    /// Writes an event to the outbox, in the request's transaction.
+    pub async fn write(&self, request: &HttpRequest<BoxBody>, event: Event) -> Result<(), OutboxError<P::Error>> {
+        let Some(transaction) = request.extensions().get::<Transaction<P::Transaction>>() else {
+            return Err(OutboxError::NoTransaction);
+        };
+        let mut transaction = transaction.lock().await;
+        let Some(transaction) = transaction.as_mut() else { return Err(OutboxError::NoTransaction); };
+
+        self.database.pool().insert(transaction, &event).await.map_err(OutboxError::Store)
+    }
+
+    /// Publishes the pending events, oldest first, and marks them as published. Returns how many
+    /// events were published.
+    ///
+    /// Relaying stops at the first event the publisher fails on, so events are published in the
+    /// order they were written. The events published before it are still marked.
+    pub async fn relay<Q: Publisher>(&self, publisher: &Q) -> Result<usize, OutboxError<P::Error>> {
+        let pool = self.database.pool();
+        let mut published = 0;
+
+        loop {
+            let pending = pool.pending(self.batch).await.map_err(OutboxError::Store)?;
+            let count = pending.len();
+            let mut ids = Vec::with_capacity(count);
+            let mut failure = None;
+
+            for StoredEvent { id, event } in pending {
+                match publisher.publish(event.header(OUTBOX_ID, id.to_string())).await {
+                    Ok(()) => { ids.push(id); }
+                    Err(error) => {
+                        failure = Some(OutboxError::Publish(error.to_string()));
+                        break;
+                    }
+                }
+            }
+
+            if !ids.is_empty() {
+                pool.mark_published(&ids).await.map_err(OutboxError::Store)?;
+                published += ids.len();
+            }
+
+            if let Some(failure) = failure {
+                return Err(failure);
+            }
+            if count < self.batch {
+                return Ok(published);
+            }
+        }
+    }
+
+    /// Registers an `outbox-relay` job with `jobs`, relaying pending events to the publisher every
+    /// `period`.
+    pub fn schedule<Q: Publisher>(&self, jobs: Jobs, publisher: Q, period: Duration) -> Jobs {
+        let outbox = self.clone();
+        let publisher = Arc::new(publisher);
+
+        jobs.job("outbox-relay", Schedule::Every(period), move || {
+            let outbox = outbox.clone();
+            let publisher = publisher.clone();
+            async move { outbox.relay(&*publisher).await.map(|_| ()) }
+        })
+    }
+}
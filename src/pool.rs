@@ -0,0 +1,251 @@
+//! Pooling of outbound connections.
+//!
+//! A `ConnectionPool` keeps idle connections to upstream hosts around for reuse, so that repeated
+//! requests to the same host skip the connection handshake. Connections are keyed by scheme, host,
+//! and port, and are resolved and connected through a `Resolver` when none are idle.
+
+use crate::dns::{connect, CachingResolver, Resolver, CONNECTION_ATTEMPT_DELAY};
+use std::collections::HashMap;
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Identifies the upstream a pooled connection is connected to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PoolKey {
+    /// The scheme of the upstream, such as `http`.
+    pub scheme: String,
+
+    /// The host name of the upstream.
+    pub host: String,
+
+    /// The port of the upstream.
+    pub port: u16,
+}
+
+impl PoolKey {
+    /// Constructs a new `PoolKey`.
+    pub fn new(scheme: impl Into<String>, host: impl Into<String>, port: u16) -> PoolKey {
+        PoolKey {
+            scheme: scheme.into(),
+            host: host.into(),
+            port,
+        }
+    }
+}
+
+/// The limits of a `ConnectionPool`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolConfig {
+    /// The most idle connections kept per host.
+    pub max_idle_per_host: usize,
+
+    /// How long a connection may sit idle before it's closed.
+    pub idle_timeout: Duration,
+
+    /// The most connections (idle or in use) open to a single host at once. Checkouts beyond this
+    /// wait for a connection to be released.
+    pub max_per_host: usize,
+}
+
+impl Default for PoolConfig {
+    fn default() -> PoolConfig {
+        PoolConfig {
+            max_idle_per_host: 8,
+            idle_timeout: Duration::from_secs(90),
+            max_per_host: 64,
+        }
+    }
+}
+
+/// A snapshot of a `ConnectionPool`'s counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolMetrics {
+    /// The number of checkouts served by an idle connection.
+    pub hits: u64,
+
+    /// The number of checkouts which opened a new connection.
+    pub misses: u64,
+
+    /// The number of idle connections closed because they timed out, or the idle limit was hit.
+    pub evictions: u64,
+
+    /// The number of connections currently idle in the pool.
+    pub idle: usize,
+}
+
+/// The idle connections and connection limit of a single host.
+struct Host {
+    idle: Vec<(TcpStream, Instant)>,
+    limit: Arc<Semaphore>,
+}
+
+/// The shared state of a `ConnectionPool`.
+struct Pool<R> {
+    resolver: R,
+    config: PoolConfig,
+    hosts: Mutex<HashMap<PoolKey, Host>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl<R> Pool<R> {
+    fn lock(&self) -> MutexGuard<'_, HashMap<PoolKey, Host>> {
+        self.hosts.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A pool of outbound TCP connections, keyed by scheme, host, and port.
+///
+/// `ConnectionPool`s are cheap to clone, and all clones share the same connections.
+pub struct ConnectionPool<R = CachingResolver> {
+    pool: Arc<Pool<R>>,
+}
+
+impl<R> Clone for ConnectionPool<R> {
+    fn clone(&self) -> ConnectionPool<R> {
+        ConnectionPool {
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+impl ConnectionPool<CachingResolver> {
+    /// Constructs a new `ConnectionPool`, resolving hosts with a `CachingResolver`.
+    pub fn new(config: PoolConfig) -> ConnectionPool<CachingResolver> {
+        ConnectionPool::with_resolver(CachingResolver::new(), config)
+    }
+}
+
+impl<R: Resolver> ConnectionPool<R> {
+    /// Constructs a new `ConnectionPool`, resolving hosts with the provided resolver.
+    pub fn with_resolver(resolver: R, config: PoolConfig) -> ConnectionPool<R> {
+        ConnectionPool {
+            pool: Arc::new(Pool {
+                resolver,
+                config: PoolConfig {
+                    max_per_host: config.max_per_host.max(1),
+                    ..config
+                },
+                hosts: Mutex::new(HashMap::new()),
+                hits: AtomicU64::new(0),
+                misses: AtomicU64::new(0),
+                evictions: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Checks out a connection to a host, reusing an idle connection if one is available.
+    ///
+    /// Waits for a connection to be released if the host is at its connection limit.
+    pub async fn checkout(&self, key: &PoolKey) -> io::Result<PooledConnection<R>> {
+        let limit = self.host(key, |host| host.limit.clone());
+        let permit = limit.acquire_owned().await.map_err(io::Error::other)?;
+
+        let now = Instant::now();
+        let idle = self.host(key, |host| {
+            let (fresh, expired): (Vec<_>, Vec<_>) = host.idle
+                .drain(..)
+                .partition(|(_, since)| now.duration_since(*since) < self.pool.config.idle_timeout);
+
+            self.pool.evictions.fetch_add(expired.len() as u64, Ordering::Relaxed);
+            host.idle = fresh;
+            host.idle.pop()
+        });
+
+        let stream = match idle {
+            Some((stream, _)) => {
+                self.pool.hits.fetch_add(1, Ordering::Relaxed);
+                stream
+            }
+            None => {
+                self.pool.misses.fetch_add(1, Ordering::Relaxed);
+
+                let resolved = self.pool.resolver.resolve(&key.host, key.port).await?;
+                let stream = connect(&resolved.addrs, CONNECTION_ATTEMPT_DELAY).await?;
+                stream.set_nodelay(true)?;
+                stream
+            }
+        };
+
+        Ok(PooledConnection {
+            stream: Some(stream),
+            key: key.clone(),
+            pool: self.pool.clone(),
+            _permit: permit,
+        })
+    }
+
+    /// Gets a snapshot of this pool's counters.
+    pub fn metrics(&self) -> PoolMetrics {
+        PoolMetrics {
+            hits: self.pool.hits.load(Ordering::Relaxed),
+            misses: self.pool.misses.load(Ordering::Relaxed),
+            evictions: self.pool.evictions.load(Ordering::Relaxed),
+            idle: self.pool.lock().values().map(|host| host.idle.len()).sum(),
+        }
+    }
+
+    /// Runs a closure against a host's state, creating the state if the host hasn't been seen.
+    fn host<T>(&self, key: &PoolKey, f: impl FnOnce(&mut Host) -> T) -> T {
+        let mut hosts = self.pool.lock();
+        let host = hosts.entry(key.clone()).or_insert_with(|| Host {
+            idle: Vec::new(),
+            limit: Arc::new(Semaphore::new(self.pool.config.max_per_host)),
+        });
+
+        f(host)
+    }
+}
+
+/// A connection checked out of a `ConnectionPool`.
+///
+/// Dropping the connection closes it. To return the connection to the pool for reuse, call
+/// `release` once the response has been fully read, and the connection is known to be reusable.
+pub struct PooledConnection<R> {
+    stream: Option<TcpStream>,
+    key: PoolKey,
+    pool: Arc<Pool<R>>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl<R> PooledConnection<R> {
+    /// Gets the key of the host this connection is connected to.
+    pub fn key(&self) -> &PoolKey {
+        &self.key
+    }
+
+    /// Returns this connection to the pool, to be reused by a later checkout.
+    pub fn release(mut self) {
+        let Some(stream) = self.stream.take() else { return; };
+
+        let mut hosts = self.pool.lock();
+        let Some(host) = hosts.get_mut(&self.key) else { return; };
+
+        if host.idle.len() >= self.pool.config.max_idle_per_host {
+            self.pool.evictions.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        host.idle.push((stream, Instant::now()));
+    }
+}
+
+impl<R> Deref for PooledConnection<R> {
+    type Target = TcpStream;
+
+    fn deref(&self) -> &TcpStream {
+        self.stream.as_ref().unwrap()
+    }
+}
+
+impl<R> DerefMut for PooledConnection<R> {
+    fn deref_mut(&mut self) -> &mut TcpStream {
+        self.stream.as_mut().unwrap()
+    }
+}
@@ -0,0 +1,309 @@
+//! Pagination, sorting, and filtering parameters, parsed from query strings.
+//!
+//! These follow the common conventions for listing endpoints:
+//!
+//! - `?page=2&per_page=50` for pages, counted from 1, parsed into a `Pagination`.
+//! - `?sort=-created_at,name` for sort orders, where a leading `-` sorts descending, parsed into a
+//!   `Sort`.
+//! - `?filter[status]=open,pending` for filters, parsed into a `Filter`.
+//!
+//! Sorts and filters are only accepted on the fields a listing allows, so that clients can't sort
+//! or filter on unindexed columns. `Pagination::links` builds the `Link` header pointing at the
+//! neighbouring pages:
+//!
+//! ```
+//! use grazie::http::Uri;
+//! use grazie::query::Pagination;
+//!
+//! let uri: Uri = "/orders?status=open&page=2".parse().unwrap();
+//! let pagination = Pagination::new(2, 20);
+//!
+//! assert_eq!(
+//!     pagination.links(&uri, 45).to_str().unwrap(),
+//!     "</orders?status=open&page=1&per_page=20>; rel=\"first\", \
+//!      </orders?status=open&page=1&per_page=20>; rel=\"prev\", \
+//!      </orders?status=open&page=3&per_page=20>; rel=\"next\", \
+//!      </orders?status=open&page=3&per_page=20>; rel=\"last\"",
+//! );
+//! ```
+
+use crate::core::seeder::{BoxBody, Rejection};
+use crate::http::header::HeaderValue;
+use crate::http::{HttpRequest, StatusCode, Uri};
+use crate::links::Links;
+use crate::util::{percent_encode, query_pairs};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+/// A query parameter which couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    /// The name of the parameter, such as `per_page`.
+    pub parameter: String,
+
+    /// A human-readable message describing the problem.
+    pub message: String,
+}
+
+impl QueryError {
+    fn new(parameter: impl Into<String>, message: impl Into<String>) -> QueryError {
+        QueryError {
+            parameter: parameter.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for QueryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.parameter, self.message)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+impl From<QueryError> for Rejection {
+    fn from(error: QueryError) -> Rejection {
+        Rejection::new(StatusCode::BAD_REQUEST, "invalid_query", error.to_string())
+    }
+}
+
+/// Gets a request's decoded query parameters.
+fn parameters(request: &HttpRequest<BoxBody>) -> impl Iterator<Item = (String, String)> + '_ {
+    query_pairs(request.uri().query().unwrap_or_default())
+}
+
+/// A page of a listing, counted from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pagination {
+    /// The page number, counted from 1.
+    pub page: u32,
+
+    /// The number of items per page.
+    pub per_page: u32,
+}
+
+impl Pagination {
+    /// Constructs a new `Pagination`.
+    pub fn new(page: u32, per_page: u32) -> Pagination {
+        Pagination {
+            page: page.max(1),
+            per_page: per_page.max(1),
+        }
+    }
+
+    /// Parses a request's `page` and `per_page` parameters, with 20 items per page by default, and
+    /// at most 100.
+    pub fn from_request(request: &HttpRequest<BoxBody>) -> Result<Pagination, QueryError> {
+        PaginationLimits::default().parse(request)
+    }
+
+    /// Gets the number of items to skip to reach this page.
+    pub fn offset(&self) -> u64 {
+        (self.page as u64 - 1) * self.per_page as u64
+    }
+
+    /// Gets the number of items on this page.
+    pub fn limit(&self) -> u64 {
+        self.per_page as u64
+    }
+
+    /// Gets the number of pages needed to list `total` items. There's always at least one page,
+    /// even if it's empty.
+    pub fn pages(&self, total: u64) -> u32 {
+        total.div_ceil(self.per_page as u64).clamp(1, u32::MAX as u64) as u32
+    }
+
+    /// Builds a `Link` header pointing at the first, previous, next, and last pages of a listing of
+    /// `total` items, relative to the request's URI. Other query parameters are kept as they are.
+    pub fn links(&self, uri: &Uri, total: u64) -> HeaderValue {
+        let last = self.pages(total);
+        let mut links = vec![(1, "first")];
+
+        if self.page > 1 {
+            links.push((self.page.min(last.saturating_add(1)) - 1, "prev"));
+        }
+        if self.page < last {
+            links.push((self.page + 1, "next"));
+        }
+        links.push((last, "last"));
+
+        links
+            .into_iter()
+            .fold(Links::new(), |links, (page, rel)| links.link(rel, self.page_uri(uri, page)))
+            .to_header()
+    }
+
+    /// Builds the URI of another page, keeping the URI's other query parameters.
+    fn page_uri(&self, uri: &Uri, page: u32) -> String {
+        let mut query: Vec<String> = query_pairs(uri.query().unwrap_or_default())
+            .filter(|(name, _)| name != "page" && name != "per_page")
+            .map(|(name, value)| format!("{}={}", percent_encode(&name), percent_encode(&value)))
+            .collect();
+
+        query.push(format!("page={page}"));
+        query.push(format!("per_page={}", self.per_page));
+        format!("{}?{}", uri.path(), query.join("&"))
+    }
+}
+
+/// The defaults and limits `Pagination` parameters are parsed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaginationLimits {
+    default_per_page: u32,
+    max_per_page: u32,
+}
+
+impl PaginationLimits {
+    /// Constructs a new `PaginationLimits`, with 20 items per page by default, and at most 100.
+    pub fn new() -> PaginationLimits {
+        PaginationLimits {
+            default_per_page: 20,
+            max_per_page: 100,
+        }
+    }
+
+    /// Sets the number of items per page when a request doesn't ask for any.
+    pub fn default_per_page(mut self, default_per_page: u32) -> PaginationLimits {
+        self.default_per_page = default_per_page.max(1);
+        self
+    }
+
+    /// Sets the most items a request can ask for per page. Larger requests are given this many.
+    pub fn max_per_page(mut self, max_per_page: u32) -> PaginationLimits {
+        self.max_per_page = max_per_page.max(1);
+        self
+    }
+
+    /// Parses a request's `page` and `per_page` parameters.
+    pub fn parse(&self, request: &HttpRequest<BoxBody>) -> Result<Pagination, QueryError> {
+        let mut page = 1;
+        let mut per_page = self.default_per_page;
+
+        for (name, value) in parameters(request) {
+            let number = || {
+                let error = || QueryError::new(name.clone(), "must be a positive whole number");
+                value.parse::<u32>().ok().filter(|number| *number > 0).ok_or_else(error)
+            };
+
+            match name.as_str() {
+                "page" => { page = number()?; }
+                "per_page" => { per_page = number()?; }
+                _ => {}
+            }
+        }
+
+        Ok(Pagination {
+            page,
+            per_page: per_page.min(self.max_per_page),
+        })
+    }
+}
+
+impl Default for PaginationLimits {
+    fn default() -> PaginationLimits {
+        PaginationLimits::new()
+    }
+}
+
+/// The direction a field is sorted in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Smallest first.
+    Ascending,
+
+    /// Largest first.
+    Descending,
+}
+
+/// A sort order, parsed from a `sort` parameter such as `-created_at,name`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sort {
+    /// The fields to sort by, most significant first.
+    pub fields: Vec<(String, Direction)>,
+}
+
+impl Sort {
+    /// Parses a request's `sort` parameter, only accepting the allowed fields. Requests without one
+    /// get an empty `Sort`.
+    pub fn from_request(
+        request: &HttpRequest<BoxBody>,
+        allowed: &[&str],
+    ) -> Result<Sort, QueryError> {
+        let Some((_, value)) = parameters(request).find(|(name, _)| name == "sort") else {
+            return Ok(Sort::default());
+        };
+        let mut fields = Vec::new();
+
+        for field in value.split(',').filter(|field| !field.is_empty()) {
+            let (field, direction) = match field.strip_prefix('-') {
+                Some(field) => { (field, Direction::Descending) }
+                None => { (field, Direction::Ascending) }
+            };
+
+            if !allowed.contains(&field) {
+                return Err(QueryError::new("sort", format!("can't sort by `{field}`")));
+            }
+
+            fields.push((field.to_owned(), direction));
+        }
+
+        Ok(Sort { fields })
+    }
+
+    /// Checks whether no fields were asked for.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+/// The filters of a listing, parsed from `filter[<field>]` parameters.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filter {
+    fields: BTreeMap<String, String>,
+}
+
+impl Filter {
+    /// Parses a request's `filter[<field>]` parameters, only accepting the allowed fields.
+    pub fn from_request(
+        request: &HttpRequest<BoxBody>,
+        allowed: &[&str],
+    ) -> Result<Filter, QueryError> {
+        let mut fields = BTreeMap::new();
+
+        for (name, value) in parameters(request) {
+            let field = name.strip_prefix("filter[").and_then(|field| field.strip_suffix(']'));
+            let Some(field) = field else { continue; };
+
+            if !allowed.contains(&field) {
+                return Err(QueryError::new(name.clone(), format!("can't filter by `{field}`")));
+            }
+
+            fields.insert(field.to_owned(), value);
+        }
+
+        Ok(Filter { fields })
+    }
+
+    /// Gets the value a field was filtered by.
+    pub fn get(&self, field: &str) -> Option<&str> {
+        self.fields.get(field).map(String::as_str)
+    }
+
+    /// Gets the comma-separated values a field was filtered by, such as `open` and `pending` from
+    /// `filter[status]=open,pending`.
+    pub fn values(&self, field: &str) -> Vec<&str> {
+        let values = self.get(field).unwrap_or_default().split(',');
+        values.filter(|value| !value.is_empty()).collect()
+    }
+
+    /// Gets every filtered field and its value.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fields.iter().map(|(field, value)| (field.as_str(), value.as_str()))
+    }
+
+    /// Checks whether no filters were asked for.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
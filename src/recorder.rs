@@ -0,0 +1,460 @@
+//! Recording of full requests and responses, for replaying them later.
+//!
+//! A `Recorder` captures every request it sees (and the responses passed to
+//! `Recorder::record_response`) into a file, with secrets redacted. Recordings can be fed back
+//! through a seeder chain with `grazie::test::replay`, to turn real traffic into regression tests.
+//!
+//! Each message is written as a marker line (`### request <sequence> <body length>`, or
+//! `### response ...`), followed by its request or status line, its headers, a blank line, and its
+//! body:
+//!
+//! ```text
+//! ### request 0 14
+//! POST /users
+//! content-type: application/json
+//! authorization: REDACTED
+//!
+//! {"name":"Ada"}
+//! ### response 0 0
+//! 201
+//! location: /users/1
+//!
+//! ```
+
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::header::{Entry, HeaderMap, HeaderName, HeaderValue};
+use crate::http::header::{AUTHORIZATION, CONTENT_ENCODING, CONTENT_TYPE, COOKIE};
+use crate::http::header::{PROXY_AUTHORIZATION, SET_COOKIE};
+use crate::http::{HttpRequest, HttpResponse, Method, StatusCode, Uri};
+use crate::util::percent_decode;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+/// The value redacted secrets are replaced with.
+pub const REDACTED: &str = "REDACTED";
+
+/// A recorded request, along with its response if one was recorded.
+#[derive(Debug, Clone)]
+pub struct Recording {
+    /// The sequence number of the request, in the order it was recorded.
+    pub sequence: u64,
+
+    /// The request's method.
+    pub method: Method,
+
+    /// The request's URI.
+    pub uri: Uri,
+
+    /// The request's headers.
+    pub headers: HeaderMap,
+
+    /// The request's body.
+    pub body: Box<[u8]>,
+
+    /// The response to the request, if one was recorded.
+    pub response: Option<RecordedResponse>,
+}
+
+impl Recording {
+    /// Rebuilds the recorded request.
+    pub fn request(&self) -> HttpRequest<BoxBody> {
+        let mut request = HttpRequest::new(BoxBody::new(self.body.clone()));
+        *request.method_mut() = self.method.clone();
+        *request.uri_mut() = self.uri.clone();
+        *request.headers_mut() = self.headers.clone();
+
+        request
+    }
+}
+
+/// A recorded response.
+#[derive(Debug, Clone)]
+pub struct RecordedResponse {
+    /// The response's status.
+    pub status: StatusCode,
+
+    /// The response's headers.
+    pub headers: HeaderMap,
+
+    /// The response's body.
+    pub body: Box<[u8]>,
+}
+
+/// A single recorded message, queued for writing.
+struct Message {
+    sequence: u64,
+    start: String,
+    headers: HeaderMap,
+    body: Box<[u8]>,
+    response: bool,
+}
+
+impl Message {
+    fn encode(&self) -> Vec<u8> {
+        let kind = match self.response {
+            true => { "response" }
+            false => { "request" }
+        };
+
+        let mut out = format!("### {kind} {} {}\n", self.sequence, self.body.len()).into_bytes();
+        out.extend_from_slice(self.start.as_bytes());
+        out.push(b'\n');
+
+        for (name, value) in &self.headers {
+            out.extend_from_slice(name.as_str().as_bytes());
+            out.extend_from_slice(b": ");
+            out.extend_from_slice(value.as_bytes());
+            out.push(b'\n');
+        }
+
+        out.push(b'\n');
+        out.extend_from_slice(&self.body);
+        out.push(b'\n');
+        out
+    }
+}
+
+/// Marks a request which was recorded, so that its response is recorded with the same sequence.
+#[derive(Debug, Clone, Copy)]
+struct Recorded(u64);
+
+/// Decides whether the bodies of a request and its response are redacted whole.
+type BodyFilter = Box<dyn Fn(&HttpRequest<BoxBody>) -> bool + Send + Sync>;
+
+/// A `Seeder` which records every request to a file, with secrets redacted.
+///
+/// The `Authorization`, `Proxy-Authorization`, `Cookie`, and `Set-Cookie` headers are redacted by
+/// default. Bodies are recorded as they are, unless fields or whole bodies are redacted through
+/// `redact_field` and `redact_body`. Messages are queued for a background task which writes them,
+/// and dropped when the queue is full. Requests are always accepted.
+pub struct Recorder {
+    sender: mpsc::Sender<Message>,
+    headers: Vec<HeaderName>,
+    query: Vec<String>,
+    fields: Vec<String>,
+    body: BodyFilter,
+    sequence: AtomicU64,
+    dropped: Arc<AtomicU64>,
+    failed: Arc<AtomicU64>,
+}
+
+impl Recorder {
+    /// Opens a file to record to, creating it if it doesn't exist, and spawns the writer task.
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<Recorder> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path).await?;
+        let (sender, mut receiver) = mpsc::channel::<Message>(1024);
+        let failed = Arc::new(AtomicU64::new(0));
+
+        let writer_failed = failed.clone();
+        tokio::spawn(async move {
+            while let Some(message) = receiver.recv().await {
+                if file.write_all(&message.encode()).await.is_err() {
+                    writer_failed.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        });
+
+        Ok(Recorder {
+            sender,
+            headers: vec![AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE, SET_COOKIE],
+            query: Vec::new(),
+            fields: Vec::new(),
+            body: Box::new(|_| false),
+            sequence: AtomicU64::new(0),
+            dropped: Arc::new(AtomicU64::new(0)),
+            failed,
+        })
+    }
+
+    /// Redacts a header, in both requests and responses.
+    pub fn redact_header(mut self, name: HeaderName) -> Recorder {
+        self.headers.push(name);
+        self
+    }
+
+    /// Redacts a query parameter, such as an API key, from request URIs.
+    pub fn redact_query(mut self, name: impl Into<String>) -> Recorder {
+        self.query.push(name.into());
+        self
+    }
+
+    /// Redacts a field, such as `password`, from form-encoded and JSON bodies, in both requests
+    /// and responses. JSON fields are redacted at any depth.
+    ///
+    /// JSON bodies are only parsed with the `serde_json` feature. Without it, and for bodies which
+    /// are compressed or malformed, a body which could hold a redacted field is redacted whole.
+    pub fn redact_field(mut self, name: impl Into<String>) -> Recorder {
+        self.fields.push(name.into());
+        self
+    }
+
+    /// Redacts the whole bodies of requests for which `redact` returns `true`, along with those of
+    /// their responses, such as for a login route.
+    pub fn redact_body<F>(mut self, redact: F) -> Recorder
+    where
+        F: Fn(&HttpRequest<BoxBody>) -> bool + Send + Sync + 'static,
+    {
+        self.body = Box::new(redact);
+        self
+    }
+
+    /// Gets the number of messages dropped because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Gets the number of messages which couldn't be written to the file.
+    pub fn failed(&self) -> u64 {
+        self.failed.load(Ordering::Relaxed)
+    }
+
+    /// Records the response to a recorded request.
+    pub fn record_response(
+        &self,
+        request: &HttpRequest<BoxBody>,
+        response: &HttpResponse<BoxBody>,
+    ) {
+        let Some(Recorded(sequence)) = request.extensions().get::<Recorded>().copied() else {
+            return;
+        };
+
+        self.enqueue(Message {
+            sequence,
+            start: response.status().as_u16().to_string(),
+            headers: self.redact_headers(response.headers()),
+            body: self.redacted_body(request, response.headers(), response.body().raw_bytes()),
+            response: true,
+        });
+    }
+
+    fn redact_headers(&self, headers: &HeaderMap) -> HeaderMap {
+        let mut headers = headers.clone();
+
+        for name in &self.headers {
+            if let Entry::Occupied(mut entry) = headers.entry(name) {
+                for value in entry.iter_mut() {
+                    *value = HeaderValue::from_static(REDACTED);
+                }
+            }
+        }
+
+        headers
+    }
+
+    fn redact_uri(&self, uri: &Uri) -> String {
+        match uri.query().filter(|_| !self.query.is_empty()) {
+            Some(query) => { format!("{}?{}", uri.path(), redact_pairs(query, &self.query)) }
+            None => { uri.to_string() }
+        }
+    }
+
+    /// Redacts the body of a request or its response, described by `headers`.
+    fn redacted_body(
+        &self,
+        request: &HttpRequest<BoxBody>,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Box<[u8]> {
+        if (self.body)(request) {
+            return REDACTED.as_bytes().into();
+        }
+
+        if self.fields.is_empty() || body.is_empty() {
+            return body.into();
+        }
+
+        let media_type = headers
+            .get(CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .and_then(|content_type| content_type.split(';').next())
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+
+        let form = media_type == "application/x-www-form-urlencoded";
+        let json = media_type == "application/json" || media_type.ends_with("+json");
+        if !form && !json {
+            return body.into();
+        }
+
+        let encoded = headers.contains_key(CONTENT_ENCODING);
+        let redacted = match std::str::from_utf8(body).ok().filter(|_| !encoded) {
+            Some(text) if form => { Some(redact_pairs(text, &self.fields).into_bytes()) }
+            Some(text) => { redact_json(text, &self.fields) }
+            None => { None }
+        };
+
+        redacted.unwrap_or_else(|| REDACTED.as_bytes().to_vec()).into()
+    }
+
+    fn enqueue(&self, message: Message) {
+        if self.sender.try_send(message).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Seeder for Recorder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+
+        self.enqueue(Message {
+            sequence,
+            start: format!("{} {}", request.method(), self.redact_uri(request.uri())),
+            headers: self.redact_headers(request.headers()),
+            body: self.redacted_body(request, request.headers(), request.body().raw_bytes()),
+            response: false,
+        });
+
+        request.extensions_mut().insert(Recorded(sequence));
+
+        Guard::Accessible(request)
+    }
+}
+
+/// Redacts the values of the named pairs of a query string or form-encoded body. Names are
+/// compared once decoded, so that an escaped name can't slip past.
+fn redact_pairs(pairs: &str, names: &[String]) -> String {
+    let pairs: Vec<String> = pairs
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, _)) if percent_decode(name).is_some_and(|name| names.contains(&name)) => {
+                format!("{name}={REDACTED}")
+            }
+            _ => { pair.to_owned() }
+        })
+        .collect();
+
+    pairs.join("&")
+}
+
+/// Redacts the named members of a JSON document, at any depth. Returns `None` if the document is
+/// malformed.
+#[cfg(feature = "serde_json")]
+fn redact_json(text: &str, names: &[String]) -> Option<Vec<u8>> {
+    fn redact(value: &mut serde_json::Value, names: &[String]) {
+        match value {
+            serde_json::Value::Object(object) => {
+                for (name, member) in object.iter_mut() {
+                    match names.contains(name) {
+                        true => { *member = serde_json::Value::String(REDACTED.to_owned()); }
+                        false => { redact(member, names); }
+                    }
+                }
+            }
+            serde_json::Value::Array(array) => {
+                array.iter_mut().for_each(|element| redact(element, names));
+            }
+            _ => {}
+        }
+    }
+
+    let mut value = serde_json::from_str(text).ok()?;
+    redact(&mut value, names);
+
+    serde_json::to_vec(&value).ok()
+}
+
+/// Without the `serde_json` feature, JSON documents can't be parsed, so they're redacted whole.
+#[cfg(not(feature = "serde_json"))]
+fn redact_json(_text: &str, _names: &[String]) -> Option<Vec<u8>> {
+    None
+}
+
+/// Parses a recording file's contents, pairing responses up with their requests.
+pub fn parse(contents: &[u8]) -> io::Result<Vec<Recording>> {
+    let malformed = |what: &str| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("malformed recording: {what}"))
+    };
+    let mut recordings: Vec<Recording> = Vec::new();
+    let mut rest = contents;
+
+    while !rest.is_empty() {
+        let (marker, after) = split_line(rest).ok_or_else(|| malformed("missing marker"))?;
+        let marker = marker.strip_prefix("### ").ok_or_else(|| malformed("missing marker"))?;
+        let mut marker = marker.split(' ');
+        let (kind, sequence, length) = (marker.next(), marker.next(), marker.next());
+        let (Some(kind), Some(sequence), Some(length)) = (kind, sequence, length) else {
+            return Err(malformed("incomplete marker"));
+        };
+        let sequence: u64 = sequence.parse().map_err(|_| malformed("bad sequence"))?;
+        let length: usize = length.parse().map_err(|_| malformed("bad body length"))?;
+
+        let (start, mut after) = split_line(after).ok_or_else(|| malformed("missing start line"))?;
+        let mut headers = HeaderMap::new();
+
+        loop {
+            let (line, next) = split_line(after).ok_or_else(|| malformed("unterminated headers"))?;
+            after = next;
+
+            if line.is_empty() {
+                break;
+            }
+
+            let (name, value) = line.split_once(": ").ok_or_else(|| malformed("bad header"))?;
+            let name = HeaderName::try_from(name).map_err(|_| malformed("bad header name"))?;
+            let value = HeaderValue::try_from(value).map_err(|_| malformed("bad header value"))?;
+            headers.append(name, value);
+        }
+
+        let body = after.get(..length).ok_or_else(|| malformed("truncated body"))?;
+        let body: Box<[u8]> = body.into();
+        rest = after.get(length + 1..).unwrap_or_default();
+
+        match kind {
+            "request" => {
+                let (method, uri) =
+                    start.split_once(' ').ok_or_else(|| malformed("bad request line"))?;
+
+                recordings.push(Recording {
+                    sequence,
+                    method: method.parse().map_err(|_| malformed("bad method"))?,
+                    uri: uri.parse().map_err(|_| malformed("bad uri"))?,
+                    headers,
+                    body,
+                    response: None,
+                });
+            }
+            "response" => {
+                let status = start.parse::<u16>().ok();
+                let status = status.and_then(|status| StatusCode::from_u16(status).ok());
+                let status = status.ok_or_else(|| malformed("bad status"))?;
+
+                // Responses are usually written shortly after their request, so search from the
+                // end.
+                let recording = recordings
+                    .iter_mut()
+                    .rev()
+                    .find(|recording| recording.sequence == sequence);
+
+                if let Some(recording) = recording {
+                    recording.response = Some(RecordedResponse { status, headers, body });
+                }
+            }
+            _ => { return Err(malformed("unknown message kind")); }
+        }
+    }
+
+    Ok(recordings)
+}
+
+/// Splits the first line off some bytes, as UTF-8.
+fn split_line(bytes: &[u8]) -> Option<(&str, &[u8])> {
+    let end = bytes.iter().position(|byte| *byte == b'\n')?;
+    let line = std::str::from_utf8(&bytes[..end]).ok()?;
+
+    Some((line, &bytes[end + 1..]))
+}
@@ -0,0 +1,354 @@
+//! Redis-backed stores, for sharing sessions, idempotency keys, tenant quotas and rate limits
+//! between instances.
+//!
+//! Every store runs over a `ConnectionManager`, which multiplexes commands from every clone over
+//! a single connection and reconnects when it's lost. `connect` opens one with connection and
+//! response timeouts:
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), grazie::redis::RedisError> {
+//! use grazie::redis::{connect, RedisIdempotencyStore};
+//! use grazie::seeders::IdempotencySeeder;
+//! use std::time::Duration;
+//!
+//! let connection = connect("redis://127.0.0.1/", Duration::from_secs(1)).await?;
+//! let seeder = IdempotencySeeder::new(RedisIdempotencyStore::new(connection));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Keys are namespaced by a prefix per store, which defaults to `grazie:<store>:`. Sessions need
+//! Redis 6.2 or later, for `GETEX`.
+//!
+//! Part of the `redis` feature.
+
+use crate::auth::session::{cookie, is_cookie_name, set_cookie, token};
+use crate::auth::session::{SessionStore, DEFAULT_COOKIE};
+use crate::core::seeder::BoxBody;
+use crate::http::header::HeaderValue;
+use crate::http::HttpRequest;
+use crate::seeders::idempotency::{IdempotencyStore, Lookup, StoredResponse};
+use crate::seeders::tenant::{Quota, QuotaStore, RateLimitStore};
+use ::redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use ::redis::{Client, ErrorKind, RedisResult};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+pub use ::redis::RedisError;
+
+/// Connects to a Redis server, timing out connection attempts and commands after `timeout`.
+pub async fn connect(url: &str, timeout: Duration) -> RedisResult<ConnectionManager> {
+    let config = ConnectionManagerConfig::new().set_connection_timeout(timeout).set_response_timeout(timeout);
+    ConnectionManager::new_with_config(Client::open(url)?, config).await
+}
+
+/// Converts a duration to the milliseconds of a `PX` argument, which must be at least 1.
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis().clamp(1, u64::MAX as u128) as u64
+}
+
+/// A `SessionStore` in Redis, sharing sessions between instances.
+///
+/// Values are stored as JSON, and sessions expire in Redis once they've gone unused for the idle
+/// timeout. Sessions whose value no longer deserializes are treated as missing.
+pub struct RedisSessionStore<T> {
+    connection: ConnectionManager,
+    prefix: String,
+    idle_timeout: Duration,
+    cookie: String,
+    secure: bool,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<T> RedisSessionStore<T> {
+    /// Constructs a new `RedisSessionStore`, expiring sessions after `idle_timeout` without use.
+    pub fn new(connection: ConnectionManager, idle_timeout: Duration) -> RedisSessionStore<T> {
+        RedisSessionStore {
+            connection,
+            prefix: "grazie:session:".to_owned(),
+            idle_timeout,
+            cookie: DEFAULT_COOKIE.to_owned(),
+            secure: true,
+            value: PhantomData,
+        }
+    }
+
+    /// Sets the prefix of session keys. Defaults to `grazie:session:`.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> RedisSessionStore<T> {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Sets the name of the session cookie. Defaults to `DEFAULT_COOKIE`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name isn't a valid cookie name.
+    pub fn cookie(mut self, name: impl Into<String>) -> RedisSessionStore<T> {
+        self.cookie = name.into();
+        assert!(is_cookie_name(&self.cookie), "A session cookie must have a valid name!");
+        self
+    }
+
+    /// Sets whether the session cookie is only sent over HTTPS. Defaults to `true`, and should
+    /// only be disabled for local development.
+    pub fn secure(mut self, secure: bool) -> RedisSessionStore<T> {
+        self.secure = secure;
+        self
+    }
+}
+
+impl<T: Serialize + DeserializeOwned + Send> SessionStore<T> for RedisSessionStore<T> {
+    type Error = RedisError;
+
+    async fn create(&self, value: T) -> RedisResult<HeaderValue> {
+        let id = token();
+        let value = serde_json::to_vec(&value).map_err(|error| {
+            RedisError::from((ErrorKind::TypeError, "the session doesn't serialize", error.to_string()))
+        })?;
+
+        ::redis::cmd("SET")
+            .arg(format!("{}{id}", self.prefix))
+            .arg(value)
+            .arg("PX")
+            .arg(millis(self.idle_timeout))
+            .query_async::<()>(&mut self.connection.clone())
+            .await?;
+
+        Ok(set_cookie(&self.cookie, &id, "/", self.idle_timeout, self.secure))
+    }
+
+    async fn get(&self, request: &HttpRequest<BoxBody>) -> RedisResult<Option<T>> {
+        let Some(id) = cookie(request, &self.cookie) else { return Ok(None); };
+
+        let value: Option<Vec<u8>> = ::redis::cmd("GETEX")
+            .arg(format!("{}{id}", self.prefix))
+            .arg("PX")
+            .arg(millis(self.idle_timeout))
+            .query_async(&mut self.connection.clone())
+            .await?;
+
+        Ok(value.and_then(|value| serde_json::from_slice(&value).ok()))
+    }
+
+    async fn destroy(&self, request: &HttpRequest<BoxBody>) -> RedisResult<HeaderValue> {
+        if let Some(id) = cookie(request, &self.cookie) {
+            ::redis::cmd("DEL")
+                .arg(format!("{}{id}", self.prefix))
+                .query_async::<()>(&mut self.connection.clone())
+                .await?;
+        }
+
+        Ok(set_cookie(&self.cookie, "", "/", Duration::ZERO, self.secure))
+    }
+
+    fn is_secure(&self) -> bool {
+        self.secure
+    }
+}
+
+/// The value of an in-flight idempotency key, which never decodes as a `StoredResponse`.
+const IN_FLIGHT: &[u8] = b"";
+
+/// An `IdempotencyStore` in Redis, for deployments with more than one instance.
+///
+/// `begin` is atomic, through `SET NX`. If Redis can't be reached, keys are reported as in flight,
+/// so that requests are refused rather than risk being handled twice.
+pub struct RedisIdempotencyStore {
+    connection: ConnectionManager,
+    prefix: String,
+}
+
+impl RedisIdempotencyStore {
+    /// Constructs a new `RedisIdempotencyStore`.
+    pub fn new(connection: ConnectionManager) -> RedisIdempotencyStore {
+        RedisIdempotencyStore {
+            connection,
+            prefix: "grazie:idempotency:".to_owned(),
+        }
+    }
+
+    /// Sets the prefix of idempotency keys. Defaults to `grazie:idempotency:`.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> RedisIdempotencyStore {
+        self.prefix = prefix.into();
+        self
+    }
+}
+
+impl IdempotencyStore for RedisIdempotencyStore {
+    async fn begin(&self, key: &str, ttl: Duration) -> Lookup {
+        let mut connection = self.connection.clone();
+        let key = format!("{}{key}", self.prefix);
+
+        let set: RedisResult<Option<String>> = ::redis::cmd("SET")
+            .arg(&key)
+            .arg(IN_FLIGHT)
+            .arg("NX")
+            .arg("PX")
+            .arg(millis(ttl))
+            .query_async(&mut connection)
+            .await;
+
+        match set {
+            Ok(Some(_)) => { return Lookup::New; }
+            Ok(None) => {}
+            Err(_) => { return Lookup::InFlight; }
+        }
+
+        match ::redis::cmd("GET").arg(&key).query_async::<Option<Vec<u8>>>(&mut connection).await {
+            Ok(Some(stored)) => {
+                StoredResponse::from_bytes(&stored).map(Lookup::Completed).unwrap_or(Lookup::InFlight)
+            }
+            _ => { Lookup::InFlight }
+        }
+    }
+
+    async fn complete(&self, key: &str, response: StoredResponse, ttl: Duration) {
+        let _ = ::redis::cmd("SET")
+            .arg(format!("{}{key}", self.prefix))
+            .arg(response.to_bytes())
+            .arg("PX")
+            .arg(millis(ttl))
+            .query_async::<()>(&mut self.connection.clone())
+            .await;
+    }
+
+    async fn abandon(&self, key: &str) {
+        let _ = ::redis::cmd("DEL")
+            .arg(format!("{}{key}", self.prefix))
+            .query_async::<()>(&mut self.connection.clone())
+            .await;
+    }
+}
+
+/// Encodes a quota as the fields of its Redis hash. Every field is written, empty when its limit
+/// isn't set, so that a tenant without limits is still known.
+pub(crate) fn quota_fields(quota: &Quota) -> [(&'static str, String); 4] {
+    let limit = |limit: Option<u128>| limit.map(|limit| limit.to_string()).unwrap_or_default();
+
+    [
+        ("requests", limit(quota.rate.map(|(requests, _)| requests as u128))),
+        ("window_ms", limit(quota.rate.map(|(_, window)| window.as_millis()))),
+        ("concurrency", limit(quota.concurrency.map(|concurrency| concurrency as u128))),
+        ("max_body", limit(quota.max_body.map(|max_body| max_body as u128))),
+    ]
+}
+
+/// Decodes a quota from the fields of its Redis hash, returning `None` for an unknown tenant.
+pub(crate) fn quota_from_fields(fields: &HashMap<String, String>) -> Option<Quota> {
+    if fields.is_empty() {
+        return None;
+    }
+
+    let limit = |name: &str| fields.get(name).and_then(|value| value.parse::<u64>().ok());
+    let rate = limit("requests").zip(limit("window_ms"));
+
+    Some(Quota {
+        rate: rate.map(|(requests, window)| (requests as u32, Duration::from_millis(window))),
+        concurrency: limit("concurrency").map(|concurrency| concurrency as usize),
+        max_body: limit("max_body").map(|max_body| max_body as usize),
+    })
+}
+
+/// A `QuotaStore` in Redis, with each tenant's quota in a hash.
+///
+/// Tenants are unknown while Redis can't be reached, so their requests are refused.
+pub struct RedisQuotaStore {
+    connection: ConnectionManager,
+    prefix: String,
+}
+
+impl RedisQuotaStore {
+    /// Constructs a new `RedisQuotaStore`.
+    pub fn new(connection: ConnectionManager) -> RedisQuotaStore {
+        RedisQuotaStore {
+            connection,
+            prefix: "grazie:quota:".to_owned(),
+        }
+    }
+
+    /// Sets the prefix of quota keys. Defaults to `grazie:quota:`.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> RedisQuotaStore {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Sets a tenant's quota.
+    pub async fn set(&self, tenant: &str, quota: Quota) -> RedisResult<()> {
+        ::redis::cmd("HSET")
+            .arg(format!("{}{tenant}", self.prefix))
+            .arg(&quota_fields(&quota)[..])
+            .query_async(&mut self.connection.clone())
+            .await
+    }
+
+    /// Removes a tenant's quota.
+    pub async fn remove(&self, tenant: &str) -> RedisResult<()> {
+        ::redis::cmd("DEL")
+            .arg(format!("{}{tenant}", self.prefix))
+            .query_async(&mut self.connection.clone())
+            .await
+    }
+}
+
+impl QuotaStore for RedisQuotaStore {
+    async fn quota(&self, tenant: &str) -> Option<Quota> {
+        let fields: HashMap<String, String> = ::redis::cmd("HGETALL")
+            .arg(format!("{}{tenant}", self.prefix))
+            .query_async(&mut self.connection.clone())
+            .await
+            .ok()?;
+
+        quota_from_fields(&fields)
+    }
+}
+
+/// A `RateLimitStore` in Redis, counting fixed windows across every instance, such as for a
+/// `TenantSeeder`'s rates.
+///
+/// Requests are refused for the whole window while Redis can't be reached.
+pub struct RedisRateLimitStore {
+    connection: ConnectionManager,
+    prefix: String,
+}
+
+impl RedisRateLimitStore {
+    /// Constructs a new `RedisRateLimitStore`.
+    pub fn new(connection: ConnectionManager) -> RedisRateLimitStore {
+        RedisRateLimitStore {
+            connection,
+            prefix: "grazie:rate:".to_owned(),
+        }
+    }
+
+    /// Sets the prefix of counter keys. Defaults to `grazie:rate:`.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> RedisRateLimitStore {
+        self.prefix = prefix.into();
+        self
+    }
+}
+
+impl RateLimitStore for RedisRateLimitStore {
+    async fn hit(&self, key: &str, requests: u32, window: Duration) -> Option<Duration> {
+        let key = format!("{}{key}", self.prefix);
+
+        let counted: RedisResult<(Option<String>, u64, i64)> = ::redis::pipe()
+            .atomic()
+            .cmd("SET").arg(&key).arg(0).arg("NX").arg("PX").arg(millis(window))
+            .cmd("INCR").arg(&key)
+            .cmd("PTTL").arg(&key)
+            .query_async(&mut self.connection.clone())
+            .await;
+
+        match counted {
+            Ok((_, count, ttl)) if count > requests as u64 => {
+                Some(Duration::from_millis(ttl.max(0) as u64))
+            }
+            Ok(_) => { None }
+            Err(_) => { Some(window) }
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Retries for outbound requests, with per-try timeouts, backoff with jitter, and a retry budget.
+//!
+//! A `RetryPolicy` runs a request, and tries it again when it fails, for as long as the policy
+//! allows:
+//!
+//! ```
+//! use grazie::http::Method;
+//! use grazie::retry::RetryPolicy;
+//! use std::time::Duration;
+//!
+//! # tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap().block_on(async {
+//! let policy = RetryPolicy::new().try_timeout(Duration::from_secs(2));
+//!
+//! let response = policy.run(&Method::GET, |attempt| async move {
+//!     match attempt {
+//!         1 => { Err("connection reset") }
+//!         _ => { Ok("200 OK") }
+//!     }
+//! }).await;
+//!
+//! assert_eq!(response, Ok("200 OK"));
+//! # });
+//! ```
+//!
+//! There's no outbound client in the crate, so requests are sent by the caller's closure. Requests
+//! to an `UpstreamPool` are retried through `UpstreamPool::retry`, which sends each try to a
+//! healthy upstream.
+
+use crate::http::Method;
+use crate::util::random_u64;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// The ways a request run through a `RetryPolicy` can fail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RetryError<E> {
+    /// Every upstream in the pool the request was sent through is ejected.
+    NoUpstream,
+
+    /// The last try took longer than the policy's per-try timeout.
+    TimedOut,
+
+    /// The last try failed, with its error.
+    Failed(E),
+}
+
+impl<E: Display> Display for RetryError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RetryError::NoUpstream => { write!(f, "every upstream is ejected") }
+            RetryError::TimedOut => { write!(f, "the request timed out") }
+            RetryError::Failed(error) => { write!(f, "the request failed: {error}") }
+        }
+    }
+}
+
+/// The methods RFC 9110 defines as idempotent, which a `RetryPolicy` retries by default.
+const IDEMPOTENT: [Method; 6] =
+    [Method::GET, Method::HEAD, Method::OPTIONS, Method::TRACE, Method::PUT, Method::DELETE];
+
+/// A policy for trying failed outbound requests again, to ride out transient failures.
+///
+/// By default, a request is tried up to 3 times if its method is idempotent, backing off
+/// exponentially from 25 milliseconds up to a second between tries, with full jitter. Retries are
+/// drawn from a budget, so that a struggling upstream isn't buried under them: every request adds
+/// a fifth of a retry to the budget, which holds up to 10.
+///
+/// `RetryPolicy`s are cheap to clone, and all clones share the same budget.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    attempts: u32,
+    any_method: bool,
+    try_timeout: Option<Duration>,
+    base: Duration,
+    max_backoff: Duration,
+    ratio: f64,
+    reserve: f64,
+    budget: Arc<Mutex<f64>>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy::new()
+    }
+}
+
+impl RetryPolicy {
+    /// Constructs a new `RetryPolicy`, with a full budget and no per-try timeout.
+    pub fn new() -> RetryPolicy {
+        RetryPolicy {
+            attempts: 3,
+            any_method: false,
+            try_timeout: None,
+            base: Duration::from_millis(25),
+            max_backoff: Duration::from_secs(1),
+            ratio: 0.2,
+            reserve: 10.0,
+            budget: Arc::new(Mutex::new(10.0)),
+        }
+    }
+
+    /// Sets how many times a request is tried, including the first try.
+    pub fn attempts(mut self, attempts: u32) -> RetryPolicy {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    /// Retries requests whatever their method. Only use this if the upstream deduplicates
+    /// non-idempotent requests, such as by an `Idempotency-Key` header.
+    pub fn any_method(mut self) -> RetryPolicy {
+        self.any_method = true;
+        self
+    }
+
+    /// Sets how long each try may take before it's abandoned and counted as a failure.
+    pub fn try_timeout(mut self, timeout: Duration) -> RetryPolicy {
+        self.try_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the backoff before the first retry, and the most any backoff can grow to.
+    pub fn backoff(mut self, base: Duration, max: Duration) -> RetryPolicy {
+        self.base = base;
+        self.max_backoff = max.max(base);
+        self
+    }
+
+    /// Sets the retry budget: each request adds `ratio` retries to the budget, which holds up to
+    /// `reserve`. The budget starts full, and is no longer shared with earlier clones.
+    pub fn budget(mut self, ratio: f64, reserve: u32) -> RetryPolicy {
+        self.ratio = ratio.max(0.0);
+        self.reserve = reserve as f64;
+        self.budget = Arc::new(Mutex::new(self.reserve));
+        self
+    }
+
+    /// Checks whether requests with a method may be retried under this policy.
+    pub fn retries(&self, method: &Method) -> bool {
+        self.any_method || IDEMPOTENT.contains(method)
+    }
+
+    /// Gets a jittered backoff before a retry, counting from 1: a random duration up to the base
+    /// backoff doubled for every earlier retry, capped at the maximum.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let ceiling = self.base.saturating_mul(1 << retry.saturating_sub(1).min(31)).min(self.max_backoff);
+        Duration::from_nanos(random_u64() % (ceiling.as_nanos() as u64).saturating_add(1))
+    }
+
+    /// Runs a request, trying it again on failure as this policy allows. `attempt` is called with
+    /// the number of each try, counting from 1, and sends it.
+    ///
+    /// Errors are all treated as transient: map responses which shouldn't be retried, such as
+    /// client errors, to `Ok`.
+    pub async fn run<T, E, F, Fut>(&self, method: &Method, mut attempt: F) -> Result<T, RetryError<E>>
+    where
+        F: FnMut(u32) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        self.deposit();
+
+        let mut tries = 0;
+        loop {
+            tries += 1;
+
+            match self.attempt(attempt(tries)).await {
+                Ok(value) => { return Ok(value); }
+                Err(error) => {
+                    if !self.again(method, tries).await {
+                        return Err(error);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Adds a request's share of a retry to the budget.
+    pub(crate) fn deposit(&self) {
+        let mut budget = lock(&self.budget);
+        *budget = (*budget + self.ratio).min(self.reserve);
+    }
+
+    /// Runs a single try, within the per-try timeout.
+    pub(crate) async fn attempt<T, E>(
+        &self,
+        request: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, RetryError<E>> {
+        match self.try_timeout {
+            Some(timeout) => {
+                match tokio::time::timeout(timeout, request).await {
+                    Ok(result) => { result.map_err(RetryError::Failed) }
+                    Err(_) => { Err(RetryError::TimedOut) }
+                }
+            }
+            None => { request.await.map_err(RetryError::Failed) }
+        }
+    }
+
+    /// Decides whether a request should be tried again after `tries` failed tries, taking the
+    /// retry from the budget and backing off if so.
+    pub(crate) async fn again(&self, method: &Method, tries: u32) -> bool {
+        if tries >= self.attempts || !self.retries(method) || !self.withdraw() {
+            return false;
+        }
+
+        tokio::time::sleep(self.delay(tries)).await;
+        true
+    }
+
+    /// Takes a retry from the budget, if there's one left.
+    fn withdraw(&self) -> bool {
+        let mut budget = lock(&self.budget);
+        let available = *budget >= 1.0;
+        if available {
+            *budget -= 1.0;
+        }
+
+        available
+    }
+}
+
+/// Locks a mutex, recovering the guard if a panicking thread poisoned it.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
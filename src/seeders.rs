@@ -0,0 +1,84 @@
+//! Ready-made `Seeder` implementations packaged with `grazie`.
+//!
+//! Each module in here contains a single `Seeder` (along with any supporting types it needs), which
+//! can be constructed and passed along into the request chain like any user-defined `Seeder`.
+
+pub mod catch_panic;
+pub mod chaos;
+#[cfg(feature = "challenge")]
+pub mod challenge;
+pub mod circuit_breaker;
+pub mod concurrency;
+pub mod experiment;
+pub mod fingerprint;
+pub mod force_https;
+pub mod geoip;
+pub mod header_rewrite;
+pub mod html_transform;
+pub mod idempotency;
+pub mod load_shed;
+pub mod locale;
+pub mod maintenance;
+pub mod pipeline;
+#[cfg(feature = "plugins")]
+pub mod plugin;
+pub mod precondition;
+pub mod problem;
+#[cfg(feature = "serde_json")]
+pub mod redact;
+#[cfg(feature = "regex")]
+pub mod rewrite;
+#[cfg(feature = "serde_json")]
+pub mod schema;
+#[cfg(feature = "rhai")]
+pub mod script;
+#[cfg(feature = "signatures")]
+pub mod signature;
+pub mod tarpit;
+pub mod tee;
+pub mod tenant;
+#[cfg(feature = "otel")]
+pub mod trace;
+pub mod versioning;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use catch_panic::CatchPanicSeeder;
+pub use chaos::ChaosSeeder;
+#[cfg(feature = "challenge")]
+pub use challenge::ChallengeSeeder;
+pub use circuit_breaker::CircuitBreakerSeeder;
+pub use concurrency::ConcurrencySeeder;
+pub use experiment::ExperimentSeeder;
+pub use fingerprint::FingerprintSeeder;
+pub use force_https::ForceHttpsSeeder;
+pub use geoip::GeoIpSeeder;
+pub use header_rewrite::HeaderRewriteSeeder;
+pub use html_transform::HtmlTransformSeeder;
+pub use idempotency::IdempotencySeeder;
+pub use load_shed::LoadShedSeeder;
+pub use locale::LocaleSeeder;
+pub use maintenance::MaintenanceSeeder;
+pub use pipeline::Traced;
+#[cfg(feature = "plugins")]
+pub use plugin::PluginSeeder;
+pub use precondition::PreconditionSeeder;
+pub use problem::ProblemSeeder;
+#[cfg(feature = "serde_json")]
+pub use redact::RedactSeeder;
+#[cfg(feature = "regex")]
+pub use rewrite::RewriteSeeder;
+#[cfg(feature = "serde_json")]
+pub use schema::SchemaSeeder;
+#[cfg(feature = "rhai")]
+pub use script::ScriptSeeder;
+#[cfg(feature = "signatures")]
+pub use signature::SignatureSeeder;
+pub use tarpit::TarpitSeeder;
+pub use tee::TeeSeeder;
+pub use tenant::TenantSeeder;
+#[cfg(feature = "otel")]
+pub use trace::TraceSeeder;
+pub use versioning::VersioningSeeder;
+#[cfg(feature = "wasm")]
+pub use wasm::WasmSeeder;
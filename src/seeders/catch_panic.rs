@@ -0,0 +1,139 @@
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use std::any::Any;
+use std::fmt::{Display, Formatter};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::task::Poll;
+
+/// A panic caught while running a future.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Panic {
+    /// The panic's message, if it panicked with a string payload.
+    pub message: Option<String>,
+}
+
+impl Panic {
+    /// Extracts the message from a panic's payload.
+    pub fn from_payload(payload: &(dyn Any + Send)) -> Panic {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| (*message).to_owned())
+            .or_else(|| payload.downcast_ref::<String>().cloned());
+
+        Panic { message }
+    }
+}
+
+impl Display for Panic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.message {
+            Some(message) => { write!(f, "panicked: {message}") }
+            None => { write!(f, "panicked") }
+        }
+    }
+}
+
+impl std::error::Error for Panic {}
+
+/// Runs a future to completion, catching any panic raised while it's polled.
+///
+/// The future is considered unwind-safe; any state it shares with the caller may be left in an
+/// inconsistent state after a panic.
+pub async fn catch_panic<F: Future>(future: F) -> Result<F::Output, Panic> {
+    let mut future = Box::pin(future);
+
+    std::future::poll_fn(move |cx| {
+        match catch_unwind(AssertUnwindSafe(|| Pin::as_mut(&mut future).poll(cx))) {
+            Ok(Poll::Ready(output)) => { Poll::Ready(Ok(output)) }
+            Ok(Poll::Pending) => { Poll::Pending }
+            Err(payload) => { Poll::Ready(Err(Panic::from_payload(payload.as_ref()))) }
+        }
+    }).await
+}
+
+/// The hook which builds a response for a caught panic.
+type PanicHook = Box<dyn Fn(&HttpRequest<BoxBody>, &Panic) -> HttpResponse<BoxBody> + Send + Sync>;
+
+/// A `Seeder` which wraps another `Seeder`, answering with `500 Internal Server Error` if it
+/// panics, rather than tearing down the connection.
+///
+/// The response can be customized (and the panic logged) through `CatchPanicSeeder::on_panic`.
+/// Wrapped seeders are run on the current task, since they borrow the request. They're passed
+/// inaccessible guards too, so that seeders which handle rejections are also caught.
+pub struct CatchPanicSeeder<S> {
+    inner: S,
+    hook: PanicHook,
+}
+
+impl<S: Seeder + Sync> CatchPanicSeeder<S> {
+    /// Constructs a new `CatchPanicSeeder`, wrapping the provided `Seeder`.
+    pub fn new(inner: S) -> CatchPanicSeeder<S> {
+        CatchPanicSeeder {
+            inner,
+            hook: Box::new(|_, _| {
+                HttpResponse::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(BoxBody::empty())
+                    .unwrap()
+            }),
+        }
+    }
+
+    /// Sets the hook which builds the response for a caught panic. The hook is also the place to
+    /// log the panic.
+    pub fn on_panic<F>(mut self, hook: F) -> CatchPanicSeeder<S>
+    where
+        F: Fn(&HttpRequest<BoxBody>, &Panic) -> HttpResponse<BoxBody> + Send + Sync + 'static,
+    {
+        self.hook = Box::new(hook);
+        self
+    }
+
+    /// Gets the wrapped `Seeder`.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: Seeder + Sync> Seeder for CatchPanicSeeder<S> {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let (request, rejected) = match input {
+            Guard::Accessible(request) => { (request, None) }
+            Guard::Inaccessible { request, respondent, rejection } => {
+                (request, Some((respondent, rejection)))
+            }
+        };
+
+        // The wrapped seeder gets every guard, including inaccessible ones, with a reborrow of the
+        // request, so that the request is still available to answer with if the seeder panics.
+        let reborrowed = match rejected {
+            Some((respondent, rejection)) => {
+                Guard::Inaccessible { request: &mut *request, respondent, rejection }
+            }
+            None => { Guard::Accessible(&mut *request) }
+        };
+
+        let outcome = catch_panic(self.inner.seed(reborrowed)).await;
+
+        match outcome {
+            Ok(Guard::Accessible(_)) => { Guard::Accessible(request) }
+            Ok(Guard::Inaccessible { respondent, rejection, .. }) => {
+                Guard::Inaccessible { request, respondent, rejection }
+            }
+            Err(panic) => {
+                let response = (self.hook)(request, &panic);
+                let status = response.status();
+
+                Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Respond(Box::new(response)),
+                    rejection: Rejection::new(status, "panicked", "A seeder panicked."),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,416 @@
+use crate::auth::session::{cookie, is_cookie_name, set_cookie, token};
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::{HeaderValue, CONTENT_TYPE, LOCATION, SET_COOKIE};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::util::{constant_time_eq, percent_encode, query_pairs, safe_return_path};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The default path challenge solutions are submitted to.
+pub const DEFAULT_VERIFY_PATH: &str = "/__grazie/challenge";
+
+/// The most leading zero bits a `ProofOfWork` may require. Solving takes about 2^32 hashes at
+/// this difficulty, already far more than a browser should be asked for.
+pub const MAX_DIFFICULTY: u32 = 32;
+
+/// Challenges clients on behalf of a `ChallengeSeeder`, and verifies their solutions.
+pub trait ChallengeVerifier: Send + Sync + 'static {
+    /// Builds the response which challenges a client. `verify_uri` is where the solution should be
+    /// submitted to, and already carries the page to return to afterwards.
+    fn challenge(&self, request: &HttpRequest<BoxBody>, verify_uri: &str) -> HttpResponse<BoxBody>;
+
+    /// Checks a client's submitted solution.
+    fn verify(&self, request: &HttpRequest<BoxBody>) -> impl Future<Output = bool> + Send;
+}
+
+/// Gets the fields of a request's query string and form-encoded body, with body fields first.
+fn fields(request: &HttpRequest<BoxBody>) -> Vec<(String, String)> {
+    let body = std::str::from_utf8(request.body().raw_bytes()).unwrap_or_default();
+    let query = request.uri().query().unwrap_or_default();
+
+    query_pairs(body).chain(query_pairs(query)).collect()
+}
+
+/// Gets a field of a request's query string or form-encoded body.
+fn field(request: &HttpRequest<BoxBody>, name: &str) -> Option<String> {
+    fields(request).into_iter().find(|(key, _)| key == name).map(|(_, value)| value)
+}
+
+/// Gets the current time in seconds since the Unix epoch.
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Signs a message with HMAC-SHA256, as lowercase hex.
+fn sign(secret: &[u8], message: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC keys may be any length");
+    mac.update(message.as_bytes());
+
+    mac.finalize().into_bytes().iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Checks a signed, expiring token in the form `<expiry>.<payload>.<signature>`, which was issued
+/// for `purpose`. Returns the token's expiry if it's valid.
+fn verify_signed(secret: &[u8], purpose: &str, signed: &str) -> Option<u64> {
+    let (message, signature) = signed.rsplit_once('.')?;
+    let expiry = message.split('.').next()?.parse::<u64>().ok()?;
+    let expected = sign(secret, &format!("{purpose}.{message}"));
+
+    (expiry > unix_now() && constant_time_eq(expected.as_bytes(), signature.as_bytes()))
+        .then_some(expiry)
+}
+
+/// Issues a signed token for `purpose` which expires after `ttl`.
+///
+/// The purpose is signed but not included in the token, so that a token issued for one purpose
+/// can't be passed off as another under the same secret.
+fn issue_signed(secret: &[u8], purpose: &str, ttl: Duration) -> String {
+    let message = format!("{}.{}", unix_now() + ttl.as_secs(), token());
+    let signature = sign(secret, &format!("{purpose}.{message}"));
+
+    format!("{message}.{signature}")
+}
+
+/// A lightweight proof-of-work challenge.
+///
+/// Clients are handed a signed, expiring challenge, and must find a `nonce` such that the SHA-256
+/// hash of `<challenge>:<nonce>` starts with `difficulty` zero bits. Each extra bit of difficulty
+/// doubles the expected work. Solutions are submitted as the `challenge` and `nonce` fields of the
+/// query string or a form-encoded body.
+///
+/// Each challenge can only be redeemed once. Redeemed challenges are remembered until they expire.
+pub struct ProofOfWork {
+    secret: Vec<u8>,
+    difficulty: u32,
+    ttl: Duration,
+    redeemed: Mutex<HashMap<String, u64>>,
+}
+
+impl ProofOfWork {
+    /// Constructs a new `ProofOfWork`, signing challenges with `secret`. Challenges require 18
+    /// zero bits, and expire after 5 minutes.
+    pub fn new(secret: impl Into<Vec<u8>>) -> ProofOfWork {
+        ProofOfWork {
+            secret: secret.into(),
+            difficulty: 18,
+            ttl: Duration::from_secs(300),
+            redeemed: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sets how many leading zero bits a solution's hash must have, up to `MAX_DIFFICULTY`.
+    /// Higher difficulties are lowered to `MAX_DIFFICULTY`.
+    pub fn difficulty(mut self, difficulty: u32) -> ProofOfWork {
+        self.difficulty = difficulty.min(MAX_DIFFICULTY);
+        self
+    }
+
+    /// Sets how long a challenge may be solved for.
+    pub fn ttl(mut self, ttl: Duration) -> ProofOfWork {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Issues a new challenge.
+    pub fn issue(&self) -> String {
+        issue_signed(&self.secret, "pow", self.ttl)
+    }
+
+    /// Checks whether a nonce solves a challenge, without checking the challenge's signature.
+    pub fn solves(&self, challenge: &str, nonce: &str) -> bool {
+        let hash = Sha256::digest(format!("{challenge}:{nonce}").as_bytes());
+        let mut zeros = 0;
+
+        for byte in hash {
+            zeros += byte.leading_zeros();
+
+            if byte != 0 {
+                break;
+            }
+        }
+
+        zeros >= self.difficulty
+    }
+
+    /// Marks a challenge as redeemed, returning `false` if it already was.
+    fn redeem(&self, challenge: &str, expiry: u64) -> bool {
+        let now = unix_now();
+        let mut redeemed = self.redeemed.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        redeemed.retain(|_, expiry| *expiry > now);
+
+        redeemed.insert(challenge.to_owned(), expiry).is_none()
+    }
+
+    /// Finds a nonce which solves a challenge, as a client would.
+    pub fn solve(&self, challenge: &str) -> String {
+        (0u64..).map(|nonce| nonce.to_string()).find(|nonce| self.solves(challenge, nonce)).unwrap()
+    }
+}
+
+impl ChallengeVerifier for ProofOfWork {
+    /// Responds with `403 Forbidden` and a JSON body of the form
+    /// `{"challenge":"...","difficulty":18,"verify":"..."}`.
+    fn challenge(
+        &self,
+        _request: &HttpRequest<BoxBody>,
+        verify_uri: &str,
+    ) -> HttpResponse<BoxBody> {
+        let body = format!(
+            r#"{{"challenge":"{}","difficulty":{},"verify":"{}"}}"#,
+            self.issue(),
+            self.difficulty,
+            verify_uri,
+        );
+
+        HttpResponse::builder()
+            .status(StatusCode::FORBIDDEN)
+            .header(CONTENT_TYPE, "application/json")
+            .body(BoxBody::new(body.into_bytes().into()))
+            .unwrap()
+    }
+
+    async fn verify(&self, request: &HttpRequest<BoxBody>) -> bool {
+        let challenge = field(request, "challenge");
+        let (Some(challenge), Some(nonce)) = (challenge, field(request, "nonce")) else {
+            return false;
+        };
+        let Some(expiry) = verify_signed(&self.secret, "pow", &challenge) else { return false; };
+
+        self.solves(&challenge, &nonce) && self.redeem(&challenge, expiry)
+    }
+}
+
+/// A challenge which hands clients to an external captcha service.
+///
+/// The challenge page embeds the captcha widget, and posts its response token (the `field` of the
+/// form) to the verify path. The token is then checked with the service through `check`, such as
+/// by calling its `siteverify` API.
+pub struct Captcha<F> {
+    page: String,
+    field: String,
+    check: F,
+}
+
+impl<F, Fut> Captcha<F>
+where
+    F: Fn(String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = bool> + Send,
+{
+    /// Constructs a new `Captcha`, serving `page` as the challenge. Every `{verify}` in the page is
+    /// replaced with the URI the form should post to.
+    pub fn new(page: impl Into<String>, check: F) -> Captcha<F> {
+        Captcha {
+            page: page.into(),
+            field: "captcha-response".to_owned(),
+            check,
+        }
+    }
+
+    /// Sets the form field holding the captcha's response token, such as `h-captcha-response` or
+    /// `cf-turnstile-response`.
+    pub fn field(mut self, field: impl Into<String>) -> Captcha<F> {
+        self.field = field.into();
+        self
+    }
+}
+
+impl<F, Fut> ChallengeVerifier for Captcha<F>
+where
+    F: Fn(String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = bool> + Send,
+{
+    fn challenge(
+        &self,
+        _request: &HttpRequest<BoxBody>,
+        verify_uri: &str,
+    ) -> HttpResponse<BoxBody> {
+        let page = self.page.replace("{verify}", &verify_uri.replace('&', "&amp;"));
+
+        HttpResponse::builder()
+            .status(StatusCode::FORBIDDEN)
+            .header(CONTENT_TYPE, "text/html; charset=utf-8")
+            .body(BoxBody::new(page.into_bytes().into()))
+            .unwrap()
+    }
+
+    async fn verify(&self, request: &HttpRequest<BoxBody>) -> bool {
+        match field(request, &self.field) {
+            Some(response) => { (self.check)(response).await }
+            None => { false }
+        }
+    }
+}
+
+/// A `Seeder` which requires clients to pass a challenge before accessing protected paths.
+///
+/// Clients without a valid pass are answered with the verifier's challenge. Solutions are submitted
+/// to the verify path (`DEFAULT_VERIFY_PATH` by default), and once verified, the client is issued a
+/// signed pass cookie and redirected back to the page it was challenged on.
+pub struct ChallengeSeeder<V> {
+    verifier: V,
+    secret: Vec<u8>,
+    prefixes: Vec<String>,
+    verify_path: String,
+    cookie: String,
+    ttl: Duration,
+    secure: bool,
+}
+
+impl<V: ChallengeVerifier> ChallengeSeeder<V> {
+    /// Constructs a new `ChallengeSeeder`, signing passes with `secret`. Passes last an hour.
+    ///
+    /// Without any protected prefixes, every path is protected.
+    pub fn new(verifier: V, secret: impl Into<Vec<u8>>) -> ChallengeSeeder<V> {
+        ChallengeSeeder {
+            verifier,
+            secret: secret.into(),
+            prefixes: Vec::new(),
+            verify_path: DEFAULT_VERIFY_PATH.to_owned(),
+            cookie: "grazie_pass".to_owned(),
+            ttl: Duration::from_secs(3600),
+            secure: true,
+        }
+    }
+
+    /// Protects paths starting with `prefix`.
+    pub fn protect(mut self, prefix: impl Into<String>) -> ChallengeSeeder<V> {
+        self.prefixes.push(prefix.into());
+        self
+    }
+
+    /// Sets the path solutions are submitted to.
+    pub fn verify_path(mut self, path: impl Into<String>) -> ChallengeSeeder<V> {
+        self.verify_path = path.into();
+        self
+    }
+
+    /// Sets the name of the pass cookie.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name isn't a valid cookie name.
+    pub fn cookie(mut self, name: impl Into<String>) -> ChallengeSeeder<V> {
+        self.cookie = name.into();
+        assert!(is_cookie_name(&self.cookie), "A pass cookie must have a valid name!");
+        self
+    }
+
+    /// Sets how long a pass lasts.
+    pub fn ttl(mut self, ttl: Duration) -> ChallengeSeeder<V> {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sets whether the pass cookie is only sent over HTTPS. Defaults to `true`.
+    pub fn secure(mut self, secure: bool) -> ChallengeSeeder<V> {
+        self.secure = secure;
+        self
+    }
+
+    /// Issues a new pass, as the `Set-Cookie` value which hands it to the client.
+    pub fn issue_pass(&self) -> HeaderValue {
+        let pass = issue_signed(&self.secret, "pass", self.ttl);
+
+        set_cookie(&self.cookie, &pass, "/", self.ttl, self.secure)
+    }
+
+    /// Checks whether a request carries a valid pass.
+    pub fn has_pass(&self, request: &HttpRequest<BoxBody>) -> bool {
+        cookie(request, &self.cookie)
+            .is_some_and(|pass| verify_signed(&self.secret, "pass", pass).is_some())
+    }
+
+    fn protects(&self, path: &str) -> bool {
+        self.prefixes.is_empty()
+            || self.prefixes.iter().any(|prefix| path.starts_with(prefix.as_str()))
+    }
+}
+
+impl<V: ChallengeVerifier> Seeder for ChallengeSeeder<V> {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        if request.uri().path() == self.verify_path {
+            if !self.verifier.verify(request).await {
+                let response = HttpResponse::builder()
+                    .status(StatusCode::FORBIDDEN)
+                    .body(BoxBody::empty())
+                    .unwrap();
+
+                return Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Respond(Box::new(response)),
+                    rejection: Rejection::new(
+                        StatusCode::FORBIDDEN,
+                        "challenge_failed",
+                        "The challenge wasn't solved.",
+                    ),
+                };
+            }
+
+            let return_to = match field(request, "return_to") {
+                Some(path) => { safe_return_path(&path) }
+                None => { Some(HeaderValue::from_static("/")) }
+            };
+            let Some(return_to) = return_to else {
+                let rejection = Rejection::new(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_return_to",
+                    "The return path isn't a local path.",
+                );
+                let response = HttpResponse::builder()
+                    .status(rejection.status)
+                    .body(BoxBody::empty())
+                    .unwrap();
+
+                return Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Respond(Box::new(response)),
+                    rejection,
+                };
+            };
+
+            let mut response = HttpResponse::new(BoxBody::empty());
+            *response.status_mut() = StatusCode::SEE_OTHER;
+            response.headers_mut().insert(LOCATION, return_to);
+            response.headers_mut().insert(SET_COOKIE, self.issue_pass());
+
+            return Guard::Inaccessible {
+                request,
+                respondent: Respondent::Respond(Box::new(response)),
+                rejection: Rejection::new(
+                    StatusCode::SEE_OTHER,
+                    "redirected",
+                    "The challenge was solved.",
+                ),
+            };
+        }
+
+        if !self.protects(request.uri().path()) || self.has_pass(request) {
+            return Guard::Accessible(request);
+        }
+
+        let return_to = request.uri().path_and_query().map(|path| path.as_str()).unwrap_or("/");
+        let verify_uri = format!("{}?return_to={}", self.verify_path, percent_encode(return_to));
+        let response = self.verifier.challenge(request, &verify_uri);
+
+        Guard::Inaccessible {
+            request,
+            respondent: Respondent::Respond(Box::new(response)),
+            rejection: Rejection::new(
+                StatusCode::FORBIDDEN,
+                "challenge_required",
+                "The request requires passing a challenge.",
+            ),
+        }
+    }
+}
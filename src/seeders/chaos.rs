@@ -0,0 +1,193 @@
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::util::random_u64;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A fault injected by a `ChaosSeeder`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Delays the request, then lets it through.
+    Latency(Duration),
+
+    /// Answers the request with an error status.
+    Error(StatusCode),
+
+    /// Rejects the request with a `DropConnection` respondent, for the server to close the
+    /// connection without answering.
+    Drop,
+
+    /// Lets the request through, marking its response to be truncated by
+    /// `ChaosSeeder::truncate_response`.
+    Truncate,
+}
+
+/// The `Respondent::Other` value of requests whose connection should be dropped without a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DropConnection;
+
+/// Marks a request whose response should be truncated.
+#[derive(Debug, Clone, Copy)]
+struct Truncated;
+
+/// A handle for toggling chaos at runtime.
+///
+/// Handles are cheap to clone, and every clone toggles the same `ChaosSeeder`.
+#[derive(Debug, Clone, Default)]
+pub struct ChaosHandle {
+    enabled: Arc<AtomicBool>,
+}
+
+impl ChaosHandle {
+    /// Starts injecting faults.
+    pub fn enable(&self) {
+        self.enabled.store(true, Ordering::Release);
+    }
+
+    /// Stops injecting faults.
+    pub fn disable(&self) {
+        self.enabled.store(false, Ordering::Release);
+    }
+
+    /// Checks whether faults are being injected.
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Acquire)
+    }
+}
+
+/// A `Seeder` which injects faults into a sample of requests, for testing how clients cope with
+/// latency, errors, dropped connections, and truncated bodies.
+///
+/// No faults are injected until the seeder is enabled through its `ChaosHandle`, so it can be left
+/// in place outside of testing. Each route prefix can have its own sample rate, with the longest
+/// matching prefix winning; sampled requests get one fault, picked by weight.
+pub struct ChaosSeeder {
+    handle: ChaosHandle,
+    rate: f64,
+    routes: Vec<(String, f64)>,
+    faults: Vec<(Fault, u32)>,
+    injected: AtomicU64,
+}
+
+impl ChaosSeeder {
+    /// Constructs a new, disabled `ChaosSeeder`, sampling every route at `rate` (between `0.0` and
+    /// `1.0`), without any faults.
+    pub fn new(rate: f64) -> ChaosSeeder {
+        ChaosSeeder {
+            handle: ChaosHandle::default(),
+            rate: rate.clamp(0.0, 1.0),
+            routes: Vec::new(),
+            faults: Vec::new(),
+            injected: AtomicU64::new(0),
+        }
+    }
+
+    /// Adds a fault, weighted against the other faults.
+    pub fn fault(mut self, fault: Fault, weight: u32) -> ChaosSeeder {
+        self.faults.push((fault, weight));
+        self
+    }
+
+    /// Sets the sample rate of the routes under a path prefix.
+    pub fn route(mut self, prefix: impl Into<String>, rate: f64) -> ChaosSeeder {
+        self.routes.push((prefix.into(), rate.clamp(0.0, 1.0)));
+        self
+    }
+
+    /// Gets a handle for enabling and disabling this seeder at runtime.
+    pub fn handle(&self) -> ChaosHandle {
+        self.handle.clone()
+    }
+
+    /// Gets the number of faults injected so far.
+    pub fn injected(&self) -> u64 {
+        self.injected.load(Ordering::Relaxed)
+    }
+
+    /// Truncates a response's body to half its length, if its request was picked for truncation.
+    /// The `Content-Length` header is left as it was, so clients see the body end early.
+    pub fn truncate_response(&self, request: &HttpRequest<BoxBody>, response: &mut HttpResponse<BoxBody>) {
+        if request.extensions().get::<Truncated>().is_none() {
+            return;
+        }
+
+        let bytes = response.body().raw_bytes();
+        let truncated: Box<[u8]> = bytes[..bytes.len() / 2].into();
+        *response.body_mut() = BoxBody::new(truncated);
+    }
+
+    /// Picks the fault to inject into a request, if any.
+    fn pick(&self, path: &str) -> Option<Fault> {
+        let rate = self.routes
+            .iter()
+            .filter(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.rate, |(_, rate)| *rate);
+
+        if (random_u64() as f64 / u64::MAX as f64) >= rate {
+            return None;
+        }
+
+        let total: u64 = self.faults.iter().map(|(_, weight)| *weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut bucket = random_u64() % total;
+        for (fault, weight) in &self.faults {
+            match bucket.checked_sub(*weight as u64) {
+                Some(rest) => { bucket = rest; }
+                None => { return Some(*fault); }
+            }
+        }
+
+        None
+    }
+}
+
+impl Seeder for ChaosSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        if !self.handle.enabled() {
+            return Guard::Accessible(request);
+        }
+
+        let Some(fault) = self.pick(request.uri().path()) else { return Guard::Accessible(request); };
+        self.injected.fetch_add(1, Ordering::Relaxed);
+
+        match fault {
+            Fault::Latency(delay) => {
+                tokio::time::sleep(delay).await;
+                Guard::Accessible(request)
+            }
+            Fault::Truncate => {
+                request.extensions_mut().insert(Truncated);
+                Guard::Accessible(request)
+            }
+            Fault::Error(status) => {
+                let response = HttpResponse::builder().status(status).body(BoxBody::empty()).unwrap();
+
+                Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Respond(Box::new(response)),
+                    rejection: Rejection::new(status, "chaos_error", "An error was injected."),
+                }
+            }
+            Fault::Drop => {
+                Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Other(Box::new(DropConnection)),
+                    rejection: Rejection::new(StatusCode::INTERNAL_SERVER_ERROR, "chaos_dropped", "The connection was dropped."),
+                }
+            }
+        }
+    }
+}
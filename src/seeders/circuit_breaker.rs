@@ -0,0 +1,336 @@
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::RETRY_AFTER;
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// The state of a single route's circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests flow through normally, while outcomes are tracked.
+    Closed,
+
+    /// Requests are rejected until the circuit's cool-down has elapsed.
+    Open,
+
+    /// A limited number of probe requests are let through, to test whether the route has
+    /// recovered.
+    HalfOpen,
+}
+
+/// A snapshot of a route's circuit, as returned by `CircuitBreakerSeeder::metrics`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitMetrics {
+    /// The current state of the circuit.
+    pub state: CircuitState,
+
+    /// The number of successful outcomes reported for the route.
+    pub successes: u64,
+
+    /// The number of failed outcomes reported for the route.
+    pub failures: u64,
+
+    /// The number of requests rejected while the circuit was open.
+    pub rejected: u64,
+
+    /// The number of times the circuit has tripped open.
+    pub trips: u64,
+}
+
+/// The route which an accepted request was counted against. This is stored in the request's
+/// extensions, so that its outcome is reported against the same circuit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircuitRoute(pub String);
+
+/// The tracked state of a route's circuit.
+struct Circuit {
+    state: CircuitState,
+    outcomes: VecDeque<bool>,
+    opened_at: Instant,
+    half_opened_at: Instant,
+    probes: u32,
+    probe_successes: u32,
+    metrics: CircuitMetrics,
+}
+
+impl Circuit {
+    fn new() -> Circuit {
+        Circuit {
+            state: CircuitState::Closed,
+            outcomes: VecDeque::new(),
+            opened_at: Instant::now(),
+            half_opened_at: Instant::now(),
+            probes: 0,
+            probe_successes: 0,
+            metrics: CircuitMetrics {
+                state: CircuitState::Closed,
+                successes: 0,
+                failures: 0,
+                rejected: 0,
+                trips: 0,
+            },
+        }
+    }
+
+    fn set_state(&mut self, state: CircuitState) {
+        self.state = state;
+        self.metrics.state = state;
+        self.probes = 0;
+        self.probe_successes = 0;
+
+        match state {
+            CircuitState::Closed => { self.outcomes.clear(); }
+            CircuitState::Open => {
+                self.opened_at = Instant::now();
+                self.metrics.trips += 1;
+            }
+            CircuitState::HalfOpen => { self.half_opened_at = Instant::now(); }
+        }
+    }
+}
+
+/// Maps a request to the route its circuit is tracked under.
+type RouteKey = Box<dyn Fn(&HttpRequest<BoxBody>) -> String + Send + Sync>;
+
+/// A `Seeder` which stops sending requests to routes whose downstream dependencies are failing.
+///
+/// Each route has its own circuit. While a circuit is closed, the outcomes of its most recent
+/// requests are tracked; once enough of them fail, the circuit trips open, and requests are
+/// rejected with `503 Service Unavailable` for a cool-down period. After the cool-down, the circuit
+/// is half-open: a few probe requests are let through, and the circuit closes again once they all
+/// succeed (or re-opens on the first failure). Probes whose outcomes aren't reported within another
+/// cool-down are given up on, and new probes are let through in their place.
+///
+/// At most 1024 routes are tracked by default (see `CircuitBreakerSeeder::max_tracked`), so that
+/// clients requesting unique paths can't grow the set of circuits without bound.
+///
+/// Handlers report outcomes through `CircuitBreakerSeeder::report` (treating `5xx` responses as
+/// failures), or `CircuitBreakerSeeder::record` for failures which aren't reflected in the status.
+pub struct CircuitBreakerSeeder {
+    circuits: Mutex<HashMap<String, Circuit>>,
+    route_key: RouteKey,
+    window: usize,
+    minimum_requests: usize,
+    failure_ratio: f64,
+    cool_down: Duration,
+    probes: u32,
+    max_tracked: usize,
+}
+
+impl CircuitBreakerSeeder {
+    /// Constructs a new `CircuitBreakerSeeder`.
+    ///
+    /// By default, circuits are keyed by path, track the last 20 outcomes, trip once at least 10
+    /// outcomes are tracked and half of them are failures, cool down for 30 seconds, and probe with
+    /// 3 requests.
+    pub fn new() -> CircuitBreakerSeeder {
+        CircuitBreakerSeeder {
+            circuits: Mutex::new(HashMap::new()),
+            route_key: Box::new(|request| request.uri().path().to_owned()),
+            window: 20,
+            minimum_requests: 10,
+            failure_ratio: 0.5,
+            cool_down: Duration::from_secs(30),
+            probes: 3,
+            max_tracked: 1024,
+        }
+    }
+
+    /// Sets how requests are mapped to circuits. Routes with path parameters should map every path
+    /// of the route to the same key.
+    pub fn route_key<F>(mut self, route_key: F) -> CircuitBreakerSeeder
+    where
+        F: Fn(&HttpRequest<BoxBody>) -> String + Send + Sync + 'static,
+    {
+        self.route_key = Box::new(route_key);
+        self
+    }
+
+    /// Sets the number of recent outcomes tracked per route, and how many must be tracked before
+    /// a circuit can trip.
+    pub fn window(mut self, window: usize, minimum_requests: usize) -> CircuitBreakerSeeder {
+        self.window = window.max(1);
+        self.minimum_requests = minimum_requests.clamp(1, self.window);
+        self
+    }
+
+    /// Sets the fraction of tracked outcomes which must be failures for a circuit to trip. A circuit
+    /// never trips without a failure, so a ratio of 0 trips on the first failure.
+    pub fn failure_ratio(mut self, failure_ratio: f64) -> CircuitBreakerSeeder {
+        self.failure_ratio = failure_ratio.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets how long a tripped circuit stays open before probing.
+    pub fn cool_down(mut self, cool_down: Duration) -> CircuitBreakerSeeder {
+        self.cool_down = cool_down;
+        self
+    }
+
+    /// Sets the number of probe requests let through while half-open.
+    pub fn probes(mut self, probes: u32) -> CircuitBreakerSeeder {
+        self.probes = probes.max(1);
+        self
+    }
+
+    /// Sets how many routes' circuits are tracked at once. When the limit is hit, closed circuits
+    /// are forgotten, and requests to routes which still can't be tracked pass through unchecked.
+    pub fn max_tracked(mut self, max_tracked: usize) -> CircuitBreakerSeeder {
+        self.max_tracked = max_tracked;
+        self
+    }
+
+    /// Reports the outcome of a request from its response, where `5xx` statuses are failures.
+    pub fn report(&self, request: &HttpRequest<BoxBody>, response: &HttpResponse<BoxBody>) {
+        if let Some(CircuitRoute(route)) = request.extensions().get::<CircuitRoute>() {
+            self.record(route, !response.status().is_server_error());
+        }
+    }
+
+    /// Records the outcome of a request against a route's circuit.
+    pub fn record(&self, route: &str, success: bool) {
+        let mut circuits = self.lock();
+        let Some(circuit) = self.track(&mut circuits, route) else { return; };
+
+        match success {
+            true => { circuit.metrics.successes += 1; }
+            false => { circuit.metrics.failures += 1; }
+        }
+
+        match circuit.state {
+            CircuitState::Closed => {
+                circuit.outcomes.push_back(success);
+                while circuit.outcomes.len() > self.window {
+                    circuit.outcomes.pop_front();
+                }
+
+                let failures = circuit.outcomes.iter().filter(|success| !**success).count();
+                let tracked = circuit.outcomes.len();
+
+                let failing = failures > 0 && failures as f64 >= tracked as f64 * self.failure_ratio;
+                if tracked >= self.minimum_requests && failing {
+                    circuit.set_state(CircuitState::Open);
+                }
+            }
+            CircuitState::HalfOpen if !success => { circuit.set_state(CircuitState::Open); }
+            CircuitState::HalfOpen => {
+                circuit.probe_successes += 1;
+
+                if circuit.probe_successes >= self.probes {
+                    circuit.set_state(CircuitState::Closed);
+                }
+            }
+            CircuitState::Open => {}
+        }
+    }
+
+    /// Gets the metrics of a route's circuit.
+    pub fn metrics(&self, route: &str) -> Option<CircuitMetrics> {
+        self.lock().get(route).map(|circuit| circuit.metrics)
+    }
+
+    /// Gets the metrics of every tracked route's circuit.
+    pub fn snapshot(&self) -> Vec<(String, CircuitMetrics)> {
+        self.lock()
+            .iter()
+            .map(|(route, circuit)| (route.clone(), circuit.metrics))
+            .collect()
+    }
+
+    /// Checks whether a request may be let through to a route, returning the remaining cool-down
+    /// if it may not.
+    fn admit(&self, route: &str) -> Result<(), Duration> {
+        let mut circuits = self.lock();
+        let Some(circuit) = self.track(&mut circuits, route) else { return Ok(()); };
+
+        if circuit.state == CircuitState::Open {
+            let elapsed = circuit.opened_at.elapsed();
+
+            if elapsed < self.cool_down {
+                circuit.metrics.rejected += 1;
+                return Err(self.cool_down - elapsed);
+            }
+
+            circuit.set_state(CircuitState::HalfOpen);
+        }
+
+        if circuit.state == CircuitState::HalfOpen {
+            if circuit.probes >= self.probes && circuit.half_opened_at.elapsed() >= self.cool_down {
+                circuit.set_state(CircuitState::HalfOpen);
+            }
+
+            if circuit.probes >= self.probes {
+                circuit.metrics.rejected += 1;
+                return Err(Duration::from_secs(1));
+            }
+
+            circuit.probes += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Gets a route's circuit, starting to track it if there's room.
+    fn track<'c>(&self, circuits: &'c mut HashMap<String, Circuit>, route: &str) -> Option<&'c mut Circuit> {
+        if circuits.len() >= self.max_tracked && !circuits.contains_key(route) {
+            circuits.retain(|_, circuit| circuit.state != CircuitState::Closed);
+
+            if circuits.len() >= self.max_tracked {
+                return None;
+            }
+        }
+
+        Some(circuits.entry(route.to_owned()).or_insert_with(Circuit::new))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Circuit>> {
+        self.circuits.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for CircuitBreakerSeeder {
+    fn default() -> CircuitBreakerSeeder {
+        CircuitBreakerSeeder::new()
+    }
+}
+
+impl Seeder for CircuitBreakerSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        let route = (self.route_key)(request);
+
+        match self.admit(&route) {
+            Ok(()) => {
+                request.extensions_mut().insert(CircuitRoute(route));
+
+                Guard::Accessible(request)
+            }
+            Err(retry_after) => {
+                let response = HttpResponse::builder()
+                    .status(StatusCode::SERVICE_UNAVAILABLE)
+                    .header(RETRY_AFTER, retry_after.as_secs().max(1))
+                    .body(BoxBody::empty())
+                    .unwrap();
+
+                Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Respond(Box::new(response)),
+                    rejection: Rejection::new(
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        "circuit_open",
+                        "The circuit for this route is open.",
+                    ),
+                }
+            }
+        }
+    }
+}
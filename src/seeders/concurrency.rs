@@ -0,0 +1,201 @@
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::RETRY_AFTER;
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use std::cmp::Reverse;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// A slot held by a request admitted by a `ConcurrencySeeder`, stored in the request's
+/// extensions. The slot is freed once the request (and every clone of this permit) is dropped.
+#[derive(Debug, Clone)]
+pub struct ConcurrencyPermit {
+    _slot: Arc<OwnedSemaphorePermit>,
+}
+
+/// A snapshot of a route's counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConcurrencyMetrics {
+    /// The number of requests currently admitted.
+    pub active: usize,
+
+    /// The number of requests waiting in the queue.
+    pub queued: usize,
+
+    /// The number of requests rejected, because the queue wait expired or the queue was full.
+    pub rejected: u64,
+}
+
+/// A concurrency-limited route.
+struct Route {
+    prefix: String,
+    limit: usize,
+    semaphore: Arc<Semaphore>,
+    queued: AtomicUsize,
+    rejected: AtomicU64,
+}
+
+/// A request's place in a route's queue, which it leaves when this is dropped, including when the
+/// request is cancelled while waiting.
+struct Queued<'a> {
+    route: &'a Route,
+    ahead: usize,
+}
+
+impl<'a> Queued<'a> {
+    fn join(route: &'a Route) -> Queued<'a> {
+        Queued {
+            route,
+            ahead: route.queued.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+}
+
+impl Drop for Queued<'_> {
+    fn drop(&mut self) {
+        self.route.queued.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A `Seeder` which caps the number of requests handled at once on individual routes.
+///
+/// Each route is matched by path prefix, with the longest matching prefix winning; requests to
+/// other paths aren't limited. Requests beyond a route's cap wait in a first-in, first-out queue,
+/// and are rejected with `503 Service Unavailable` if they can't be admitted within the queue
+/// wait. This keeps a single expensive endpoint from taking over the server, without turning away
+/// requests to everything else.
+///
+/// An admitted request holds its slot through a `ConcurrencyPermit` in its extensions, so the
+/// slot stays held until the request has been handled and dropped.
+pub struct ConcurrencySeeder {
+    routes: Vec<Route>,
+    queue_wait: Duration,
+    max_queue: Option<usize>,
+}
+
+impl ConcurrencySeeder {
+    /// Constructs a new `ConcurrencySeeder`, without any limited routes.
+    pub fn new() -> ConcurrencySeeder {
+        ConcurrencySeeder {
+            routes: Vec::new(),
+            queue_wait: Duration::from_secs(5),
+            max_queue: None,
+        }
+    }
+
+    /// Caps the number of concurrent requests to paths beginning with `prefix`.
+    pub fn route(mut self, prefix: impl Into<String>, limit: usize) -> ConcurrencySeeder {
+        let limit = limit.max(1);
+
+        self.routes.push(Route {
+            prefix: prefix.into(),
+            limit,
+            semaphore: Arc::new(Semaphore::new(limit)),
+            queued: AtomicUsize::new(0),
+            rejected: AtomicU64::new(0),
+        });
+        self.routes.sort_by_key(|route| Reverse(route.prefix.len()));
+        self
+    }
+
+    /// Sets how long a request may wait in a route's queue before it's rejected. Defaults to 5
+    /// seconds.
+    pub fn queue_wait(mut self, queue_wait: Duration) -> ConcurrencySeeder {
+        self.queue_wait = queue_wait;
+        self
+    }
+
+    /// Sets the most requests which may wait in each route's queue. Requests beyond this are
+    /// rejected straight away. Unbounded by default.
+    pub fn max_queue(mut self, max_queue: usize) -> ConcurrencySeeder {
+        self.max_queue = Some(max_queue);
+        self
+    }
+
+    /// Gets the counters of the route with the provided prefix.
+    pub fn metrics(&self, prefix: &str) -> Option<ConcurrencyMetrics> {
+        self.routes
+            .iter()
+            .find(|route| route.prefix == prefix)
+            .map(|route| {
+                ConcurrencyMetrics {
+                    active: route.limit - route.semaphore.available_permits(),
+                    queued: route.queued.load(Ordering::Relaxed),
+                    rejected: route.rejected.load(Ordering::Relaxed),
+                }
+            })
+    }
+
+    /// Admits a request to a route, waiting in its queue if the route is full.
+    async fn admit(&self, route: &Route) -> Option<OwnedSemaphorePermit> {
+        if let Ok(permit) = route.semaphore.clone().try_acquire_owned() {
+            return Some(permit);
+        }
+
+        let queued = Queued::join(route);
+        let permit = match self.max_queue {
+            Some(max_queue) if queued.ahead >= max_queue => { None }
+            _ => {
+                tokio::time::timeout(self.queue_wait, route.semaphore.clone().acquire_owned())
+                    .await
+                    .ok()
+                    .and_then(Result::ok)
+            }
+        };
+        drop(queued);
+
+        if permit.is_none() {
+            route.rejected.fetch_add(1, Ordering::Relaxed);
+        }
+
+        permit
+    }
+}
+
+impl Default for ConcurrencySeeder {
+    fn default() -> ConcurrencySeeder {
+        ConcurrencySeeder::new()
+    }
+}
+
+impl Seeder for ConcurrencySeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        let path = request.uri().path();
+        let Some(route) = self.routes.iter().find(|route| path.starts_with(route.prefix.as_str())) else {
+            return Guard::Accessible(request);
+        };
+
+        match self.admit(route).await {
+            Some(permit) => {
+                request.extensions_mut().insert(ConcurrencyPermit { _slot: Arc::new(permit) });
+                Guard::Accessible(request)
+            }
+            None => {
+                let response = HttpResponse::builder()
+                    .status(StatusCode::SERVICE_UNAVAILABLE)
+                    .header(RETRY_AFTER, self.queue_wait.as_secs().max(1))
+                    .body(BoxBody::empty())
+                    .unwrap();
+
+                Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Respond(Box::new(response)),
+                    rejection: Rejection::new(
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        "concurrency_limited",
+                        "Too many requests to this route are being handled at once.",
+                    ),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use crate::auth::session::{cookie, is_cookie_name, set_cookie};
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::header::SET_COOKIE;
+use crate::http::{HttpRequest, HttpResponse};
+use crate::server::PeerAddr;
+use crate::util::{fnv1a, random_u64};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// The variants requests have been assigned to, by experiment, as an extension of the request.
+///
+/// Every `ExperimentSeeder` adds its assignment, so a single `Assignments` covers every experiment
+/// a request is part of. Loggers and metrics can record the assignments alongside the request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Assignments {
+    variants: HashMap<String, String>,
+}
+
+impl Assignments {
+    /// Gets the variant assigned for an experiment.
+    pub fn variant(&self, experiment: &str) -> Option<&str> {
+        self.variants.get(experiment).map(String::as_str)
+    }
+
+    /// Gets every `(experiment, variant)` assignment.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.variants.iter().map(|(experiment, variant)| (experiment.as_str(), variant.as_str()))
+    }
+}
+
+/// Assignments which haven't been persisted to their cookies yet, as an extension of the request.
+#[derive(Debug, Clone, Default)]
+struct Unpersisted(Vec<String>);
+
+/// Finds the key a request is bucketed by.
+pub type BucketKey = Box<dyn Fn(&HttpRequest<BoxBody>) -> Option<String> + Send + Sync>;
+
+/// What requests are bucketed by.
+pub enum BucketBy {
+    /// A random bucket, kept through the assignment cookie.
+    Cookie,
+
+    /// A hash of the request's `PeerAddr`'s IP.
+    Ip,
+
+    /// A key found in the request, such as a user's ID. Requests without a key are bucketed
+    /// randomly, as with `Cookie`.
+    Key(BucketKey),
+}
+
+/// A `Seeder` which assigns requests to the variants of an experiment, attaching their
+/// `Assignments`.
+///
+/// Assignment is deterministic for the same key, with each variant's share of requests set by its
+/// weight. Once assigned, the variant is kept through a cookie (which `persist` sets on the
+/// response), so that clients see the same variant even if their key changes, and so that removing
+/// a variant only reassigns the clients which were in it.
+pub struct ExperimentSeeder {
+    name: String,
+    variants: Vec<(String, u32, AtomicU64)>,
+    bucket_by: BucketBy,
+    cookie: String,
+    max_age: Duration,
+    secure: bool,
+}
+
+impl ExperimentSeeder {
+    /// Constructs a new `ExperimentSeeder` for an experiment, without any variants.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the experiment's name can't be used in a cookie's name.
+    pub fn new(name: impl Into<String>) -> ExperimentSeeder {
+        let name = name.into();
+        assert!(is_cookie_name(&name), "An experiment's name must be a valid cookie name!");
+
+        ExperimentSeeder {
+            cookie: format!("grazie_exp_{name}"),
+            name,
+            variants: Vec::new(),
+            bucket_by: BucketBy::Cookie,
+            max_age: Duration::from_secs(90 * 24 * 60 * 60),
+            secure: true,
+        }
+    }
+
+    /// Adds a variant, weighted against the other variants.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the variant's name can't be kept in the assignment cookie, which holds it as is.
+    pub fn variant(mut self, name: impl Into<String>, weight: u32) -> ExperimentSeeder {
+        let name = name.into();
+        assert!(is_cookie_name(&name), "A variant's name must be a valid cookie name!");
+        self.variants.push((name, weight, AtomicU64::new(0)));
+        self
+    }
+
+    /// Sets what requests are bucketed by. Defaults to `BucketBy::Cookie`.
+    pub fn bucket_by(mut self, bucket_by: BucketBy) -> ExperimentSeeder {
+        self.bucket_by = bucket_by;
+        self
+    }
+
+    /// Sets the name of the assignment cookie. Defaults to `grazie_exp_<experiment>`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name isn't a valid cookie name.
+    pub fn cookie(mut self, name: impl Into<String>) -> ExperimentSeeder {
+        self.cookie = name.into();
+        assert!(is_cookie_name(&self.cookie), "An assignment cookie must have a valid name!");
+        self
+    }
+
+    /// Sets how long the assignment cookie is kept for. Defaults to 90 days.
+    pub fn max_age(mut self, max_age: Duration) -> ExperimentSeeder {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Sets whether the assignment cookie is only sent over HTTPS. Defaults to `true`, and should
+    /// only be disabled for local development.
+    pub fn secure(mut self, secure: bool) -> ExperimentSeeder {
+        self.secure = secure;
+        self
+    }
+
+    /// Gets the number of requests assigned to each variant.
+    pub fn metrics(&self) -> Vec<(&str, u64)> {
+        self.variants.iter().map(|(name, _, assigned)| (name.as_str(), assigned.load(Ordering::Relaxed))).collect()
+    }
+
+    /// Picks the variant a key is bucketed into.
+    pub fn assign(&self, key: &str) -> Option<&str> {
+        let total: u64 = self.variants.iter().map(|(_, weight, _)| *weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut bucket = fnv1a(format!("{}:{key}", self.name).as_bytes()) % total;
+
+        for (name, weight, _) in &self.variants {
+            match bucket.checked_sub(*weight as u64) {
+                Some(rest) => { bucket = rest; }
+                None => { return Some(name); }
+            }
+        }
+
+        None
+    }
+
+    /// Sets the assignment cookie on the response, if the request was newly assigned.
+    pub fn persist(&self, request: &HttpRequest<BoxBody>, response: &mut HttpResponse<BoxBody>) {
+        let Some(unpersisted) = request.extensions().get::<Unpersisted>() else { return; };
+        if !unpersisted.0.contains(&self.name) {
+            return;
+        }
+
+        let Some(variant) = request.extensions().get::<Assignments>().and_then(|assignments| assignments.variant(&self.name)) else {
+            return;
+        };
+
+        let cookie = set_cookie(&self.cookie, variant, "/", self.max_age, self.secure);
+        response.headers_mut().append(SET_COOKIE, cookie);
+    }
+
+    /// Finds the key a request is bucketed by.
+    fn key(&self, request: &HttpRequest<BoxBody>) -> String {
+        let key = match &self.bucket_by {
+            BucketBy::Cookie => { None }
+            BucketBy::Ip => { request.extensions().get::<PeerAddr>().map(|peer| peer.0.ip().to_string()) }
+            BucketBy::Key(key) => { key(request) }
+        };
+
+        key.unwrap_or_else(|| format!("{:016x}", random_u64()))
+    }
+}
+
+impl Seeder for ExperimentSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        // Assignments are kept as long as their variant still exists.
+        let kept = cookie(request, &self.cookie)
+            .and_then(|kept| self.variants.iter().position(|(name, weight, _)| name == kept && *weight > 0));
+
+        let (index, persisted) = match kept {
+            Some(index) => { (Some(index), true) }
+            None => {
+                let key = self.key(request);
+                let index = self.assign(&key).and_then(|variant| self.variants.iter().position(|(name, _, _)| name == variant));
+                (index, false)
+            }
+        };
+
+        let Some(index) = index else { return Guard::Accessible(request); };
+        let (variant, _, assigned) = &self.variants[index];
+        assigned.fetch_add(1, Ordering::Relaxed);
+
+        let extensions = request.extensions_mut();
+        extensions.get_or_insert_default::<Assignments>().variants.insert(self.name.clone(), variant.clone());
+
+        if !persisted {
+            extensions.get_or_insert_default::<Unpersisted>().0.push(self.name.clone());
+        }
+
+        Guard::Accessible(request)
+    }
+}
@@ -0,0 +1,172 @@
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::{ACCEPT, ACCEPT_ENCODING, ACCEPT_LANGUAGE, USER_AGENT};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::util::fnv1a;
+use hyper::Version;
+
+/// User agent fragments of common automation tools and crawlers, matched case-insensitively.
+pub const AUTOMATION_AGENTS: &[&str] = &[
+    "bot", "crawler", "curl", "go-http-client", "headless", "httpclient", "java/", "libwww", "okhttp",
+    "python-requests", "python-urllib", "scrapy", "spider", "wget",
+];
+
+/// The parameters of a TLS ClientHello, as an extension of the request.
+///
+/// grazie doesn't terminate TLS itself, so this is inserted by whichever layer does. When present,
+/// a `FingerprintSeeder` includes it in the fingerprint as a JA3 string.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsClientHello {
+    /// The TLS version offered, such as `771` for TLS 1.2.
+    pub version: u16,
+
+    /// The offered cipher suites, in order.
+    pub ciphers: Vec<u16>,
+
+    /// The types of the offered extensions, in order.
+    pub extensions: Vec<u16>,
+
+    /// The offered elliptic curves, in order.
+    pub curves: Vec<u16>,
+
+    /// The offered elliptic curve point formats, in order.
+    pub point_formats: Vec<u8>,
+}
+
+impl TlsClientHello {
+    /// Formats these parameters as a JA3 string. GREASE values are skipped, so that clients
+    /// which randomize them keep a stable string.
+    pub fn ja3(&self) -> String {
+        fn join<T: ToString + Copy>(values: &[T], skip: impl Fn(T) -> bool) -> String {
+            values.iter().copied().filter(|value| !skip(*value)).map(|value| value.to_string()).collect::<Vec<_>>().join("-")
+        }
+
+        // GREASE values are two identical bytes with a lower nibble of `a`.
+        let grease = |value: u16| value & 0x0f0f == 0x0a0a && value >> 8 == value & 0xff;
+
+        format!(
+            "{},{},{},{},{}",
+            self.version,
+            join(&self.ciphers, grease),
+            join(&self.extensions, grease),
+            join(&self.curves, grease),
+            join(&self.point_formats, |_| false),
+        )
+    }
+}
+
+/// The fingerprint of a request, as an extension of the request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fingerprint {
+    /// A stable hash over the request's header order, user agent, accepted encodings and
+    /// languages, and TLS parameters, as 16 hex digits.
+    pub id: String,
+
+    /// The request's JA3 string, if its TLS parameters are known.
+    pub ja3: Option<String>,
+
+    /// How likely the request is to come from automation, between 0 and 100.
+    pub score: u8,
+}
+
+/// A `Seeder` which fingerprints requests, attaching a `Fingerprint` for downstream seeders and
+/// handlers to act on.
+///
+/// Clients of the same software send their headers in the same order, with the same values, so
+/// the fingerprint identifies the client's software (and TLS stack, if known) rather than the user.
+/// The score is a simple heuristic: points are added for automation user agents, and for headers
+/// every mainstream browser sends being missing.
+///
+/// Requests are only blocked if a blocking threshold is set.
+pub struct FingerprintSeeder {
+    block_above: Option<u8>,
+}
+
+impl FingerprintSeeder {
+    /// Constructs a new `FingerprintSeeder`, which doesn't block any requests.
+    pub fn new() -> FingerprintSeeder {
+        FingerprintSeeder {
+            block_above: None,
+        }
+    }
+
+    /// Blocks requests scoring above `score` with `403 Forbidden`.
+    pub fn block_above(mut self, score: u8) -> FingerprintSeeder {
+        self.block_above = Some(score);
+        self
+    }
+
+    /// Fingerprints a request.
+    pub fn fingerprint(&self, request: &HttpRequest<BoxBody>) -> Fingerprint {
+        let headers = request.headers();
+        let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+        let ja3 = request.extensions().get::<TlsClientHello>().map(TlsClientHello::ja3);
+
+        let mut material = String::new();
+        for name in headers.keys() {
+            material.push_str(name.as_str());
+            material.push(',');
+        }
+
+        for value in [header(USER_AGENT), header(ACCEPT_ENCODING), header(ACCEPT_LANGUAGE), ja3.as_deref()] {
+            material.push('|');
+            material.push_str(value.unwrap_or_default());
+        }
+
+        let mut score = 0u32;
+        match header(USER_AGENT).map(str::to_ascii_lowercase) {
+            Some(agent) if AUTOMATION_AGENTS.iter().any(|fragment| agent.contains(fragment)) => { score += 50; }
+            Some(_) => {}
+            None => { score += 40; }
+        }
+
+        for (name, points) in [(ACCEPT, 10), (ACCEPT_ENCODING, 15), (ACCEPT_LANGUAGE, 20)] {
+            if !headers.contains_key(name) {
+                score += points;
+            }
+        }
+
+        if request.version() <= Version::HTTP_10 {
+            score += 15;
+        }
+
+        Fingerprint {
+            id: format!("{:016x}", fnv1a(material.as_bytes())),
+            ja3,
+            score: score.min(100) as u8,
+        }
+    }
+}
+
+impl Default for FingerprintSeeder {
+    fn default() -> FingerprintSeeder {
+        FingerprintSeeder::new()
+    }
+}
+
+impl Seeder for FingerprintSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        let fingerprint = self.fingerprint(request);
+        let blocked = self.block_above.is_some_and(|threshold| fingerprint.score > threshold);
+        request.extensions_mut().insert(fingerprint);
+
+        if !blocked {
+            return Guard::Accessible(request);
+        }
+
+        let response = HttpResponse::builder().status(StatusCode::FORBIDDEN).body(BoxBody::empty()).unwrap();
+
+        Guard::Inaccessible {
+            request,
+            respondent: Respondent::Respond(Box::new(response)),
+            rejection: Rejection::new(StatusCode::FORBIDDEN, "automation_suspected", "The request appears to come from automation."),
+        }
+    }
+}
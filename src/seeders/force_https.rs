@@ -0,0 +1,188 @@
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::{HeaderValue, HOST, LOCATION, STRICT_TRANSPORT_SECURITY};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use hyper::http::uri::Authority;
+use std::time::Duration;
+
+/// The `Strict-Transport-Security` policy attached by a `ForceHttpsSeeder`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hsts {
+    /// How long browsers should only connect over https, sent in whole seconds.
+    pub max_age: Duration,
+
+    /// Whether the policy applies to every subdomain as well.
+    pub include_subdomains: bool,
+
+    /// Whether the domain opts in to browsers' HSTS preload lists.
+    pub preload: bool,
+}
+
+impl Hsts {
+    /// Constructs a new `Hsts` policy, which doesn't include subdomains or opt in to preloading.
+    pub fn new(max_age: Duration) -> Hsts {
+        Hsts {
+            max_age,
+            include_subdomains: false,
+            preload: false,
+        }
+    }
+
+    /// Formats this policy as a `Strict-Transport-Security` header value.
+    pub fn header_value(&self) -> HeaderValue {
+        let mut value = format!("max-age={}", self.max_age.as_secs());
+
+        if self.include_subdomains {
+            value.push_str("; includeSubDomains");
+        }
+
+        if self.preload {
+            value.push_str("; preload");
+        }
+
+        HeaderValue::try_from(value).unwrap()
+    }
+}
+
+/// A `Seeder` which redirects plaintext requests to their https equivalent.
+///
+/// Requests are considered secure when their URI has the `https` scheme, or (when trusted) when the
+/// `X-Forwarded-Proto` header is `https`. Plaintext requests are answered with
+/// `301 Moved Permanently`, preserving the host, path, and query.
+///
+/// Since there's no response stage to the request chain, HSTS headers are attached by passing
+/// secure responses to `ForceHttpsSeeder::secure_response` once they've been created.
+pub struct ForceHttpsSeeder {
+    https_port: Option<u16>,
+    trust_forwarded: bool,
+    exempt_prefixes: Vec<String>,
+    hsts: Option<Hsts>,
+}
+
+impl ForceHttpsSeeder {
+    /// Constructs a new `ForceHttpsSeeder`, redirecting to the default https port.
+    pub fn new() -> ForceHttpsSeeder {
+        ForceHttpsSeeder {
+            https_port: None,
+            trust_forwarded: false,
+            exempt_prefixes: Vec::new(),
+            hsts: None,
+        }
+    }
+
+    /// Sets a non-default port to redirect to.
+    pub fn https_port(mut self, port: u16) -> ForceHttpsSeeder {
+        self.https_port = Some(port).filter(|port| *port != 443);
+        self
+    }
+
+    /// Sets whether the `X-Forwarded-Proto` header is trusted. This should only be enabled behind a
+    /// proxy which terminates TLS and sets the header itself.
+    pub fn trust_forwarded(mut self, trust_forwarded: bool) -> ForceHttpsSeeder {
+        self.trust_forwarded = trust_forwarded;
+        self
+    }
+
+    /// Exempts every path starting with `prefix` from being redirected, such as
+    /// `/.well-known/acme-challenge/`.
+    pub fn exempt(mut self, prefix: impl Into<String>) -> ForceHttpsSeeder {
+        self.exempt_prefixes.push(prefix.into());
+        self
+    }
+
+    /// Sets the HSTS policy attached to secure responses.
+    pub fn hsts(mut self, hsts: Hsts) -> ForceHttpsSeeder {
+        self.hsts = Some(hsts);
+        self
+    }
+
+    /// Checks whether a request arrived over https.
+    pub fn is_secure(&self, request: &HttpRequest<BoxBody>) -> bool {
+        if request.uri().scheme_str() == Some("https") {
+            return true;
+        }
+
+        self.trust_forwarded
+            && request
+                .headers()
+                .get("x-forwarded-proto")
+                .and_then(|proto| proto.to_str().ok())
+                .is_some_and(|proto| proto.trim().eq_ignore_ascii_case("https"))
+    }
+
+    /// Attaches the HSTS header to a response, if one is configured and the request was secure.
+    /// HSTS headers are ignored by browsers over plaintext, so they're never sent there.
+    pub fn secure_response(&self, request: &HttpRequest<BoxBody>, response: &mut HttpResponse<BoxBody>) {
+        if let Some(hsts) = self.hsts.filter(|_| self.is_secure(request)) {
+            response.headers_mut().insert(STRICT_TRANSPORT_SECURITY, hsts.header_value());
+        }
+    }
+
+    /// Builds the https location a plaintext request is redirected to.
+    fn location(&self, request: &HttpRequest<BoxBody>) -> Option<HeaderValue> {
+        let host = request
+            .headers()
+            .get(HOST)
+            .and_then(|host| host.to_str().ok())
+            .or(request.uri().host())?;
+
+        // Drop any plaintext port from the host, since it won't be the https port.
+        let authority: Authority = host.parse().ok()?;
+        let host = authority.host();
+
+        let path = request.uri().path_and_query().map(|path| path.as_str()).unwrap_or("/");
+
+        let location = match self.https_port {
+            Some(port) => { format!("https://{host}:{port}{path}") }
+            None => { format!("https://{host}{path}") }
+        };
+
+        HeaderValue::try_from(location).ok()
+    }
+}
+
+impl Default for ForceHttpsSeeder {
+    fn default() -> ForceHttpsSeeder {
+        ForceHttpsSeeder::new()
+    }
+}
+
+impl Seeder for ForceHttpsSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        let path = request.uri().path();
+        if self.is_secure(request) || self.exempt_prefixes.iter().any(|prefix| path.starts_with(prefix.as_str())) {
+            return Guard::Accessible(request);
+        }
+
+        let response = match self.location(request) {
+            Some(location) => {
+                HttpResponse::builder()
+                    .status(StatusCode::MOVED_PERMANENTLY)
+                    .header(LOCATION, location)
+                    .body(BoxBody::empty())
+                    .unwrap()
+            }
+            None => {
+                HttpResponse::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(BoxBody::empty())
+                    .unwrap()
+            }
+        };
+
+        let status = response.status();
+
+        Guard::Inaccessible {
+            request,
+            respondent: Respondent::Respond(Box::new(response)),
+            rejection: Rejection::new(status, "https_required", "Plaintext requests are redirected to https."),
+        }
+    }
+}
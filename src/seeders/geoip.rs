@@ -0,0 +1,236 @@
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::HeaderName;
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::server::PeerAddr;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::{Mutex, MutexGuard};
+
+/// The `X-Forwarded-For` header.
+const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+
+/// The metric label used for requests whose country isn't known.
+pub const UNKNOWN_COUNTRY: &str = "unknown";
+
+/// Where a client IP is located, as an extension of the request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Geo {
+    /// The IP address which was looked up.
+    pub ip: Option<IpAddr>,
+
+    /// The ISO 3166-1 alpha-2 code of the IP's country, such as `NZ`.
+    pub country: Option<String>,
+
+    /// The number of the autonomous system the IP belongs to.
+    pub asn: Option<u32>,
+
+    /// The organization operating the autonomous system.
+    pub organization: Option<String>,
+}
+
+/// Looks IP addresses up in a geolocation database, for a `GeoIpSeeder`.
+pub trait GeoLookup: Send + Sync + 'static {
+    /// Looks up an IP address, returning `None` if it isn't in the database.
+    fn lookup(&self, ip: IpAddr) -> Option<Geo>;
+}
+
+/// A `GeoLookup` over MaxMind GeoIP2 or GeoLite2 databases.
+///
+/// Part of the `maxminddb` feature.
+#[cfg(feature = "maxminddb")]
+pub struct MaxMindLookup {
+    country: maxminddb::Reader<Vec<u8>>,
+    asn: Option<maxminddb::Reader<Vec<u8>>>,
+}
+
+#[cfg(feature = "maxminddb")]
+impl MaxMindLookup {
+    /// Opens a Country or City database.
+    pub fn open(
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<MaxMindLookup, maxminddb::MaxMindDBError> {
+        Ok(MaxMindLookup {
+            country: maxminddb::Reader::open_readfile(path)?,
+            asn: None,
+        })
+    }
+
+    /// Opens an ASN database, to look autonomous systems up in as well.
+    pub fn asn_database(
+        mut self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<MaxMindLookup, maxminddb::MaxMindDBError> {
+        self.asn = Some(maxminddb::Reader::open_readfile(path)?);
+        Ok(self)
+    }
+}
+
+#[cfg(feature = "maxminddb")]
+impl GeoLookup for MaxMindLookup {
+    fn lookup(&self, ip: IpAddr) -> Option<Geo> {
+        use maxminddb::geoip2;
+
+        let country = self.country.lookup::<geoip2::Country>(ip).ok();
+        let asn = self.asn.as_ref().and_then(|asn| asn.lookup::<geoip2::Asn>(ip).ok());
+
+        if country.is_none() && asn.is_none() {
+            return None;
+        }
+
+        Some(Geo {
+            ip: Some(ip),
+            country: country.and_then(|country| country.country?.iso_code.map(str::to_owned)),
+            asn: asn.as_ref().and_then(|asn| asn.autonomous_system_number),
+            organization: asn.and_then(|asn| asn.autonomous_system_organization.map(str::to_owned)),
+        })
+    }
+}
+
+/// A `Seeder` which resolves the effective client IP of each request to its country and
+/// autonomous system, attaching a `Geo` extension.
+///
+/// The effective client IP is the request's `PeerAddr`, unless the peer is a trusted proxy. Then,
+/// `X-Forwarded-For` is read from the right, skipping any other trusted proxies, so that clients
+/// can't spoof their IP by sending the header themselves.
+///
+/// Requests can be restricted by country, and are counted by country for metrics.
+pub struct GeoIpSeeder<L> {
+    lookup: L,
+    trusted_proxies: HashSet<IpAddr>,
+    allow: Option<HashSet<String>>,
+    deny: HashSet<String>,
+    allow_unknown: bool,
+    counts: Mutex<HashMap<String, u64>>,
+}
+
+impl<L: GeoLookup> GeoIpSeeder<L> {
+    /// Constructs a new `GeoIpSeeder`, which doesn't restrict any countries.
+    pub fn new(lookup: L) -> GeoIpSeeder<L> {
+        GeoIpSeeder {
+            lookup,
+            trusted_proxies: HashSet::new(),
+            allow: None,
+            deny: HashSet::new(),
+            allow_unknown: true,
+            counts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Trusts a proxy to report client IPs through `X-Forwarded-For`.
+    pub fn trust_proxy(mut self, proxy: IpAddr) -> GeoIpSeeder<L> {
+        self.trusted_proxies.insert(proxy);
+        self
+    }
+
+    /// Only allows requests from the provided countries, by ISO 3166-1 alpha-2 code. Requests whose
+    /// country isn't known are then denied, unless `allow_unknown` is set afterwards.
+    pub fn allow_countries<C: Into<String>>(
+        mut self,
+        countries: impl IntoIterator<Item = C>,
+    ) -> GeoIpSeeder<L> {
+        let countries = countries.into_iter().map(|country| country.into().to_ascii_uppercase());
+        self.allow = Some(countries.collect());
+        self.allow_unknown = false;
+        self
+    }
+
+    /// Denies requests from the provided countries, by ISO 3166-1 alpha-2 code.
+    pub fn deny_countries<C: Into<String>>(
+        mut self,
+        countries: impl IntoIterator<Item = C>,
+    ) -> GeoIpSeeder<L> {
+        self.deny.extend(countries.into_iter().map(|country| country.into().to_ascii_uppercase()));
+        self
+    }
+
+    /// Sets whether requests whose country isn't known are allowed.
+    pub fn allow_unknown(mut self, allow_unknown: bool) -> GeoIpSeeder<L> {
+        self.allow_unknown = allow_unknown;
+        self
+    }
+
+    /// Gets the number of requests seen per country, with `UNKNOWN_COUNTRY` for requests whose
+    /// country isn't known.
+    pub fn metrics(&self) -> HashMap<String, u64> {
+        self.lock().clone()
+    }
+
+    /// Resolves the effective client IP of a request.
+    pub fn client_ip(&self, request: &HttpRequest<BoxBody>) -> Option<IpAddr> {
+        let peer = request.extensions().get::<PeerAddr>()?.0.ip();
+
+        if !self.trusted_proxies.contains(&peer) {
+            return Some(peer);
+        }
+
+        let forwarded: Vec<IpAddr> = request
+            .headers()
+            .get_all(X_FORWARDED_FOR)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map_while(|hop| hop.trim().parse().ok())
+            .collect();
+
+        let client = forwarded.into_iter().rev().find(|hop| !self.trusted_proxies.contains(hop));
+        Some(client.unwrap_or(peer))
+    }
+
+    /// Checks whether a country may access the application.
+    fn permits(&self, country: Option<&str>) -> bool {
+        match country {
+            Some(country) => {
+                !self.deny.contains(country)
+                    && self.allow.as_ref().is_none_or(|allow| allow.contains(country))
+            }
+            None => { self.allow_unknown }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, u64>> {
+        self.counts.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<L: GeoLookup> Seeder for GeoIpSeeder<L> {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        let ip = self.client_ip(request);
+        let geo = ip.and_then(|ip| self.lookup.lookup(ip)).unwrap_or(Geo {
+            ip,
+            ..Geo::default()
+        });
+
+        let label = geo.country.clone().unwrap_or_else(|| UNKNOWN_COUNTRY.to_owned());
+        *self.lock().entry(label).or_default() += 1;
+
+        let permitted = self.permits(geo.country.as_deref());
+        request.extensions_mut().insert(geo);
+
+        if permitted {
+            return Guard::Accessible(request);
+        }
+
+        let response = HttpResponse::builder()
+            .status(StatusCode::FORBIDDEN)
+            .body(BoxBody::empty())
+            .unwrap();
+
+        Guard::Inaccessible {
+            request,
+            respondent: Respondent::Respond(Box::new(response)),
+            rejection: Rejection::new(
+                StatusCode::FORBIDDEN,
+                "country_blocked",
+                "Requests from this country aren't allowed.",
+            ),
+        }
+    }
+}
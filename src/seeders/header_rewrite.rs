@@ -0,0 +1,171 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::header::{HeaderMap, HeaderName, HeaderValue};
+use crate::http::{HttpRequest, HttpResponse};
+
+#[cfg(feature = "regex")]
+use regex::Regex;
+
+/// A single header manipulation applied by a `HeaderRewriteSeeder`.
+pub enum HeaderRule {
+    /// Appends a value to a header, keeping any existing values.
+    Add(HeaderName, HeaderValue),
+
+    /// Sets a header to a single value, replacing any existing values.
+    Set(HeaderName, HeaderValue),
+
+    /// Sets a header only if it isn't already present.
+    SetIfMissing(HeaderName, HeaderValue),
+
+    /// Removes every value of a header.
+    Remove(HeaderName),
+
+    /// Moves every value of a header over to a different header, replacing the destination's values.
+    Rename(HeaderName, HeaderName),
+
+    /// Copies every value of a header over to a different header, replacing the destination's
+    /// values.
+    Copy(HeaderName, HeaderName),
+
+    /// Rewrites every value of a header which matches a regular expression. The replacement may
+    /// refer to capture groups, such as `$1` or `${name}`. Values which would become invalid
+    /// header values are left as they were.
+    ///
+    /// Part of the `regex` feature.
+    #[cfg(feature = "regex")]
+    Replace(HeaderName, Regex, String),
+}
+
+impl HeaderRule {
+    /// Applies this rule to a set of headers.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        match self {
+            HeaderRule::Add(name, value) => { headers.append(name, value.clone()); }
+            HeaderRule::Set(name, value) => { headers.insert(name, value.clone()); }
+            HeaderRule::SetIfMissing(name, value) => {
+                if !headers.contains_key(name) {
+                    headers.insert(name, value.clone());
+                }
+            }
+            HeaderRule::Remove(name) => { headers.remove(name); }
+            HeaderRule::Rename(from, to) => {
+                let values: Vec<HeaderValue> = headers.get_all(from).iter().cloned().collect();
+
+                if !values.is_empty() {
+                    headers.remove(from);
+                    replace_all(headers, to, values);
+                }
+            }
+            HeaderRule::Copy(from, to) => {
+                let values: Vec<HeaderValue> = headers.get_all(from).iter().cloned().collect();
+
+                if !values.is_empty() {
+                    replace_all(headers, to, values);
+                }
+            }
+
+            #[cfg(feature = "regex")]
+            HeaderRule::Replace(name, pattern, replacement) => {
+                let values: Vec<HeaderValue> = headers
+                    .get_all(name)
+                    .iter()
+                    .map(|value| {
+                        let Ok(text) = value.to_str() else { return value.clone(); };
+                        let rewritten = pattern.replace_all(text, replacement.as_str());
+
+                        HeaderValue::from_str(&rewritten).unwrap_or_else(|_| value.clone())
+                    })
+                    .collect();
+
+                if !values.is_empty() {
+                    replace_all(headers, name, values);
+                }
+            }
+        }
+    }
+}
+
+/// Replaces every value of a header with the provided values.
+fn replace_all(headers: &mut HeaderMap, name: &HeaderName, values: Vec<HeaderValue>) {
+    headers.remove(name);
+
+    for value in values {
+        headers.append(name, value);
+    }
+}
+
+/// A `Seeder` which applies a declarative set of header rules to requests, and to responses.
+///
+/// Request rules are applied as the request passes through the seeder. Since there's no response
+/// stage to the request chain, response rules are applied by passing the response to
+/// `HeaderRewriteSeeder::rewrite_response` once it has been created.
+///
+/// ```
+/// use grazie::http::header::{HeaderName, HeaderValue, SERVER};
+/// use grazie::seeders::header_rewrite::HeaderRule;
+/// use grazie::seeders::HeaderRewriteSeeder;
+///
+/// let seeder = HeaderRewriteSeeder::new()
+///     .request(HeaderRule::Rename(
+///         HeaderName::from_static("x-real-ip"),
+///         HeaderName::from_static("x-client-ip"),
+///     ))
+///     .response(HeaderRule::Remove(SERVER))
+///     .response(HeaderRule::Set(
+///         HeaderName::from_static("x-frame-options"),
+///         HeaderValue::from_static("DENY"),
+///     ));
+/// ```
+#[derive(Default)]
+pub struct HeaderRewriteSeeder {
+    request_rules: Vec<HeaderRule>,
+    response_rules: Vec<HeaderRule>,
+}
+
+impl HeaderRewriteSeeder {
+    /// Constructs a new `HeaderRewriteSeeder` without any rules.
+    pub fn new() -> HeaderRewriteSeeder {
+        HeaderRewriteSeeder::default()
+    }
+
+    /// Adds a rule applied to requests. Rules are applied in the order they're added.
+    pub fn request(mut self, rule: HeaderRule) -> HeaderRewriteSeeder {
+        self.request_rules.push(rule);
+        self
+    }
+
+    /// Adds a rule applied to responses. Rules are applied in the order they're added.
+    pub fn response(mut self, rule: HeaderRule) -> HeaderRewriteSeeder {
+        self.response_rules.push(rule);
+        self
+    }
+
+    /// Applies the request rules to a request.
+    pub fn rewrite_request(&self, request: &mut HttpRequest<BoxBody>) {
+        for rule in &self.request_rules {
+            rule.apply(request.headers_mut());
+        }
+    }
+
+    /// Applies the response rules to a response.
+    pub fn rewrite_response(&self, response: &mut HttpResponse<BoxBody>) {
+        for rule in &self.response_rules {
+            rule.apply(response.headers_mut());
+        }
+    }
+}
+
+impl Seeder for HeaderRewriteSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        self.rewrite_request(request);
+
+        Guard::Accessible(request)
+    }
+}
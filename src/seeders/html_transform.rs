@@ -0,0 +1,433 @@
+use crate::auth::session::token;
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::header::{CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE};
+use crate::http::{HttpRequest, HttpResponse};
+use std::ops::Range;
+use std::sync::Arc;
+
+/// The `nonce` attribute value which marks a `<script>` or `<style>` tag for a CSP nonce, as in
+/// `<script nonce="{{nonce}}">`.
+pub const NONCE_PLACEHOLDER: &str = "{{nonce}}";
+
+/// Where an `HtmlRewriter` injects a snippet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Position {
+    /// Directly after the first start tag with this name, such as `AfterOpen("body")`.
+    AfterOpen(String),
+
+    /// Directly before the first end tag with this name, such as `BeforeClose("head")`. If the end
+    /// tag never appears, the snippet is appended to the end of the document.
+    BeforeClose(String),
+}
+
+/// A snippet injected by an `HtmlRewriter`.
+#[derive(Debug, Clone)]
+struct Injection {
+    position: Position,
+    snippet: Arc<[u8]>,
+}
+
+/// The parsing state of an `HtmlRewriter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Reading text content.
+    Text,
+
+    /// Read a `<`, and waiting on the next byte to decide whether it begins a tag.
+    TagOpen,
+
+    /// Reading a tag, up until its closing `>`. Holds the quote of the attribute value being read.
+    Tag(Option<u8>),
+
+    /// Reading a `<!-- ... -->` comment.
+    Comment,
+
+    /// Reading the raw text contents of a `<script>` or `<style>` element.
+    RawText,
+}
+
+/// A streaming HTML rewriter, which injects snippets and CSP nonces into a document as it's fed
+/// through in chunks.
+///
+/// Nonces only fill in `<script>` and `<style>` tags which opt in with `NONCE_PLACEHOLDER`, along
+/// with every such tag in the injected snippets. Other tags in the document are never nonced, so
+/// that markup reflected into the page isn't trusted by the page's CSP.
+///
+/// The rewriter only tokenizes tags (never building a document tree), so it holds on to at most
+/// a single tag between chunks, rather than buffering the whole page.
+#[derive(Debug, Clone)]
+pub struct HtmlRewriter {
+    injections: Vec<Injection>,
+    applied: Vec<bool>,
+    nonce: Option<String>,
+    nonce_all: bool,
+    state: State,
+    buffer: Vec<u8>,
+    raw_end: &'static [u8],
+}
+
+impl HtmlRewriter {
+    /// Constructs a new `HtmlRewriter`, which doesn't change the document.
+    pub fn new() -> HtmlRewriter {
+        HtmlRewriter {
+            injections: Vec::new(),
+            applied: Vec::new(),
+            nonce: None,
+            nonce_all: false,
+            state: State::Text,
+            buffer: Vec::new(),
+            raw_end: b"",
+        }
+    }
+
+    /// Injects a snippet at the provided position.
+    pub fn inject(mut self, position: Position, snippet: impl Into<Arc<[u8]>>) -> HtmlRewriter {
+        let position = match position {
+            Position::AfterOpen(name) => { Position::AfterOpen(name.to_ascii_lowercase()) }
+            Position::BeforeClose(name) => { Position::BeforeClose(name.to_ascii_lowercase()) }
+        };
+
+        self.injections.push(Injection {
+            position,
+            snippet: snippet.into(),
+        });
+        self.applied.push(false);
+        self
+    }
+
+    /// Sets the nonce which replaces `NONCE_PLACEHOLDER` in `<script>` and `<style>` start tags,
+    /// and which is added to those tags in injected snippets.
+    pub fn nonce(mut self, nonce: impl Into<String>) -> HtmlRewriter {
+        self.nonce = Some(nonce.into());
+        self
+    }
+
+    /// Rewrites the next chunk of the document, returning the rewritten bytes which are ready to
+    /// be sent.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(chunk.len());
+
+        for byte in chunk.iter().copied() {
+            match self.state {
+                State::Text => {
+                    if byte == b'<' {
+                        self.buffer.push(byte);
+                        self.state = State::TagOpen;
+                    } else {
+                        out.push(byte);
+                    }
+                }
+                State::TagOpen => {
+                    if byte.is_ascii_alphabetic() || matches!(byte, b'/' | b'!' | b'?') {
+                        self.buffer.push(byte);
+                        self.state = State::Tag(None);
+                    } else {
+                        // A `<` which doesn't begin a tag is just text.
+                        out.append(&mut self.buffer);
+                        self.state = State::Text;
+
+                        if byte == b'<' {
+                            self.buffer.push(byte);
+                            self.state = State::TagOpen;
+                        } else {
+                            out.push(byte);
+                        }
+                    }
+                }
+                State::Tag(quote) => {
+                    self.buffer.push(byte);
+
+                    if self.buffer == b"<!--" {
+                        self.state = State::Comment;
+                        continue;
+                    }
+
+                    self.state = match (quote, byte) {
+                        (Some(quote), byte) if byte == quote => { State::Tag(None) }
+                        (Some(quote), _) => { State::Tag(Some(quote)) }
+                        (None, b'"' | b'\'') => { State::Tag(Some(byte)) }
+                        (None, b'>') => {
+                            self.finish_tag(&mut out);
+                            continue;
+                        }
+                        (None, _) => { State::Tag(None) }
+                    };
+                }
+                State::Comment => {
+                    self.buffer.push(byte);
+
+                    if self.buffer.ends_with(b"-->") {
+                        out.append(&mut self.buffer);
+                        self.state = State::Text;
+                    }
+                }
+                State::RawText => {
+                    let position = self.buffer.len();
+
+                    if position < self.raw_end.len() && byte.eq_ignore_ascii_case(&self.raw_end[position]) {
+                        self.buffer.push(byte);
+
+                        if self.buffer.len() == self.raw_end.len() {
+                            self.state = State::Tag(None);
+                        }
+                    } else {
+                        out.append(&mut self.buffer);
+
+                        if byte == b'<' {
+                            self.buffer.push(byte);
+                        } else {
+                            out.push(byte);
+                        }
+                    }
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Finishes the document, returning any bytes still held by the rewriter, along with any
+    /// `BeforeClose` snippets whose end tag never appeared.
+    pub fn finish(&mut self) -> Vec<u8> {
+        let mut out = std::mem::take(&mut self.buffer);
+        self.state = State::Text;
+        self.apply(&mut out, |position| matches!(position, Position::BeforeClose(_)));
+
+        out
+    }
+
+    /// Rewrites a whole document in one go.
+    pub fn rewrite(&mut self, document: &[u8]) -> Vec<u8> {
+        let mut out = self.feed(document);
+        out.append(&mut self.finish());
+
+        out
+    }
+
+    /// Handles a complete tag held in the buffer, applying injections and nonces.
+    fn finish_tag(&mut self, out: &mut Vec<u8>) {
+        let mut tag = std::mem::take(&mut self.buffer);
+        self.state = State::Text;
+
+        let closing = tag.get(1) == Some(&b'/');
+        let name: Vec<u8> = tag[if closing { 2 } else { 1 }..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_alphanumeric() || **byte == b'-')
+            .map(u8::to_ascii_lowercase)
+            .collect();
+        let name = String::from_utf8_lossy(&name).into_owned();
+
+        let raw_text = !closing && matches!(name.as_str(), "script" | "style");
+
+        if let Some(nonce) = self.nonce.as_ref().filter(|_| raw_text) {
+            match nonce_value(&tag) {
+                Some(value) if tag[value.clone()] == *NONCE_PLACEHOLDER.as_bytes() => {
+                    tag.splice(value, nonce.bytes());
+                }
+                None if self.nonce_all => {
+                    let end = match tag.ends_with(b"/>") {
+                        true => { tag.len() - 2 }
+                        false => { tag.len() - 1 }
+                    };
+                    let attribute = format!(" nonce=\"{nonce}\"");
+                    tag.splice(end..end, attribute.into_bytes());
+                }
+                _ => {}
+            }
+        }
+
+        if closing {
+            self.apply(out, |position| matches!(position, Position::BeforeClose(tag) if *tag == name));
+        }
+
+        out.append(&mut tag);
+
+        if !closing {
+            self.apply(out, |position| matches!(position, Position::AfterOpen(tag) if *tag == name));
+        }
+
+        if raw_text {
+            self.raw_end = match name.as_str() {
+                "script" => { b"</script" }
+                _ => { b"</style" }
+            };
+            self.state = State::RawText;
+        }
+    }
+
+    /// Emits every unapplied snippet whose position matches, adding the nonce to their tags.
+    fn apply(&mut self, out: &mut Vec<u8>, matches: impl Fn(&Position) -> bool) {
+        for (injection, applied) in self.injections.iter().zip(self.applied.iter_mut()) {
+            if *applied || !matches(&injection.position) {
+                continue;
+            }
+
+            match &self.nonce {
+                Some(nonce) => {
+                    let mut snippet = HtmlRewriter::new().nonce(nonce.clone());
+                    snippet.nonce_all = true;
+                    out.append(&mut snippet.rewrite(&injection.snippet));
+                }
+                None => { out.extend_from_slice(&injection.snippet); }
+            }
+            *applied = true;
+        }
+    }
+}
+
+/// Finds the value of a start tag's `nonce` attribute, as a range of the tag's bytes. An attribute
+/// without a value has an empty range.
+fn nonce_value(tag: &[u8]) -> Option<Range<usize>> {
+    let space = |byte: u8| byte.is_ascii_whitespace();
+    let mut at = scan(tag, 1, |byte| space(byte) || matches!(byte, b'/' | b'>'));
+
+    loop {
+        at = scan(tag, at, |byte| !space(byte) && byte != b'/');
+        if at >= tag.len() || tag[at] == b'>' {
+            return None;
+        }
+
+        let name = at..scan(tag, at, |byte| space(byte) || matches!(byte, b'=' | b'/' | b'>'));
+        at = scan(tag, name.end, |byte| !space(byte));
+
+        let value = if tag.get(at) == Some(&b'=') {
+            at = scan(tag, at + 1, |byte| !space(byte));
+
+            match tag.get(at).copied() {
+                Some(quote @ (b'"' | b'\'')) => {
+                    let value = at + 1..scan(tag, at + 1, |byte| byte == quote);
+                    at = value.end + 1;
+                    value
+                }
+                _ => {
+                    let value = at..scan(tag, at, |byte| space(byte) || byte == b'>');
+                    at = value.end;
+                    value
+                }
+            }
+        } else {
+            at..at
+        };
+
+        if tag[name].eq_ignore_ascii_case(b"nonce") {
+            return Some(value);
+        }
+    }
+}
+
+/// Finds the first byte from `at` which matches `stop`, or the end of the tag.
+fn scan(tag: &[u8], at: usize, stop: impl Fn(u8) -> bool) -> usize {
+    let rest = tag.get(at..).unwrap_or_default();
+
+    rest.iter().position(|byte| stop(*byte)).map_or(tag.len(), |offset| at + offset)
+}
+
+impl Default for HtmlRewriter {
+    fn default() -> HtmlRewriter {
+        HtmlRewriter::new()
+    }
+}
+
+/// The CSP nonce generated for a request by an `HtmlTransformSeeder`. This is stored in the
+/// request's extensions, so that handlers can reference it in their `Content-Security-Policy`
+/// header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CspNonce(pub String);
+
+/// An opt-in `Seeder` which rewrites HTML responses, injecting snippets (such as a script tag, or
+/// a live-reload client in development) and CSP nonces.
+///
+/// When nonces are enabled, a fresh `CspNonce`, with 256 bits from the operating system's CSPRNG,
+/// is generated for each request as it passes through the seeder. It fills in the `<script>` and
+/// `<style>` tags marked with `NONCE_PLACEHOLDER`, and those in the injected snippets.
+///
+/// Since there's no response stage to the request chain, responses are rewritten by passing them
+/// to `HtmlTransformSeeder::transform_response` once they've been created. For bodies produced in
+/// chunks, `HtmlTransformSeeder::rewriter` provides a streaming `HtmlRewriter`.
+pub struct HtmlTransformSeeder {
+    injections: Vec<Injection>,
+    nonces: bool,
+}
+
+impl HtmlTransformSeeder {
+    /// Constructs a new `HtmlTransformSeeder`, which doesn't change responses.
+    pub fn new() -> HtmlTransformSeeder {
+        HtmlTransformSeeder {
+            injections: Vec::new(),
+            nonces: false,
+        }
+    }
+
+    /// Injects a snippet at the provided position in every HTML response.
+    pub fn inject(mut self, position: Position, snippet: impl Into<Arc<[u8]>>) -> HtmlTransformSeeder {
+        self.injections.push(Injection {
+            position,
+            snippet: snippet.into(),
+        });
+        self
+    }
+
+    /// Enables generating a CSP nonce per request, which is added to the injected snippets and the
+    /// tags marked with `NONCE_PLACEHOLDER`.
+    pub fn nonces(mut self, nonces: bool) -> HtmlTransformSeeder {
+        self.nonces = nonces;
+        self
+    }
+
+    /// Creates a streaming rewriter for a request's response.
+    pub fn rewriter(&self, request: &HttpRequest<BoxBody>) -> HtmlRewriter {
+        let mut rewriter = self.injections
+            .iter()
+            .fold(HtmlRewriter::new(), |rewriter, injection| {
+                rewriter.inject(injection.position.clone(), injection.snippet.clone())
+            });
+
+        if let Some(CspNonce(nonce)) = request.extensions().get::<CspNonce>() {
+            rewriter = rewriter.nonce(nonce.clone());
+        }
+
+        rewriter
+    }
+
+    /// Rewrites a response, if it's an uncompressed HTML response.
+    pub fn transform_response(&self, request: &HttpRequest<BoxBody>, response: &mut HttpResponse<BoxBody>) {
+        let html = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .is_some_and(|content_type| content_type.trim_start().starts_with("text/html"));
+
+        if !html || response.headers().contains_key(CONTENT_ENCODING) {
+            return;
+        }
+
+        let rewritten = self.rewriter(request).rewrite(response.body().raw_bytes());
+
+        response.headers_mut().insert(CONTENT_LENGTH, rewritten.len().into());
+        *response.body_mut() = BoxBody::new(rewritten.into_boxed_slice());
+    }
+}
+
+impl Default for HtmlTransformSeeder {
+    fn default() -> HtmlTransformSeeder {
+        HtmlTransformSeeder::new()
+    }
+}
+
+impl Seeder for HtmlTransformSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        if self.nonces {
+            request.extensions_mut().insert(CspNonce(token()));
+        }
+
+        Guard::Accessible(request)
+    }
+}
@@ -0,0 +1,362 @@
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::{HeaderMap, HeaderName, HeaderValue};
+use crate::http::{HttpRequest, HttpResponse, Method, StatusCode};
+use crate::server::PeerAddr;
+use crate::util::fnv1a;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// The name of the header which clients send idempotency keys in.
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// The name of the header which is set on replayed responses.
+pub const IDEMPOTENT_REPLAYED: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// A response which has been stored against an idempotency key.
+#[derive(Debug, Clone)]
+pub struct StoredResponse {
+    /// The status of the stored response.
+    pub status: StatusCode,
+
+    /// The headers of the stored response.
+    pub headers: HeaderMap,
+
+    /// The body of the stored response.
+    pub body: Box<[u8]>,
+
+    /// The FNV-1a hash of the body of the request which the response answered.
+    pub request_hash: u64,
+}
+
+impl StoredResponse {
+    /// Copies the response to a request, so that it can be stored.
+    pub fn from_response(
+        request: &HttpRequest<BoxBody>,
+        response: &HttpResponse<BoxBody>,
+    ) -> StoredResponse {
+        StoredResponse {
+            request_hash: fnv1a(request.body().raw_bytes()),
+            status: response.status(),
+            headers: response.headers().clone(),
+            body: response.body().raw_bytes().into(),
+        }
+    }
+
+    /// Rebuilds the stored response, so that it can be replayed.
+    pub fn to_response(&self) -> HttpResponse<BoxBody> {
+        let mut response = HttpResponse::new(BoxBody::new(self.body.clone()));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+
+        response
+    }
+
+    /// Encodes the stored response as bytes, for stores which keep responses outside the process.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.body.len() + 64);
+        bytes.extend_from_slice(&self.status.as_u16().to_be_bytes());
+        bytes.extend_from_slice(&self.request_hash.to_be_bytes());
+        bytes.extend_from_slice(&(self.headers.len() as u32).to_be_bytes());
+
+        for (name, value) in &self.headers {
+            for part in [name.as_str().as_bytes(), value.as_bytes()] {
+                bytes.extend_from_slice(&(part.len() as u32).to_be_bytes());
+                bytes.extend_from_slice(part);
+            }
+        }
+
+        bytes.extend_from_slice(&self.body);
+        bytes
+    }
+
+    /// Decodes a stored response encoded by `StoredResponse::to_bytes`, returning `None` if the
+    /// bytes are malformed.
+    pub fn from_bytes(bytes: &[u8]) -> Option<StoredResponse> {
+        fn take<'a>(bytes: &mut &'a [u8], count: usize) -> Option<&'a [u8]> {
+            let (taken, rest) = bytes.split_at_checked(count)?;
+            *bytes = rest;
+            Some(taken)
+        }
+
+        fn length(bytes: &mut &[u8]) -> Option<usize> {
+            Some(u32::from_be_bytes(take(bytes, 4)?.try_into().ok()?) as usize)
+        }
+
+        fn part<'a>(bytes: &mut &'a [u8]) -> Option<&'a [u8]> {
+            let length = length(bytes)?;
+            take(bytes, length)
+        }
+
+        let mut bytes = bytes;
+        let status = StatusCode::from_u16(u16::from_be_bytes(take(&mut bytes, 2)?.try_into().ok()?)).ok()?;
+        let request_hash = u64::from_be_bytes(take(&mut bytes, 8)?.try_into().ok()?);
+        let count = length(&mut bytes)?;
+
+        let mut headers = HeaderMap::new();
+        for _ in 0..count {
+            let name = HeaderName::from_bytes(part(&mut bytes)?).ok()?;
+            headers.append(name, HeaderValue::from_bytes(part(&mut bytes)?).ok()?);
+        }
+
+        Some(StoredResponse {
+            status,
+            headers,
+            body: bytes.into(),
+            request_hash,
+        })
+    }
+}
+
+/// The state of an idempotency key, as returned by `IdempotencyStore::begin`.
+pub enum Lookup {
+    /// The key hasn't been seen before (or has expired), and has now been marked as in-flight.
+    New,
+
+    /// A request with this key is currently being handled.
+    InFlight,
+
+    /// A request with this key has already completed with the contained response.
+    Completed(StoredResponse),
+}
+
+/// Storage for idempotency keys and their responses.
+///
+/// Implementations must make `begin` atomic, so that two concurrent requests with the same key can
+/// never both observe `Lookup::New`.
+pub trait IdempotencyStore: Send + Sync {
+    /// Looks up a key, marking it as in-flight for `ttl` if it hasn't been seen before.
+    fn begin(&self, key: &str, ttl: Duration) -> impl Future<Output = Lookup> + Send;
+
+    /// Stores the response for an in-flight key, to be replayed for `ttl`.
+    fn complete(&self, key: &str, response: StoredResponse, ttl: Duration) -> impl Future<Output = ()> + Send;
+
+    /// Releases an in-flight key without storing a response, allowing it to be retried.
+    fn abandon(&self, key: &str) -> impl Future<Output = ()> + Send;
+}
+
+/// The state of a key held by a `MemoryIdempotencyStore`.
+enum Entry {
+    InFlight,
+    Completed(StoredResponse),
+}
+
+/// An in-memory `IdempotencyStore`, suitable for single-instance deployments.
+#[derive(Default)]
+pub struct MemoryIdempotencyStore {
+    entries: Mutex<HashMap<String, (Entry, Instant)>>,
+}
+
+impl MemoryIdempotencyStore {
+    /// Constructs a new, empty `MemoryIdempotencyStore`.
+    pub fn new() -> MemoryIdempotencyStore {
+        MemoryIdempotencyStore::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, (Entry, Instant)>> {
+        self.entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl IdempotencyStore for MemoryIdempotencyStore {
+    async fn begin(&self, key: &str, ttl: Duration) -> Lookup {
+        let now = Instant::now();
+        let mut entries = self.lock();
+        entries.retain(|_, (_, expires)| *expires > now);
+
+        match entries.get(key) {
+            Some((Entry::InFlight, _)) => { Lookup::InFlight }
+            Some((Entry::Completed(response), _)) => { Lookup::Completed(response.clone()) }
+            None => {
+                entries.insert(key.to_owned(), (Entry::InFlight, now + ttl));
+                Lookup::New
+            }
+        }
+    }
+
+    async fn complete(&self, key: &str, response: StoredResponse, ttl: Duration) {
+        self.lock().insert(key.to_owned(), (Entry::Completed(response), Instant::now() + ttl));
+    }
+
+    async fn abandon(&self, key: &str) {
+        self.lock().remove(key);
+    }
+}
+
+/// Gets the client a request's idempotency key is scoped to.
+type Scope = Box<dyn Fn(&HttpRequest<BoxBody>) -> Option<String> + Send + Sync>;
+
+/// The idempotency key of a request which is being handled for the first time. This is stored in
+/// the request's extensions by an `IdempotencySeeder`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdempotencyKey(pub String);
+
+/// A `Seeder` which makes retries of `POST` and `PATCH` requests safe, by replaying the first
+/// response sent for a given `Idempotency-Key`.
+///
+/// - Requests with a key that hasn't been seen are accepted, and the key is marked as in-flight.
+/// - Requests with an in-flight key are rejected with `409 Conflict`.
+/// - Requests with a completed key are answered with the stored response, with the
+///   `Idempotent-Replayed: true` header set, unless their body differs from the first request's.
+///   Then they're rejected with `422 Unprocessable Entity`.
+///
+/// Keys are scoped to the client which sent them, by the request's `PeerAddr`'s IP unless another
+/// scope is provided (such as a user's ID), so that clients can't replay each other's responses.
+/// Requests without a scope are handled as if they carried no key.
+///
+/// Once a first-time request has been handled, its response must be handed back through
+/// `IdempotencySeeder::complete`, which stores it for replay (or `abandon`, if it shouldn't be).
+pub struct IdempotencySeeder<S> {
+    store: S,
+    scope: Scope,
+    methods: Vec<Method>,
+    in_flight_ttl: Duration,
+    ttl: Duration,
+}
+
+impl<S: IdempotencyStore> IdempotencySeeder<S> {
+    /// Constructs a new `IdempotencySeeder`, keeping responses in the provided store for 24 hours.
+    pub fn new(store: S) -> IdempotencySeeder<S> {
+        IdempotencySeeder {
+            store,
+            scope: Box::new(|request| {
+                request.extensions().get::<PeerAddr>().map(|peer| peer.0.ip().to_string())
+            }),
+            methods: vec![Method::POST, Method::PATCH],
+            in_flight_ttl: Duration::from_secs(60),
+            ttl: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+
+    /// Sets how the client a request's key is scoped to is found, such as from its session.
+    pub fn scope<F>(mut self, scope: F) -> IdempotencySeeder<S>
+    where
+        F: Fn(&HttpRequest<BoxBody>) -> Option<String> + Send + Sync + 'static,
+    {
+        self.scope = Box::new(scope);
+        self
+    }
+
+    /// Sets how long stored responses are replayed for.
+    pub fn ttl(mut self, ttl: Duration) -> IdempotencySeeder<S> {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sets how long a key stays in-flight before it's assumed its request was lost.
+    pub fn in_flight_ttl(mut self, ttl: Duration) -> IdempotencySeeder<S> {
+        self.in_flight_ttl = ttl;
+        self
+    }
+
+    /// Sets the methods which idempotency keys are recognized on.
+    pub fn methods(mut self, methods: impl IntoIterator<Item = Method>) -> IdempotencySeeder<S> {
+        self.methods = methods.into_iter().collect();
+        self
+    }
+
+    /// Stores the response to a first-time request, so that it is replayed for retries.
+    ///
+    /// Does nothing if the request didn't carry a new idempotency key.
+    pub async fn complete(&self, request: &HttpRequest<BoxBody>, response: &HttpResponse<BoxBody>) {
+        if let Some(IdempotencyKey(key)) = request.extensions().get::<IdempotencyKey>() {
+            let stored = StoredResponse::from_response(request, response);
+            self.store.complete(key, stored, self.ttl).await;
+        }
+    }
+
+    /// Releases the key of a first-time request without storing its response, so that a retry may
+    /// be handled again. This should be used when a request fails in a way that is safe to retry.
+    pub async fn abandon(&self, request: &HttpRequest<BoxBody>) {
+        if let Some(IdempotencyKey(key)) = request.extensions().get::<IdempotencyKey>() {
+            self.store.abandon(key).await;
+        }
+    }
+
+    /// Scopes a client-provided key to the client, method and path it was sent to.
+    fn scoped_key(&self, request: &HttpRequest<BoxBody>, key: &str) -> Option<String> {
+        let scope = (self.scope)(request)?;
+        let (method, path) = (request.method(), request.uri().path());
+
+        // The scope is length-prefixed, so that a scope containing spaces can't imitate another.
+        Some(format!("{}:{scope} {method} {path} {key}", scope.len()))
+    }
+}
+
+impl<S: IdempotencyStore> Seeder for IdempotencySeeder<S> {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        if !self.methods.contains(request.method()) {
+            return Guard::Accessible(request);
+        }
+
+        let key = request.headers().get(IDEMPOTENCY_KEY).and_then(|key| key.to_str().ok());
+        let key = match key.and_then(|key| self.scoped_key(request, key)) {
+            Some(key) => { key }
+            None => { return Guard::Accessible(request); }
+        };
+
+        let body_hash = fnv1a(request.body().raw_bytes());
+
+        match self.store.begin(&key, self.in_flight_ttl).await {
+            Lookup::New => {
+                request.extensions_mut().insert(IdempotencyKey(key));
+
+                Guard::Accessible(request)
+            }
+            Lookup::InFlight => {
+                let response = HttpResponse::builder()
+                    .status(StatusCode::CONFLICT)
+                    .body(BoxBody::empty())
+                    .unwrap();
+
+                Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Respond(Box::new(response)),
+                    rejection: Rejection::new(
+                        StatusCode::CONFLICT,
+                        "idempotency_in_flight",
+                        "A request with this idempotency key is already in progress.",
+                    ),
+                }
+            }
+            Lookup::Completed(stored) if stored.request_hash != body_hash => {
+                let response = HttpResponse::builder()
+                    .status(StatusCode::UNPROCESSABLE_ENTITY)
+                    .body(BoxBody::empty())
+                    .unwrap();
+
+                Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Respond(Box::new(response)),
+                    rejection: Rejection::new(
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        "idempotency_key_reused",
+                        "This idempotency key was already used with a different request body.",
+                    ),
+                }
+            }
+            Lookup::Completed(stored) => {
+                let mut response = stored.to_response();
+                response.headers_mut().insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+
+                Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Respond(Box::new(response)),
+                    rejection: Rejection::new(
+                        stored.status,
+                        "idempotency_replayed",
+                        "Replayed the stored response for this idempotency key.",
+                    ),
+                }
+            }
+        }
+    }
+}
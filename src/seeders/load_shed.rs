@@ -0,0 +1,261 @@
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::RETRY_AFTER;
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// How important a route's requests are to keep serving under pressure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Shed first, once pressure reaches 70% of a limit.
+    Low,
+
+    /// Shed once pressure reaches 85% of a limit.
+    Normal,
+
+    /// Shed once pressure reaches a limit.
+    High,
+
+    /// Never shed, such as health checks.
+    Critical,
+}
+
+impl Priority {
+    /// Gets the pressure level, as a fraction of the limits, at which requests of this priority
+    /// are shed.
+    fn threshold(&self) -> f64 {
+        match self {
+            Priority::Low => { 0.7 }
+            Priority::Normal => { 0.85 }
+            Priority::High => { 1.0 }
+            Priority::Critical => { f64::INFINITY }
+        }
+    }
+}
+
+/// A snapshot of the runtime pressure signals watched by a `LoadShedSeeder`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Pressure {
+    /// The number of admitted requests which haven't been dropped yet.
+    pub in_flight: usize,
+
+    /// How late the monitor's last tick ran, as a measure of how busy the runtime is.
+    pub lag: Duration,
+
+    /// The process's resident memory in bytes, if it's being measured.
+    pub memory: Option<u64>,
+
+    /// The highest of the signals, as a fraction of its limit.
+    pub level: f64,
+}
+
+/// Gets the process's resident memory in bytes.
+type MemoryProbe = Box<dyn Fn() -> Option<u64> + Send + Sync>;
+
+/// The pressure signals shared between a `LoadShedSeeder`, its monitor, and its permits.
+#[derive(Debug, Default)]
+struct Signals {
+    in_flight: AtomicUsize,
+    lag: AtomicU64,
+    memory: AtomicU64,
+    shed: AtomicU64,
+}
+
+/// Counts a request as in flight until it's dropped.
+#[derive(Debug)]
+struct InFlight(Arc<Signals>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A request admitted by a `LoadShedSeeder`, stored in the request's extensions. The request is
+/// counted as in flight until it (and every clone of this permit) is dropped.
+#[derive(Debug, Clone)]
+pub struct LoadPermit {
+    _in_flight: Arc<InFlight>,
+}
+
+/// A `Seeder` which sheds the least important requests with `503 Service Unavailable` as the
+/// server comes under pressure, before it becomes unresponsive.
+///
+/// Pressure is the highest of the in-flight request count, the runtime's scheduling lag, and the
+/// process's memory, each as a fraction of its limit; signals without a limit are ignored. Lag and
+/// memory are sampled by the task spawned with `monitor`. Routes are prioritized by path prefix,
+/// with the longest matching prefix winning, and other routes are `Priority::Normal`.
+pub struct LoadShedSeeder {
+    signals: Arc<Signals>,
+    max_in_flight: Option<usize>,
+    max_lag: Option<Duration>,
+    max_memory: Option<u64>,
+    probe: Arc<MemoryProbe>,
+    routes: Vec<(String, Priority)>,
+    retry_after: Duration,
+}
+
+impl LoadShedSeeder {
+    /// Constructs a new `LoadShedSeeder`, without any limits.
+    pub fn new() -> LoadShedSeeder {
+        LoadShedSeeder {
+            signals: Arc::new(Signals::default()),
+            max_in_flight: None,
+            max_lag: None,
+            max_memory: None,
+            probe: Arc::new(Box::new(resident_memory)),
+            routes: Vec::new(),
+            retry_after: Duration::from_secs(1),
+        }
+    }
+
+    /// Limits the number of requests in flight at once.
+    pub fn max_in_flight(mut self, max_in_flight: usize) -> LoadShedSeeder {
+        self.max_in_flight = Some(max_in_flight.max(1));
+        self
+    }
+
+    /// Limits how late the runtime may run the monitor's ticks.
+    pub fn max_lag(mut self, max_lag: Duration) -> LoadShedSeeder {
+        self.max_lag = Some(max_lag);
+        self
+    }
+
+    /// Limits the process's resident memory, in bytes.
+    pub fn max_memory(mut self, max_memory: u64) -> LoadShedSeeder {
+        self.max_memory = Some(max_memory.max(1));
+        self
+    }
+
+    /// Sets how the process's resident memory is measured. Defaults to reading `/proc/self/status`,
+    /// which is only available on Linux.
+    pub fn memory_probe<F>(mut self, probe: F) -> LoadShedSeeder
+    where
+        F: Fn() -> Option<u64> + Send + Sync + 'static,
+    {
+        self.probe = Arc::new(Box::new(probe));
+        self
+    }
+
+    /// Sets the priority of the routes under a path prefix.
+    pub fn priority(mut self, prefix: impl Into<String>, priority: Priority) -> LoadShedSeeder {
+        self.routes.push((prefix.into(), priority));
+        self
+    }
+
+    /// Sets the `Retry-After` sent with shed requests. Defaults to 1 second.
+    pub fn retry_after(mut self, retry_after: Duration) -> LoadShedSeeder {
+        self.retry_after = retry_after;
+        self
+    }
+
+    /// Spawns a task which samples the runtime's lag and the process's memory every interval.
+    ///
+    /// The task runs until it's aborted.
+    pub fn monitor(&self, interval: Duration) -> JoinHandle<()> {
+        let signals = self.signals.clone();
+        let probe = self.probe.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let started = Instant::now();
+                tokio::time::sleep(interval).await;
+
+                let lag = started.elapsed().saturating_sub(interval);
+                signals.lag.store(lag.as_micros() as u64, Ordering::Relaxed);
+                signals.memory.store(probe().unwrap_or_default(), Ordering::Relaxed);
+            }
+        })
+    }
+
+    /// Gets a snapshot of the current pressure.
+    pub fn pressure(&self) -> Pressure {
+        let in_flight = self.signals.in_flight.load(Ordering::Relaxed);
+        let lag = Duration::from_micros(self.signals.lag.load(Ordering::Relaxed));
+        let memory = Some(self.signals.memory.load(Ordering::Relaxed)).filter(|memory| *memory > 0);
+
+        let level = [
+            self.max_in_flight.map(|max| in_flight as f64 / max as f64),
+            self.max_lag.filter(|max| !max.is_zero()).map(|max| lag.as_secs_f64() / max.as_secs_f64()),
+            self.max_memory.zip(memory).map(|(max, memory)| memory as f64 / max as f64),
+        ]
+        .into_iter()
+        .flatten()
+        .fold(0.0, f64::max);
+
+        Pressure {
+            in_flight,
+            lag,
+            memory,
+            level,
+        }
+    }
+
+    /// Gets the number of requests shed so far.
+    pub fn shed(&self) -> u64 {
+        self.signals.shed.load(Ordering::Relaxed)
+    }
+
+    /// Gets the priority of a path.
+    fn priority_of(&self, path: &str) -> Priority {
+        self.routes
+            .iter()
+            .filter(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(Priority::Normal, |(_, priority)| *priority)
+    }
+}
+
+impl Default for LoadShedSeeder {
+    fn default() -> LoadShedSeeder {
+        LoadShedSeeder::new()
+    }
+}
+
+impl Seeder for LoadShedSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        let priority = self.priority_of(request.uri().path());
+
+        if self.pressure().level < priority.threshold() {
+            self.signals.in_flight.fetch_add(1, Ordering::Relaxed);
+            request.extensions_mut().insert(LoadPermit {
+                _in_flight: Arc::new(InFlight(self.signals.clone())),
+            });
+
+            return Guard::Accessible(request);
+        }
+
+        self.signals.shed.fetch_add(1, Ordering::Relaxed);
+
+        let response = HttpResponse::builder()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .header(RETRY_AFTER, self.retry_after.as_secs().max(1))
+            .body(BoxBody::empty())
+            .unwrap();
+
+        Guard::Inaccessible {
+            request,
+            respondent: Respondent::Respond(Box::new(response)),
+            rejection: Rejection::new(StatusCode::SERVICE_UNAVAILABLE, "load_shed", "The server is under too much load."),
+        }
+    }
+}
+
+/// Reads the process's resident memory from `/proc/self/status`.
+fn resident_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: u64 = line.trim_start_matches("VmRSS:").trim().trim_end_matches("kB").trim().parse().ok()?;
+
+    Some(kilobytes * 1024)
+}
@@ -0,0 +1,159 @@
+use crate::auth::session::cookie;
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::header::ACCEPT_LANGUAGE;
+use crate::http::HttpRequest;
+
+/// The locale negotiated for a request, as an extension of the request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Locale(pub String);
+
+/// Parses an `Accept-Language` header into its language ranges, ordered by preference.
+///
+/// Ranges with a quality of zero are left out, and ranges of equal quality keep their order.
+pub fn parse_accept_language(header: &str) -> Vec<String> {
+    let mut ranges: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|range| {
+            let mut params = range.split(';');
+            let tag = params.next()?.trim();
+
+            let quality = params
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|quality| quality.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            (!tag.is_empty() && quality > 0.0).then(|| (tag.to_owned(), quality))
+        })
+        .collect();
+
+    ranges.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    ranges.into_iter().map(|(tag, _)| tag).collect()
+}
+
+/// A `Seeder` which negotiates the locale of each request, attaching it as a `Locale`.
+///
+/// An explicit choice wins over the browser's preferences: a supported locale in the query
+/// parameter (`lang` by default) is used first, then one in the cookie (`locale` by default), then
+/// the best match for `Accept-Language`, and finally the default locale.
+///
+/// Language ranges match supported locales case-insensitively, first exactly, then by dropping
+/// subtags from the range (`en-GB` matches `en`), then by primary language (`en` matches `en-US`).
+pub struct LocaleSeeder {
+    supported: Vec<String>,
+    default: String,
+    query: Option<String>,
+    cookie: Option<String>,
+}
+
+impl LocaleSeeder {
+    /// Constructs a new `LocaleSeeder`, falling back to `default` when no supported locale matches.
+    pub fn new<S: Into<String>>(default: impl Into<String>, supported: impl IntoIterator<Item = S>) -> LocaleSeeder {
+        let default = default.into();
+        let mut supported: Vec<String> = supported.into_iter().map(Into::into).collect();
+
+        if !supported.iter().any(|locale| locale.eq_ignore_ascii_case(&default)) {
+            supported.push(default.clone());
+        }
+
+        LocaleSeeder {
+            supported,
+            default,
+            query: Some("lang".to_owned()),
+            cookie: Some("locale".to_owned()),
+        }
+    }
+
+    /// Sets the query parameter which overrides the negotiated locale, or disables it with `None`.
+    pub fn query(mut self, parameter: Option<&str>) -> LocaleSeeder {
+        self.query = parameter.map(str::to_owned);
+        self
+    }
+
+    /// Sets the cookie which overrides the negotiated locale, or disables it with `None`.
+    pub fn cookie(mut self, name: Option<&str>) -> LocaleSeeder {
+        self.cookie = name.map(str::to_owned);
+        self
+    }
+
+    /// Negotiates the locale of a request.
+    pub fn negotiate(&self, request: &HttpRequest<BoxBody>) -> String {
+        let from_query = self.query.as_deref().and_then(|parameter| {
+            request
+                .uri()
+                .query()?
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(key, _)| *key == parameter)
+                .map(|(_, value)| value)
+        });
+
+        let from_cookie = self.cookie.as_deref().and_then(|name| cookie(request, name));
+
+        for chosen in [from_query, from_cookie].into_iter().flatten() {
+            if let Some(locale) = self.exact(chosen) {
+                return locale.to_owned();
+            }
+        }
+
+        let ranges = request
+            .headers()
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .map(parse_accept_language)
+            .unwrap_or_default();
+
+        ranges
+            .iter()
+            .find_map(|range| self.best_match(range))
+            .unwrap_or(&self.default)
+            .to_owned()
+    }
+
+    /// Finds the supported locale matching a tag exactly.
+    fn exact(&self, tag: &str) -> Option<&str> {
+        self.supported.iter().find(|locale| locale.eq_ignore_ascii_case(tag)).map(String::as_str)
+    }
+
+    /// Finds the supported locale best matching a language range.
+    fn best_match(&self, range: &str) -> Option<&String> {
+        if range == "*" {
+            return Some(&self.default);
+        }
+
+        let mut truncated = range;
+        loop {
+            if let Some(locale) = self.supported.iter().find(|locale| locale.eq_ignore_ascii_case(truncated)) {
+                return Some(locale);
+            }
+
+            match truncated.rsplit_once('-') {
+                Some((shorter, _)) => { truncated = shorter; }
+                None => { break; }
+            }
+        }
+
+        self.supported.iter().find(|locale| {
+            locale
+                .split('-')
+                .next()
+                .is_some_and(|primary| primary.eq_ignore_ascii_case(truncated))
+        })
+    }
+}
+
+impl Seeder for LocaleSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        let locale = self.negotiate(request);
+        request.extensions_mut().insert(Locale(locale));
+
+        Guard::Accessible(request)
+    }
+}
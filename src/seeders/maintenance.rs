@@ -0,0 +1,154 @@
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::RETRY_AFTER;
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A handle for toggling maintenance mode at runtime.
+///
+/// Handles are cheap to clone, and every clone toggles the same `MaintenanceSeeder`. A handle can be
+/// handed off to an admin endpoint, a signal handler, or a deploy script hook.
+#[derive(Debug, Clone, Default)]
+pub struct MaintenanceHandle {
+    enabled: Arc<AtomicBool>,
+}
+
+impl MaintenanceHandle {
+    /// Puts the server into maintenance mode.
+    pub fn enable(&self) {
+        self.enabled.store(true, Ordering::Release);
+    }
+
+    /// Takes the server out of maintenance mode.
+    pub fn disable(&self) {
+        self.enabled.store(false, Ordering::Release);
+    }
+
+    /// Toggles maintenance mode, returning whether maintenance mode is now enabled.
+    pub fn toggle(&self) -> bool {
+        !self.enabled.fetch_xor(true, Ordering::AcqRel)
+    }
+
+    /// Checks whether maintenance mode is enabled.
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Acquire)
+    }
+}
+
+/// A `Seeder` which rejects requests with `503 Service Unavailable` while maintenance mode is
+/// enabled.
+///
+/// Maintenance mode is toggled at runtime through a `MaintenanceHandle`. Paths on the allowlist
+/// (such as `/healthz`) keep being served while maintenance mode is enabled.
+pub struct MaintenanceSeeder {
+    handle: MaintenanceHandle,
+
+    /// Paths which are served regardless of maintenance mode.
+    allowed: Vec<String>,
+
+    /// Path prefixes which are served regardless of maintenance mode.
+    allowed_prefixes: Vec<String>,
+
+    /// The value of the `Retry-After` header sent with rejected requests.
+    retry_after: Option<Duration>,
+
+    /// The body sent with rejected requests.
+    message: Option<&'static str>,
+}
+
+impl MaintenanceSeeder {
+    /// Constructs a new `MaintenanceSeeder`, with maintenance mode disabled.
+    pub fn new() -> MaintenanceSeeder {
+        MaintenanceSeeder {
+            handle: MaintenanceHandle::default(),
+            allowed: Vec::new(),
+            allowed_prefixes: Vec::new(),
+            retry_after: None,
+            message: None,
+        }
+    }
+
+    /// Allows a path to be served while maintenance mode is enabled.
+    pub fn allow(mut self, path: impl Into<String>) -> MaintenanceSeeder {
+        self.allowed.push(path.into());
+        self
+    }
+
+    /// Allows every path starting with `prefix` to be served while maintenance mode is enabled.
+    pub fn allow_prefix(mut self, prefix: impl Into<String>) -> MaintenanceSeeder {
+        self.allowed_prefixes.push(prefix.into());
+        self
+    }
+
+    /// Sets the `Retry-After` duration sent with rejected requests. This is sent in whole seconds.
+    pub fn retry_after(mut self, retry_after: Duration) -> MaintenanceSeeder {
+        self.retry_after = Some(retry_after);
+        self
+    }
+
+    /// Sets the body sent with rejected requests.
+    pub fn message(mut self, message: &'static str) -> MaintenanceSeeder {
+        self.message = Some(message);
+        self
+    }
+
+    /// Gets a handle for toggling maintenance mode on this seeder.
+    pub fn handle(&self) -> MaintenanceHandle {
+        self.handle.clone()
+    }
+
+    /// Checks whether a path is served while maintenance mode is enabled.
+    pub fn allows(&self, path: &str) -> bool {
+        self.allowed.iter().any(|allowed| allowed == path)
+            || self.allowed_prefixes.iter().any(|prefix| path.starts_with(prefix.as_str()))
+    }
+
+    /// Builds the response sent to requests rejected during maintenance mode.
+    fn response(&self) -> HttpResponse<BoxBody> {
+        let mut builder = HttpResponse::builder().status(StatusCode::SERVICE_UNAVAILABLE);
+
+        if let Some(retry_after) = self.retry_after {
+            builder = builder.header(RETRY_AFTER, retry_after.as_secs());
+        }
+
+        let body = match self.message {
+            Some(message) => { BoxBody::new(message.as_bytes().into()) }
+            None => { BoxBody::empty() }
+        };
+
+        builder.body(body).unwrap()
+    }
+}
+
+impl Default for MaintenanceSeeder {
+    fn default() -> MaintenanceSeeder {
+        MaintenanceSeeder::new()
+    }
+}
+
+impl Seeder for MaintenanceSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        if !self.handle.enabled() || self.allows(request.uri().path()) {
+            return Guard::Accessible(request);
+        }
+
+        Guard::Inaccessible {
+            request,
+            respondent: Respondent::Respond(Box::new(self.response())),
+            rejection: Rejection::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "maintenance",
+                "The server is in maintenance mode.",
+            ),
+        }
+    }
+}
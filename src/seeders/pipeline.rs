@@ -0,0 +1,221 @@
+use crate::core::seeder::{BoxBody, Guard, Respondent, Seeder};
+use crate::http::header::{HeaderName, HeaderValue};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::util::fnv1a;
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// The header a request sends as `X-Grazie-Trace: 1` to get its pipeline trace back, and the
+/// header the trace is sent back in.
+pub const X_GRAZIE_TRACE: HeaderName = HeaderName::from_static("x-grazie-trace");
+
+/// What a traced seeder did with a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    /// The seeder accepted the request.
+    Accepted,
+
+    /// The seeder rejected the request, with the rejection's status and code.
+    Rejected(StatusCode, String),
+
+    /// The seeder rejected the request, but asked for the rejection to be ignored.
+    Ignored,
+
+    /// The request had already been rejected when it reached the seeder.
+    Skipped,
+}
+
+impl Display for Decision {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Decision::Accepted => { write!(f, "accepted") }
+            Decision::Rejected(status, code) => { write!(f, "rejected({} {code})", status.as_u16()) }
+            Decision::Ignored => { write!(f, "ignored") }
+            Decision::Skipped => { write!(f, "skipped") }
+        }
+    }
+}
+
+/// A single seeder's part in a request's pipeline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    /// The name the seeder was traced under.
+    pub seeder: String,
+
+    /// What the seeder did with the request.
+    pub decision: Decision,
+
+    /// How long the seeder took.
+    pub elapsed: Duration,
+
+    /// Whether the seeder changed the request's body.
+    pub body_changed: bool,
+}
+
+/// The steps a request has been through, in order, as an extension of the request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PipelineTrace {
+    /// Every traced seeder the request reached.
+    pub steps: Vec<TraceStep>,
+}
+
+impl Display for PipelineTrace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (index, step) in self.steps.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{} {} {}us", step.seeder, step.decision, step.elapsed.as_micros())?;
+            if step.body_changed {
+                write!(f, " body-changed")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A `Seeder` which records what the seeder it wraps does with each request in the request's
+/// `PipelineTrace`, to make the order and effect of a seeder chain visible:
+///
+/// ```
+/// use grazie::core::seeder::Seeder;
+/// use grazie::seeders::pipeline::Traced;
+/// use grazie::seeders::{MaintenanceSeeder, ProblemSeeder};
+///
+/// let chain = Traced::new("maintenance", MaintenanceSeeder::new())
+///     .then(Traced::new("problem", ProblemSeeder::new()));
+/// ```
+///
+/// Traces are only collected in debug builds. In release builds, `Traced` runs the seeder it
+/// wraps without recording anything.
+pub struct Traced<S> {
+    name: String,
+    seeder: S,
+}
+
+impl<S: Seeder> Traced<S> {
+    /// Constructs a new `Traced`, recording a seeder under the provided name.
+    pub fn new(name: impl Into<String>, seeder: S) -> Traced<S> {
+        Traced {
+            name: name.into(),
+            seeder,
+        }
+    }
+
+    /// Gets the seeder being traced.
+    pub fn inner(&self) -> &S {
+        &self.seeder
+    }
+}
+
+impl<S: Seeder + Sync> Seeder for Traced<S> {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        if !cfg!(debug_assertions) {
+            return self.seeder.seed(input).await;
+        }
+
+        let (skipped, body) = match &input {
+            Guard::Accessible(request) => { (false, fnv1a(request.body().raw_bytes())) }
+            Guard::Inaccessible { request, .. } => { (true, fnv1a(request.body().raw_bytes())) }
+        };
+
+        let start = Instant::now();
+        let mut output = self.seeder.seed(input).await;
+        let elapsed = start.elapsed();
+
+        let (request, decision) = match &mut output {
+            Guard::Accessible(request) => { (request, Decision::Accepted) }
+            Guard::Inaccessible { request, .. } if skipped => { (request, Decision::Skipped) }
+            Guard::Inaccessible { request, respondent: Respondent::Ignore, .. } => { (request, Decision::Ignored) }
+            Guard::Inaccessible { request, rejection, .. } => {
+                (request, Decision::Rejected(rejection.status, rejection.code.clone()))
+            }
+        };
+
+        let step = TraceStep {
+            seeder: self.name.clone(),
+            decision,
+            elapsed,
+            body_changed: fnv1a(request.body().raw_bytes()) != body,
+        };
+
+        match request.extensions_mut().get_mut::<PipelineTrace>() {
+            Some(trace) => { trace.steps.push(step); }
+            None => { request.extensions_mut().insert(PipelineTrace { steps: vec![step] }); }
+        }
+
+        output
+    }
+}
+
+/// A traced request, as kept by a `TraceLog`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TracedRequest {
+    /// The request's method and path, such as `GET /orders`.
+    pub request: String,
+
+    /// The request's trace.
+    pub trace: PipelineTrace,
+}
+
+/// A handle to the traces of the most recent requests, for inspecting them through the admin
+/// socket (see `AdminServer::traces`).
+///
+/// Since there's no response stage to the request chain, requests are added to the log by passing
+/// them to `TraceLog::finish` once their response has been created.
+#[derive(Debug, Clone)]
+pub struct TraceLog {
+    capacity: usize,
+    traces: Arc<Mutex<VecDeque<TracedRequest>>>,
+}
+
+impl TraceLog {
+    /// Constructs a new `TraceLog`, keeping the traces of up to `capacity` requests.
+    pub fn new(capacity: usize) -> TraceLog {
+        TraceLog {
+            capacity,
+            traces: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+        }
+    }
+
+    /// Records a request's trace, and sends it back in an `X-Grazie-Trace` header if the request
+    /// asked for it with `X-Grazie-Trace: 1`.
+    pub fn finish(&self, request: &HttpRequest<BoxBody>, response: &mut HttpResponse<BoxBody>) {
+        let Some(trace) = request.extensions().get::<PipelineTrace>() else { return; };
+
+        if request.headers().get(X_GRAZIE_TRACE).is_some_and(|value| value == "1") {
+            if let Ok(value) = HeaderValue::try_from(trace.to_string()) {
+                response.headers_mut().insert(X_GRAZIE_TRACE, value);
+            }
+        }
+
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut traces = self.lock();
+        if traces.len() == self.capacity {
+            traces.pop_front();
+        }
+        traces.push_back(TracedRequest {
+            request: format!("{} {}", request.method(), request.uri().path()),
+            trace: trace.clone(),
+        });
+    }
+
+    /// Gets the recorded traces, most recent first.
+    pub fn recent(&self) -> Vec<TracedRequest> {
+        self.lock().iter().rev().cloned().collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, VecDeque<TracedRequest>> {
+        self.traces.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
@@ -0,0 +1,162 @@
+//! Request filters loaded from shared libraries, over a small C ABI.
+//!
+//! `Seeder` itself can't cross a library boundary, since its futures and hyper's types have no
+//! stable layout, so plugins implement a synchronous C entry point instead. A plugin library
+//! exports two functions, which can be written in any language with C linkage:
+//!
+//! ```c
+//! // Returns the ABI version the plugin was built for, which must be `PLUGIN_ABI`.
+//! uint32_t grazie_plugin_abi(void);
+//!
+//! // Decides what happens to a request, given its head as `len` bytes of UTF-8 text. Returns `0`
+//! // to pass the request along, or a status code to reject the request with.
+//! uint16_t grazie_on_request(const uint8_t *head, size_t len);
+//! ```
+//!
+//! The request's head is handed over as a `<method> <path and query>` line, then a
+//! `<name>: <value>` line for each header with a UTF-8 value, with lowercase names. Each line
+//! ends with `\n`, as with `WasmSeeder`. The head is only valid for the length of the call, and
+//! `grazie_on_request` may be called from many threads at once.
+//!
+//! Plugins run in the server's process, without a sandbox, so they should only be loaded from
+//! trusted paths, such as ones listed in the application's config. `WasmSeeder` suits filters
+//! which aren't trusted.
+//!
+//! Part of the `plugins` feature.
+
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::util::request_head;
+use libloading::Library;
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+/// The version of the plugin ABI, which plugins report through `grazie_plugin_abi`.
+pub const PLUGIN_ABI: u32 = 1;
+
+/// A plugin's `grazie_on_request` entry point.
+pub type OnRequest = unsafe extern "C" fn(head: *const u8, len: usize) -> u16;
+
+/// An error from loading a plugin.
+#[derive(Debug)]
+pub enum PluginError {
+    /// The library couldn't be loaded, or doesn't export the plugin's functions.
+    Load(libloading::Error),
+
+    /// The plugin was built for another version of the ABI.
+    Abi(u32),
+}
+
+impl Display for PluginError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PluginError::Load(error) => { write!(f, "couldn't load the plugin: {error}") }
+            PluginError::Abi(version) => {
+                write!(f, "the plugin was built for ABI version {version}, not {PLUGIN_ABI}")
+            }
+        }
+    }
+}
+
+impl Error for PluginError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PluginError::Load(error) => { Some(error) }
+            PluginError::Abi(_) => { None }
+        }
+    }
+}
+
+/// A `Seeder` which runs a plugin's `grazie_on_request` on every request, to pass or reject it.
+///
+/// Seeders are cheap to clone, and keep their library loaded until every clone is dropped.
+/// Requests which the plugin answers with something other than `0` or a status code are answered
+/// with `500 Internal Server Error`.
+///
+/// Part of the `plugins` feature.
+#[derive(Clone)]
+pub struct PluginSeeder {
+    on_request: OnRequest,
+    _library: Option<Arc<Library>>,
+}
+
+impl PluginSeeder {
+    /// Loads a plugin from a shared library, checking its ABI version.
+    ///
+    /// # Safety
+    ///
+    /// Loading a library runs its initializers, and the plugin's functions are trusted to match
+    /// the signatures above, and to be safe to call with any request from any thread.
+    pub unsafe fn load(path: impl AsRef<OsStr>) -> Result<PluginSeeder, PluginError> {
+        let library = unsafe { Library::new(path) }.map_err(PluginError::Load)?;
+
+        let abi = unsafe { library.get::<unsafe extern "C" fn() -> u32>(b"grazie_plugin_abi") };
+        let version = unsafe { abi.map_err(PluginError::Load)?() };
+        if version != PLUGIN_ABI {
+            return Err(PluginError::Abi(version));
+        }
+
+        let on_request = unsafe { library.get::<OnRequest>(b"grazie_on_request") };
+        let on_request = *on_request.map_err(PluginError::Load)?;
+
+        Ok(PluginSeeder {
+            on_request,
+            _library: Some(Arc::new(library)),
+        })
+    }
+
+    /// Constructs a new `PluginSeeder` over an entry point linked into the application, such as
+    /// one from a statically linked plugin.
+    ///
+    /// # Safety
+    ///
+    /// `on_request` must be safe to call with any request from any thread.
+    pub unsafe fn from_fn(on_request: OnRequest) -> PluginSeeder {
+        PluginSeeder {
+            on_request,
+            _library: None,
+        }
+    }
+
+    /// Runs the plugin on a request, returning the status to reject it with, or `None` to pass the
+    /// request along.
+    fn decide(&self, request: &HttpRequest<BoxBody>) -> Result<Option<StatusCode>, u16> {
+        let head = request_head(request);
+
+        match unsafe { (self.on_request)(head.as_ptr(), head.len()) } {
+            0 => { Ok(None) }
+            status => { StatusCode::from_u16(status).map(Some).map_err(|_| status) }
+        }
+    }
+}
+
+impl Seeder for PluginSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        let rejection = match self.decide(request) {
+            Ok(None) => { return Guard::Accessible(request); }
+            Ok(Some(status)) => { Rejection::new(status, "plugin_denied", "The request was denied.") }
+            Err(_) => {
+                let message = "The request's plugin failed.";
+                Rejection::new(StatusCode::INTERNAL_SERVER_ERROR, "plugin_failed", message)
+            }
+        };
+
+        let response = HttpResponse::builder().status(rejection.status).body(BoxBody::empty()).unwrap();
+
+        Guard::Inaccessible {
+            request,
+            respondent: Respondent::Respond(Box::new(response)),
+            rejection,
+        }
+    }
+}
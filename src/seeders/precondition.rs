@@ -0,0 +1,184 @@
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::IF_MATCH;
+use crate::http::{HttpRequest, HttpResponse, Method, StatusCode};
+use std::fmt::{Display, Formatter};
+
+/// An entity tag, as sent in `ETag` and `If-Match` headers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EntityTag {
+    /// The tag's opaque value, without its quotes.
+    pub tag: String,
+
+    /// Whether the tag is weak (`W/"..."`), and so only identifies equivalent, rather than
+    /// identical, representations.
+    pub weak: bool,
+}
+
+impl EntityTag {
+    /// Constructs a new strong `EntityTag`.
+    pub fn strong(tag: impl Into<String>) -> EntityTag {
+        EntityTag {
+            tag: tag.into(),
+            weak: false,
+        }
+    }
+
+    /// Constructs a new weak `EntityTag`.
+    pub fn weak(tag: impl Into<String>) -> EntityTag {
+        EntityTag {
+            tag: tag.into(),
+            weak: true,
+        }
+    }
+
+    /// Parses a single entity tag, such as `"abc"` or `W/"abc"`.
+    pub fn parse(value: &str) -> Option<EntityTag> {
+        let value = value.trim();
+        let (weak, quoted) = match value.strip_prefix("W/") {
+            Some(quoted) => { (true, quoted) }
+            None => { (false, value) }
+        };
+
+        let tag = quoted.strip_prefix('"')?.strip_suffix('"')?;
+        if tag.contains('"') {
+            return None;
+        }
+
+        Some(EntityTag {
+            tag: tag.to_owned(),
+            weak,
+        })
+    }
+
+    /// Compares two tags strongly, as `If-Match` does: both must be strong, with the same value.
+    pub fn strong_eq(&self, other: &EntityTag) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+}
+
+impl Display for EntityTag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.weak {
+            true => { write!(f, "W/\"{}\"", self.tag) }
+            false => { write!(f, "\"{}\"", self.tag) }
+        }
+    }
+}
+
+/// Checks a request's `If-Match` header against the current tag of the resource it targets, for
+/// handlers which check preconditions themselves.
+///
+/// Requests without `If-Match` are rejected with `428 Precondition Required`, and requests whose
+/// tags don't strongly match (or which target a resource that doesn't exist, where `current` is
+/// `None`) with `412 Precondition Failed`.
+pub fn check_if_match(request: &HttpRequest<BoxBody>, current: Option<&EntityTag>) -> Result<(), Rejection> {
+    let mut values = request.headers().get_all(IF_MATCH).iter().peekable();
+
+    if values.peek().is_none() {
+        return Err(Rejection::new(
+            StatusCode::PRECONDITION_REQUIRED,
+            "precondition_required",
+            "The request must be made conditional with If-Match.",
+        ));
+    }
+
+    let matched = current.is_some_and(|current| {
+        values
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|tag| match tag.trim() {
+                "*" => { true }
+                tag => { EntityTag::parse(tag).is_some_and(|tag| tag.strong_eq(current)) }
+            })
+    });
+
+    match matched {
+        true => { Ok(()) }
+        false => {
+            Err(Rejection::new(
+                StatusCode::PRECONDITION_FAILED,
+                "precondition_failed",
+                "The resource has changed since it was fetched.",
+            ))
+        }
+    }
+}
+
+/// Looks up the current tag of the resource a request targets, for a `PreconditionSeeder`.
+///
+/// This is implemented for closures, for lookups which don't need to wait on anything.
+pub trait EtagSource: Send + Sync {
+    /// Gets the current tag of the resource a request targets, or `None` if it doesn't exist.
+    fn current(&self, request: &HttpRequest<BoxBody>) -> impl Future<Output = Option<EntityTag>> + Send;
+}
+
+impl<F> EtagSource for F
+where
+    F: Fn(&HttpRequest<BoxBody>) -> Option<EntityTag> + Send + Sync,
+{
+    async fn current(&self, request: &HttpRequest<BoxBody>) -> Option<EntityTag> {
+        self(request)
+    }
+}
+
+/// A `Seeder` which requires mutating requests to carry an `If-Match` header matching the current
+/// tag of the resource they target, so that clients can't overwrite changes they haven't seen.
+///
+/// `PUT`, `PATCH`, and `DELETE` requests are checked by default, as described in `check_if_match`.
+pub struct PreconditionSeeder<S> {
+    source: S,
+    methods: Vec<Method>,
+}
+
+impl<S: EtagSource> PreconditionSeeder<S> {
+    /// Constructs a new `PreconditionSeeder`, looking current tags up from the provided source.
+    pub fn new(source: S) -> PreconditionSeeder<S> {
+        PreconditionSeeder {
+            source,
+            methods: vec![Method::PUT, Method::PATCH, Method::DELETE],
+        }
+    }
+
+    /// Sets the methods which must be conditional.
+    pub fn methods(mut self, methods: impl IntoIterator<Item = Method>) -> PreconditionSeeder<S> {
+        self.methods = methods.into_iter().collect();
+        self
+    }
+}
+
+impl<S: EtagSource> Seeder for PreconditionSeeder<S> {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        if !self.methods.contains(request.method()) {
+            return Guard::Accessible(request);
+        }
+
+        let current = match request.headers().contains_key(IF_MATCH) {
+            true => { self.source.current(request).await }
+            false => { None }
+        };
+
+        match check_if_match(request, current.as_ref()) {
+            Ok(()) => { Guard::Accessible(request) }
+            Err(rejection) => {
+                let response = HttpResponse::builder()
+                    .status(rejection.status)
+                    .body(BoxBody::empty())
+                    .unwrap();
+
+                Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Respond(Box::new(response)),
+                    rejection,
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use std::fmt::Write;
+
+/// The media type of problem details documents.
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// The members of a problem details document which extensions may not override.
+#[cfg(feature = "serde_json")]
+const RESERVED: [&str; 5] = ["type", "title", "status", "detail", "instance"];
+
+/// An RFC 7807 problem details document, describing an error in a machine-readable way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    /// A URI identifying the type of problem. Defaults to `about:blank`.
+    pub type_uri: String,
+
+    /// A short, human-readable summary of the type of problem.
+    pub title: String,
+
+    /// The status code of the response.
+    pub status: StatusCode,
+
+    /// A human-readable explanation specific to this occurrence of the problem.
+    pub detail: Option<String>,
+
+    /// A URI identifying this specific occurrence of the problem.
+    pub instance: Option<String>,
+
+    /// Additional members of the document.
+    ///
+    /// Part of the `serde_json` feature.
+    #[cfg(feature = "serde_json")]
+    pub extensions: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Problem {
+    /// Constructs a new `Problem` of type `about:blank`, titled after the status code's canonical
+    /// reason.
+    pub fn new(status: StatusCode) -> Problem {
+        Problem {
+            type_uri: "about:blank".to_owned(),
+            title: status.canonical_reason().unwrap_or("Unknown Error").to_owned(),
+            status,
+            detail: None,
+            instance: None,
+            #[cfg(feature = "serde_json")]
+            extensions: serde_json::Map::new(),
+        }
+    }
+
+    /// Constructs a `Problem` from a `Rejection`.
+    ///
+    /// If a base URI is provided, the problem's type is the rejection's code appended to it.
+    /// Object details are merged into the document as extension members; other details are placed
+    /// under a `details` member.
+    pub fn from_rejection(rejection: &Rejection, base: Option<&str>) -> Problem {
+        let mut problem = Problem::new(rejection.status).detail(rejection.message.clone());
+
+        if let Some(base) = base {
+            problem = problem.type_uri(format!("{base}{}", rejection.code));
+        }
+
+        #[cfg(feature = "serde_json")]
+        match &rejection.details {
+            Some(serde_json::Value::Object(details)) => {
+                for (name, value) in details {
+                    problem = problem.extension(name, value.clone());
+                }
+            }
+            Some(details) => { problem = problem.extension("details", details.clone()); }
+            None => {}
+        }
+
+        problem
+    }
+
+    /// Sets the problem's type URI.
+    pub fn type_uri(mut self, type_uri: impl Into<String>) -> Problem {
+        self.type_uri = type_uri.into();
+        self
+    }
+
+    /// Sets the problem's title.
+    pub fn title(mut self, title: impl Into<String>) -> Problem {
+        self.title = title.into();
+        self
+    }
+
+    /// Sets the problem's detail.
+    pub fn detail(mut self, detail: impl Into<String>) -> Problem {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Sets the problem's instance URI.
+    pub fn instance(mut self, instance: impl Into<String>) -> Problem {
+        self.instance = Some(instance.into());
+        self
+    }
+
+    /// Adds an extension member to the document. Members which would override one of the
+    /// standard members are ignored.
+    ///
+    /// Part of the `serde_json` feature.
+    #[cfg(feature = "serde_json")]
+    pub fn extension(mut self, name: impl Into<String>, value: impl Into<serde_json::Value>) -> Problem {
+        let name = name.into();
+
+        if !RESERVED.contains(&name.as_str()) {
+            self.extensions.insert(name, value.into());
+        }
+
+        self
+    }
+
+    /// Renders the problem as a JSON document.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{");
+
+        write_member(&mut json, "type", &self.type_uri);
+        json.push(',');
+        write_member(&mut json, "title", &self.title);
+        let _ = write!(json, ",\"status\":{}", self.status.as_u16());
+
+        if let Some(detail) = &self.detail {
+            json.push(',');
+            write_member(&mut json, "detail", detail);
+        }
+
+        if let Some(instance) = &self.instance {
+            json.push(',');
+            write_member(&mut json, "instance", instance);
+        }
+
+        #[cfg(feature = "serde_json")]
+        for (name, value) in &self.extensions {
+            json.push(',');
+            write_string(&mut json, name);
+            json.push(':');
+            json.push_str(&value.to_string());
+        }
+
+        json.push('}');
+        json
+    }
+
+    /// Creates an `application/problem+json` response from the problem.
+    pub fn into_response(self) -> HttpResponse<BoxBody> {
+        let body = self.to_json().into_bytes();
+
+        HttpResponse::builder()
+            .status(self.status)
+            .header(CONTENT_TYPE, PROBLEM_JSON)
+            .header(CONTENT_LENGTH, body.len())
+            .body(BoxBody::new(body.into_boxed_slice()))
+            .unwrap()
+    }
+}
+
+/// Writes a `"name":"value"` member into a JSON document.
+fn write_member(json: &mut String, name: &str, value: &str) {
+    write_string(json, name);
+    json.push(':');
+    write_string(json, value);
+}
+
+/// Writes an escaped JSON string into a JSON document.
+fn write_string(json: &mut String, value: &str) {
+    json.push('"');
+
+    for character in value.chars() {
+        match character {
+            '"' => { json.push_str("\\\""); }
+            '\\' => { json.push_str("\\\\"); }
+            '\n' => { json.push_str("\\n"); }
+            '\r' => { json.push_str("\\r"); }
+            '\t' => { json.push_str("\\t"); }
+            character if character.is_control() => { let _ = write!(json, "\\u{:04x}", character as u32); }
+            character => { json.push(character); }
+        }
+    }
+
+    json.push('"');
+}
+
+/// A `Seeder` which converts rejections into RFC 7807 `application/problem+json` responses.
+///
+/// This should be placed after the `Seeder`s whose rejections it converts. Only error rejections
+/// (with a `4xx` or `5xx` status) which respond directly are converted, and only when the
+/// rejection's response has no body of its own; the response's other headers (such as `Retry-After` or `Allow`) are kept.
+///
+/// Handlers can produce the same documents directly, through `Problem::into_response`.
+pub struct ProblemSeeder {
+    base: Option<String>,
+    instances: bool,
+}
+
+impl ProblemSeeder {
+    /// Constructs a new `ProblemSeeder`, which uses `about:blank` as the type of every problem.
+    pub fn new() -> ProblemSeeder {
+        ProblemSeeder {
+            base: None,
+            instances: true,
+        }
+    }
+
+    /// Sets the base URI for problem types. The type of each problem becomes the rejection's code
+    /// appended to this base, such as `https://example.com/errors/maintenance`.
+    pub fn base_uri(mut self, base: impl Into<String>) -> ProblemSeeder {
+        self.base = Some(base.into());
+        self
+    }
+
+    /// Sets whether the request's path is included as the problem's instance. Enabled by default.
+    pub fn instances(mut self, instances: bool) -> ProblemSeeder {
+        self.instances = instances;
+        self
+    }
+
+    /// Creates the problem for a rejected request.
+    pub fn problem(&self, request: &HttpRequest<BoxBody>, rejection: &Rejection) -> Problem {
+        let problem = Problem::from_rejection(rejection, self.base.as_deref());
+
+        match self.instances {
+            true => { problem.instance(request.uri().path()) }
+            false => { problem }
+        }
+    }
+}
+
+impl Default for ProblemSeeder {
+    fn default() -> ProblemSeeder {
+        ProblemSeeder::new()
+    }
+}
+
+impl Seeder for ProblemSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let (request, respondent, rejection) = match input {
+            Guard::Inaccessible { request, respondent, rejection } => { (request, respondent, rejection) }
+            accessible => { return accessible; }
+        };
+
+        let error = rejection.status.is_client_error() || rejection.status.is_server_error();
+        let respondent = match respondent {
+            Respondent::Respond(response) if error && response.body().raw_bytes().is_empty() => {
+                let mut problem = self.problem(request, &rejection).into_response();
+
+                for (name, value) in response.headers() {
+                    if !problem.headers().contains_key(name) {
+                        problem.headers_mut().insert(name, value.clone());
+                    }
+                }
+
+                Respondent::Respond(Box::new(problem))
+            }
+            respondent => { respondent }
+        };
+
+        Guard::Inaccessible { request, respondent, rejection }
+    }
+}
+
+impl From<Problem> for HttpResponse<BoxBody> {
+    fn from(problem: Problem) -> HttpResponse<BoxBody> {
+        problem.into_response()
+    }
+}
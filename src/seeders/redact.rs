@@ -0,0 +1,266 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::header::{CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use serde_json::Value;
+
+/// The scopes a request was granted, as an extension of the request. Authentication seeders (or
+/// the application) insert this for `RedactSeeder` to check.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Scopes(pub Vec<String>);
+
+impl Scopes {
+    /// Checks whether a scope was granted.
+    pub fn has(&self, scope: &str) -> bool {
+        self.0.iter().any(|granted| granted == scope)
+    }
+}
+
+/// Gets the scopes a request was granted.
+type ScopeLookup = Box<dyn Fn(&HttpRequest<BoxBody>) -> Scopes + Send + Sync>;
+
+/// What happens to a redacted field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Remove,
+    Mask,
+}
+
+/// A field redacted for requests without a scope.
+struct Rule {
+    pointer: Vec<String>,
+    scope: String,
+    action: Action,
+}
+
+/// The rules which apply to a request, by index, as an extension of the request.
+#[derive(Debug, Clone)]
+struct Redactions(Vec<usize>);
+
+/// A `Seeder` which removes or masks fields of JSON responses for requests lacking the scopes
+/// needed to see them, so that sensitive fields are filtered in one place rather than in every
+/// handler.
+///
+/// Fields are named by JSON pointer, such as `/owner/email`, where a `*` segment matches every
+/// element of an array (or member of an object), as in `/items/*/cost`. The scopes of a request
+/// are read from its `Scopes` extension by default, and checked as it passes through the seeder.
+/// Since there's no response stage to the request chain, responses are redacted by passing them
+/// to `RedactSeeder::redact_response` once they've been created.
+///
+/// Redaction fails closed: a JSON response which has fields to redact but can't be redacted, as
+/// it's compressed or malformed, is replaced with an empty `500 Internal Server Error`.
+///
+/// Part of the `serde_json` feature.
+pub struct RedactSeeder {
+    rules: Vec<Rule>,
+    mask: Value,
+    scopes: ScopeLookup,
+}
+
+impl RedactSeeder {
+    /// Constructs a new `RedactSeeder`, which doesn't redact any fields.
+    pub fn new() -> RedactSeeder {
+        RedactSeeder {
+            rules: Vec::new(),
+            mask: Value::String("***".to_owned()),
+            scopes: Box::new(|request| request.extensions().get::<Scopes>().cloned().unwrap_or_default()),
+        }
+    }
+
+    /// Removes a field from responses to requests without a scope.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pointer isn't `""` and doesn't start with `/`.
+    pub fn remove(self, pointer: &str, scope: impl Into<String>) -> RedactSeeder {
+        self.rule(pointer, scope.into(), Action::Remove)
+    }
+
+    /// Replaces a field's value in responses to requests without a scope.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pointer isn't `""` and doesn't start with `/`.
+    pub fn mask(self, pointer: &str, scope: impl Into<String>) -> RedactSeeder {
+        self.rule(pointer, scope.into(), Action::Mask)
+    }
+
+    /// Sets the value masked fields are replaced with. Defaults to `"***"`.
+    pub fn mask_with(mut self, mask: Value) -> RedactSeeder {
+        self.mask = mask;
+        self
+    }
+
+    /// Sets how the scopes of a request are found.
+    pub fn scopes<F>(mut self, scopes: F) -> RedactSeeder
+    where
+        F: Fn(&HttpRequest<BoxBody>) -> Scopes + Send + Sync + 'static,
+    {
+        self.scopes = Box::new(scopes);
+        self
+    }
+
+    fn rule(mut self, pointer: &str, scope: String, action: Action) -> RedactSeeder {
+        assert!(
+            pointer.is_empty() || pointer.starts_with('/'),
+            "A JSON pointer must be empty or start with a '/', but {pointer:?} doesn't!"
+        );
+
+        // JSON pointers escape `~` as `~0` and `/` as `~1`.
+        let pointer = pointer
+            .split('/')
+            .skip(1)
+            .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+            .collect();
+
+        self.rules.push(Rule { pointer, scope, action });
+        self
+    }
+
+    /// Redacts a JSON value for a request.
+    pub fn redact(&self, request: &HttpRequest<BoxBody>, value: &mut Value) {
+        let Some(Redactions(indices)) = request.extensions().get::<Redactions>() else { return; };
+
+        for rule in indices.iter().filter_map(|index| self.rules.get(*index)) {
+            apply(value, &rule.pointer, rule.action, &self.mask);
+        }
+    }
+
+    /// Redacts a response, if it's a JSON response. Responses which can't be redacted are replaced
+    /// with an empty `500 Internal Server Error`.
+    pub fn redact_response(
+        &self,
+        request: &HttpRequest<BoxBody>,
+        response: &mut HttpResponse<BoxBody>,
+    ) {
+        let redactions = request.extensions().get::<Redactions>();
+        if redactions.is_none_or(|redactions| redactions.0.is_empty()) {
+            return;
+        }
+
+        let json = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .and_then(|content_type| content_type.split(';').next())
+            .is_some_and(|media_type| {
+                let media_type = media_type.trim();
+                media_type == "application/json" || media_type.ends_with("+json")
+            });
+
+        if !json || response.body().raw_bytes().is_empty() {
+            return;
+        }
+
+        let value = match response.headers().contains_key(CONTENT_ENCODING) {
+            true => { None }
+            false => { serde_json::from_slice::<Value>(response.body().raw_bytes()).ok() }
+        };
+        let redacted = value.and_then(|mut value| {
+            self.redact(request, &mut value);
+            serde_json::to_vec(&value).ok()
+        });
+
+        match redacted {
+            Some(redacted) => {
+                response.headers_mut().insert(CONTENT_LENGTH, redacted.len().into());
+                *response.body_mut() = BoxBody::new(redacted.into_boxed_slice());
+            }
+            None => {
+                *response = HttpResponse::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .header(CONTENT_LENGTH, 0)
+                    .body(BoxBody::empty())
+                    .unwrap();
+            }
+        }
+    }
+}
+
+impl Default for RedactSeeder {
+    fn default() -> RedactSeeder {
+        RedactSeeder::new()
+    }
+}
+
+impl Seeder for RedactSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        let scopes = (self.scopes)(request);
+        let indices = self.rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| !scopes.has(&rule.scope))
+            .map(|(index, _)| index)
+            .collect();
+
+        request.extensions_mut().insert(Redactions(indices));
+
+        Guard::Accessible(request)
+    }
+}
+
+/// Applies a redaction to every field a pointer matches.
+fn apply(value: &mut Value, pointer: &[String], action: Action, mask: &Value) {
+    let Some((segment, rest)) = pointer.split_first() else { return; };
+
+    if rest.is_empty() {
+        match (value, segment.as_str()) {
+            (Value::Object(object), "*") => {
+                match action {
+                    Action::Remove => { object.clear(); }
+                    Action::Mask => { object.values_mut().for_each(|field| *field = mask.clone()); }
+                }
+            }
+            (Value::Array(array), "*") => {
+                match action {
+                    Action::Remove => { array.clear(); }
+                    Action::Mask => { array.iter_mut().for_each(|element| *element = mask.clone()); }
+                }
+            }
+            (Value::Object(object), key) => {
+                match action {
+                    Action::Remove => { object.remove(key); }
+                    Action::Mask => {
+                        if let Some(field) = object.get_mut(key) {
+                            *field = mask.clone();
+                        }
+                    }
+                }
+            }
+            (Value::Array(array), index) => {
+                let Some(index) = index.parse::<usize>().ok().filter(|index| *index < array.len()) else { return; };
+
+                match action {
+                    Action::Remove => { array.remove(index); }
+                    Action::Mask => { array[index] = mask.clone(); }
+                }
+            }
+            _ => {}
+        }
+
+        return;
+    }
+
+    match (value, segment.as_str()) {
+        (Value::Object(object), "*") => { object.values_mut().for_each(|field| apply(field, rest, action, mask)); }
+        (Value::Array(array), "*") => { array.iter_mut().for_each(|element| apply(element, rest, action, mask)); }
+        (Value::Object(object), key) => {
+            if let Some(field) = object.get_mut(key) {
+                apply(field, rest, action, mask);
+            }
+        }
+        (Value::Array(array), index) => {
+            if let Some(element) = index.parse::<usize>().ok().and_then(|index| array.get_mut(index)) {
+                apply(element, rest, action, mask);
+            }
+        }
+        _ => {}
+    }
+}
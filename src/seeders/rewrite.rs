@@ -0,0 +1,233 @@
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::{HeaderValue, HOST, LOCATION};
+use crate::http::{HttpRequest, HttpResponse, StatusCode, Uri};
+use hyper::http::uri::PathAndQuery;
+use regex::Regex;
+
+/// The part of a request a `RewriteRule` is matched against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subject {
+    /// The request's path and query, such as `/api/users?page=2`.
+    Path,
+
+    /// The request's full URL, such as `http://example.com/api/users?page=2`. The scheme is taken
+    /// from the request URI (or the `X-Forwarded-Proto` header, when trusted), defaulting to
+    /// `http`, and the host is taken from the `Host` header.
+    Url,
+}
+
+/// What a `RewriteRule` does with a matching request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RewriteAction {
+    /// Internally rewrites the request's path and query to the expanded replacement, which must
+    /// start with `/`.
+    Rewrite(String),
+
+    /// Redirects the client to the expanded replacement, with the provided status (generally
+    /// `301`, `302`, `307` or `308`).
+    Redirect(StatusCode, String),
+}
+
+/// A single rule of a `RewriteSeeder`.
+///
+/// Replacements may refer to the capture groups of the rule's pattern, such as `$1` or `${name}`.
+pub struct RewriteRule {
+    subject: Subject,
+    pattern: Regex,
+    action: RewriteAction,
+}
+
+impl RewriteRule {
+    /// Constructs a new `RewriteRule`.
+    pub fn new(subject: Subject, pattern: Regex, action: RewriteAction) -> RewriteRule {
+        RewriteRule {
+            subject,
+            pattern,
+            action,
+        }
+    }
+
+    /// Constructs a rule which internally rewrites matching paths.
+    pub fn rewrite(pattern: Regex, replacement: impl Into<String>) -> RewriteRule {
+        RewriteRule::new(Subject::Path, pattern, RewriteAction::Rewrite(replacement.into()))
+    }
+
+    /// Constructs a rule which redirects matching paths.
+    pub fn redirect(
+        pattern: Regex,
+        status: StatusCode,
+        location: impl Into<String>,
+    ) -> RewriteRule {
+        RewriteRule::new(Subject::Path, pattern, RewriteAction::Redirect(status, location.into()))
+    }
+
+    /// Constructs a rule which redirects matching URLs, for matching on the scheme or host.
+    pub fn redirect_url(
+        pattern: Regex,
+        status: StatusCode,
+        location: impl Into<String>,
+    ) -> RewriteRule {
+        RewriteRule::new(Subject::Url, pattern, RewriteAction::Redirect(status, location.into()))
+    }
+}
+
+/// The URI of a request before it was rewritten by a `RewriteSeeder`. This is stored in the
+/// request's extensions whenever a request is rewritten.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OriginalUri(pub Uri);
+
+/// A `Seeder` which rewrites request paths and issues redirects, according to an ordered list of
+/// rules.
+///
+/// Rules are tried in the order they were added, and only the first matching rule is applied.
+/// Rewritten requests continue down the request chain with their new path and query, and
+/// redirected requests are answered with a `Location` header.
+///
+/// Part of the `regex` feature.
+///
+/// ```
+/// use grazie::http::StatusCode;
+/// use grazie::seeders::rewrite::RewriteRule;
+/// use grazie::seeders::RewriteSeeder;
+/// use regex::Regex;
+///
+/// let seeder = RewriteSeeder::new()
+///     // Force https, preserving the host, path, and query.
+///     .rule(RewriteRule::redirect_url(
+///         Regex::new(r"^http://([^/]+)(.*)$").unwrap(),
+///         StatusCode::MOVED_PERMANENTLY,
+///         "https://$1$2",
+///     ))
+///     // Strip the `/api` prefix before routing.
+///     .rule(RewriteRule::rewrite(Regex::new(r"^/api(/.*)$").unwrap(), "$1"));
+/// ```
+#[derive(Default)]
+pub struct RewriteSeeder {
+    rules: Vec<RewriteRule>,
+    trust_forwarded: bool,
+}
+
+impl RewriteSeeder {
+    /// Constructs a new `RewriteSeeder` without any rules.
+    pub fn new() -> RewriteSeeder {
+        RewriteSeeder::default()
+    }
+
+    /// Adds a rule.
+    pub fn rule(mut self, rule: RewriteRule) -> RewriteSeeder {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Sets whether the `X-Forwarded-Proto` header is trusted when building a request's URL. This
+    /// should only be enabled behind a proxy which sets the header itself.
+    pub fn trust_forwarded(mut self, trust_forwarded: bool) -> RewriteSeeder {
+        self.trust_forwarded = trust_forwarded;
+        self
+    }
+
+    /// Finds the first rule matching a request, returning its action with the replacement
+    /// expanded.
+    pub fn apply(&self, request: &HttpRequest<BoxBody>) -> Option<RewriteAction> {
+        let path = request.uri().path_and_query().map(PathAndQuery::as_str).unwrap_or("/");
+        let url = self.url(request, path);
+
+        self.rules.iter().find_map(|rule| {
+            let subject = match rule.subject {
+                Subject::Path => { path }
+                Subject::Url => { url.as_str() }
+            };
+
+            let captures = rule.pattern.captures(subject)?;
+            let mut expanded = String::new();
+
+            match &rule.action {
+                RewriteAction::Rewrite(replacement) => {
+                    captures.expand(replacement, &mut expanded);
+                    Some(RewriteAction::Rewrite(expanded))
+                }
+                RewriteAction::Redirect(status, replacement) => {
+                    captures.expand(replacement, &mut expanded);
+                    Some(RewriteAction::Redirect(*status, expanded))
+                }
+            }
+        })
+    }
+
+    /// Builds the full URL of a request.
+    fn url(&self, request: &HttpRequest<BoxBody>, path: &str) -> String {
+        let forwarded = match self.trust_forwarded {
+            true => {
+                let forwarded = request.headers().get("x-forwarded-proto");
+                forwarded.and_then(|value| value.to_str().ok())
+            }
+            false => { None }
+        };
+
+        let scheme = request.uri().scheme_str().or(forwarded).unwrap_or("http");
+        let host = request
+            .headers()
+            .get(HOST)
+            .and_then(|host| host.to_str().ok())
+            .or(request.uri().authority().map(|authority| authority.as_str()))
+            .unwrap_or_default();
+
+        format!("{scheme}://{host}{path}")
+    }
+}
+
+impl Seeder for RewriteSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        match self.apply(request) {
+            Some(RewriteAction::Rewrite(path)) => {
+                let Ok(path) = path.parse::<PathAndQuery>() else {
+                    return Guard::Accessible(request);
+                };
+                if !path.as_str().starts_with('/') {
+                    return Guard::Accessible(request);
+                }
+
+                let original = request.uri().clone();
+                let mut parts = original.clone().into_parts();
+                parts.path_and_query = Some(path);
+
+                if let Ok(uri) = Uri::from_parts(parts) {
+                    *request.uri_mut() = uri;
+                    request.extensions_mut().insert(OriginalUri(original));
+                }
+
+                Guard::Accessible(request)
+            }
+            Some(RewriteAction::Redirect(status, location)) => {
+                let Ok(location) = HeaderValue::try_from(location) else {
+                    return Guard::Accessible(request);
+                };
+
+                let response = HttpResponse::builder()
+                    .status(status)
+                    .header(LOCATION, location)
+                    .body(BoxBody::empty())
+                    .unwrap();
+
+                Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Respond(Box::new(response)),
+                    rejection: Rejection::new(
+                        status,
+                        "redirected",
+                        "The request was redirected by a rewrite rule.",
+                    ),
+                }
+            }
+            None => { Guard::Accessible(request) }
+        }
+    }
+}
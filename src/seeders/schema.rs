@@ -0,0 +1,592 @@
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::{HttpRequest, HttpResponse, Method, StatusCode};
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt::{Display, Formatter};
+
+/// An error compiling a JSON Schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    /// A JSON pointer to the part of the schema which is invalid.
+    pub pointer: String,
+
+    /// A description of the problem.
+    pub message: String,
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid schema at `{}`: {}", self.pointer, self.message)
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// A part of a document which doesn't satisfy its schema.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Violation {
+    /// A JSON pointer to the offending member of the document, such as `/items/2/price`.
+    pub pointer: String,
+
+    /// The schema keyword which wasn't satisfied, such as `"minimum"`.
+    pub keyword: &'static str,
+
+    /// A human-readable description of the problem.
+    pub message: String,
+}
+
+/// The JSON types a schema's `type` keyword can name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Type {
+    Null,
+    Boolean,
+    Object,
+    Array,
+    Number,
+    Integer,
+    String,
+}
+
+impl Type {
+    fn parse(name: &str) -> Option<Type> {
+        match name {
+            "null" => { Some(Type::Null) }
+            "boolean" => { Some(Type::Boolean) }
+            "object" => { Some(Type::Object) }
+            "array" => { Some(Type::Array) }
+            "number" => { Some(Type::Number) }
+            "integer" => { Some(Type::Integer) }
+            "string" => { Some(Type::String) }
+            _ => { None }
+        }
+    }
+
+    fn matches(self, value: &Value) -> bool {
+        match (self, value) {
+            (Type::Null, Value::Null) => { true }
+            (Type::Boolean, Value::Bool(_)) => { true }
+            (Type::Object, Value::Object(_)) => { true }
+            (Type::Array, Value::Array(_)) => { true }
+            (Type::Number, Value::Number(_)) => { true }
+            (Type::Integer, Value::Number(number)) => {
+                number.as_f64().is_some_and(|number| number.fract() == 0.0)
+            }
+            (Type::String, Value::String(_)) => { true }
+            _ => { false }
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Type::Null => { "null" }
+            Type::Boolean => { "boolean" }
+            Type::Object => { "object" }
+            Type::Array => { "array" }
+            Type::Number => { "number" }
+            Type::Integer => { "integer" }
+            Type::String => { "string" }
+        }
+    }
+}
+
+/// A compiled schema, or subschema.
+#[derive(Debug, Default)]
+struct Node {
+    /// Set for the `true` and `false` schemas, which accept everything and nothing.
+    always: Option<bool>,
+    types: Vec<Type>,
+    enumeration: Option<Vec<Value>>,
+    constant: Option<Value>,
+    minimum: Option<f64>,
+    maximum: Option<f64>,
+    exclusive_minimum: Option<f64>,
+    exclusive_maximum: Option<f64>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    #[cfg(feature = "regex")]
+    pattern: Option<regex::Regex>,
+    properties: Vec<(String, Node)>,
+    required: Vec<String>,
+    additional_properties: Option<Box<Node>>,
+    items: Option<Box<Node>>,
+    min_items: Option<usize>,
+    max_items: Option<usize>,
+    unique_items: bool,
+    all_of: Vec<Node>,
+    any_of: Vec<Node>,
+    one_of: Vec<Node>,
+    not: Option<Box<Node>>,
+}
+
+/// A JSON Schema, compiled once so that documents can be checked against it cheaply.
+///
+/// The validation keywords of JSON Schema (draft 2020-12) which don't need references are
+/// supported: `type`, `enum`, `const`, the numeric bounds, `minLength` and `maxLength`,
+/// `properties`, `required`, `additionalProperties`, `items`, `minItems`, `maxItems`,
+/// `uniqueItems`, `allOf`, `anyOf`, `oneOf` and `not`, with `pattern` under the `regex` feature.
+/// Annotations such as `title`, and keywords which aren't supported, are ignored.
+#[derive(Debug)]
+pub struct Schema {
+    root: Node,
+}
+
+impl Schema {
+    /// Compiles a schema.
+    pub fn compile(schema: &Value) -> Result<Schema, SchemaError> {
+        Ok(Schema { root: compile(schema, "")? })
+    }
+
+    /// Checks a document against the schema, returning every violation found.
+    pub fn validate(&self, document: &Value) -> Result<(), Vec<Violation>> {
+        let mut violations = Vec::new();
+        check(&self.root, document, &mut String::new(), &mut violations);
+
+        match violations.is_empty() {
+            true => { Ok(()) }
+            false => { Err(violations) }
+        }
+    }
+
+    /// Checks whether a document satisfies the schema.
+    pub fn is_valid(&self, document: &Value) -> bool {
+        let mut violations = Vec::new();
+        check(&self.root, document, &mut String::new(), &mut violations);
+        violations.is_empty()
+    }
+}
+
+/// Compiles a schema (or subschema) found at a pointer.
+fn compile(schema: &Value, pointer: &str) -> Result<Node, SchemaError> {
+    let invalid = |keyword: &str, message: &str| SchemaError {
+        pointer: format!("{pointer}/{keyword}"),
+        message: message.to_owned(),
+    };
+
+    let object = match schema {
+        Value::Bool(always) => { return Ok(Node { always: Some(*always), ..Node::default() }); }
+        Value::Object(object) => { object }
+        _ => {
+            return Err(SchemaError {
+                pointer: pointer.to_owned(),
+                message: "schemas must be objects or booleans".to_owned(),
+            });
+        }
+    };
+
+    let number = |keyword: &str| -> Result<Option<f64>, SchemaError> {
+        match object.get(keyword) {
+            None => { Ok(None) }
+            Some(value) => {
+                value.as_f64().map(Some).ok_or_else(|| invalid(keyword, "must be a number"))
+            }
+        }
+    };
+    let count = |keyword: &str| -> Result<Option<usize>, SchemaError> {
+        match object.get(keyword) {
+            None => { Ok(None) }
+            Some(value) => {
+                value
+                    .as_u64()
+                    .map(|count| Some(count as usize))
+                    .ok_or_else(|| invalid(keyword, "must be a non-negative integer"))
+            }
+        }
+    };
+    let subschemas = |keyword: &str| -> Result<Vec<Node>, SchemaError> {
+        match object.get(keyword) {
+            None => { Ok(Vec::new()) }
+            Some(Value::Array(schemas)) if !schemas.is_empty() => {
+                schemas
+                    .iter()
+                    .enumerate()
+                    .map(|(index, schema)| compile(schema, &format!("{pointer}/{keyword}/{index}")))
+                    .collect()
+            }
+            Some(_) => { Err(invalid(keyword, "must be a non-empty array of schemas")) }
+        }
+    };
+
+    let types = match object.get("type") {
+        None => { Vec::new() }
+        Some(Value::String(name)) => {
+            vec![Type::parse(name).ok_or_else(|| invalid("type", "unknown type"))?]
+        }
+        Some(Value::Array(names)) => {
+            names
+                .iter()
+                .map(|name| {
+                    name.as_str()
+                        .and_then(Type::parse)
+                        .ok_or_else(|| invalid("type", "unknown type"))
+                })
+                .collect::<Result<_, _>>()?
+        }
+        Some(_) => { return Err(invalid("type", "must be a type name or an array of them")); }
+    };
+
+    let properties = match object.get("properties") {
+        None => { Vec::new() }
+        Some(Value::Object(properties)) => {
+            properties
+                .iter()
+                .map(|(name, schema)| {
+                    let pointer = format!("{pointer}/properties/{}", escape(name));
+                    Ok((name.clone(), compile(schema, &pointer)?))
+                })
+                .collect::<Result<_, SchemaError>>()?
+        }
+        Some(_) => { return Err(invalid("properties", "must be an object of schemas")); }
+    };
+
+    let required = match object.get("required") {
+        None => { Vec::new() }
+        Some(Value::Array(names)) => {
+            names
+                .iter()
+                .map(|name| {
+                    name.as_str()
+                        .map(str::to_owned)
+                        .ok_or_else(|| invalid("required", "must be an array of strings"))
+                })
+                .collect::<Result<_, _>>()?
+        }
+        Some(_) => { return Err(invalid("required", "must be an array of strings")); }
+    };
+
+    let subschema = |keyword: &str| -> Result<Option<Box<Node>>, SchemaError> {
+        match object.get(keyword) {
+            None => { Ok(None) }
+            Some(schema) => {
+                Ok(Some(Box::new(compile(schema, &format!("{pointer}/{keyword}"))?)))
+            }
+        }
+    };
+
+    Ok(Node {
+        always: None,
+        types,
+        enumeration: match object.get("enum") {
+            None => { None }
+            Some(Value::Array(values)) => { Some(values.clone()) }
+            Some(_) => { return Err(invalid("enum", "must be an array")); }
+        },
+        constant: object.get("const").cloned(),
+        minimum: number("minimum")?,
+        maximum: number("maximum")?,
+        exclusive_minimum: number("exclusiveMinimum")?,
+        exclusive_maximum: number("exclusiveMaximum")?,
+        min_length: count("minLength")?,
+        max_length: count("maxLength")?,
+        #[cfg(feature = "regex")]
+        pattern: match object.get("pattern") {
+            None => { None }
+            Some(pattern) => {
+                let pattern = pattern
+                    .as_str()
+                    .ok_or_else(|| invalid("pattern", "must be a string"))?;
+                let pattern = regex::Regex::new(pattern)
+                    .map_err(|error| invalid("pattern", &error.to_string()))?;
+
+                Some(pattern)
+            }
+        },
+        properties,
+        required,
+        additional_properties: subschema("additionalProperties")?,
+        items: subschema("items")?,
+        min_items: count("minItems")?,
+        max_items: count("maxItems")?,
+        unique_items: object.get("uniqueItems").and_then(Value::as_bool).unwrap_or(false),
+        all_of: subschemas("allOf")?,
+        any_of: subschemas("anyOf")?,
+        one_of: subschemas("oneOf")?,
+        not: subschema("not")?,
+    })
+}
+
+/// Escapes a member name as a JSON pointer segment.
+fn escape(name: &str) -> String {
+    name.replace('~', "~0").replace('/', "~1")
+}
+
+/// Checks a value against a compiled schema, collecting violations under a pointer.
+fn check(node: &Node, value: &Value, pointer: &mut String, violations: &mut Vec<Violation>) {
+    let mut violation = |keyword: &'static str, message: String| {
+        violations.push(Violation {
+            pointer: pointer.clone(),
+            keyword,
+            message,
+        });
+    };
+
+    match node.always {
+        Some(true) => { return; }
+        Some(false) => {
+            violation("false", "no value is allowed here".to_owned());
+            return;
+        }
+        None => {}
+    }
+
+    if !node.types.is_empty() && !node.types.iter().any(|kind| kind.matches(value)) {
+        let names: Vec<&str> = node.types.iter().map(|kind| kind.name()).collect();
+        violation("type", format!("must be of type {}", names.join(" or ")));
+        return;
+    }
+
+    if node.enumeration.as_ref().is_some_and(|values| !values.contains(value)) {
+        violation("enum", "must be one of the allowed values".to_owned());
+    }
+    if node.constant.as_ref().is_some_and(|constant| constant != value) {
+        violation("const", format!("must be {}", node.constant.as_ref().unwrap()));
+    }
+
+    match value {
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or(f64::NAN);
+
+            if let Some(minimum) = node.minimum.filter(|minimum| number < *minimum) {
+                violation("minimum", format!("must be at least {minimum}"));
+            }
+            if let Some(maximum) = node.maximum.filter(|maximum| number > *maximum) {
+                violation("maximum", format!("must be at most {maximum}"));
+            }
+            if let Some(minimum) = node.exclusive_minimum.filter(|minimum| number <= *minimum) {
+                violation("exclusiveMinimum", format!("must be greater than {minimum}"));
+            }
+            if let Some(maximum) = node.exclusive_maximum.filter(|maximum| number >= *maximum) {
+                violation("exclusiveMaximum", format!("must be less than {maximum}"));
+            }
+        }
+        Value::String(string) => {
+            let length = string.chars().count();
+
+            if let Some(min_length) = node.min_length.filter(|min_length| length < *min_length) {
+                violation("minLength", format!("must be at least {min_length} characters long"));
+            }
+            if let Some(max_length) = node.max_length.filter(|max_length| length > *max_length) {
+                violation("maxLength", format!("must be at most {max_length} characters long"));
+            }
+
+            #[cfg(feature = "regex")]
+            if let Some(pattern) = node.pattern.as_ref().filter(|regex| !regex.is_match(string)) {
+                violation("pattern", format!("must match `{}`", pattern.as_str()));
+            }
+        }
+        Value::Array(elements) => {
+            if let Some(min_items) = node.min_items.filter(|min| elements.len() < *min) {
+                violation("minItems", format!("must have at least {min_items} items"));
+            }
+            if let Some(max_items) = node.max_items.filter(|max| elements.len() > *max) {
+                violation("maxItems", format!("must have at most {max_items} items"));
+            }
+            let duplicated = |(index, element): (usize, &Value)| {
+                elements[..index].contains(element)
+            };
+            if node.unique_items && elements.iter().enumerate().any(duplicated) {
+                violation("uniqueItems", "must not contain duplicate items".to_owned());
+            }
+        }
+        Value::Object(object) => {
+            for name in node.required.iter().filter(|name| !object.contains_key(*name)) {
+                violation("required", format!("is missing the required member `{name}`"));
+            }
+        }
+        _ => {}
+    }
+
+    // Subschemas push their own violations, under longer pointers.
+    match value {
+        Value::Array(elements) => {
+            if let Some(items) = &node.items {
+                for (index, element) in elements.iter().enumerate() {
+                    descend(items, element, &index.to_string(), pointer, violations);
+                }
+            }
+        }
+        Value::Object(object) => {
+            for (name, member) in object {
+                match node.properties.iter().find(|(property, _)| property == name) {
+                    Some((_, schema)) => {
+                        descend(schema, member, &escape(name), pointer, violations);
+                    }
+                    None => {
+                        if let Some(additional) = &node.additional_properties {
+                            descend(additional, member, &escape(name), pointer, violations);
+                        }
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+
+    for schema in &node.all_of {
+        check(schema, value, pointer, violations);
+    }
+
+    let matching = |schemas: &[Node], pointer: &mut String| {
+        schemas
+            .iter()
+            .filter(|schema| {
+                let mut ignored = Vec::new();
+                check(schema, value, pointer, &mut ignored);
+                ignored.is_empty()
+            })
+            .count()
+    };
+
+    if !node.any_of.is_empty() && matching(&node.any_of, pointer) == 0 {
+        violations.push(Violation {
+            pointer: pointer.clone(),
+            keyword: "anyOf",
+            message: "must match at least one of the allowed schemas".to_owned(),
+        });
+    }
+    if !node.one_of.is_empty() && matching(&node.one_of, pointer) != 1 {
+        violations.push(Violation {
+            pointer: pointer.clone(),
+            keyword: "oneOf",
+            message: "must match exactly one of the allowed schemas".to_owned(),
+        });
+    }
+    if let Some(not) = &node.not {
+        if matching(std::slice::from_ref(not.as_ref()), pointer) == 1 {
+            violations.push(Violation {
+                pointer: pointer.clone(),
+                keyword: "not",
+                message: "must not match the disallowed schema".to_owned(),
+            });
+        }
+    }
+}
+
+/// Checks a member or element, under its segment of the pointer.
+fn descend(
+    node: &Node,
+    value: &Value,
+    segment: &str,
+    pointer: &mut String,
+    violations: &mut Vec<Violation>,
+) {
+    let length = pointer.len();
+    pointer.push('/');
+    pointer.push_str(segment);
+
+    check(node, value, pointer, violations);
+    pointer.truncate(length);
+}
+
+/// A schema for requests with a method, under a path prefix.
+struct Route {
+    method: Method,
+    prefix: String,
+    schema: Schema,
+}
+
+/// A `Seeder` which validates JSON request bodies against per-route JSON Schemas before they
+/// reach handlers.
+///
+/// Schemas are compiled once, when they're added, and matched against requests by method and
+/// the longest matching path prefix. Requests whose bodies aren't JSON are rejected with
+/// `400 Bad Request` (`malformed_body`), as are bodies which don't satisfy their schema
+/// (`invalid_body`), with every violation listed under an `errors` detail, each naming the
+/// offending member by JSON pointer. Requests without a matching route pass straight through.
+///
+/// Part of the `serde_json` feature.
+#[derive(Default)]
+pub struct SchemaSeeder {
+    routes: Vec<Route>,
+}
+
+impl SchemaSeeder {
+    /// Constructs a new `SchemaSeeder`, without any schemas.
+    pub fn new() -> SchemaSeeder {
+        SchemaSeeder::default()
+    }
+
+    /// Validates the bodies of requests with a method, under a path prefix.
+    pub fn route(
+        mut self,
+        method: Method,
+        prefix: impl Into<String>,
+        schema: Schema,
+    ) -> SchemaSeeder {
+        self.routes.push(Route {
+            method,
+            prefix: prefix.into(),
+            schema,
+        });
+        self
+    }
+
+    /// Compiles a schema and validates the bodies of requests with a method, under a path prefix.
+    pub fn schema(
+        self,
+        method: Method,
+        prefix: impl Into<String>,
+        schema: &Value,
+    ) -> Result<SchemaSeeder, SchemaError> {
+        Ok(self.route(method, prefix, Schema::compile(schema)?))
+    }
+
+    /// Finds the schema for a request.
+    fn schema_for(&self, request: &HttpRequest<BoxBody>) -> Option<&Schema> {
+        let path = request.uri().path();
+
+        self.routes
+            .iter()
+            .filter(|route| route.method == request.method())
+            .filter(|route| path.starts_with(route.prefix.as_str()))
+            .max_by_key(|route| route.prefix.len())
+            .map(|route| &route.schema)
+    }
+}
+
+impl Seeder for SchemaSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        let Some(schema) = self.schema_for(request) else { return Guard::Accessible(request); };
+
+        let rejection = match serde_json::from_slice::<Value>(request.body().raw_bytes()) {
+            Ok(document) => {
+                match schema.validate(&document) {
+                    Ok(()) => { return Guard::Accessible(request); }
+                    Err(violations) => {
+                        Rejection::new(
+                            StatusCode::BAD_REQUEST,
+                            "invalid_body",
+                            "The request body doesn't match its schema.",
+                        )
+                        .details(serde_json::json!({ "errors": violations }))
+                    }
+                }
+            }
+            Err(_) => {
+                Rejection::new(
+                    StatusCode::BAD_REQUEST,
+                    "malformed_body",
+                    "The request body isn't valid JSON.",
+                )
+            }
+        };
+
+        let response = HttpResponse::builder()
+            .status(rejection.status)
+            .body(BoxBody::empty())
+            .unwrap();
+
+        Guard::Inaccessible {
+            request,
+            respondent: Respondent::Respond(Box::new(response)),
+            rejection,
+        }
+    }
+}
@@ -0,0 +1,309 @@
+//! Request rules written as [Rhai](https://rhai.rs) scripts, hot-reloaded at runtime.
+//!
+//! A `ScriptSeeder` runs its script once for every request. The script sees the request as a
+//! `request` object map, with its `method`, `path`, `query` (empty without one), `headers` (keyed
+//! by lowercase name, with repeated headers joined by `, `), and `peer` (the `PeerAddr`'s IP, or
+//! `()` without one). The value the script ends with decides what happens to the request:
+//!
+//! - `()` or `true` passes the request along unchanged.
+//! - `false` rejects the request with `403 Forbidden`.
+//! - `deny(status)` or `deny(status, message)` rejects the request with a status.
+//! - `redirect(location)` or `redirect(status, location)` redirects the request, with
+//!   `302 Found` by default.
+//! - `rewrite(path)` rewrites the request's path and query, which must start with `/`.
+//!
+//! ```
+//! use grazie::seeders::ScriptSeeder;
+//!
+//! let seeder = ScriptSeeder::new(r#"
+//!     if request.path.starts_with("/admin") && request.headers["x-internal"] != "1" {
+//!         deny(404)
+//!     } else if request.path == "/old-pricing" {
+//!         redirect(301, "/pricing")
+//!     }
+//! "#).unwrap();
+//! ```
+//!
+//! Part of the `rhai` feature.
+
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::{HeaderValue, LOCATION};
+use crate::http::{HttpRequest, HttpResponse, StatusCode, Uri};
+use crate::server::PeerAddr;
+use hyper::http::uri::PathAndQuery;
+use rhai::{Dynamic, Engine, EvalAltResult, Map, ParseError, Scope, AST};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+
+/// An error from loading a script.
+#[derive(Debug)]
+pub enum ScriptError {
+    /// A script file couldn't be read.
+    Io(PathBuf, std::io::Error),
+
+    /// A script doesn't compile.
+    Parse(ParseError),
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScriptError::Io(path, error) => {
+                write!(f, "couldn't read {}: {error}", path.display())
+            }
+            ScriptError::Parse(error) => { write!(f, "the script doesn't compile: {error}") }
+        }
+    }
+}
+
+impl Error for ScriptError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ScriptError::Io(_, error) => { Some(error) }
+            ScriptError::Parse(error) => { Some(error) }
+        }
+    }
+}
+
+/// The message of requests denied without one.
+const DENIED: &str = "The request was denied.";
+
+/// What a script decided to do with a request.
+#[derive(Debug, Clone)]
+enum Decision {
+    Deny(StatusCode, String),
+    Redirect(StatusCode, String),
+    Rewrite(String),
+}
+
+/// A `Seeder` which runs a script on every request, to pass, reject, redirect or rewrite it.
+///
+/// Seeders are cheap to clone, and every clone shares the same script, so a clone can be watching
+/// the script file while the original serves requests. Scripts are limited to 100,000 operations
+/// per request by default, and requests whose script fails or runs out of operations are answered
+/// with `500 Internal Server Error`.
+///
+/// Part of the `rhai` feature.
+#[derive(Clone)]
+pub struct ScriptSeeder {
+    engine: Arc<Engine>,
+    ast: Arc<RwLock<Arc<AST>>>,
+}
+
+impl ScriptSeeder {
+    /// Constructs a new `ScriptSeeder` running a script.
+    pub fn new(source: &str) -> Result<ScriptSeeder, ScriptError> {
+        let engine = engine(100_000);
+        let ast = engine.compile(source).map_err(ScriptError::Parse)?;
+
+        Ok(ScriptSeeder {
+            engine: Arc::new(engine),
+            ast: Arc::new(RwLock::new(Arc::new(ast))),
+        })
+    }
+
+    /// Constructs a new `ScriptSeeder` running the script in a file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<ScriptSeeder, ScriptError> {
+        ScriptSeeder::new(&read(path.as_ref())?)
+    }
+
+    /// Sets how many operations the script may run for each request.
+    pub fn max_operations(mut self, operations: u64) -> ScriptSeeder {
+        self.engine = Arc::new(engine(operations.max(1)));
+        self
+    }
+
+    /// Replaces the script. The last script is kept if the new one doesn't compile.
+    pub fn load(&self, source: &str) -> Result<(), ScriptError> {
+        let ast = self.engine.compile(source).map_err(ScriptError::Parse)?;
+        *self.ast.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(ast);
+        Ok(())
+    }
+
+    /// Replaces the script with the one in a file.
+    pub fn reload(&self, path: impl AsRef<Path>) -> Result<(), ScriptError> {
+        self.load(&read(path.as_ref())?)
+    }
+
+    /// Spawns a task which checks a script file for changes every interval, and reloads the script
+    /// when it changes.
+    ///
+    /// The task runs until it's aborted. Files which can't be read or don't compile are skipped
+    /// over, keeping the last script which loaded.
+    pub fn watch(&self, path: impl Into<PathBuf>, interval: Duration) -> JoinHandle<()> {
+        let seeder = self.clone();
+        let path = path.into();
+
+        tokio::spawn(async move {
+            let mut last: Option<SystemTime> = None;
+            let mut interval = tokio::time::interval(interval);
+
+            loop {
+                interval.tick().await;
+
+                let metadata = tokio::fs::metadata(&path).await;
+                let Ok(modified) = metadata.and_then(|metadata| metadata.modified()) else {
+                    continue;
+                };
+
+                if last != Some(modified) {
+                    if let Ok(source) = tokio::fs::read_to_string(&path).await {
+                        if seeder.load(&source).is_ok() {
+                            last = Some(modified);
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    /// Runs the script on a request, returning its decision, or `None` to pass the request along.
+    fn decide(
+        &self,
+        request: &HttpRequest<BoxBody>,
+    ) -> Result<Option<Decision>, Box<EvalAltResult>> {
+        let ast = self.ast.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+        let mut scope = Scope::new();
+        scope.push_constant("request", request_map(request));
+
+        let value = self.engine.eval_ast_with_scope::<Dynamic>(&mut scope, &ast)?;
+        if value.is_unit() {
+            return Ok(None);
+        }
+        if let Ok(pass) = value.as_bool() {
+            return Ok((!pass).then(|| Decision::Deny(StatusCode::FORBIDDEN, DENIED.to_owned())));
+        }
+
+        let type_name = value.type_name();
+        match value.try_cast::<Decision>() {
+            Some(decision) => { Ok(Some(decision)) }
+            None => { Err(format!("the script ended with a {type_name}, not a decision").into()) }
+        }
+    }
+}
+
+/// Reads a script file.
+fn read(path: &Path) -> Result<String, ScriptError> {
+    std::fs::read_to_string(path).map_err(|error| ScriptError::Io(path.to_owned(), error))
+}
+
+/// Constructs an engine with the decision functions registered, and an operation limit.
+fn engine(max_operations: u64) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(max_operations);
+    engine.register_type_with_name::<Decision>("Decision");
+
+    engine.register_fn("deny", |status: i64| deny(status, DENIED));
+    engine.register_fn("deny", deny);
+    engine.register_fn("redirect", |location: &str| redirect(302, location));
+    engine.register_fn("redirect", redirect);
+    engine.register_fn("rewrite", |path: &str| Decision::Rewrite(path.to_owned()));
+
+    engine
+}
+
+/// Rejects a request with a status.
+fn deny(status: i64, message: &str) -> Result<Decision, Box<EvalAltResult>> {
+    Ok(Decision::Deny(status_code(status)?, message.to_owned()))
+}
+
+/// Redirects a request with a status.
+fn redirect(status: i64, location: &str) -> Result<Decision, Box<EvalAltResult>> {
+    Ok(Decision::Redirect(status_code(status)?, location.to_owned()))
+}
+
+/// Converts a script's status code, raising a script error if it isn't one.
+fn status_code(status: i64) -> Result<StatusCode, Box<EvalAltResult>> {
+    u16::try_from(status)
+        .ok()
+        .and_then(|status| StatusCode::from_u16(status).ok())
+        .ok_or_else(|| format!("{status} isn't a status code").into())
+}
+
+/// Builds the `request` object map the script sees.
+fn request_map(request: &HttpRequest<BoxBody>) -> Map {
+    let mut headers = Map::new();
+    for (name, value) in request.headers() {
+        let Ok(value) = value.to_str() else { continue; };
+
+        match headers.get_mut(name.as_str()) {
+            Some(joined) => { *joined = format!("{joined}, {value}").into(); }
+            None => { headers.insert(name.as_str().into(), value.into()); }
+        }
+    }
+
+    let peer = match request.extensions().get::<PeerAddr>() {
+        Some(peer) => { peer.0.ip().to_string().into() }
+        None => { Dynamic::UNIT }
+    };
+
+    let mut map = Map::new();
+    map.insert("method".into(), request.method().as_str().into());
+    map.insert("path".into(), request.uri().path().into());
+    map.insert("query".into(), request.uri().query().unwrap_or_default().into());
+    map.insert("headers".into(), headers.into());
+    map.insert("peer".into(), peer);
+    map
+}
+
+impl Seeder for ScriptSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        let (status, code, message, location) = match self.decide(request) {
+            Ok(None) => { return Guard::Accessible(request); }
+            Ok(Some(Decision::Rewrite(path))) => {
+                let Ok(path) = path.parse::<PathAndQuery>() else {
+                    return Guard::Accessible(request);
+                };
+                if !path.as_str().starts_with('/') {
+                    return Guard::Accessible(request);
+                }
+
+                let mut parts = request.uri().clone().into_parts();
+                parts.path_and_query = Some(path);
+                if let Ok(uri) = Uri::from_parts(parts) {
+                    *request.uri_mut() = uri;
+                }
+
+                return Guard::Accessible(request);
+            }
+            Ok(Some(Decision::Deny(status, message))) => {
+                (status, "script_denied", message, None)
+            }
+            Ok(Some(Decision::Redirect(status, location))) => {
+                let Ok(location) = HeaderValue::try_from(location) else {
+                    return Guard::Accessible(request);
+                };
+
+                let message = "The request was redirected by a script.".to_owned();
+                (status, "redirected", message, Some(location))
+            }
+            Err(_) => {
+                let message = "The request's script failed.".to_owned();
+                (StatusCode::INTERNAL_SERVER_ERROR, "script_failed", message, None)
+            }
+        };
+
+        let mut response = HttpResponse::builder().status(status).body(BoxBody::empty()).unwrap();
+        if let Some(location) = location {
+            response.headers_mut().insert(LOCATION, location);
+        }
+
+        Guard::Inaccessible {
+            request,
+            respondent: Respondent::Respond(Box::new(response)),
+            rejection: Rejection::new(status, code, message),
+        }
+    }
+}
@@ -0,0 +1,369 @@
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::HeaderName;
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::util::{base64_decode, base64_encode, constant_time_eq};
+use hmac::{Hmac, Mac};
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest as _, Sha256, Sha512};
+
+/// The `Content-MD5` header.
+const CONTENT_MD5: HeaderName = HeaderName::from_static("content-md5");
+
+/// The RFC 3230 `Digest` header.
+const DIGEST: HeaderName = HeaderName::from_static("digest");
+
+/// Checks the integrity or signature of a request, for a `SignatureSeeder`.
+pub trait Verifier: Send + Sync + 'static {
+    /// Verifies a request, returning the rejection to respond with if it fails.
+    fn verify(&self, request: &HttpRequest<BoxBody>) -> Result<(), Rejection>;
+}
+
+/// A hash algorithm used for HMAC signatures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HmacAlgorithm {
+    /// HMAC-SHA1, which is only still used by older providers.
+    Sha1,
+
+    /// HMAC-SHA256.
+    Sha256,
+
+    /// HMAC-SHA512.
+    Sha512,
+}
+
+impl HmacAlgorithm {
+    /// Computes the HMAC of the concatenation of `parts` under `key`.
+    pub fn sign(&self, key: &[u8], parts: &[&[u8]]) -> Vec<u8> {
+        fn sign<M: Mac + hmac::digest::KeyInit>(key: &[u8], parts: &[&[u8]]) -> Vec<u8> {
+            let mut mac = <M as hmac::digest::KeyInit>::new_from_slice(key)
+                .expect("HMAC keys may be any length");
+
+            for part in parts {
+                mac.update(part);
+            }
+
+            mac.finalize().into_bytes().to_vec()
+        }
+
+        match self {
+            HmacAlgorithm::Sha1 => { sign::<Hmac<Sha1>>(key, parts) }
+            HmacAlgorithm::Sha256 => { sign::<Hmac<Sha256>>(key, parts) }
+            HmacAlgorithm::Sha512 => { sign::<Hmac<Sha512>>(key, parts) }
+        }
+    }
+}
+
+/// How a signature is encoded in its header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SignatureEncoding {
+    /// Lowercase or uppercase hexadecimal.
+    Hex,
+
+    /// Standard or URL-safe base64.
+    Base64,
+}
+
+impl SignatureEncoding {
+    /// Decodes a signature, returning `None` if it's malformed.
+    pub fn decode(&self, signature: &str) -> Option<Vec<u8>> {
+        match self {
+            SignatureEncoding::Hex => { decode_hex(signature.trim()) }
+            SignatureEncoding::Base64 => { base64_decode(signature) }
+        }
+    }
+}
+
+/// Decodes a hexadecimal string. Anything but pairs of hex digits is refused, including the signs
+/// `u8::from_str_radix` would otherwise accept.
+pub(crate) fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&hex[index..index + 2], 16).ok())
+        .collect()
+}
+
+/// Builds the rejection for a request with a missing or invalid signature.
+pub(crate) fn unauthorized(code: &str, message: &str) -> Rejection {
+    Rejection::new(StatusCode::UNAUTHORIZED, code, message)
+}
+
+/// Builds the rejection for a request whose body doesn't match its checksum.
+fn mismatched(code: &str, message: &str) -> Rejection {
+    Rejection::new(StatusCode::BAD_REQUEST, code, message)
+}
+
+/// Verifies the `Content-MD5` header against the request body.
+///
+/// Requests without the header are accepted, unless the header is required.
+#[derive(Debug, Clone, Default)]
+pub struct ContentMd5 {
+    required: bool,
+}
+
+impl ContentMd5 {
+    /// Constructs a new `ContentMd5` verifier.
+    pub fn new() -> ContentMd5 {
+        ContentMd5::default()
+    }
+
+    /// Rejects requests without a `Content-MD5` header.
+    pub fn required(mut self) -> ContentMd5 {
+        self.required = true;
+        self
+    }
+}
+
+impl Verifier for ContentMd5 {
+    fn verify(&self, request: &HttpRequest<BoxBody>) -> Result<(), Rejection> {
+        let Some(expected) = request.headers().get(CONTENT_MD5) else {
+            return match self.required {
+                true => {
+                    Err(mismatched(
+                        "missing_checksum",
+                        "The request is missing a Content-MD5 header.",
+                    ))
+                }
+                false => { Ok(()) }
+            };
+        };
+
+        let expected = expected.to_str().ok().and_then(base64_decode).unwrap_or_default();
+        let actual = Md5::digest(request.body().raw_bytes());
+
+        match constant_time_eq(&expected, &actual) {
+            true => { Ok(()) }
+            false => {
+                Err(mismatched(
+                    "checksum_mismatch",
+                    "The request body doesn't match its Content-MD5 header.",
+                ))
+            }
+        }
+    }
+}
+
+/// Verifies the RFC 3230 `Digest` header (with the `sha-256` or `sha-512` algorithms) against the
+/// request body.
+///
+/// Digests in other algorithms are ignored. Requests without a supported digest are accepted,
+/// unless a digest is required.
+#[derive(Debug, Clone, Default)]
+pub struct Digest {
+    required: bool,
+}
+
+impl Digest {
+    /// Constructs a new `Digest` verifier.
+    pub fn new() -> Digest {
+        Digest::default()
+    }
+
+    /// Rejects requests without a supported digest.
+    pub fn required(mut self) -> Digest {
+        self.required = true;
+        self
+    }
+
+    /// Computes the `Digest` header value for a body, using `sha-256`.
+    pub fn header_value(body: &[u8]) -> String {
+        format!("sha-256={}", base64_encode(&Sha256::digest(body)))
+    }
+}
+
+impl Verifier for Digest {
+    fn verify(&self, request: &HttpRequest<BoxBody>) -> Result<(), Rejection> {
+        let body = request.body().raw_bytes();
+        let mut verified = false;
+
+        for value in request.headers().get_all(DIGEST) {
+            let Ok(value) = value.to_str() else { continue; };
+
+            for digest in value.split(',') {
+                let Some((algorithm, expected)) = digest.trim().split_once('=') else { continue; };
+
+                let actual = match algorithm.to_ascii_lowercase().as_str() {
+                    "sha-256" => { request.body().sha256().to_vec() }
+                    "sha-512" => { Sha512::digest(body).to_vec() }
+                    _ => { continue; }
+                };
+
+                let expected = base64_decode(expected).unwrap_or_default();
+                if !constant_time_eq(&expected, &actual) {
+                    return Err(mismatched(
+                        "digest_mismatch",
+                        "The request body doesn't match its Digest header.",
+                    ));
+                }
+
+                verified = true;
+            }
+        }
+
+        match !verified && self.required {
+            true => {
+                Err(mismatched(
+                    "missing_digest",
+                    "The request is missing a supported Digest header.",
+                ))
+            }
+            false => { Ok(()) }
+        }
+    }
+}
+
+/// Verifies an HMAC signature of the request body, sent in a header.
+///
+/// Several secrets can be configured, so that secrets can be rotated without rejecting requests
+/// signed with the previous one.
+///
+/// # Example
+///
+/// GitHub signs webhook deliveries in the `X-Hub-Signature-256` header, as `sha256=<hex>`:
+///
+/// ```
+/// use grazie::http::header::HeaderName;
+/// use grazie::seeders::signature::{HmacAlgorithm, HmacSignature};
+///
+/// let header = HeaderName::from_static("x-hub-signature-256");
+/// let github = HmacSignature::new(HmacAlgorithm::Sha256, header, "secret").prefix("sha256=");
+/// ```
+#[derive(Debug, Clone)]
+pub struct HmacSignature {
+    algorithm: HmacAlgorithm,
+    header: HeaderName,
+    prefix: String,
+    encoding: SignatureEncoding,
+    secrets: Vec<Vec<u8>>,
+}
+
+impl HmacSignature {
+    /// Constructs a new `HmacSignature`, reading hex signatures from the provided header.
+    pub fn new(
+        algorithm: HmacAlgorithm,
+        header: HeaderName,
+        secret: impl Into<Vec<u8>>,
+    ) -> HmacSignature {
+        HmacSignature {
+            algorithm,
+            header,
+            prefix: String::new(),
+            encoding: SignatureEncoding::Hex,
+            secrets: vec![secret.into()],
+        }
+    }
+
+    /// Sets the prefix which comes before the signature in the header, such as `sha256=`.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> HmacSignature {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Sets how the signature is encoded. Defaults to hex.
+    pub fn encoding(mut self, encoding: SignatureEncoding) -> HmacSignature {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Accepts signatures made with another secret.
+    pub fn secret(mut self, secret: impl Into<Vec<u8>>) -> HmacSignature {
+        self.secrets.push(secret.into());
+        self
+    }
+
+    /// Checks whether a signature of the provided parts was made with any configured secret.
+    pub fn matches(&self, signature: &[u8], parts: &[&[u8]]) -> bool {
+        self.secrets
+            .iter()
+            .any(|secret| constant_time_eq(signature, &self.algorithm.sign(secret, parts)))
+    }
+}
+
+impl Verifier for HmacSignature {
+    fn verify(&self, request: &HttpRequest<BoxBody>) -> Result<(), Rejection> {
+        let header = request.headers().get(&self.header).and_then(|header| header.to_str().ok());
+        let Some(header) = header else {
+            return Err(unauthorized("missing_signature", "The request is missing its signature."));
+        };
+
+        let signature = header
+            .trim()
+            .strip_prefix(self.prefix.as_str())
+            .and_then(|signature| self.encoding.decode(signature));
+
+        let body = request.body().raw_bytes();
+        match signature.is_some_and(|signature| self.matches(&signature, &[body])) {
+            true => { Ok(()) }
+            false => {
+                Err(unauthorized("invalid_signature", "The request's signature is invalid."))
+            }
+        }
+    }
+}
+
+/// A `Seeder` which verifies request body checksums and signatures before handlers run.
+///
+/// Each configured `Verifier` is run in order, and the first to fail rejects the request: with
+/// `400 Bad Request` if the body doesn't match its checksum, or `401 Unauthorized` if its
+/// signature is missing or invalid.
+pub struct SignatureSeeder {
+    verifiers: Vec<Box<dyn Verifier>>,
+}
+
+impl SignatureSeeder {
+    /// Constructs a new `SignatureSeeder`, without any verifiers.
+    pub fn new() -> SignatureSeeder {
+        SignatureSeeder {
+            verifiers: Vec::new(),
+        }
+    }
+
+    /// Adds a verifier, which is run after those already added.
+    pub fn verify_with(mut self, verifier: impl Verifier) -> SignatureSeeder {
+        self.verifiers.push(Box::new(verifier));
+        self
+    }
+
+    /// Runs every verifier against a request.
+    pub fn verify(&self, request: &HttpRequest<BoxBody>) -> Result<(), Rejection> {
+        self.verifiers.iter().try_for_each(|verifier| verifier.verify(request))
+    }
+}
+
+impl Default for SignatureSeeder {
+    fn default() -> SignatureSeeder {
+        SignatureSeeder::new()
+    }
+}
+
+impl Seeder for SignatureSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        match self.verify(request) {
+            Ok(()) => { Guard::Accessible(request) }
+            Err(rejection) => {
+                let response = HttpResponse::builder()
+                    .status(rejection.status)
+                    .body(BoxBody::empty())
+                    .unwrap();
+
+                Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Respond(Box::new(response)),
+                    rejection,
+                }
+            }
+        }
+    }
+}
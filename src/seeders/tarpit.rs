@@ -0,0 +1,289 @@
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::CONTENT_TYPE;
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::server::PeerAddr;
+use crate::util::random_u64;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+/// Paths commonly probed by vulnerability scanners, which no legitimate client of most
+/// applications requests.
+pub const SCANNER_PATHS: &[&str] = &[
+    "/.aws/",
+    "/.env",
+    "/.git/",
+    "/.svn/",
+    "/actuator/",
+    "/cgi-bin/",
+    "/config.php",
+    "/phpmyadmin",
+    "/server-status",
+    "/vendor/phpunit/",
+    "/wp-admin",
+    "/wp-login.php",
+    "/xmlrpc.php",
+];
+
+/// How a `TarpitSeeder` answers trapped requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TarpitResponse {
+    /// Waits, then responds with an empty `404 Not Found`.
+    Delay(Duration),
+
+    /// Waits, then responds with `size` bytes of garbage.
+    Garbage {
+        /// How long to wait before responding.
+        delay: Duration,
+
+        /// How many bytes of garbage to respond with.
+        size: usize,
+    },
+}
+
+/// A snapshot of a `TarpitSeeder`'s counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TarpitMetrics {
+    /// The number of requests which were trapped.
+    pub trapped: u64,
+
+    /// The number of trapped requests answered immediately, because too many were already waiting.
+    pub overflowed: u64,
+
+    /// The number of addresses currently flagged.
+    pub flagged: usize,
+}
+
+/// How often an address has been trapped.
+#[derive(Debug, Default)]
+struct Offender {
+    hits: u32,
+    flagged: bool,
+}
+
+/// A `Seeder` which traps requests to paths probed by scanners, answering them extremely slowly
+/// (or with garbage) to waste the scanner's time.
+///
+/// Trapped requests are tracked by their `PeerAddr`. Once an address has been trapped `threshold`
+/// times it's flagged: the `on_flag` hook is called with it (such as to add it to a deny list), and
+/// when `trap_flagged` is set, every later request from it is trapped too.
+///
+/// Each trapped request holds its connection open while it waits, so only `max_waiting` requests
+/// wait at once. Beyond that, trapped requests are answered immediately.
+pub struct TarpitSeeder {
+    paths: Vec<String>,
+    response: TarpitResponse,
+    threshold: u32,
+    trap_flagged: bool,
+    on_flag: Option<Box<dyn Fn(IpAddr) + Send + Sync>>,
+    offenders: Mutex<HashMap<IpAddr, Offender>>,
+    max_tracked: usize,
+    waiting: Semaphore,
+    trapped: AtomicU64,
+    overflowed: AtomicU64,
+}
+
+impl TarpitSeeder {
+    /// Constructs a new `TarpitSeeder`, trapping `SCANNER_PATHS` with a 30 second delay.
+    pub fn new() -> TarpitSeeder {
+        TarpitSeeder {
+            paths: SCANNER_PATHS.iter().map(|path| path.to_string()).collect(),
+            response: TarpitResponse::Delay(Duration::from_secs(30)),
+            threshold: 3,
+            trap_flagged: false,
+            on_flag: None,
+            offenders: Mutex::new(HashMap::new()),
+            max_tracked: 65536,
+            waiting: Semaphore::new(256),
+            trapped: AtomicU64::new(0),
+            overflowed: AtomicU64::new(0),
+        }
+    }
+
+    /// Traps paths starting with `prefix`, in addition to those already trapped.
+    pub fn path(mut self, prefix: impl Into<String>) -> TarpitSeeder {
+        self.paths.push(prefix.into());
+        self
+    }
+
+    /// Only traps the provided path prefixes, replacing `SCANNER_PATHS`.
+    pub fn paths<P: Into<String>>(mut self, prefixes: impl IntoIterator<Item = P>) -> TarpitSeeder {
+        self.paths = prefixes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets how trapped requests are answered.
+    pub fn response(mut self, response: TarpitResponse) -> TarpitSeeder {
+        self.response = response;
+        self
+    }
+
+    /// Sets how many times an address is trapped before it's flagged.
+    pub fn threshold(mut self, threshold: u32) -> TarpitSeeder {
+        self.threshold = threshold.max(1);
+        self
+    }
+
+    /// Sets whether every request from a flagged address is trapped, rather than only requests to
+    /// trapped paths.
+    pub fn trap_flagged(mut self, trap_flagged: bool) -> TarpitSeeder {
+        self.trap_flagged = trap_flagged;
+        self
+    }
+
+    /// Sets a hook which is called with each address as it's flagged.
+    pub fn on_flag<F>(mut self, hook: F) -> TarpitSeeder
+    where
+        F: Fn(IpAddr) + Send + Sync + 'static,
+    {
+        self.on_flag = Some(Box::new(hook));
+        self
+    }
+
+    /// Sets how many trapped requests may wait at once.
+    pub fn max_waiting(mut self, max_waiting: usize) -> TarpitSeeder {
+        self.waiting = Semaphore::new(max_waiting);
+        self
+    }
+
+    /// Sets how many addresses are tracked at once. When the limit is hit, addresses which haven't
+    /// been flagged are forgotten.
+    pub fn max_tracked(mut self, max_tracked: usize) -> TarpitSeeder {
+        self.max_tracked = max_tracked;
+        self
+    }
+
+    /// Checks whether an address has been flagged.
+    pub fn is_flagged(&self, address: IpAddr) -> bool {
+        self.lock().get(&address).is_some_and(|offender| offender.flagged)
+    }
+
+    /// Gets every flagged address.
+    pub fn flagged(&self) -> Vec<IpAddr> {
+        self.lock()
+            .iter()
+            .filter(|(_, offender)| offender.flagged)
+            .map(|(address, _)| *address)
+            .collect()
+    }
+
+    /// Forgets an address, unflagging it.
+    pub fn forgive(&self, address: IpAddr) {
+        self.lock().remove(&address);
+    }
+
+    /// Gets a snapshot of this seeder's counters.
+    pub fn metrics(&self) -> TarpitMetrics {
+        TarpitMetrics {
+            trapped: self.trapped.load(Ordering::Relaxed),
+            overflowed: self.overflowed.load(Ordering::Relaxed),
+            flagged: self.lock().values().filter(|offender| offender.flagged).count(),
+        }
+    }
+
+    /// Records a trapped request from an address, flagging it once it hits the threshold.
+    fn record(&self, address: IpAddr) {
+        let mut offenders = self.lock();
+
+        if offenders.len() >= self.max_tracked && !offenders.contains_key(&address) {
+            offenders.retain(|_, offender| offender.flagged);
+        }
+
+        let offender = offenders.entry(address).or_default();
+        offender.hits = offender.hits.saturating_add(1);
+
+        if offender.flagged || offender.hits < self.threshold {
+            return;
+        }
+
+        offender.flagged = true;
+        drop(offenders);
+
+        if let Some(hook) = &self.on_flag {
+            hook(address);
+        }
+    }
+
+    /// Builds the response to a trapped request, waiting first unless too many are waiting.
+    async fn trap(&self) -> HttpResponse<BoxBody> {
+        self.trapped.fetch_add(1, Ordering::Relaxed);
+
+        let (delay, body) = match self.response {
+            TarpitResponse::Delay(delay) => { (delay, None) }
+            TarpitResponse::Garbage { delay, size } => { (delay, Some(garbage(size))) }
+        };
+
+        match self.waiting.try_acquire() {
+            Ok(_permit) => { tokio::time::sleep(delay).await; }
+            Err(_) => { self.overflowed.fetch_add(1, Ordering::Relaxed); }
+        }
+
+        match body {
+            Some(body) => {
+                HttpResponse::builder()
+                    .status(StatusCode::OK)
+                    .header(CONTENT_TYPE, "text/html")
+                    .body(BoxBody::new(body.into()))
+                    .unwrap()
+            }
+            None => { HttpResponse::builder().status(StatusCode::NOT_FOUND).body(BoxBody::empty()).unwrap() }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<IpAddr, Offender>> {
+        self.offenders.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for TarpitSeeder {
+    fn default() -> TarpitSeeder {
+        TarpitSeeder::new()
+    }
+}
+
+/// Generates `size` bytes of random lowercase letters and spaces.
+fn garbage(size: usize) -> Vec<u8> {
+    const ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz      ";
+
+    (0..size.div_ceil(8))
+        .flat_map(|_| random_u64().to_le_bytes())
+        .take(size)
+        .map(|byte| ALPHABET[byte as usize % ALPHABET.len()])
+        .collect()
+}
+
+impl Seeder for TarpitSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        let address = request.extensions().get::<PeerAddr>().map(|peer| peer.0.ip());
+        let path = request.uri().path();
+        let probing = self.paths.iter().any(|prefix| path.starts_with(prefix.as_str()));
+        let flagged = self.trap_flagged && address.is_some_and(|address| self.is_flagged(address));
+
+        if !probing && !flagged {
+            return Guard::Accessible(request);
+        }
+
+        if let Some(address) = address.filter(|_| probing) {
+            self.record(address);
+        }
+
+        let response = self.trap().await;
+
+        Guard::Inaccessible {
+            request,
+            respondent: Respondent::Respond(Box::new(response)),
+            rejection: Rejection::new(StatusCode::NOT_FOUND, "tarpitted", "The request was trapped as a scanner probe."),
+        }
+    }
+}
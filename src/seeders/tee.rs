@@ -0,0 +1,245 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::header::HeaderMap;
+use crate::http::{HttpRequest, HttpResponse, Method, StatusCode, Uri};
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, Mutex};
+
+/// A copy of a request or response body, as captured by a `TeeSeeder`.
+#[derive(Debug, Clone)]
+pub struct TeeRecord {
+    /// The sequence number of the request this record belongs to. A request and its response share
+    /// the same sequence number.
+    pub sequence: u64,
+
+    /// The method of the request this record belongs to.
+    pub method: Method,
+
+    /// The URI of the request this record belongs to.
+    pub uri: Uri,
+
+    /// The status of the response, or `None` if this record holds a request.
+    pub status: Option<StatusCode>,
+
+    /// The headers of the captured request or response.
+    pub headers: HeaderMap,
+
+    /// The captured body, truncated to the `TeeSeeder`'s maximum body size.
+    pub body: Box<[u8]>,
+
+    /// The length of the full body, before any truncation.
+    pub length: usize,
+}
+
+impl TeeRecord {
+    /// Checks whether this record's body was truncated.
+    pub fn truncated(&self) -> bool {
+        self.body.len() < self.length
+    }
+}
+
+/// A destination for the records captured by a `TeeSeeder`.
+///
+/// Records are written from a background task, so a slow sink never blocks the request path.
+pub trait TeeSink: Send + Sync + 'static {
+    /// Writes a single record to this sink.
+    fn write(&self, record: TeeRecord) -> impl Future<Output = ()> + Send;
+}
+
+/// A `TeeSink` which forwards records over a channel.
+pub struct ChannelSink(pub mpsc::Sender<TeeRecord>);
+
+impl TeeSink for ChannelSink {
+    async fn write(&self, record: TeeRecord) {
+        let _ = self.0.send(record).await;
+    }
+}
+
+/// A `TeeSink` which appends records to a file.
+///
+/// Each record is written as a header line (`> <seq> <method> <uri>` for requests, `< <seq> <status>`
+/// for responses), followed by the record's headers, a blank line, and the captured body.
+pub struct FileSink {
+    file: Mutex<File>,
+}
+
+impl FileSink {
+    /// Opens a file for appending records to, creating it if it doesn't exist.
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<FileSink> {
+        let file = OpenOptions::new().create(true).append(true).open(path).await?;
+
+        Ok(FileSink {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl TeeSink for FileSink {
+    async fn write(&self, record: TeeRecord) {
+        let mut out = match record.status {
+            Some(status) => { format!("< {} {}\n", record.sequence, status.as_u16()) }
+            None => { format!("> {} {} {}\n", record.sequence, record.method, record.uri) }
+        }.into_bytes();
+
+        for (name, value) in &record.headers {
+            out.extend_from_slice(name.as_str().as_bytes());
+            out.extend_from_slice(b": ");
+            out.extend_from_slice(value.as_bytes());
+            out.push(b'\n');
+        }
+
+        out.push(b'\n');
+        out.extend_from_slice(&record.body);
+
+        if record.truncated() {
+            out.extend_from_slice(format!("\n[truncated {} of {} bytes]", record.body.len(), record.length).as_bytes());
+        }
+
+        out.extend_from_slice(b"\n\n");
+
+        let mut file = self.file.lock().await;
+        let _ = file.write_all(&out).await;
+    }
+}
+
+/// Marks a request which was sampled by a `TeeSeeder`, so that its response is captured as well.
+#[derive(Debug, Clone, Copy)]
+struct Teed(u64);
+
+/// A `Seeder` which copies request (and optionally response) bodies to a `TeeSink`, for audit and
+/// compliance logging.
+///
+/// Captured records are queued for a background task which writes them to the sink. When the queue
+/// is full, records are dropped rather than holding up the request. Requests are always accepted.
+///
+/// Since there's no response stage to the request chain, responses are captured by passing them to
+/// `TeeSeeder::tee_response` once they've been created.
+pub struct TeeSeeder {
+    sender: mpsc::Sender<TeeRecord>,
+    sample_rate: f64,
+    max_body: usize,
+    responses: bool,
+    sequence: AtomicU64,
+    dropped: Arc<AtomicU64>,
+}
+
+impl TeeSeeder {
+    /// Constructs a new `TeeSeeder` writing to the provided sink, with room for `capacity` queued
+    /// records.
+    ///
+    /// This spawns the background writer task, and so must be called from within a tokio runtime.
+    pub fn new<S: TeeSink>(sink: S, capacity: usize) -> TeeSeeder {
+        let (sender, mut receiver) = mpsc::channel(capacity.max(1));
+
+        tokio::spawn(async move {
+            while let Some(record) = receiver.recv().await {
+                sink.write(record).await;
+            }
+        });
+
+        TeeSeeder {
+            sender,
+            sample_rate: 1.0,
+            max_body: usize::MAX,
+            responses: false,
+            sequence: AtomicU64::new(0),
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Sets the fraction of requests which are captured, between `0.0` and `1.0`.
+    ///
+    /// Sampling is deterministic: a rate of `0.25` captures exactly every fourth request.
+    pub fn sample(mut self, rate: f64) -> TeeSeeder {
+        self.sample_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets the maximum number of body bytes captured per record.
+    pub fn max_body(mut self, max_body: usize) -> TeeSeeder {
+        self.max_body = max_body;
+        self
+    }
+
+    /// Sets whether responses passed to `tee_response` are captured.
+    pub fn responses(mut self, responses: bool) -> TeeSeeder {
+        self.responses = responses;
+        self
+    }
+
+    /// Gets the number of records dropped because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Captures a response, if response capturing is enabled and its request was sampled.
+    pub fn tee_response(&self, request: &HttpRequest<BoxBody>, response: &HttpResponse<BoxBody>) {
+        if !self.responses {
+            return;
+        }
+
+        let Some(Teed(sequence)) = request.extensions().get::<Teed>().copied() else { return; };
+        let bytes = response.body().raw_bytes();
+
+        self.enqueue(TeeRecord {
+            sequence,
+            method: request.method().clone(),
+            uri: request.uri().clone(),
+            status: Some(response.status()),
+            headers: response.headers().clone(),
+            body: bytes[..bytes.len().min(self.max_body)].into(),
+            length: bytes.len(),
+        });
+    }
+
+    /// Checks whether the request with the provided sequence number is sampled.
+    fn sampled(&self, sequence: u64) -> bool {
+        let before = (sequence as f64 * self.sample_rate).floor();
+        let after = ((sequence + 1) as f64 * self.sample_rate).floor();
+
+        after > before
+    }
+
+    fn enqueue(&self, record: TeeRecord) {
+        if self.sender.try_send(record).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Seeder for TeeSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        if !self.sampled(sequence) {
+            return Guard::Accessible(request);
+        }
+
+        let bytes = request.body().raw_bytes();
+
+        self.enqueue(TeeRecord {
+            sequence,
+            method: request.method().clone(),
+            uri: request.uri().clone(),
+            status: None,
+            headers: request.headers().clone(),
+            body: bytes[..bytes.len().min(self.max_body)].into(),
+            length: bytes.len(),
+        });
+
+        request.extensions_mut().insert(Teed(sequence));
+
+        Guard::Accessible(request)
+    }
+}
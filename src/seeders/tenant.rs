@@ -0,0 +1,391 @@
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::{HeaderName, CONTENT_LENGTH, HOST, RETRY_AFTER};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use hyper::http::uri::Authority;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// The limits a tenant's requests are held to. Limits which aren't set aren't enforced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Quota {
+    /// The number of requests allowed per window, and the length of the window.
+    pub rate: Option<(u32, Duration)>,
+
+    /// The number of requests allowed in flight at once.
+    pub concurrency: Option<usize>,
+
+    /// The largest request body allowed, in bytes.
+    pub max_body: Option<usize>,
+}
+
+impl Quota {
+    /// Constructs a new `Quota`, without any limits.
+    pub fn new() -> Quota {
+        Quota::default()
+    }
+
+    /// Limits the number of requests per window.
+    pub fn rate(mut self, requests: u32, window: Duration) -> Quota {
+        self.rate = Some((requests, window));
+        self
+    }
+
+    /// Limits the number of requests in flight at once.
+    pub fn concurrency(mut self, concurrency: usize) -> Quota {
+        self.concurrency = Some(concurrency);
+        self
+    }
+
+    /// Limits the size of request bodies.
+    pub fn max_body(mut self, max_body: usize) -> Quota {
+        self.max_body = Some(max_body);
+        self
+    }
+}
+
+/// Storage for tenants' quotas.
+pub trait QuotaStore: Send + Sync {
+    /// Looks a tenant's quota up, returning `None` if the tenant isn't known.
+    fn quota(&self, tenant: &str) -> impl Future<Output = Option<Quota>> + Send;
+}
+
+/// An in-memory `QuotaStore`.
+#[derive(Default)]
+pub struct MemoryQuotaStore {
+    quotas: Mutex<HashMap<String, Quota>>,
+    fallback: Option<Quota>,
+}
+
+impl MemoryQuotaStore {
+    /// Constructs a new, empty `MemoryQuotaStore`, which doesn't know any tenants.
+    pub fn new() -> MemoryQuotaStore {
+        MemoryQuotaStore::default()
+    }
+
+    /// Sets the quota of tenants without their own, so that every tenant is known.
+    pub fn fallback(mut self, quota: Quota) -> MemoryQuotaStore {
+        self.fallback = Some(quota);
+        self
+    }
+
+    /// Sets a tenant's quota.
+    pub fn set(&self, tenant: impl Into<String>, quota: Quota) {
+        self.lock().insert(tenant.into(), quota);
+    }
+
+    /// Removes a tenant's quota.
+    pub fn remove(&self, tenant: &str) {
+        self.lock().remove(tenant);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Quota>> {
+        self.quotas.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl QuotaStore for MemoryQuotaStore {
+    async fn quota(&self, tenant: &str) -> Option<Quota> {
+        self.lock().get(tenant).copied().or(self.fallback)
+    }
+}
+
+/// Storage for fixed-window rate limit counters.
+pub trait RateLimitStore: Send + Sync {
+    /// Counts a request against a key, allowing `requests` per `window`. Returns `None` if the
+    /// request is allowed, or how long until the window resets if it isn't.
+    fn hit(
+        &self,
+        key: &str,
+        requests: u32,
+        window: Duration,
+    ) -> impl Future<Output = Option<Duration>> + Send;
+}
+
+/// An in-memory `RateLimitStore`, counting requests per instance.
+///
+/// Requests over the limit aren't counted, so they don't extend how long a key stays limited.
+#[derive(Default)]
+pub struct MemoryRateLimitStore {
+    windows: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl MemoryRateLimitStore {
+    /// Constructs a new `MemoryRateLimitStore`, without any counters.
+    pub fn new() -> MemoryRateLimitStore {
+        MemoryRateLimitStore::default()
+    }
+}
+
+impl RateLimitStore for MemoryRateLimitStore {
+    async fn hit(&self, key: &str, requests: u32, window: Duration) -> Option<Duration> {
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let (started, count) = windows.entry(key.to_owned()).or_insert((now, 0));
+
+        if now.duration_since(*started) >= window {
+            *started = now;
+            *count = 0;
+        } else if *count >= requests {
+            return Some(window - now.duration_since(*started));
+        }
+
+        *count += 1;
+        None
+    }
+}
+
+/// Finds the tenant of a request.
+pub type TenantResolver = Box<dyn Fn(&HttpRequest<BoxBody>) -> Option<String> + Send + Sync>;
+
+/// Where a `TenantSeeder` finds the tenant of a request.
+pub enum TenantSource {
+    /// The subdomain of a base domain in the `Host` header, such as `acme` in `acme.example.com`
+    /// for the base domain `example.com`.
+    Subdomain(String),
+
+    /// The value of a header.
+    Header(HeaderName),
+
+    /// A claim in the payload of the bearer token. The token's signature isn't checked, so it must
+    /// already have been verified by an earlier seeder.
+    ///
+    /// Part of the `serde_json` feature.
+    #[cfg(feature = "serde_json")]
+    Claim(String),
+
+    /// A custom resolver.
+    Custom(TenantResolver),
+}
+
+impl TenantSource {
+    /// Finds the tenant of a request.
+    fn resolve(&self, request: &HttpRequest<BoxBody>) -> Option<String> {
+        match self {
+            TenantSource::Subdomain(base) => {
+                let host = request
+                    .headers()
+                    .get(HOST)
+                    .and_then(|host| host.to_str().ok())
+                    .or(request.uri().host())?;
+
+                let authority: Authority = host.parse().ok()?;
+                let subdomain = authority.host().to_ascii_lowercase();
+                let subdomain = subdomain.strip_suffix(base.as_str())?.strip_suffix('.')?;
+
+                // Only the label directly under the base domain names the tenant.
+                subdomain.rsplit('.').next().filter(|label| !label.is_empty()).map(str::to_owned)
+            }
+            TenantSource::Header(name) => {
+                request.headers().get(name).and_then(|value| value.to_str().ok()).map(str::to_owned)
+            }
+            #[cfg(feature = "serde_json")]
+            TenantSource::Claim(claim) => {
+                let authorization = request.headers().get(crate::http::header::AUTHORIZATION)?.to_str().ok()?;
+                let token = authorization.strip_prefix("Bearer ")?;
+                let payload = crate::util::base64_decode(token.split('.').nth(1)?)?;
+                let claims: serde_json::Value = serde_json::from_slice(&payload).ok()?;
+
+                match claims.get(claim)? {
+                    serde_json::Value::String(tenant) => { Some(tenant.clone()) }
+                    serde_json::Value::Number(tenant) => { Some(tenant.to_string()) }
+                    _ => { None }
+                }
+            }
+            TenantSource::Custom(resolver) => { resolver(request) }
+        }
+    }
+}
+
+/// The tenant a request was made for, as an extension of the request. Handlers should scope every
+/// lookup to this tenant.
+///
+/// The tenant's request counts towards its concurrency quota until this extension (and every clone
+/// of it) is dropped.
+#[derive(Debug, Clone)]
+pub struct Tenant {
+    /// The tenant's identifier.
+    pub id: String,
+
+    /// The tenant's quota.
+    pub quota: Quota,
+
+    _in_flight: Arc<InFlight>,
+}
+
+/// Counts a request towards its tenant's concurrency until it's dropped.
+#[derive(Debug)]
+struct InFlight(Arc<AtomicUsize>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// A `Seeder` which resolves the tenant of each request and holds it to the tenant's quota.
+///
+/// Sources are tried in order, and the first to find a tenant wins. Requests without a tenant are
+/// rejected with `400 Bad Request`, and requests for tenants the `QuotaStore` doesn't know with
+/// `403 Forbidden`. Tenants over their rate or concurrency quota get `429 Too Many Requests`, and
+/// bodies over their size quota get `413 Payload Too Large`.
+///
+/// Rates are counted in fixed windows by a `RateLimitStore`, which is a `MemoryRateLimitStore`
+/// counting per instance unless another is set. Concurrency is always counted per instance.
+pub struct TenantSeeder<S, R = MemoryRateLimitStore> {
+    store: S,
+    limits: R,
+    sources: Vec<TenantSource>,
+    in_flight: Mutex<HashMap<String, Arc<AtomicUsize>>>,
+}
+
+impl<S: QuotaStore> TenantSeeder<S> {
+    /// Constructs a new `TenantSeeder` with the provided quota store, without any sources.
+    pub fn new(store: S) -> TenantSeeder<S> {
+        TenantSeeder {
+            store,
+            limits: MemoryRateLimitStore::new(),
+            sources: Vec::new(),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sets the store tenants' request rates are counted in, such as one shared between instances.
+    pub fn rate_limits<T: RateLimitStore>(self, limits: T) -> TenantSeeder<S, T> {
+        TenantSeeder {
+            store: self.store,
+            limits,
+            sources: self.sources,
+            in_flight: self.in_flight,
+        }
+    }
+}
+
+impl<S: QuotaStore, R: RateLimitStore> TenantSeeder<S, R> {
+    /// Adds a source to find tenants from, tried after the sources added before it.
+    pub fn source(mut self, source: TenantSource) -> TenantSeeder<S, R> {
+        self.sources.push(source);
+        self
+    }
+
+    /// Gets the number of requests a tenant has in flight.
+    pub fn in_flight(&self, tenant: &str) -> usize {
+        self.lock().get(tenant).map_or(0, |in_flight| in_flight.load(Ordering::Acquire))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Arc<AtomicUsize>>> {
+        self.in_flight.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Counts a request against a tenant's quota, returning its in-flight counter if it's allowed,
+    /// or when to retry if it's over its rate. Requests refused for concurrency aren't counted
+    /// against the tenant's rate.
+    async fn admit(&self, tenant: &str, quota: &Quota) -> Result<InFlight, Option<Duration>> {
+        let counter = self.lock().entry(tenant.to_owned()).or_default().clone();
+
+        let admitted = counter.fetch_add(1, Ordering::AcqRel);
+        let in_flight = InFlight(counter);
+
+        if quota.concurrency.is_some_and(|concurrency| admitted >= concurrency) {
+            return Err(None);
+        }
+
+        if let Some((requests, window)) = quota.rate {
+            if let Some(retry_after) = self.limits.hit(tenant, requests, window).await {
+                return Err(Some(retry_after));
+            }
+        }
+
+        Ok(in_flight)
+    }
+}
+
+impl<S: QuotaStore, R: RateLimitStore> Seeder for TenantSeeder<S, R> {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        let Some(id) = self.sources.iter().find_map(|source| source.resolve(request)) else {
+            return reject(request, StatusCode::BAD_REQUEST, "missing_tenant", "The request doesn't name a tenant.", None);
+        };
+
+        let Some(quota) = self.store.quota(&id).await else {
+            return reject(request, StatusCode::FORBIDDEN, "unknown_tenant", "The tenant isn't known.", None);
+        };
+
+        if let Some(max_body) = quota.max_body {
+            let declared = request
+                .headers()
+                .get(CONTENT_LENGTH)
+                .and_then(|length| length.to_str().ok())
+                .and_then(|length| length.parse::<usize>().ok())
+                .unwrap_or_default();
+
+            if declared.max(request.body().raw_bytes().len()) > max_body {
+                return reject(
+                    request,
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    "tenant_body_too_large",
+                    "The request body is larger than the tenant's quota allows.",
+                    None,
+                );
+            }
+        }
+
+        match self.admit(&id, &quota).await {
+            Ok(in_flight) => {
+                request.extensions_mut().insert(Tenant {
+                    id,
+                    quota,
+                    _in_flight: Arc::new(in_flight),
+                });
+
+                Guard::Accessible(request)
+            }
+            Err(Some(retry_after)) => {
+                reject(
+                    request,
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "tenant_rate_limited",
+                    "The tenant has made too many requests.",
+                    Some(retry_after),
+                )
+            }
+            Err(None) => {
+                reject(
+                    request,
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "tenant_concurrency_limited",
+                    "The tenant has too many requests in progress.",
+                    None,
+                )
+            }
+        }
+    }
+}
+
+/// Rejects a request, with an empty response.
+fn reject<'a>(
+    request: &'a mut HttpRequest<BoxBody>,
+    status: StatusCode,
+    code: &'static str,
+    message: &'static str,
+    retry_after: Option<Duration>,
+) -> Guard<'a, HttpRequest<BoxBody>> {
+    let mut response = HttpResponse::builder().status(status);
+    if let Some(retry_after) = retry_after {
+        response = response.header(RETRY_AFTER, retry_after.as_secs_f64().ceil().max(1.0) as u64);
+    }
+
+    Guard::Inaccessible {
+        request,
+        respondent: Respondent::Respond(Box::new(response.body(BoxBody::empty()).unwrap())),
+        rejection: Rejection::new(status, code, message),
+    }
+}
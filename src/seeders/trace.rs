@@ -0,0 +1,340 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::header::{HeaderMap, HeaderName, HeaderValue, HOST, USER_AGENT};
+use crate::http::{HttpRequest, HttpResponse};
+use crate::util::random_u64;
+use std::fmt::{Display, Formatter, Write};
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::mpsc;
+
+/// The W3C trace context header carrying the trace and parent span IDs.
+pub const TRACEPARENT: HeaderName = HeaderName::from_static("traceparent");
+
+/// The W3C trace context header carrying vendor-specific trace state.
+pub const TRACESTATE: HeaderName = HeaderName::from_static("tracestate");
+
+/// A parsed W3C `traceparent` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TraceParent {
+    /// The ID of the whole trace.
+    pub trace_id: [u8; 16],
+
+    /// The ID of the span which made the request.
+    pub parent_id: [u8; 8],
+
+    /// The trace flags. The lowest bit marks the trace as sampled.
+    pub flags: u8,
+}
+
+impl TraceParent {
+    /// Starts a new, sampled trace with random IDs.
+    pub fn generate() -> TraceParent {
+        let mut trace_id = [0; 16];
+        trace_id[..8].copy_from_slice(&random_u64().to_be_bytes());
+        trace_id[8..].copy_from_slice(&random_u64().to_be_bytes());
+
+        TraceParent {
+            trace_id,
+            parent_id: random_u64().to_be_bytes(),
+            flags: 1,
+        }
+    }
+
+    /// Creates a child of this trace parent, sharing its trace ID and flags with a new span ID.
+    pub fn child(&self) -> TraceParent {
+        TraceParent {
+            parent_id: random_u64().to_be_bytes(),
+            ..*self
+        }
+    }
+
+    /// Checks whether the trace is sampled.
+    pub fn sampled(&self) -> bool {
+        self.flags & 1 == 1
+    }
+}
+
+impl FromStr for TraceParent {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<TraceParent, ()> {
+        let mut parts = value.trim().split('-');
+        let (Some(version), Some(trace_id), Some(parent_id), Some(flags)) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(());
+        };
+
+        // Version `ff` is forbidden, and only version `00` may be followed by nothing else.
+        if version.len() != 2 || version == "ff" || (version == "00" && parts.next().is_some()) {
+            return Err(());
+        }
+
+        let mut parent = TraceParent {
+            trace_id: [0; 16],
+            parent_id: [0; 8],
+            flags: 0,
+        };
+        decode_hex(trace_id, &mut parent.trace_id)?;
+        decode_hex(parent_id, &mut parent.parent_id)?;
+
+        let mut flags_byte = [0];
+        decode_hex(flags, &mut flags_byte)?;
+        parent.flags = flags_byte[0];
+
+        // All-zero IDs are invalid.
+        if parent.trace_id == [0; 16] || parent.parent_id == [0; 8] {
+            return Err(());
+        }
+
+        Ok(parent)
+    }
+}
+
+impl Display for TraceParent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "00-{}-{}-{:02x}", encode_hex(&self.trace_id), encode_hex(&self.parent_id), self.flags)
+    }
+}
+
+/// Decodes a lowercase hex string into a fixed-size buffer.
+fn decode_hex(value: &str, out: &mut [u8]) -> Result<(), ()> {
+    if value.len() != out.len() * 2 || !value.bytes().all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f')) {
+        return Err(());
+    }
+
+    for (index, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&value[index * 2..index * 2 + 2], 16).map_err(|_| ())?;
+    }
+
+    Ok(())
+}
+
+/// Encodes bytes as a lowercase hex string.
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::with_capacity(bytes.len() * 2), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+/// The trace context of a request, stored in its extensions by a `TraceSeeder`.
+///
+/// `span` identifies the span created for the request; outbound requests made while handling it
+/// should carry the context through `TraceContext::inject`, so that they're parented to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    /// The span created for this request.
+    pub span: TraceParent,
+
+    /// The span ID of the incoming request's parent, if the request carried a `traceparent`.
+    pub parent_id: Option<[u8; 8]>,
+
+    /// The incoming `tracestate`, passed along unchanged.
+    pub tracestate: Option<HeaderValue>,
+}
+
+impl TraceContext {
+    /// Gets the trace ID as a hex string.
+    pub fn trace_id(&self) -> String {
+        encode_hex(&self.span.trace_id)
+    }
+
+    /// Gets the span ID as a hex string.
+    pub fn span_id(&self) -> String {
+        encode_hex(&self.span.parent_id)
+    }
+
+    /// Inserts `traceparent` and `tracestate` headers for an outbound request, parenting it to this
+    /// request's span.
+    pub fn inject(&self, headers: &mut HeaderMap) {
+        if let Ok(traceparent) = HeaderValue::from_str(&self.span.to_string()) {
+            headers.insert(TRACEPARENT, traceparent);
+        }
+
+        match &self.tracestate {
+            Some(tracestate) => { headers.insert(TRACESTATE, tracestate.clone()); }
+            None => { headers.remove(TRACESTATE); }
+        }
+    }
+}
+
+/// A span attribute value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeValue {
+    /// A string attribute.
+    String(String),
+
+    /// An integer attribute.
+    Int(i64),
+}
+
+/// A completed request span, as exported by a `TraceSeeder`.
+///
+/// Attributes follow the OpenTelemetry HTTP semantic conventions, such as `http.request.method`,
+/// `url.path`, and `http.response.status_code`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanRecord {
+    /// The span's name, such as `GET`.
+    pub name: String,
+
+    /// The span's trace context.
+    pub context: TraceContext,
+
+    /// When the span started.
+    pub start: SystemTime,
+
+    /// How long the span lasted.
+    pub duration: Duration,
+
+    /// The span's attributes.
+    pub attributes: Vec<(&'static str, AttributeValue)>,
+}
+
+/// A destination for the spans completed by a `TraceSeeder`, such as an OTLP exporter.
+///
+/// Exporting happens on the request path, so exporters should hand spans off (for example, over a
+/// channel to a batching task) rather than sending them inline.
+pub trait SpanExporter: Send + Sync + 'static {
+    /// Exports a single completed span.
+    fn export(&self, span: SpanRecord);
+}
+
+impl SpanExporter for mpsc::Sender<SpanRecord> {
+    fn export(&self, span: SpanRecord) {
+        let _ = self.try_send(span);
+    }
+}
+
+impl SpanExporter for mpsc::UnboundedSender<SpanRecord> {
+    fn export(&self, span: SpanRecord) {
+        let _ = self.send(span);
+    }
+}
+
+/// The in-progress span for a request, stored in its extensions by a `TraceSeeder`.
+#[derive(Debug, Clone)]
+struct ActiveSpan {
+    start: SystemTime,
+    started: Instant,
+    attributes: Vec<(&'static str, AttributeValue)>,
+}
+
+/// A `Seeder` which propagates W3C trace context, and records a span for each request.
+///
+/// Incoming `traceparent` and `tracestate` headers are parsed, and a child span is created for the
+/// request (or a new trace is started, if the request carried none). The request's `TraceContext`
+/// is stored in its extensions for handlers and outbound clients to propagate.
+///
+/// Spans are completed, and handed to the `SpanExporter`, once the response is passed to
+/// `TraceSeeder::finish`. Unsampled traces are propagated, but not exported.
+pub struct TraceSeeder<E> {
+    exporter: E,
+}
+
+impl<E: SpanExporter> TraceSeeder<E> {
+    /// Constructs a new `TraceSeeder`, exporting spans to the provided exporter.
+    pub fn new(exporter: E) -> TraceSeeder<E> {
+        TraceSeeder { exporter }
+    }
+
+    /// Extracts the trace context from a request's headers, creating the span for the request.
+    pub fn extract(headers: &HeaderMap) -> TraceContext {
+        let parent = headers
+            .get(TRACEPARENT)
+            .and_then(|traceparent| traceparent.to_str().ok())
+            .and_then(|traceparent| traceparent.parse::<TraceParent>().ok());
+
+        match parent {
+            Some(parent) => {
+                TraceContext {
+                    span: parent.child(),
+                    parent_id: Some(parent.parent_id),
+                    tracestate: headers.get(TRACESTATE).cloned(),
+                }
+            }
+            None => {
+                // The trace state is meaningless without the parent it belongs to.
+                TraceContext {
+                    span: TraceParent::generate(),
+                    parent_id: None,
+                    tracestate: None,
+                }
+            }
+        }
+    }
+
+    /// Completes the span for a request with its response, and exports it.
+    ///
+    /// Returns the exported span, or `None` if the request wasn't traced or isn't sampled.
+    pub fn finish(&self, request: &HttpRequest<BoxBody>, response: &HttpResponse<BoxBody>) -> Option<SpanRecord> {
+        let context = request.extensions().get::<TraceContext>()?;
+        let span = request.extensions().get::<ActiveSpan>()?;
+
+        if !context.span.sampled() {
+            return None;
+        }
+
+        let mut attributes = span.attributes.clone();
+        attributes.push(("http.response.status_code", AttributeValue::Int(response.status().as_u16().into())));
+
+        let record = SpanRecord {
+            name: request.method().to_string(),
+            context: context.clone(),
+            start: span.start,
+            duration: span.started.elapsed(),
+            attributes,
+        };
+
+        self.exporter.export(record.clone());
+        Some(record)
+    }
+}
+
+impl<E: SpanExporter> Seeder for TraceSeeder<E> {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        let context = TraceSeeder::<E>::extract(request.headers());
+
+        let mut attributes = vec![
+            ("http.request.method", AttributeValue::String(request.method().to_string())),
+            ("url.path", AttributeValue::String(request.uri().path().to_owned())),
+        ];
+
+        if let Some(query) = request.uri().query() {
+            attributes.push(("url.query", AttributeValue::String(query.to_owned())));
+        }
+
+        if let Some(scheme) = request.uri().scheme_str() {
+            attributes.push(("url.scheme", AttributeValue::String(scheme.to_owned())));
+        }
+
+        let host = request.uri().host().map(str::to_owned).or_else(|| {
+            request.headers().get(HOST).and_then(|host| host.to_str().ok()).map(str::to_owned)
+        });
+
+        if let Some(host) = host {
+            attributes.push(("server.address", AttributeValue::String(host)));
+        }
+
+        if let Some(agent) = request.headers().get(USER_AGENT).and_then(|agent| agent.to_str().ok()) {
+            attributes.push(("user_agent.original", AttributeValue::String(agent.to_owned())));
+        }
+
+        request.extensions_mut().insert(context);
+        request.extensions_mut().insert(ActiveSpan {
+            start: SystemTime::now(),
+            started: Instant::now(),
+            attributes,
+        });
+
+        Guard::Accessible(request)
+    }
+}
@@ -0,0 +1,190 @@
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::{HeaderName, ACCEPT};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use std::collections::BTreeMap;
+
+/// An API version requested by a client, as resolved by a `VersioningSeeder`.
+///
+/// Once a request has passed through a `VersioningSeeder`, the resolved `ApiVersion` is stored
+/// within the request's extensions, and can be read back with
+/// `request.extensions().get::<ApiVersion>()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ApiVersion(pub u32);
+
+/// Describes where a `VersioningSeeder` should look for the requested API version.
+pub enum VersionSource {
+    /// Reads the version from a vendor media type in the `Accept` header, such as
+    /// `application/vnd.myapp.v2+json`. The contained value is the vendor name (`myapp`).
+    Accept(String),
+
+    /// Reads the version from a custom header, such as `Api-Version: 2` or `Api-Version: v2`.
+    Header(HeaderName),
+}
+
+/// A `Seeder` which resolves the API version a client has requested, and exposes it to the rest
+/// of the request chain via the request's extensions.
+///
+/// Requests which don't specify a version fall back to the default version, if one is set.
+/// Requests which specify an unsupported version (or no version, and there isn't a default) are
+/// rejected with `406 Not Acceptable`.
+pub struct VersioningSeeder {
+    /// Where the requested version is read from.
+    source: VersionSource,
+
+    /// The versions which are accepted by this seeder. An empty list accepts any version.
+    supported: Vec<ApiVersion>,
+
+    /// The version to use whenever a request doesn't specify one.
+    default: Option<ApiVersion>,
+}
+
+impl VersioningSeeder {
+    /// Constructs a new `VersioningSeeder` which reads `application/vnd.<vendor>.v<N>` media types
+    /// from the `Accept` header.
+    pub fn accept(vendor: impl Into<String>) -> VersioningSeeder {
+        VersioningSeeder::from_source(VersionSource::Accept(vendor.into()))
+    }
+
+    /// Constructs a new `VersioningSeeder` which reads the version from a custom header.
+    pub fn header(name: HeaderName) -> VersioningSeeder {
+        VersioningSeeder::from_source(VersionSource::Header(name))
+    }
+
+    /// Constructs a new `VersioningSeeder` reading from the provided `VersionSource`.
+    pub fn from_source(source: VersionSource) -> VersioningSeeder {
+        VersioningSeeder {
+            source,
+            supported: Vec::new(),
+            default: None,
+        }
+    }
+
+    /// Restricts this seeder to only accept the provided versions.
+    pub fn supported(mut self, versions: impl IntoIterator<Item = u32>) -> VersioningSeeder {
+        self.supported = versions.into_iter().map(ApiVersion).collect();
+        self
+    }
+
+    /// Sets the version used for requests which don't specify a version.
+    pub fn default_version(mut self, version: u32) -> VersioningSeeder {
+        self.default = Some(ApiVersion(version));
+        self
+    }
+
+    /// Resolves the version requested by an `HttpRequest`, without falling back to the default
+    /// version.
+    ///
+    /// Returns `None` if the request doesn't specify a version this seeder can read.
+    pub fn resolve(&self, request: &HttpRequest<BoxBody>) -> Option<ApiVersion> {
+        match &self.source {
+            VersionSource::Accept(vendor) => {
+                let accept = request.headers().get(ACCEPT)?.to_str().ok()?;
+
+                accept
+                    .split(',')
+                    .find_map(|media_type| parse_vendor_media_type(media_type, vendor))
+            }
+            VersionSource::Header(name) => {
+                let value = request.headers().get(name)?.to_str().ok()?.trim();
+                let value = value.strip_prefix(['v', 'V']).unwrap_or(value);
+
+                value.parse().ok().map(ApiVersion)
+            }
+        }
+    }
+
+    /// Checks whether this seeder accepts the provided version.
+    pub fn accepts(&self, version: ApiVersion) -> bool {
+        self.supported.is_empty() || self.supported.contains(&version)
+    }
+}
+
+impl Seeder for VersioningSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        match self.resolve(request).or(self.default) {
+            Some(version) if self.accepts(version) => {
+                request.extensions_mut().insert(version);
+
+                Guard::Accessible(request)
+            }
+            _ => {
+                let response = HttpResponse::builder()
+                    .status(StatusCode::NOT_ACCEPTABLE)
+                    .body(BoxBody::empty())
+                    .unwrap();
+
+                Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Respond(Box::new(response)),
+                    rejection: Rejection::new(
+                        StatusCode::NOT_ACCEPTABLE,
+                        "unsupported_version",
+                        "The requested API version is not supported.",
+                    ),
+                }
+            }
+        }
+    }
+}
+
+/// Parses a single media range from an `Accept` header, returning the version from a
+/// `application/vnd.<vendor>.v<N>[+suffix]` media type.
+fn parse_vendor_media_type(media_type: &str, vendor: &str) -> Option<ApiVersion> {
+    let essence = media_type.split(';').next()?.trim();
+    let subtype = essence.strip_prefix("application/vnd.")?;
+    let subtype = subtype.split('+').next()?;
+    let version = subtype.strip_prefix(vendor)?.strip_prefix(".v")?;
+
+    version.parse().ok().map(ApiVersion)
+}
+
+/// A table of values (generally handlers) keyed by the `ApiVersion` they serve.
+///
+/// This allows for dispatching a single path to different handlers depending on the version
+/// resolved by a `VersioningSeeder`. Lookups select the newest entry which isn't newer than the
+/// requested version, so a `v3` request is served by the `v2` entry until a `v3` entry exists.
+pub struct VersionMap<H> {
+    entries: BTreeMap<ApiVersion, H>,
+}
+
+impl<H> VersionMap<H> {
+    /// Constructs a new, empty `VersionMap`.
+    pub fn new() -> VersionMap<H> {
+        VersionMap {
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Adds an entry serving the provided version (and any newer version without its own entry).
+    pub fn version(mut self, version: u32, value: H) -> VersionMap<H> {
+        self.entries.insert(ApiVersion(version), value);
+        self
+    }
+
+    /// Gets the entry which serves the provided version.
+    pub fn get(&self, version: ApiVersion) -> Option<&H> {
+        self.entries.range(..=version).next_back().map(|(_, value)| value)
+    }
+
+    /// Gets the entry which serves the version stored in a request's extensions.
+    ///
+    /// Returns `None` if the request hasn't passed through a `VersioningSeeder`, or if no entry
+    /// serves the requested version.
+    pub fn for_request(&self, request: &HttpRequest<BoxBody>) -> Option<&H> {
+        self.get(*request.extensions().get::<ApiVersion>()?)
+    }
+}
+
+impl<H> Default for VersionMap<H> {
+    fn default() -> VersionMap<H> {
+        VersionMap::new()
+    }
+}
@@ -0,0 +1,226 @@
+//! Request filters compiled to WebAssembly, run in a sandbox and hot-reloaded at runtime.
+//!
+//! A `WasmSeeder` runs a module once for every request, in a fresh instance with a fuel limit, so
+//! filters can be deployed without recompiling the server, and can't crash or stall it. Modules
+//! can't import anything, and must export:
+//!
+//! - `memory`, the module's memory.
+//! - `alloc(len: i32) -> i32`, which reserves `len` bytes of memory, returning their offset.
+//! - `on_request(ptr: i32, len: i32) -> i32`, which decides what happens to a request, given the
+//!   `len` bytes at `ptr`. It returns `0` to pass the request along, or a status code to reject
+//!   the request with.
+//!
+//! The request is handed over as UTF-8 text: a `<method> <path and query>` line, then a
+//! `<name>: <value>` line for each header with a UTF-8 value, with lowercase names. Each line
+//! ends with `\n`. Bodies aren't handed over, since the module decides before the body is read.
+//!
+//! ```
+//! use grazie::seeders::WasmSeeder;
+//!
+//! // Rejects every `DELETE` request, by checking the method's first letter.
+//! let seeder = WasmSeeder::new(r#"
+//!     (module
+//!         (memory (export "memory") 1)
+//!         (func (export "alloc") (param i32) (result i32) (i32.const 0))
+//!         (func (export "on_request") (param $ptr i32) (param $len i32) (result i32)
+//!             (if (result i32) (i32.eq (i32.load8_u (local.get $ptr)) (i32.const 68))
+//!                 (then (i32.const 405))
+//!                 (else (i32.const 0)))))
+//! "#).unwrap();
+//! ```
+//!
+//! Part of the `wasm` feature.
+
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::util::request_head;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+use wasmtime::{Config, Engine, InstancePre, Linker, Module, Store};
+
+/// An error from loading a module.
+#[derive(Debug)]
+pub enum WasmError {
+    /// A module file couldn't be read.
+    Io(PathBuf, std::io::Error),
+
+    /// A module doesn't compile, or imports something.
+    Compile(wasmtime::Error),
+}
+
+impl Display for WasmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WasmError::Io(path, error) => { write!(f, "couldn't read {}: {error}", path.display()) }
+            WasmError::Compile(error) => { write!(f, "the module doesn't compile: {error}") }
+        }
+    }
+}
+
+impl Error for WasmError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WasmError::Io(_, error) => { Some(error) }
+            WasmError::Compile(error) => { Some(error.as_ref()) }
+        }
+    }
+}
+
+/// A `Seeder` which runs a WebAssembly module on every request, to pass or reject it.
+///
+/// Seeders are cheap to clone, and every clone shares the same module, so a clone can be watching
+/// the module file while the original serves requests. Modules get 1,000,000 units of fuel per
+/// request by default, and requests whose module traps, runs out of fuel, or returns something
+/// other than `0` or a status code are answered with `500 Internal Server Error`.
+///
+/// Part of the `wasm` feature.
+#[derive(Clone)]
+pub struct WasmSeeder {
+    engine: Engine,
+    module: Arc<RwLock<InstancePre<()>>>,
+    fuel: u64,
+}
+
+impl WasmSeeder {
+    /// Constructs a new `WasmSeeder` running a module, in either the binary or the text format.
+    pub fn new(wasm: impl AsRef<[u8]>) -> Result<WasmSeeder, WasmError> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+
+        let engine = Engine::new(&config).map_err(WasmError::Compile)?;
+        let module = compile(&engine, wasm.as_ref())?;
+
+        Ok(WasmSeeder {
+            engine,
+            module: Arc::new(RwLock::new(module)),
+            fuel: 1_000_000,
+        })
+    }
+
+    /// Constructs a new `WasmSeeder` running the module in a file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<WasmSeeder, WasmError> {
+        let path = path.as_ref();
+        let wasm = std::fs::read(path).map_err(|error| WasmError::Io(path.to_owned(), error))?;
+        WasmSeeder::new(wasm)
+    }
+
+    /// Sets how much fuel the module may use for each request.
+    pub fn fuel(mut self, fuel: u64) -> WasmSeeder {
+        self.fuel = fuel;
+        self
+    }
+
+    /// Replaces the module. The last module is kept if the new one doesn't compile.
+    pub fn load(&self, wasm: impl AsRef<[u8]>) -> Result<(), WasmError> {
+        let module = compile(&self.engine, wasm.as_ref())?;
+        *self.module.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = module;
+        Ok(())
+    }
+
+    /// Replaces the module with the one in a file.
+    pub fn reload(&self, path: impl AsRef<Path>) -> Result<(), WasmError> {
+        let path = path.as_ref();
+        let wasm = std::fs::read(path).map_err(|error| WasmError::Io(path.to_owned(), error))?;
+        self.load(wasm)
+    }
+
+    /// Spawns a task which checks a module file for changes every interval, and reloads the module
+    /// when it changes.
+    ///
+    /// The task runs until it's aborted. Files which can't be read or don't compile are skipped
+    /// over, keeping the last module which loaded.
+    pub fn watch(&self, path: impl Into<PathBuf>, interval: Duration) -> JoinHandle<()> {
+        let seeder = self.clone();
+        let path = path.into();
+
+        tokio::spawn(async move {
+            let mut last: Option<SystemTime> = None;
+            let mut interval = tokio::time::interval(interval);
+
+            loop {
+                interval.tick().await;
+
+                let metadata = tokio::fs::metadata(&path).await;
+                let Ok(modified) = metadata.and_then(|metadata| metadata.modified()) else { continue; };
+
+                if last != Some(modified) {
+                    if let Ok(wasm) = tokio::fs::read(&path).await {
+                        if seeder.load(wasm).is_ok() {
+                            last = Some(modified);
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    /// Runs the module on a request, returning the status to reject it with, or `None` to pass the
+    /// request along.
+    fn decide(&self, request: &HttpRequest<BoxBody>) -> Result<Option<StatusCode>, wasmtime::Error> {
+        let module = self.module.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+        let mut store = Store::new(&self.engine, ());
+        store.set_fuel(self.fuel)?;
+
+        let instance = module.instantiate(&mut store)?;
+        let Some(memory) = instance.get_memory(&mut store, "memory") else {
+            return Err(wasmtime::Error::msg("the module doesn't export its memory"));
+        };
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+        let on_request = instance.get_typed_func::<(i32, i32), i32>(&mut store, "on_request")?;
+
+        let head = request_head(request);
+        let len = i32::try_from(head.len())?;
+        let ptr = alloc.call(&mut store, len)?;
+        memory.write(&mut store, ptr as u32 as usize, head.as_bytes())?;
+
+        match on_request.call(&mut store, (ptr, len))? {
+            0 => { Ok(None) }
+            status => {
+                u16::try_from(status)
+                    .ok()
+                    .and_then(|status| StatusCode::from_u16(status).ok())
+                    .map(Some)
+                    .ok_or_else(|| wasmtime::Error::msg(format!("{status} isn't a status code")))
+            }
+        }
+    }
+}
+
+/// Compiles a module, checking that it can be instantiated without imports.
+fn compile(engine: &Engine, wasm: &[u8]) -> Result<InstancePre<()>, WasmError> {
+    let module = Module::new(engine, wasm).map_err(WasmError::Compile)?;
+    Linker::new(engine).instantiate_pre(&module).map_err(WasmError::Compile)
+}
+
+impl Seeder for WasmSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        let rejection = match self.decide(request) {
+            Ok(None) => { return Guard::Accessible(request); }
+            Ok(Some(status)) => { Rejection::new(status, "wasm_denied", "The request was denied.") }
+            Err(_) => {
+                let message = "The request's filter failed.";
+                Rejection::new(StatusCode::INTERNAL_SERVER_ERROR, "wasm_failed", message)
+            }
+        };
+
+        let response = HttpResponse::builder().status(rejection.status).body(BoxBody::empty()).unwrap();
+
+        Guard::Inaccessible {
+            request,
+            respondent: Respondent::Respond(Box::new(response)),
+            rejection,
+        }
+    }
+}
@@ -1,20 +1,163 @@
+use std::fmt::{Display, Formatter};
+use std::net::SocketAddr;
+use std::pin::Pin;
 use tokio::net::{TcpListener, ToSocketAddrs};
 
+/// A lifecycle hook registered on an `HttpServer`.
+type Hook = Box<dyn Fn() -> Pin<Box<dyn Future<Output = Result<(), String>> + Send>> + Send + Sync>;
+
+/// A lifecycle hook which failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LifecycleError {
+    /// The name the hook was registered with.
+    pub hook: String,
+
+    /// Whether the hook ran during startup, rather than shutdown.
+    pub startup: bool,
+
+    /// The error returned by the hook.
+    pub error: String,
+}
+
+impl Display for LifecycleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let stage = if self.startup { "startup" } else { "shutdown" };
+        write!(f, "{stage} hook `{}` failed: {}", self.hook, self.error)
+    }
+}
+
+impl std::error::Error for LifecycleError {}
+
+impl From<LifecycleError> for std::io::Error {
+    fn from(error: LifecycleError) -> std::io::Error {
+        std::io::Error::other(error)
+    }
+}
+
+/// The address of the peer a request was received from, as an extension of the request.
+///
+/// This is the address of the TCP connection's other end, so behind a reverse proxy it's the
+/// proxy's address, not the client's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PeerAddr(pub SocketAddr);
+
 pub struct HttpServer {
     listener: TcpListener,
+    on_start: Vec<(String, Hook)>,
+    on_shutdown: Vec<(String, Hook)>,
 }
 
 impl HttpServer {
     pub async fn new<A: ToSocketAddrs>(host: A) -> std::io::Result<HttpServer> {
         let listener = TcpListener::bind(host).await?;
 
-        Ok(HttpServer {
+        Ok(HttpServer::from_listener(listener))
+    }
+
+    /// Creates a server from a listener which is already bound.
+    pub fn from_listener(listener: TcpListener) -> HttpServer {
+        HttpServer {
             listener,
-        })
+            on_start: Vec::new(),
+            on_shutdown: Vec::new(),
+        }
+    }
+
+    /// Creates a server from a bound standard library listener, such as one built from a raw file
+    /// descriptor. The listener is switched into non-blocking mode.
+    ///
+    /// This must be called from within a tokio runtime.
+    pub fn from_std_listener(listener: std::net::TcpListener) -> std::io::Result<HttpServer> {
+        listener.set_nonblocking(true)?;
+
+        Ok(HttpServer::from_listener(TcpListener::from_std(listener)?))
+    }
+
+    /// Creates a server from the first socket passed by systemd socket activation.
+    ///
+    /// Returns an error of kind `NotFound` if the process wasn't socket-activated. See
+    /// `grazie::activation` for taking every passed socket.
+    #[cfg(unix)]
+    pub fn from_systemd() -> std::io::Result<HttpServer> {
+        let listener = crate::activation::listeners()?
+            .into_iter()
+            .next()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no sockets were passed by systemd"))?;
+
+        HttpServer::from_std_listener(listener.listener)
+    }
+
+    /// Returns the local address that this server's listener is bound to.
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Registers a hook to run during startup, before any connections are accepted. Useful for
+    /// running migrations, or warming up connection pools.
+    ///
+    /// Startup hooks run in the order they were registered. If a hook fails, the remaining hooks
+    /// are skipped, and the server doesn't start.
+    pub fn on_start<F, Fut, E>(mut self, name: impl Into<String>, hook: F) -> HttpServer
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Display,
+    {
+        self.on_start.push((name.into(), box_hook(hook)));
+        self
+    }
+
+    /// Registers a hook to run during shutdown, once the server has stopped accepting connections.
+    /// Useful for flushing caches, or closing connection pools.
+    ///
+    /// Shutdown hooks run in the reverse order they were registered, so resources are torn down in
+    /// the opposite order they were set up. Every hook runs, even if an earlier one fails.
+    pub fn on_shutdown<F, Fut, E>(mut self, name: impl Into<String>, hook: F) -> HttpServer
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Display,
+    {
+        self.on_shutdown.push((name.into(), box_hook(hook)));
+        self
+    }
+
+    /// Runs the startup hooks, stopping at the first which fails. This is called by
+    /// `HttpServer::run` before it accepts any connections.
+    pub async fn startup(&self) -> Result<(), LifecycleError> {
+        for (name, hook) in &self.on_start {
+            hook().await.map_err(|error| LifecycleError {
+                hook: name.clone(),
+                startup: true,
+                error,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs every shutdown hook, returning those which failed. This is called by
+    /// `HttpServer::run` once it stops accepting connections.
+    pub async fn shutdown(&self) -> Vec<LifecycleError> {
+        let mut errors = Vec::new();
+
+        for (name, hook) in self.on_shutdown.iter().rev() {
+            if let Err(error) = hook().await {
+                errors.push(LifecycleError {
+                    hook: name.clone(),
+                    startup: false,
+                    error,
+                });
+            }
+        }
+
+        errors
     }
 
     /// Runs the HTTP server.
     pub async fn run(&self) -> std::io::Result<()> {
+        self.startup().await?;
+
         unimplemented!()
         // loop {
         //     let (mut socket, _) = self.listener.accept().await?;
@@ -49,3 +192,16 @@ impl HttpServer {
         // }
     }
 }
+
+/// Boxes a lifecycle hook, converting its error into a string.
+fn box_hook<F, Fut, E>(hook: F) -> Hook
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), E>> + Send + 'static,
+    E: Display,
+{
+    Box::new(move || {
+        let run = hook();
+        Box::pin(async move { run.await.map_err(|error| error.to_string()) })
+    })
+}
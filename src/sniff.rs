@@ -0,0 +1,388 @@
+//! Protocol sniffing of accepted connections, for serving HTTP, TLS, and PROXY protocol
+//! connections from a single port.
+//!
+//! A `Sniffer` reads just enough of a connection to tell which protocol it speaks, and hands back
+//! a `SniffedStream` which replays those bytes before reading on, so the connection can be passed
+//! to the right handler as if nothing had been read. Connections from trusted proxies may start
+//! with a PROXY protocol (v1 or v2) header, which is consumed, and its source address reported as
+//! the connection's peer.
+//!
+//! grazie doesn't terminate TLS itself, so connections sniffed as `Protocol::Tls` should be handed
+//! to a TLS acceptor.
+
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, ReadBuf};
+
+/// The signature which starts a PROXY protocol v2 header.
+const PROXY_V2_SIGNATURE: &[u8; 12] = b"\r\n\r\n\0\r\nQUIT\n";
+
+/// The longest a PROXY protocol v1 header may be, including its line ending.
+const PROXY_V1_MAX: usize = 107;
+
+/// The protocol a connection speaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    /// Plaintext HTTP/1.
+    Http,
+
+    /// Plaintext HTTP/2, with prior knowledge.
+    Http2,
+
+    /// A TLS handshake.
+    Tls,
+
+    /// A PROXY protocol header.
+    Proxy,
+
+    /// Anything else.
+    Unknown,
+}
+
+/// Classifies a connection from its first bytes, or returns `None` if more bytes are needed.
+pub fn classify(prefix: &[u8]) -> Option<Protocol> {
+    const HTTP2_PREFACE: &[u8] = b"PRI * HTTP/2.0";
+    const PROXY_V1: &[u8] = b"PROXY ";
+
+    let first = prefix.first()?;
+
+    // A TLS record starts with the handshake type, then a major version of 3.
+    if *first == 0x16 {
+        return match prefix.get(1) {
+            Some(0x03) => { Some(Protocol::Tls) }
+            Some(_) => { Some(Protocol::Unknown) }
+            None => { None }
+        };
+    }
+
+    for (signature, protocol) in [
+        (PROXY_V2_SIGNATURE.as_slice(), Protocol::Proxy),
+        (PROXY_V1, Protocol::Proxy),
+        (HTTP2_PREFACE, Protocol::Http2),
+    ] {
+        let length = prefix.len().min(signature.len());
+
+        if prefix[..length] == signature[..length] {
+            return match length == signature.len() {
+                true => { Some(protocol) }
+                false => { None }
+            };
+        }
+    }
+
+    // HTTP/1 requests start with a method, which is an uppercase token followed by a space.
+    match prefix.iter().position(|byte| !byte.is_ascii_uppercase()) {
+        Some(0) => { Some(Protocol::Unknown) }
+        Some(index) if prefix[index] == b' ' => { Some(Protocol::Http) }
+        Some(_) => { Some(Protocol::Unknown) }
+        None if prefix.len() >= 16 => { Some(Protocol::Unknown) }
+        None => { None }
+    }
+}
+
+/// The addresses reported by a PROXY protocol header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProxyHeader {
+    /// The address of the client which connected to the proxy, or `None` if the proxy didn't
+    /// report one (such as for its own health checks).
+    pub source: Option<SocketAddr>,
+
+    /// The address the client connected to.
+    pub destination: Option<SocketAddr>,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
+
+/// Reads a PROXY protocol v1 or v2 header from the start of a stream, leaving the stream at the
+/// first byte after it.
+pub async fn read_proxy_header<S: AsyncRead + Unpin>(stream: &mut S) -> io::Result<ProxyHeader> {
+    let mut start = [0u8; 12];
+    stream.read_exact(&mut start[..6]).await?;
+
+    if &start[..6] == b"PROXY " {
+        let mut line = start[..6].to_vec();
+
+        while !line.ends_with(b"\r\n") {
+            if line.len() >= PROXY_V1_MAX {
+                return Err(invalid("PROXY header is too long"));
+            }
+
+            line.push(stream.read_u8().await?);
+        }
+
+        return parse_proxy_v1(&line[6..line.len() - 2]);
+    }
+
+    stream.read_exact(&mut start[6..]).await?;
+    if &start != PROXY_V2_SIGNATURE {
+        return Err(invalid("connection doesn't start with a PROXY header"));
+    }
+
+    let version_command = stream.read_u8().await?;
+    let family = stream.read_u8().await?;
+    let length = stream.read_u16().await? as usize;
+
+    let mut addresses = vec![0u8; length];
+    stream.read_exact(&mut addresses).await?;
+
+    if version_command >> 4 != 2 {
+        return Err(invalid("unsupported PROXY protocol version"));
+    }
+
+    // Local connections are the proxy's own, and carry no addresses.
+    if version_command & 0x0f == 0 {
+        return Ok(ProxyHeader { source: None, destination: None });
+    }
+
+    let port = |offset: usize| u16::from_be_bytes([addresses[offset], addresses[offset + 1]]);
+
+    match family >> 4 {
+        1 if length >= 12 => {
+            let ip = |offset: usize| {
+                let octets = <[u8; 4]>::try_from(&addresses[offset..offset + 4]).unwrap();
+                IpAddr::V4(Ipv4Addr::from(octets))
+            };
+
+            Ok(ProxyHeader {
+                source: Some(SocketAddr::new(ip(0), port(8))),
+                destination: Some(SocketAddr::new(ip(4), port(10))),
+            })
+        }
+        2 if length >= 36 => {
+            let ip = |offset: usize| {
+                let octets = <[u8; 16]>::try_from(&addresses[offset..offset + 16]).unwrap();
+                IpAddr::V6(Ipv6Addr::from(octets))
+            };
+
+            Ok(ProxyHeader {
+                source: Some(SocketAddr::new(ip(0), port(32))),
+                destination: Some(SocketAddr::new(ip(16), port(34))),
+            })
+        }
+        _ => { Ok(ProxyHeader { source: None, destination: None }) }
+    }
+}
+
+/// Parses the fields of a PROXY protocol v1 header, after `PROXY ` and before the line ending.
+fn parse_proxy_v1(fields: &[u8]) -> io::Result<ProxyHeader> {
+    let fields = std::str::from_utf8(fields).map_err(|_| invalid("PROXY header isn't ASCII"))?;
+    let fields: Vec<&str> = fields.split(' ').collect();
+
+    match fields.as_slice() {
+        ["UNKNOWN", ..] => { Ok(ProxyHeader { source: None, destination: None }) }
+        ["TCP4" | "TCP6", source, destination, source_port, destination_port] => {
+            let address = |ip: &str, port: &str| -> io::Result<SocketAddr> {
+                let ip = ip
+                    .parse::<IpAddr>()
+                    .map_err(|_| invalid("PROXY header has an invalid address"))?;
+                let port = port
+                    .parse::<u16>()
+                    .map_err(|_| invalid("PROXY header has an invalid port"))?;
+                Ok(SocketAddr::new(ip, port))
+            };
+
+            Ok(ProxyHeader {
+                source: Some(address(source, source_port)?),
+                destination: Some(address(destination, destination_port)?),
+            })
+        }
+        _ => { Err(invalid("malformed PROXY header")) }
+    }
+}
+
+/// A stream which replays the bytes read while sniffing it, before reading on.
+#[derive(Debug)]
+pub struct SniffedStream<S> {
+    inner: S,
+    buffered: Vec<u8>,
+    offset: usize,
+}
+
+impl<S> SniffedStream<S> {
+    /// Wraps a stream without any bytes to replay.
+    pub fn new(inner: S) -> SniffedStream<S> {
+        SniffedStream {
+            inner,
+            buffered: Vec::new(),
+            offset: 0,
+        }
+    }
+
+    /// Gets the bytes which are yet to be replayed.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buffered[self.offset..]
+    }
+
+    /// Gets the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Unwraps the underlying stream, along with any bytes yet to be replayed.
+    pub fn into_parts(self) -> (S, Vec<u8>) {
+        (self.inner, self.buffered[self.offset..].to_vec())
+    }
+}
+
+impl<S: AsyncRead + Unpin> SniffedStream<S> {
+    /// Reads more bytes into the replay buffer, returning how many were read.
+    async fn fill(&mut self) -> io::Result<usize> {
+        let mut chunk = [0u8; 64];
+        let read = self.inner.read(&mut chunk).await?;
+        self.buffered.extend_from_slice(&chunk[..read]);
+        Ok(read)
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for SniffedStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if self.offset < self.buffered.len() {
+            let length = buf.remaining().min(self.buffered.len() - self.offset);
+            let offset = self.offset;
+
+            buf.put_slice(&self.buffered[offset..offset + length]);
+            self.offset += length;
+
+            return Poll::Ready(Ok(()));
+        }
+
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for SniffedStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+/// A connection which has been sniffed.
+#[derive(Debug)]
+pub struct Sniffed<S> {
+    /// The protocol the connection speaks, after any PROXY header.
+    pub protocol: Protocol,
+
+    /// The connection's peer: the PROXY header's source if there was one, or the TCP peer.
+    pub peer: SocketAddr,
+
+    /// The PROXY header the connection started with, if any.
+    pub proxy: Option<ProxyHeader>,
+
+    /// The connection, positioned at the start of `protocol`.
+    pub stream: SniffedStream<S>,
+}
+
+/// Sniffs the protocol of accepted connections.
+#[derive(Debug, Clone)]
+pub struct Sniffer {
+    trusted_proxies: Vec<IpAddr>,
+    timeout: Duration,
+}
+
+impl Sniffer {
+    /// Constructs a new `Sniffer`, which doesn't accept PROXY headers from any peer, and gives up
+    /// on connections which haven't sent enough to sniff within 5 seconds.
+    pub fn new() -> Sniffer {
+        Sniffer {
+            trusted_proxies: Vec::new(),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Accepts PROXY headers from a peer. Headers from untrusted peers are treated as
+    /// `Protocol::Proxy` without being read, since they could spoof any source address.
+    pub fn trust_proxy(mut self, proxy: IpAddr) -> Sniffer {
+        self.trusted_proxies.push(proxy);
+        self
+    }
+
+    /// Sets how long a connection has to send enough bytes to be sniffed.
+    pub fn timeout(mut self, timeout: Duration) -> Sniffer {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sniffs an accepted connection, consuming its PROXY header if the peer is trusted.
+    pub async fn accept<S: AsyncRead + Unpin>(
+        &self,
+        stream: S,
+        peer: SocketAddr,
+    ) -> io::Result<Sniffed<S>> {
+        tokio::time::timeout(self.timeout, self.sniff(stream, peer))
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "timed out sniffing connection"))?
+    }
+
+    async fn sniff<S: AsyncRead + Unpin>(
+        &self,
+        stream: S,
+        peer: SocketAddr,
+    ) -> io::Result<Sniffed<S>> {
+        let mut stream = SniffedStream::new(stream);
+        let mut protocol = sniff_protocol(&mut stream).await?;
+        let mut proxy = None;
+
+        if protocol == Protocol::Proxy && self.trusted_proxies.contains(&peer.ip()) {
+            let header = read_proxy_header(&mut stream).await?;
+
+            let (inner, rest) = stream.into_parts();
+            stream = SniffedStream {
+                inner,
+                buffered: rest,
+                offset: 0,
+            };
+
+            protocol = sniff_protocol(&mut stream).await?;
+            proxy = Some(header);
+        }
+
+        Ok(Sniffed {
+            protocol,
+            peer: proxy.and_then(|proxy| proxy.source).unwrap_or(peer),
+            proxy,
+            stream,
+        })
+    }
+}
+
+impl Default for Sniffer {
+    fn default() -> Sniffer {
+        Sniffer::new()
+    }
+}
+
+/// Reads until the stream's buffered bytes are enough to classify it.
+async fn sniff_protocol<S: AsyncRead + Unpin>(
+    stream: &mut SniffedStream<S>,
+) -> io::Result<Protocol> {
+    loop {
+        if let Some(protocol) = classify(stream.buffered()) {
+            return Ok(protocol);
+        }
+
+        if stream.fill().await? == 0 {
+            return Ok(Protocol::Unknown);
+        }
+    }
+}
@@ -0,0 +1,283 @@
+//! A minimal SOAP 1.1 endpoint, for integrating with legacy clients.
+//!
+//! A `SoapService` maps operations to async handlers, routing on the `SOAPAction` header, or on
+//! the name of the `Body`'s first element when the header is empty. Requests are deserialized from
+//! the `Body` and responses are serialized into one through serde, and failed operations are
+//! answered with SOAP faults:
+//!
+//! ```
+//! use grazie::soap::{SoapFault, SoapService};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Deserialize)]
+//! struct GetPrice {
+//!     #[serde(rename = "Item")]
+//!     item: String,
+//! }
+//!
+//! #[derive(Serialize)]
+//! struct GetPriceResponse {
+//!     #[serde(rename = "Price")]
+//!     price: f64,
+//! }
+//!
+//! let service = SoapService::new()
+//!     .operation("GetPrice", |request: GetPrice| async move {
+//!         match request.item.as_str() {
+//!             "Apples" => { Ok(GetPriceResponse { price: 1.5 }) }
+//!             _ => { Err(SoapFault::client("Unknown item.")) }
+//!         }
+//!     });
+//! ```
+//!
+//! Element names are matched without their namespace prefixes. Only the literal document style
+//! is supported; SOAP 1.2 and RPC-encoded messages aren't.
+//!
+//! Part of the `serde_xml` feature.
+
+use crate::core::seeder::BoxBody;
+use crate::http::header::CONTENT_TYPE;
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::pin::Pin;
+
+/// The SOAP 1.1 envelope namespace.
+pub const ENVELOPE_NAMESPACE: &str = "http://schemas.xmlsoap.org/soap/envelope/";
+
+/// The class of a SOAP fault.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultCode {
+    /// The envelope had an unsupported namespace.
+    VersionMismatch,
+
+    /// A mandatory header wasn't understood.
+    MustUnderstand,
+
+    /// The request was malformed, or otherwise the client's fault.
+    Client,
+
+    /// The request couldn't be processed, through no fault of the client.
+    Server,
+}
+
+impl FaultCode {
+    /// Gets the code's name, as it's written in a fault.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FaultCode::VersionMismatch => { "VersionMismatch" }
+            FaultCode::MustUnderstand => { "MustUnderstand" }
+            FaultCode::Client => { "Client" }
+            FaultCode::Server => { "Server" }
+        }
+    }
+}
+
+/// A SOAP fault.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SoapFault {
+    /// The class of the fault.
+    pub code: FaultCode,
+
+    /// A human readable description of the fault.
+    pub string: String,
+
+    /// The URI of the node which faulted, if it wasn't the endpoint itself.
+    pub actor: Option<String>,
+
+    /// Application specific details, as raw XML which is written into the fault unescaped.
+    pub detail: Option<String>,
+}
+
+impl SoapFault {
+    /// Constructs a new `SoapFault`.
+    pub fn new(code: FaultCode, string: impl Into<String>) -> SoapFault {
+        SoapFault {
+            code,
+            string: string.into(),
+            actor: None,
+            detail: None,
+        }
+    }
+
+    /// Constructs a `Client` fault.
+    pub fn client(string: impl Into<String>) -> SoapFault {
+        SoapFault::new(FaultCode::Client, string)
+    }
+
+    /// Constructs a `Server` fault.
+    pub fn server(string: impl Into<String>) -> SoapFault {
+        SoapFault::new(FaultCode::Server, string)
+    }
+
+    /// Sets the URI of the node which faulted.
+    pub fn actor(mut self, actor: impl Into<String>) -> SoapFault {
+        self.actor = Some(actor.into());
+        self
+    }
+
+    /// Attaches application specific details, as raw XML.
+    pub fn detail(mut self, detail: impl Into<String>) -> SoapFault {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Serializes this fault into an envelope.
+    pub fn to_envelope(&self) -> String {
+        let mut fault = format!(
+            "<soap:Fault><faultcode>soap:{}</faultcode><faultstring>{}</faultstring>",
+            self.code.as_str(),
+            escape(&self.string),
+        );
+
+        if let Some(actor) = &self.actor {
+            fault.push_str(&format!("<faultactor>{}</faultactor>", escape(actor)));
+        }
+
+        if let Some(detail) = &self.detail {
+            fault.push_str(&format!("<detail>{detail}</detail>"));
+        }
+
+        fault.push_str("</soap:Fault>");
+        wrap(&fault)
+    }
+}
+
+impl Display for SoapFault {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SOAP fault {}: {}", self.code.as_str(), self.string)
+    }
+}
+
+impl Error for SoapFault {}
+
+#[derive(Deserialize)]
+struct Envelope<T> {
+    #[serde(rename = "Body")]
+    body: Body<T>,
+}
+
+#[derive(Deserialize)]
+struct Body<T> {
+    #[serde(rename = "$value")]
+    content: T,
+}
+
+/// Deserializes the first element of an envelope's `Body`.
+pub fn from_envelope<T: DeserializeOwned>(envelope: &[u8]) -> Result<T, SoapFault> {
+    serde_xml_rs::from_reader::<_, Envelope<T>>(envelope)
+        .map(|envelope| envelope.body.content)
+        .map_err(|error| SoapFault::client(format!("Malformed request: {error}")))
+}
+
+/// Serializes a value into an envelope's `Body`, as an element named after its type.
+pub fn to_envelope<T: Serialize>(value: &T) -> Result<String, SoapFault> {
+    let content = serde_xml_rs::to_string(value).map_err(|error| SoapFault::server(format!("Malformed response: {error}")))?;
+
+    // The serializer writes its own declaration, which can't appear inside the envelope.
+    let content = match content.strip_prefix("<?xml") {
+        Some(rest) => { rest.split_once("?>").map(|(_, content)| content).unwrap_or_default() }
+        None => { content.as_str() }
+    };
+
+    Ok(wrap(content))
+}
+
+/// A type-erased operation handler.
+type Operation = Box<dyn Fn(&[u8]) -> Pin<Box<dyn Future<Output = Result<String, SoapFault>> + Send>> + Send + Sync>;
+
+/// A registry of SOAP operations.
+#[derive(Default)]
+pub struct SoapService {
+    operations: HashMap<String, Operation>,
+}
+
+impl SoapService {
+    /// Constructs a new `SoapService` without any operations.
+    pub fn new() -> SoapService {
+        SoapService::default()
+    }
+
+    /// Registers an operation, under the `SOAPAction` clients send for it (or the name of its
+    /// request element, for clients which send an empty `SOAPAction`).
+    pub fn operation<T, R, F, Fut>(mut self, action: impl Into<String>, handler: F) -> SoapService
+    where
+        T: DeserializeOwned + Send + 'static,
+        R: Serialize + 'static,
+        F: Fn(T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R, SoapFault>> + Send + 'static,
+    {
+        let operation: Operation = Box::new(move |envelope| {
+            match from_envelope::<T>(envelope) {
+                Ok(request) => {
+                    let future = handler(request);
+                    Box::pin(async move { to_envelope(&future.await?) })
+                }
+                Err(fault) => { Box::pin(async move { Err(fault) }) }
+            }
+        });
+
+        self.operations.insert(action.into(), operation);
+        self
+    }
+
+    /// Handles an envelope, returning the response envelope.
+    pub async fn handle(&self, action: Option<&str>, envelope: &[u8]) -> Result<String, SoapFault> {
+        let action = match action.map(|action| action.trim().trim_matches('"')) {
+            Some(action) if !action.is_empty() => { action.to_owned() }
+            _ => {
+                operation_name(envelope).ok_or_else(|| SoapFault::client("The request has no SOAPAction or operation."))?
+            }
+        };
+
+        match self.operations.get(&action) {
+            Some(operation) => { operation(envelope).await }
+            None => { Err(SoapFault::client(format!("Unknown SOAPAction `{action}`."))) }
+        }
+    }
+
+    /// Handles an HTTP request, responding with the response envelope, or a fault with
+    /// `500 Internal Server Error` as SOAP 1.1 requires.
+    pub async fn respond(&self, request: &HttpRequest<BoxBody>) -> HttpResponse<BoxBody> {
+        let action = request.headers().get("soapaction").and_then(|action| action.to_str().ok());
+
+        let (status, envelope) = match self.handle(action, request.body().raw_bytes()).await {
+            Ok(envelope) => { (StatusCode::OK, envelope) }
+            Err(fault) => { (StatusCode::INTERNAL_SERVER_ERROR, fault.to_envelope()) }
+        };
+
+        HttpResponse::builder()
+            .status(status)
+            .header(CONTENT_TYPE, "text/xml; charset=utf-8")
+            .body(BoxBody::new(envelope.into_bytes().into()))
+            .unwrap()
+    }
+}
+
+/// Wraps content in an envelope's `Body`.
+fn wrap(content: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?><soap:Envelope xmlns:soap=\"{ENVELOPE_NAMESPACE}\"><soap:Body>{content}</soap:Body></soap:Envelope>",
+    )
+}
+
+/// Finds the local name of the first element in an envelope's `Body`.
+fn operation_name(envelope: &[u8]) -> Option<String> {
+    let envelope = std::str::from_utf8(envelope).ok()?;
+    let mut tags = envelope.split('<').skip(1).filter(|tag| !tag.starts_with(['?', '!', '/']));
+    let local_name = |tag: &str| {
+        let name = tag.split(|c: char| c.is_whitespace() || c == '>' || c == '/').next().unwrap_or_default();
+        name.rsplit(':').next().unwrap_or_default().to_owned()
+    };
+
+    tags.find(|tag| local_name(tag) == "Body")?;
+    tags.next().map(local_name).filter(|name| !name.is_empty())
+}
+
+/// Escapes text for use in XML content.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
@@ -0,0 +1,355 @@
+//! SQL-backed stores for sessions, idempotency keys and the outbox, for deployments without
+//! Redis.
+//!
+//! The stores run over an `sqlx` pool for Postgres (the `postgres` feature) or SQLite (the
+//! `sqlite` feature). Each store keeps its rows in its own table, which `migrate` creates if it's
+//! missing:
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), sqlx::Error> {
+//! use grazie::seeders::IdempotencySeeder;
+//! use grazie::sql::SqlIdempotencyStore;
+//!
+//! let pool = sqlx::PgPool::connect("postgres://localhost/app").await?;
+//! let store = SqlIdempotencyStore::new(pool);
+//! store.migrate().await?;
+//!
+//! let seeder = IdempotencySeeder::new(store);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Expired rows are ignored as they're read, and removed by `sweep`, which is worth scheduling as
+//! a job.
+//!
+//! `sqlx` pools are also `OutboxStore`s, keeping events in the `grazie_outbox` table, which
+//! `Outbox::migrate` creates. Published events are deleted from the table, rather than kept.
+//!
+//! Part of the `postgres` and `sqlite` features.
+
+use crate::auth::session::{cookie, is_cookie_name, set_cookie, token};
+use crate::auth::session::{SessionStore, DEFAULT_COOKIE};
+use crate::core::seeder::BoxBody;
+use crate::events::Event;
+use crate::http::header::HeaderValue;
+use crate::http::HttpRequest;
+use crate::outbox::{OutboxStore, StoredEvent};
+use crate::seeders::idempotency::{IdempotencyStore, Lookup, StoredResponse};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Gets a time, as milliseconds since the Unix epoch.
+fn millis(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as i64
+}
+
+/// A `SessionStore` in a SQL database, sharing sessions between instances.
+///
+/// Values are stored as JSON, in the `grazie_sessions` table. Sessions whose value no longer
+/// deserializes are treated as missing.
+pub struct SqlSessionStore<DB: sqlx::Database, T> {
+    pool: sqlx::Pool<DB>,
+    idle_timeout: Duration,
+    cookie: String,
+    secure: bool,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<DB: sqlx::Database, T> SqlSessionStore<DB, T> {
+    /// Constructs a new `SqlSessionStore`, expiring sessions after `idle_timeout` without use.
+    pub fn new(pool: sqlx::Pool<DB>, idle_timeout: Duration) -> SqlSessionStore<DB, T> {
+        SqlSessionStore {
+            pool,
+            idle_timeout,
+            cookie: DEFAULT_COOKIE.to_owned(),
+            secure: true,
+            value: PhantomData,
+        }
+    }
+
+    /// Sets the name of the session cookie. Defaults to `DEFAULT_COOKIE`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name isn't a valid cookie name.
+    pub fn cookie(mut self, name: impl Into<String>) -> SqlSessionStore<DB, T> {
+        self.cookie = name.into();
+        assert!(is_cookie_name(&self.cookie), "A session cookie must have a valid name!");
+        self
+    }
+
+    /// Sets whether the session cookie is only sent over HTTPS. Defaults to `true`, and should
+    /// only be disabled for local development.
+    pub fn secure(mut self, secure: bool) -> SqlSessionStore<DB, T> {
+        self.secure = secure;
+        self
+    }
+}
+
+/// An `IdempotencyStore` in a SQL database, for deployments with more than one instance. Keys are
+/// stored in the `grazie_idempotency` table.
+///
+/// `begin` is atomic, through the primary key on the key column. If the database can't be
+/// reached, keys are reported as in flight, so that requests are refused rather than risk being
+/// handled twice.
+pub struct SqlIdempotencyStore<DB: sqlx::Database> {
+    pool: sqlx::Pool<DB>,
+}
+
+impl<DB: sqlx::Database> SqlIdempotencyStore<DB> {
+    /// Constructs a new `SqlIdempotencyStore`.
+    pub fn new(pool: sqlx::Pool<DB>) -> SqlIdempotencyStore<DB> {
+        SqlIdempotencyStore {
+            pool,
+        }
+    }
+}
+
+// Postgres and SQLite both accept `$n` parameters, upserts and `RETURNING`, so only the schemas
+// differ between them.
+const INSERT_SESSION: &str =
+    "INSERT INTO grazie_sessions (id, value, expires_at) VALUES ($1, $2, $3)";
+const TOUCH_SESSION: &str =
+    "UPDATE grazie_sessions SET expires_at = $1 WHERE id = $2 AND expires_at > $3 RETURNING value";
+const DELETE_SESSION: &str = "DELETE FROM grazie_sessions WHERE id = $1";
+const EXPIRE_KEY: &str =
+    "DELETE FROM grazie_idempotency WHERE idempotency_key = $1 AND expires_at <= $2";
+const INSERT_KEY: &str =
+    "INSERT INTO grazie_idempotency (idempotency_key, response, expires_at) \
+    VALUES ($1, NULL, $2) ON CONFLICT (idempotency_key) DO NOTHING";
+const SELECT_RESPONSE: &str = "SELECT response FROM grazie_idempotency WHERE idempotency_key = $1";
+const STORE_RESPONSE: &str =
+    "INSERT INTO grazie_idempotency (idempotency_key, response, expires_at) \
+    VALUES ($1, $2, $3) ON CONFLICT (idempotency_key) \
+    DO UPDATE SET response = excluded.response, expires_at = excluded.expires_at";
+const ABANDON_KEY: &str =
+    "DELETE FROM grazie_idempotency WHERE idempotency_key = $1 AND response IS NULL";
+const INSERT_EVENT: &str =
+    "INSERT INTO grazie_outbox (subject, headers, payload) VALUES ($1, $2, $3)";
+const PENDING_EVENTS: &str =
+    "SELECT id, subject, headers, payload FROM grazie_outbox ORDER BY id LIMIT $1";
+const DELETE_EVENT: &str = "DELETE FROM grazie_outbox WHERE id = $1";
+
+/// An outbox row: its ID, subject, headers as JSON, and payload.
+type EventRow = (i64, String, String, Vec<u8>);
+
+/// Decodes an outbox row into the event it stores.
+fn stored_event((id, subject, headers, payload): EventRow) -> Result<StoredEvent, sqlx::Error> {
+    let headers =
+        serde_json::from_str(&headers).map_err(|error| sqlx::Error::Decode(error.into()))?;
+
+    Ok(StoredEvent {
+        id: id as u64,
+        event: Event {
+            subject,
+            headers,
+            payload,
+        },
+    })
+}
+
+/// Implements the stores for a database, whose binary column type is `$blob`, and whose
+/// auto-incrementing primary key is declared as `$serial`.
+macro_rules! stores {
+    ($database:ty, $blob:literal, $serial:literal) => {
+        impl<T: Serialize + DeserializeOwned> SqlSessionStore<$database, T> {
+            /// Creates the sessions table, if it doesn't exist yet.
+            pub async fn migrate(&self) -> Result<(), sqlx::Error> {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS grazie_sessions \
+                    (id TEXT PRIMARY KEY, value TEXT NOT NULL, expires_at BIGINT NOT NULL)",
+                )
+                .execute(&self.pool)
+                .await?;
+
+                Ok(())
+            }
+
+            /// Removes every expired session, returning how many were removed.
+            pub async fn sweep(&self) -> Result<u64, sqlx::Error> {
+                let swept = sqlx::query("DELETE FROM grazie_sessions WHERE expires_at <= $1")
+                    .bind(millis(SystemTime::now()))
+                    .execute(&self.pool)
+                    .await?;
+
+                Ok(swept.rows_affected())
+            }
+        }
+
+        impl<T: Serialize + DeserializeOwned + Send> SessionStore<T>
+            for SqlSessionStore<$database, T>
+        {
+            type Error = sqlx::Error;
+
+            async fn create(&self, value: T) -> Result<HeaderValue, sqlx::Error> {
+                let id = token();
+                let value = serde_json::to_string(&value)
+                    .map_err(|error| sqlx::Error::Encode(error.into()))?;
+
+                sqlx::query(INSERT_SESSION)
+                    .bind(&id)
+                    .bind(value)
+                    .bind(millis(SystemTime::now() + self.idle_timeout))
+                    .execute(&self.pool)
+                    .await?;
+
+                Ok(set_cookie(&self.cookie, &id, "/", self.idle_timeout, self.secure))
+            }
+
+            async fn get(&self, request: &HttpRequest<BoxBody>) -> Result<Option<T>, sqlx::Error> {
+                let Some(id) = cookie(request, &self.cookie) else { return Ok(None); };
+                let now = SystemTime::now();
+
+                let value: Option<(String,)> = sqlx::query_as(TOUCH_SESSION)
+                    .bind(millis(now + self.idle_timeout))
+                    .bind(id)
+                    .bind(millis(now))
+                    .fetch_optional(&self.pool)
+                    .await?;
+
+                Ok(value.and_then(|(value,)| serde_json::from_str(&value).ok()))
+            }
+
+            async fn destroy(
+                &self,
+                request: &HttpRequest<BoxBody>,
+            ) -> Result<HeaderValue, sqlx::Error> {
+                if let Some(id) = cookie(request, &self.cookie) {
+                    sqlx::query(DELETE_SESSION).bind(id).execute(&self.pool).await?;
+                }
+
+                Ok(set_cookie(&self.cookie, "", "/", Duration::ZERO, self.secure))
+            }
+
+            fn is_secure(&self) -> bool {
+                self.secure
+            }
+        }
+
+        impl SqlIdempotencyStore<$database> {
+            /// Creates the idempotency table, if it doesn't exist yet.
+            pub async fn migrate(&self) -> Result<(), sqlx::Error> {
+                sqlx::query(concat!(
+                    "CREATE TABLE IF NOT EXISTS grazie_idempotency ",
+                    "(idempotency_key TEXT PRIMARY KEY, response ",
+                    $blob,
+                    ", expires_at BIGINT NOT NULL)",
+                ))
+                .execute(&self.pool)
+                .await?;
+
+                Ok(())
+            }
+
+            /// Removes every expired key, returning how many were removed.
+            pub async fn sweep(&self) -> Result<u64, sqlx::Error> {
+                let swept = sqlx::query("DELETE FROM grazie_idempotency WHERE expires_at <= $1")
+                    .bind(millis(SystemTime::now()))
+                    .execute(&self.pool)
+                    .await?;
+
+                Ok(swept.rows_affected())
+            }
+
+            async fn try_begin(&self, key: &str, ttl: Duration) -> Result<Lookup, sqlx::Error> {
+                let now = SystemTime::now();
+                sqlx::query(EXPIRE_KEY).bind(key).bind(millis(now)).execute(&self.pool).await?;
+
+                let inserted = sqlx::query(INSERT_KEY)
+                    .bind(key)
+                    .bind(millis(now + ttl))
+                    .execute(&self.pool)
+                    .await?;
+
+                if inserted.rows_affected() == 1 {
+                    return Ok(Lookup::New);
+                }
+
+                let stored: Option<(Option<Vec<u8>>,)> =
+                    sqlx::query_as(SELECT_RESPONSE).bind(key).fetch_optional(&self.pool).await?;
+
+                let response =
+                    stored.and_then(|(response,)| StoredResponse::from_bytes(&response?));
+                Ok(response.map(Lookup::Completed).unwrap_or(Lookup::InFlight))
+            }
+        }
+
+        impl IdempotencyStore for SqlIdempotencyStore<$database> {
+            async fn begin(&self, key: &str, ttl: Duration) -> Lookup {
+                self.try_begin(key, ttl).await.unwrap_or(Lookup::InFlight)
+            }
+
+            async fn complete(&self, key: &str, response: StoredResponse, ttl: Duration) {
+                let _ = sqlx::query(STORE_RESPONSE)
+                    .bind(key)
+                    .bind(response.to_bytes())
+                    .bind(millis(SystemTime::now() + ttl))
+                    .execute(&self.pool)
+                    .await;
+            }
+
+            async fn abandon(&self, key: &str) {
+                let _ = sqlx::query(ABANDON_KEY).bind(key).execute(&self.pool).await;
+            }
+        }
+
+        impl OutboxStore for sqlx::Pool<$database> {
+            async fn insert(
+                &self,
+                transaction: &mut sqlx::Transaction<'static, $database>,
+                event: &Event,
+            ) -> Result<(), sqlx::Error> {
+                let headers = serde_json::to_string(&event.headers)
+                    .map_err(|error| sqlx::Error::Encode(error.into()))?;
+
+                sqlx::query(INSERT_EVENT)
+                    .bind(&event.subject)
+                    .bind(headers)
+                    .bind(&event.payload)
+                    .execute(&mut **transaction)
+                    .await?;
+
+                Ok(())
+            }
+
+            async fn pending(&self, limit: usize) -> Result<Vec<StoredEvent>, sqlx::Error> {
+                let rows: Vec<EventRow> = sqlx::query_as(PENDING_EVENTS)
+                    .bind(limit.min(i64::MAX as usize) as i64)
+                    .fetch_all(self)
+                    .await?;
+
+                rows.into_iter().map(stored_event).collect()
+            }
+
+            async fn mark_published(&self, ids: &[u64]) -> Result<(), sqlx::Error> {
+                for &id in ids {
+                    sqlx::query(DELETE_EVENT).bind(id as i64).execute(self).await?;
+                }
+
+                Ok(())
+            }
+
+            async fn migrate(&self) -> Result<(), sqlx::Error> {
+                sqlx::query(concat!(
+                    "CREATE TABLE IF NOT EXISTS grazie_outbox (id ",
+                    $serial,
+                    ", subject TEXT NOT NULL, headers TEXT NOT NULL, payload ",
+                    $blob,
+                    " NOT NULL)",
+                ))
+                .execute(self)
+                .await?;
+
+                Ok(())
+            }
+        }
+    };
+}
+
+#[cfg(feature = "postgres")]
+stores!(sqlx::Postgres, "BYTEA", "BIGSERIAL PRIMARY KEY");
+
+#[cfg(feature = "sqlite")]
+stores!(sqlx::Sqlite, "BLOB", "INTEGER PRIMARY KEY AUTOINCREMENT");
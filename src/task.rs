@@ -0,0 +1,238 @@
+//! Offloading of blocking and CPU-heavy work.
+//!
+//! Work such as image processing or password hashing blocks whichever thread runs it. A
+//! `BlockingPool` runs that work on its own dedicated threads, separate from tokio's runtime and
+//! blocking pool, with a bounded queue so a burst of heavy requests is turned away rather than
+//! piling up without limit.
+
+use crate::seeders::catch_panic::Panic;
+use std::fmt::{Display, Formatter};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use tokio::sync::oneshot;
+
+/// A unit of work queued on a `BlockingPool`.
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// The limits of a `BlockingPool`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockingConfig {
+    /// The number of worker threads.
+    pub threads: usize,
+
+    /// The most jobs which may wait in the queue at once. Jobs submitted beyond this are rejected.
+    pub queue_limit: usize,
+
+    /// The prefix of each worker thread's name.
+    pub thread_name: String,
+}
+
+impl Default for BlockingConfig {
+    fn default() -> BlockingConfig {
+        BlockingConfig {
+            threads: thread::available_parallelism().map(usize::from).unwrap_or(4),
+            queue_limit: 1024,
+            thread_name: "grazie-blocking".to_owned(),
+        }
+    }
+}
+
+/// A snapshot of a `BlockingPool`'s counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockingMetrics {
+    /// The number of jobs waiting in the queue.
+    pub queued: usize,
+
+    /// The number of jobs currently running.
+    pub active: usize,
+
+    /// The number of jobs which ran to completion, including those which panicked.
+    pub completed: u64,
+
+    /// The number of jobs rejected because the queue was full.
+    pub rejected: u64,
+
+    /// The number of jobs which panicked.
+    pub panicked: u64,
+}
+
+/// Why a job on a `BlockingPool` didn't produce a value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockingError {
+    /// The queue was full, so the job was never run.
+    QueueFull,
+
+    /// The job panicked.
+    Panicked(Panic),
+
+    /// The pool shut down before the job ran.
+    Shutdown,
+}
+
+impl Display for BlockingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockingError::QueueFull => { write!(f, "the blocking queue is full") }
+            BlockingError::Panicked(panic) => { write!(f, "the blocking job {panic}") }
+            BlockingError::Shutdown => { write!(f, "the blocking pool has shut down") }
+        }
+    }
+}
+
+impl std::error::Error for BlockingError {}
+
+/// The counters shared between a `BlockingPool` and its workers.
+#[derive(Default)]
+struct Counters {
+    queued: AtomicUsize,
+    active: AtomicUsize,
+    completed: AtomicU64,
+    rejected: AtomicU64,
+    panicked: AtomicU64,
+}
+
+/// A dedicated pool of threads for running blocking work.
+///
+/// `BlockingPool`s are cheap to clone, and all clones share the same threads. The threads exit
+/// once every clone has been dropped and the queue has drained.
+#[derive(Clone)]
+pub struct BlockingPool {
+    sender: SyncSender<Job>,
+    counters: Arc<Counters>,
+}
+
+impl BlockingPool {
+    /// Constructs a new `BlockingPool`, spawning its worker threads.
+    pub fn new(config: BlockingConfig) -> BlockingPool {
+        let (sender, receiver) = sync_channel::<Job>(config.queue_limit);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let counters = Arc::new(Counters::default());
+
+        for index in 0..config.threads.max(1) {
+            let receiver = receiver.clone();
+
+            thread::Builder::new()
+                .name(format!("{}-{index}", config.thread_name))
+                .spawn(move || BlockingPool::work(&receiver))
+                .expect("failed to spawn a blocking pool thread");
+        }
+
+        BlockingPool { sender, counters }
+    }
+
+    /// Runs a closure on the pool, waiting for its result.
+    pub async fn run<F, T>(&self, f: F) -> Result<T, BlockingError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        let counters = self.counters.clone();
+
+        let job: Job = Box::new(move || {
+            counters.queued.fetch_sub(1, Ordering::Relaxed);
+            counters.active.fetch_add(1, Ordering::Relaxed);
+
+            let result = catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+                counters.panicked.fetch_add(1, Ordering::Relaxed);
+                BlockingError::Panicked(Panic::from_payload(payload.as_ref()))
+            });
+
+            counters.active.fetch_sub(1, Ordering::Relaxed);
+            counters.completed.fetch_add(1, Ordering::Relaxed);
+
+            // The caller may have stopped waiting, in which case the result is discarded.
+            let _ = sender.send(result);
+        });
+
+        self.counters.queued.fetch_add(1, Ordering::Relaxed);
+        match self.sender.try_send(job) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                self.counters.queued.fetch_sub(1, Ordering::Relaxed);
+                self.counters.rejected.fetch_add(1, Ordering::Relaxed);
+                return Err(BlockingError::QueueFull);
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                self.counters.queued.fetch_sub(1, Ordering::Relaxed);
+                return Err(BlockingError::Shutdown);
+            }
+        }
+
+        receiver.await.unwrap_or(Err(BlockingError::Shutdown))
+    }
+
+    /// Gets a snapshot of this pool's counters.
+    pub fn metrics(&self) -> BlockingMetrics {
+        BlockingMetrics {
+            queued: self.counters.queued.load(Ordering::Relaxed),
+            active: self.counters.active.load(Ordering::Relaxed),
+            completed: self.counters.completed.load(Ordering::Relaxed),
+            rejected: self.counters.rejected.load(Ordering::Relaxed),
+            panicked: self.counters.panicked.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Runs jobs from the queue until every sender has been dropped.
+    fn work(receiver: &Mutex<Receiver<Job>>) {
+        loop {
+            let job = {
+                let receiver = receiver.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                receiver.recv()
+            };
+
+            match job {
+                Ok(job) => { job(); }
+                Err(_) => { return; }
+            }
+        }
+    }
+}
+
+/// The pool used by `blocking`.
+static DEFAULT_POOL: OnceLock<BlockingPool> = OnceLock::new();
+
+/// Sets the configuration of the pool used by `blocking`.
+///
+/// This must be called before the first call to `blocking`; returns `false` if the pool has
+/// already been started.
+pub fn configure(config: BlockingConfig) -> bool {
+    let mut configured = false;
+
+    DEFAULT_POOL.get_or_init(|| {
+        configured = true;
+        BlockingPool::new(config)
+    });
+
+    configured
+}
+
+/// Gets the pool used by `blocking`, starting it with the default configuration if it hasn't
+/// been configured.
+pub fn default_pool() -> &'static BlockingPool {
+    DEFAULT_POOL.get_or_init(|| BlockingPool::new(BlockingConfig::default()))
+}
+
+/// Runs a closure on the default `BlockingPool`, waiting for its result.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn example() -> Result<(), grazie::task::BlockingError> {
+/// let digest = grazie::task::blocking(|| {
+///     // Some CPU-heavy work, such as hashing a password.
+///     (0..1_000_000u64).fold(0u64, |hash, n| hash.wrapping_mul(31).wrapping_add(n))
+/// }).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn blocking<F, T>(f: F) -> Result<T, BlockingError>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    default_pool().run(f).await
+}
@@ -0,0 +1,442 @@
+//! Helpers for testing seeders and applications built on `grazie`.
+
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder, Unpacker};
+use crate::http::{HttpRequest, HttpResponse, Method, StatusCode, Uri};
+use crate::recorder::{parse, Recording};
+use crate::http::header::HeaderName;
+use std::collections::VecDeque;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+/// The outcome of replaying a single recorded request.
+pub struct Replayed {
+    /// The recording which was replayed.
+    pub recording: Recording,
+
+    /// The request, as it was left by the seeder chain.
+    pub request: HttpRequest<BoxBody>,
+
+    /// The rejection, if the seeder chain rejected the request.
+    pub rejection: Option<Rejection>,
+
+    /// The response the seeder chain answered with, if it rejected the request with one.
+    pub response: Option<HttpResponse<BoxBody>>,
+}
+
+impl Replayed {
+    /// Checks whether the seeder chain accepted the request.
+    pub fn accepted(&self) -> bool {
+        self.rejection.is_none()
+    }
+}
+
+/// Loads the recordings from a file written by a `Recorder`.
+pub async fn load(path: impl AsRef<Path>) -> io::Result<Vec<Recording>> {
+    parse(&tokio::fs::read(path).await?)
+}
+
+/// Feeds every request recorded to a file back through a seeder chain, in the order they were
+/// recorded.
+pub async fn replay<S: Seeder>(file: impl AsRef<Path>, seeder: &S) -> io::Result<Vec<Replayed>> {
+    let mut replayed = Vec::new();
+
+    for recording in load(file).await? {
+        let mut request = recording.request();
+
+        let (rejection, response) = match seeder.seed(Guard::Accessible(&mut request)).await {
+            Guard::Accessible(_) | Guard::Inaccessible { respondent: Respondent::Ignore, .. } => { (None, None) }
+            Guard::Inaccessible { respondent, rejection, .. } => {
+                match respondent {
+                    Respondent::Respond(response) => { (Some(rejection), Some(*response)) }
+                    _ => { (Some(rejection), None) }
+                }
+            }
+        };
+
+        replayed.push(Replayed {
+            recording,
+            request,
+            rejection,
+            response,
+        });
+    }
+
+    Ok(replayed)
+}
+
+/// What a `MockSeeder` does with a request.
+#[derive(Debug, Clone, PartialEq)]
+enum Scripted {
+    Accept,
+    Reject(Rejection),
+    Respond(Rejection),
+    Ignore(Rejection),
+}
+
+/// A request seen by a `MockSeeder`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeenRequest {
+    /// The request's method.
+    pub method: Method,
+
+    /// The request's URI.
+    pub uri: Uri,
+
+    /// Whether the request reached the seeder accessible, rather than already rejected.
+    pub accessible: bool,
+}
+
+/// A scriptable `Seeder`, for testing seeders and routers which wrap or follow other seeders.
+///
+/// A `MockSeeder` handles requests according to its script, one step per request it's handed,
+/// and accepts every request once the script runs out. Requests which arrive already rejected
+/// are passed along untouched, without using up a step. Every request it's handed is recorded,
+/// for asserting on afterwards:
+///
+/// ```
+/// use grazie::core::seeder::{BoxBody, Guard, Seeder};
+/// use grazie::http::{HttpRequest, StatusCode};
+/// use grazie::test::MockSeeder;
+///
+/// # async fn example() {
+/// let mock = MockSeeder::new().accept().reject(StatusCode::UNAUTHORIZED, "unauthorized");
+///
+/// for _ in 0..2 {
+///     let mut request = HttpRequest::builder().uri("/").body(BoxBody::empty()).unwrap();
+///     mock.seed(Guard::Accessible(&mut request)).await;
+/// }
+///
+/// mock.assert_called(2);
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct MockSeeder {
+    script: Mutex<VecDeque<Scripted>>,
+    seen: Mutex<Vec<SeenRequest>>,
+}
+
+impl MockSeeder {
+    /// Constructs a new `MockSeeder` with an empty script, which accepts every request.
+    pub fn new() -> MockSeeder {
+        MockSeeder::default()
+    }
+
+    /// Accepts the next request.
+    pub fn accept(self) -> MockSeeder {
+        self.step(Scripted::Accept)
+    }
+
+    /// Rejects the next request, without a response.
+    pub fn reject(self, status: StatusCode, code: &str) -> MockSeeder {
+        self.step(Scripted::Reject(Rejection::new(status, code, code)))
+    }
+
+    /// Rejects the next request with an empty response of the rejection's status.
+    pub fn respond(self, status: StatusCode, code: &str) -> MockSeeder {
+        self.step(Scripted::Respond(Rejection::new(status, code, code)))
+    }
+
+    /// Rejects the next request, asking for the rejection to be ignored.
+    pub fn ignore(self, status: StatusCode, code: &str) -> MockSeeder {
+        self.step(Scripted::Ignore(Rejection::new(status, code, code)))
+    }
+
+    fn step(self, step: Scripted) -> MockSeeder {
+        lock(&self.script).push_back(step);
+        self
+    }
+
+    /// Gets every request the seeder has been handed, in order.
+    pub fn seen(&self) -> Vec<SeenRequest> {
+        lock(&self.seen).clone()
+    }
+
+    /// Gets how many requests the seeder has been handed.
+    pub fn calls(&self) -> usize {
+        lock(&self.seen).len()
+    }
+
+    /// Panics unless the seeder has been handed exactly `calls` requests.
+    #[track_caller]
+    pub fn assert_called(&self, calls: usize) {
+        let seen = self.calls();
+        assert_eq!(seen, calls, "expected the mock seeder to be called {calls} time(s), but it was called {seen} time(s)");
+    }
+
+    /// Panics unless every step of the script has been used.
+    #[track_caller]
+    pub fn assert_finished(&self) {
+        let left = lock(&self.script).len();
+        assert_eq!(left, 0, "the mock seeder has {left} scripted step(s) left");
+    }
+}
+
+impl Seeder for MockSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            Guard::Inaccessible { request, respondent, rejection } => {
+                lock(&self.seen).push(SeenRequest {
+                    method: request.method().clone(),
+                    uri: request.uri().clone(),
+                    accessible: false,
+                });
+
+                return Guard::Inaccessible { request, respondent, rejection };
+            }
+        };
+
+        lock(&self.seen).push(SeenRequest {
+            method: request.method().clone(),
+            uri: request.uri().clone(),
+            accessible: true,
+        });
+
+        let step = lock(&self.script).pop_front().unwrap_or(Scripted::Accept);
+        match step {
+            Scripted::Accept => { Guard::Accessible(request) }
+            Scripted::Reject(rejection) => {
+                Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Other(Box::new(())),
+                    rejection,
+                }
+            }
+            Scripted::Respond(rejection) => {
+                let response = HttpResponse::builder().status(rejection.status).body(BoxBody::empty()).unwrap();
+
+                Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Respond(Box::new(response)),
+                    rejection,
+                }
+            }
+            Scripted::Ignore(rejection) => {
+                Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Ignore,
+                    rejection,
+                }
+            }
+        }
+    }
+}
+
+/// An `Unpacker` which hands out canned requests, in order, regardless of the bytes it's given,
+/// for testing code which drives an `Unpacker` without a socket.
+#[derive(Default)]
+pub struct MockUnpacker {
+    requests: Mutex<VecDeque<HttpRequest<BoxBody>>>,
+    received: Mutex<Vec<Vec<u8>>>,
+}
+
+impl MockUnpacker {
+    /// Constructs a new `MockUnpacker`, without any canned requests.
+    pub fn new() -> MockUnpacker {
+        MockUnpacker::default()
+    }
+
+    /// Queues a request to be handed out.
+    pub fn request(self, request: HttpRequest<BoxBody>) -> MockUnpacker {
+        lock(&self.requests).push_back(request);
+        self
+    }
+
+    /// Gets the bytes of every stream the unpacker has been asked to unpack, in order.
+    pub fn received(&self) -> Vec<Vec<u8>> {
+        lock(&self.received).clone()
+    }
+
+    /// Gets how many canned requests are yet to be handed out.
+    pub fn remaining(&self) -> usize {
+        lock(&self.requests).len()
+    }
+}
+
+impl Unpacker for MockUnpacker {
+    /// Hands out the next canned request.
+    ///
+    /// # Panics
+    ///
+    /// Panics if every canned request has already been handed out.
+    async fn unpack(&self, stream: &mut [u8]) -> HttpRequest<BoxBody> {
+        lock(&self.received).push(stream.to_vec());
+
+        lock(&self.requests).pop_front().expect("the mock unpacker has no canned requests left")
+    }
+}
+
+/// The environment variable which, when set to `1`, makes `Snapshots` overwrite stored snapshots
+/// rather than fail on a mismatch.
+pub const UPDATE_SNAPSHOTS: &str = "GRAZIE_UPDATE_SNAPSHOTS";
+
+/// Golden-file snapshots of responses, for catching unintended changes to an API's responses.
+///
+/// A response is serialized as its status line, its headers (sorted by name) and its body, with
+/// the values of volatile headers such as `Date` replaced by `[redacted]`. JSON bodies are
+/// pretty-printed when the `serde_json` feature is enabled, so that changes diff line by line.
+///
+/// The first time a snapshot is checked, it's written to `<directory>/<name>.snap` and the check
+/// passes. Afterwards, a response which doesn't match its snapshot fails the check, and is written
+/// to `<name>.snap.new` alongside it for review. Running with `GRAZIE_UPDATE_SNAPSHOTS=1`
+/// overwrites snapshots with the new responses instead:
+///
+/// ```
+/// use grazie::core::seeder::BoxBody;
+/// use grazie::http::HttpResponse;
+/// use grazie::test::Snapshots;
+///
+/// # fn example() {
+/// let snapshots = Snapshots::new("tests/snapshots").redact("x-correlation-id");
+/// let response = HttpResponse::new(BoxBody::new(b"hello".to_vec().into_boxed_slice()));
+///
+/// snapshots.assert("hello", &response);
+/// # }
+/// ```
+pub struct Snapshots {
+    directory: PathBuf,
+    redacted: Vec<HeaderName>,
+}
+
+impl Snapshots {
+    /// Constructs new `Snapshots`, stored in the provided directory. The `Date`, `X-Request-Id`,
+    /// `Traceparent` and `X-Grazie-Trace` headers are redacted.
+    pub fn new(directory: impl Into<PathBuf>) -> Snapshots {
+        Snapshots {
+            directory: directory.into(),
+            redacted: ["date", "x-request-id", "traceparent", "x-grazie-trace"]
+                .into_iter()
+                .map(HeaderName::from_static)
+                .collect(),
+        }
+    }
+
+    /// Redacts another header's value.
+    pub fn redact(mut self, header: &str) -> Snapshots {
+        self.redacted.extend(HeaderName::try_from(header).ok());
+        self
+    }
+
+    /// Serializes a response as it would be stored in a snapshot.
+    pub fn serialize(&self, response: &HttpResponse<BoxBody>) -> String {
+        let mut serialized = format!("{:?} {}\n", response.version(), response.status());
+
+        let mut headers: Vec<(&str, String)> = response
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                let value = match self.redacted.contains(name) {
+                    true => { "[redacted]".to_owned() }
+                    false => { String::from_utf8_lossy(value.as_bytes()).into_owned() }
+                };
+
+                (name.as_str(), value)
+            })
+            .collect();
+        headers.sort();
+
+        for (name, value) in headers {
+            serialized.push_str(&format!("{name}: {value}\n"));
+        }
+
+        serialized.push('\n');
+        serialized.push_str(&body_text(response.body().raw_bytes()));
+        serialized
+    }
+
+    /// Checks a response against its snapshot, returning a description of the mismatch if it
+    /// doesn't match.
+    pub fn check(&self, name: &str, response: &HttpResponse<BoxBody>) -> io::Result<Result<(), String>> {
+        let actual = self.serialize(response);
+        let path = self.directory.join(format!("{name}.snap"));
+        let pending = self.directory.join(format!("{name}.snap.new"));
+
+        let update = std::env::var(UPDATE_SNAPSHOTS).is_ok_and(|update| update == "1");
+        let expected = match std::fs::read_to_string(&path) {
+            Ok(expected) => { Some(expected) }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => { None }
+            Err(error) => { return Err(error); }
+        };
+
+        if expected.as_deref() == Some(actual.as_str()) {
+            remove_if_exists(&pending)?;
+            return Ok(Ok(()));
+        }
+
+        if expected.is_none() || update {
+            std::fs::create_dir_all(&self.directory)?;
+            std::fs::write(&path, &actual)?;
+            remove_if_exists(&pending)?;
+            return Ok(Ok(()));
+        }
+
+        std::fs::write(&pending, &actual)?;
+        Ok(Err(mismatch(&path, expected.as_deref().unwrap_or_default(), &actual)))
+    }
+
+    /// Panics unless a response matches its snapshot.
+    #[track_caller]
+    pub fn assert(&self, name: &str, response: &HttpResponse<BoxBody>) {
+        match self.check(name, response) {
+            Ok(Ok(())) => {}
+            Ok(Err(mismatch)) => { panic!("{mismatch}"); }
+            Err(error) => { panic!("failed to check snapshot `{name}`: {error}"); }
+        }
+    }
+}
+
+/// Formats a body for a snapshot: as text if it's UTF-8 (pretty-printed, if it's JSON), and as
+/// its length and hash otherwise.
+fn body_text(body: &[u8]) -> String {
+    #[cfg(feature = "serde_json")]
+    if let Ok(json) = serde_json::from_slice::<serde_json::Value>(body) {
+        if json.is_object() || json.is_array() {
+            return serde_json::to_string_pretty(&json).unwrap_or_default();
+        }
+    }
+
+    match std::str::from_utf8(body) {
+        Ok(text) => { text.to_owned() }
+        Err(_) => { format!("[{} bytes, fnv1a {:016x}]", body.len(), crate::util::fnv1a(body)) }
+    }
+}
+
+/// Describes how a response differs from its snapshot, line by line.
+fn mismatch(path: &Path, expected: &str, actual: &str) -> String {
+    let mut description = format!("response doesn't match snapshot {}:\n", path.display());
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+
+    for line in 0..expected.len().max(actual.len()) {
+        match (expected.get(line), actual.get(line)) {
+            (Some(expected), Some(actual)) if expected == actual => {}
+            (expected, actual) => {
+                if let Some(expected) = expected {
+                    description.push_str(&format!("{:>4} - {expected}\n", line + 1));
+                }
+                if let Some(actual) = actual {
+                    description.push_str(&format!("{:>4} + {actual}\n", line + 1));
+                }
+            }
+        }
+    }
+
+    description.push_str(&format!("run with {UPDATE_SNAPSHOTS}=1 to accept the new response"));
+    description
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match std::fs::remove_file(path) {
+        Ok(()) => { Ok(()) }
+        Err(error) if error.kind() == io::ErrorKind::NotFound => { Ok(()) }
+        Err(error) => { Err(error) }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
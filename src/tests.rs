@@ -1 +1,58 @@
-mod server;
\ No newline at end of file
+mod admin;
+#[cfg(feature = "serde_json")]
+mod audit;
+mod auth;
+mod cache;
+#[cfg(feature = "config")]
+mod config;
+#[cfg(feature = "csv")]
+mod csv;
+mod db;
+#[cfg(feature = "dev")]
+mod dev;
+#[cfg(feature = "digest")]
+mod digest;
+mod dns;
+mod embedded;
+#[cfg(feature = "compression")]
+mod encoding;
+mod events;
+mod flags;
+mod grpc_web;
+mod hub;
+mod jobs;
+#[cfg(feature = "jsonapi")]
+mod jsonapi;
+#[cfg(feature = "serde_json")]
+mod jsonrpc;
+mod links;
+mod longpoll;
+mod mail;
+#[cfg(feature = "serde_json")]
+mod ndjson;
+mod outbox;
+mod pool;
+mod query;
+mod recorder;
+#[cfg(feature = "redis")]
+mod redis;
+mod retry;
+mod seeder;
+mod seeders;
+mod server;
+mod sniff;
+#[cfg(feature = "serde_xml")]
+mod soap;
+#[cfg(feature = "sqlite")]
+mod sql;
+mod task;
+mod test;
+mod tunnel;
+mod tus;
+#[cfg(all(unix, feature = "upgrade"))]
+mod upgrade;
+mod upstream;
+mod validate;
+#[cfg(feature = "webhooks")]
+mod webhooks;
+mod wellknown;
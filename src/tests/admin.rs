@@ -0,0 +1,111 @@
+use crate::admin::{AdminServer, MAX_FAILED_AUTH, MAX_LINE};
+use crate::seeders::MaintenanceSeeder;
+use crate::upstream::CanaryHandle;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+#[tokio::test]
+async fn runs_authenticated_commands() {
+    let seeder = MaintenanceSeeder::new();
+    let canary = CanaryHandle::default();
+    let admin = AdminServer::bind("127.0.0.1:0", "s3cr3t")
+        .await
+        .unwrap()
+        .auth_delay(Duration::from_millis(10))
+        .maintenance(seeder.handle())
+        .canary(canary.clone());
+    let addr = admin.local_addr().unwrap();
+    tokio::spawn(admin.run());
+
+    let mut stream = BufReader::new(TcpStream::connect(addr).await.unwrap());
+    let mut line = String::new();
+
+    for (command, expected) in [
+        ("wrong maintenance on\n", "err unauthorized\n"),
+        ("s3cr3t maintenance on\n", "ok maintenance enabled\n"),
+        ("s3cr3t canary 5%\n", "ok canary at 5%\n"),
+        ("s3cr3t canary 150\n", "err usage: canary <percent>|status\n"),
+        ("s3cr3t frobnicate\n", "err unknown command `frobnicate`\n"),
+    ] {
+        stream.get_mut().write_all(command.as_bytes()).await.unwrap();
+        line.clear();
+        stream.read_line(&mut line).await.unwrap();
+        assert_eq!(line, expected);
+    }
+
+    assert!(seeder.handle().enabled());
+    assert_eq!(canary.percent(), 5);
+}
+
+#[tokio::test]
+async fn refuses_empty_tokens() {
+    for token in ["", "two words"] {
+        let error = AdminServer::bind("127.0.0.1:0", token).await.err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
+
+#[tokio::test]
+async fn closes_connections_sending_long_lines() {
+    let admin = AdminServer::bind("127.0.0.1:0", "s3cr3t").await.unwrap();
+    let addr = admin.local_addr().unwrap();
+    tokio::spawn(admin.run());
+
+    let mut stream = BufReader::new(TcpStream::connect(addr).await.unwrap());
+    // One byte over the limit, so that the whole line is read before the connection is closed.
+    let long = format!("s3cr3t {}\n", "a".repeat(MAX_LINE + 1 - "s3cr3t ".len()));
+    stream.get_mut().write_all(long.as_bytes()).await.unwrap();
+
+    let mut line = String::new();
+    stream.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "err line too long\n");
+
+    line.clear();
+    assert_eq!(stream.read_line(&mut line).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn throttles_wrong_tokens() {
+    let delay = Duration::from_millis(50);
+    let admin = AdminServer::bind("127.0.0.1:0", "s3cr3t").await.unwrap().auth_delay(delay);
+    let addr = admin.local_addr().unwrap();
+    tokio::spawn(admin.run());
+
+    let mut stream = BufReader::new(TcpStream::connect(addr).await.unwrap());
+    let mut line = String::new();
+    let started = Instant::now();
+
+    for attempt in 1..=MAX_FAILED_AUTH {
+        stream.get_mut().write_all(b"guess maintenance on\n").await.unwrap();
+        line.clear();
+        stream.read_line(&mut line).await.unwrap();
+
+        match attempt < MAX_FAILED_AUTH {
+            true => { assert_eq!(line, "err unauthorized\n"); }
+            false => { assert_eq!(line, "err too many failed attempts\n"); }
+        }
+    }
+
+    assert!(started.elapsed() >= delay * MAX_FAILED_AUTH);
+
+    line.clear();
+    assert_eq!(stream.read_line(&mut line).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn keeps_shutdowns_until_awaited() {
+    let admin = AdminServer::bind("127.0.0.1:0", "s3cr3t").await.unwrap();
+    let addr = admin.local_addr().unwrap();
+    let shutdown = admin.shutdown_signal();
+    tokio::spawn(admin.run());
+
+    let mut stream = BufReader::new(TcpStream::connect(addr).await.unwrap());
+    stream.get_mut().write_all(b"s3cr3t shutdown\n").await.unwrap();
+
+    let mut line = String::new();
+    stream.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "ok shutting down\n");
+
+    tokio::time::timeout(Duration::from_secs(5), shutdown.notified()).await.unwrap();
+}
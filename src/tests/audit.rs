@@ -0,0 +1,52 @@
+use crate::audit::{AuditLog, AuditSeeder};
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use std::time::Duration;
+
+fn request(path: &str) -> HttpRequest<BoxBody> {
+    HttpRequest::builder().method("DELETE").uri(path).header("x-user", "ada").body(BoxBody::empty()).unwrap()
+}
+
+fn response(status: StatusCode) -> HttpResponse<BoxBody> {
+    HttpResponse::builder().status(status).body(BoxBody::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn writes_audited_routes_and_rotates() {
+    let dir = std::env::temp_dir().join(format!("grazie-audit-{}", std::process::id()));
+    tokio::fs::create_dir_all(&dir).await.unwrap();
+    let path = dir.join("audit.jsonl");
+
+    let log = AuditLog::builder(&path).max_size(200).keep(1).open().await.unwrap();
+    let seeder = AuditSeeder::new(log)
+        .audit(true)
+        .route("/healthz", false)
+        .actor(|request| request.headers().get("x-user").and_then(|user| user.to_str().ok()).map(str::to_owned));
+
+    for (path, status) in [("/users/1", StatusCode::NO_CONTENT), ("/healthz", StatusCode::OK), ("/admin", StatusCode::FORBIDDEN)] {
+        let mut request = request(path);
+        seeder.seed(Guard::Accessible(&mut request)).await;
+        seeder.finish(&request, &response(status));
+    }
+
+    let rotated = dir.join("audit.jsonl.1");
+    for _ in 0..200 {
+        if tokio::fs::read_to_string(&path).await.is_ok_and(|current| !current.is_empty()) && rotated.exists() {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let first: serde_json::Value = serde_json::from_str(tokio::fs::read_to_string(&rotated).await.unwrap().trim()).unwrap();
+    assert_eq!(first["actor"], "ada");
+    assert_eq!(first["method"], "DELETE");
+    assert_eq!(first["route"], "/users/1");
+    assert_eq!(first["outcome"], "success");
+
+    let current = tokio::fs::read_to_string(&path).await.unwrap();
+    assert_eq!(current.lines().count(), 1);
+    assert!(current.contains(r#""route":"/admin","status":403,"outcome":"denied""#));
+
+    tokio::fs::remove_dir_all(&dir).await.unwrap();
+}
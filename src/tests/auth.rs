@@ -0,0 +1,5 @@
+#[cfg(feature = "oauth")]
+mod oauth;
+#[cfg(any(feature = "argon2", feature = "bcrypt"))]
+mod password;
+mod session;
@@ -0,0 +1,225 @@
+use crate::auth::oauth::{Identity, OAuthProvider, OAuthSeeder, Pkce, TokenExchange};
+use crate::auth::oauth::{TokenRequest, TokenResponse};
+use crate::auth::session::is_cookie_path;
+use crate::core::seeder::{BoxBody, Guard, Respondent, Seeder};
+use crate::http::header::{LOCATION, SET_COOKIE};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::util::{base64url_encode, query_pairs};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// The discovery document of a provider at `id.example.com`.
+const DISCOVERY: &str = concat!(
+    r#"{"issuer":"https://id.example.com","#,
+    r#""authorization_endpoint":"https://id.example.com/authorize","#,
+    r#""token_endpoint":"https://id.example.com/token"}"#,
+);
+
+/// Issues an ID token for whichever nonce the test is expecting.
+#[derive(Default)]
+struct MockExchange {
+    nonce: Mutex<String>,
+    requests: Mutex<Vec<TokenRequest>>,
+}
+
+impl TokenExchange for &'static MockExchange {
+    async fn exchange(&self, request: TokenRequest) -> Result<TokenResponse, String> {
+        self.requests.lock().unwrap().push(request);
+
+        let nonce = self.nonce.lock().unwrap().clone();
+        let claims = format!(r#"{{"sub":"user-1","email":"user@example.com","nonce":"{nonce}"}}"#);
+
+        Ok(TokenResponse {
+            access_token: "access".to_owned(),
+            token_type: "Bearer".to_owned(),
+            expires_in: Some(3600),
+            refresh_token: None,
+            id_token: Some(format!("e30.{}.signature", base64url_encode(claims.as_bytes()))),
+            scope: None,
+        })
+    }
+}
+
+fn request(uri: &str, cookies: &[String]) -> HttpRequest<BoxBody> {
+    HttpRequest::builder()
+        .uri(uri)
+        .header("cookie", cookies.join("; "))
+        .body(BoxBody::empty())
+        .unwrap()
+}
+
+async fn respond<X: TokenExchange>(
+    seeder: &OAuthSeeder<X>,
+    mut request: HttpRequest<BoxBody>,
+) -> HttpResponse<BoxBody> {
+    match seeder.seed(Guard::Accessible(&mut request)).await {
+        Guard::Inaccessible { respondent: Respondent::Respond(response), .. } => { *response }
+        _ => panic!("request wasn't answered"),
+    }
+}
+
+fn cookie_pair(response: &HttpResponse<BoxBody>) -> String {
+    let set_cookie = response.headers().get(SET_COOKIE).unwrap().to_str().unwrap();
+    set_cookie.split(';').next().unwrap().to_owned()
+}
+
+#[test]
+fn computes_pkce_challenges() {
+    // From RFC 7636, appendix B.
+    let pkce = Pkce::from_verifier("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk");
+
+    assert_eq!(pkce.challenge, "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM");
+}
+
+#[tokio::test]
+async fn signs_users_in_with_the_code_flow() {
+    let exchange: &'static MockExchange = Box::leak(Box::default());
+    let provider = OAuthProvider::from_discovery(
+        DISCOVERY,
+        "client",
+        "https://app.example.com/oauth/callback",
+    ).unwrap();
+    let seeder = OAuthSeeder::new(provider, exchange).require_login(true);
+
+    let redirected = respond(&seeder, request("/account?tab=profile", &[])).await;
+    let login = redirected.headers()[LOCATION].to_str().unwrap().to_owned();
+    assert_eq!(login, "/oauth/login?return_to=%2Faccount%3Ftab%3Dprofile");
+
+    let authorize = respond(&seeder, request(&login, &[])).await;
+    let state_cookie = cookie_pair(&authorize);
+    let location = authorize.headers()[LOCATION].to_str().unwrap();
+    let query = location.split_once('?').unwrap().1;
+    let params: HashMap<String, String> = query_pairs(query).collect();
+    let prefix = "https://id.example.com/authorize?response_type=code&client_id=client";
+    assert!(location.starts_with(prefix));
+    assert_eq!(params["code_challenge_method"], "S256");
+    *exchange.nonce.lock().unwrap() = params["nonce"].clone();
+
+    let callback = format!("/oauth/callback?code=abc&state={}", params["state"]);
+
+    let forged = respond(&seeder, request(&callback, &[])).await;
+    assert_eq!(forged.status(), StatusCode::BAD_REQUEST);
+
+    let authorize = respond(&seeder, request(&login, &[])).await;
+    let state_cookie_2 = cookie_pair(&authorize);
+    assert_ne!(state_cookie, state_cookie_2);
+
+    // The forged callback didn't cancel the first login, which still completes.
+    let signed_in = respond(&seeder, request(&callback, &[state_cookie])).await;
+    assert_eq!(signed_in.status(), StatusCode::FOUND);
+    assert_eq!(signed_in.headers()[LOCATION], "/account?tab=profile");
+    assert!(exchange.requests.lock().unwrap()[0].body.contains("&code_verifier="));
+
+    let mut account = request("/account", &[cookie_pair(&signed_in)]);
+    assert!(seeder.seed(Guard::Accessible(&mut account)).await.accessible());
+
+    let identity = account.extensions().get::<Identity>().unwrap();
+    assert_eq!(identity.subject.as_deref(), Some("user-1"));
+    assert_eq!(identity.claim("email").unwrap(), "user@example.com");
+}
+
+#[tokio::test]
+async fn only_returns_to_local_paths() {
+    let exchange: &'static MockExchange = Box::leak(Box::default());
+    let provider = OAuthProvider::from_discovery(
+        DISCOVERY,
+        "client",
+        "https://app.example.com/oauth/callback",
+    ).unwrap();
+    let seeder = OAuthSeeder::new(provider, exchange).landing("/home");
+
+    for return_to in [
+        "/%0Aevil",
+        "/%5Cevil.com",
+        "//evil.com",
+        "https://evil.com",
+        "/ok%09tab",
+        "/orders?page=2",
+    ] {
+        let login = format!("/oauth/login?return_to={return_to}");
+        let authorize = respond(&seeder, request(&login, &[])).await;
+        let location = authorize.headers()[LOCATION].to_str().unwrap();
+        let query = location.split_once('?').unwrap().1;
+        let params: HashMap<String, String> = query_pairs(query).collect();
+        *exchange.nonce.lock().unwrap() = params["nonce"].clone();
+
+        let callback = format!("/oauth/callback?code=abc&state={}", params["state"]);
+        let signed_in = respond(&seeder, request(&callback, &[cookie_pair(&authorize)])).await;
+
+        let expected = match return_to {
+            "/orders?page=2" => { "/orders?page=2" }
+            _ => { "/home" }
+        };
+        assert_eq!(signed_in.headers()[LOCATION], expected, "return_to={return_to}");
+    }
+}
+
+#[tokio::test]
+async fn limits_pending_logins() {
+    let exchange: &'static MockExchange = Box::leak(Box::default());
+    let provider = OAuthProvider::new(
+        "client",
+        "https://id.example.com/authorize",
+        "https://id.example.com/token",
+        "https://app.example.com/oauth/callback",
+    );
+    let seeder = OAuthSeeder::new(provider, exchange).max_pending(2);
+
+    for _ in 0..2 {
+        let authorize = respond(&seeder, request("/oauth/login", &[])).await;
+        assert_eq!(authorize.status(), StatusCode::FOUND);
+    }
+    let refused = respond(&seeder, request("/oauth/login", &[])).await;
+    assert_eq!(refused.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn rotates_sessions_on_sign_in() {
+    let exchange: &'static MockExchange = Box::leak(Box::default());
+    let provider = OAuthProvider::new(
+        "client",
+        "https://id.example.com/authorize",
+        "https://id.example.com/token",
+        "https://app.example.com/oauth/callback",
+    );
+    let seeder = OAuthSeeder::new(provider, exchange).require_login(true);
+
+    let sign_in = |session: Option<String>| async {
+        let authorize = respond(&seeder, request("/oauth/login", &[])).await;
+        let location = authorize.headers()[LOCATION].to_str().unwrap();
+        let params: HashMap<String, String> = query_pairs(location.split_once('?').unwrap().1)
+            .collect();
+        *exchange.nonce.lock().unwrap() = params["nonce"].clone();
+
+        let callback = format!("/oauth/callback?code=abc&state={}", params["state"]);
+        let cookies: Vec<String> =
+            [Some(cookie_pair(&authorize)), session].into_iter().flatten().collect();
+        cookie_pair(&respond(&seeder, request(&callback, &cookies)).await)
+    };
+
+    // A session fixed before the login, here one the attacker signed in with themselves.
+    let fixed = sign_in(None).await;
+    let session = sign_in(Some(fixed.clone())).await;
+    assert_ne!(session, fixed);
+
+    let mut current = request("/account", &[session]);
+    assert!(seeder.seed(Guard::Accessible(&mut current)).await.accessible());
+    let mut stale = request("/account", &[fixed]);
+    assert!(!seeder.seed(Guard::Accessible(&mut stale)).await.accessible());
+}
+
+#[test]
+fn checks_cookie_paths() {
+    assert!(is_cookie_path("/oauth/callback"));
+    for path in ["", "oauth", "/a;b", "/a b", "/a\n"] {
+        assert!(!is_cookie_path(path), "{path:?}");
+    }
+}
+
+#[test]
+#[should_panic(expected = "must be a valid cookie path")]
+fn refuses_invalid_callback_paths() {
+    let provider = OAuthProvider::new("client", "https://id", "https://token", "https://app/cb");
+    let exchange: &'static MockExchange = Box::leak(Box::default());
+    let _ = OAuthSeeder::new(provider, exchange).callback_path("/callback; Domain=evil.com");
+}
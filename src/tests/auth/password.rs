@@ -0,0 +1,105 @@
+use crate::auth::login::{Credentials, LoginHandler};
+use crate::auth::password;
+use crate::auth::session::{MemorySessionStore, SessionStore};
+use crate::core::seeder::BoxBody;
+use crate::http::header::{LOCATION, SET_COOKIE};
+use crate::http::{HttpRequest, StatusCode};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn verifies_hashes() {
+    let hash = password::hash("hunter2").unwrap();
+
+    assert!(password::verify("hunter2", &hash));
+    assert!(!password::verify("hunter3", &hash));
+    assert!(!password::verify("hunter2", "not a hash"));
+    assert!(!password::needs_rehash(&hash));
+}
+
+#[cfg(feature = "bcrypt")]
+#[test]
+fn verifies_bcrypt_hashes() {
+    let hash = bcrypt::hash("hunter2", 4).unwrap();
+
+    assert!(password::verify("hunter2", &hash));
+    assert!(!password::verify("hunter3", &hash));
+    assert_eq!(password::needs_rehash(&hash), cfg!(feature = "argon2"));
+}
+
+#[tokio::test]
+async fn signs_users_in_from_forms() {
+    let hash = password::hash("hunter2").unwrap();
+    let sessions = Arc::new(MemorySessionStore::new(Duration::from_secs(60)));
+    let handler = LoginHandler::new(sessions.clone(), |username: String| {
+        let hash = hash.clone();
+
+        async move {
+            (username == "alice").then_some(Credentials { hash, user: 7 })
+        }
+    }).landing("/home");
+
+    let form = |body: &str| {
+        HttpRequest::builder()
+            .method("POST")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(BoxBody::new(body.as_bytes().into()))
+            .unwrap()
+    };
+
+    let response = handler.handle(&form("username=alice&password=hunter2")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert_eq!(response.headers()[LOCATION], "/home");
+
+    let session = response.headers()[SET_COOKIE].to_str().unwrap().split(';').next().unwrap();
+    let signed_in = HttpRequest::builder()
+        .header("cookie", session.to_owned())
+        .body(BoxBody::empty())
+        .unwrap();
+    assert_eq!(sessions.get(&signed_in).await, Ok(Some(7)));
+
+    for body in ["username=alice&password=hunter3", "username=bob&password=hunter2"] {
+        let rejection = handler.handle(&form(body)).await.err().unwrap();
+        assert_eq!(rejection.code, "invalid_credentials");
+    }
+
+    let rejection = handler.handle(&form("username=alice")).await.err().unwrap();
+    assert_eq!(rejection.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn rotates_sessions_on_sign_in() {
+    let hash = password::hash("hunter2").unwrap();
+    let sessions = Arc::new(MemorySessionStore::new(Duration::from_secs(60)));
+    let handler = LoginHandler::new(sessions.clone(), |_| {
+        let hash = hash.clone();
+
+        async move { Some(Credentials { hash, user: 7 }) }
+    });
+
+    // A session planted in the victim's browser before they sign in.
+    let planted = sessions.create(0).await.unwrap();
+    let planted = planted.to_str().unwrap().split(';').next().unwrap().to_owned();
+    let login = HttpRequest::builder()
+        .method("POST")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .header("cookie", planted.as_str())
+        .body(BoxBody::new(b"username=alice&password=hunter2".as_slice().into()))
+        .unwrap();
+
+    let response = handler.handle(&login).await.unwrap();
+    let session = response.headers()[SET_COOKIE].to_str().unwrap().split(';').next().unwrap();
+    assert_ne!(session, planted);
+    assert_eq!(sessions.len(), 1);
+
+    let fixed = HttpRequest::builder().header("cookie", planted).body(BoxBody::empty()).unwrap();
+    assert_eq!(sessions.get(&fixed).await, Ok(None));
+}
+
+#[test]
+#[should_panic(expected = "must be a valid header value")]
+fn refuses_invalid_landing_paths() {
+    let sessions = Arc::new(MemorySessionStore::<u32>::new(Duration::from_secs(60)));
+    let lookup = |_: String| async { None::<Credentials<u32>> };
+    let _ = LoginHandler::new(sessions, lookup).landing("/home\n");
+}
@@ -0,0 +1,43 @@
+use crate::auth::session::{cookie, is_cookie_name};
+use crate::auth::session::{MemorySessionStore, SessionStore};
+use crate::core::seeder::BoxBody;
+use crate::http::HttpRequest;
+use std::time::Duration;
+
+fn request(cookies: &str) -> HttpRequest<BoxBody> {
+    HttpRequest::builder().header("cookie", cookies).body(BoxBody::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn sessions_round_trip_through_cookies() {
+    let sessions = MemorySessionStore::new(Duration::from_secs(60)).cookie("sid");
+    let set_cookie = sessions.create(42).await.unwrap();
+
+    let set_cookie = set_cookie.to_str().unwrap();
+    assert!(set_cookie.contains("HttpOnly") && set_cookie.contains("Secure"));
+
+    let id = set_cookie.split(';').next().unwrap();
+    let signed_in = request(&format!("theme=dark; {id}"));
+    assert_eq!(cookie(&signed_in, "theme"), Some("dark"));
+    assert_eq!(sessions.get(&signed_in).await, Ok(Some(42)));
+    assert_eq!(sessions.get(&request("sid=forged")).await, Ok(None));
+
+    let cleared = sessions.destroy(&signed_in).await.unwrap();
+    assert!(cleared.to_str().unwrap().starts_with("sid=;"));
+    assert_eq!(sessions.get(&signed_in).await, Ok(None));
+    assert!(sessions.is_empty());
+}
+
+#[test]
+fn checks_cookie_names() {
+    assert!(is_cookie_name("grazie_session"));
+    for name in ["", "a b", "a;b", "a=b", "sess\u{e9}"] {
+        assert!(!is_cookie_name(name), "{name:?}");
+    }
+}
+
+#[test]
+#[should_panic(expected = "must have a valid name")]
+fn refuses_invalid_cookie_names() {
+    let _ = MemorySessionStore::<u32>::new(Duration::from_secs(60)).cookie("sid; Domain=evil.com");
+}
@@ -0,0 +1,27 @@
+use crate::cache::{CacheDefaults, CachePolicy};
+use std::time::Duration;
+
+#[test]
+fn formats_directives() {
+    assert_eq!(
+        CachePolicy::immutable_asset().to_string(),
+        "public, max-age=31536000, immutable"
+    );
+    assert_eq!(
+        CachePolicy::new().public().private().no_cache().s_maxage(Duration::from_secs(10)).to_string(),
+        "private, no-cache, s-maxage=10"
+    );
+    assert_eq!(CachePolicy::no_store().max_age(Duration::from_secs(5)).to_string(), "no-store");
+}
+
+#[test]
+fn picks_longest_prefix() {
+    let defaults = CacheDefaults::new()
+        .prefix("/assets", CachePolicy::immutable_asset())
+        .prefix("/assets/dev", CachePolicy::no_store())
+        .fallback(CachePolicy::new().no_cache());
+
+    assert_eq!(defaults.policy_for("/assets/dev/app.js"), Some(&CachePolicy::no_store()));
+    assert_eq!(defaults.policy_for("/assets/app.js"), Some(&CachePolicy::immutable_asset()));
+    assert_eq!(defaults.policy_for("/users"), Some(&CachePolicy::new().no_cache()));
+}
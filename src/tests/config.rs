@@ -0,0 +1,54 @@
+use crate::config::{Config, ConfigError};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Settings {
+    name: String,
+    debug: bool,
+    server: Server,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Server {
+    port: u16,
+    hosts: Vec<String>,
+}
+
+#[test]
+fn layers_files_environment_and_overrides() {
+    let path = std::env::temp_dir().join(format!("grazie-config-{}.toml", std::process::id()));
+    std::fs::write(&path, "name = \"app\"\ndebug = false\n[server]\nport = 80\nhosts = [\"a\", \"b\"]").unwrap();
+
+    let vars = [
+        ("APP_DEBUG".to_owned(), "true".to_owned()),
+        ("APP_SERVER__PORT".to_owned(), "8080".to_owned()),
+        ("OTHER_NAME".to_owned(), "ignored".to_owned()),
+    ];
+
+    let settings: Config<Settings> = Config::builder()
+        .file(&path).unwrap()
+        .file_optional(path.with_extension("local.toml")).unwrap()
+        .vars("APP", vars)
+        .args(["serve", "--set", "server.port=9090", "--set=name=cli"])
+        .build()
+        .unwrap();
+
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(*settings, Settings {
+        name: "cli".to_owned(),
+        debug: true,
+        server: Server {
+            port: 9090,
+            hosts: vec!["a".to_owned(), "b".to_owned()],
+        },
+    });
+}
+
+#[test]
+fn reports_type_mismatches() {
+    let result = Config::builder().json(r#"{"name":"app","debug":"sometimes"}"#).unwrap().build::<Settings>();
+
+    assert!(matches!(result, Err(ConfigError::Invalid(_))));
+    assert!(matches!(Config::builder().toml("name = "), Err(ConfigError::Parse(..))));
+}
@@ -0,0 +1,100 @@
+use crate::core::seeder::BoxBody;
+use crate::csv::Csv;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Status {
+    Open,
+    Closed,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Ticket {
+    id: u64,
+    title: String,
+    status: Status,
+    urgent: bool,
+}
+
+fn body(text: &str) -> BoxBody {
+    BoxBody::new(text.as_bytes().into())
+}
+
+#[test]
+fn quotes_fields_which_need_it() {
+    let tickets = [Ticket { id: 1, title: "Says \"hi\"\nthen leaves".into(), status: Status::Open, urgent: false }];
+    let encoded = Csv::new().encode(&tickets).unwrap();
+
+    assert_eq!(encoded.raw_bytes(), b"id,title,status,urgent\r\n1,\"Says \"\"hi\"\"\nthen leaves\",Open,false\r\n");
+    assert_eq!(Csv::new().decode::<Ticket>(&encoded).unwrap(), tickets);
+}
+
+#[test]
+fn supports_other_dialects() {
+    let csv = Csv::new().delimiter(b';').headers(false);
+
+    let encoded = csv.encode([(1, "a;b"), (2, "c,d")]).unwrap();
+    assert_eq!(encoded.raw_bytes(), b"1;\"a;b\"\r\n2;c,d\r\n");
+
+    let decoded: Vec<(u32, String)> = csv.decode(&encoded).unwrap();
+    assert_eq!(decoded, vec![(1, "a;b".into()), (2, "c,d".into())]);
+
+    let row: BTreeMap<&str, u32> = [("b", 2), ("a", 1)].into();
+    assert_eq!(Csv::new().encode([row]).unwrap().raw_bytes(), b"a,b\r\n1,2\r\n");
+}
+
+#[test]
+fn matches_columns_by_header() {
+    let decoded: Vec<Ticket> = Csv::new()
+        .decode(&body("urgent,status,id,title,notes\ntrue,Closed,7,Late,ignored\n\n"))
+        .unwrap();
+
+    assert_eq!(decoded, vec![Ticket { id: 7, title: "Late".into(), status: Status::Closed, urgent: true }]);
+}
+
+#[test]
+fn reports_bad_records() {
+    let error = Csv::new().decode::<Ticket>(&body("id,title,status,urgent\n1,A,Open,false\nx,B,Open,false\n")).unwrap_err();
+    assert_eq!(error.record, Some(3));
+
+    let error = Csv::new().decode::<Ticket>(&body("id,title\n1\n")).unwrap_err();
+    assert_eq!(error.record, Some(2));
+
+    assert!(Csv::new().decode::<Ticket>(&body("id\n\"1\n")).is_err());
+    assert!(Csv::new().encode([vec![vec![1]]]).is_err());
+}
+
+#[tokio::test]
+async fn writes_rows_incrementally() {
+    let mut writer = Csv::new().writer(Vec::new());
+    writer.write(&Ticket { id: 1, title: "A".into(), status: Status::Open, urgent: true }).await.unwrap();
+    writer.write(&Ticket { id: 2, title: "B".into(), status: Status::Closed, urgent: false }).await.unwrap();
+
+    assert_eq!(writer.written(), 2);
+    assert_eq!(writer.into_inner(), b"id,title,status,urgent\r\n1,A,Open,true\r\n2,B,Closed,false\r\n");
+
+    let mut body = BoxBody::empty();
+    body.close_csv([Ticket { id: 3, title: "C".into(), status: Status::Open, urgent: false }]).await.unwrap();
+    assert_eq!(body.open_csv::<Ticket>().await.unwrap()[0].id, 3);
+}
+
+#[test]
+fn round_trips_empty_single_field_records() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Note {
+        text: String,
+    }
+
+    let notes = [Note { text: "first".into() }, Note { text: String::new() }, Note { text: "last".into() }];
+    let encoded = Csv::new().encode(&notes).unwrap();
+
+    assert_eq!(encoded.raw_bytes(), b"text\r\nfirst\r\n\"\"\r\nlast\r\n");
+    assert_eq!(Csv::new().decode::<Note>(&encoded).unwrap(), notes);
+}
+
+#[test]
+#[should_panic(expected = "A CSV delimiter must be an ASCII byte")]
+fn refuses_non_ascii_delimiters() {
+    let _ = Csv::new().delimiter(0xA7);
+}
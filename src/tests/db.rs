@@ -0,0 +1,175 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::db::{Checkout, Database, Outcome, Pool, Transaction, Transactional, TxSeeder};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::HttpServer;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A pool whose database comes up after a number of pings.
+#[derive(Default)]
+struct Flaky {
+    failures: u32,
+    pings: AtomicU32,
+    closed: AtomicBool,
+}
+
+impl Pool for Flaky {
+    type Error = String;
+
+    async fn ping(&self) -> Result<(), String> {
+        match self.pings.fetch_add(1, Ordering::SeqCst) < self.failures {
+            true => { Err("connection refused".to_owned()) }
+            false => { Ok(()) }
+        }
+    }
+
+    async fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+    }
+}
+
+#[tokio::test]
+async fn verifies_at_startup_with_retries() {
+    let database = Database::new(Flaky { failures: 2, ..Flaky::default() }).startup_attempts(3).retry_delay(Duration::ZERO);
+    let server = database.register(HttpServer::new("127.0.0.1:0").await.unwrap());
+
+    server.startup().await.unwrap();
+    assert!(database.healthy());
+    assert_eq!(database.pool().pings.load(Ordering::SeqCst), 3);
+
+    let database = Database::new(Flaky { failures: 5, ..Flaky::default() }).startup_attempts(2).retry_delay(Duration::ZERO);
+    let server = database.register(HttpServer::new("127.0.0.1:0").await.unwrap());
+
+    let error = server.startup().await.unwrap_err();
+    assert_eq!((error.hook.as_str(), error.error.as_str()), ("database", "connection refused"));
+    assert!(!database.healthy());
+}
+
+#[tokio::test]
+async fn drains_requests_before_closing() {
+    let database = Database::new(Flaky::default()).drain_timeout(Duration::from_secs(5));
+
+    let mut request = HttpRequest::builder().uri("/").body(BoxBody::empty()).unwrap();
+    database.seed(Guard::Accessible(&mut request)).await;
+    assert!(request.extensions().get::<Arc<Checkout<Flaky>>>().is_some());
+    assert_eq!(database.checked_out(), 1);
+
+    let draining = tokio::spawn({
+        let database = database.clone();
+        async move { database.drain().await }
+    });
+
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(!database.pool().closed.load(Ordering::SeqCst));
+
+    drop(request);
+    assert!(draining.await.unwrap());
+    assert!(database.pool().closed.load(Ordering::SeqCst));
+
+    // Checkouts which outlive the timeout don't hold shutdown up forever.
+    let database = Database::new(Flaky::default()).drain_timeout(Duration::from_millis(10));
+    let _held = database.checkout();
+    assert!(!database.drain().await);
+    assert!(database.pool().closed.load(Ordering::SeqCst));
+}
+
+/// A pool whose transactions are logs of statements, recording how each one ended.
+#[derive(Default)]
+struct Ledger {
+    finished: Mutex<Vec<(Vec<String>, bool)>>,
+}
+
+impl Pool for Ledger {
+    type Error = String;
+
+    async fn ping(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    async fn close(&self) {}
+}
+
+impl Transactional for Ledger {
+    type Transaction = Vec<String>;
+
+    async fn begin(&self) -> Result<Vec<String>, String> {
+        Ok(Vec::new())
+    }
+
+    async fn commit(&self, transaction: Vec<String>) -> Result<(), String> {
+        self.finished.lock().unwrap().push((transaction, true));
+        Ok(())
+    }
+
+    async fn rollback(&self, transaction: Vec<String>) -> Result<(), String> {
+        self.finished.lock().unwrap().push((transaction, false));
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn commits_successes_and_rolls_back_failures() {
+    let seeder = TxSeeder::new(Database::new(Ledger::default()));
+    let response = |status| HttpResponse::builder().status(status).body(BoxBody::empty()).unwrap();
+
+    for (statement, status, outcome) in [
+        ("insert 1", StatusCode::CREATED, Outcome::Committed),
+        ("insert 2", StatusCode::UNPROCESSABLE_ENTITY, Outcome::RolledBack),
+    ] {
+        let mut request = HttpRequest::builder().method("POST").uri("/orders").body(BoxBody::empty()).unwrap();
+        seeder.seed(Guard::Accessible(&mut request)).await;
+
+        let transaction = request.extensions().get::<Transaction<Vec<String>>>().unwrap().clone();
+        transaction.lock().await.as_mut().unwrap().push(statement.to_owned());
+
+        assert_eq!(seeder.finish(&request, &response(status)).await.unwrap(), outcome);
+        assert!(transaction.lock().await.is_none());
+        assert_eq!(seeder.finish(&request, &response(status)).await.unwrap(), Outcome::None);
+    }
+
+    let mut read = HttpRequest::builder().uri("/orders").body(BoxBody::empty()).unwrap();
+    seeder.seed(Guard::Accessible(&mut read)).await;
+    assert!(read.extensions().get::<Transaction<Vec<String>>>().is_none());
+
+    let seeder = TxSeeder::new(Database::new(Ledger::default())).methods([crate::http::Method::GET]);
+    seeder.seed(Guard::Accessible(&mut read)).await;
+    assert!(read.extensions().get::<Transaction<Vec<String>>>().is_some());
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn runs_requests_in_sqlx_transactions() {
+    let pool = sqlx::sqlite::SqlitePoolOptions::new().max_connections(1);
+    let pool = pool.connect("sqlite::memory:").await.unwrap();
+    sqlx::query("CREATE TABLE orders (id INTEGER)").execute(&pool).await.unwrap();
+
+    let database = Database::new(pool.clone());
+    database.verify().await.unwrap();
+    assert!(database.healthy());
+
+    let seeder = TxSeeder::new(database.clone());
+    let response = |status| HttpResponse::builder().status(status).body(BoxBody::empty()).unwrap();
+
+    for (id, status) in [(1, StatusCode::CREATED), (2, StatusCode::UNPROCESSABLE_ENTITY)] {
+        let mut request = HttpRequest::builder().method("POST").uri("/orders").body(BoxBody::empty()).unwrap();
+        seeder.seed(Guard::Accessible(&mut request)).await;
+
+        let transaction = request.extensions().get::<Transaction<sqlx::Transaction<sqlx::Sqlite>>>();
+        let mut transaction = transaction.unwrap().lock().await;
+        sqlx::query("INSERT INTO orders (id) VALUES ($1)")
+            .bind(id)
+            .execute(&mut **transaction.as_mut().unwrap())
+            .await
+            .unwrap();
+        drop(transaction);
+
+        seeder.finish(&request, &response(status)).await.unwrap();
+    }
+
+    let ids: Vec<(i64,)> = sqlx::query_as("SELECT id FROM orders").fetch_all(&pool).await.unwrap();
+    assert_eq!(ids, [(1,)]);
+
+    assert!(database.drain().await);
+    assert!(pool.is_closed());
+}
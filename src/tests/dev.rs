@@ -0,0 +1,52 @@
+use crate::core::seeder::BoxBody;
+use crate::dev::LiveReload;
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use std::time::Duration;
+
+fn poll() -> HttpRequest<BoxBody> {
+    HttpRequest::builder().uri("/__grazie/livereload").body(BoxBody::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn notifies_clients_when_files_change() {
+    let dir = std::env::temp_dir().join(format!("grazie-dev-{}", std::process::id()));
+    tokio::fs::create_dir_all(dir.join("nested")).await.unwrap();
+
+    let reload = LiveReload::new(&dir)
+        .interval(Duration::from_millis(10))
+        .poll_timeout(Duration::from_millis(50));
+    let watcher = reload.watch();
+
+    let response = reload.respond(&poll()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let waiting = {
+        let reload = reload.clone();
+        tokio::spawn(async move { reload.poll_timeout(Duration::from_secs(5)).respond(&poll()).await })
+    };
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    tokio::fs::write(dir.join("nested/index.html"), "<p>changed</p>").await.unwrap();
+
+    let response = waiting.await.unwrap().unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    watcher.abort();
+    tokio::fs::remove_dir_all(&dir).await.unwrap();
+}
+
+#[test]
+fn injects_the_client_snippet() {
+    let reload = LiveReload::new(".").endpoint("/reload");
+    let request = poll();
+    let mut response = HttpResponse::builder()
+        .header("content-type", "text/html")
+        .body(BoxBody::new(b"<body><p>hi</p></body>".as_slice().into()))
+        .unwrap();
+
+    reload.html_transform().transform_response(&request, &mut response);
+
+    let body = String::from_utf8(response.body().raw_bytes().to_vec()).unwrap();
+    assert!(body.starts_with("<body><p>hi</p><script>"));
+    assert!(body.contains("fetch(\"/reload\""));
+    assert!(body.ends_with("</script></body>"));
+}
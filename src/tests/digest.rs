@@ -0,0 +1,33 @@
+use crate::core::seeder::BoxBody;
+use crate::digest::{add_digest_headers, content_address, CONTENT_DIGEST, DIGEST, REPR_DIGEST};
+use crate::http::{HttpResponse, StatusCode};
+
+fn response(status: StatusCode, body: &[u8]) -> HttpResponse<BoxBody> {
+    HttpResponse::builder().status(status).body(BoxBody::new(body.into())).unwrap()
+}
+
+#[test]
+fn digest_follows_body_rewrites() {
+    let mut body = BoxBody::new(b"hello".to_vec().into_boxed_slice());
+    let hello = content_address(&body);
+    assert_eq!(body.sha256(), body.sha256());
+
+    body.close(b"world".to_vec()).unwrap();
+    assert_ne!(content_address(&body), hello);
+    assert_eq!(content_address(&body), "486ea46224d1bb4fb680f34f7c9ad96a8f24ec88be73ea8e5a6c65260e9cb8a7");
+}
+
+#[test]
+fn adds_digest_headers() {
+    let mut full = response(StatusCode::OK, b"hello");
+    add_digest_headers(&mut full, true);
+    assert_eq!(full.headers()[REPR_DIGEST], "sha-256=:LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=:");
+    assert_eq!(full.headers()[CONTENT_DIGEST], full.headers()[REPR_DIGEST]);
+    assert_eq!(full.headers()[DIGEST], "sha-256=LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=");
+
+    let mut partial = response(StatusCode::PARTIAL_CONTENT, b"hel");
+    add_digest_headers(&mut partial, true);
+    assert!(partial.headers().contains_key(CONTENT_DIGEST));
+    assert!(!partial.headers().contains_key(REPR_DIGEST));
+    assert!(!partial.headers().contains_key(DIGEST));
+}
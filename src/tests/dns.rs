@@ -0,0 +1,48 @@
+use crate::dns::{connect, CachingResolver, Resolved, Resolver};
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+#[derive(Default)]
+struct CountingResolver(AtomicUsize);
+
+impl Resolver for CountingResolver {
+    async fn resolve(&self, _host: &str, port: u16) -> io::Result<Resolved> {
+        self.0.fetch_add(1, Ordering::Relaxed);
+
+        Ok(Resolved {
+            addrs: vec![SocketAddr::from(([127, 0, 0, 1], port))],
+            ttl: Some(Duration::from_secs(30)),
+        })
+    }
+}
+
+#[tokio::test]
+async fn caches_until_ttl() {
+    let resolver = CachingResolver::wrap(CountingResolver::default());
+
+    resolver.resolve("backend", 80).await.unwrap();
+    resolver.resolve("backend", 80).await.unwrap();
+    resolver.resolve("backend", 81).await.unwrap();
+    assert_eq!(resolver.inner().0.load(Ordering::Relaxed), 2);
+
+    let resolver = resolver.max_ttl(Duration::ZERO);
+    resolver.clear();
+    resolver.resolve("backend", 80).await.unwrap();
+    resolver.resolve("backend", 80).await.unwrap();
+    assert_eq!(resolver.inner().0.load(Ordering::Relaxed), 4);
+}
+
+#[tokio::test]
+async fn connects_past_unreachable_addresses() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let reachable = listener.local_addr().unwrap();
+
+    // Nothing listens on the closed port, so the first attempt is refused and the next begins.
+    let closed = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+
+    let stream = connect(&[closed, reachable], Duration::from_secs(5)).await.unwrap();
+    assert_eq!(stream.peer_addr().unwrap(), reachable);
+}
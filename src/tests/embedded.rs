@@ -0,0 +1,33 @@
+use crate::core::seeder::BoxBody;
+use crate::embedded::EmbeddedFiles;
+use crate::http::{HttpRequest, StatusCode};
+
+fn request(path: &str, etag: Option<&str>) -> HttpRequest<BoxBody> {
+    let mut builder = HttpRequest::builder().uri(path);
+    if let Some(etag) = etag {
+        builder = builder.header("if-none-match", etag);
+    }
+
+    builder.body(BoxBody::empty()).unwrap()
+}
+
+#[test]
+fn serves_embedded_files_with_etags() {
+    let files = EmbeddedFiles::new("/static")
+        .file("index.html", b"<h1>hi</h1>")
+        .file("app.js", b"console.log(1)")
+        .index("index.html");
+
+    let response = files.respond(&request("/static/app.js", None)).unwrap();
+    assert_eq!(response.headers()["content-type"], "text/javascript; charset=utf-8");
+    let etag = response.headers()["etag"].to_str().unwrap().to_owned();
+
+    let response = files.respond(&request("/static/app.js", Some(&etag))).unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    let response = files.respond(&request("/static/", None)).unwrap();
+    assert_eq!(response.body().raw_bytes(), b"<h1>hi</h1>");
+
+    assert!(files.respond(&request("/staticx/app.js", None)).is_none());
+    assert!(files.respond(&request("/static/missing.css", None)).is_none());
+}
@@ -0,0 +1,36 @@
+use crate::core::encoding::ContentEncoding;
+use crate::core::seeder::BoxBody;
+use crate::http::header::HeaderMap;
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use std::io::Write;
+
+fn gzip(data: &[u8]) -> Box<[u8]> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap().into_boxed_slice()
+}
+
+#[test]
+fn reads_encodings_from_headers() {
+    let mut headers = HeaderMap::new();
+    assert_eq!(ContentEncoding::from_headers(&headers), Some(ContentEncoding::Identity));
+
+    headers.insert("content-encoding", "BR".parse().unwrap());
+    assert_eq!(ContentEncoding::from_headers(&headers), Some(ContentEncoding::Brotli));
+
+    headers.insert("content-encoding", "gzip, br".parse().unwrap());
+    assert_eq!(ContentEncoding::from_headers(&headers), None);
+}
+
+#[test]
+fn opens_compressed_bodies() {
+    let body = BoxBody::new(gzip(b"hello, grazie"));
+    assert_eq!(body.open_compressed::<Vec<u8>>(ContentEncoding::Gzip, 64).unwrap(), b"hello, grazie");
+    assert!(body.open_compressed::<Vec<u8>>(ContentEncoding::Gzip, 4).is_none());
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(b"deflated").unwrap();
+    let body = BoxBody::new(encoder.finish().unwrap().into_boxed_slice());
+    assert_eq!(body.decompress(ContentEncoding::Deflate, 64).unwrap(), b"deflated");
+}
@@ -0,0 +1,119 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::events::{ChannelPublisher, Event, EventSeeder, Events, MemoryPublisher};
+use crate::http::{HttpRequest, HttpResponse, Method, StatusCode};
+use tokio::sync::mpsc;
+
+fn header<'a>(event: &'a Event, name: &str) -> Option<&'a str> {
+    event.headers.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+}
+
+#[tokio::test]
+async fn handlers_publish_through_the_request() {
+    let publisher = MemoryPublisher::new();
+    let seeder = EventSeeder::new(publisher.clone());
+
+    let mut request = HttpRequest::builder().uri("/orders").body(BoxBody::empty()).unwrap();
+    assert!(matches!(seeder.seed(Guard::Accessible(&mut request)).await, Guard::Accessible(_)));
+
+    let events = request.extensions().get::<Events<MemoryPublisher>>().unwrap();
+    events.publish(Event::new("orders.created", b"42".to_vec()).header("tenant", "acme")).await.unwrap();
+
+    assert_eq!(publisher.take(), vec![Event {
+        subject: "orders.created".to_owned(),
+        headers: vec![("tenant".to_owned(), "acme".to_owned())],
+        payload: b"42".to_vec(),
+    }]);
+    assert!(publisher.events().is_empty());
+}
+
+#[tokio::test]
+async fn publishes_lifecycle_events() {
+    let publisher = MemoryPublisher::new();
+    let seeder = EventSeeder::new(publisher.clone()).lifecycle("http");
+
+    let mut request = HttpRequest::builder().method(Method::POST).uri("/orders?draft=1").body(BoxBody::empty()).unwrap();
+    seeder.seed(Guard::Accessible(&mut request)).await;
+    let response = HttpResponse::builder().status(StatusCode::CREATED).body(BoxBody::empty()).unwrap();
+    seeder.finish(&request, &response).await;
+
+    let events = publisher.events();
+    assert_eq!(events.iter().map(|event| event.subject.as_str()).collect::<Vec<_>>(), ["http.received", "http.completed"]);
+    assert_eq!((header(&events[0], "method"), header(&events[0], "path")), (Some("POST"), Some("/orders")));
+    assert_eq!(header(&events[1], "status"), Some("201"));
+    assert!(header(&events[1], "elapsed_ms").is_some());
+    assert!(events.iter().all(|event| event.payload.is_empty()));
+}
+
+#[tokio::test]
+async fn publish_failures_dont_fail_requests() {
+    let (sender, receiver) = mpsc::channel(1);
+    drop(receiver);
+    let seeder = EventSeeder::new(ChannelPublisher(sender)).lifecycle("http");
+
+    let mut request = HttpRequest::builder().uri("/").body(BoxBody::empty()).unwrap();
+    assert!(matches!(seeder.seed(Guard::Accessible(&mut request)).await, Guard::Accessible(_)));
+    assert!(seeder.events().publish(Event::new("orders.created", Vec::new())).await.is_err());
+}
+
+#[tokio::test]
+async fn channel_publisher_delivers_in_process() {
+    let (sender, mut receiver) = mpsc::channel(4);
+    let seeder = EventSeeder::new(ChannelPublisher(sender));
+
+    seeder.events().publish(Event::new("orders.created", b"42".to_vec())).await.unwrap();
+    assert_eq!(receiver.recv().await.unwrap().payload, b"42");
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn json_events_are_typed() {
+    let event = Event::json("orders.created", &serde_json::json!({"id": 42})).unwrap();
+
+    assert_eq!(event.payload, br#"{"id":42}"#);
+    assert_eq!(header(&event, "content-type"), Some("application/json"));
+}
+
+#[cfg(feature = "nats")]
+#[tokio::test]
+async fn publishes_to_nats() {
+    use crate::events::{NatsPublisher, Publisher};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+    // A NATS server which answers pings, and hands each published message's header line and
+    // payload back to the test.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let (published, mut messages) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let info = b"INFO {\"server_id\":\"fake\",\"max_payload\":1048576,\"headers\":true}\r\n";
+        writer.write_all(info).await.unwrap();
+
+        let mut reader = BufReader::new(reader);
+        let mut line = String::new();
+        while reader.read_line(&mut line).await.unwrap() > 0 {
+            if line.starts_with("PING") {
+                writer.write_all(b"PONG\r\n").await.unwrap();
+            } else if line.starts_with("HPUB") {
+                let length: usize = line.split_whitespace().last().unwrap().parse().unwrap();
+                let mut message = vec![0; length + 2];
+                reader.read_exact(&mut message).await.unwrap();
+                published.send((line.clone(), String::from_utf8(message).unwrap())).unwrap();
+            }
+
+            line.clear();
+        }
+    });
+
+    let client = async_nats::connect(address.to_string()).await.unwrap();
+    let publisher = NatsPublisher(client);
+    let event = Event::new("orders.created", "{\"id\":1}").header("source", "checkout");
+    publisher.publish(event).await.unwrap();
+
+    let (line, message) = messages.recv().await.unwrap();
+    assert!(line.starts_with("HPUB orders.created "));
+    assert!(message.contains("source: checkout\r\n"));
+    assert!(message.ends_with("\r\n\r\n{\"id\":1}\r\n"));
+}
@@ -0,0 +1,77 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::flags::{FeatureFlags, FlagSeeder, Flags, FlagsError, RequireFlag};
+use crate::http::{HttpRequest, StatusCode};
+use std::time::Duration;
+
+fn checkout(user: &str) -> HttpRequest<BoxBody> {
+    HttpRequest::builder().uri("/checkout").header("x-user", user).body(BoxBody::empty()).unwrap()
+}
+
+#[test]
+fn rolls_out_to_a_stable_share_of_keys() {
+    let flags = FeatureFlags::new();
+    flags.load("# rollouts\nnew_checkout = 30%\ndark_mode = on\nlegacy = off").unwrap();
+
+    let enabled = (0..1000).filter(|user| flags.is_enabled("new_checkout", &user.to_string())).count();
+    assert!((200..400).contains(&enabled), "{enabled} of 1000 keys were enabled");
+
+    for user in 0..100 {
+        let user = user.to_string();
+        assert_eq!(flags.is_enabled("new_checkout", &user), flags.evaluate(&user).is_enabled("new_checkout"));
+        assert!(flags.is_enabled("dark_mode", &user));
+        assert!(!flags.is_enabled("legacy", &user));
+    }
+
+    assert!(matches!(flags.load("ok = on\nbroken = 120%"), Err(FlagsError::Parse(2))));
+    assert_eq!(flags.rollout("new_checkout"), Some(30));
+}
+
+#[tokio::test]
+async fn guards_requests_by_their_flags() {
+    let flags = FeatureFlags::new();
+    let seeder = FlagSeeder::new(flags.clone())
+        .key(|request| request.headers().get("x-user").and_then(|user| user.to_str().ok()).map(str::to_owned));
+
+    let mut request = checkout("ada");
+    assert!(matches!(seeder.seed(Guard::Accessible(&mut request)).await, Guard::Accessible(_)));
+    assert!(matches!(
+        RequireFlag("new_checkout").seed(Guard::Accessible(&mut request)).await,
+        Guard::Inaccessible { rejection, .. } if rejection.status == StatusCode::NOT_FOUND
+    ));
+
+    flags.enable("new_checkout");
+
+    let mut request = checkout("ada");
+    seeder.seed(Guard::Accessible(&mut request)).await;
+    assert!(request.extensions().get::<Flags>().unwrap().is_enabled("new_checkout"));
+    assert!(matches!(RequireFlag("new_checkout").seed(Guard::Accessible(&mut request)).await, Guard::Accessible(_)));
+}
+
+#[tokio::test]
+async fn reloads_changed_files() {
+    let path = std::env::temp_dir().join(format!("grazie-flags-{}.txt", std::process::id()));
+    std::fs::write(&path, "beta = off").unwrap();
+
+    let flags = FeatureFlags::from_file(&path).unwrap();
+    let watcher = flags.watch(&path, Duration::from_millis(10));
+    assert_eq!(flags.rollout("beta"), Some(0));
+
+    // Some filesystems only track modification times to the second, so rather than waiting for
+    // the time to tick over, the rewritten file is dated a minute later.
+    let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+    std::fs::write(&path, "beta = 50%").unwrap();
+    let file = std::fs::File::options().write(true).open(&path).unwrap();
+    file.set_modified(modified + Duration::from_secs(60)).unwrap();
+
+    for _ in 0..200 {
+        if flags.rollout("beta") == Some(50) {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    watcher.abort();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(flags.rollout("beta"), Some(50));
+}
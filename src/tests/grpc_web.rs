@@ -0,0 +1,75 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::grpc_web::{Code, GrpcStatus, GrpcWeb, GrpcWebCall, GrpcWebSeeder};
+use crate::http::{HttpRequest, StatusCode};
+use crate::util::{base64_decode, base64_encode};
+
+fn service() -> GrpcWeb {
+    GrpcWeb::new().unary("/echo.Echo/Say", |message: Vec<u8>| async move {
+        match message.is_empty() {
+            true => { Err(GrpcStatus::new(Code::InvalidArgument, "Nothing to say.")) }
+            false => { Ok(message.to_ascii_uppercase()) }
+        }
+    })
+}
+
+fn framed(message: &[u8]) -> Vec<u8> {
+    let mut body = vec![0];
+    body.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    body.extend_from_slice(message);
+    body
+}
+
+fn request(path: &str, content_type: &str, body: Vec<u8>) -> HttpRequest<BoxBody> {
+    HttpRequest::builder()
+        .method("POST")
+        .uri(path)
+        .header("content-type", content_type)
+        .body(BoxBody::new(body.into()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn answers_binary_calls_with_framed_replies() {
+    let request = request("/echo.Echo/Say", "application/grpc-web+proto", framed(b"hi"));
+    let response = service().respond(&request).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/grpc-web+proto");
+
+    let mut expected = framed(b"HI");
+    expected.extend_from_slice(&[0x80, 0, 0, 0, 15]);
+    expected.extend_from_slice(b"grpc-status:0\r\n");
+    assert_eq!(response.body().raw_bytes(), expected.as_slice());
+}
+
+#[tokio::test]
+async fn answers_text_calls_in_base64() {
+    // Clients may send several padded chunks back to back.
+    let body = framed(b"hey");
+    let body = format!("{}{}", base64_encode(&body[..2]), base64_encode(&body[2..]));
+    let mut request = request("/echo.Echo/Say", "application/grpc-web-text", body.into_bytes());
+
+    match GrpcWebSeeder::new().seed(Guard::Accessible(&mut request)).await {
+        Guard::Accessible(request) => { assert_eq!(request.extensions().get::<GrpcWebCall>().unwrap().message, b"hey"); }
+        _ => { panic!("the call should be decoded"); }
+    }
+
+    let response = service().respond(&request).await;
+    assert_eq!(response.headers()["content-type"], "application/grpc-web-text+proto");
+
+    let reply = base64_decode(std::str::from_utf8(response.body().raw_bytes()).unwrap()).unwrap();
+    assert!(reply.starts_with(&framed(b"HEY")));
+}
+
+#[tokio::test]
+async fn reports_failures_in_trailers_only_replies() {
+    let response = service().respond(&request("/echo.Echo/Say", "application/grpc-web", framed(b""))).await;
+    assert_eq!(response.headers()["grpc-status"], "3");
+    assert!(response.body().raw_bytes().ends_with(b"grpc-status:3\r\ngrpc-message:Nothing to say.\r\n"));
+
+    let response = service().respond(&request("/echo.Echo/Shout", "application/grpc-web", framed(b"hi"))).await;
+    assert_eq!(response.headers()["grpc-status"], "12");
+
+    let request = request("/echo.Echo/Say", "application/grpc-web", vec![0, 0, 0, 0, 9, 1]);
+    assert!(matches!(GrpcWebCall::from_request(&request), Some(Err(GrpcStatus { code: Code::InvalidArgument, .. }))));
+}
@@ -0,0 +1,37 @@
+use crate::hub::{Hub, Overflow};
+
+#[tokio::test]
+async fn sends_to_rooms_and_cleans_up() {
+    let hub = Hub::new(4);
+    let mut alice = hub.connect();
+    let mut bob = hub.connect();
+
+    hub.join(alice.id(), "general");
+    hub.join(bob.id(), "general");
+    hub.join(bob.id(), "random");
+
+    assert_eq!(hub.send_to("general", "hello").delivered, 2);
+    assert_eq!(hub.send_to("random", "psst").delivered, 1);
+    assert_eq!(alice.recv().await, Some("hello"));
+    assert_eq!(bob.recv().await, Some("hello"));
+    assert_eq!(bob.recv().await, Some("psst"));
+
+    drop(bob);
+    assert_eq!(hub.members("general"), vec![alice.id()]);
+    assert!(hub.members("random").is_empty());
+    assert_eq!(hub.len(), 1);
+}
+
+#[tokio::test]
+async fn handles_full_queues() {
+    let hub = Hub::new(1);
+    let _slow = hub.connect();
+
+    assert_eq!(hub.broadcast(1).delivered, 1);
+    assert_eq!(hub.broadcast(2).dropped, 1);
+    assert_eq!(hub.len(), 1);
+
+    let hub = hub.overflow(Overflow::Disconnect);
+    assert_eq!(hub.broadcast(3).dropped, 1);
+    assert!(hub.is_empty());
+}
@@ -0,0 +1,70 @@
+use crate::jobs::{Cron, JobOutcome, Jobs, Schedule};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+#[test]
+fn finds_next_cron_times() {
+    // 2024-02-28 23:59:30 UTC, a Wednesday.
+    let now = UNIX_EPOCH + Duration::from_secs(1_709_164_770);
+    let at = |cron: &str| {
+        let next = cron.parse::<Cron>().unwrap().next_after(now).unwrap();
+        next.duration_since(UNIX_EPOCH).unwrap().as_secs()
+    };
+
+    // 2024-02-29 00:00 UTC.
+    assert_eq!(at("@daily"), 1_709_164_800);
+    // 2024-02-29 09:00 UTC.
+    assert_eq!(at("*/15 9-17 * * 1-5"), 1_709_197_200);
+    // 2024-03-01 00:00 UTC.
+    assert_eq!(at("0 0 1 * *"), 1_709_251_200);
+    // 2024-03-03 12:00 UTC, the following Sunday.
+    assert_eq!(at("0 12 * * 7"), 1_709_467_200);
+
+    assert!("0 0 31 2 *".parse::<Cron>().unwrap().next_after(now).is_none());
+    assert!("60 * * * *".parse::<Cron>().is_err());
+    assert!("* * *".parse::<Cron>().is_err());
+}
+
+#[tokio::test]
+async fn runs_jobs_and_records_failures() {
+    let count = Arc::new(AtomicU32::new(0));
+
+    let jobs = Jobs::new()
+        .job("tick", Schedule::Every(Duration::from_millis(5)), {
+            let count = count.clone();
+            move || {
+                let count = count.clone();
+                async move {
+                    count.fetch_add(1, Ordering::Relaxed);
+                    Ok::<(), String>(())
+                }
+            }
+        })
+        .job("explode", Schedule::Once(Duration::ZERO), || async {
+            if true {
+                panic!("job exploded");
+            }
+            Ok::<(), String>(())
+        })
+        .start();
+
+    for _ in 0..200 {
+        if count.load(Ordering::Relaxed) >= 2 && jobs.status("explode").unwrap().runs == 1 {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    jobs.shutdown().await;
+
+    assert!(count.load(Ordering::Relaxed) >= 2);
+
+    let explode = jobs.status("explode").unwrap();
+    assert_eq!((explode.runs, explode.failures, explode.next_run), (1, 1, None));
+    assert!(matches!(explode.last_outcome, Some(JobOutcome::Panicked(_))));
+
+    let tick = jobs.status("tick").unwrap();
+    assert_eq!(tick.last_outcome, Some(JobOutcome::Succeeded));
+    assert!(!tick.running);
+}
@@ -0,0 +1,97 @@
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::jsonapi::{Document, ErrorDocument, JsonApiSeeder, Linkage, Relationship, Resource, JSON_API};
+use crate::validate::ValidationErrors;
+use serde::Deserialize;
+use serde_json::json;
+
+#[test]
+fn serializes_collections() {
+    let document = Document::collection([
+        Resource::new("articles", "1").relationship("tags", Relationship::many("tags", ["2", "3"])),
+        Resource::new("articles", "2").relationship("author", Relationship::none()),
+    ])
+    .include(Resource::new("tags", "2"))
+    .include(Resource::new("tags", "2"))
+    .link("next", "/articles?page=2")
+    .meta(json!({ "total": 2 }));
+
+    assert_eq!(document.to_value(), json!({
+        "data": [
+            { "type": "articles", "id": "1", "relationships": { "tags": { "data": [{ "type": "tags", "id": "2" }, { "type": "tags", "id": "3" }] } } },
+            { "type": "articles", "id": "2", "relationships": { "author": { "data": null } } },
+        ],
+        "included": [{ "type": "tags", "id": "2" }],
+        "links": { "next": "/articles?page=2" },
+        "meta": { "total": 2 },
+    }));
+
+    let response = document.into_response(StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], JSON_API);
+}
+
+#[test]
+fn parses_request_documents() {
+    #[derive(Deserialize)]
+    struct Article {
+        title: String,
+    }
+
+    let body = br#"{"data":{"type":"articles","attributes":{"title":"Hello"},"relationships":{"author":{"data":{"type":"people","id":"9"}}}}}"#;
+    let document = Document::from_body(&BoxBody::new(body.as_slice().into())).unwrap();
+    let resource = document.resource().unwrap();
+
+    assert_eq!(resource.id, None);
+    assert_eq!(resource.decode_attributes::<Article>().unwrap().title, "Hello");
+    assert!(matches!(&resource.relationships["author"].data, Linkage::One(Some(author)) if author.id == "9"));
+
+    let rejection = Document::from_body(&BoxBody::new(b"{}".as_slice().into())).unwrap_err();
+    assert_eq!(rejection.status, StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn formats_rejections_as_errors() {
+    let mut errors = ValidationErrors::new();
+    errors.add("title", "length", "must be at most 80 characters");
+    errors.add("author.name", "blank", "must not be blank");
+
+    let document = ErrorDocument::from_rejection(&errors.into_rejection());
+    let pointers: Vec<_> = document.errors.iter().filter_map(|error| error.source.as_ref()?.pointer.clone()).collect();
+    assert_eq!(pointers, vec!["/data/attributes/author/name", "/data/attributes/title"]);
+    assert_eq!(document.errors[0].status.as_deref(), Some("422"));
+
+    let document = ErrorDocument::from_rejection(&Rejection::new(StatusCode::NOT_FOUND, "missing", "No such article."));
+    assert_eq!(document.errors[0].code.as_deref(), Some("missing"));
+    assert_eq!(document.errors[0].detail.as_deref(), Some("No such article."));
+}
+
+#[tokio::test]
+async fn converts_rejections_and_checks_media_types() {
+    let mut request = HttpRequest::builder()
+        .header("content-type", "application/json")
+        .body(BoxBody::new(b"{}".as_slice().into()))
+        .unwrap();
+
+    match JsonApiSeeder.seed(Guard::Accessible(&mut request)).await {
+        Guard::Inaccessible { rejection, .. } => { assert_eq!(rejection.status, StatusCode::UNSUPPORTED_MEDIA_TYPE); }
+        _ => { panic!("other media types should be rejected"); }
+    }
+
+    let mut request = HttpRequest::new(BoxBody::empty());
+    let rejected = Guard::Inaccessible {
+        request: &mut request,
+        respondent: Respondent::Respond(Box::new(HttpResponse::builder().status(404).header("x-trace", "1").body(BoxBody::empty()).unwrap())),
+        rejection: Rejection::new(StatusCode::NOT_FOUND, "missing", "No such article."),
+    };
+
+    match JsonApiSeeder.seed(rejected).await {
+        Guard::Inaccessible { respondent: Respondent::Respond(response), .. } => {
+            assert_eq!(response.headers()["content-type"], JSON_API);
+            assert_eq!(response.headers()["x-trace"], "1");
+
+            let document: ErrorDocument = serde_json::from_slice(response.body().raw_bytes()).unwrap();
+            assert_eq!(document.errors[0].code.as_deref(), Some("missing"));
+        }
+        _ => { panic!("the rejection should be converted"); }
+    }
+}
@@ -0,0 +1,78 @@
+use crate::jsonrpc::{JsonRpc, RpcError, INVALID_PARAMS};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Deserialize)]
+struct Greeting {
+    name: String,
+}
+
+fn rpc() -> JsonRpc {
+    JsonRpc::new()
+        .method("subtract", |(a, b): (i64, i64)| async move { Ok::<_, RpcError>(a - b) })
+        .method("greet", |greeting: Greeting| async move { Ok::<_, RpcError>(format!("Hello, {}!", greeting.name)) })
+        .method("ping", |()| async move { Ok::<_, RpcError>("pong") })
+        .method("fail", |()| async move { Err::<(), _>(RpcError::new(-32000, "Nope.").data("details")) })
+}
+
+async fn call(rpc: &JsonRpc, body: &str) -> Option<Value> {
+    rpc.handle(body.as_bytes()).await.map(|response| serde_json::from_str(&response).unwrap())
+}
+
+#[tokio::test]
+async fn dispatches_positional_and_named_params() {
+    let rpc = rpc();
+
+    let response = call(&rpc, r#"{"jsonrpc":"2.0","method":"subtract","params":[42,23],"id":1}"#).await;
+    assert_eq!(response, Some(json!({"jsonrpc": "2.0", "result": 19, "id": 1})));
+
+    let response = call(&rpc, r#"{"jsonrpc":"2.0","method":"greet","params":{"name":"Ada"},"id":"a"}"#).await;
+    assert_eq!(response, Some(json!({"jsonrpc": "2.0", "result": "Hello, Ada!", "id": "a"})));
+
+    let response = call(&rpc, r#"{"jsonrpc":"2.0","method":"ping","id":2}"#).await;
+    assert_eq!(response, Some(json!({"jsonrpc": "2.0", "result": "pong", "id": 2})));
+}
+
+#[tokio::test]
+async fn returns_spec_error_objects() {
+    let rpc = rpc();
+
+    let response = call(&rpc, r#"{"jsonrpc":"2.0","method":"subtract","params":["a"],"id":1}"#).await.unwrap();
+    assert_eq!(response["error"]["code"], INVALID_PARAMS);
+
+    let response = call(&rpc, r#"{"jsonrpc":"2.0","method":"missing","id":1}"#).await.unwrap();
+    assert_eq!(response["error"], json!({"code": -32601, "message": "Method not found"}));
+
+    let response = call(&rpc, r#"{"jsonrpc":"2.0","method":"fail","id":1}"#).await.unwrap();
+    assert_eq!(response["error"], json!({"code": -32000, "message": "Nope.", "data": "details"}));
+
+    let response = call(&rpc, r#"{"jsonrpc":"2.0","method""#).await.unwrap();
+    assert_eq!(response, json!({"jsonrpc": "2.0", "error": {"code": -32700, "message": "Parse error"}, "id": null}));
+
+    let response = call(&rpc, r#"{"jsonrpc":"1.0","method":"ping","id":3}"#).await.unwrap();
+    assert_eq!(response["error"]["code"], -32600);
+    assert_eq!(response["id"], 3);
+
+    let response = call(&rpc, "[]").await.unwrap();
+    assert_eq!(response["error"]["code"], -32600);
+}
+
+#[tokio::test]
+async fn answers_batches_without_notifications() {
+    let rpc = rpc();
+
+    let response = call(&rpc, r#"[
+        {"jsonrpc":"2.0","method":"subtract","params":[1,2],"id":1},
+        {"jsonrpc":"2.0","method":"ping"},
+        {"jsonrpc":"2.0","method":"missing"},
+        1
+    ]"#).await.unwrap();
+
+    assert_eq!(response, json!([
+        {"jsonrpc": "2.0", "result": -1, "id": 1},
+        {"jsonrpc": "2.0", "error": {"code": -32600, "message": "Invalid Request"}, "id": null},
+    ]));
+
+    assert_eq!(call(&rpc, r#"[{"jsonrpc":"2.0","method":"ping"}]"#).await, None);
+    assert_eq!(call(&rpc, r#"{"jsonrpc":"2.0","method":"missing"}"#).await, None);
+}
@@ -0,0 +1,43 @@
+use crate::links::{expand, Link, Links};
+
+#[test]
+fn expands_templates() {
+    assert_eq!(expand("/orders/{id}/items/{item}", &[("id", "7"), ("item", "a/b")]), "/orders/7/items/a%2Fb");
+    assert_eq!(expand("/orders/{id}", &[]), "/orders/{id}");
+    assert_eq!(expand("/orders/{id", &[("id", "7")]), "/orders/{id");
+}
+
+#[test]
+fn formats_link_headers() {
+    let links = Links::new()
+        .link("self", "/orders/7")
+        .with(Link::new("help", "/docs/orders").title("About \"orders\"").media_type("text/html"));
+
+    assert_eq!(
+        links.to_header(),
+        "</orders/7>; rel=\"self\", </docs/orders>; rel=\"help\"; title=\"About \\\"orders\\\"\"; type=\"text/html\"",
+    );
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn embeds_links_in_json() {
+    let links = Links::new()
+        .link("self", "/orders/7")
+        .link("item", "/items/1")
+        .link("item", "/items/2");
+
+    let mut order = serde_json::json!({ "id": 7 });
+    links.embed(&mut order);
+
+    assert_eq!(
+        order,
+        serde_json::json!({
+            "id": 7,
+            "_links": {
+                "self": { "href": "/orders/7" },
+                "item": [{ "href": "/items/1" }, { "href": "/items/2" }],
+            },
+        }),
+    );
+}
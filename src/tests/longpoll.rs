@@ -0,0 +1,55 @@
+use crate::core::seeder::BoxBody;
+use crate::http::StatusCode;
+use crate::longpoll::{LongPoll, CURSOR_HEADER};
+use std::time::Duration;
+
+#[tokio::test]
+async fn returns_missed_events_immediately() {
+    let poll = LongPoll::new(8);
+    poll.publish("orders", "first");
+    poll.publish("orders", "second");
+
+    let events = poll.poll("orders", Some(0), Duration::from_secs(5)).await;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event, "second");
+}
+
+#[tokio::test]
+async fn wakes_on_publish() {
+    let poll = LongPoll::new(8);
+    let publisher = poll.clone();
+
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        publisher.publish("orders", "new");
+    });
+
+    let events = poll.poll("orders", None, Duration::from_secs(5)).await;
+    assert_eq!(events[0].event, "new");
+}
+
+#[tokio::test]
+async fn responds_no_content_on_expiry() {
+    let poll: LongPoll<&'static str> = LongPoll::new(8);
+
+    let response = poll
+        .respond("orders", None, Duration::from_millis(10), |_| BoxBody::empty())
+        .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    poll.publish("orders", "seen");
+    poll.publish("orders", "unseen");
+    let response = poll
+        .respond("orders", Some(0), Duration::from_secs(30), |_| BoxBody::empty())
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CURSOR_HEADER], "1");
+}
+
+#[tokio::test]
+async fn polling_doesnt_create_topics() {
+    let poll: LongPoll<&'static str> = LongPoll::new(8);
+
+    assert!(poll.poll("made-up", None, Duration::from_millis(10)).await.is_empty());
+    assert!(poll.topics().is_empty());
+}
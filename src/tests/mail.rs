@@ -0,0 +1,170 @@
+use crate::jobs::Jobs;
+use crate::mail::{DeliveryStatus, Mailer, MemoryTransport, Message, Template, Transport};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A transport which fails a number of times, then delivers.
+struct Flaky {
+    failures: u32,
+    tries: AtomicU32,
+}
+
+impl Transport for Flaky {
+    type Error = &'static str;
+
+    async fn send(&self, _: &Message) -> Result<(), &'static str> {
+        match self.tries.fetch_add(1, Ordering::SeqCst) < self.failures {
+            true => { Err("connection refused") }
+            false => { Ok(()) }
+        }
+    }
+}
+
+#[test]
+fn renders_templates() {
+    let template = Template::new("Welcome, {name}", "Hi {name}.").html("<p title='{name}'>Hi {name}.</p>");
+    let mailer = Mailer::new(MemoryTransport::new()).template("welcome", template);
+
+    let message = mailer.render("welcome", "ada@example.com", &[("name", "Ada <3 O'Hara")]).unwrap();
+    assert_eq!(message.to, ["ada@example.com"]);
+    assert_eq!(message.subject, "Welcome, Ada <3 O'Hara");
+    assert_eq!(message.text, "Hi Ada <3 O'Hara.");
+    let html = "<p title='Ada &lt;3 O&#39;Hara'>Hi Ada &lt;3 O&#39;Hara.</p>";
+    assert_eq!(message.html.as_deref(), Some(html));
+
+    assert!(mailer.render("missing", "ada@example.com", &[]).is_none());
+}
+
+#[test]
+fn fills_placeholders_in_one_pass() {
+    let template = Template::new("{greeting}, {name}", "{name} {unknown}, {{name}");
+
+    let message = template.render("ada@example.com", &[("name", "{greeting}"), ("greeting", "Hello")]);
+    assert_eq!(message.subject, "Hello, {greeting}");
+    assert_eq!(message.text, "{greeting} {unknown}, {{greeting}");
+}
+
+#[tokio::test]
+async fn sends_from_the_default_sender() {
+    let mailer = Mailer::new(MemoryTransport::new()).from("noreply@example.com");
+
+    mailer.send(Message::new("ada@example.com", "Hello", "Hi.")).await.unwrap();
+    mailer.send(Message::new("ada@example.com", "Hello", "Hi.").from("ceo@example.com").to("bob@example.com")).await.unwrap();
+
+    let sent = mailer.transport().sent();
+    assert_eq!(sent[0].from.as_deref(), Some("noreply@example.com"));
+    assert_eq!(sent[1].from.as_deref(), Some("ceo@example.com"));
+    assert_eq!(sent[1].to, ["ada@example.com", "bob@example.com"]);
+}
+
+#[tokio::test]
+async fn retries_queued_messages_and_reports_deliveries() {
+    let statuses = Arc::new(Mutex::new(Vec::new()));
+    let mailer = Mailer::new(Flaky { failures: 1, tries: AtomicU32::new(0) }).on_delivery({
+        let statuses = statuses.clone();
+        move |_, status| statuses.lock().unwrap().push(status.clone())
+    });
+
+    mailer.queue(Message::new("ada@example.com", "Hello", "Hi."));
+    assert_eq!(mailer.flush().await, 0);
+    assert_eq!(mailer.queued(), 1);
+    assert_eq!(mailer.flush().await, 1);
+    assert_eq!(mailer.queued(), 0);
+
+    assert_eq!(*statuses.lock().unwrap(), [DeliveryStatus::Failed("connection refused".to_owned()), DeliveryStatus::Sent]);
+}
+
+#[tokio::test]
+async fn abandons_messages_after_their_last_attempt() {
+    let statuses = Arc::new(Mutex::new(Vec::new()));
+    let mailer = Mailer::new(Flaky { failures: 5, tries: AtomicU32::new(0) }).attempts(2).on_delivery({
+        let statuses = statuses.clone();
+        move |_, status| statuses.lock().unwrap().push(status.clone())
+    });
+
+    mailer.queue(Message::new("ada@example.com", "Hello", "Hi."));
+    mailer.flush().await;
+    mailer.flush().await;
+
+    assert_eq!(mailer.queued(), 0);
+    assert_eq!(statuses.lock().unwrap().last(), Some(&DeliveryStatus::Abandoned("connection refused".to_owned())));
+}
+
+#[tokio::test]
+async fn sends_the_queue_on_a_schedule() {
+    let mailer = Mailer::new(MemoryTransport::new());
+    mailer.queue(Message::new("ada@example.com", "Hello", "Hi."));
+
+    let jobs = mailer.schedule(Jobs::new(), Duration::from_millis(10)).start();
+    for _ in 0..200 {
+        if !mailer.transport().sent().is_empty() {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    jobs.shutdown().await;
+
+    assert_eq!(mailer.transport().sent().len(), 1);
+}
+
+#[cfg(feature = "lettre")]
+#[tokio::test]
+async fn sends_over_smtp() {
+    use crate::mail::SmtpTransport;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    // An SMTP server which accepts every message, handing each one's envelope and data back to
+    // the test.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (received, mut messages) = tokio::sync::mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        writer.write_all(b"220 fake ESMTP\r\n").await.unwrap();
+
+        let mut reader = BufReader::new(reader);
+        let mut transcript = String::new();
+        let mut line = String::new();
+        let mut data = false;
+        while reader.read_line(&mut line).await.unwrap() > 0 {
+            transcript.push_str(&line);
+
+            let reply: &[u8] = match (data, line.as_str()) {
+                (true, ".\r\n") => {
+                    data = false;
+                    received.send(std::mem::take(&mut transcript)).unwrap();
+                    b"250 queued\r\n"
+                }
+                (true, _) => { b"" }
+                (false, "DATA\r\n") => {
+                    data = true;
+                    b"354 go ahead\r\n"
+                }
+                (false, "QUIT\r\n") => { b"221 bye\r\n" }
+                (false, _) => { b"250 ok\r\n" }
+            };
+
+            writer.write_all(reply).await.unwrap();
+            line.clear();
+        }
+    });
+
+    let transport = lettre::AsyncSmtpTransport::<lettre::Tokio1Executor>::builder_dangerous("127.0.0.1");
+    let transport = SmtpTransport::new(transport.port(port).build());
+    assert!(transport.send(&Message::new("ada@example.com", "Hello", "Hi.")).await.is_err());
+
+    let mailer = Mailer::new(transport).from("noreply@example.com");
+    mailer.send(Message::new("ada@example.com", "Welcome", "Hi Ada.").html("<p>Hi Ada.</p>")).await.unwrap();
+
+    let transcript = messages.recv().await.unwrap();
+    assert!(transcript.contains("MAIL FROM:<noreply@example.com>"));
+    assert!(transcript.contains("RCPT TO:<ada@example.com>"));
+    assert!(transcript.contains("Subject: Welcome\r\n"));
+    assert!(transcript.contains("multipart/alternative"));
+    assert!(transcript.contains("Hi Ada.\r\n"));
+    assert!(transcript.contains("<p>Hi Ada.</p>"));
+}
@@ -0,0 +1,39 @@
+use crate::core::seeder::BoxBody;
+use crate::ndjson::{from_body, NdjsonError, NdjsonReader, NdjsonWriter};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Event {
+    id: u32,
+}
+
+#[tokio::test]
+async fn writes_and_reads_lines() {
+    let mut writer = NdjsonWriter::new(Vec::new());
+    writer.write(&Event { id: 1 }).await.unwrap();
+    writer.write(&Event { id: 2 }).await.unwrap();
+    assert_eq!(writer.written(), 2);
+
+    let mut written = writer.into_inner();
+    written.extend_from_slice(b"\r\n{\"id\":3}");
+    assert_eq!(written, b"{\"id\":1}\n{\"id\":2}\n\r\n{\"id\":3}");
+
+    let mut reader = NdjsonReader::new(written.as_slice());
+    let mut events = Vec::new();
+    while let Some(event) = reader.next::<Event>().await {
+        events.push(event.unwrap());
+    }
+
+    assert_eq!(events, vec![Event { id: 1 }, Event { id: 2 }, Event { id: 3 }]);
+}
+
+#[tokio::test]
+async fn rejects_long_and_invalid_lines() {
+    let mut reader = NdjsonReader::new(b"{\"id\":1234567}\n".as_slice()).max_line(8);
+    assert!(matches!(reader.next::<Event>().await, Some(Err(NdjsonError::TooLong(1)))));
+
+    let body = BoxBody::new(b"{\"id\":1}\n\nnope\n".as_slice().into());
+    let results: Vec<Result<Event, NdjsonError>> = from_body(&body).collect();
+    assert!(results[0].is_ok());
+    assert!(matches!(results[1], Err(NdjsonError::Json(3, _))));
+}
@@ -0,0 +1,162 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::db::{Database, Pool, Transactional, TxSeeder};
+use crate::events::{Event, MemoryPublisher, Publisher};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::jobs::Jobs;
+use crate::outbox::{Outbox, OutboxError, OutboxStore, StoredEvent, OUTBOX_ID};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A pool with an outbox table, whose transactions buffer the events written in them.
+#[derive(Default)]
+struct Table {
+    rows: Mutex<Vec<(StoredEvent, bool)>>,
+}
+
+impl Pool for Table {
+    type Error = String;
+
+    async fn ping(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    async fn close(&self) {}
+}
+
+impl Transactional for Table {
+    type Transaction = Vec<Event>;
+
+    async fn begin(&self) -> Result<Vec<Event>, String> {
+        Ok(Vec::new())
+    }
+
+    async fn commit(&self, transaction: Vec<Event>) -> Result<(), String> {
+        let mut rows = self.rows.lock().unwrap();
+        for event in transaction {
+            let id = rows.len() as u64 + 1;
+            rows.push((StoredEvent { id, event }, false));
+        }
+
+        Ok(())
+    }
+
+    async fn rollback(&self, _: Vec<Event>) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+impl OutboxStore for Table {
+    async fn insert(&self, transaction: &mut Vec<Event>, event: &Event) -> Result<(), String> {
+        transaction.push(event.clone());
+        Ok(())
+    }
+
+    async fn pending(&self, limit: usize) -> Result<Vec<StoredEvent>, String> {
+        let rows = self.rows.lock().unwrap();
+        Ok(rows.iter().filter(|(_, published)| !published).take(limit).map(|(row, _)| row.clone()).collect())
+    }
+
+    async fn mark_published(&self, ids: &[u64]) -> Result<(), String> {
+        for (row, published) in self.rows.lock().unwrap().iter_mut() {
+            *published |= ids.contains(&row.id);
+        }
+
+        Ok(())
+    }
+}
+
+/// A publisher which accepts a number of events, then fails.
+struct Failing {
+    accepts: usize,
+    published: AtomicUsize,
+}
+
+impl Publisher for Failing {
+    type Error = &'static str;
+
+    async fn publish(&self, _: Event) -> Result<(), &'static str> {
+        match self.published.fetch_add(1, Ordering::SeqCst) < self.accepts {
+            true => { Ok(()) }
+            false => { Err("broker unavailable") }
+        }
+    }
+}
+
+async fn request(seeder: &TxSeeder<Table>, outbox: &Outbox<Table>, subject: &str, status: StatusCode) {
+    let mut request = HttpRequest::builder().method("POST").uri("/orders").body(BoxBody::empty()).unwrap();
+    seeder.seed(Guard::Accessible(&mut request)).await;
+    outbox.write(&request, Event::new(subject, Vec::new())).await.unwrap();
+
+    let response = HttpResponse::builder().status(status).body(BoxBody::empty()).unwrap();
+    seeder.finish(&request, &response).await.unwrap();
+}
+
+#[tokio::test]
+async fn only_committed_events_are_relayed() {
+    let database = Database::new(Table::default());
+    let seeder = TxSeeder::new(database.clone());
+    let outbox = Outbox::new(database.clone()).batch(2);
+
+    request(&seeder, &outbox, "orders.created", StatusCode::CREATED).await;
+    request(&seeder, &outbox, "orders.rejected", StatusCode::UNPROCESSABLE_ENTITY).await;
+    request(&seeder, &outbox, "orders.updated", StatusCode::OK).await;
+    request(&seeder, &outbox, "orders.deleted", StatusCode::NO_CONTENT).await;
+
+    let publisher = MemoryPublisher::new();
+    assert_eq!(outbox.relay(&publisher).await.unwrap(), 3);
+    assert_eq!(outbox.relay(&publisher).await.unwrap(), 0);
+
+    let events = publisher.events();
+    assert_eq!(events.iter().map(|event| event.subject.as_str()).collect::<Vec<_>>(), ["orders.created", "orders.updated", "orders.deleted"]);
+    assert_eq!(events[2].headers, [(OUTBOX_ID.to_owned(), "3".to_owned())]);
+}
+
+#[tokio::test]
+async fn failed_publishes_are_retried_in_order() {
+    let database = Database::new(Table::default());
+    let seeder = TxSeeder::new(database.clone());
+    let outbox = Outbox::new(database.clone());
+
+    for subject in ["first", "second", "third"] {
+        request(&seeder, &outbox, subject, StatusCode::CREATED).await;
+    }
+
+    let failing = Failing { accepts: 1, published: AtomicUsize::new(0) };
+    assert_eq!(outbox.relay(&failing).await, Err(OutboxError::Publish("broker unavailable".to_owned())));
+
+    let publisher = MemoryPublisher::new();
+    assert_eq!(outbox.relay(&publisher).await.unwrap(), 2);
+    assert_eq!(publisher.events().iter().map(|event| event.subject.as_str()).collect::<Vec<_>>(), ["second", "third"]);
+}
+
+#[tokio::test]
+async fn writes_need_a_transaction() {
+    let outbox = Outbox::new(Database::new(Table::default()));
+    let request = HttpRequest::builder().uri("/orders").body(BoxBody::empty()).unwrap();
+
+    assert_eq!(outbox.write(&request, Event::new("orders.read", Vec::new())).await, Err(OutboxError::NoTransaction));
+}
+
+#[tokio::test]
+async fn relays_on_a_schedule() {
+    let database = Database::new(Table::default());
+    let seeder = TxSeeder::new(database.clone());
+    let outbox = Outbox::new(database.clone());
+    request(&seeder, &outbox, "orders.created", StatusCode::CREATED).await;
+
+    let publisher = MemoryPublisher::new();
+    let jobs = outbox.schedule(Jobs::new(), publisher.clone(), Duration::from_millis(10)).start();
+
+    for _ in 0..200 {
+        if !publisher.events().is_empty() && jobs.status("outbox-relay").unwrap().runs >= 1 {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    jobs.shutdown().await;
+
+    assert_eq!(publisher.events().len(), 1);
+    assert!(jobs.status("outbox-relay").unwrap().runs >= 1);
+}
@@ -0,0 +1,31 @@
+use crate::pool::{ConnectionPool, PoolConfig, PoolKey};
+use tokio::net::TcpListener;
+
+#[tokio::test]
+async fn reuses_released_connections() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let key = PoolKey::new("http", "127.0.0.1", listener.local_addr().unwrap().port());
+    tokio::spawn(async move {
+        let mut accepted = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            accepted.push(stream);
+        }
+    });
+
+    let pool = ConnectionPool::new(PoolConfig {
+        max_idle_per_host: 1,
+        ..PoolConfig::default()
+    });
+
+    let first = pool.checkout(&key).await.unwrap();
+    let second = pool.checkout(&key).await.unwrap();
+    let local = first.local_addr().unwrap();
+    first.release();
+    second.release();
+
+    let reused = pool.checkout(&key).await.unwrap();
+    assert_eq!(reused.local_addr().unwrap(), local);
+
+    let metrics = pool.metrics();
+    assert_eq!((metrics.hits, metrics.misses, metrics.evictions), (1, 2, 1));
+}
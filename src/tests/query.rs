@@ -0,0 +1,66 @@
+use crate::core::seeder::{BoxBody, Rejection};
+use crate::http::{HttpRequest, StatusCode};
+use crate::query::{Direction, Filter, Pagination, PaginationLimits, Sort};
+
+fn get(uri: &str) -> HttpRequest<BoxBody> {
+    HttpRequest::builder().uri(uri).body(BoxBody::empty()).unwrap()
+}
+
+#[test]
+fn parses_pagination_with_limits() {
+    assert_eq!(Pagination::from_request(&get("/orders")).unwrap(), Pagination::new(1, 20));
+    assert_eq!(Pagination::from_request(&get("/orders?page=3&per_page=500")).unwrap(), Pagination::new(3, 100));
+
+    let limits = PaginationLimits::new().default_per_page(10).max_per_page(50);
+    let pagination = limits.parse(&get("/orders?page=4")).unwrap();
+    assert_eq!(pagination.offset(), 30);
+    assert_eq!(pagination.limit(), 10);
+
+    let error = Pagination::from_request(&get("/orders?page=0")).unwrap_err();
+    assert_eq!(error.parameter, "page");
+    assert_eq!(Rejection::from(error).status, StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn links_neighbouring_pages() {
+    let uri = "/orders?sort=-created_at&page=1&per_page=10".parse().unwrap();
+
+    assert_eq!(
+        Pagination::new(1, 10).links(&uri, 25),
+        "</orders?sort=-created_at&page=1&per_page=10>; rel=\"first\", \
+         </orders?sort=-created_at&page=2&per_page=10>; rel=\"next\", \
+         </orders?sort=-created_at&page=3&per_page=10>; rel=\"last\"",
+    );
+
+    assert_eq!(
+        Pagination::new(5, 10).links(&uri, 0),
+        "</orders?sort=-created_at&page=1&per_page=10>; rel=\"first\", \
+         </orders?sort=-created_at&page=1&per_page=10>; rel=\"prev\", \
+         </orders?sort=-created_at&page=1&per_page=10>; rel=\"last\"",
+    );
+
+    // Listings long enough to clamp the page count don't overflow.
+    let uri = "/orders".parse().unwrap();
+    let links = Pagination::new(u32::MAX, 1).links(&uri, u64::MAX);
+    assert!(links.to_str().unwrap().contains(&format!("page={}&per_page=1>; rel=\"prev\"", u32::MAX - 1)));
+}
+
+#[test]
+fn parses_allowed_sorts() {
+    let sort = Sort::from_request(&get("/orders?sort=-created_at,name"), &["created_at", "name"]).unwrap();
+    assert_eq!(sort.fields, vec![("created_at".into(), Direction::Descending), ("name".into(), Direction::Ascending)]);
+
+    assert!(Sort::from_request(&get("/orders"), &["name"]).unwrap().is_empty());
+    assert_eq!(Sort::from_request(&get("/orders?sort=secret"), &["name"]).unwrap_err().parameter, "sort");
+}
+
+#[test]
+fn parses_allowed_filters() {
+    let filter = Filter::from_request(&get("/orders?filter%5Bstatus%5D=open,pending&filter[customer]=7&page=2"), &["status", "customer"]).unwrap();
+    assert_eq!(filter.values("status"), vec!["open", "pending"]);
+    assert_eq!(filter.get("customer"), Some("7"));
+    assert_eq!(filter.iter().count(), 2);
+
+    let error = Filter::from_request(&get("/orders?filter[secret]=1"), &["status"]).unwrap_err();
+    assert_eq!(error.parameter, "filter[secret]");
+}
@@ -0,0 +1,120 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::header::HeaderName;
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::recorder::Recorder;
+use crate::seeders::MaintenanceSeeder;
+use crate::test::{load, replay};
+use std::path::Path;
+use std::time::Duration;
+
+/// Waits for the recording at `path` to hold `count` requests, the last with its response.
+async fn recorded(path: &Path, count: usize) -> Vec<crate::recorder::Recording> {
+    let mut recordings = Vec::new();
+    for _ in 0..200 {
+        recordings = load(path).await.unwrap_or_default();
+        if recordings.get(count - 1).is_some_and(|recording| recording.response.is_some()) {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    recordings
+}
+
+#[tokio::test]
+async fn records_redacted_traffic_for_replay() {
+    let path = std::env::temp_dir().join(format!("grazie-recording-{}.txt", std::process::id()));
+    let recorder = Recorder::open(&path)
+        .await
+        .unwrap()
+        .redact_header(HeaderName::from_static("x-api-key"))
+        .redact_query("token")
+        .redact_query("api_key");
+
+    let mut request = HttpRequest::builder()
+        .method("POST")
+        .uri("/users?token=hunter2&page=2&api%5Fkey=hunter2")
+        .header("authorization", "Bearer hunter2")
+        .header("x-api-key", "hunter2")
+        .header("content-type", "application/json")
+        .body(BoxBody::new(b"{\"name\":\"Ada\"}\n".as_slice().into()))
+        .unwrap();
+    recorder.seed(Guard::Accessible(&mut request)).await;
+
+    let response = HttpResponse::builder()
+        .status(StatusCode::CREATED)
+        .header("location", "/users/1")
+        .body(BoxBody::empty())
+        .unwrap();
+    recorder.record_response(&request, &response);
+
+    let recordings = recorded(&path, 1).await;
+
+    let contents = tokio::fs::read_to_string(&path).await.unwrap();
+    assert!(!contents.contains("hunter2"));
+
+    let recording = &recordings[0];
+    assert_eq!(recording.uri, "/users?token=REDACTED&page=2&api%5Fkey=REDACTED");
+    assert_eq!(recording.headers["content-type"], "application/json");
+    assert_eq!(&*recording.body, b"{\"name\":\"Ada\"}\n");
+    assert_eq!(recording.response.as_ref().unwrap().status, StatusCode::CREATED);
+
+    let maintenance = MaintenanceSeeder::new();
+    maintenance.handle().enable();
+
+    let replayed = replay(&path, &maintenance).await.unwrap();
+    tokio::fs::remove_file(&path).await.unwrap();
+
+    assert_eq!(replayed.len(), 1);
+    assert!(!replayed[0].accepted());
+    assert_eq!(replayed[0].response.as_ref().unwrap().status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn redacts_body_fields_and_whole_bodies() {
+    let path = std::env::temp_dir().join(format!("grazie-bodies-{}.txt", std::process::id()));
+    let recorder = Recorder::open(&path)
+        .await
+        .unwrap()
+        .redact_field("password")
+        .redact_body(|request| request.uri().path() == "/token");
+
+    let requests = [
+        ("/login", "application/x-www-form-urlencoded", "user=ada&pass%77ord=hunter2".as_bytes()),
+        ("/token", "text/plain", b"hunter2"),
+        ("/users", "application/json", br#"{"user":{"name":"Ada","password":"hunter2"}}"#),
+    ];
+
+    for (uri, content_type, body) in requests {
+        let mut request = HttpRequest::builder()
+            .method("POST")
+            .uri(uri)
+            .header("content-type", content_type)
+            .body(BoxBody::new(body.into()))
+            .unwrap();
+        recorder.seed(Guard::Accessible(&mut request)).await;
+
+        let response = HttpResponse::builder()
+            .header("content-type", "application/json")
+            .header("content-encoding", "gzip")
+            .body(BoxBody::new(b"password".as_slice().into()))
+            .unwrap();
+        recorder.record_response(&request, &response);
+    }
+
+    let recordings = recorded(&path, 3).await;
+    tokio::fs::remove_file(&path).await.unwrap();
+
+    assert_eq!(&*recordings[0].body, b"user=ada&pass%77ord=REDACTED");
+    assert_eq!(&*recordings[1].body, b"REDACTED");
+    #[cfg(feature = "serde_json")]
+    assert_eq!(&*recordings[2].body, br#"{"user":{"name":"Ada","password":"REDACTED"}}"#);
+    #[cfg(not(feature = "serde_json"))]
+    assert_eq!(&*recordings[2].body, b"REDACTED");
+
+    for recording in &recordings {
+        assert_eq!(&*recording.response.as_ref().unwrap().body, b"REDACTED");
+    }
+    assert_eq!((recorder.dropped(), recorder.failed()), (0, 0));
+}
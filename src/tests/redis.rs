@@ -0,0 +1,100 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::header::COOKIE;
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::redis::{connect, quota_fields, quota_from_fields};
+use crate::auth::session::SessionStore;
+use crate::redis::{RedisIdempotencyStore, RedisQuotaStore, RedisRateLimitStore, RedisSessionStore};
+use crate::seeders::idempotency::{IdempotencyStore, Lookup};
+use crate::seeders::tenant::{Quota, QuotaStore, RateLimitStore};
+use crate::seeders::IdempotencySeeder;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Connects to the server at `REDIS_URL`, with keys namespaced to this test run.
+async fn connection() -> (::redis::aio::ConnectionManager, String) {
+    let url = std::env::var("REDIS_URL").expect("REDIS_URL isn't set");
+    let connection = connect(&url, Duration::from_secs(1)).await.unwrap();
+    (connection, format!("grazie-test:{}:{}:", std::process::id(), crate::util::random_u64()))
+}
+
+#[test]
+fn quotas_round_trip_through_hash_fields() {
+    let fields = |quota: &Quota| {
+        quota_fields(quota).into_iter().map(|(name, value)| (name.to_owned(), value)).collect()
+    };
+
+    let quota = Quota::new().rate(100, Duration::from_secs(60)).max_body(1024);
+    assert_eq!(quota_from_fields(&fields(&quota)), Some(quota));
+    assert_eq!(quota_from_fields(&fields(&Quota::new())), Some(Quota::new()));
+    assert_eq!(quota_from_fields(&HashMap::new()), None);
+}
+
+#[tokio::test]
+#[ignore = "needs a Redis server at REDIS_URL"]
+async fn sessions_are_shared_through_redis() {
+    let (connection, prefix) = connection().await;
+    let sessions = RedisSessionStore::<String>::new(connection, Duration::from_secs(60)).prefix(prefix);
+
+    let set_cookie = sessions.create("alice".to_owned()).await.unwrap();
+    let pair = set_cookie.to_str().unwrap().split(';').next().unwrap().to_owned();
+    let request = HttpRequest::builder().header(COOKIE, pair).body(BoxBody::empty()).unwrap();
+
+    assert_eq!(sessions.get(&request).await.unwrap().as_deref(), Some("alice"));
+    sessions.destroy(&request).await.unwrap();
+    assert_eq!(sessions.get(&request).await.unwrap(), None);
+}
+
+#[tokio::test]
+#[ignore = "needs a Redis server at REDIS_URL"]
+async fn idempotency_keys_are_shared_through_redis() {
+    let (connection, prefix) = connection().await;
+    let store = RedisIdempotencyStore::new(connection.clone()).prefix(prefix.clone());
+    let ttl = Duration::from_secs(60);
+
+    assert!(matches!(store.begin("charge", ttl).await, Lookup::New));
+    assert!(matches!(store.begin("charge", ttl).await, Lookup::InFlight));
+    store.abandon("charge").await;
+
+    let store = RedisIdempotencyStore::new(connection).prefix(prefix);
+    let seeder = IdempotencySeeder::new(store).scope(|_| Some("client".to_owned()));
+    let request = || {
+        let request = HttpRequest::builder().method("POST").uri("/charges");
+        request.header("idempotency-key", "charge").body(BoxBody::empty())
+    };
+    let mut first = request().unwrap();
+    assert!(seeder.seed(Guard::Accessible(&mut first)).await.accessible());
+
+    let response = HttpResponse::builder().status(StatusCode::CREATED).body(BoxBody::empty()).unwrap();
+    seeder.complete(&first, &response).await;
+
+    let mut retry = request().unwrap();
+    match seeder.seed(Guard::Accessible(&mut retry)).await {
+        Guard::Inaccessible { rejection, .. } => { assert_eq!(rejection.status, StatusCode::CREATED); }
+        Guard::Accessible(_) => { panic!("the retry wasn't replayed"); }
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs a Redis server at REDIS_URL"]
+async fn rate_limits_are_shared_through_redis() {
+    let (connection, prefix) = connection().await;
+    let limits = RedisRateLimitStore::new(connection).prefix(prefix);
+
+    assert_eq!(limits.hit("tenant", 2, Duration::from_secs(60)).await, None);
+    assert_eq!(limits.hit("tenant", 2, Duration::from_secs(60)).await, None);
+    let reset = limits.hit("tenant", 2, Duration::from_secs(60)).await;
+    assert!(reset.is_some_and(|reset| reset <= Duration::from_secs(60)));
+}
+
+#[tokio::test]
+#[ignore = "needs a Redis server at REDIS_URL"]
+async fn quotas_are_shared_through_redis() {
+    let (connection, prefix) = connection().await;
+    let store = RedisQuotaStore::new(connection).prefix(prefix);
+    let quota = Quota::new().concurrency(4);
+
+    store.set("acme", quota).await.unwrap();
+    assert_eq!(store.quota("acme").await, Some(quota));
+    store.remove("acme").await.unwrap();
+    assert_eq!(store.quota("acme").await, None);
+}
@@ -0,0 +1,77 @@
+use crate::http::Method;
+use crate::retry::{RetryError, RetryPolicy};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+/// Sends a request which fails on its first try, and succeeds after, counting tries.
+async fn flaky(attempt: u32, tries: &AtomicU32) -> Result<u32, &'static str> {
+    tries.fetch_add(1, Ordering::SeqCst);
+    match attempt {
+        1 => { Err("connection reset") }
+        _ => { Ok(attempt) }
+    }
+}
+
+#[tokio::test]
+async fn retries_idempotent_requests() {
+    let policy = RetryPolicy::new().backoff(Duration::from_millis(1), Duration::from_millis(5));
+
+    let tries = AtomicU32::new(0);
+    let served = policy.run(&Method::GET, |attempt| flaky(attempt, &tries)).await;
+    assert_eq!((served, tries.load(Ordering::SeqCst)), (Ok(2), 2));
+
+    let tries = AtomicU32::new(0);
+    let refused = policy.run(&Method::POST, |attempt| flaky(attempt, &tries)).await;
+    assert_eq!((refused, tries.load(Ordering::SeqCst)), (Err(RetryError::Failed("connection reset")), 1));
+
+    let tries = AtomicU32::new(0);
+    let forced = policy.clone().any_method().run(&Method::POST, |attempt| flaky(attempt, &tries)).await;
+    assert_eq!(forced, Ok(2));
+}
+
+#[tokio::test]
+async fn abandons_slow_tries() {
+    let policy = RetryPolicy::new()
+        .try_timeout(Duration::from_millis(20))
+        .backoff(Duration::from_millis(1), Duration::from_millis(1));
+
+    let served = policy.run(&Method::GET, |attempt| async move {
+        if attempt == 1 {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+        Ok::<_, ()>(attempt)
+    }).await;
+    assert_eq!(served, Ok(2));
+
+    let policy = policy.attempts(1);
+    let slow = policy.run(&Method::GET, |_| async {
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        Ok::<_, ()>(())
+    }).await;
+    assert_eq!(slow, Err(RetryError::TimedOut));
+}
+
+#[tokio::test]
+async fn retries_are_budgeted() {
+    let policy = RetryPolicy::new().attempts(5).budget(0.0, 2).backoff(Duration::ZERO, Duration::ZERO);
+
+    let tries = AtomicU32::new(0);
+    assert!(policy.run(&Method::GET, |_| flaky(1, &tries)).await.is_err());
+    assert_eq!(tries.load(Ordering::SeqCst), 3);
+
+    let tries = AtomicU32::new(0);
+    let clone = policy.clone();
+    assert!(clone.run(&Method::GET, |_| flaky(1, &tries)).await.is_err());
+    assert_eq!(tries.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn backs_off_exponentially_with_jitter() {
+    let policy = RetryPolicy::new().backoff(Duration::from_millis(10), Duration::from_millis(100));
+
+    for _ in 0..100 {
+        assert!(policy.delay(1) <= Duration::from_millis(10));
+        assert!(policy.delay(3) <= Duration::from_millis(40));
+        assert!(policy.delay(40) <= Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,242 @@
+use crate::core::seeder::{BoxBody, Guard, Rejection, Reseeded, Respondent, Seeder, SeederFactory};
+use crate::core::seeder::{ReseedLimit, DEFAULT_RESEED_LIMIT};
+use crate::http::{HttpRequest, StatusCode};
+
+/// Appends its name to the `x-trail` header, optionally rejecting the request afterwards.
+struct Step {
+    name: &'static str,
+    respondent: Option<fn() -> Respondent>,
+}
+
+impl Seeder for Step {
+    async fn seed<'a>(&'a self, input: Guard<'a, HttpRequest<BoxBody>>) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        let trail = match request.headers().get("x-trail") {
+            Some(trail) => { format!("{},{}", trail.to_str().unwrap(), self.name) }
+            None => { self.name.to_owned() }
+        };
+        request.headers_mut().insert("x-trail", trail.parse().unwrap());
+
+        match self.respondent {
+            Some(respondent) => {
+                Guard::Inaccessible {
+                    request,
+                    respondent: respondent(),
+                    rejection: Rejection::new(StatusCode::FORBIDDEN, "step", self.name),
+                }
+            }
+            None => { Guard::Accessible(request) }
+        }
+    }
+}
+
+fn step(name: &'static str) -> Step {
+    Step { name, respondent: None }
+}
+
+#[tokio::test]
+async fn chains_run_in_order() {
+    let stack = step("auth").then(step("limit")).then(step("log"));
+
+    let mut request = HttpRequest::builder().uri("/").body(BoxBody::empty()).unwrap();
+    let request = stack.seed(Guard::Accessible(&mut request)).await.unwrap();
+    assert_eq!(request.headers()["x-trail"], "auth,limit,log");
+}
+
+#[tokio::test]
+async fn chains_stop_at_rejections_unless_ignored() {
+    let ignored = Step { name: "audit", respondent: Some(|| Respondent::Ignore) };
+    let rejected = Step { name: "auth", respondent: Some(|| Respondent::Other(Box::new(()))) };
+    let stack = ignored.then(rejected).then(step("handler"));
+
+    let mut request = HttpRequest::builder().uri("/").body(BoxBody::empty()).unwrap();
+    match stack.seed(Guard::Accessible(&mut request)).await {
+        Guard::Inaccessible { request, rejection, .. } => {
+            assert_eq!(rejection.message, "auth");
+            assert_eq!(request.headers()["x-trail"], "audit,auth");
+        }
+        _ => panic!("request was not rejected"),
+    }
+}
+
+/// A factory for `Respondent::Reseed`, whose seeder appends `reseeded` to the trail and accepts
+/// the request, or, if it's `endless`, appends `again` and reseeds the request once more.
+struct Reseeder {
+    endless: bool,
+}
+
+impl SeederFactory for Reseeder {
+    fn create<T: Seeder>() -> T {
+        panic!("chains reseed through `SeederFactory::reseed`")
+    }
+
+    fn reseed<'a>(self: Box<Self>, request: &'a mut HttpRequest<BoxBody>) -> Reseeded<'a> {
+        Box::pin(async move {
+            let again = || Respondent::Reseed(Box::new(Reseeder { endless: true }));
+            let step = match self.endless {
+                true => { Step { name: "again", respondent: Some(again) } }
+                false => { step("reseeded") }
+            };
+
+            match step.seed(Guard::Accessible(request)).await {
+                Guard::Accessible(_) => { None }
+                Guard::Inaccessible { respondent, rejection, .. } => {
+                    Some((respondent, rejection))
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        match self.endless {
+            true => { "again" }
+            false => { "reseeded" }
+        }
+    }
+}
+
+/// A factory whose seeder reseeds the request into the other named factory, without end.
+struct Bounce(&'static str, &'static str);
+
+impl SeederFactory for Bounce {
+    fn create<T: Seeder>() -> T {
+        panic!("chains reseed through `SeederFactory::reseed`")
+    }
+
+    fn reseed<'a>(self: Box<Self>, _request: &'a mut HttpRequest<BoxBody>) -> Reseeded<'a> {
+        Box::pin(async move {
+            let respondent = Respondent::Reseed(Box::new(Bounce(self.1, self.0)));
+            Some((respondent, Rejection::new(StatusCode::FORBIDDEN, "bounce", self.0)))
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        self.0
+    }
+}
+
+/// A factory written before `SeederFactory::reseed`, which only implements `create`.
+struct Legacy;
+
+impl SeederFactory for Legacy {
+    fn create<T: Seeder>() -> T {
+        panic!("chains reseed through `SeederFactory::reseed`")
+    }
+}
+
+#[tokio::test]
+async fn factories_which_cant_reseed_refuse_the_request() {
+    let respondent: fn() -> Respondent = || Respondent::Reseed(Box::new(Legacy));
+    let stack = Step { name: "start", respondent: Some(respondent) }.then(step("handler"));
+
+    let mut request = HttpRequest::builder().uri("/").body(BoxBody::empty()).unwrap();
+    match stack.seed(Guard::Accessible(&mut request)).await {
+        Guard::Inaccessible { request, rejection, .. } => {
+            assert_eq!(rejection.status, StatusCode::INTERNAL_SERVER_ERROR);
+            assert_eq!(rejection.code, "reseed_unsupported");
+            assert_eq!(request.headers()["x-trail"], "start");
+        }
+        Guard::Accessible(_) => panic!("a request was let through a factory which can't reseed"),
+    }
+}
+
+#[tokio::test]
+async fn reseed_limits_name_the_looping_factories() {
+    let respondent: fn() -> Respondent = || Respondent::Reseed(Box::new(Bounce("a", "b")));
+    let bounce = Step { name: "start", respondent: Some(respondent) };
+    let stack = ReseedLimit(3).then(bounce);
+
+    let mut request = HttpRequest::builder().uri("/").body(BoxBody::empty()).unwrap();
+    match stack.seed(Guard::Accessible(&mut request)).await {
+        Guard::Inaccessible { rejection, .. } => {
+            assert_eq!(rejection.status, StatusCode::LOOP_DETECTED);
+            assert_eq!(rejection.code, "reseed_limit");
+            let message = "The request was reseeded too many times, through b -> a -> b.";
+            assert_eq!(rejection.message, message);
+        }
+        Guard::Accessible(_) => panic!("an endless reseed was accepted"),
+    }
+}
+
+/// The names of the steps in a generated chain.
+const NAMES: [&str; 6] = ["a", "b", "c", "d", "e", "f"];
+
+/// Runs a six-step chain, where each step accepts or rejects with a respondent by its decision,
+/// and checks which steps ran and what came back.
+async fn check_chain(chosen: [usize; 6]) {
+    let decisions: [Option<fn() -> Respondent>; 6] = [
+        None,
+        Some(|| Respondent::Ignore),
+        Some(|| Respondent::Other(Box::new(()))),
+        Some(|| Respondent::Respond(Box::new(crate::http::HttpResponse::new(BoxBody::empty())))),
+        Some(|| Respondent::Reseed(Box::new(Reseeder { endless: false }))),
+        Some(|| Respondent::Reseed(Box::new(Reseeder { endless: true }))),
+    ];
+    let step = |position: usize| Step { name: NAMES[position], respondent: decisions[chosen[position]] };
+
+    // Steps run up to and including the first which rejects without ignoring or being reseeded
+    // into acceptance. Each reseed adds its seeder to the trail, and a step which reseeds without
+    // end is reseeded until the request's reseeds run out.
+    let rejecting = chosen.iter().position(|choice| matches!(choice, 2 | 3 | 5));
+    let ran = rejecting.map_or(NAMES.len(), |position| position + 1);
+    let mut trail = Vec::new();
+    for (name, choice) in NAMES[..ran].iter().zip(chosen) {
+        trail.push(*name);
+        match choice {
+            4 => { trail.push("reseeded"); }
+            5 => { trail.resize(ran + DEFAULT_RESEED_LIMIT, "again"); }
+            _ => {}
+        }
+    }
+
+    let stack = step(0).then(step(1)).then(step(2)).then(step(3)).then(step(4)).then(step(5));
+
+    let mut request = HttpRequest::builder().uri("/").body(BoxBody::empty()).unwrap();
+    let guard = stack.seed(Guard::Accessible(&mut request)).await;
+    let request = match guard {
+        Guard::Accessible(request) => {
+            assert_eq!(rejecting, None, "{chosen:?}");
+            request
+        }
+        // The last step's ignored rejection is left for whatever runs the chain to hand along.
+        Guard::Inaccessible { request, respondent: Respondent::Ignore, rejection } => {
+            assert_eq!(rejecting, None, "{chosen:?}");
+            assert_eq!(rejection.message, NAMES[NAMES.len() - 1], "{chosen:?}");
+            request
+        }
+        // Responses are handed back from the step which chose them, and endless reseeds are
+        // rejected once the request has been reseeded too many times.
+        Guard::Inaccessible { request, respondent, rejection } => {
+            let rejecting = rejecting.expect("only rejections which aren't ignored end a chain");
+            let (message, status) = match chosen[rejecting] {
+                5 => {
+                    let message =
+                        "The request was reseeded too many times, through again -> again.";
+                    (message, StatusCode::LOOP_DETECTED)
+                }
+                _ => { (NAMES[rejecting], StatusCode::FORBIDDEN) }
+            };
+            let responds = matches!(respondent, Respondent::Respond(_));
+            assert_eq!(rejection.message, message, "{chosen:?}");
+            assert_eq!(rejection.status, status, "{chosen:?}");
+            assert_eq!(responds, chosen[rejecting] != 2, "{chosen:?}");
+            request
+        }
+    };
+
+    assert_eq!(request.headers()["x-trail"], trail.join(","), "{chosen:?}");
+}
+
+proptest::proptest! {
+    #[test]
+    fn chains_hold_their_invariants_for_any_decisions(
+        chosen in proptest::array::uniform6(0..6usize),
+    ) {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime.block_on(check_chain(chosen));
+    }
+}
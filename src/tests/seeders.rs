@@ -0,0 +1,39 @@
+mod catch_panic;
+mod chaos;
+#[cfg(feature = "challenge")]
+mod challenge;
+mod circuit_breaker;
+mod concurrency;
+mod experiment;
+mod fingerprint;
+mod force_https;
+mod geoip;
+mod header_rewrite;
+mod html_transform;
+mod idempotency;
+mod load_shed;
+mod locale;
+mod maintenance;
+mod pipeline;
+#[cfg(feature = "plugins")]
+mod plugin;
+mod precondition;
+mod problem;
+#[cfg(feature = "serde_json")]
+mod redact;
+#[cfg(feature = "regex")]
+mod rewrite;
+#[cfg(feature = "serde_json")]
+mod schema;
+#[cfg(feature = "rhai")]
+mod script;
+#[cfg(feature = "signatures")]
+mod signature;
+mod tarpit;
+mod tee;
+mod tenant;
+#[cfg(feature = "otel")]
+mod trace;
+mod versioning;
+#[cfg(feature = "wasm")]
+mod wasm;
@@ -0,0 +1,64 @@
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::seeders::CatchPanicSeeder;
+
+struct Panicking;
+
+impl Seeder for Panicking {
+    async fn seed<'a>(&'a self, input: Guard<'a, HttpRequest<BoxBody>>) -> Guard<'a, HttpRequest<BoxBody>> {
+        if input.accessible() {
+            panic!("seeder exploded");
+        }
+
+        input
+    }
+}
+
+/// Panics while handling a rejection.
+struct PanickingOnRejections;
+
+impl Seeder for PanickingOnRejections {
+    async fn seed<'a>(&'a self, input: Guard<'a, HttpRequest<BoxBody>>) -> Guard<'a, HttpRequest<BoxBody>> {
+        if !input.accessible() {
+            panic!("rejection handler exploded");
+        }
+
+        input
+    }
+}
+
+#[tokio::test]
+async fn answers_panics_through_the_hook() {
+    let seeder = CatchPanicSeeder::new(Panicking).on_panic(|_, panic| {
+        HttpResponse::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(BoxBody::new(panic.message.clone().unwrap_or_default().into_bytes().into()))
+            .unwrap()
+    });
+
+    let mut request = HttpRequest::builder().uri("/").body(BoxBody::empty()).unwrap();
+    match seeder.seed(Guard::Accessible(&mut request)).await {
+        Guard::Inaccessible { respondent: Respondent::Respond(response), rejection, .. } => {
+            assert_eq!(rejection.status, StatusCode::INTERNAL_SERVER_ERROR);
+            assert_eq!(response.body().raw_bytes(), b"seeder exploded");
+        }
+        _ => panic!("panic was not caught"),
+    }
+}
+
+#[tokio::test]
+async fn catches_panics_while_handling_rejections() {
+    let seeder = CatchPanicSeeder::new(PanickingOnRejections);
+
+    let mut request = HttpRequest::builder().uri("/").body(BoxBody::empty()).unwrap();
+    let rejected = Guard::Inaccessible {
+        request: &mut request,
+        respondent: Respondent::Ignore,
+        rejection: Rejection::new(StatusCode::NOT_FOUND, "not_found", "Nothing is here."),
+    };
+
+    match seeder.seed(rejected).await {
+        Guard::Inaccessible { rejection, .. } => { assert_eq!(rejection.code, "panicked"); }
+        Guard::Accessible(_) => { panic!("the rejection was dropped"); }
+    }
+}
@@ -0,0 +1,155 @@
+use crate::core::seeder::{BoxBody, Guard, Respondent, Seeder};
+use crate::http::header::{LOCATION, SET_COOKIE};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::seeders::challenge::{Captcha, ChallengeVerifier, ProofOfWork, MAX_DIFFICULTY};
+use crate::seeders::ChallengeSeeder;
+
+fn request(uri: &str, cookie: Option<&str>) -> HttpRequest<BoxBody> {
+    let mut builder = HttpRequest::builder().uri(uri);
+    if let Some(cookie) = cookie {
+        builder = builder.header("cookie", cookie);
+    }
+
+    builder.body(BoxBody::empty()).unwrap()
+}
+
+async fn respond<V: ChallengeVerifier>(
+    seeder: &ChallengeSeeder<V>,
+    mut request: HttpRequest<BoxBody>,
+) -> Option<HttpResponse<BoxBody>> {
+    match seeder.seed(Guard::Accessible(&mut request)).await {
+        Guard::Inaccessible { respondent: Respondent::Respond(response), .. } => { Some(*response) }
+        _ => { None }
+    }
+}
+
+#[tokio::test]
+async fn issues_passes_for_solved_proofs_of_work() {
+    let pow = ProofOfWork::new("pow secret").difficulty(8);
+    let seeder = ChallengeSeeder::new(ProofOfWork::new("pow secret").difficulty(8), "pass secret")
+        .protect("/signup");
+
+    assert!(respond(&seeder, request("/", None)).await.is_none());
+
+    let challenged = respond(&seeder, request("/signup", None)).await.unwrap();
+    assert_eq!(challenged.status(), StatusCode::FORBIDDEN);
+
+    let body = std::str::from_utf8(challenged.body().raw_bytes()).unwrap();
+    let challenge = body.split('"').nth(3).unwrap();
+    assert!(body.contains(r#""verify":"/__grazie/challenge?return_to=%2Fsignup""#));
+
+    let wrong = format!("/__grazie/challenge?challenge={challenge}&nonce=x");
+    if !pow.solves(challenge, "x") {
+        let refused = respond(&seeder, request(&wrong, None)).await.unwrap();
+        assert_eq!(refused.status(), StatusCode::FORBIDDEN);
+    }
+
+    let nonce = pow.solve(challenge);
+    let solved = format!(
+        "/__grazie/challenge?challenge={challenge}&nonce={nonce}&return_to=%2Fsignup",
+    );
+    let passed = respond(&seeder, request(&solved, None)).await.unwrap();
+    assert_eq!(passed.status(), StatusCode::SEE_OTHER);
+    assert_eq!(passed.headers()[LOCATION], "/signup");
+
+    let pass = passed.headers()[SET_COOKIE].to_str().unwrap().split(';').next().unwrap().to_owned();
+    assert!(respond(&seeder, request("/signup", Some(&pass))).await.is_none());
+    let forged = request("/signup", Some("grazie_pass=9999999999.forged.00"));
+    assert!(respond(&seeder, forged).await.is_some());
+}
+
+#[tokio::test]
+async fn checks_captcha_tokens_externally() {
+    let page = r#"<form action="{verify}" method="post"></form>"#;
+    let captcha = Captcha::new(page, |token: String| async move { token == "valid" })
+        .field("h-captcha-response");
+    let seeder = ChallengeSeeder::new(captcha, "pass secret");
+
+    let challenged = respond(&seeder, request("/?a=1", None)).await.unwrap();
+    let page = br#"<form action="/__grazie/challenge?return_to=%2F%3Fa%3D1" method="post"></form>"#;
+    assert_eq!(challenged.body().raw_bytes(), page);
+
+    let mut submit = HttpRequest::builder()
+        .method("POST")
+        .uri("/__grazie/challenge?return_to=%2F%3Fa%3D1")
+        .body(BoxBody::new(b"h-captcha-response=valid".as_slice().into()))
+        .unwrap();
+
+    match seeder.seed(Guard::Accessible(&mut submit)).await {
+        Guard::Inaccessible { respondent: Respondent::Respond(response), .. } => {
+            assert_eq!(response.headers()[LOCATION], "/?a=1");
+        }
+        _ => panic!("captcha wasn't verified"),
+    }
+}
+
+#[tokio::test]
+async fn refuses_unsafe_return_paths() {
+    let captcha = Captcha::new("<form></form>", |token: String| async move { token == "valid" })
+        .field("h-captcha-response");
+    let seeder = ChallengeSeeder::new(captcha, "pass secret");
+
+    for return_to in ["%2F%0Aevil", "%2F%5Cevil.com", "%2F%2Fevil.com", "https%3A%2F%2Fevil.com"] {
+        let mut submit = HttpRequest::builder()
+            .method("POST")
+            .uri(format!("/__grazie/challenge?return_to={return_to}"))
+            .body(BoxBody::new(b"h-captcha-response=valid".as_slice().into()))
+            .unwrap();
+
+        match seeder.seed(Guard::Accessible(&mut submit)).await {
+            Guard::Inaccessible { respondent: Respondent::Respond(response), rejection, .. } => {
+                assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{return_to}");
+                assert_eq!(rejection.code, "invalid_return_to", "{return_to}");
+                assert!(response.headers().get(SET_COOKIE).is_none());
+            }
+            _ => panic!("unsafe return path was accepted"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn redeems_solved_challenges_once() {
+    let pow = ProofOfWork::new("secret").difficulty(8);
+    let seeder = ChallengeSeeder::new(ProofOfWork::new("secret").difficulty(8), "secret");
+
+    let challenged = respond(&seeder, request("/", None)).await.unwrap();
+    let body = std::str::from_utf8(challenged.body().raw_bytes()).unwrap().to_owned();
+    let challenge = body.split('"').nth(3).unwrap();
+
+    let nonce = pow.solve(challenge);
+    let solved = format!("/__grazie/challenge?challenge={challenge}&nonce={nonce}");
+    let passed = respond(&seeder, request(&solved, None)).await.unwrap();
+    assert_eq!(passed.status(), StatusCode::SEE_OTHER);
+
+    let replayed = respond(&seeder, request(&solved, None)).await.unwrap();
+    assert_eq!(replayed.status(), StatusCode::FORBIDDEN);
+    assert!(replayed.headers().get(SET_COOKIE).is_none());
+}
+
+#[tokio::test]
+async fn refuses_challenges_as_passes() {
+    let seeder = ChallengeSeeder::new(ProofOfWork::new("secret").difficulty(8), "secret");
+
+    let challenged = respond(&seeder, request("/", None)).await.unwrap();
+    let body = std::str::from_utf8(challenged.body().raw_bytes()).unwrap().to_owned();
+    let challenge = body.split('"').nth(3).unwrap();
+
+    let copied = format!("grazie_pass={challenge}");
+    let response = respond(&seeder, request("/", Some(&copied))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn caps_proof_of_work_difficulty() {
+    let pow = ProofOfWork::new("secret").difficulty(256);
+    let challenged = pow.challenge(&request("/", None), "/verify");
+    let body = std::str::from_utf8(challenged.body().raw_bytes()).unwrap();
+
+    assert!(body.contains(&format!(r#""difficulty":{MAX_DIFFICULTY},"#)));
+}
+
+#[test]
+#[should_panic(expected = "must have a valid name")]
+fn refuses_invalid_cookie_names() {
+    let _ = ChallengeSeeder::new(ProofOfWork::new("secret"), "secret").cookie("pass; Path=/admin");
+}
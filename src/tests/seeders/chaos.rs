@@ -0,0 +1,56 @@
+use crate::core::seeder::{BoxBody, Guard, Respondent, Seeder};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::seeders::chaos::{ChaosSeeder, DropConnection, Fault};
+
+fn get(path: &str) -> HttpRequest<BoxBody> {
+    HttpRequest::builder().uri(path).body(BoxBody::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn only_injects_once_enabled() {
+    let seeder = ChaosSeeder::new(1.0).fault(Fault::Error(StatusCode::INTERNAL_SERVER_ERROR), 1);
+
+    let mut request = get("/");
+    assert!(seeder.seed(Guard::Accessible(&mut request)).await.accessible());
+
+    seeder.handle().enable();
+    match seeder.seed(Guard::Accessible(&mut request)).await {
+        Guard::Inaccessible { rejection, .. } => { assert_eq!(rejection.code, "chaos_error"); }
+        _ => { panic!("an error should be injected"); }
+    }
+
+    assert_eq!(seeder.injected(), 1);
+}
+
+#[tokio::test]
+async fn samples_per_route() {
+    let seeder = ChaosSeeder::new(0.0).route("/flaky", 1.0).fault(Fault::Drop, 1);
+    seeder.handle().enable();
+
+    let mut request = get("/stable");
+    assert!(seeder.seed(Guard::Accessible(&mut request)).await.accessible());
+
+    let mut request = get("/flaky/orders");
+    match seeder.seed(Guard::Accessible(&mut request)).await {
+        Guard::Inaccessible { respondent: Respondent::Other(other), .. } => { assert!(other.downcast_ref::<DropConnection>().is_some()); }
+        _ => { panic!("the connection should be dropped"); }
+    }
+}
+
+#[tokio::test]
+async fn truncates_marked_responses() {
+    let seeder = ChaosSeeder::new(1.0).fault(Fault::Truncate, 1);
+    seeder.handle().enable();
+
+    let mut request = get("/");
+    assert!(seeder.seed(Guard::Accessible(&mut request)).await.accessible());
+
+    let mut response = HttpResponse::builder()
+        .header("content-length", "8")
+        .body(BoxBody::new(b"12345678".as_slice().into()))
+        .unwrap();
+    seeder.truncate_response(&request, &mut response);
+
+    assert_eq!(response.body().raw_bytes(), b"1234");
+    assert_eq!(response.headers()["content-length"], "8");
+}
@@ -0,0 +1,93 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::HttpRequest;
+use crate::seeders::circuit_breaker::CircuitRoute;
+use crate::seeders::circuit_breaker::CircuitState;
+use crate::seeders::CircuitBreakerSeeder;
+use std::time::Duration;
+
+fn request() -> HttpRequest<BoxBody> {
+    HttpRequest::builder().uri("/reports").body(BoxBody::empty()).unwrap()
+}
+
+#[tokio::test(start_paused = true)]
+async fn trips_and_recovers() {
+    let breaker = CircuitBreakerSeeder::new()
+        .window(4, 4)
+        .cool_down(Duration::from_millis(50))
+        .probes(1);
+
+    for success in [true, false, false, true] {
+        let mut req = request();
+        assert!(breaker.seed(Guard::Accessible(&mut req)).await.accessible());
+        breaker.record("/reports", success);
+    }
+
+    let metrics = breaker.metrics("/reports").unwrap();
+    assert_eq!(metrics.state, CircuitState::Open);
+    assert_eq!(metrics.trips, 1);
+
+    // After the cool-down, the next request is a half-open probe, and a second is rejected.
+    tokio::time::advance(Duration::from_millis(60)).await;
+    let mut probe = request();
+    assert!(breaker.seed(Guard::Accessible(&mut probe)).await.accessible());
+    let mut req = request();
+    assert!(breaker.seed(Guard::Accessible(&mut req)).await.inaccessible());
+
+    breaker.record("/reports", true);
+    assert_eq!(breaker.metrics("/reports").unwrap().state, CircuitState::Closed);
+}
+
+#[tokio::test(start_paused = true)]
+async fn gives_up_on_unreported_probes() {
+    let breaker = CircuitBreakerSeeder::new()
+        .window(1, 1)
+        .cool_down(Duration::from_millis(20))
+        .probes(1);
+
+    breaker.record("/reports", false);
+    tokio::time::advance(Duration::from_millis(30)).await;
+
+    // The probe's request is dropped without its outcome being reported.
+    let mut lost = request();
+    assert!(breaker.seed(Guard::Accessible(&mut lost)).await.accessible());
+    drop(lost);
+    let mut req = request();
+    assert!(breaker.seed(Guard::Accessible(&mut req)).await.inaccessible());
+
+    tokio::time::advance(Duration::from_millis(30)).await;
+    let mut probe = request();
+    assert!(breaker.seed(Guard::Accessible(&mut probe)).await.accessible());
+    breaker.record("/reports", true);
+    assert_eq!(breaker.metrics("/reports").unwrap().state, CircuitState::Closed);
+}
+
+#[tokio::test]
+async fn bounds_the_routes_tracked() {
+    let breaker = CircuitBreakerSeeder::new().window(1, 1).max_tracked(2);
+
+    breaker.record("/reports", false);
+    for path in ["/a", "/b", "/c"] {
+        let mut req = HttpRequest::builder().uri(path).body(BoxBody::empty()).unwrap();
+        assert!(breaker.seed(Guard::Accessible(&mut req)).await.accessible());
+        assert_eq!(req.extensions().get::<CircuitRoute>(), Some(&CircuitRoute(path.to_owned())));
+    }
+
+    // Closed circuits make way for new routes, but open ones are kept.
+    let tracked = breaker.snapshot();
+    assert_eq!(tracked.len(), 2);
+    assert_eq!(breaker.metrics("/reports").unwrap().state, CircuitState::Open);
+    assert!(breaker.metrics("/c").is_some());
+}
+
+#[test]
+fn never_trips_without_failures() {
+    let breaker = CircuitBreakerSeeder::new().window(2, 2).failure_ratio(0.0);
+
+    for _ in 0..4 {
+        breaker.record("/reports", true);
+    }
+    assert_eq!(breaker.metrics("/reports").unwrap().state, CircuitState::Closed);
+
+    breaker.record("/reports", false);
+    assert_eq!(breaker.metrics("/reports").unwrap().state, CircuitState::Open);
+}
@@ -0,0 +1,58 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::{HttpRequest, StatusCode};
+use crate::seeders::ConcurrencySeeder;
+use std::time::Duration;
+
+fn request(path: &str) -> HttpRequest<BoxBody> {
+    HttpRequest::builder().uri(path).body(BoxBody::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn queues_then_rejects_beyond_the_cap() {
+    let seeder = ConcurrencySeeder::new()
+        .route("/reports", 1)
+        .queue_wait(Duration::from_millis(20));
+
+    let mut first = request("/reports/annual");
+    assert!(seeder.seed(Guard::Accessible(&mut first)).await.accessible());
+
+    let mut other = request("/users");
+    assert!(seeder.seed(Guard::Accessible(&mut other)).await.accessible());
+
+    let mut second = request("/reports/monthly");
+    match seeder.seed(Guard::Accessible(&mut second)).await {
+        Guard::Inaccessible { rejection, .. } => { assert_eq!(rejection.status, StatusCode::SERVICE_UNAVAILABLE); }
+        _ => panic!("request was admitted beyond the cap"),
+    }
+    assert_eq!(seeder.metrics("/reports").unwrap().rejected, 1);
+
+    // Dropping the admitted request frees its slot for the next in line.
+    drop(first);
+    let mut third = request("/reports/daily");
+    assert!(seeder.seed(Guard::Accessible(&mut third)).await.accessible());
+    assert_eq!(seeder.metrics("/reports").unwrap().active, 1);
+}
+
+#[tokio::test]
+async fn cancelled_waits_leave_the_queue() {
+    let seeder = ConcurrencySeeder::new()
+        .route("/reports", 1)
+        .max_queue(1)
+        .queue_wait(Duration::from_secs(60));
+
+    let mut first = request("/reports/annual");
+    assert!(seeder.seed(Guard::Accessible(&mut first)).await.accessible());
+
+    // A client which disconnects while queued takes its place in the queue with it.
+    let mut cancelled = request("/reports/monthly");
+    assert!(tokio::time::timeout(Duration::from_millis(20), seeder.seed(Guard::Accessible(&mut cancelled))).await.is_err());
+    assert_eq!(seeder.metrics("/reports").unwrap().queued, 0);
+
+    let mut waiting = request("/reports/daily");
+    let (admitted, ()) = tokio::join!(seeder.seed(Guard::Accessible(&mut waiting)), async move {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        drop(first);
+    });
+    assert!(admitted.accessible());
+    assert_eq!(seeder.metrics("/reports").unwrap().rejected, 0);
+}
@@ -0,0 +1,73 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::{HttpRequest, HttpResponse};
+use crate::seeders::experiment::{Assignments, BucketBy, ExperimentSeeder};
+
+fn seeder() -> ExperimentSeeder {
+    ExperimentSeeder::new("checkout")
+        .variant("control", 1)
+        .variant("one_page", 1)
+        .bucket_by(BucketBy::Key(Box::new(|request| {
+            request.headers().get("x-user").and_then(|user| user.to_str().ok()).map(str::to_owned)
+        })))
+}
+
+async fn variant(seeder: &ExperimentSeeder, request: &mut HttpRequest<BoxBody>) -> String {
+    match seeder.seed(Guard::Accessible(request)).await {
+        Guard::Accessible(request) => {
+            request.extensions().get::<Assignments>().unwrap().variant("checkout").unwrap().to_owned()
+        }
+        _ => { panic!("experiments shouldn't reject requests"); }
+    }
+}
+
+#[tokio::test]
+async fn buckets_keys_deterministically() {
+    let seeder = seeder();
+    let mut seen = Vec::new();
+
+    for user in 0..50 {
+        let request = || HttpRequest::builder().header("x-user", user.to_string()).body(BoxBody::empty()).unwrap();
+        let first = variant(&seeder, &mut request()).await;
+        assert_eq!(variant(&seeder, &mut request()).await, first);
+        seen.push(first);
+    }
+
+    assert!(seen.iter().any(|variant| variant == "control"));
+    assert!(seen.iter().any(|variant| variant == "one_page"));
+    assert_eq!(seeder.metrics().iter().map(|(_, assigned)| assigned).sum::<u64>(), 100);
+}
+
+#[tokio::test]
+async fn persists_new_assignments_in_a_cookie() {
+    let seeder = seeder();
+
+    let mut request = HttpRequest::builder().header("x-user", "ada").body(BoxBody::empty()).unwrap();
+    let assigned = variant(&seeder, &mut request).await;
+
+    let mut response = HttpResponse::new(BoxBody::empty());
+    seeder.persist(&request, &mut response);
+    let cookie = response.headers()["set-cookie"].to_str().unwrap();
+    assert!(cookie.starts_with(&format!("grazie_exp_checkout={assigned};")));
+
+    // The cookie wins over the key, and doesn't need setting again.
+    let other = match assigned.as_str() {
+        "control" => { "one_page" }
+        _ => { "control" }
+    };
+    let mut request = HttpRequest::builder()
+        .header("x-user", "ada")
+        .header("cookie", format!("grazie_exp_checkout={other}"))
+        .body(BoxBody::empty())
+        .unwrap();
+    assert_eq!(variant(&seeder, &mut request).await, other);
+
+    let mut response = HttpResponse::new(BoxBody::empty());
+    seeder.persist(&request, &mut response);
+    assert!(response.headers().get("set-cookie").is_none());
+}
+
+#[test]
+#[should_panic(expected = "name must be a valid cookie name")]
+fn refuses_variants_which_cant_be_kept_in_cookies() {
+    let _ = ExperimentSeeder::new("checkout").variant("one page; Path=/admin", 1);
+}
@@ -0,0 +1,57 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::HttpRequest;
+use crate::seeders::fingerprint::{Fingerprint, TlsClientHello};
+use crate::seeders::FingerprintSeeder;
+
+fn request(headers: &[(&str, &str)]) -> HttpRequest<BoxBody> {
+    let mut builder = HttpRequest::builder().uri("/");
+    for (name, value) in headers {
+        builder = builder.header(*name, *value);
+    }
+
+    builder.body(BoxBody::empty()).unwrap()
+}
+
+const BROWSER: &[(&str, &str)] = &[
+    ("user-agent", "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0"),
+    ("accept", "text/html"),
+    ("accept-language", "en-GB,en;q=0.5"),
+    ("accept-encoding", "gzip, deflate, br"),
+];
+
+#[test]
+fn scores_automation_higher_than_browsers() {
+    let seeder = FingerprintSeeder::new();
+
+    let browser = seeder.fingerprint(&request(BROWSER));
+    assert_eq!(browser.score, 0);
+    assert_eq!(browser, seeder.fingerprint(&request(BROWSER)));
+
+    let curl = seeder.fingerprint(&request(&[("user-agent", "curl/8.5.0"), ("accept", "*/*")]));
+    assert_eq!(curl.score, 85);
+    assert_ne!(curl.id, browser.id);
+
+    let mut reordered = BROWSER.to_vec();
+    reordered.swap(1, 2);
+    assert_ne!(seeder.fingerprint(&request(&reordered)).id, browser.id);
+}
+
+#[tokio::test]
+async fn includes_tls_parameters_and_blocks_above_the_threshold() {
+    let seeder = FingerprintSeeder::new().block_above(50);
+
+    let mut browser = request(BROWSER);
+    browser.extensions_mut().insert(TlsClientHello {
+        version: 771,
+        ciphers: vec![0x1a1a, 4865, 4866],
+        extensions: vec![0, 23, 65281],
+        curves: vec![29, 23],
+        point_formats: vec![0],
+    });
+
+    assert!(seeder.seed(Guard::Accessible(&mut browser)).await.accessible());
+    assert_eq!(browser.extensions().get::<Fingerprint>().unwrap().ja3.as_deref(), Some("771,4865-4866,0-23-65281,29-23,0"));
+
+    let mut bot = request(&[("user-agent", "python-requests/2.32")]);
+    assert!(!seeder.seed(Guard::Accessible(&mut bot)).await.accessible());
+}
@@ -0,0 +1,45 @@
+use crate::core::seeder::{BoxBody, Guard, Respondent, Seeder};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::seeders::force_https::Hsts;
+use crate::seeders::ForceHttpsSeeder;
+use std::time::Duration;
+
+fn request(uri: &str, host: &str) -> HttpRequest<BoxBody> {
+    HttpRequest::builder().uri(uri).header("host", host).body(BoxBody::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn redirects_plaintext_requests() {
+    let seeder = ForceHttpsSeeder::new().https_port(8443).exempt("/.well-known/acme-challenge/");
+
+    let mut req = request("/login?next=%2F", "example.com:8080");
+    match seeder.seed(Guard::Accessible(&mut req)).await {
+        Guard::Inaccessible { respondent: Respondent::Respond(response), .. } => {
+            assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+            assert_eq!(response.headers()["location"], "https://example.com:8443/login?next=%2F");
+        }
+        _ => panic!("plaintext request was not redirected"),
+    }
+
+    let mut req = request("/.well-known/acme-challenge/token", "example.com");
+    assert!(seeder.seed(Guard::Accessible(&mut req)).await.accessible());
+}
+
+#[tokio::test]
+async fn attaches_hsts_to_secure_responses() {
+    let seeder = ForceHttpsSeeder::new()
+        .trust_forwarded(true)
+        .hsts(Hsts { include_subdomains: true, ..Hsts::new(Duration::from_secs(31536000)) });
+
+    let mut req = HttpRequest::builder()
+        .uri("/")
+        .header("host", "example.com")
+        .header("x-forwarded-proto", "https")
+        .body(BoxBody::empty())
+        .unwrap();
+    let req = seeder.seed(Guard::Accessible(&mut req)).await.unwrap();
+
+    let mut response = HttpResponse::new(BoxBody::empty());
+    seeder.secure_response(req, &mut response);
+    assert_eq!(response.headers()["strict-transport-security"], "max-age=31536000; includeSubDomains");
+}
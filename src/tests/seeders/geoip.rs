@@ -0,0 +1,72 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::HttpRequest;
+use crate::seeders::geoip::{Geo, GeoLookup, UNKNOWN_COUNTRY};
+use crate::seeders::GeoIpSeeder;
+use crate::server::PeerAddr;
+use std::net::{IpAddr, SocketAddr};
+
+struct Fixed;
+
+impl GeoLookup for Fixed {
+    fn lookup(&self, ip: IpAddr) -> Option<Geo> {
+        let country = match ip.to_string().as_str() {
+            "192.0.2.1" => { "NZ" }
+            "198.51.100.1" => { "KP" }
+            _ => { return None; }
+        };
+
+        Some(Geo {
+            ip: Some(ip),
+            country: Some(country.to_owned()),
+            asn: Some(64496),
+            organization: None,
+        })
+    }
+}
+
+fn request(peer: &str, forwarded: Option<&str>) -> HttpRequest<BoxBody> {
+    let mut builder = HttpRequest::builder().uri("/");
+    if let Some(forwarded) = forwarded {
+        builder = builder.header("x-forwarded-for", forwarded);
+    }
+
+    let mut request = builder.body(BoxBody::empty()).unwrap();
+    request.extensions_mut().insert(PeerAddr(SocketAddr::new(peer.parse().unwrap(), 443)));
+    request
+}
+
+#[test]
+fn resolves_client_ips_through_trusted_proxies() {
+    let seeder = GeoIpSeeder::new(Fixed).trust_proxy("10.0.0.1".parse().unwrap());
+
+    let direct = request("192.0.2.1", Some("198.51.100.1"));
+    assert_eq!(seeder.client_ip(&direct), "192.0.2.1".parse().ok());
+
+    let proxied = request("10.0.0.1", Some("198.51.100.1, 192.0.2.1"));
+    assert_eq!(seeder.client_ip(&proxied), "192.0.2.1".parse().ok());
+}
+
+#[tokio::test]
+async fn restricts_and_counts_countries() {
+    let seeder = GeoIpSeeder::new(Fixed).deny_countries(["kp"]);
+
+    let mut allowed = request("192.0.2.1", None);
+    assert!(seeder.seed(Guard::Accessible(&mut allowed)).await.accessible());
+    assert_eq!(allowed.extensions().get::<Geo>().unwrap().country.as_deref(), Some("NZ"));
+
+    let mut denied = request("198.51.100.1", None);
+    match seeder.seed(Guard::Accessible(&mut denied)).await {
+        Guard::Inaccessible { rejection, .. } => { assert_eq!(rejection.code, "country_blocked"); }
+        _ => panic!("denied country was accepted"),
+    }
+
+    let mut unknown = request("203.0.113.1", None);
+    assert!(seeder.seed(Guard::Accessible(&mut unknown)).await.accessible());
+
+    let allow_list = GeoIpSeeder::new(Fixed).allow_countries(["NZ"]);
+    let mut unknown = request("203.0.113.1", None);
+    assert!(!allow_list.seed(Guard::Accessible(&mut unknown)).await.accessible());
+
+    let metrics = seeder.metrics();
+    assert_eq!((metrics["NZ"], metrics["KP"], metrics[UNKNOWN_COUNTRY]), (1, 1, 1));
+}
@@ -0,0 +1,47 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::header::{HeaderName, HeaderValue};
+use crate::http::HttpRequest;
+use crate::seeders::header_rewrite::HeaderRule;
+use crate::seeders::HeaderRewriteSeeder;
+
+#[tokio::test]
+async fn applies_rules_in_order() {
+    let seeder = HeaderRewriteSeeder::new()
+        .request(HeaderRule::Rename(HeaderName::from_static("x-real-ip"), HeaderName::from_static("x-client-ip")))
+        .request(HeaderRule::Copy(HeaderName::from_static("x-client-ip"), HeaderName::from_static("x-audit-ip")))
+        .request(HeaderRule::Add(HeaderName::from_static("via"), HeaderValue::from_static("grazie")))
+        .request(HeaderRule::SetIfMissing(HeaderName::from_static("via"), HeaderValue::from_static("other")))
+        .request(HeaderRule::Remove(HeaderName::from_static("cookie")));
+
+    let mut request = HttpRequest::builder()
+        .header("x-real-ip", "10.0.0.1")
+        .header("via", "1.1 edge")
+        .header("cookie", "session=1")
+        .body(BoxBody::empty())
+        .unwrap();
+
+    let request = seeder.seed(Guard::Accessible(&mut request)).await.unwrap();
+    let headers = request.headers();
+
+    assert!(!headers.contains_key("x-real-ip"));
+    assert!(!headers.contains_key("cookie"));
+    assert_eq!(headers["x-client-ip"], "10.0.0.1");
+    assert_eq!(headers["x-audit-ip"], "10.0.0.1");
+    assert_eq!(headers.get_all("via").iter().count(), 2);
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn replaces_with_captures() {
+    let rule = HeaderRule::Replace(
+        HeaderName::from_static("location"),
+        regex::Regex::new(r"^http://internal:8080(/.*)$").unwrap(),
+        "https://example.com$1".to_owned(),
+    );
+
+    let mut headers = crate::http::header::HeaderMap::new();
+    headers.insert("location", HeaderValue::from_static("http://internal:8080/a?b=c"));
+    rule.apply(&mut headers);
+
+    assert_eq!(headers["location"], "https://example.com/a?b=c");
+}
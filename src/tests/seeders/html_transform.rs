@@ -0,0 +1,55 @@
+use crate::seeders::html_transform::{HtmlRewriter, Position};
+
+const SNIPPET: &[u8] = b"<script src=\"/reload.js\"></script>";
+
+#[test]
+fn injects_across_chunk_boundaries() {
+    let mut rewriter = HtmlRewriter::new()
+        .inject(Position::BeforeClose("body".to_owned()), SNIPPET)
+        .inject(Position::AfterOpen("head".to_owned()), b"<meta charset=\"utf-8\">".as_slice())
+        .nonce("abc");
+
+    let document = b"<html><HEAD><title>a < b</title></HEAD><body><script nonce=\"{{nonce}}\">if (a</b) {}</script><p>hi</p></BODY></html>";
+    let mut out = Vec::new();
+    for chunk in document.chunks(3) {
+        out.extend(rewriter.feed(chunk));
+    }
+    out.extend(rewriter.finish());
+
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "<html><HEAD><meta charset=\"utf-8\"><title>a < b</title></HEAD><body>\
+         <script nonce=\"abc\">if (a</b) {}</script><p>hi</p>\
+         <script src=\"/reload.js\" nonce=\"abc\"></script></BODY></html>"
+    );
+}
+
+#[test]
+fn appends_missing_close_injections() {
+    let mut rewriter = HtmlRewriter::new().inject(Position::BeforeClose("body".to_owned()), SNIPPET);
+
+    let out = rewriter.rewrite(b"<p title='a>b'>fragment<!-- </body> -->");
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "<p title='a>b'>fragment<!-- </body> --><script src=\"/reload.js\"></script>"
+    );
+}
+
+#[test]
+fn only_nonces_marked_and_injected_tags() {
+    let snippet = b"<style nonce=\"x\"></style><style/>";
+    let mut rewriter = HtmlRewriter::new()
+        .inject(Position::AfterOpen("body".to_owned()), snippet.as_slice())
+        .nonce("abc");
+
+    let out = rewriter.rewrite(
+        b"<body><script>evil()</script><script nonce=\"other\"></script>\
+          <script src=/a.js nonce='{{nonce}}'></script><style NONCE=\"{{nonce}}\"></style></body>",
+    );
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "<body><style nonce=\"x\"></style><style nonce=\"abc\"/>\
+         <script>evil()</script><script nonce=\"other\"></script>\
+         <script src=/a.js nonce='abc'></script><style NONCE=\"abc\"></style></body>"
+    );
+}
@@ -0,0 +1,102 @@
+use crate::core::seeder::{BoxBody, Guard, Respondent, Seeder};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::seeders::idempotency::{MemoryIdempotencyStore, StoredResponse};
+use crate::seeders::IdempotencySeeder;
+use crate::server::PeerAddr;
+
+fn request() -> HttpRequest<BoxBody> {
+    sent_by("127.0.0.1:4000", b"amount=100")
+}
+
+fn sent_by(peer: &str, body: &'static [u8]) -> HttpRequest<BoxBody> {
+    let mut request = HttpRequest::builder()
+        .method("POST")
+        .uri("/charges")
+        .header("idempotency-key", "abc")
+        .body(BoxBody::new(body.into()))
+        .unwrap();
+    request.extensions_mut().insert(PeerAddr(peer.parse().unwrap()));
+
+    request
+}
+
+#[tokio::test]
+async fn rejects_concurrent_and_replays_completed() {
+    let seeder = IdempotencySeeder::new(MemoryIdempotencyStore::new());
+
+    let mut first = request();
+    assert!(seeder.seed(Guard::Accessible(&mut first)).await.accessible());
+
+    let mut retry = request();
+    match seeder.seed(Guard::Accessible(&mut retry)).await {
+        Guard::Inaccessible { rejection, .. } => assert_eq!(rejection.status, StatusCode::CONFLICT),
+        Guard::Accessible(_) => panic!("concurrent duplicate was accepted"),
+    }
+
+    let response = HttpResponse::builder()
+        .status(StatusCode::CREATED)
+        .body(BoxBody::new(b"ch_1".as_slice().into()))
+        .unwrap();
+    seeder.complete(&first, &response).await;
+
+    let mut retry = request();
+    match seeder.seed(Guard::Accessible(&mut retry)).await {
+        Guard::Inaccessible { respondent: Respondent::Respond(replayed), .. } => {
+            assert_eq!(replayed.status(), StatusCode::CREATED);
+            assert_eq!(replayed.body().raw_bytes(), b"ch_1");
+            assert_eq!(replayed.headers()["idempotent-replayed"], "true");
+        }
+        _ => panic!("completed response was not replayed"),
+    }
+}
+
+#[tokio::test]
+async fn scopes_keys_and_refuses_other_bodies() {
+    let seeder = IdempotencySeeder::new(MemoryIdempotencyStore::new());
+
+    let mut first = request();
+    assert!(seeder.seed(Guard::Accessible(&mut first)).await.accessible());
+    seeder.complete(&first, &HttpResponse::new(BoxBody::empty())).await;
+
+    let mut other_client = sent_by("10.0.0.1:4000", b"amount=100");
+    assert!(seeder.seed(Guard::Accessible(&mut other_client)).await.accessible());
+
+    let mut other_body = sent_by("127.0.0.1:5000", b"amount=999");
+    match seeder.seed(Guard::Accessible(&mut other_body)).await {
+        Guard::Inaccessible { rejection, .. } => {
+            assert_eq!(rejection.status, StatusCode::UNPROCESSABLE_ENTITY);
+        }
+        Guard::Accessible(_) => panic!("a different body was accepted"),
+    }
+
+    let mut unscoped = HttpRequest::builder()
+        .method("POST")
+        .uri("/charges")
+        .header("idempotency-key", "abc")
+        .body(BoxBody::empty())
+        .unwrap();
+    assert!(seeder.seed(Guard::Accessible(&mut unscoped)).await.accessible());
+    assert!(unscoped.extensions().get::<crate::seeders::idempotency::IdempotencyKey>().is_none());
+}
+
+#[test]
+fn stored_responses_round_trip_through_bytes() {
+    let response = HttpResponse::builder()
+        .status(StatusCode::CREATED)
+        .header("set-cookie", "a=1")
+        .header("set-cookie", "b=2")
+        .header("location", "/charges/ch_1")
+        .body(BoxBody::new(b"ch_1".as_slice().into()))
+        .unwrap();
+
+    let bytes = StoredResponse::from_response(&request(), &response).to_bytes();
+    let decoded = StoredResponse::from_bytes(&bytes).unwrap();
+    assert_eq!(decoded.status, StatusCode::CREATED);
+    assert_eq!(decoded.headers.get_all("set-cookie").iter().collect::<Vec<_>>(), ["a=1", "b=2"]);
+    assert_eq!(decoded.headers["location"], "/charges/ch_1");
+    assert_eq!(&*decoded.body, b"ch_1");
+    assert_eq!(decoded.request_hash, crate::util::fnv1a(b"amount=100"));
+
+    assert!(StoredResponse::from_bytes(&bytes[..9]).is_none());
+    assert!(StoredResponse::from_bytes(b"\x00").is_none());
+}
@@ -0,0 +1,74 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::HttpRequest;
+use crate::seeders::load_shed::{LoadShedSeeder, Priority};
+use std::time::Duration;
+
+fn get(path: &str) -> HttpRequest<BoxBody> {
+    HttpRequest::builder().uri(path).body(BoxBody::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn sheds_low_priority_first() {
+    let seeder = LoadShedSeeder::new()
+        .max_in_flight(10)
+        .priority("/reports", Priority::Low)
+        .priority("/healthz", Priority::Critical);
+
+    let mut admitted = Vec::new();
+    for _ in 0..7 {
+        let mut request = get("/orders");
+        assert!(seeder.seed(Guard::Accessible(&mut request)).await.accessible());
+        admitted.push(request);
+    }
+
+    let mut request = get("/reports/daily");
+    match seeder.seed(Guard::Accessible(&mut request)).await {
+        Guard::Inaccessible { rejection, .. } => { assert_eq!(rejection.code, "load_shed"); }
+        _ => { panic!("low priority requests should be shed"); }
+    }
+
+    let mut request = get("/orders");
+    assert!(seeder.seed(Guard::Accessible(&mut request)).await.accessible());
+    admitted.push(request);
+
+    let mut request = get("/healthz");
+    assert!(seeder.seed(Guard::Accessible(&mut request)).await.accessible());
+
+    assert_eq!(seeder.shed(), 1);
+}
+
+#[tokio::test]
+async fn releases_dropped_requests() {
+    let seeder = LoadShedSeeder::new().max_in_flight(1);
+
+    let mut request = get("/");
+    assert!(seeder.seed(Guard::Accessible(&mut request)).await.accessible());
+    assert_eq!(seeder.pressure().in_flight, 1);
+
+    let mut next = get("/");
+    assert!(!seeder.seed(Guard::Accessible(&mut next)).await.accessible());
+
+    drop(request);
+    assert_eq!(seeder.pressure().in_flight, 0);
+    assert!(seeder.seed(Guard::Accessible(&mut next)).await.accessible());
+}
+
+#[tokio::test]
+async fn watches_memory() {
+    let seeder = LoadShedSeeder::new()
+        .max_memory(1000)
+        .memory_probe(|| Some(900))
+        .priority("/admin", Priority::High);
+
+    let monitor = seeder.monitor(Duration::from_millis(5));
+    while seeder.pressure().memory.is_none() {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    monitor.abort();
+
+    let mut request = get("/orders");
+    assert!(!seeder.seed(Guard::Accessible(&mut request)).await.accessible());
+
+    let mut request = get("/admin/users");
+    assert!(seeder.seed(Guard::Accessible(&mut request)).await.accessible());
+}
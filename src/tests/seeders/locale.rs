@@ -0,0 +1,53 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::HttpRequest;
+use crate::i18n::Messages;
+use crate::seeders::locale::{parse_accept_language, Locale};
+use crate::seeders::LocaleSeeder;
+
+fn request(uri: &str, headers: &[(&str, &str)]) -> HttpRequest<BoxBody> {
+    let mut builder = HttpRequest::builder().uri(uri);
+    for (name, value) in headers {
+        builder = builder.header(*name, *value);
+    }
+
+    builder.body(BoxBody::empty()).unwrap()
+}
+
+#[test]
+fn parses_accept_language_by_quality() {
+    assert_eq!(
+        parse_accept_language("fr-CH, fr;q=0.9, en;q=0.8, de;q=0, *;q=0.5"),
+        vec!["fr-CH", "fr", "en", "*"],
+    );
+}
+
+#[test]
+fn negotiates_locales() {
+    let seeder = LocaleSeeder::new("en-US", ["fr", "pt-BR"]);
+    let negotiate = |uri: &str, headers: &[(&str, &str)]| seeder.negotiate(&request(uri, headers));
+
+    assert_eq!(negotiate("/", &[("accept-language", "fr-CA, en;q=0.5")]), "fr");
+    assert_eq!(negotiate("/", &[("accept-language", "pt;q=0.9, de")]), "pt-BR");
+    assert_eq!(negotiate("/", &[("accept-language", "ja")]), "en-US");
+    assert_eq!(negotiate("/", &[("accept-language", "en"), ("cookie", "locale=fr")]), "fr");
+    assert_eq!(negotiate("/?lang=PT-br", &[("cookie", "locale=fr")]), "pt-BR");
+    assert_eq!(negotiate("/?lang=xx", &[("accept-language", "en-GB")]), "en-US");
+}
+
+#[tokio::test]
+async fn localizes_messages_for_requests() {
+    let seeder = LocaleSeeder::new("en", ["de"]);
+    let messages = Messages::new("en")
+        .add("en", "welcome", "Welcome, {name}.")
+        .load("de", "# German\nwelcome = Willkommen, {name}.").unwrap();
+
+    let mut german = request("/", &[("accept-language", "de-DE")]);
+    assert!(seeder.seed(Guard::Accessible(&mut german)).await.accessible());
+    assert_eq!(german.extensions().get::<Locale>(), Some(&Locale("de".to_owned())));
+
+    let localized = messages.for_request(&german);
+    assert_eq!(localized.format("welcome", &[("name", "Ada")]), "Willkommen, Ada.");
+    assert_eq!(localized.get("missing"), "missing");
+
+    assert!(Messages::new("en").load("en", "valid = yes\nnot a pair").is_err());
+}
@@ -0,0 +1,36 @@
+use crate::core::seeder::{BoxBody, Guard, Respondent, Seeder};
+use crate::http::{HttpRequest, StatusCode};
+use crate::seeders::MaintenanceSeeder;
+use std::time::Duration;
+
+fn request(path: &str) -> HttpRequest<BoxBody> {
+    HttpRequest::builder().uri(path).body(BoxBody::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn rejects_only_while_enabled() {
+    let seeder = MaintenanceSeeder::new()
+        .allow("/healthz")
+        .retry_after(Duration::from_secs(120));
+    let handle = seeder.handle();
+
+    let mut req = request("/orders");
+    assert!(seeder.seed(Guard::Accessible(&mut req)).await.accessible());
+
+    assert!(handle.toggle());
+    match seeder.seed(Guard::Accessible(&mut req)).await {
+        Guard::Inaccessible { respondent: Respondent::Respond(response), rejection, .. } => {
+            assert_eq!(rejection.status, StatusCode::SERVICE_UNAVAILABLE);
+            assert_eq!(rejection.code, "maintenance");
+            assert_eq!(response.headers()["retry-after"], "120");
+        }
+        _ => panic!("request was not rejected during maintenance"),
+    }
+
+    let mut req = request("/healthz");
+    assert!(seeder.seed(Guard::Accessible(&mut req)).await.accessible());
+
+    handle.disable();
+    let mut req = request("/orders");
+    assert!(seeder.seed(Guard::Accessible(&mut req)).await.accessible());
+}
@@ -0,0 +1,62 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::seeders::pipeline::{Decision, PipelineTrace, TraceLog, Traced, X_GRAZIE_TRACE};
+use crate::seeders::MaintenanceSeeder;
+
+/// Uppercases the request body.
+struct Shout;
+
+impl Seeder for Shout {
+    async fn seed<'a>(&'a self, input: Guard<'a, HttpRequest<BoxBody>>) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        let shouted = request.body().raw_bytes().to_ascii_uppercase();
+        request.body_mut().close(shouted);
+        Guard::Accessible(request)
+    }
+}
+
+#[tokio::test]
+async fn traces_each_seeder_and_reports_back() {
+    let maintenance = MaintenanceSeeder::new();
+    let handle = maintenance.handle();
+    let chain = Traced::new("shout", Shout)
+        .then(Traced::new("maintenance", maintenance))
+        .then(Traced::new("shout-again", Shout));
+    let log = TraceLog::new(1);
+
+    let mut request = HttpRequest::builder().uri("/orders").header(X_GRAZIE_TRACE, "1").body(BoxBody::new(b"hi".to_vec().into_boxed_slice())).unwrap();
+    chain.seed(Guard::Accessible(&mut request)).await;
+
+    let trace = request.extensions().get::<PipelineTrace>().unwrap();
+    let decisions: Vec<&Decision> = trace.steps.iter().map(|step| &step.decision).collect();
+    assert_eq!(decisions, [&Decision::Accepted, &Decision::Accepted, &Decision::Accepted]);
+    assert_eq!(trace.steps.iter().map(|step| step.body_changed).collect::<Vec<_>>(), [true, false, false]);
+
+    let mut response = HttpResponse::new(BoxBody::empty());
+    log.finish(&request, &mut response);
+    let header = response.headers()[X_GRAZIE_TRACE].to_str().unwrap().to_owned();
+    assert!(header.starts_with("shout accepted "), "{header}");
+    assert!(header.contains("body-changed, maintenance accepted"), "{header}");
+
+    handle.enable();
+    let mut request = HttpRequest::builder().uri("/orders").body(BoxBody::empty()).unwrap();
+    chain.seed(Guard::Accessible(&mut request)).await;
+
+    let trace = request.extensions().get::<PipelineTrace>().unwrap();
+    assert_eq!(trace.steps[1].decision, Decision::Rejected(StatusCode::SERVICE_UNAVAILABLE, "maintenance".to_owned()));
+    assert_eq!(trace.steps[2].decision, Decision::Skipped);
+
+    // Only requests which ask for their trace get it back, but every trace is logged.
+    let mut response = HttpResponse::new(BoxBody::empty());
+    log.finish(&request, &mut response);
+    assert!(!response.headers().contains_key(X_GRAZIE_TRACE));
+
+    let recent = log.recent();
+    assert_eq!(recent.len(), 1);
+    assert_eq!(recent[0].request, "GET /orders");
+    assert_eq!(recent[0].trace.steps.len(), 3);
+}
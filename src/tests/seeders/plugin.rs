@@ -0,0 +1,91 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::{HttpRequest, StatusCode};
+use crate::seeders::plugin::PluginError;
+use crate::seeders::PluginSeeder;
+
+/// Rejects `DELETE` requests, and requests without an `x-tenant` header.
+unsafe extern "C" fn on_request(head: *const u8, len: usize) -> u16 {
+    let head = std::str::from_utf8(unsafe { std::slice::from_raw_parts(head, len) }).unwrap();
+
+    match (head.starts_with("DELETE "), head.lines().any(|line| line.starts_with("x-tenant: "))) {
+        (true, _) => { 405 }
+        (false, true) => { 0 }
+        (false, false) => { 400 }
+    }
+}
+
+/// Answers every request with something which isn't a status code.
+unsafe extern "C" fn broken(_: *const u8, _: usize) -> u16 {
+    42
+}
+
+fn request(method: &str, tenant: Option<&str>) -> HttpRequest<BoxBody> {
+    let request = HttpRequest::builder().method(method).uri("/orders").body(BoxBody::empty());
+    let mut request = request.unwrap();
+    if let Some(tenant) = tenant {
+        request.headers_mut().insert("x-tenant", tenant.parse().unwrap());
+    }
+
+    request
+}
+
+/// Seeds a request, returning its rejection's status.
+async fn rejected(seeder: &PluginSeeder, mut request: HttpRequest<BoxBody>) -> Option<StatusCode> {
+    match seeder.seed(Guard::Accessible(&mut request)).await {
+        Guard::Accessible(_) => { None }
+        Guard::Inaccessible { rejection, .. } => { Some(rejection.status) }
+    }
+}
+
+#[tokio::test]
+async fn plugins_decide_what_happens_to_requests() {
+    let seeder = unsafe { PluginSeeder::from_fn(on_request) };
+
+    assert_eq!(rejected(&seeder, request("GET", Some("acme"))).await, None);
+    assert_eq!(rejected(&seeder, request("GET", None)).await, Some(StatusCode::BAD_REQUEST));
+    let deleted = rejected(&seeder, request("DELETE", Some("acme"))).await;
+    assert_eq!(deleted, Some(StatusCode::METHOD_NOT_ALLOWED));
+
+    let broken = unsafe { PluginSeeder::from_fn(broken) };
+    assert_eq!(rejected(&broken, request("GET", None)).await, Some(StatusCode::INTERNAL_SERVER_ERROR));
+}
+
+#[test]
+fn refuses_missing_libraries() {
+    let error = unsafe { PluginSeeder::load("/nonexistent/libplugin.so") }.err().unwrap();
+    assert!(matches!(error, PluginError::Load(_)));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+#[ignore = "needs a C compiler as `cc`"]
+async fn loads_plugins_from_shared_libraries() {
+    let directory = std::env::temp_dir().join(format!("grazie-plugin-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+
+    let build = |name: &str, abi: u32| {
+        let source = directory.join(format!("{name}.c"));
+        let library = directory.join(format!("lib{name}.so"));
+        std::fs::write(&source, format!(
+            "#include <stddef.h>\n#include <stdint.h>\n\
+            uint32_t grazie_plugin_abi(void) {{ return {abi}; }}\n\
+            uint16_t grazie_on_request(const uint8_t *head, size_t len) {{\n\
+                return head[0] == 'D' ? 405 : 0;\n\
+            }}\n"
+        )).unwrap();
+
+        let mut cc = std::process::Command::new("cc");
+        cc.args(["-shared", "-fPIC", "-o"]).arg(&library).arg(&source);
+        assert!(cc.status().unwrap().success());
+        library
+    };
+
+    let seeder = unsafe { PluginSeeder::load(build("deny_deletes", 1)) }.unwrap();
+    assert_eq!(rejected(&seeder, request("GET", None)).await, None);
+    assert_eq!(rejected(&seeder, request("DELETE", None)).await, Some(StatusCode::METHOD_NOT_ALLOWED));
+
+    let error = unsafe { PluginSeeder::load(build("future", 2)) }.err().unwrap();
+    assert!(matches!(error, PluginError::Abi(2)));
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
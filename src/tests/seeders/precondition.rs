@@ -0,0 +1,55 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::{HttpRequest, StatusCode};
+use crate::seeders::precondition::{check_if_match, EntityTag, PreconditionSeeder};
+
+fn put(if_match: Option<&str>) -> HttpRequest<BoxBody> {
+    let mut builder = HttpRequest::builder().method("PUT").uri("/orders/1");
+    if let Some(if_match) = if_match {
+        builder = builder.header("if-match", if_match);
+    }
+
+    builder.body(BoxBody::empty()).unwrap()
+}
+
+#[test]
+fn parses_entity_tags() {
+    assert_eq!(EntityTag::parse("\"abc\""), Some(EntityTag::strong("abc")));
+    assert_eq!(EntityTag::parse("W/\"abc\""), Some(EntityTag::weak("abc")));
+    assert_eq!(EntityTag::parse("abc"), None);
+    assert_eq!(EntityTag::weak("abc").to_string(), "W/\"abc\"");
+    assert!(!EntityTag::weak("abc").strong_eq(&EntityTag::strong("abc")));
+}
+
+#[test]
+fn checks_if_match() {
+    let current = EntityTag::strong("v2");
+
+    assert_eq!(check_if_match(&put(None), Some(&current)).unwrap_err().status, StatusCode::PRECONDITION_REQUIRED);
+    assert_eq!(check_if_match(&put(Some("\"v1\"")), Some(&current)).unwrap_err().status, StatusCode::PRECONDITION_FAILED);
+    assert!(check_if_match(&put(Some("\"v1\", \"v2\"")), Some(&current)).is_ok());
+    assert!(check_if_match(&put(Some("*")), Some(&current)).is_ok());
+    assert!(check_if_match(&put(Some("*")), None).is_err());
+}
+
+#[tokio::test]
+async fn guards_mutating_requests() {
+    let seeder = PreconditionSeeder::new(|_: &HttpRequest<BoxBody>| Some(EntityTag::strong("v2")));
+
+    let mut request = HttpRequest::builder().uri("/orders/1").body(BoxBody::empty()).unwrap();
+    assert!(seeder.seed(Guard::Accessible(&mut request)).await.accessible());
+
+    let mut request = put(Some("\"v2\""));
+    assert!(seeder.seed(Guard::Accessible(&mut request)).await.accessible());
+
+    let mut request = put(Some("\"v1\""));
+    match seeder.seed(Guard::Accessible(&mut request)).await {
+        Guard::Inaccessible { rejection, .. } => { assert_eq!(rejection.code, "precondition_failed"); }
+        _ => { panic!("stale tags should be rejected"); }
+    }
+
+    let mut request = put(None);
+    match seeder.seed(Guard::Accessible(&mut request)).await {
+        Guard::Inaccessible { rejection, .. } => { assert_eq!(rejection.code, "precondition_required"); }
+        _ => { panic!("unconditional requests should be rejected"); }
+    }
+}
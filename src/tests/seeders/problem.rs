@@ -0,0 +1,42 @@
+use crate::core::seeder::{BoxBody, Guard, Respondent, Seeder};
+use crate::http::{HttpRequest, StatusCode};
+use crate::seeders::problem::{Problem, PROBLEM_JSON};
+use crate::seeders::{MaintenanceSeeder, ProblemSeeder};
+use std::time::Duration;
+
+#[test]
+fn renders_problem_documents() {
+    let problem = Problem::new(StatusCode::NOT_FOUND)
+        .type_uri("https://example.com/errors/missing")
+        .detail("No \"widget\" here.\n");
+
+    assert_eq!(
+        problem.to_json(),
+        "{\"type\":\"https://example.com/errors/missing\",\"title\":\"Not Found\",\"status\":404,\
+         \"detail\":\"No \\\"widget\\\" here.\\n\"}"
+    );
+}
+
+#[tokio::test]
+async fn converts_rejections_into_problems() {
+    let maintenance = MaintenanceSeeder::new().retry_after(Duration::from_secs(60));
+    maintenance.handle().enable();
+    let problems = ProblemSeeder::new().base_uri("https://example.com/errors/");
+
+    let mut request = HttpRequest::builder().uri("/orders?page=2").body(BoxBody::empty()).unwrap();
+    let guard = maintenance.seed(Guard::Accessible(&mut request)).await;
+
+    match problems.seed(guard).await {
+        Guard::Inaccessible { respondent: Respondent::Respond(response), .. } => {
+            assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+            assert_eq!(response.headers()["content-type"], PROBLEM_JSON);
+            assert!(response.headers().contains_key("retry-after"));
+            assert_eq!(
+                std::str::from_utf8(response.body().raw_bytes()).unwrap(),
+                "{\"type\":\"https://example.com/errors/maintenance\",\"title\":\"Service Unavailable\",\
+                 \"status\":503,\"detail\":\"The server is in maintenance mode.\",\"instance\":\"/orders\"}"
+            );
+        }
+        _ => panic!("rejection was not converted"),
+    }
+}
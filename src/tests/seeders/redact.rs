@@ -0,0 +1,93 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::seeders::redact::{RedactSeeder, Scopes};
+use serde_json::json;
+
+fn get(scopes: &[&str]) -> HttpRequest<BoxBody> {
+    let mut request = HttpRequest::builder().uri("/orders").body(BoxBody::empty()).unwrap();
+    request.extensions_mut().insert(Scopes(scopes.iter().map(|scope| scope.to_string()).collect()));
+    request
+}
+
+fn json_response(value: serde_json::Value) -> HttpResponse<BoxBody> {
+    HttpResponse::builder()
+        .header("content-type", "application/json; charset=utf-8")
+        .body(BoxBody::new(serde_json::to_vec(&value).unwrap().into_boxed_slice()))
+        .unwrap()
+}
+
+fn seeder() -> RedactSeeder {
+    RedactSeeder::new()
+        .remove("/owner/email", "users:read")
+        .mask("/items/*/cost", "billing:read")
+}
+
+#[tokio::test]
+async fn redacts_for_missing_scopes() {
+    let seeder = seeder();
+
+    let mut request = get(&["billing:read"]);
+    assert!(seeder.seed(Guard::Accessible(&mut request)).await.accessible());
+
+    let mut response = json_response(json!({
+        "owner": { "name": "Ada", "email": "ada@example.com" },
+        "items": [{ "id": 1, "cost": 5 }, { "id": 2, "cost": 7 }],
+    }));
+    seeder.redact_response(&request, &mut response);
+
+    let redacted: serde_json::Value = serde_json::from_slice(response.body().raw_bytes()).unwrap();
+    assert_eq!(redacted, json!({
+        "owner": { "name": "Ada" },
+        "items": [{ "id": 1, "cost": 5 }, { "id": 2, "cost": 7 }],
+    }));
+
+    let mut request = get(&[]);
+    assert!(seeder.seed(Guard::Accessible(&mut request)).await.accessible());
+
+    let mut value = json!({ "items": [{ "cost": 5 }] });
+    seeder.redact(&request, &mut value);
+    assert_eq!(value, json!({ "items": [{ "cost": "***" }] }));
+}
+
+#[tokio::test]
+async fn leaves_other_responses_alone() {
+    let seeder = seeder();
+
+    let mut request = get(&[]);
+    assert!(seeder.seed(Guard::Accessible(&mut request)).await.accessible());
+
+    let body = br#"{"owner":{"email":"ada@example.com"}}"#;
+    let mut response = HttpResponse::builder()
+        .header("content-type", "text/plain")
+        .body(BoxBody::new(body.as_slice().into()))
+        .unwrap();
+
+    seeder.redact_response(&request, &mut response);
+    assert_eq!(response.body().raw_bytes(), body);
+}
+
+#[tokio::test]
+async fn fails_closed_for_unredactable_responses() {
+    let seeder = seeder();
+
+    let mut request = get(&[]);
+    assert!(seeder.seed(Guard::Accessible(&mut request)).await.accessible());
+
+    let mut compressed = json_response(json!({ "owner": { "email": "ada@example.com" } }));
+    compressed.headers_mut().insert("content-encoding", "gzip".parse().unwrap());
+    seeder.redact_response(&request, &mut compressed);
+    assert_eq!(compressed.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(compressed.body().raw_bytes().is_empty());
+
+    let mut malformed = json_response(json!({}));
+    let truncated = br#"{"owner":{"email":"ada@example.com""#;
+    *malformed.body_mut() = BoxBody::new(truncated.as_slice().into());
+    seeder.redact_response(&request, &mut malformed);
+    assert_eq!(malformed.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[test]
+#[should_panic(expected = "must be empty or start with a '/'")]
+fn refuses_relative_pointers() {
+    let _ = RedactSeeder::new().remove("owner/email", "users:read");
+}
@@ -0,0 +1,43 @@
+use crate::core::seeder::{BoxBody, Guard, Respondent, Seeder};
+use crate::http::{HttpRequest, StatusCode};
+use crate::seeders::rewrite::{OriginalUri, RewriteRule};
+use crate::seeders::RewriteSeeder;
+use regex::Regex;
+
+fn seeder() -> RewriteSeeder {
+    RewriteSeeder::new()
+        .rule(RewriteRule::redirect_url(
+            Regex::new(r"^https?://example\.com(.*)$").unwrap(),
+            StatusCode::MOVED_PERMANENTLY,
+            "https://www.example.com$1",
+        ))
+        .rule(RewriteRule::rewrite(Regex::new(r"^/api(/.*)$").unwrap(), "$1"))
+}
+
+fn request(host: &str, path: &str) -> HttpRequest<BoxBody> {
+    HttpRequest::builder().uri(path).header("host", host).body(BoxBody::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn rewrites_paths_internally() {
+    let seeder = seeder();
+    let mut req = request("www.example.com", "/api/users?page=2");
+
+    let req = seeder.seed(Guard::Accessible(&mut req)).await.unwrap();
+    assert_eq!(req.uri(), "/users?page=2");
+    assert_eq!(req.extensions().get::<OriginalUri>().unwrap().0, "/api/users?page=2");
+}
+
+#[tokio::test]
+async fn redirects_to_canonical_host() {
+    let seeder = seeder();
+    let mut req = request("example.com", "/api/users?page=2");
+
+    match seeder.seed(Guard::Accessible(&mut req)).await {
+        Guard::Inaccessible { respondent: Respondent::Respond(response), .. } => {
+            assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+            assert_eq!(response.headers()["location"], "https://www.example.com/api/users?page=2");
+        }
+        _ => panic!("request was not redirected"),
+    }
+}
@@ -0,0 +1,78 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::{HttpRequest, Method, StatusCode};
+use crate::seeders::schema::{Schema, SchemaSeeder};
+use serde_json::json;
+
+fn post(path: &str, body: &str) -> HttpRequest<BoxBody> {
+    HttpRequest::builder().method("POST").uri(path).body(BoxBody::new(body.as_bytes().into())).unwrap()
+}
+
+#[test]
+fn reports_every_violation_by_pointer() {
+    let schema = Schema::compile(&json!({
+        "type": "object",
+        "required": ["name", "items"],
+        "additionalProperties": false,
+        "properties": {
+            "name": { "type": "string", "minLength": 1 },
+            "items": {
+                "type": "array",
+                "minItems": 1,
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "sku": { "type": "string" },
+                        "quantity": { "type": "integer", "minimum": 1 }
+                    }
+                }
+            },
+            "tier": { "enum": ["free", "pro"] },
+            "contact": { "oneOf": [{ "type": "string" }, { "type": "null" }] }
+        }
+    }))
+    .unwrap();
+
+    assert!(schema.is_valid(&json!({ "name": "Ada", "items": [{ "sku": "a", "quantity": 2 }], "contact": null })));
+
+    let violations = schema
+        .validate(&json!({ "name": "", "items": [{ "quantity": 1 }, { "quantity": 0.5 }], "tier": "gold", "extra": true }))
+        .unwrap_err();
+    let found: Vec<(&str, &str)> = violations.iter().map(|violation| (violation.pointer.as_str(), violation.keyword)).collect();
+
+    assert_eq!(found.len(), 4, "{violations:?}");
+    for expected in [("/name", "minLength"), ("/items/1/quantity", "type"), ("/tier", "enum"), ("/extra", "false")] {
+        assert!(found.contains(&expected), "missing {expected:?} in {found:?}");
+    }
+
+    let invalid = Schema::compile(&json!({ "properties": { "a/b": { "minimum": "one" } } })).unwrap_err();
+    assert_eq!(invalid.pointer, "/properties/a~1b/minimum");
+}
+
+#[tokio::test]
+async fn rejects_invalid_bodies_on_matching_routes() {
+    let seeder = SchemaSeeder::new()
+        .schema(Method::POST, "/orders", &json!({ "type": "object", "required": ["sku"] }))
+        .unwrap();
+
+    let mut valid = post("/orders", r#"{"sku":"a"}"#);
+    assert!(matches!(seeder.seed(Guard::Accessible(&mut valid)).await, Guard::Accessible(_)));
+
+    let mut unrouted = post("/users", "not json");
+    assert!(matches!(seeder.seed(Guard::Accessible(&mut unrouted)).await, Guard::Accessible(_)));
+
+    let mut missing = post("/orders/7", "{}");
+    match seeder.seed(Guard::Accessible(&mut missing)).await {
+        Guard::Inaccessible { rejection, .. } => {
+            assert_eq!(rejection.status, StatusCode::BAD_REQUEST);
+            assert_eq!(rejection.code, "invalid_body");
+            assert_eq!(rejection.details.unwrap()["errors"][0]["keyword"], "required");
+        }
+        _ => { panic!("expected the body to be rejected"); }
+    }
+
+    let mut malformed = post("/orders", "{");
+    match seeder.seed(Guard::Accessible(&mut malformed)).await {
+        Guard::Inaccessible { rejection, .. } => { assert_eq!(rejection.code, "malformed_body"); }
+        _ => { panic!("expected the body to be rejected"); }
+    }
+}
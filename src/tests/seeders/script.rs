@@ -0,0 +1,80 @@
+use crate::core::seeder::{BoxBody, Guard, Respondent, Seeder};
+use crate::http::header::LOCATION;
+use crate::http::{HttpRequest, StatusCode};
+use crate::seeders::ScriptSeeder;
+
+const RULES: &str = r#"
+    if request.path.starts_with("/admin") && request.headers["x-internal"] != "1" {
+        deny(404, "Not here.")
+    } else if request.path == "/old-pricing" {
+        redirect(301, "/pricing?from=" + request.method)
+    } else if request.path == "/v1/users" {
+        rewrite("/users?" + request.query)
+    } else {
+        request.method != "DELETE"
+    }
+"#;
+
+fn request(method: &str, uri: &str) -> HttpRequest<BoxBody> {
+    HttpRequest::builder().method(method).uri(uri).body(BoxBody::empty()).unwrap()
+}
+
+/// Seeds a request, returning its rejection's status and the response's `Location` header.
+async fn rejected(seeder: &ScriptSeeder, request: &mut HttpRequest<BoxBody>) -> Option<(StatusCode, Option<String>)> {
+    match seeder.seed(Guard::Accessible(request)).await {
+        Guard::Accessible(_) => { None }
+        Guard::Inaccessible { rejection, respondent: Respondent::Respond(response), .. } => {
+            let location = response.headers().get(LOCATION).map(|location| location.to_str().unwrap().to_owned());
+            Some((rejection.status, location))
+        }
+        Guard::Inaccessible { .. } => { panic!("the script didn't respond") }
+    }
+}
+
+#[tokio::test]
+async fn scripts_decide_what_happens_to_requests() {
+    let seeder = ScriptSeeder::new(RULES).unwrap();
+
+    assert_eq!(rejected(&seeder, &mut request("GET", "/admin/users")).await, Some((StatusCode::NOT_FOUND, None)));
+    let mut internal = request("GET", "/admin/users");
+    internal.headers_mut().insert("x-internal", "1".parse().unwrap());
+    assert_eq!(rejected(&seeder, &mut internal).await, None);
+
+    let redirect = rejected(&seeder, &mut request("GET", "/old-pricing")).await;
+    assert_eq!(redirect, Some((StatusCode::MOVED_PERMANENTLY, Some("/pricing?from=GET".to_owned()))));
+
+    let mut rewritten = request("GET", "/v1/users?page=2");
+    assert_eq!(rejected(&seeder, &mut rewritten).await, None);
+    assert_eq!(rewritten.uri(), "/users?page=2");
+
+    assert_eq!(rejected(&seeder, &mut request("DELETE", "/users/1")).await, Some((StatusCode::FORBIDDEN, None)));
+}
+
+#[tokio::test]
+async fn failing_scripts_answer_with_server_errors() {
+    let seeder = ScriptSeeder::new("loop {}").unwrap().max_operations(1_000);
+    assert_eq!(rejected(&seeder, &mut request("GET", "/")).await, Some((StatusCode::INTERNAL_SERVER_ERROR, None)));
+
+    seeder.load("deny(1000)").unwrap();
+    assert_eq!(rejected(&seeder, &mut request("GET", "/")).await, Some((StatusCode::INTERNAL_SERVER_ERROR, None)));
+
+    seeder.load(r#""not a decision""#).unwrap();
+    assert_eq!(rejected(&seeder, &mut request("GET", "/")).await, Some((StatusCode::INTERNAL_SERVER_ERROR, None)));
+}
+
+#[tokio::test]
+async fn reloads_keep_the_last_script_which_compiled() {
+    let path = std::env::temp_dir().join(format!("grazie-script-{}.rhai", std::process::id()));
+    std::fs::write(&path, "false").unwrap();
+    let seeder = ScriptSeeder::from_file(&path).unwrap();
+    assert!(rejected(&seeder, &mut request("GET", "/")).await.is_some());
+
+    std::fs::write(&path, "true").unwrap();
+    seeder.clone().reload(&path).unwrap();
+    assert!(rejected(&seeder, &mut request("GET", "/")).await.is_none());
+
+    std::fs::write(&path, "if {").unwrap();
+    assert!(seeder.reload(&path).is_err());
+    std::fs::remove_file(&path).unwrap();
+    assert!(rejected(&seeder, &mut request("GET", "/")).await.is_none());
+}
@@ -0,0 +1,57 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::header::HeaderName;
+use crate::http::{HttpRequest, StatusCode};
+use crate::seeders::signature::{decode_hex, ContentMd5, Digest, HmacAlgorithm, HmacSignature, Verifier};
+use crate::seeders::SignatureSeeder;
+
+fn request(headers: &[(&str, &str)], body: &str) -> HttpRequest<BoxBody> {
+    let mut builder = HttpRequest::builder().method("POST").uri("/hooks");
+    for (name, value) in headers {
+        builder = builder.header(*name, *value);
+    }
+
+    builder.body(BoxBody::new(body.as_bytes().into())).unwrap()
+}
+
+#[tokio::test]
+async fn verifies_github_style_signatures() {
+    let signature = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+    let seeder = SignatureSeeder::new().verify_with(
+        HmacSignature::new(HmacAlgorithm::Sha256, HeaderName::from_static("x-hub-signature-256"), "old secret")
+            .secret("It's a Secret to Everybody")
+            .prefix("sha256="),
+    );
+
+    let mut valid = request(&[("x-hub-signature-256", signature)], "Hello, World!");
+    assert!(seeder.seed(Guard::Accessible(&mut valid)).await.accessible());
+
+    let mut tampered = request(&[("x-hub-signature-256", signature)], "Hello, World?");
+    match seeder.seed(Guard::Accessible(&mut tampered)).await {
+        Guard::Inaccessible { rejection, .. } => {
+            assert_eq!(rejection.status, StatusCode::UNAUTHORIZED);
+            assert_eq!(rejection.code, "invalid_signature");
+        }
+        _ => panic!("tampered payload was accepted"),
+    }
+}
+
+#[test]
+fn verifies_body_checksums() {
+    let digest = Digest::header_value(b"hello");
+    assert!(Digest::new().verify(&request(&[("digest", &digest)], "hello")).is_ok());
+    assert_eq!(Digest::new().verify(&request(&[("digest", &digest)], "hullo")).unwrap_err().status, StatusCode::BAD_REQUEST);
+    assert!(Digest::new().required().verify(&request(&[("digest", "unixsum=30637")], "hello")).is_err());
+
+    let md5 = ("content-md5", "XUFAKrxLKna5cZ2REBfFkg==");
+    assert!(ContentMd5::new().verify(&request(&[md5], "hello")).is_ok());
+    assert!(ContentMd5::new().verify(&request(&[md5], "hullo")).is_err());
+    assert!(ContentMd5::new().verify(&request(&[], "hullo")).is_ok());
+}
+
+#[test]
+fn decodes_only_hex_digits() {
+    assert_eq!(decode_hex("00ff7A"), Some(vec![0x00, 0xff, 0x7a]));
+    assert_eq!(decode_hex("+f"), None);
+    assert_eq!(decode_hex("-0"), None);
+    assert_eq!(decode_hex("abc"), None);
+}
@@ -0,0 +1,64 @@
+use crate::core::seeder::{BoxBody, Guard, Respondent, Seeder};
+use crate::http::{HttpRequest, StatusCode};
+use crate::seeders::tarpit::TarpitResponse;
+use crate::seeders::TarpitSeeder;
+use crate::server::PeerAddr;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const SCANNER: IpAddr = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+
+fn request(path: &str) -> HttpRequest<BoxBody> {
+    let mut request = HttpRequest::builder().uri(path).body(BoxBody::empty()).unwrap();
+    request.extensions_mut().insert(PeerAddr(SocketAddr::new(SCANNER, 40000)));
+    request
+}
+
+#[tokio::test]
+async fn traps_probes_and_flags_repeat_offenders() {
+    let flagged = Arc::new(Mutex::new(Vec::new()));
+    let hook = flagged.clone();
+    let seeder = TarpitSeeder::new()
+        .response(TarpitResponse::Garbage { delay: Duration::from_millis(5), size: 100 })
+        .threshold(2)
+        .trap_flagged(true)
+        .on_flag(move |address| hook.lock().unwrap().push(address));
+
+    let mut page = request("/about");
+    assert!(seeder.seed(Guard::Accessible(&mut page)).await.accessible());
+
+    for path in ["/.env", "/wp-login.php?redirect=1"] {
+        let mut probe = request(path);
+        match seeder.seed(Guard::Accessible(&mut probe)).await {
+            Guard::Inaccessible { respondent: Respondent::Respond(response), rejection, .. } => {
+                assert_eq!(rejection.code, "tarpitted");
+                assert_eq!(response.body().raw_bytes().len(), 100);
+            }
+            _ => panic!("probe wasn't trapped"),
+        }
+    }
+
+    assert_eq!(*flagged.lock().unwrap(), vec![SCANNER]);
+    assert_eq!(seeder.metrics().trapped, 2);
+
+    let mut page = request("/about");
+    assert!(!seeder.seed(Guard::Accessible(&mut page)).await.accessible());
+
+    seeder.forgive(SCANNER);
+    let mut page = request("/about");
+    assert!(seeder.seed(Guard::Accessible(&mut page)).await.accessible());
+}
+
+#[tokio::test]
+async fn answers_immediately_when_too_many_are_waiting() {
+    let seeder = TarpitSeeder::new().response(TarpitResponse::Delay(Duration::from_secs(60))).max_waiting(0);
+
+    let mut probe = request("/.git/config");
+    match seeder.seed(Guard::Accessible(&mut probe)).await {
+        Guard::Inaccessible { rejection, .. } => { assert_eq!(rejection.status, StatusCode::NOT_FOUND); }
+        _ => panic!("probe wasn't trapped"),
+    }
+
+    assert_eq!(seeder.metrics().overflowed, 1);
+}
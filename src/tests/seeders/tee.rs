@@ -0,0 +1,44 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::seeders::tee::ChannelSink;
+use crate::seeders::TeeSeeder;
+use tokio::sync::mpsc;
+
+fn request(body: &[u8]) -> HttpRequest<BoxBody> {
+    HttpRequest::builder()
+        .method("POST")
+        .uri("/payments")
+        .body(BoxBody::new(body.into()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn samples_and_truncates() {
+    let (sender, mut receiver) = mpsc::channel(16);
+    let seeder = TeeSeeder::new(ChannelSink(sender), 16)
+        .sample(0.5)
+        .max_body(4)
+        .responses(true);
+
+    for _ in 0..4 {
+        let mut req = request(b"card=4242");
+        let req = seeder.seed(Guard::Accessible(&mut req)).await.unwrap();
+
+        let response = HttpResponse::builder()
+            .status(StatusCode::CREATED)
+            .body(BoxBody::new(b"ok".as_slice().into()))
+            .unwrap();
+        seeder.tee_response(req, &response);
+    }
+
+    let mut records = Vec::new();
+    for _ in 0..4 {
+        records.push(receiver.recv().await.unwrap());
+    }
+
+    assert_eq!(records.iter().filter(|record| record.status.is_none()).count(), 2);
+    assert_eq!(&*records[0].body, b"card");
+    assert!(records[0].truncated());
+    assert_eq!(records[1].status, Some(StatusCode::CREATED));
+    assert_eq!(records[1].sequence, records[0].sequence);
+}
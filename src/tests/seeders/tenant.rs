@@ -0,0 +1,106 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::header::HeaderName;
+use crate::http::{HttpRequest, StatusCode};
+use crate::seeders::tenant::{MemoryQuotaStore, MemoryRateLimitStore, Quota, RateLimitStore};
+use crate::seeders::tenant::{Tenant, TenantSeeder, TenantSource};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A `RateLimitStore` shared between seeders, standing in for one shared between instances.
+struct SharedLimits(Arc<MemoryRateLimitStore>);
+
+impl RateLimitStore for SharedLimits {
+    async fn hit(&self, key: &str, requests: u32, window: Duration) -> Option<Duration> {
+        self.0.hit(key, requests, window).await
+    }
+}
+
+fn get(host: &str) -> HttpRequest<BoxBody> {
+    HttpRequest::builder().uri("/orders").header("host", host).body(BoxBody::empty()).unwrap()
+}
+
+async fn code<S: Seeder>(seeder: &S, request: &mut HttpRequest<BoxBody>) -> Option<(StatusCode, String)> {
+    match seeder.seed(Guard::Accessible(request)).await {
+        Guard::Inaccessible { rejection, .. } => { Some((rejection.status, rejection.code)) }
+        _ => { None }
+    }
+}
+
+#[tokio::test]
+async fn resolves_tenants_in_order() {
+    let seeder = TenantSeeder::new(MemoryQuotaStore::new().fallback(Quota::new()))
+        .source(TenantSource::Header(HeaderName::from_static("x-tenant")))
+        .source(TenantSource::Subdomain("example.com".into()));
+
+    let mut request = get("acme.example.com:8080");
+    assert_eq!(code(&seeder, &mut request).await, None);
+    assert_eq!(request.extensions().get::<Tenant>().unwrap().id, "acme");
+
+    let mut request = get("acme.example.com");
+    request.headers_mut().insert("x-tenant", "globex".parse().unwrap());
+    assert_eq!(code(&seeder, &mut request).await, None);
+    assert_eq!(request.extensions().get::<Tenant>().unwrap().id, "globex");
+
+    let mut request = get("example.com");
+    assert_eq!(code(&seeder, &mut request).await, Some((StatusCode::BAD_REQUEST, "missing_tenant".into())));
+}
+
+#[tokio::test]
+async fn rejects_unknown_tenants() {
+    let store = MemoryQuotaStore::new();
+    store.set("acme", Quota::new());
+    let seeder = TenantSeeder::new(store).source(TenantSource::Subdomain("example.com".into()));
+
+    let mut request = get("globex.example.com");
+    assert_eq!(code(&seeder, &mut request).await, Some((StatusCode::FORBIDDEN, "unknown_tenant".into())));
+}
+
+#[tokio::test]
+async fn enforces_quotas_per_tenant() {
+    let store = MemoryQuotaStore::new();
+    store.set("acme", Quota::new().rate(2, Duration::from_secs(60)));
+    store.set("globex", Quota::new().concurrency(1).max_body(4));
+    let seeder = TenantSeeder::new(store).source(TenantSource::Subdomain("example.com".into()));
+
+    for _ in 0..2 {
+        assert_eq!(code(&seeder, &mut get("acme.example.com")).await, None);
+    }
+    assert_eq!(code(&seeder, &mut get("acme.example.com")).await, Some((StatusCode::TOO_MANY_REQUESTS, "tenant_rate_limited".into())));
+
+    let mut held = get("globex.example.com");
+    assert_eq!(code(&seeder, &mut held).await, None);
+    assert_eq!(seeder.in_flight("globex"), 1);
+    assert_eq!(code(&seeder, &mut get("globex.example.com")).await, Some((StatusCode::TOO_MANY_REQUESTS, "tenant_concurrency_limited".into())));
+
+    drop(held);
+    let mut request = get("globex.example.com");
+    *request.body_mut() = BoxBody::new(b"too large".as_slice().into());
+    assert_eq!(code(&seeder, &mut request).await, Some((StatusCode::PAYLOAD_TOO_LARGE, "tenant_body_too_large".into())));
+    assert_eq!(code(&seeder, &mut get("globex.example.com")).await, None);
+}
+
+#[tokio::test]
+async fn counts_rates_in_the_rate_limit_store() {
+    let limits = Arc::new(MemoryRateLimitStore::new());
+    let seeder = || {
+        TenantSeeder::new(MemoryQuotaStore::new().fallback(Quota::new().rate(1, Duration::from_secs(60))))
+            .rate_limits(SharedLimits(limits.clone()))
+            .source(TenantSource::Subdomain("example.com".into()))
+    };
+
+    assert_eq!(code(&seeder(), &mut get("acme.example.com")).await, None);
+    let limited = code(&seeder(), &mut get("acme.example.com")).await;
+    assert_eq!(limited, Some((StatusCode::TOO_MANY_REQUESTS, "tenant_rate_limited".into())));
+}
+
+#[cfg(feature = "serde_json")]
+#[tokio::test]
+async fn resolves_token_claims() {
+    let seeder = TenantSeeder::new(MemoryQuotaStore::new().fallback(Quota::new())).source(TenantSource::Claim("org".into()));
+    let payload = crate::util::base64_encode(br#"{"sub":"ada","org":"acme"}"#);
+
+    let mut request = get("example.com");
+    request.headers_mut().insert("authorization", format!("Bearer e30.{payload}.sig").parse().unwrap());
+    assert_eq!(code(&seeder, &mut request).await, None);
+    assert_eq!(request.extensions().get::<Tenant>().unwrap().id, "acme");
+}
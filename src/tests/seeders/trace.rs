@@ -0,0 +1,51 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::header::HeaderMap;
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::seeders::trace::{AttributeValue, TraceContext, TraceParent, TraceSeeder};
+use tokio::sync::mpsc;
+
+const PARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+#[test]
+fn parses_and_formats_traceparents() {
+    let parent: TraceParent = PARENT.parse().unwrap();
+    assert!(parent.sampled());
+    assert_eq!(parent.to_string(), PARENT);
+
+    assert!("00-00000000000000000000000000000000-00f067aa0ba902b7-01".parse::<TraceParent>().is_err());
+    assert!("00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01".parse::<TraceParent>().is_err());
+    assert!("00-+bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".parse::<TraceParent>().is_err());
+    assert!("00-4bf92f3577b34da6a3ce929d0e0e47é-00f067aa0ba902b7-01".parse::<TraceParent>().is_err());
+    assert!("ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".parse::<TraceParent>().is_err());
+}
+
+#[tokio::test]
+async fn propagates_and_exports_request_spans() {
+    let (sender, mut spans) = mpsc::unbounded_channel();
+    let seeder = TraceSeeder::new(sender);
+
+    let mut request = HttpRequest::builder()
+        .uri("/orders?page=2")
+        .header("traceparent", PARENT)
+        .header("tracestate", "vendor=abc")
+        .body(BoxBody::empty())
+        .unwrap();
+    let request = seeder.seed(Guard::Accessible(&mut request)).await.unwrap();
+
+    let context = request.extensions().get::<TraceContext>().unwrap().clone();
+    assert_eq!(context.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+    assert_ne!(context.span_id(), "00f067aa0ba902b7");
+
+    let mut outbound = HeaderMap::new();
+    context.inject(&mut outbound);
+    assert_eq!(outbound["traceparent"], context.span.to_string());
+    assert_eq!(outbound["tracestate"], "vendor=abc");
+
+    let response = HttpResponse::builder().status(StatusCode::CREATED).body(BoxBody::empty()).unwrap();
+    seeder.finish(request, &response).unwrap();
+
+    let span = spans.recv().await.unwrap();
+    assert_eq!(span.name, "GET");
+    assert!(span.attributes.contains(&("url.path", AttributeValue::String("/orders".to_owned()))));
+    assert!(span.attributes.contains(&("http.response.status_code", AttributeValue::Int(201))));
+}
@@ -0,0 +1,56 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::{HttpRequest, StatusCode};
+use crate::seeders::versioning::{ApiVersion, VersionMap};
+use crate::seeders::VersioningSeeder;
+
+fn request(name: &str, value: &str) -> HttpRequest<BoxBody> {
+    HttpRequest::builder()
+        .uri("/users")
+        .header(name, value)
+        .body(BoxBody::empty())
+        .unwrap()
+}
+
+#[test]
+fn resolves_vendor_media_type() {
+    let seeder = VersioningSeeder::accept("myapp");
+
+    let req = request("accept", "text/html;q=0.9, application/vnd.myapp.v2+json");
+    assert_eq!(seeder.resolve(&req), Some(ApiVersion(2)));
+
+    let req = request("accept", "application/vnd.other.v2+json");
+    assert_eq!(seeder.resolve(&req), None);
+}
+
+#[test]
+fn resolves_custom_header() {
+    let seeder = VersioningSeeder::header("api-version".parse().unwrap());
+
+    assert_eq!(seeder.resolve(&request("api-version", "v3")), Some(ApiVersion(3)));
+    assert_eq!(seeder.resolve(&request("api-version", "4")), Some(ApiVersion(4)));
+    assert_eq!(seeder.resolve(&request("api-version", "latest")), None);
+}
+
+#[tokio::test]
+async fn stores_version_and_rejects_unsupported() {
+    let seeder = VersioningSeeder::accept("myapp").supported([1, 2]).default_version(1);
+
+    let mut req = request("accept", "*/*");
+    let guard = seeder.seed(Guard::Accessible(&mut req)).await;
+    assert_eq!(guard.unwrap().extensions().get::<ApiVersion>(), Some(&ApiVersion(1)));
+
+    let mut req = request("accept", "application/vnd.myapp.v9+json");
+    match seeder.seed(Guard::Accessible(&mut req)).await {
+        Guard::Inaccessible { rejection, .. } => assert_eq!(rejection.status, StatusCode::NOT_ACCEPTABLE),
+        Guard::Accessible(_) => panic!("unsupported version was accepted"),
+    }
+}
+
+#[test]
+fn version_map_falls_back_to_older_entries() {
+    let map = VersionMap::new().version(1, "v1").version(3, "v3");
+
+    assert_eq!(map.get(ApiVersion(0)), None);
+    assert_eq!(map.get(ApiVersion(2)), Some(&"v1"));
+    assert_eq!(map.get(ApiVersion(5)), Some(&"v3"));
+}
@@ -0,0 +1,91 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::{HttpRequest, StatusCode};
+use crate::seeders::WasmSeeder;
+
+/// Rejects requests with a `404` unless their head contains `x-internal: 1`, copying the head
+/// to offset 1024 so it can be searched.
+const FILTER: &str = r#"
+    (module
+        (memory (export "memory") 1)
+        (data (i32.const 0) "x-internal: 1\n")
+        (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+        (func (export "on_request") (param $ptr i32) (param $len i32) (result i32)
+            (local $at i32) (local $i i32)
+            (block $missing
+                (loop $scan
+                    (br_if $missing (i32.gt_u (i32.add (local.get $at) (i32.const 14)) (local.get $len)))
+                    (local.set $i (i32.const 0))
+                    (block $mismatch
+                        (loop $compare
+                            (br_if $mismatch (i32.ne
+                                (i32.load8_u
+                                    (i32.add (local.get $ptr) (i32.add (local.get $at) (local.get $i))))
+                                (i32.load8_u (local.get $i))))
+                            (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                            (br_if $compare (i32.lt_u (local.get $i) (i32.const 14)))
+                            (return (i32.const 0))))
+                    (local.set $at (i32.add (local.get $at) (i32.const 1)))
+                    (br $scan)))
+            (i32.const 404)))
+"#;
+
+fn request(uri: &str) -> HttpRequest<BoxBody> {
+    HttpRequest::builder().uri(uri).body(BoxBody::empty()).unwrap()
+}
+
+/// Seeds a request, returning its rejection's status.
+async fn rejected(seeder: &WasmSeeder, request: &mut HttpRequest<BoxBody>) -> Option<StatusCode> {
+    match seeder.seed(Guard::Accessible(request)).await {
+        Guard::Accessible(_) => { None }
+        Guard::Inaccessible { rejection, .. } => { Some(rejection.status) }
+    }
+}
+
+#[tokio::test]
+async fn modules_decide_what_happens_to_requests() {
+    let seeder = WasmSeeder::new(FILTER).unwrap();
+    assert_eq!(rejected(&seeder, &mut request("/admin")).await, Some(StatusCode::NOT_FOUND));
+
+    let mut internal = request("/admin");
+    internal.headers_mut().insert("x-internal", "1".parse().unwrap());
+    assert_eq!(rejected(&seeder, &mut internal).await, None);
+}
+
+#[tokio::test]
+async fn failing_modules_answer_with_errors() {
+    let spins = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) (i32.const 0))
+            (func (export "on_request") (param i32 i32) (result i32) (loop $spin (br $spin)) (i32.const 0)))
+    "#;
+    let seeder = WasmSeeder::new(spins).unwrap().fuel(10_000);
+    assert_eq!(rejected(&seeder, &mut request("/")).await, Some(StatusCode::INTERNAL_SERVER_ERROR));
+
+    let invalid = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) (i32.const 0))
+            (func (export "on_request") (param i32 i32) (result i32) (i32.const 42)))
+    "#;
+    seeder.load(invalid).unwrap();
+    assert_eq!(rejected(&seeder, &mut request("/")).await, Some(StatusCode::INTERNAL_SERVER_ERROR));
+}
+
+#[tokio::test]
+async fn reloads_keep_the_last_module_which_compiled() {
+    let seeder = WasmSeeder::new(FILTER).unwrap();
+
+    assert!(seeder.load("(module (func (export \"on_request\")").is_err());
+    assert!(seeder.load(r#"(module (import "env" "clock" (func)))"#).is_err());
+    assert_eq!(rejected(&seeder, &mut request("/")).await, Some(StatusCode::NOT_FOUND));
+
+    let pass = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) (i32.const 0))
+            (func (export "on_request") (param i32 i32) (result i32) (i32.const 0)))
+    "#;
+    seeder.load(pass).unwrap();
+    assert_eq!(rejected(&seeder, &mut request("/")).await, None);
+}
@@ -0,0 +1,45 @@
+use crate::HttpServer;
+use std::sync::{Arc, Mutex};
+
+#[tokio::test]
+async fn runs_lifecycle_hooks_in_order() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let record = |entry: &'static str, fail: bool| {
+        let log = log.clone();
+        move || {
+            let log = log.clone();
+            async move {
+                log.lock().unwrap().push(entry);
+                if fail { Err(format!("{entry} broke")) } else { Ok(()) }
+            }
+        }
+    };
+
+    let server = HttpServer::new("127.0.0.1:0").await.unwrap()
+        .on_start("migrate", record("migrate", false))
+        .on_start("warmup", record("warmup", true))
+        .on_start("never", record("never", false))
+        .on_shutdown("pool", record("pool", true))
+        .on_shutdown("cache", record("cache", false));
+
+    let error = server.startup().await.unwrap_err();
+    assert_eq!(error.to_string(), "startup hook `warmup` failed: warmup broke");
+
+    let errors = server.shutdown().await;
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].hook, "pool");
+
+    assert_eq!(*log.lock().unwrap(), ["migrate", "warmup", "cache", "pool"]);
+}
+
+#[tokio::test]
+async fn adopts_bound_std_listeners() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = HttpServer::from_std_listener(listener).unwrap();
+    assert_eq!(server.local_addr().unwrap(), addr);
+
+    #[cfg(unix)]
+    assert!(crate::activation::listeners().unwrap().is_empty());
+}
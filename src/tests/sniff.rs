@@ -0,0 +1,43 @@
+use crate::sniff::{classify, Protocol, Sniffer};
+use std::net::SocketAddr;
+use tokio::io::AsyncReadExt;
+
+#[test]
+fn classifies_connection_prefixes() {
+    assert_eq!(classify(b"GET / HTTP/1.1\r\n"), Some(Protocol::Http));
+    assert_eq!(classify(b"PRI * HTTP/2.0\r\n"), Some(Protocol::Http2));
+    assert_eq!(classify(&[0x16, 0x03, 0x01]), Some(Protocol::Tls));
+    assert_eq!(classify(b"PROXY TCP4"), Some(Protocol::Proxy));
+    assert_eq!(classify(b"\r\n\r\n\0\r\nQUIT\n\x21"), Some(Protocol::Proxy));
+    assert_eq!(classify(b"SSH-2.0"), Some(Protocol::Unknown));
+    assert_eq!(classify(b"PR"), None);
+    assert_eq!(classify(&[0x16]), None);
+}
+
+#[tokio::test]
+async fn consumes_proxy_headers_from_trusted_peers() {
+    let proxy: SocketAddr = "10.0.0.1:50000".parse().unwrap();
+    let sniffer = Sniffer::new().trust_proxy(proxy.ip());
+
+    let v1 = b"PROXY TCP4 192.0.2.1 203.0.113.1 56324 443\r\nGET / HTTP/1.1\r\n\r\n".as_slice();
+    let mut sniffed = sniffer.accept(v1, proxy).await.unwrap();
+    assert_eq!(sniffed.protocol, Protocol::Http);
+    assert_eq!(sniffed.peer, "192.0.2.1:56324".parse().unwrap());
+
+    let mut request = String::new();
+    sniffed.stream.read_to_string(&mut request).await.unwrap();
+    assert_eq!(request, "GET / HTTP/1.1\r\n\r\n");
+
+    let mut v2 = b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0c".to_vec();
+    v2.extend_from_slice(&[198, 51, 100, 7, 203, 0, 113, 1, 0x1f, 0x90, 0x01, 0xbb]);
+    v2.extend_from_slice(&[0x16, 0x03, 0x01, 0x00]);
+
+    let sniffed = sniffer.accept(v2.as_slice(), proxy).await.unwrap();
+    assert_eq!(sniffed.protocol, Protocol::Tls);
+    assert_eq!(sniffed.peer, "198.51.100.7:8080".parse().unwrap());
+    assert_eq!(sniffed.stream.buffered(), &[0x16, 0x03, 0x01, 0x00]);
+
+    let untrusted: SocketAddr = "192.0.2.50:40000".parse().unwrap();
+    let sniffed = sniffer.accept(v1, untrusted).await.unwrap();
+    assert_eq!((sniffed.protocol, sniffed.peer), (Protocol::Proxy, untrusted));
+}
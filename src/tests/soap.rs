@@ -0,0 +1,64 @@
+use crate::core::seeder::BoxBody;
+use crate::http::{HttpRequest, StatusCode};
+use crate::soap::{FaultCode, SoapFault, SoapService};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+struct GetPrice {
+    #[serde(rename = "Item")]
+    item: String,
+}
+
+#[derive(Serialize)]
+struct GetPriceResponse {
+    #[serde(rename = "Price")]
+    price: f64,
+}
+
+fn service() -> SoapService {
+    SoapService::new().operation("GetPrice", |request: GetPrice| async move {
+        match request.item.as_str() {
+            "Apples" => { Ok(GetPriceResponse { price: 1.5 }) }
+            _ => { Err(SoapFault::client("Unknown <item>.").detail("<code>42</code>")) }
+        }
+    })
+}
+
+fn envelope(item: &str) -> String {
+    format!(
+        r#"<?xml version="1.0"?><soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/" xmlns:m="urn:shop"><soap:Header><m:Trace>1</m:Trace></soap:Header><soap:Body><m:GetPrice><m:Item>{item}</m:Item></m:GetPrice></soap:Body></soap:Envelope>"#
+    )
+}
+
+#[tokio::test]
+async fn routes_on_the_action_or_the_body() {
+    let service = service();
+
+    let response = service.handle(Some("\"GetPrice\""), envelope("Apples").as_bytes()).await.unwrap();
+    assert!(response.contains("<soap:Body><GetPriceResponse><Price>1.5</Price></GetPriceResponse></soap:Body>"));
+
+    assert!(service.handle(Some(""), envelope("Apples").as_bytes()).await.is_ok());
+    assert!(service.handle(None, envelope("Apples").as_bytes()).await.is_ok());
+
+    let fault = service.handle(Some("urn:shop#Refund"), envelope("Apples").as_bytes()).await.unwrap_err();
+    assert_eq!(fault.code, FaultCode::Client);
+}
+
+#[tokio::test]
+async fn answers_faults_with_server_errors() {
+    let request = HttpRequest::builder()
+        .method("POST")
+        .uri("/soap")
+        .header("SOAPAction", "\"GetPrice\"")
+        .body(BoxBody::new(envelope("Pears").into_bytes().into()))
+        .unwrap();
+
+    let response = service().respond(&request).await;
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    let body = std::str::from_utf8(response.body().raw_bytes()).unwrap();
+    assert!(body.contains("<faultcode>soap:Client</faultcode><faultstring>Unknown &lt;item&gt;.</faultstring><detail><code>42</code></detail>"));
+
+    let fault = service().handle(None, b"<not-soap").await.unwrap_err();
+    assert_eq!(fault.code, FaultCode::Client);
+}
@@ -0,0 +1,132 @@
+use crate::auth::session::SessionStore;
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::db::{Database, TxSeeder};
+use crate::events::{Event, MemoryPublisher};
+use crate::http::header::COOKIE;
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::outbox::{Outbox, OutboxStore, OUTBOX_ID};
+use crate::seeders::idempotency::{IdempotencyStore, Lookup};
+use crate::seeders::IdempotencySeeder;
+use crate::sql::{SqlIdempotencyStore, SqlSessionStore};
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::SqlitePool;
+use std::time::Duration;
+
+/// Opens an in-memory database. Every connection to `:memory:` opens its own database, so the
+/// pool only keeps one.
+async fn database() -> SqlitePool {
+    SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap()
+}
+
+#[tokio::test]
+async fn sessions_are_kept_in_the_database() {
+    let sessions = SqlSessionStore::<_, Vec<String>>::new(database().await, Duration::from_secs(60));
+    sessions.migrate().await.unwrap();
+    sessions.migrate().await.unwrap();
+
+    let set_cookie = sessions.create(vec!["admin".to_owned()]).await.unwrap();
+    let pair = set_cookie.to_str().unwrap().split(';').next().unwrap().to_owned();
+    let request = HttpRequest::builder().header(COOKIE, pair).body(BoxBody::empty()).unwrap();
+    assert_eq!(sessions.get(&request).await.unwrap(), Some(vec!["admin".to_owned()]));
+
+    sessions.destroy(&request).await.unwrap();
+    assert_eq!(sessions.get(&request).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn sessions_expire_when_idle() {
+    // Without an idle timeout, a session expires as soon as it's created, so the test doesn't
+    // need to wait for it.
+    let sessions = SqlSessionStore::<_, String>::new(database().await, Duration::ZERO);
+    sessions.migrate().await.unwrap();
+
+    let set_cookie = sessions.create("alice".to_owned()).await.unwrap();
+    let pair = set_cookie.to_str().unwrap().split(';').next().unwrap().to_owned();
+    let request = HttpRequest::builder().header(COOKIE, pair).body(BoxBody::empty()).unwrap();
+
+    assert_eq!(sessions.get(&request).await.unwrap(), None);
+    assert_eq!(sessions.sweep().await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn idempotency_keys_are_kept_in_the_database() {
+    let store = SqlIdempotencyStore::new(database().await);
+    store.migrate().await.unwrap();
+    let ttl = Duration::from_secs(60);
+
+    assert!(matches!(store.begin("charge", ttl).await, Lookup::New));
+    assert!(matches!(store.begin("charge", ttl).await, Lookup::InFlight));
+    store.abandon("charge").await;
+    assert!(matches!(store.begin("charge", Duration::ZERO).await, Lookup::New));
+    assert!(matches!(store.begin("charge", ttl).await, Lookup::New));
+    store.abandon("charge").await;
+
+    let seeder = IdempotencySeeder::new(store).scope(|_| Some("client".to_owned()));
+    let request = || {
+        let request = HttpRequest::builder().method("POST").uri("/charges");
+        request.header("idempotency-key", "charge").body(BoxBody::empty()).unwrap()
+    };
+
+    let mut first = request();
+    assert!(seeder.seed(Guard::Accessible(&mut first)).await.accessible());
+    let response = HttpResponse::builder().status(StatusCode::CREATED).body(BoxBody::empty()).unwrap();
+    seeder.complete(&first, &response).await;
+
+    let mut retry = request();
+    match seeder.seed(Guard::Accessible(&mut retry)).await {
+        Guard::Inaccessible { rejection, .. } => { assert_eq!(rejection.status, StatusCode::CREATED); }
+        Guard::Accessible(_) => { panic!("the retry wasn't replayed"); }
+    }
+}
+
+#[tokio::test]
+async fn failing_databases_refuse_idempotent_requests() {
+    let store = SqlIdempotencyStore::new(database().await);
+
+    assert!(matches!(store.begin("charge", Duration::from_secs(60)).await, Lookup::InFlight));
+}
+
+/// Writes an event in a committed request and one in a rolled back request, and relays them.
+async fn relays_committed_events<P: OutboxStore<Error = sqlx::Error>>(database: Database<P>) {
+    let seeder = TxSeeder::new(database.clone());
+    let outbox = Outbox::new(database);
+    outbox.migrate().await.unwrap();
+    outbox.migrate().await.unwrap();
+    outbox.relay(&MemoryPublisher::new()).await.unwrap();
+
+    let requests = [("orders.created", StatusCode::CREATED), ("orders.failed", StatusCode::CONFLICT)];
+    for (subject, status) in requests {
+        let mut request = HttpRequest::builder().method("POST").body(BoxBody::empty()).unwrap();
+        seeder.seed(Guard::Accessible(&mut request)).await;
+        let event = Event::new(subject, b"{}".to_vec()).header("tenant", "acme");
+        outbox.write(&request, event).await.unwrap();
+
+        let response = HttpResponse::builder().status(status).body(BoxBody::empty()).unwrap();
+        seeder.finish(&request, &response).await.unwrap();
+    }
+
+    let publisher = MemoryPublisher::new();
+    assert_eq!(outbox.relay(&publisher).await.unwrap(), 1);
+    assert_eq!(outbox.relay(&publisher).await.unwrap(), 0);
+
+    let events = publisher.events();
+    assert_eq!(events.len(), 1);
+    assert_eq!((events[0].subject.as_str(), &events[0].payload[..]), ("orders.created", &b"{}"[..]));
+    assert_eq!(events[0].headers[0], ("tenant".to_owned(), "acme".to_owned()));
+    assert_eq!(events[0].headers[1].0, OUTBOX_ID);
+}
+
+#[tokio::test]
+async fn outbox_events_are_kept_in_the_database() {
+    relays_committed_events(Database::new(database().await)).await;
+}
+
+#[cfg(feature = "postgres")]
+#[tokio::test]
+#[ignore = "needs a Postgres server at DATABASE_URL"]
+async fn outbox_events_are_kept_in_postgres() {
+    let url = std::env::var("DATABASE_URL").expect("DATABASE_URL isn't set");
+    let pool = sqlx::PgPool::connect(&url).await.unwrap();
+
+    relays_committed_events(Database::new(pool)).await;
+}
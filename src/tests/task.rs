@@ -0,0 +1,48 @@
+use crate::task::{BlockingConfig, BlockingError, BlockingPool};
+use std::sync::mpsc;
+
+#[tokio::test]
+async fn runs_jobs_and_isolates_panics() {
+    let pool = BlockingPool::new(BlockingConfig { threads: 2, ..BlockingConfig::default() });
+
+    assert_eq!(pool.run(|| 6 * 7).await, Ok(42));
+
+    match pool.run(|| panic!("hash failed")).await {
+        Err(BlockingError::Panicked(panic)) => { assert_eq!(panic.message.as_deref(), Some("hash failed")); }
+        other => panic!("unexpected result: {other:?}"),
+    }
+
+    let metrics = pool.metrics();
+    assert_eq!((metrics.completed, metrics.panicked, metrics.active), (2, 1, 0));
+}
+
+#[tokio::test]
+async fn rejects_jobs_beyond_the_queue_limit() {
+    let pool = BlockingPool::new(BlockingConfig { threads: 1, queue_limit: 1, ..BlockingConfig::default() });
+    let (release, wait) = mpsc::channel::<()>();
+
+    let busy = tokio::spawn({
+        let pool = pool.clone();
+        async move {
+            pool.run(move || wait.recv().unwrap()).await
+        }
+    });
+    while pool.metrics().active == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    let queued = tokio::spawn({
+        let pool = pool.clone();
+        async move { pool.run(|| ()).await }
+    });
+    while pool.metrics().queued == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    assert_eq!(pool.run(|| ()).await, Err(BlockingError::QueueFull));
+    assert_eq!(pool.metrics().rejected, 1);
+
+    release.send(()).unwrap();
+    assert!(busy.await.unwrap().is_ok());
+    assert!(queued.await.unwrap().is_ok());
+}
@@ -0,0 +1,79 @@
+use crate::core::seeder::{BoxBody, Guard, Respondent, Seeder, Unpacker};
+use crate::http::{HttpRequest, HttpResponse, StatusCode};
+use crate::test::{MockSeeder, MockUnpacker, Snapshots};
+
+fn get(path: &str) -> HttpRequest<BoxBody> {
+    HttpRequest::builder().uri(path).body(BoxBody::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn mock_seeders_follow_their_script() {
+    let chain = MockSeeder::new()
+        .ignore(StatusCode::FORBIDDEN, "audited")
+        .respond(StatusCode::UNAUTHORIZED, "unauthorized")
+        .then(MockSeeder::new());
+
+    let mut request = get("/a");
+    assert!(matches!(chain.seed(Guard::Accessible(&mut request)).await, Guard::Accessible(_)));
+
+    let mut request = get("/b");
+    match chain.seed(Guard::Accessible(&mut request)).await {
+        Guard::Inaccessible { respondent: Respondent::Respond(response), rejection, .. } => {
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(rejection.code, "unauthorized");
+        }
+        _ => { panic!("expected the scripted rejection"); }
+    }
+
+    // The script has run out, so the mock accepts from here on.
+    let mut request = get("/c");
+    assert!(matches!(chain.seed(Guard::Accessible(&mut request)).await, Guard::Accessible(_)));
+
+    chain.first().assert_called(3);
+    chain.first().assert_finished();
+    let seen: Vec<(String, bool)> = chain.second().seen().into_iter().map(|seen| (seen.uri.to_string(), seen.accessible)).collect();
+    assert_eq!(seen, [("/a".to_owned(), true), ("/b".to_owned(), false), ("/c".to_owned(), true)]);
+}
+
+#[tokio::test]
+async fn mock_unpackers_hand_out_canned_requests() {
+    let unpacker = MockUnpacker::new().request(get("/first")).request(get("/second"));
+
+    let mut stream = b"GET /ignored HTTP/1.1\r\n\r\n".to_vec();
+    assert_eq!(unpacker.unpack(&mut stream).await.uri(), "/first");
+    assert_eq!(unpacker.unpack(&mut stream).await.uri(), "/second");
+
+    assert_eq!(unpacker.remaining(), 0);
+    assert_eq!(unpacker.received().len(), 2);
+}
+
+#[test]
+fn snapshots_record_then_catch_changes() {
+    let dir = std::env::temp_dir().join(format!("grazie-snapshots-{}", std::process::id()));
+    let snapshots = Snapshots::new(&dir).redact("x-correlation-id");
+    let response = |body: &str, date: &str| {
+        HttpResponse::builder()
+            .status(StatusCode::OK)
+            .header("x-correlation-id", "abc")
+            .header("date", date)
+            .header("content-type", "text/plain")
+            .body(BoxBody::new(body.as_bytes().into()))
+            .unwrap()
+    };
+
+    // The first check records the snapshot, and volatile headers don't break later checks.
+    snapshots.assert("greeting", &response("hello", "Mon, 01 Jan 2024 00:00:00 GMT"));
+    snapshots.assert("greeting", &response("hello", "Tue, 02 Jan 2024 00:00:00 GMT"));
+
+    let stored = std::fs::read_to_string(dir.join("greeting.snap")).unwrap();
+    assert_eq!(stored, "HTTP/1.1 200 OK\ncontent-type: text/plain\ndate: [redacted]\nx-correlation-id: [redacted]\n\nhello");
+
+    let mismatch = snapshots.check("greeting", &response("goodbye", "")).unwrap().unwrap_err();
+    assert!(mismatch.contains("   6 - hello\n   6 + goodbye"), "{mismatch}");
+    assert!(dir.join("greeting.snap.new").exists());
+
+    snapshots.assert("greeting", &response("hello", ""));
+    assert!(!dir.join("greeting.snap.new").exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,99 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::HttpRequest;
+use crate::tunnel::{ConnectSeeder, ConnectTarget, Tunnel};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+fn connect(authority: &str) -> HttpRequest<BoxBody> {
+    HttpRequest::builder().method("CONNECT").uri(authority).body(BoxBody::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn approves_allowed_targets() {
+    let seeder = ConnectSeeder::new().allow_host(".example.com").allow_host("api.test").allow_port(8443);
+
+    let mut allowed = connect("www.example.com:443");
+    assert!(seeder.seed(Guard::Accessible(&mut allowed)).await.accessible());
+    assert_eq!(allowed.extensions().get::<ConnectTarget>().unwrap().host, "www.example.com");
+
+    for denied in ["example.org:443", "api.test:22", "evil-example.com:443"] {
+        let mut request = connect(denied);
+        assert!(!seeder.seed(Guard::Accessible(&mut request)).await.accessible(), "{denied} was allowed");
+    }
+
+    let mut get = HttpRequest::builder().uri("/").body(BoxBody::empty()).unwrap();
+    assert!(seeder.seed(Guard::Accessible(&mut get)).await.accessible());
+}
+
+#[tokio::test]
+async fn splices_both_directions_until_closed() {
+    let (client, mut client_peer) = tokio::io::duplex(64);
+    let (target, mut target_peer) = tokio::io::duplex(64);
+    let tunnel = Tunnel::new();
+
+    let splice = tokio::spawn({
+        let tunnel = tunnel.clone();
+        async move { tunnel.splice(client, target).await }
+    });
+
+    client_peer.write_all(b"ping").await.unwrap();
+    let mut ping = [0u8; 4];
+    target_peer.read_exact(&mut ping).await.unwrap();
+    assert_eq!(&ping, b"ping");
+
+    target_peer.write_all(b"pong!").await.unwrap();
+    drop(target_peer);
+    let mut pong = Vec::new();
+    client_peer.read_to_end(&mut pong).await.unwrap();
+    assert_eq!(pong, b"pong!");
+    drop(client_peer);
+
+    let stats = splice.await.unwrap().unwrap();
+    assert_eq!((stats.sent, stats.received, stats.timed_out), (4, 5, false));
+    assert_eq!(tunnel.metrics().open, 0);
+}
+
+#[tokio::test]
+async fn closes_idle_tunnels() {
+    let (client, _client_peer) = tokio::io::duplex(64);
+    let (target, _target_peer) = tokio::io::duplex(64);
+
+    let stats = Tunnel::new().idle_timeout(Duration::from_millis(10)).splice(client, target).await.unwrap();
+    assert!(stats.timed_out);
+}
+
+#[tokio::test]
+async fn closes_tunnels_whose_writes_stall() {
+    let (client, mut client_peer) = tokio::io::duplex(64);
+    let (target, _target_peer) = tokio::io::duplex(64);
+
+    tokio::spawn(async move { client_peer.write_all(&[0; 256]).await });
+
+    let tunnel = Tunnel::new().idle_timeout(Duration::from_millis(20));
+    let stats = tokio::time::timeout(Duration::from_secs(5), tunnel.splice(client, target))
+        .await
+        .expect("the stalled tunnel wasn't closed")
+        .unwrap();
+    assert!(stats.timed_out);
+    assert_eq!(tunnel.metrics().open, 0);
+}
+
+#[tokio::test]
+async fn cancelled_splices_are_closed() {
+    let (client, _client_peer) = tokio::io::duplex(64);
+    let (target, _target_peer) = tokio::io::duplex(64);
+    let tunnel = Tunnel::new();
+
+    let splice = tokio::spawn({
+        let tunnel = tunnel.clone();
+        async move { tunnel.splice(client, target).await }
+    });
+
+    while tunnel.metrics().open == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    splice.abort();
+    assert!(splice.await.unwrap_err().is_cancelled());
+    assert_eq!(tunnel.metrics().open, 0);
+}
@@ -0,0 +1,198 @@
+use crate::core::seeder::{BoxBody, Guard, Seeder};
+use crate::http::{HttpRequest, StatusCode};
+use crate::tus::{FileTusStore, TusServer, TusStore, Upload, UPLOAD_EXPIRES, UPLOAD_OFFSET};
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn tus(method: &str, path: &str, headers: &[(&str, &str)], body: &[u8]) -> HttpRequest<BoxBody> {
+    let mut builder = HttpRequest::builder()
+        .method(method)
+        .uri(path)
+        .header("tus-resumable", "1.0.0");
+    for (name, value) in headers {
+        builder = builder.header(*name, *value);
+    }
+
+    builder.body(BoxBody::new(body.into())).unwrap()
+}
+
+fn patch(path: &str, offset: &str, body: &[u8]) -> HttpRequest<BoxBody> {
+    let headers = [("content-type", "application/offset+octet-stream"), ("upload-offset", offset)];
+    tus("PATCH", path, &headers, body)
+}
+
+#[tokio::test]
+async fn resumes_uploads_across_requests() {
+    let dir = std::env::temp_dir().join(format!("grazie-tus-{}", std::process::id()));
+    let completed = Arc::new(AtomicUsize::new(0));
+    let counter = completed.clone();
+    let server = TusServer::new("/uploads", FileTusStore::new(&dir))
+        .max_size(64)
+        .expiration(Duration::from_secs(60))
+        .on_complete(move |_| { counter.fetch_add(1, Ordering::SeqCst); });
+
+    let options = HttpRequest::builder()
+        .method("OPTIONS")
+        .uri("/uploads")
+        .body(BoxBody::empty())
+        .unwrap();
+    let options = server.respond(&options).await.unwrap();
+    assert_eq!(options.status(), StatusCode::NO_CONTENT);
+    assert_eq!(options.headers()["tus-max-size"], "64");
+
+    let oversized = tus("POST", "/uploads", &[("upload-length", "65")], b"");
+    let oversized = server.respond(&oversized).await.unwrap();
+    assert_eq!(oversized.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let headers = [("upload-length", "11"), ("upload-metadata", "filename aGVsbG8udHh0,private")];
+    let created = server.respond(&tus("POST", "/uploads", &headers, b"")).await.unwrap();
+    assert_eq!(created.status(), StatusCode::CREATED);
+    assert!(created.headers().contains_key(UPLOAD_EXPIRES));
+    let location = created.headers()["location"].to_str().unwrap().to_owned();
+    let id = location.strip_prefix("/uploads/").unwrap().to_owned();
+
+    let first = server.respond(&patch(&location, "0", b"hello")).await.unwrap();
+    assert_eq!(first.status(), StatusCode::NO_CONTENT);
+    assert_eq!(first.headers()[UPLOAD_OFFSET], "5");
+
+    // A client which lost the response asks where to resume, rather than resending.
+    let stale = server.respond(&patch(&location, "0", b"hello")).await.unwrap();
+    assert_eq!(stale.status(), StatusCode::CONFLICT);
+
+    let head = server.respond(&tus("HEAD", &location, &[], b"")).await.unwrap();
+    assert_eq!(head.headers()[UPLOAD_OFFSET], "5");
+    assert_eq!(head.headers()["upload-length"], "11");
+    assert_eq!(head.headers()["cache-control"], "no-store");
+
+    let overflow = server.respond(&patch(&location, "5", b" world!")).await.unwrap();
+    assert_eq!(overflow.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let last = server.respond(&patch(&location, "5", b" world")).await.unwrap();
+    assert_eq!(last.headers()[UPLOAD_OFFSET], "11");
+    assert!(!last.headers().contains_key(UPLOAD_EXPIRES));
+    assert_eq!(completed.load(Ordering::SeqCst), 1);
+
+    let upload = server.upload(&id).await.unwrap().unwrap();
+    assert_eq!(upload.metadata("filename").as_deref(), Some("hello.txt"));
+    assert_eq!(upload.metadata("private").as_deref(), Some(""));
+    assert_eq!(tokio::fs::read(server.store().path(&id)).await.unwrap(), b"hello world");
+
+    let deleted = server.respond(&tus("DELETE", &location, &[], b"")).await.unwrap();
+    assert_eq!(deleted.status(), StatusCode::NO_CONTENT);
+    assert!(server.store().get(&id).await.unwrap().is_none());
+
+    tokio::fs::remove_dir_all(&dir).await.unwrap();
+}
+
+#[tokio::test]
+async fn refuses_expired_uploads_and_other_versions() {
+    let dir = std::env::temp_dir().join(format!("grazie-tus-expired-{}", std::process::id()));
+    let server = TusServer::new("/uploads", FileTusStore::new(&dir)).expiration(Duration::ZERO);
+
+    let headers = [("upload-length", "4"), ("content-type", "application/offset+octet-stream")];
+    let created = server.respond(&tus("POST", "/uploads", &headers, b"ab")).await.unwrap();
+    assert_eq!(created.headers()[UPLOAD_OFFSET], "2");
+    let location = created.headers()["location"].to_str().unwrap().to_owned();
+
+    let expired = server.respond(&patch(&location, "2", b"cd")).await.unwrap();
+    assert_eq!(expired.status(), StatusCode::GONE);
+    let removed = server.respond(&tus("HEAD", &location, &[], b"")).await.unwrap();
+    assert_eq!(removed.status(), StatusCode::NOT_FOUND);
+
+    server.respond(&tus("POST", "/uploads", &[("upload-length", "4")], b"")).await.unwrap();
+    assert_eq!(server.store().purge_expired().await.unwrap(), 1);
+
+    let mut unversioned = HttpRequest::builder()
+        .method("POST")
+        .uri("/uploads")
+        .header("upload-length", "4")
+        .body(BoxBody::empty())
+        .unwrap();
+    match server.seed(Guard::Accessible(&mut unversioned)).await {
+        Guard::Inaccessible { rejection, .. } => {
+            assert_eq!(rejection.status, StatusCode::PRECONDITION_FAILED);
+        }
+        _ => { panic!("expected the request to be refused"); }
+    }
+
+    let mut other = tus("GET", "/assets/app.js", &[], b"");
+    assert!(matches!(server.seed(Guard::Accessible(&mut other)).await, Guard::Accessible(_)));
+
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+}
+
+/// A store whose first append never completes, like one whose client disconnects mid-upload.
+struct Stalling {
+    inner: FileTusStore,
+    stall: AtomicBool,
+}
+
+impl TusStore for Stalling {
+    async fn create(&self, upload: &Upload) -> io::Result<()> {
+        self.inner.create(upload).await
+    }
+
+    async fn get(&self, id: &str) -> io::Result<Option<Upload>> {
+        self.inner.get(id).await
+    }
+
+    async fn append(&self, id: &str, bytes: &[u8]) -> io::Result<u64> {
+        if self.stall.swap(false, Ordering::SeqCst) {
+            std::future::pending::<()>().await;
+        }
+
+        self.inner.append(id, bytes).await
+    }
+
+    async fn remove(&self, id: &str) -> io::Result<()> {
+        self.inner.remove(id).await
+    }
+}
+
+#[tokio::test]
+async fn resumes_uploads_after_a_cancelled_patch() {
+    let dir = std::env::temp_dir().join(format!("grazie-tus-cancelled-{}", std::process::id()));
+    let store = Stalling { inner: FileTusStore::new(&dir), stall: AtomicBool::new(true) };
+    let server = TusServer::new("/uploads", store);
+
+    let created = tus("POST", "/uploads", &[("upload-length", "5")], b"");
+    let created = server.respond(&created).await.unwrap();
+    let location = created.headers()["location"].to_str().unwrap().to_owned();
+
+    let cancelled = patch(&location, "0", b"hello");
+    let cancelled = tokio::time::timeout(Duration::from_millis(20), server.respond(&cancelled));
+    assert!(cancelled.await.is_err());
+
+    let resumed = server.respond(&patch(&location, "0", b"hello")).await.unwrap();
+    assert_eq!(resumed.status(), StatusCode::NO_CONTENT);
+    assert_eq!(resumed.headers()[UPLOAD_OFFSET], "5");
+
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+}
+
+#[tokio::test]
+async fn completes_empty_uploads_on_creation() {
+    let dir = std::env::temp_dir().join(format!("grazie-tus-empty-{}", std::process::id()));
+    let completed = Arc::new(AtomicUsize::new(0));
+    let counter = completed.clone();
+    let server = TusServer::new("/uploads", FileTusStore::new(&dir))
+        .expiration(Duration::from_secs(60))
+        .on_complete(move |upload| {
+            assert_eq!(upload.length, 0);
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+    let created = tus("POST", "/uploads", &[("upload-length", "0")], b"");
+    let created = server.respond(&created).await.unwrap();
+    assert_eq!(created.status(), StatusCode::CREATED);
+    assert!(!created.headers().contains_key(UPLOAD_EXPIRES));
+    assert_eq!(completed.load(Ordering::SeqCst), 1);
+
+    let location = created.headers()["location"].to_str().unwrap().to_owned();
+    let head = server.respond(&tus("HEAD", &location, &[], b"")).await.unwrap();
+    assert_eq!(head.headers()[UPLOAD_OFFSET], "0");
+
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+}
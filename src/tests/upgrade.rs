@@ -0,0 +1,27 @@
+use crate::upgrade::{receive, UpgradeListener};
+use std::net::TcpListener;
+
+#[tokio::test]
+async fn hands_listeners_to_the_new_process() {
+    let path = std::env::temp_dir().join(format!("grazie-upgrade-{}.sock", std::process::id()));
+    let old = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = old.local_addr().unwrap();
+
+    let upgrade = UpgradeListener::bind(&path).unwrap();
+    let sockets = [&old];
+    let (handed_off, received) = tokio::join!(upgrade.hand_off(&sockets), async {
+        let upgrade = receive(&path).await.unwrap();
+        assert_eq!(upgrade.listeners.len(), 1);
+        assert_eq!(upgrade.listeners[0].local_addr().unwrap(), addr);
+
+        let listener = upgrade.listeners[0].try_clone().unwrap();
+        upgrade.ready().await.unwrap();
+        listener
+    });
+    handed_off.unwrap();
+
+    drop(old);
+    let client = std::net::TcpStream::connect(addr).unwrap();
+    let (accepted, _) = received.accept().unwrap();
+    assert_eq!(accepted.peer_addr().unwrap(), client.local_addr().unwrap());
+}
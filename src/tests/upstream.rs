@@ -0,0 +1,112 @@
+use crate::core::seeder::BoxBody;
+use crate::http::{HttpRequest, Method};
+use crate::retry::{RetryError, RetryPolicy};
+use crate::upstream::{CanaryPool, Strategy, Track, Upstream, UpstreamPool};
+use std::time::Duration;
+
+fn authorities(pool: &UpstreamPool, count: usize) -> Vec<String> {
+    (0..count)
+        .map(|_| pool.select().unwrap().upstream().authority.clone())
+        .collect()
+}
+
+#[test]
+fn weighted_interleaves_by_weight() {
+    let pool = UpstreamPool::new(Strategy::Weighted, [
+        Upstream::new("a:80").weight(2),
+        Upstream::new("b:80"),
+    ]);
+
+    assert_eq!(authorities(&pool, 6), ["a:80", "b:80", "a:80", "a:80", "b:80", "a:80"]);
+}
+
+#[test]
+fn least_connections_avoids_busy_upstreams() {
+    let pool = UpstreamPool::new(Strategy::LeastConnections, [Upstream::new("a:80"), Upstream::new("b:80")]);
+
+    let busy = pool.select().unwrap();
+    for _ in 0..3 {
+        assert_ne!(pool.select().unwrap().upstream(), busy.upstream());
+    }
+}
+
+#[test]
+fn ejects_after_consecutive_failures() {
+    let pool = UpstreamPool::with_health(
+        Strategy::RoundRobin,
+        [Upstream::new("a:80"), Upstream::new("b:80")],
+        2,
+        Duration::from_secs(60),
+    );
+
+    for _ in 0..4 {
+        let lease = pool.select().unwrap();
+        match lease.upstream().authority.as_str() {
+            "a:80" => lease.failure(),
+            _ => lease.success(),
+        }
+    }
+
+    assert_eq!(authorities(&pool, 3), ["b:80", "b:80", "b:80"]);
+    assert_eq!(pool.upstreams().iter().filter(|(_, healthy)| *healthy).count(), 1);
+}
+
+#[tokio::test]
+async fn retries_on_other_upstreams() {
+    let pool = UpstreamPool::with_health(
+        Strategy::RoundRobin,
+        [Upstream::new("a:80"), Upstream::new("b:80")],
+        2,
+        Duration::from_secs(60),
+    );
+    let policy = RetryPolicy::new().backoff(Duration::from_millis(1), Duration::from_millis(5));
+    let send = |upstream: &Upstream| {
+        let authority = upstream.authority.clone();
+        async move {
+            match authority.as_str() {
+                "a:80" => { Err("connection reset") }
+                _ => { Ok(authority) }
+            }
+        }
+    };
+
+    assert_eq!(pool.retry(&policy, &Method::GET, send).await, Ok("b:80".to_owned()));
+    assert_eq!(pool.retry(&policy, &Method::POST, send).await, Err(RetryError::Failed("connection reset")));
+    assert_eq!(pool.upstreams().iter().filter(|(_, healthy)| *healthy).count(), 1);
+
+    let empty = UpstreamPool::new(Strategy::RoundRobin, []);
+    assert_eq!(empty.retry(&policy, &Method::GET, send).await, Err(RetryError::NoUpstream));
+}
+
+#[test]
+fn canaries_by_weight_and_stickiness() {
+    let canary = CanaryPool::new(
+        UpstreamPool::new(Strategy::RoundRobin, [Upstream::new("v1:80")]),
+        UpstreamPool::new(Strategy::RoundRobin, [Upstream::new("v2:80")]),
+        0,
+    );
+    let request = || HttpRequest::builder().body(BoxBody::empty()).unwrap();
+    let pinned = || HttpRequest::builder().header("cookie", "grazie_canary=canary").body(BoxBody::empty()).unwrap();
+
+    // Without any weight, even pinned clients are rolled back.
+    assert_eq!(canary.select(&pinned()).unwrap().0, Track::Stable);
+
+    canary.handle().set(100);
+    assert_eq!(canary.select(&request()).unwrap().1.upstream().authority, "v2:80");
+
+    canary.handle().set(5);
+    let canaried = (0..1000).filter(|_| canary.select(&request()).unwrap().0 == Track::Canary).count();
+    assert!((10..120).contains(&canaried), "{canaried} of 1000 requests were canaried");
+    assert_eq!(canary.select(&pinned()).unwrap().0, Track::Canary);
+
+    let header = HttpRequest::builder().header("x-canary", "stable").body(BoxBody::empty()).unwrap();
+    assert_eq!(canary.select(&header).unwrap().0, Track::Stable);
+    assert!(canary.cookie(Track::Canary).to_str().unwrap().starts_with("grazie_canary=canary;"));
+}
+
+#[test]
+#[should_panic(expected = "must have a valid name")]
+fn refuses_invalid_sticky_cookie_names() {
+    let pool = || UpstreamPool::new(Strategy::RoundRobin, [Upstream::new("v1:80")]);
+    let _ = CanaryPool::new(pool(), pool(), 0).cookie_name("canary track");
+}
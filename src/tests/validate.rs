@@ -0,0 +1,52 @@
+use crate::http::StatusCode;
+use crate::validate::{self, Validate, ValidationErrors};
+
+struct Address {
+    city: String,
+}
+
+impl Validate for Address {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        errors.check("city", validate::not_blank(&self.city), "required", "City is required.");
+        errors.into_result()
+    }
+}
+
+struct Signup {
+    name: String,
+    email: String,
+    age: u8,
+    address: Address,
+}
+
+impl Validate for Signup {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        errors.check("name", validate::length(&self.name, 1, 8), "length", "Name must be 1-8 characters.");
+        errors.check("email", validate::email(&self.email), "email", "Email is invalid.");
+        errors.check("age", validate::range(&self.age, &13, &120), "range", "Age must be 13-120.");
+        errors.nested("address", &self.address);
+        errors.into_result()
+    }
+}
+
+#[test]
+fn collects_every_failed_field() {
+    let signup = Signup {
+        name: "Grazie".to_owned(),
+        email: "grazie@localhost".to_owned(),
+        age: 9,
+        address: Address { city: " ".to_owned() },
+    };
+
+    let errors = signup.validate().unwrap_err();
+    assert!(errors.field("name").is_empty());
+    assert_eq!(errors.field("email")[0].code, "email");
+    assert_eq!(errors.field("age")[0].code, "range");
+    assert_eq!(errors.field("address.city")[0].code, "required");
+
+    let rejection = errors.into_rejection();
+    assert_eq!(rejection.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(rejection.code, "validation_failed");
+}
@@ -0,0 +1,95 @@
+use crate::core::seeder::BoxBody;
+use crate::http::{HttpRequest, StatusCode};
+use crate::seeders::signature::{HmacAlgorithm, Verifier};
+use crate::webhooks::{GitHub, SlackEvent, Slack, Stripe};
+use std::time::{Duration, UNIX_EPOCH};
+
+fn request(headers: &[(&str, &str)], body: &str) -> HttpRequest<BoxBody> {
+    let mut builder = HttpRequest::builder().method("POST").uri("/hooks");
+    for (name, value) in headers {
+        builder = builder.header(*name, *value);
+    }
+
+    builder.body(BoxBody::new(body.as_bytes().into())).unwrap()
+}
+
+#[test]
+fn verifies_github_deliveries() {
+    let headers = [
+        (
+            "x-hub-signature-256",
+            "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17",
+        ),
+        ("x-github-event", "ping"),
+        ("x-github-delivery", "72d3162e-cc78-11e3-81ab-4c9367dc0958"),
+    ];
+    let github = GitHub::new("It's a Secret to Everybody");
+
+    assert!(github.verify(&request(&headers, "Hello, World!")).is_ok());
+    assert!(github.verify(&request(&headers, "Hello, World?")).is_err());
+
+    let ping = request(&headers, r#"{"zen":"Keep it logically awesome."}"#);
+    let event = GitHub::event::<serde_json::Value>(&ping).unwrap();
+    assert_eq!(event.name, "ping");
+    assert_eq!(event.payload["zen"], "Keep it logically awesome.");
+}
+
+#[test]
+fn verifies_stripe_events_within_tolerance() {
+    let body = concat!(
+        r#"{"id":"evt_1","type":"invoice.paid","created":1700000000,"#,
+        r#""data":{"object":{"amount":500}}}"#,
+    );
+    let signature: String = HmacAlgorithm::Sha256
+        .sign(b"whsec_test", &[b"1700000000", b".", body.as_bytes()])
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    let header = format!("t=1700000000,v1=deadbeef,v1={signature}");
+
+    let stripe = Stripe::new("whsec_test");
+    let signed = request(&[("stripe-signature", &header)], body);
+
+    assert!(stripe.verify_at(&signed, UNIX_EPOCH + Duration::from_secs(1700000100)).is_ok());
+
+    let later = UNIX_EPOCH + Duration::from_secs(1700001000);
+    let stale = stripe.verify_at(&signed, later).unwrap_err();
+    assert_eq!(stale.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(stale.code, "stale_signature");
+
+    let event = Stripe::event::<serde_json::Value>(&signed).unwrap();
+    assert_eq!(event.kind, "invoice.paid");
+    assert_eq!(event.data.object["amount"], 500);
+}
+
+#[test]
+fn verifies_slack_requests() {
+    let body = "token=xyzz0WbapA4vBCDEFasx0q6G&team_id=T1DC2JH3J&team_domain=testteamnow\
+        &channel_id=G8PSS9T3V&channel_name=foobar&user_id=U2CERLKJA&user_name=roadrunner\
+        &command=%2Fwebhook-collect&text=\
+        &response_url=https%3A%2F%2Fhooks.slack.com%2Fcommands%2FT1DC2JH3J%2F397700885554\
+        %2F96rGlfmibIGlgcZRskXaIFfN\
+        &trigger_id=398738663015.47445629121.803a0bc887a14d10d2c447fce8b6703c";
+    let headers = [
+        (
+            "x-slack-signature",
+            "v0=a2114d57b48eac39b9ad189dd8316235a7b4a8d21a10bd27519666489c69b503",
+        ),
+        ("x-slack-request-timestamp", "1531420618"),
+    ];
+    let slack = Slack::new("8f742231b10e8888abcd99yyyzzz85a5");
+    let now = UNIX_EPOCH + Duration::from_secs(1531420700);
+
+    assert!(slack.verify_at(&request(&headers, body), now).is_ok());
+    assert!(slack.verify_at(&request(&headers, "token=forged"), now).is_err());
+
+    let body = concat!(
+        r#"{"type":"url_verification","#,
+        r#""challenge":"3eZbrw1aBm2rZgRNFdxV2595E9CY3gmdALWMmHkvFXO7tYXAYM8P"}"#,
+    );
+    let challenge = request(&[], body);
+    match Slack::event::<serde_json::Value>(&challenge).unwrap() {
+        SlackEvent::UrlVerification { challenge } => { assert!(challenge.starts_with("3eZbrw")); }
+        other => panic!("unexpected event: {other:?}"),
+    }
+}
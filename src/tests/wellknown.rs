@@ -0,0 +1,30 @@
+use crate::core::seeder::BoxBody;
+use crate::http::{HttpRequest, StatusCode};
+use crate::wellknown::{SecurityTxt, WellKnown};
+
+fn request(method: &str, path: &str) -> HttpRequest<BoxBody> {
+    HttpRequest::builder().method(method).uri(path).body(BoxBody::empty()).unwrap()
+}
+
+#[test]
+fn serves_configured_documents() {
+    let wellknown = WellKnown::new()
+        .robots("User-agent: *\nDisallow: /admin\n")
+        .security(&SecurityTxt::new().contact("mailto:security@example.com").expires("2030-01-01T00:00:00Z"))
+        .well_known_with("change-password", "text/plain", |_| BoxBody::new(b"/account".as_slice().into()));
+
+    let response = wellknown.respond(&request("GET", "/.well-known/security.txt")).unwrap();
+    assert_eq!(
+        response.body().raw_bytes(),
+        b"Contact: mailto:security@example.com\nExpires: 2030-01-01T00:00:00Z\n"
+    );
+
+    let response = wellknown.respond(&request("HEAD", "/robots.txt")).unwrap();
+    assert_eq!(response.headers()["content-length"], "31");
+    assert!(response.body().raw_bytes().is_empty());
+
+    let response = wellknown.respond(&request("POST", "/.well-known/change-password")).unwrap();
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+    assert!(wellknown.respond(&request("GET", "/users")).is_none());
+}
@@ -0,0 +1,319 @@
+//! `CONNECT` tunneling, for acting as a forward proxy.
+//!
+//! A `ConnectSeeder` approves `CONNECT` requests against an allow list of targets, attaching the
+//! approved `ConnectTarget`. Once the client has been answered with `200 OK`, a `Tunnel` splices
+//! bytes between the client's connection and the target's until either side closes, or the tunnel
+//! sits idle for too long.
+
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::{HttpRequest, HttpResponse, Method, StatusCode};
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// The target of an approved `CONNECT` request, as an extension of the request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ConnectTarget {
+    /// The host to connect to.
+    pub host: String,
+
+    /// The port to connect to.
+    pub port: u16,
+}
+
+impl ConnectTarget {
+    /// Gets the target of a `CONNECT` request, from its authority-form URI.
+    pub fn from_request(request: &HttpRequest<BoxBody>) -> Option<ConnectTarget> {
+        if request.method() != Method::CONNECT {
+            return None;
+        }
+
+        let authority = request.uri().authority()?;
+
+        Some(ConnectTarget {
+            host: authority
+                .host()
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_ascii_lowercase(),
+            port: authority.port_u16()?,
+        })
+    }
+}
+
+/// A `Seeder` which approves `CONNECT` requests to allowed targets.
+///
+/// Hosts are allowed exactly, or by suffix with a leading `.` (`.example.com` allows every
+/// subdomain of `example.com`). Only port 443 is allowed unless other ports are added. Approved
+/// requests have their `ConnectTarget` attached, and every other request passes through untouched.
+pub struct ConnectSeeder {
+    hosts: Vec<String>,
+    ports: Vec<u16>,
+}
+
+impl ConnectSeeder {
+    /// Constructs a new `ConnectSeeder`, which doesn't allow any hosts.
+    pub fn new() -> ConnectSeeder {
+        ConnectSeeder {
+            hosts: Vec::new(),
+            ports: vec![443],
+        }
+    }
+
+    /// Allows tunnels to a host, or to every subdomain of a domain when it starts with a `.`.
+    pub fn allow_host(mut self, host: impl Into<String>) -> ConnectSeeder {
+        self.hosts.push(host.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Allows tunnels to a port.
+    pub fn allow_port(mut self, port: u16) -> ConnectSeeder {
+        self.ports.push(port);
+        self
+    }
+
+    /// Checks whether a target may be tunneled to.
+    pub fn allows(&self, target: &ConnectTarget) -> bool {
+        let host_allowed = self.hosts.iter().any(|host| match host.strip_prefix('.') {
+            Some(domain) => { target.host == domain || target.host.ends_with(host.as_str()) }
+            None => { target.host == *host }
+        });
+
+        host_allowed && self.ports.contains(&target.port)
+    }
+}
+
+impl Default for ConnectSeeder {
+    fn default() -> ConnectSeeder {
+        ConnectSeeder::new()
+    }
+}
+
+impl Seeder for ConnectSeeder {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        if request.method() != Method::CONNECT {
+            return Guard::Accessible(request);
+        }
+
+        let (status, code, message) = match ConnectTarget::from_request(request) {
+            Some(target) if self.allows(&target) => {
+                request.extensions_mut().insert(target);
+                return Guard::Accessible(request);
+            }
+            Some(_) => {
+                (
+                    StatusCode::FORBIDDEN,
+                    "tunnel_forbidden",
+                    "Tunnels to this target aren't allowed.",
+                )
+            }
+            None => {
+                (
+                    StatusCode::BAD_REQUEST,
+                    "invalid_connect_target",
+                    "The CONNECT target must be a host and port.",
+                )
+            }
+        };
+
+        let response = HttpResponse::builder().status(status).body(BoxBody::empty()).unwrap();
+
+        Guard::Inaccessible {
+            request,
+            respondent: Respondent::Respond(Box::new(response)),
+            rejection: Rejection::new(status, code, message),
+        }
+    }
+}
+
+/// The outcome of a spliced tunnel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TunnelStats {
+    /// The number of bytes sent from the client to the target.
+    pub sent: u64,
+
+    /// The number of bytes received from the target by the client.
+    pub received: u64,
+
+    /// Whether the tunnel was closed for sitting idle, rather than by either side.
+    pub timed_out: bool,
+}
+
+/// A snapshot of a `Tunnel`'s counters, across every tunnel it has spliced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TunnelMetrics {
+    /// The number of tunnels currently open.
+    pub open: u64,
+
+    /// The total number of bytes sent from clients to targets.
+    pub sent: u64,
+
+    /// The total number of bytes received from targets by clients.
+    pub received: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    open: AtomicU64,
+    sent: AtomicU64,
+    received: AtomicU64,
+}
+
+/// Counts a tunnel as open until it's dropped, so that a cancelled splice is still closed.
+struct Open<'a>(&'a Counters);
+
+impl<'a> Open<'a> {
+    fn count(counters: &'a Counters) -> Open<'a> {
+        counters.open.fetch_add(1, Ordering::Relaxed);
+        Open(counters)
+    }
+}
+
+impl Drop for Open<'_> {
+    fn drop(&mut self) {
+        self.0.open.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Splices bytes between clients and their `CONNECT` targets.
+///
+/// `Tunnel`s are cheap to clone, and all clones share the same counters.
+#[derive(Debug, Clone)]
+pub struct Tunnel {
+    idle_timeout: Duration,
+    counters: Arc<Counters>,
+}
+
+impl Tunnel {
+    /// Constructs a new `Tunnel`, closing tunnels after 5 minutes without traffic.
+    pub fn new() -> Tunnel {
+        Tunnel {
+            idle_timeout: Duration::from_secs(300),
+            counters: Arc::new(Counters::default()),
+        }
+    }
+
+    /// Sets how long a tunnel may go without traffic in either direction before it's closed.
+    pub fn idle_timeout(mut self, idle_timeout: Duration) -> Tunnel {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Gets a snapshot of this tunnel's counters.
+    pub fn metrics(&self) -> TunnelMetrics {
+        TunnelMetrics {
+            open: self.counters.open.load(Ordering::Relaxed),
+            sent: self.counters.sent.load(Ordering::Relaxed),
+            received: self.counters.received.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Splices bytes between a client and its target, until both sides have closed or the tunnel
+    /// goes idle. When one side closes, the other side's write half is shut down, so half-closed
+    /// connections drain properly.
+    ///
+    /// A write which can't complete within the idle timeout, as its side has stopped reading, also
+    /// closes the tunnel as idle.
+    pub async fn splice<C, T>(&self, client: C, target: T) -> io::Result<TunnelStats>
+    where
+        C: AsyncRead + AsyncWrite,
+        T: AsyncRead + AsyncWrite,
+    {
+        let _open = Open::count(&self.counters);
+
+        self.copy(client, target).await
+    }
+
+    /// Runs a write to one side of a tunnel, returning `false` if it didn't complete within the
+    /// idle timeout.
+    async fn within(&self, write: impl Future<Output = io::Result<()>>) -> io::Result<bool> {
+        match tokio::time::timeout(self.idle_timeout, write).await {
+            Ok(result) => { result.map(|()| true) }
+            Err(_) => { Ok(false) }
+        }
+    }
+
+    async fn copy<C, T>(&self, client: C, target: T) -> io::Result<TunnelStats>
+    where
+        C: AsyncRead + AsyncWrite,
+        T: AsyncRead + AsyncWrite,
+    {
+        let (mut client_read, mut client_write) = tokio::io::split(client);
+        let (mut target_read, mut target_write) = tokio::io::split(target);
+
+        let mut upstream = vec![0u8; 16 * 1024];
+        let mut downstream = vec![0u8; 16 * 1024];
+        let mut client_open = true;
+        let mut target_open = true;
+        let mut stats = TunnelStats::default();
+
+        while client_open || target_open {
+            tokio::select! {
+                read = client_read.read(&mut upstream), if client_open => {
+                    match read? {
+                        0 => {
+                            client_open = false;
+
+                            if !self.within(target_write.shutdown()).await? {
+                                stats.timed_out = true;
+                                break;
+                            }
+                        }
+                        read => {
+                            if !self.within(target_write.write_all(&upstream[..read])).await? {
+                                stats.timed_out = true;
+                                break;
+                            }
+
+                            stats.sent += read as u64;
+                            self.counters.sent.fetch_add(read as u64, Ordering::Relaxed);
+                        }
+                    }
+                }
+                read = target_read.read(&mut downstream), if target_open => {
+                    match read? {
+                        0 => {
+                            target_open = false;
+
+                            if !self.within(client_write.shutdown()).await? {
+                                stats.timed_out = true;
+                                break;
+                            }
+                        }
+                        read => {
+                            if !self.within(client_write.write_all(&downstream[..read])).await? {
+                                stats.timed_out = true;
+                                break;
+                            }
+
+                            stats.received += read as u64;
+                            self.counters.received.fetch_add(read as u64, Ordering::Relaxed);
+                        }
+                    }
+                }
+                _ = tokio::time::sleep(self.idle_timeout) => {
+                    stats.timed_out = true;
+                    break;
+                }
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+impl Default for Tunnel {
+    fn default() -> Tunnel {
+        Tunnel::new()
+    }
+}
@@ -0,0 +1,627 @@
+//! Resumable uploads, following version 1.0.0 of the tus protocol.
+//!
+//! `TusServer` handles the protocol's requests under a mount path: clients create an upload with
+//! a `POST` to the mount path, find how much of it the server has with a `HEAD` to the upload's
+//! URL, and send the rest in `PATCH` requests, picking up where they left off after a dropped
+//! connection. The `creation`, `creation-with-upload`, `expiration` and `termination` extensions
+//! are supported.
+//!
+//! Uploads are kept by a `TusStore`, which is `FileTusStore` by default:
+//!
+//! ```no_run
+//! use grazie::tus::{FileTusStore, TusServer};
+//! use std::time::Duration;
+//!
+//! let uploads = TusServer::new("/uploads", FileTusStore::new("/var/lib/app/uploads"))
+//!     .max_size(1024 * 1024 * 1024)
+//!     .expiration(Duration::from_secs(24 * 60 * 60))
+//!     .on_complete(|upload| println!("received {} bytes as {}", upload.length, upload.id));
+//! ```
+
+use crate::auth::session::token;
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::{HeaderName, HeaderValue, CACHE_CONTROL, CONTENT_TYPE, LOCATION};
+use crate::http::{HttpRequest, HttpResponse, Method, StatusCode};
+use crate::util::{base64_decode, http_date};
+use std::collections::HashSet;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+
+/// The version of the tus protocol `TusServer` speaks.
+pub const TUS_VERSION: &str = "1.0.0";
+
+/// The media type of `PATCH` request bodies.
+pub const OFFSET_OCTET_STREAM: &str = "application/offset+octet-stream";
+
+/// The header carrying the protocol version a request or response uses.
+pub const TUS_RESUMABLE: HeaderName = HeaderName::from_static("tus-resumable");
+
+/// The header listing the protocol versions the server supports.
+pub const TUS_VERSION_HEADER: HeaderName = HeaderName::from_static("tus-version");
+
+/// The header listing the protocol extensions the server supports.
+pub const TUS_EXTENSION: HeaderName = HeaderName::from_static("tus-extension");
+
+/// The header carrying the largest upload the server accepts, in bytes.
+pub const TUS_MAX_SIZE: HeaderName = HeaderName::from_static("tus-max-size");
+
+/// The header carrying the total length of an upload, in bytes.
+pub const UPLOAD_LENGTH: HeaderName = HeaderName::from_static("upload-length");
+
+/// The header carrying how many bytes of an upload have been received.
+pub const UPLOAD_OFFSET: HeaderName = HeaderName::from_static("upload-offset");
+
+/// The header carrying an upload's metadata, as comma-separated `key base64(value)` pairs.
+pub const UPLOAD_METADATA: HeaderName = HeaderName::from_static("upload-metadata");
+
+/// The header carrying when an incomplete upload expires.
+pub const UPLOAD_EXPIRES: HeaderName = HeaderName::from_static("upload-expires");
+
+/// The extensions `TusServer` supports.
+const EXTENSIONS: &str = "creation,creation-with-upload,expiration,termination";
+
+/// An upload, complete or not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Upload {
+    /// The upload's ID, which is the last segment of its URL. IDs are random, with 256 bits of
+    /// entropy, so that uploads can't be found or appended to by guessing their URLs.
+    pub id: String,
+
+    /// The total length of the upload, in bytes.
+    pub length: u64,
+
+    /// How many bytes of the upload have been received.
+    pub offset: u64,
+
+    /// The upload's metadata, as sent in its `Upload-Metadata` header.
+    pub metadata: String,
+
+    /// When the upload expires if it's still incomplete.
+    pub expires: Option<SystemTime>,
+}
+
+impl Upload {
+    /// Checks whether every byte of the upload has been received.
+    pub fn is_complete(&self) -> bool {
+        self.offset >= self.length
+    }
+
+    /// Gets a metadata value, decoded from base64. Keys sent without a value get an empty
+    /// string, and values which aren't UTF-8 are skipped.
+    pub fn metadata(&self, key: &str) -> Option<String> {
+        parse_metadata(&self.metadata)?
+            .into_iter()
+            .find(|(name, _)| name == key)
+            .and_then(|(_, value)| String::from_utf8(value).ok())
+    }
+
+    /// Checks whether the upload is incomplete and past its expiry.
+    fn is_expired(&self, now: SystemTime) -> bool {
+        !self.is_complete() && self.expires.is_some_and(|expires| expires <= now)
+    }
+}
+
+/// Parses `Upload-Metadata` pairs, or returns `None` if they're malformed.
+fn parse_metadata(metadata: &str) -> Option<Vec<(String, Vec<u8>)>> {
+    metadata
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let mut parts = pair.split(' ');
+            let key = parts.next().filter(|key| !key.is_empty())?;
+            let value = match parts.next() {
+                Some(value) => { base64_decode(value)? }
+                None => { Vec::new() }
+            };
+
+            match parts.next() {
+                Some(_) => { None }
+                None => { Some((key.to_owned(), value)) }
+            }
+        })
+        .collect()
+}
+
+/// Storage for uploads.
+///
+/// `TusServer` checks offsets and lengths before appending, and never appends to the same upload
+/// from two requests at once, so stores only need to keep bytes in order.
+pub trait TusStore: Send + Sync {
+    /// Creates an empty upload.
+    fn create(&self, upload: &Upload) -> impl Future<Output = io::Result<()>> + Send;
+
+    /// Gets an upload, with its current offset, or `None` if it doesn't exist.
+    fn get(&self, id: &str) -> impl Future<Output = io::Result<Option<Upload>>> + Send;
+
+    /// Appends bytes to an upload, returning its new offset.
+    fn append(&self, id: &str, bytes: &[u8]) -> impl Future<Output = io::Result<u64>> + Send;
+
+    /// Removes an upload and its bytes. Removing an upload which doesn't exist is not an error.
+    fn remove(&self, id: &str) -> impl Future<Output = io::Result<()>> + Send;
+}
+
+/// A `TusStore` which keeps uploads in a directory, as a `<id>.bin` file of the bytes received
+/// so far and a `<id>.info` file describing the upload.
+pub struct FileTusStore {
+    directory: PathBuf,
+}
+
+impl FileTusStore {
+    /// Constructs a new `FileTusStore` over a directory, which is created on the first upload if
+    /// it doesn't exist.
+    pub fn new(directory: impl Into<PathBuf>) -> FileTusStore {
+        FileTusStore {
+            directory: directory.into(),
+        }
+    }
+
+    /// Gets the path of an upload's bytes, for reading a completed upload.
+    pub fn path(&self, id: &str) -> PathBuf {
+        self.directory.join(format!("{id}.bin"))
+    }
+
+    fn info_path(&self, id: &str) -> PathBuf {
+        self.directory.join(format!("{id}.info"))
+    }
+
+    /// Removes every incomplete upload which has expired, returning how many were removed.
+    ///
+    /// Expired uploads are refused as soon as they expire, but their files are only removed when
+    /// they're next requested, or by this.
+    pub async fn purge_expired(&self) -> io::Result<usize> {
+        let mut entries = match tokio::fs::read_dir(&self.directory).await {
+            Ok(entries) => { entries }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => { return Ok(0); }
+            Err(error) => { return Err(error); }
+        };
+
+        let now = SystemTime::now();
+        let mut purged = 0;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            let Some(id) = name.to_str().and_then(|name| name.strip_suffix(".info")) else {
+                continue;
+            };
+
+            if let Some(upload) = self.get(id).await? {
+                if upload.is_expired(now) {
+                    self.remove(id).await?;
+                    purged += 1;
+                }
+            }
+        }
+
+        Ok(purged)
+    }
+}
+
+impl TusStore for FileTusStore {
+    async fn create(&self, upload: &Upload) -> io::Result<()> {
+        tokio::fs::create_dir_all(&self.directory).await?;
+
+        let expires = upload
+            .expires
+            .and_then(|expires| expires.duration_since(UNIX_EPOCH).ok())
+            .map(|expires| expires.as_secs().to_string())
+            .unwrap_or_default();
+        let info = format!(
+            "length {}\nexpires {expires}\nmetadata {}\n",
+            upload.length,
+            upload.metadata,
+        );
+
+        tokio::fs::write(self.path(&upload.id), b"").await?;
+        tokio::fs::write(self.info_path(&upload.id), info).await
+    }
+
+    async fn get(&self, id: &str) -> io::Result<Option<Upload>> {
+        let info = match tokio::fs::read_to_string(self.info_path(id)).await {
+            Ok(info) => { info }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => { return Ok(None); }
+            Err(error) => { return Err(error); }
+        };
+
+        let mut upload = Upload {
+            id: id.to_owned(),
+            length: 0,
+            offset: tokio::fs::metadata(self.path(id)).await?.len(),
+            metadata: String::new(),
+            expires: None,
+        };
+
+        for line in info.lines() {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+
+            match key {
+                "length" => {
+                    upload.length = value.parse().map_err(|_| {
+                        io::Error::new(io::ErrorKind::InvalidData, "invalid upload length")
+                    })?;
+                }
+                "expires" => {
+                    let secs = value.parse().ok();
+                    upload.expires = secs.map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+                }
+                "metadata" => { upload.metadata = value.to_owned(); }
+                _ => {}
+            }
+        }
+
+        Ok(Some(upload))
+    }
+
+    async fn append(&self, id: &str, bytes: &[u8]) -> io::Result<u64> {
+        let mut file = tokio::fs::OpenOptions::new().append(true).open(self.path(id)).await?;
+        file.write_all(bytes).await?;
+        file.flush().await?;
+
+        Ok(file.metadata().await?.len())
+    }
+
+    async fn remove(&self, id: &str) -> io::Result<()> {
+        for path in [self.info_path(id), self.path(id)] {
+            match tokio::fs::remove_file(path).await {
+                Ok(()) => {}
+                Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+                Err(error) => { return Err(error); }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Called with each upload once it completes.
+type CompleteHook = Box<dyn Fn(&Upload) + Send + Sync>;
+
+/// A `Seeder` which handles tus upload requests under a mount path.
+///
+/// Uploads are created at the mount path, and live at `<mount>/<id>`. Every request but `OPTIONS`
+/// must carry `Tus-Resumable: 1.0.0`, or it's refused with `412 Precondition Failed`. When an
+/// expiration is set, incomplete uploads are refused with `410 Gone` once they expire. Requests
+/// for any other path pass straight through.
+pub struct TusServer<S> {
+    mount: String,
+    store: S,
+    max_size: Option<u64>,
+    expiration: Option<Duration>,
+    on_complete: Option<CompleteHook>,
+    writing: Mutex<HashSet<String>>,
+}
+
+impl<S: TusStore> TusServer<S> {
+    /// Constructs a new `TusServer`, handling uploads under the provided mount path (such as
+    /// `/uploads`) and keeping them in the provided store. Uploads have no size limit, and never
+    /// expire.
+    pub fn new(mount: &str, store: S) -> TusServer<S> {
+        TusServer {
+            mount: mount.trim_end_matches('/').to_owned(),
+            store,
+            max_size: None,
+            expiration: None,
+            on_complete: None,
+            writing: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Sets the largest upload accepted, in bytes.
+    pub fn max_size(mut self, max_size: u64) -> TusServer<S> {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Sets how long after creation an incomplete upload expires.
+    pub fn expiration(mut self, expiration: Duration) -> TusServer<S> {
+        self.expiration = Some(expiration);
+        self
+    }
+
+    /// Sets a function called with each upload once every byte of it has been received.
+    pub fn on_complete<F>(mut self, on_complete: F) -> TusServer<S>
+    where
+        F: Fn(&Upload) + Send + Sync + 'static,
+    {
+        self.on_complete = Some(Box::new(on_complete));
+        self
+    }
+
+    /// Gets the server's store.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Gets an upload, or `None` if it doesn't exist or has expired.
+    pub async fn upload(&self, id: &str) -> io::Result<Option<Upload>> {
+        Ok(self.store.get(id).await?.filter(|upload| !upload.is_expired(SystemTime::now())))
+    }
+
+    /// Handles a request, if it's for the mount path or an upload under it.
+    pub async fn respond(&self, request: &HttpRequest<BoxBody>) -> Option<HttpResponse<BoxBody>> {
+        let path = request.uri().path().strip_prefix(self.mount.as_str())?;
+        let id = match path.trim_end_matches('/') {
+            "" => { None }
+            rest => {
+                let id = rest.strip_prefix('/')?;
+                if id.is_empty() || !id.bytes().all(|byte| byte.is_ascii_alphanumeric()) {
+                    return None;
+                }
+
+                Some(id)
+            }
+        };
+
+        if request.method() == Method::OPTIONS {
+            let mut response = response(StatusCode::NO_CONTENT);
+            let version = HeaderValue::from_static(TUS_VERSION);
+            response.headers_mut().insert(TUS_VERSION_HEADER, version);
+            response.headers_mut().insert(TUS_EXTENSION, HeaderValue::from_static(EXTENSIONS));
+            if let Some(max_size) = self.max_size {
+                response.headers_mut().insert(TUS_MAX_SIZE, max_size.into());
+            }
+
+            return Some(response);
+        }
+
+        if request.headers().get(TUS_RESUMABLE).is_none_or(|version| version != TUS_VERSION) {
+            let mut response = response(StatusCode::PRECONDITION_FAILED);
+            let version = HeaderValue::from_static(TUS_VERSION);
+            response.headers_mut().insert(TUS_VERSION_HEADER, version);
+            return Some(response);
+        }
+
+        let result = match (request.method(), id) {
+            (&Method::POST, None) => { self.create(request).await }
+            (&Method::HEAD, Some(id)) => { self.head(id).await }
+            (&Method::PATCH, Some(id)) => { self.patch(request, id).await }
+            (&Method::DELETE, Some(id)) => { self.delete(id).await }
+            _ => { Ok(response(StatusCode::METHOD_NOT_ALLOWED)) }
+        };
+
+        Some(result.unwrap_or_else(|_| response(StatusCode::INTERNAL_SERVER_ERROR)))
+    }
+
+    async fn create(&self, request: &HttpRequest<BoxBody>) -> io::Result<HttpResponse<BoxBody>> {
+        let Some(length) = header_u64(request, &UPLOAD_LENGTH) else {
+            return Ok(response(StatusCode::BAD_REQUEST));
+        };
+        if self.max_size.is_some_and(|max_size| length > max_size) {
+            return Ok(response(StatusCode::PAYLOAD_TOO_LARGE));
+        }
+
+        let metadata = match request.headers().get(UPLOAD_METADATA) {
+            Some(metadata) => {
+                match metadata.to_str().ok().filter(|metadata| parse_metadata(metadata).is_some()) {
+                    Some(metadata) => { metadata.to_owned() }
+                    None => { return Ok(response(StatusCode::BAD_REQUEST)); }
+                }
+            }
+            None => { String::new() }
+        };
+
+        let body = request.body().raw_bytes();
+        if !body.is_empty() && !is_offset_stream(request) {
+            return Ok(response(StatusCode::UNSUPPORTED_MEDIA_TYPE));
+        }
+        if body.len() as u64 > length {
+            return Ok(response(StatusCode::PAYLOAD_TOO_LARGE));
+        }
+
+        let mut upload = Upload {
+            id: token(),
+            length,
+            offset: 0,
+            metadata,
+            expires: self.expiration.map(|expiration| SystemTime::now() + expiration),
+        };
+        self.store.create(&upload).await?;
+
+        // With `creation-with-upload`, the first chunk can be sent with the creation request.
+        if !body.is_empty() {
+            upload.offset = self.store.append(&upload.id, body).await?;
+        }
+        // An empty upload is complete as soon as it's created.
+        self.completed(&upload);
+
+        let mut response = response(StatusCode::CREATED);
+        let location = format!("{}/{}", self.mount, upload.id);
+        response.headers_mut().insert(LOCATION, HeaderValue::try_from(location).unwrap());
+        if !body.is_empty() {
+            response.headers_mut().insert(UPLOAD_OFFSET, upload.offset.into());
+        }
+        expires(&mut response, &upload);
+
+        Ok(response)
+    }
+
+    async fn head(&self, id: &str) -> io::Result<HttpResponse<BoxBody>> {
+        let upload = match self.live(id).await? {
+            Ok(upload) => { upload }
+            Err(response) => { return Ok(response); }
+        };
+
+        let mut response = response(StatusCode::OK);
+        response.headers_mut().insert(UPLOAD_OFFSET, upload.offset.into());
+        response.headers_mut().insert(UPLOAD_LENGTH, upload.length.into());
+        response.headers_mut().insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        let metadata = HeaderValue::try_from(upload.metadata.as_str()).ok();
+        if let Some(metadata) = metadata.filter(|_| !upload.metadata.is_empty()) {
+            response.headers_mut().insert(UPLOAD_METADATA, metadata);
+        }
+        expires(&mut response, &upload);
+
+        Ok(response)
+    }
+
+    async fn patch(
+        &self,
+        request: &HttpRequest<BoxBody>,
+        id: &str,
+    ) -> io::Result<HttpResponse<BoxBody>> {
+        if !is_offset_stream(request) {
+            return Ok(response(StatusCode::UNSUPPORTED_MEDIA_TYPE));
+        }
+        let Some(offset) = header_u64(request, &UPLOAD_OFFSET) else {
+            return Ok(response(StatusCode::BAD_REQUEST));
+        };
+
+        // Only one request may append to an upload at a time, so that offsets can't interleave.
+        // The claim is released on drop, so a request which is cancelled mid-append (such as by
+        // its client disconnecting) doesn't keep the upload from being resumed.
+        let Some(_writing) = Writing::claim(&self.writing, id) else {
+            return Ok(response(StatusCode::CONFLICT));
+        };
+
+        self.append(request, id, offset).await
+    }
+
+    async fn append(
+        &self,
+        request: &HttpRequest<BoxBody>,
+        id: &str,
+        offset: u64,
+    ) -> io::Result<HttpResponse<BoxBody>> {
+        let mut upload = match self.live(id).await? {
+            Ok(upload) => { upload }
+            Err(response) => { return Ok(response); }
+        };
+
+        let body = request.body().raw_bytes();
+        if offset != upload.offset {
+            return Ok(response(StatusCode::CONFLICT));
+        }
+        if upload.offset + body.len() as u64 > upload.length {
+            return Ok(response(StatusCode::PAYLOAD_TOO_LARGE));
+        }
+
+        if !body.is_empty() {
+            upload.offset = self.store.append(id, body).await?;
+            self.completed(&upload);
+        }
+
+        let mut response = response(StatusCode::NO_CONTENT);
+        response.headers_mut().insert(UPLOAD_OFFSET, upload.offset.into());
+        expires(&mut response, &upload);
+
+        Ok(response)
+    }
+
+    async fn delete(&self, id: &str) -> io::Result<HttpResponse<BoxBody>> {
+        if self.store.get(id).await?.is_none() {
+            return Ok(response(StatusCode::NOT_FOUND));
+        }
+
+        self.store.remove(id).await?;
+        Ok(response(StatusCode::NO_CONTENT))
+    }
+
+    /// Gets an upload which exists and hasn't expired, or the response refusing it. Expired
+    /// uploads are removed.
+    async fn live(&self, id: &str) -> io::Result<Result<Upload, HttpResponse<BoxBody>>> {
+        match self.store.get(id).await? {
+            Some(upload) if upload.is_expired(SystemTime::now()) => {
+                self.store.remove(id).await?;
+                Ok(Err(response(StatusCode::GONE)))
+            }
+            Some(upload) => { Ok(Ok(upload)) }
+            None => { Ok(Err(response(StatusCode::NOT_FOUND))) }
+        }
+    }
+
+    fn completed(&self, upload: &Upload) {
+        if let Some(on_complete) = self.on_complete.as_ref().filter(|_| upload.is_complete()) {
+            on_complete(upload);
+        }
+    }
+}
+
+/// A claim on appending to an upload, released when it's dropped.
+struct Writing<'a> {
+    writing: &'a Mutex<HashSet<String>>,
+    id: String,
+}
+
+impl<'a> Writing<'a> {
+    /// Claims an upload, or returns `None` if another request is appending to it.
+    fn claim(writing: &'a Mutex<HashSet<String>>, id: &str) -> Option<Writing<'a>> {
+        lock(writing).insert(id.to_owned()).then(|| Writing {
+            writing,
+            id: id.to_owned(),
+        })
+    }
+}
+
+impl Drop for Writing<'_> {
+    fn drop(&mut self) {
+        lock(self.writing).remove(&self.id);
+    }
+}
+
+fn lock(writing: &Mutex<HashSet<String>>) -> MutexGuard<'_, HashSet<String>> {
+    writing.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl<S: TusStore> Seeder for TusServer<S> {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        match self.respond(request).await {
+            Some(response) => {
+                let status = response.status();
+
+                Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Respond(Box::new(response)),
+                    rejection: Rejection::new(
+                        status,
+                        "tus_upload",
+                        "Handled a tus upload request.",
+                    ),
+                }
+            }
+            None => { Guard::Accessible(request) }
+        }
+    }
+}
+
+/// Builds an empty response carrying the protocol version.
+fn response(status: StatusCode) -> HttpResponse<BoxBody> {
+    HttpResponse::builder()
+        .status(status)
+        .header(TUS_RESUMABLE, TUS_VERSION)
+        .body(BoxBody::empty())
+        .unwrap()
+}
+
+/// Adds an `Upload-Expires` header, if the upload is incomplete and expires.
+fn expires(response: &mut HttpResponse<BoxBody>, upload: &Upload) {
+    if let Some(expires) = upload.expires.filter(|_| !upload.is_complete()) {
+        let expires = HeaderValue::try_from(http_date(expires)).unwrap();
+        response.headers_mut().insert(UPLOAD_EXPIRES, expires);
+    }
+}
+
+/// Parses a header as a non-negative integer.
+fn header_u64(request: &HttpRequest<BoxBody>, name: &HeaderName) -> Option<u64> {
+    request.headers().get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+/// Checks whether a request's body is an `application/offset+octet-stream`.
+fn is_offset_stream(request: &HttpRequest<BoxBody>) -> bool {
+    request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .and_then(|content_type| content_type.split(';').next())
+        .is_some_and(|media_type| media_type.trim().eq_ignore_ascii_case(OFFSET_OCTET_STREAM))
+}
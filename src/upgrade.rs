@@ -0,0 +1,223 @@
+//! Zero-downtime binary upgrades, by handing listening sockets over to a new process.
+//!
+//! The running (old) process binds an `UpgradeListener` on a Unix socket. When a new process
+//! starts, it calls `receive` to connect to the old one, which passes it duplicates of its
+//! listening sockets (using `SCM_RIGHTS`). Both processes can accept connections on the shared
+//! sockets in the meantime, so nothing is dropped. Once the new process has finished starting up,
+//! it calls `Upgrade::ready`, and the old process stops accepting, drains its connections, and
+//! exits.
+//!
+//! Part of the `upgrade` feature, on unix platforms only.
+
+use std::io;
+use std::mem;
+use std::net::TcpListener;
+use std::os::fd::{AsFd, AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::ptr;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, Interest};
+use tokio::net::{UnixListener, UnixStream};
+
+/// The most sockets which can be handed over at once.
+pub const MAX_FDS: usize = 32;
+
+/// Sent alongside the handed-over sockets, identifying the protocol.
+const GREETING: &[u8] = b"grazie-upgrade/1\n";
+
+/// Sent by the new process once it's ready to take over.
+const READY: &[u8] = b"ready\n";
+
+/// The flags used when sending the sockets. `MSG_NOSIGNAL` keeps a vanished peer from raising
+/// `SIGPIPE`, where it's supported.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const SEND_FLAGS: libc::c_int = libc::MSG_NOSIGNAL;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const SEND_FLAGS: libc::c_int = 0;
+
+/// The flags used when receiving the sockets. `MSG_CMSG_CLOEXEC` keeps the received sockets from
+/// leaking into child processes, where it's supported.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const RECV_FLAGS: libc::c_int = libc::MSG_CMSG_CLOEXEC;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const RECV_FLAGS: libc::c_int = 0;
+
+/// The old process's side of an upgrade, which hands its listening sockets to the new process.
+pub struct UpgradeListener {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+impl UpgradeListener {
+    /// Binds the Unix socket which new processes connect to. Any stale socket file left at the path
+    /// is replaced.
+    pub fn bind(path: impl AsRef<Path>) -> io::Result<UpgradeListener> {
+        let path = path.as_ref().to_path_buf();
+
+        match std::fs::remove_file(&path) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => { return Err(error); }
+            _ => {}
+        }
+
+        Ok(UpgradeListener {
+            listener: UnixListener::bind(&path)?,
+            path,
+        })
+    }
+
+    /// Waits for a new process to connect, hands it the provided sockets, and waits until it's
+    /// ready to take over.
+    ///
+    /// Once this returns, the old process should stop accepting connections on the sockets, drain
+    /// its remaining connections, and exit. If the new process fails before it's ready, an error is
+    /// returned, and the old process should carry on serving.
+    pub async fn hand_off<F: AsFd>(&self, sockets: &[F]) -> io::Result<()> {
+        if sockets.len() > MAX_FDS {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("at most {MAX_FDS} sockets can be handed over")));
+        }
+
+        let fds: Vec<RawFd> = sockets.iter().map(|socket| socket.as_fd().as_raw_fd()).collect();
+        let (mut stream, _) = self.listener.accept().await?;
+
+        stream.async_io(Interest::WRITABLE, || send_fds(stream.as_raw_fd(), &fds)).await?;
+
+        let mut ready = [0; READY.len()];
+        stream.read_exact(&mut ready).await?;
+
+        match ready == READY {
+            true => { Ok(()) }
+            false => { Err(io::Error::new(io::ErrorKind::InvalidData, "unexpected upgrade confirmation")) }
+        }
+    }
+}
+
+impl Drop for UpgradeListener {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// The new process's side of an upgrade, holding the sockets handed over by the old process.
+pub struct Upgrade {
+    /// The handed-over listening sockets, in the order the old process passed them.
+    pub listeners: Vec<TcpListener>,
+
+    stream: UnixStream,
+}
+
+impl Upgrade {
+    /// Tells the old process that this process is ready to take over, so that it can drain and
+    /// exit.
+    pub async fn ready(mut self) -> io::Result<()> {
+        self.stream.write_all(READY).await?;
+        self.stream.shutdown().await
+    }
+}
+
+/// Connects to a running process's `UpgradeListener`, and receives its listening sockets.
+pub async fn receive(path: impl AsRef<Path>) -> io::Result<Upgrade> {
+    let stream = UnixStream::connect(path).await?;
+    let fds = stream.async_io(Interest::READABLE, || recv_fds(stream.as_raw_fd())).await?;
+
+    let listeners = fds.into_iter().map(TcpListener::from).collect();
+
+    Ok(Upgrade { listeners, stream })
+}
+
+/// Allocates an aligned buffer for a control message holding up to `count` descriptors.
+fn control_buffer(count: usize) -> (Vec<u64>, usize) {
+    // SAFETY: `CMSG_SPACE` only computes a length.
+    let space = unsafe { libc::CMSG_SPACE((count * mem::size_of::<RawFd>()) as u32) } as usize;
+
+    (vec![0; space.div_ceil(mem::size_of::<u64>())], space)
+}
+
+/// Sends the greeting over a Unix socket, along with the provided descriptors.
+fn send_fds(socket: RawFd, fds: &[RawFd]) -> io::Result<()> {
+    let mut iov = libc::iovec {
+        iov_base: GREETING.as_ptr() as *mut libc::c_void,
+        iov_len: GREETING.len(),
+    };
+    let (mut control, space) = control_buffer(fds.len().max(1));
+
+    // SAFETY: `msghdr` is plain data, for which all zeroes is a valid (empty) value.
+    let mut message: libc::msghdr = unsafe { mem::zeroed() };
+    message.msg_iov = &mut iov;
+    message.msg_iovlen = 1;
+
+    if !fds.is_empty() {
+        message.msg_control = control.as_mut_ptr().cast();
+        message.msg_controllen = space as _;
+
+        // SAFETY: The control buffer is aligned, and sized by `CMSG_SPACE` to hold a header and
+        // every descriptor, so the header and its data are in bounds.
+        unsafe {
+            let header = libc::CMSG_FIRSTHDR(&message);
+            (*header).cmsg_level = libc::SOL_SOCKET;
+            (*header).cmsg_type = libc::SCM_RIGHTS;
+            (*header).cmsg_len = libc::CMSG_LEN(mem::size_of_val(fds) as u32) as _;
+            ptr::copy_nonoverlapping(fds.as_ptr(), libc::CMSG_DATA(header).cast::<RawFd>(), fds.len());
+        }
+    }
+
+    // SAFETY: The message points at the greeting and control buffer, which outlive the call.
+    let sent = unsafe { libc::sendmsg(socket, &message, SEND_FLAGS) };
+
+    match sent {
+        sent if sent < 0 => { Err(io::Error::last_os_error()) }
+        sent if sent as usize != GREETING.len() => { Err(io::Error::new(io::ErrorKind::WriteZero, "failed to send the upgrade greeting")) }
+        _ => { Ok(()) }
+    }
+}
+
+/// Receives the greeting over a Unix socket, along with any descriptors sent with it.
+fn recv_fds(socket: RawFd) -> io::Result<Vec<OwnedFd>> {
+    let mut buffer = [0u8; 64];
+    let mut iov = libc::iovec {
+        iov_base: buffer.as_mut_ptr().cast(),
+        iov_len: buffer.len(),
+    };
+    let (mut control, space) = control_buffer(MAX_FDS);
+
+    // SAFETY: `msghdr` is plain data, for which all zeroes is a valid (empty) value.
+    let mut message: libc::msghdr = unsafe { mem::zeroed() };
+    message.msg_iov = &mut iov;
+    message.msg_iovlen = 1;
+    message.msg_control = control.as_mut_ptr().cast();
+    message.msg_controllen = space as _;
+
+    // SAFETY: The message points at buffers which outlive the call.
+    let received = unsafe { libc::recvmsg(socket, &mut message, RECV_FLAGS) };
+    if received < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut fds = Vec::new();
+
+    // SAFETY: The kernel filled the control buffer with well-formed headers, which are walked
+    // with the `CMSG_*` macros, and each `SCM_RIGHTS` descriptor is now owned by this process.
+    unsafe {
+        let mut header = libc::CMSG_FIRSTHDR(&message);
+
+        while !header.is_null() {
+            if (*header).cmsg_level == libc::SOL_SOCKET && (*header).cmsg_type == libc::SCM_RIGHTS {
+                let data = libc::CMSG_DATA(header).cast::<RawFd>();
+                let length = (*header).cmsg_len as usize - libc::CMSG_LEN(0) as usize;
+
+                for index in 0..length / mem::size_of::<RawFd>() {
+                    fds.push(OwnedFd::from_raw_fd(ptr::read_unaligned(data.add(index))));
+                }
+            }
+
+            header = libc::CMSG_NXTHDR(&message, header);
+        }
+    }
+
+    if message.msg_flags & libc::MSG_CTRUNC != 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "too many sockets were handed over"));
+    }
+
+    match &buffer[..received as usize] == GREETING {
+        true => { Ok(fds) }
+        false => { Err(io::Error::new(io::ErrorKind::InvalidData, "unexpected upgrade greeting")) }
+    }
+}
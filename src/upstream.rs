@@ -0,0 +1,434 @@
+//! A pool of upstream servers for outbound requests, with load balancing and passive health checks.
+//!
+//! An `UpstreamPool` selects an upstream for each outbound request according to its `Strategy`,
+//! handing out a `Lease` for the upstream. The lease tracks the upstream's in-flight requests, and
+//! the outcome reported through the lease feeds the pool's passive health checks: an upstream
+//! which fails too many requests in a row is ejected from the pool for a while.
+//!
+//! `UpstreamPool::retry` runs a request under a `RetryPolicy`, sending each try to a newly selected
+//! upstream and reporting its outcome to the pool's health checks.
+//!
+//! A `CanaryPool` splits traffic between a stable pool and a canary pool by weight, keeping each
+//! client on the track it was first routed to.
+
+use crate::auth::session::{cookie, is_cookie_name, set_cookie};
+use crate::core::seeder::BoxBody;
+use crate::http::header::{HeaderName, HeaderValue};
+use crate::http::{HttpRequest, Method};
+use crate::retry::{RetryError, RetryPolicy};
+use crate::util::random_u64;
+use std::sync::atomic::{AtomicU32, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// A single upstream server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Upstream {
+    /// The authority (`host:port`) of the upstream server.
+    pub authority: String,
+
+    /// The relative weight of this upstream, used by `Strategy::Weighted`.
+    pub weight: u32,
+}
+
+impl Upstream {
+    /// Constructs a new `Upstream` with a weight of 1.
+    pub fn new(authority: impl Into<String>) -> Upstream {
+        Upstream {
+            authority: authority.into(),
+            weight: 1,
+        }
+    }
+
+    /// Sets the weight of this upstream.
+    pub fn weight(mut self, weight: u32) -> Upstream {
+        self.weight = weight;
+        self
+    }
+}
+
+/// The load-balancing strategy of an `UpstreamPool`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Cycles through each upstream in turn.
+    RoundRobin,
+
+    /// Picks the upstream with the fewest in-flight requests.
+    LeastConnections,
+
+    /// Cycles through upstreams in proportion to their weights, interleaving them smoothly.
+    Weighted,
+}
+
+/// The tracked state of an upstream within a pool.
+struct Member {
+    upstream: Upstream,
+    active: AtomicUsize,
+    failures: AtomicU32,
+    ejected_until: Mutex<Option<Instant>>,
+}
+
+impl Member {
+    /// Checks whether this upstream is currently ejected, re-admitting it once its ejection has
+    /// elapsed.
+    fn ejected(&self, now: Instant) -> bool {
+        let mut ejected_until = lock(&self.ejected_until);
+
+        match *ejected_until {
+            Some(until) if until > now => { true }
+            Some(_) => {
+                *ejected_until = None;
+                self.failures.store(0, Ordering::Relaxed);
+                false
+            }
+            None => { false }
+        }
+    }
+}
+
+/// The shared state of an `UpstreamPool`.
+struct Pool {
+    members: Vec<Arc<Member>>,
+    strategy: Strategy,
+    cursor: AtomicUsize,
+    current_weights: Mutex<Vec<i64>>,
+    max_failures: u32,
+    ejection: Duration,
+}
+
+/// A load-balanced pool of upstream servers.
+///
+/// `UpstreamPool`s are cheap to clone, and all clones share the same upstreams and health state.
+#[derive(Clone)]
+pub struct UpstreamPool {
+    pool: Arc<Pool>,
+}
+
+impl UpstreamPool {
+    /// Constructs a new `UpstreamPool`.
+    ///
+    /// By default, an upstream is ejected for 30 seconds after 5 consecutive failures.
+    pub fn new(strategy: Strategy, upstreams: impl IntoIterator<Item = Upstream>) -> UpstreamPool {
+        UpstreamPool::with_health(strategy, upstreams, 5, Duration::from_secs(30))
+    }
+
+    /// Constructs a new `UpstreamPool`, ejecting upstreams for `ejection` after `max_failures`
+    /// consecutive failures.
+    pub fn with_health(
+        strategy: Strategy,
+        upstreams: impl IntoIterator<Item = Upstream>,
+        max_failures: u32,
+        ejection: Duration,
+    ) -> UpstreamPool {
+        let members: Vec<Arc<Member>> = upstreams
+            .into_iter()
+            .map(|upstream| Arc::new(Member {
+                upstream,
+                active: AtomicUsize::new(0),
+                failures: AtomicU32::new(0),
+                ejected_until: Mutex::new(None),
+            }))
+            .collect();
+
+        UpstreamPool {
+            pool: Arc::new(Pool {
+                current_weights: Mutex::new(vec![0; members.len()]),
+                members,
+                strategy,
+                cursor: AtomicUsize::new(0),
+                max_failures: max_failures.max(1),
+                ejection,
+            }),
+        }
+    }
+
+    /// Selects an upstream for a request, according to this pool's strategy.
+    ///
+    /// Returns `None` if every upstream in the pool is currently ejected.
+    pub fn select(&self) -> Option<Lease> {
+        let now = Instant::now();
+        let healthy: Vec<usize> = (0..self.pool.members.len())
+            .filter(|index| !self.pool.members[*index].ejected(now))
+            .collect();
+
+        if healthy.is_empty() {
+            return None;
+        }
+
+        let index = match self.pool.strategy {
+            Strategy::RoundRobin => {
+                healthy[self.pool.cursor.fetch_add(1, Ordering::Relaxed) % healthy.len()]
+            }
+            Strategy::LeastConnections => {
+                let offset = self.pool.cursor.fetch_add(1, Ordering::Relaxed);
+
+                // Rotating the starting point spreads ties evenly between idle upstreams.
+                (0..healthy.len())
+                    .map(|position| healthy[(position + offset) % healthy.len()])
+                    .min_by_key(|index| self.pool.members[*index].active.load(Ordering::Relaxed))
+                    .unwrap()
+            }
+            Strategy::Weighted => { self.select_weighted(&healthy) }
+        };
+
+        let member = self.pool.members[index].clone();
+        member.active.fetch_add(1, Ordering::Relaxed);
+
+        Some(Lease {
+            member,
+            max_failures: self.pool.max_failures,
+            ejection: self.pool.ejection,
+        })
+    }
+
+    /// Gets the upstreams of this pool, along with whether each is currently healthy.
+    pub fn upstreams(&self) -> Vec<(Upstream, bool)> {
+        let now = Instant::now();
+
+        self.pool.members
+            .iter()
+            .map(|member| (member.upstream.clone(), !member.ejected(now)))
+            .collect()
+    }
+
+    /// Runs a request against upstreams selected from this pool, trying it again on failure as
+    /// `policy` allows. `attempt` is called with the upstream to send each try to.
+    ///
+    /// Each try's outcome is reported to the pool, so upstreams which keep failing are ejected, and
+    /// retries go to the upstreams which are left.
+    pub async fn retry<T, E, F, Fut>(
+        &self,
+        policy: &RetryPolicy,
+        method: &Method,
+        mut attempt: F,
+    ) -> Result<T, RetryError<E>>
+    where
+        F: FnMut(&Upstream) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        policy.deposit();
+
+        let mut tries = 0;
+        loop {
+            let lease = self.select().ok_or(RetryError::NoUpstream)?;
+            tries += 1;
+
+            match policy.attempt(attempt(lease.upstream())).await {
+                Ok(value) => {
+                    lease.success();
+                    return Ok(value);
+                }
+                Err(error) => {
+                    lease.failure();
+                    if !policy.again(method, tries).await {
+                        return Err(error);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Selects an upstream with smooth weighted round-robin, the same as nginx's.
+    fn select_weighted(&self, healthy: &[usize]) -> usize {
+        let mut current = lock(&self.pool.current_weights);
+        let total: i64 = healthy.iter().map(|index| self.pool.members[*index].upstream.weight as i64).sum();
+
+        for index in healthy {
+            current[*index] += self.pool.members[*index].upstream.weight as i64;
+        }
+
+        let selected = *healthy.iter().max_by_key(|index| current[**index]).unwrap();
+        current[selected] -= total;
+
+        selected
+    }
+}
+
+/// A lease on an upstream selected from an `UpstreamPool`.
+///
+/// The upstream counts the lease as an in-flight request until it's dropped. The outcome of the
+/// request should be reported through `success` or `failure`; a lease dropped without reporting an
+/// outcome has no effect on the upstream's health.
+pub struct Lease {
+    member: Arc<Member>,
+    max_failures: u32,
+    ejection: Duration,
+}
+
+impl Lease {
+    /// Gets the upstream this lease is for.
+    pub fn upstream(&self) -> &Upstream {
+        &self.member.upstream
+    }
+
+    /// Reports that the request to this upstream succeeded.
+    pub fn success(self) {
+        self.member.failures.store(0, Ordering::Relaxed);
+    }
+
+    /// Reports that the request to this upstream failed, ejecting it if it has now failed too many
+    /// requests in a row.
+    pub fn failure(self) {
+        let failures = self.member.failures.fetch_add(1, Ordering::Relaxed) + 1;
+
+        if failures >= self.max_failures {
+            *lock(&self.member.ejected_until) = Some(Instant::now() + self.ejection);
+        }
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        self.member.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// The track of a `CanaryPool` a request is routed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Track {
+    /// The stable pool.
+    Stable,
+
+    /// The canary pool.
+    Canary,
+}
+
+impl Track {
+    /// Gets the track's name, as it's written in the sticky cookie or header.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Track::Stable => { "stable" }
+            Track::Canary => { "canary" }
+        }
+    }
+
+    /// Parses a track from its name.
+    pub fn parse(name: &str) -> Option<Track> {
+        match name.trim() {
+            "stable" => { Some(Track::Stable) }
+            "canary" => { Some(Track::Canary) }
+            _ => { None }
+        }
+    }
+}
+
+/// A handle for adjusting a `CanaryPool`'s weight at runtime.
+///
+/// Handles are cheap to clone, and every clone adjusts the same `CanaryPool`.
+#[derive(Debug, Clone, Default)]
+pub struct CanaryHandle {
+    percent: Arc<AtomicU8>,
+}
+
+impl CanaryHandle {
+    /// Sets the percentage of new clients routed to the canary pool. Percentages above 100 are
+    /// clamped.
+    pub fn set(&self, percent: u8) {
+        self.percent.store(percent.min(100), Ordering::Relaxed);
+    }
+
+    /// Gets the percentage of new clients routed to the canary pool.
+    pub fn percent(&self) -> u8 {
+        self.percent.load(Ordering::Relaxed)
+    }
+}
+
+/// A pair of stable and canary pools, splitting traffic between them by weight.
+///
+/// Clients are kept on their track by a sticky header or cookie, which `CanaryPool::cookie` sets.
+/// Setting the canary's weight to 0 rolls every client back to the stable pool, including those
+/// stuck to the canary. Requests fall back to the stable pool while every canary upstream is
+/// ejected.
+pub struct CanaryPool {
+    stable: UpstreamPool,
+    canary: UpstreamPool,
+    handle: CanaryHandle,
+    header: HeaderName,
+    cookie: String,
+    max_age: Duration,
+}
+
+impl CanaryPool {
+    /// Constructs a new `CanaryPool`, routing `percent` of new clients to the canary pool.
+    pub fn new(stable: UpstreamPool, canary: UpstreamPool, percent: u8) -> CanaryPool {
+        let handle = CanaryHandle::default();
+        handle.set(percent);
+
+        CanaryPool {
+            stable,
+            canary,
+            handle,
+            header: HeaderName::from_static("x-canary"),
+            cookie: "grazie_canary".to_owned(),
+            max_age: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+
+    /// Sets the sticky header, which pins a request to a track. Defaults to `x-canary`.
+    pub fn header(mut self, header: HeaderName) -> CanaryPool {
+        self.header = header;
+        self
+    }
+
+    /// Sets the name of the sticky cookie. Defaults to `grazie_canary`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name isn't a valid cookie name.
+    pub fn cookie_name(mut self, name: impl Into<String>) -> CanaryPool {
+        self.cookie = name.into();
+        assert!(is_cookie_name(&self.cookie), "A sticky cookie must have a valid name!");
+        self
+    }
+
+    /// Sets how long the sticky cookie is kept for. Defaults to a day.
+    pub fn max_age(mut self, max_age: Duration) -> CanaryPool {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Gets a handle for adjusting this pool's weight at runtime.
+    pub fn handle(&self) -> CanaryHandle {
+        self.handle.clone()
+    }
+
+    /// Selects an upstream for a request, returning the track it was routed to along with its lease.
+    ///
+    /// Returns `None` if every upstream the request could be routed to is ejected.
+    pub fn select(&self, request: &HttpRequest<BoxBody>) -> Option<(Track, Lease)> {
+        let percent = self.handle.percent();
+
+        let sticky = request
+            .headers()
+            .get(&self.header)
+            .and_then(|track| track.to_str().ok())
+            .or_else(|| cookie(request, &self.cookie))
+            .and_then(Track::parse);
+
+        let track = match sticky {
+            Some(Track::Canary) if percent == 0 => { Track::Stable }
+            Some(track) => { track }
+            None if random_u64() % 100 < percent as u64 => { Track::Canary }
+            None => { Track::Stable }
+        };
+
+        match track {
+            Track::Canary => {
+                match self.canary.select() {
+                    Some(lease) => { Some((Track::Canary, lease)) }
+                    None => { self.stable.select().map(|lease| (Track::Stable, lease)) }
+                }
+            }
+            Track::Stable => { self.stable.select().map(|lease| (Track::Stable, lease)) }
+        }
+    }
+
+    /// Formats the `Set-Cookie` value which keeps a client on a track.
+    pub fn cookie(&self, track: Track) -> HeaderValue {
+        set_cookie(&self.cookie, track.as_str(), "/", self.max_age, true)
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,271 @@
+//! Small helpers shared between modules.
+
+/// Hashes bytes with 64-bit FNV-1a.
+///
+/// Unlike `std`'s default hasher, this is stable across builds and platforms, so it's suitable for
+/// values which are persisted or sent to clients, such as ETags and rollout buckets.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Generates a random 64-bit value.
+///
+/// Values are produced by SipHash under a randomly-keyed `RandomState`, over a process-wide
+/// counter. This is unpredictable without knowing the process's keys, which makes it suitable for
+/// jitter and sampling decisions, but it isn't a substitute for a CSPRNG: values which must be
+/// unguessable, such as nonces and identifiers, come from `auth::session::token`.
+pub(crate) fn random_u64() -> u64 {
+    use std::hash::{BuildHasher, RandomState};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::OnceLock;
+
+    static STATE: OnceLock<RandomState> = OnceLock::new();
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    STATE
+        .get_or_init(RandomState::new)
+        .hash_one(COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Compares two byte strings in constant time with respect to their contents.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// The standard base64 alphabet.
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes bytes as padded, standard base64.
+pub(crate) fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let block = (chunk[0] as u32) << 16
+            | (chunk.get(1).copied().unwrap_or(0) as u32) << 8
+            | chunk.get(2).copied().unwrap_or(0) as u32;
+
+        for index in 0..4 {
+            match index <= chunk.len() {
+                true => { encoded.push(BASE64[(block >> (18 - index * 6)) as usize & 0x3f] as char); }
+                false => { encoded.push('='); }
+            }
+        }
+    }
+
+    encoded
+}
+
+/// Encodes bytes as unpadded, URL-safe base64.
+#[cfg(feature = "oauth")]
+pub(crate) fn base64url_encode(bytes: &[u8]) -> String {
+    base64_encode(bytes)
+        .trim_end_matches('=')
+        .chars()
+        .map(|char| match char {
+            '+' => { '-' }
+            '/' => { '_' }
+            char => { char }
+        })
+        .collect()
+}
+
+/// Decodes standard or URL-safe base64, with or without padding. Returns `None` for malformed
+/// input.
+pub(crate) fn base64_decode(encoded: &str) -> Option<Vec<u8>> {
+    let encoded = encoded.trim().trim_end_matches('=');
+    let mut decoded = Vec::with_capacity(encoded.len() * 3 / 4);
+    let mut block = 0u32;
+    let mut bits = 0;
+
+    for byte in encoded.bytes() {
+        let value = match byte {
+            b'A'..=b'Z' => { byte - b'A' }
+            b'a'..=b'z' => { byte - b'a' + 26 }
+            b'0'..=b'9' => { byte - b'0' + 52 }
+            b'+' | b'-' => { 62 }
+            b'/' | b'_' => { 63 }
+            _ => { return None; }
+        };
+
+        block = block << 6 | value as u32;
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((block >> bits) as u8);
+        }
+    }
+
+    // A single leftover character can't encode a whole byte.
+    match bits >= 6 {
+        true => { None }
+        false => { Some(decoded) }
+    }
+}
+
+/// Percent-encodes a string for use in a query string or form body, leaving only unreserved
+/// characters as they are.
+pub(crate) fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => { encoded.push(byte as char); }
+            _ => { encoded.push_str(&format!("%{byte:02X}")); }
+        }
+    }
+
+    encoded
+}
+
+/// Decodes a percent-encoded query string component, treating `+` as a space. Returns `None` for
+/// malformed escapes or invalid UTF-8.
+pub(crate) fn percent_decode(value: &str) -> Option<String> {
+    let mut decoded = Vec::with_capacity(value.len());
+    let mut bytes = value.bytes();
+
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'+' => { decoded.push(b' '); }
+            b'%' => {
+                let high = (bytes.next()? as char).to_digit(16)?;
+                let low = (bytes.next()? as char).to_digit(16)?;
+                decoded.push((high * 16 + low) as u8);
+            }
+            byte => { decoded.push(byte); }
+        }
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+/// Splits a query string into its decoded key-value pairs, skipping malformed pairs.
+pub(crate) fn query_pairs(query: &str) -> impl Iterator<Item = (String, String)> + '_ {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .filter_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Some((percent_decode(key)?, percent_decode(value)?))
+        })
+}
+
+/// Converts days since the Unix epoch into a (year, month, day) civil date.
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+/// Converts a (year, month, day) civil date into days since the Unix epoch.
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let shifted_month = i64::from(if month > 2 { month - 3 } else { month + 9 });
+    let day_of_year = (153 * shifted_month + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Formats a time as an HTTP date (the IMF-fixdate of RFC 9110), such as
+/// `Sun, 06 Nov 1994 08:49:37 GMT`. Times before the Unix epoch are formatted as the epoch.
+pub(crate) fn http_date(time: std::time::SystemTime) -> String {
+    const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+    let seconds = time.duration_since(std::time::UNIX_EPOCH).map(|since| since.as_secs()).unwrap_or(0);
+    let days = (seconds / 86_400) as i64;
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{}, {day:02} {} {year} {:02}:{:02}:{:02} GMT",
+        DAYS[(days % 7) as usize],
+        MONTHS[month as usize - 1],
+        seconds % 86_400 / 3_600,
+        seconds % 3_600 / 60,
+        seconds % 60,
+    )
+}
+
+/// Checks that a client-provided return path is safe to redirect to, returning it as a `Location`
+/// value. Only a single local path is accepted: it must start with `/`, mustn't start with `//`,
+/// and mustn't contain `\` or control characters, since browsers treat `/\host` as `//host`.
+#[cfg(any(feature = "challenge", feature = "oauth"))]
+pub(crate) fn safe_return_path(path: &str) -> Option<crate::http::header::HeaderValue> {
+    let local = path.starts_with('/') && !path.starts_with("//");
+    let clean = !path.chars().any(|c| c == '\\' || c.is_control());
+
+    (local && clean).then(|| crate::http::header::HeaderValue::try_from(path).ok()).flatten()
+}
+
+/// Fills a template's `{name}` placeholders in from `args`, passing each value through `escape`.
+/// Placeholders without an argument are left as they are.
+///
+/// The template is scanned once, so placeholders inside a filled-in value are never filled in
+/// themselves.
+pub(crate) fn fill_placeholders(
+    template: &str,
+    args: &[(&str, &str)],
+    escape: impl Fn(&str) -> String,
+) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(open) = rest.find('{') {
+        filled.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+
+        let arg = after.find('}').and_then(|close| {
+            let name = &after[..close];
+            args.iter().find(|(arg, _)| *arg == name).map(|(_, value)| (close, value))
+        });
+
+        match arg {
+            Some((close, value)) => {
+                filled.push_str(&escape(value));
+                rest = &after[close + 1..];
+            }
+            None => {
+                filled.push('{');
+                rest = after;
+            }
+        }
+    }
+
+    filled.push_str(rest);
+    filled
+}
+
+/// Writes out a request's head as the text handed to WebAssembly and native filters: a
+/// `<method> <path and query>` line, then a `<name>: <value>` line for each header with a UTF-8
+/// value, each ending with `\n`.
+#[cfg(any(feature = "wasm", feature = "plugins"))]
+pub(crate) fn request_head(
+    request: &crate::http::HttpRequest<crate::core::seeder::BoxBody>,
+) -> String {
+    let target = request.uri().path_and_query().map_or("/", |target| target.as_str());
+    let mut head = format!("{} {target}\n", request.method());
+
+    for (name, value) in request.headers() {
+        let Ok(value) = value.to_str() else { continue; };
+        head.push_str(&format!("{name}: {value}\n"));
+    }
+
+    head
+}
@@ -0,0 +1,184 @@
+//! Request validation, for checking decoded request bodies before they reach handlers.
+//!
+//! Types implement `Validate` to check their fields, collecting every failure into
+//! `ValidationErrors`. Failed validations convert into a `422 Unprocessable Entity` `Rejection`,
+//! with the per-field errors attached as its details (under the `serde_json` feature), so that
+//! error bodies such as problem+json documents list exactly which fields failed.
+
+use crate::core::seeder::Rejection;
+use crate::http::StatusCode;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+#[cfg(feature = "serde_json")]
+use crate::core::seeder::BoxBody;
+
+#[cfg(feature = "serde_json")]
+use serde::de::DeserializeOwned;
+
+/// Implemented by types which can check their own fields.
+pub trait Validate {
+    /// Checks every field, returning all of the failures found.
+    fn validate(&self) -> Result<(), ValidationErrors>;
+}
+
+/// A single failed check on a field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FieldError {
+    /// A stable, machine-readable code for the check, such as `"length"`.
+    pub code: &'static str,
+
+    /// A human-readable message describing the failure.
+    pub message: String,
+}
+
+/// The failed checks of a validation, grouped by field.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationErrors {
+    fields: BTreeMap<String, Vec<FieldError>>,
+}
+
+impl ValidationErrors {
+    /// Constructs a new, empty `ValidationErrors`.
+    pub fn new() -> ValidationErrors {
+        ValidationErrors::default()
+    }
+
+    /// Records a failed check on a field.
+    pub fn add(&mut self, field: impl Into<String>, code: &'static str, message: impl Into<String>) {
+        self.fields
+            .entry(field.into())
+            .or_default()
+            .push(FieldError { code, message: message.into() });
+    }
+
+    /// Records a failed check on a field, if `valid` is false.
+    pub fn check(&mut self, field: &str, valid: bool, code: &'static str, message: impl Into<String>) {
+        if !valid {
+            self.add(field, code, message);
+        }
+    }
+
+    /// Validates a nested value, recording its failures with the field's name as a prefix, such as
+    /// `address.city`.
+    pub fn nested<V: Validate>(&mut self, field: &str, value: &V) {
+        if let Err(errors) = value.validate() {
+            for (name, errors) in errors.fields {
+                self.fields.entry(format!("{field}.{name}")).or_default().extend(errors);
+            }
+        }
+    }
+
+    /// Checks whether no failures were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Gets the failures recorded for a field.
+    pub fn field(&self, field: &str) -> &[FieldError] {
+        self.fields.get(field).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Gets every failure, grouped by field.
+    pub fn fields(&self) -> &BTreeMap<String, Vec<FieldError>> {
+        &self.fields
+    }
+
+    /// Converts these errors into a result, which is `Ok` if no failures were recorded.
+    pub fn into_result(self) -> Result<(), ValidationErrors> {
+        match self.is_empty() {
+            true => { Ok(()) }
+            false => { Err(self) }
+        }
+    }
+
+    /// Converts these errors into a `422 Unprocessable Entity` rejection, with the per-field
+    /// failures as its details.
+    pub fn into_rejection(self) -> Rejection {
+        let rejection = Rejection::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "validation_failed",
+            "The request body failed validation.",
+        );
+
+        #[cfg(feature = "serde_json")]
+        let rejection = rejection.details(serde_json::json!({ "errors": self.fields }));
+
+        rejection
+    }
+}
+
+impl Display for ValidationErrors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut first = true;
+
+        for (field, errors) in &self.fields {
+            for error in errors {
+                if !first {
+                    write!(f, "; ")?;
+                }
+
+                write!(f, "{field}: {}", error.message)?;
+                first = false;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+impl From<ValidationErrors> for Rejection {
+    fn from(errors: ValidationErrors) -> Rejection {
+        errors.into_rejection()
+    }
+}
+
+/// Checks whether a string's length (in characters) is within the provided bounds.
+pub fn length(value: &str, min: usize, max: usize) -> bool {
+    (min..=max).contains(&value.chars().count())
+}
+
+/// Checks whether a string contains anything other than whitespace.
+pub fn not_blank(value: &str) -> bool {
+    !value.trim().is_empty()
+}
+
+/// Checks whether a value is within the provided inclusive bounds.
+pub fn range<T: PartialOrd>(value: &T, min: &T, max: &T) -> bool {
+    value >= min && value <= max
+}
+
+/// Checks whether a string looks like an email address.
+///
+/// This only checks the address's general shape (a local part, an `@`, and a dotted domain);
+/// the only way to fully verify an address is to send mail to it.
+pub fn email(value: &str) -> bool {
+    let Some((local, domain)) = value.rsplit_once('@') else { return false; };
+
+    !local.is_empty()
+        && !value.chars().any(char::is_whitespace)
+        && domain.split('.').count() > 1
+        && domain.split('.').all(|label| !label.is_empty())
+}
+
+/// Opens a JSON body and validates it.
+///
+/// Bodies which can't be decoded are rejected with `400 Bad Request`, and bodies which fail
+/// validation are rejected with `422 Unprocessable Entity`.
+///
+/// Part of the `serde_json` feature.
+#[cfg(feature = "serde_json")]
+pub async fn open_json<T>(body: &BoxBody) -> Result<T, Rejection>
+where
+    T: DeserializeOwned + Validate,
+{
+    let value = serde_json::from_slice::<T>(body.raw_bytes()).map_err(|error| {
+        Rejection::new(StatusCode::BAD_REQUEST, "malformed_body", error.to_string())
+    })?;
+
+    value.validate().map_err(ValidationErrors::into_rejection)?;
+    Ok(value)
+}
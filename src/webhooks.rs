@@ -0,0 +1,328 @@
+//! Verifiers and typed payloads for receiving webhooks from common providers.
+//!
+//! Each provider's verifier is a `Verifier`, so it can be passed straight to a
+//! `SignatureSeeder` in front of the webhook's endpoint:
+//!
+//! ```
+//! use grazie::seeders::SignatureSeeder;
+//! use grazie::webhooks::{GitHub, Stripe};
+//!
+//! let github = SignatureSeeder::new().verify_with(GitHub::new("github secret"));
+//! let stripe = SignatureSeeder::new().verify_with(Stripe::new("whsec_..."));
+//! ```
+//!
+//! Once verified, handlers decode the payload with the provider's helpers, such as
+//! `GitHub::event` or `Stripe::event`.
+//!
+//! Part of the `webhooks` feature.
+
+use crate::core::seeder::{BoxBody, Rejection};
+use crate::http::header::HeaderName;
+use crate::http::{HttpRequest, StatusCode};
+use crate::seeders::signature::{decode_hex, unauthorized, HmacAlgorithm, HmacSignature, Verifier};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How old a timestamped signature may be by default, before it's rejected as a possible replay.
+pub const DEFAULT_TOLERANCE: Duration = Duration::from_secs(300);
+
+/// Decodes a request's JSON body, rejecting malformed bodies with `400 Bad Request`.
+pub fn payload<T: DeserializeOwned>(request: &HttpRequest<BoxBody>) -> Result<T, Rejection> {
+    serde_json::from_slice(request.body().raw_bytes())
+        .map_err(|error| {
+            Rejection::new(StatusCode::BAD_REQUEST, "malformed_payload", error.to_string())
+        })
+}
+
+/// Gets a header's value as a string.
+fn header<'a>(request: &'a HttpRequest<BoxBody>, name: &str) -> Option<&'a str> {
+    request.headers().get(name).and_then(|value| value.to_str().ok())
+}
+
+/// Checks whether a signature's timestamp is within the tolerance of the current time.
+fn fresh(timestamp: &str, tolerance: Duration, now: SystemTime) -> Result<(), Rejection> {
+    let timestamp = timestamp.trim().parse::<u64>().map_err(|_| {
+        unauthorized("invalid_signature", "The request's signature timestamp is invalid.")
+    })?;
+
+    let now = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+
+    match now.abs_diff(timestamp) <= tolerance.as_secs() {
+        true => { Ok(()) }
+        false => { Err(unauthorized("stale_signature", "The request's signature has expired.")) }
+    }
+}
+
+/// A GitHub webhook delivery.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitHubEvent<T> {
+    /// The event's name, from `X-GitHub-Event`, such as `push`.
+    pub name: String,
+
+    /// The delivery's unique ID, from `X-GitHub-Delivery`.
+    pub delivery: String,
+
+    /// The event's payload.
+    pub payload: T,
+}
+
+/// Verifies GitHub webhook deliveries, signed in the `X-Hub-Signature-256` header.
+#[derive(Debug, Clone)]
+pub struct GitHub {
+    signature: HmacSignature,
+}
+
+impl GitHub {
+    /// Constructs a new `GitHub` verifier, with the webhook's secret.
+    pub fn new(secret: impl Into<Vec<u8>>) -> GitHub {
+        let header = HeaderName::from_static("x-hub-signature-256");
+
+        GitHub {
+            signature: HmacSignature::new(HmacAlgorithm::Sha256, header, secret).prefix("sha256="),
+        }
+    }
+
+    /// Accepts deliveries signed with another secret, for rotating secrets.
+    pub fn secret(mut self, secret: impl Into<Vec<u8>>) -> GitHub {
+        self.signature = self.signature.secret(secret);
+        self
+    }
+
+    /// Decodes a delivery into its event.
+    pub fn event<T: DeserializeOwned>(
+        request: &HttpRequest<BoxBody>,
+    ) -> Result<GitHubEvent<T>, Rejection> {
+        let missing = || {
+            Rejection::new(
+                StatusCode::BAD_REQUEST,
+                "malformed_payload",
+                "The delivery is missing its event headers.",
+            )
+        };
+
+        Ok(GitHubEvent {
+            name: header(request, "x-github-event").ok_or_else(missing)?.to_owned(),
+            delivery: header(request, "x-github-delivery").ok_or_else(missing)?.to_owned(),
+            payload: payload(request)?,
+        })
+    }
+}
+
+impl Verifier for GitHub {
+    fn verify(&self, request: &HttpRequest<BoxBody>) -> Result<(), Rejection> {
+        self.signature.verify(request)
+    }
+}
+
+/// A Stripe event.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct StripeEvent<T = serde_json::Value> {
+    /// The event's unique ID, such as `evt_...`.
+    pub id: String,
+
+    /// The event's type, such as `invoice.paid`.
+    #[serde(rename = "type")]
+    pub kind: String,
+
+    /// When the event was created, in seconds since the Unix epoch.
+    pub created: u64,
+
+    /// Whether the event happened in live mode, rather than test mode.
+    #[serde(default)]
+    pub livemode: bool,
+
+    /// The object the event is about.
+    pub data: StripeData<T>,
+}
+
+/// The object a Stripe event is about.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct StripeData<T> {
+    /// The object, such as an invoice.
+    pub object: T,
+}
+
+/// Verifies Stripe webhook events, signed in the `Stripe-Signature` header.
+///
+/// Signatures are timestamped, and rejected once they're older than the tolerance, so that
+/// captured events can't be replayed.
+#[derive(Debug, Clone)]
+pub struct Stripe {
+    signature: HmacSignature,
+    tolerance: Duration,
+}
+
+impl Stripe {
+    /// Constructs a new `Stripe` verifier, with the endpoint's signing secret.
+    pub fn new(secret: impl Into<Vec<u8>>) -> Stripe {
+        let header = HeaderName::from_static("stripe-signature");
+
+        Stripe {
+            signature: HmacSignature::new(HmacAlgorithm::Sha256, header, secret),
+            tolerance: DEFAULT_TOLERANCE,
+        }
+    }
+
+    /// Accepts events signed with another secret, for rotating secrets.
+    pub fn secret(mut self, secret: impl Into<Vec<u8>>) -> Stripe {
+        self.signature = self.signature.secret(secret);
+        self
+    }
+
+    /// Sets how old a signature may be. Defaults to `DEFAULT_TOLERANCE`.
+    pub fn tolerance(mut self, tolerance: Duration) -> Stripe {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Verifies a request as of the provided time.
+    pub fn verify_at(
+        &self,
+        request: &HttpRequest<BoxBody>,
+        now: SystemTime,
+    ) -> Result<(), Rejection> {
+        let Some(header) = header(request, "stripe-signature") else {
+            return Err(unauthorized("missing_signature", "The request is missing its signature."));
+        };
+
+        let mut timestamp = None;
+        let mut signatures = Vec::new();
+
+        for pair in header.split(',') {
+            match pair.trim().split_once('=') {
+                Some(("t", value)) => { timestamp = Some(value); }
+                Some(("v1", value)) => { signatures.extend(decode_hex(value)); }
+                _ => {}
+            }
+        }
+
+        let Some(timestamp) = timestamp else {
+            return Err(unauthorized("invalid_signature", "The request's signature is invalid."));
+        };
+        fresh(timestamp, self.tolerance, now)?;
+
+        let parts: [&[u8]; 3] = [timestamp.as_bytes(), b".", request.body().raw_bytes()];
+
+        match signatures.iter().any(|signature| self.signature.matches(signature, &parts)) {
+            true => { Ok(()) }
+            false => {
+                Err(unauthorized("invalid_signature", "The request's signature is invalid."))
+            }
+        }
+    }
+
+    /// Decodes a request into its event.
+    pub fn event<T: DeserializeOwned>(
+        request: &HttpRequest<BoxBody>,
+    ) -> Result<StripeEvent<T>, Rejection> {
+        payload(request)
+    }
+}
+
+impl Verifier for Stripe {
+    fn verify(&self, request: &HttpRequest<BoxBody>) -> Result<(), Rejection> {
+        self.verify_at(request, SystemTime::now())
+    }
+}
+
+/// A Slack Events API request.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SlackEvent<T = serde_json::Value> {
+    /// Sent when the request URL is configured. The endpoint must respond with the challenge.
+    UrlVerification {
+        /// The value to respond with.
+        challenge: String,
+    },
+
+    /// An event the app is subscribed to.
+    EventCallback {
+        /// The event's unique ID.
+        event_id: String,
+
+        /// When the event happened, in seconds since the Unix epoch.
+        event_time: u64,
+
+        /// The event.
+        event: T,
+    },
+}
+
+/// Verifies Slack requests, signed in the `X-Slack-Signature` header.
+///
+/// Signatures are timestamped through `X-Slack-Request-Timestamp`, and rejected once they're
+/// older than the tolerance, so that captured requests can't be replayed.
+#[derive(Debug, Clone)]
+pub struct Slack {
+    signature: HmacSignature,
+    tolerance: Duration,
+}
+
+impl Slack {
+    /// Constructs a new `Slack` verifier, with the app's signing secret.
+    pub fn new(secret: impl Into<Vec<u8>>) -> Slack {
+        let header = HeaderName::from_static("x-slack-signature");
+
+        Slack {
+            signature: HmacSignature::new(HmacAlgorithm::Sha256, header, secret),
+            tolerance: DEFAULT_TOLERANCE,
+        }
+    }
+
+    /// Accepts requests signed with another secret, for rotating secrets.
+    pub fn secret(mut self, secret: impl Into<Vec<u8>>) -> Slack {
+        self.signature = self.signature.secret(secret);
+        self
+    }
+
+    /// Sets how old a signature may be. Defaults to `DEFAULT_TOLERANCE`.
+    pub fn tolerance(mut self, tolerance: Duration) -> Slack {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Verifies a request as of the provided time.
+    pub fn verify_at(
+        &self,
+        request: &HttpRequest<BoxBody>,
+        now: SystemTime,
+    ) -> Result<(), Rejection> {
+        let signature = header(request, "x-slack-signature");
+        let timestamp = header(request, "x-slack-request-timestamp");
+        let (Some(signature), Some(timestamp)) = (signature, timestamp) else {
+            return Err(unauthorized("missing_signature", "The request is missing its signature."));
+        };
+        fresh(timestamp, self.tolerance, now)?;
+
+        let signature = signature
+            .trim()
+            .strip_prefix("v0=")
+            .and_then(decode_hex)
+            .unwrap_or_default();
+        let parts: [&[u8]; 4] = [b"v0:", timestamp.as_bytes(), b":", request.body().raw_bytes()];
+
+        match self.signature.matches(&signature, &parts) {
+            true => { Ok(()) }
+            false => {
+                Err(unauthorized("invalid_signature", "The request's signature is invalid."))
+            }
+        }
+    }
+
+    /// Decodes an Events API request.
+    ///
+    /// Slash commands and interactivity payloads are form-encoded rather than JSON, so they
+    /// should be decoded from the body directly.
+    pub fn event<T: DeserializeOwned>(
+        request: &HttpRequest<BoxBody>,
+    ) -> Result<SlackEvent<T>, Rejection> {
+        payload(request)
+    }
+}
+
+impl Verifier for Slack {
+    fn verify(&self, request: &HttpRequest<BoxBody>) -> Result<(), Rejection> {
+        self.verify_at(request, SystemTime::now())
+    }
+}
@@ -0,0 +1,202 @@
+//! Serving of `/robots.txt`, `/.well-known/security.txt`, and other well-known documents.
+//!
+//! `WellKnown` is a `Seeder` which answers requests for its documents directly, so that these
+//! never need to reach application handlers. Requests for any other path pass straight through.
+
+use crate::core::seeder::{BoxBody, Guard, Rejection, Respondent, Seeder};
+use crate::http::header::{HeaderValue, ALLOW, CONTENT_LENGTH, CONTENT_TYPE};
+use crate::http::{HttpRequest, HttpResponse, Method, StatusCode};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Produces the body of a dynamic document for a request.
+type Render = Box<dyn Fn(&HttpRequest<BoxBody>) -> BoxBody + Send + Sync>;
+
+/// The body of a document served by `WellKnown`.
+enum Body {
+    Static(Arc<[u8]>),
+    Dynamic(Render),
+}
+
+/// A document served by `WellKnown`.
+struct Document {
+    content_type: HeaderValue,
+    body: Body,
+}
+
+/// A builder for an RFC 9116 `security.txt` document.
+///
+/// ```
+/// use grazie::wellknown::SecurityTxt;
+///
+/// let security = SecurityTxt::new()
+///     .contact("mailto:security@example.com")
+///     .expires("2030-01-01T00:00:00Z")
+///     .field("Preferred-Languages", "en, fr");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SecurityTxt {
+    fields: Vec<(String, String)>,
+}
+
+impl SecurityTxt {
+    /// Constructs a new, empty `SecurityTxt`.
+    pub fn new() -> SecurityTxt {
+        SecurityTxt::default()
+    }
+
+    /// Adds a `Contact` field, such as a `mailto:` or `https:` URI. At least one is required.
+    pub fn contact(self, contact: impl Into<String>) -> SecurityTxt {
+        self.field("Contact", contact)
+    }
+
+    /// Adds the `Expires` field, as an RFC 3339 date and time. This field is required.
+    pub fn expires(self, expires: impl Into<String>) -> SecurityTxt {
+        self.field("Expires", expires)
+    }
+
+    /// Adds an `Encryption` field, linking to a key for encrypted reports.
+    pub fn encryption(self, encryption: impl Into<String>) -> SecurityTxt {
+        self.field("Encryption", encryption)
+    }
+
+    /// Adds a `Policy` field, linking to the security policy.
+    pub fn policy(self, policy: impl Into<String>) -> SecurityTxt {
+        self.field("Policy", policy)
+    }
+
+    /// Adds any other field.
+    pub fn field(mut self, name: impl Into<String>, value: impl Into<String>) -> SecurityTxt {
+        self.fields.push((name.into(), value.into()));
+        self
+    }
+
+    /// Renders this document.
+    pub fn render(&self) -> String {
+        self.fields
+            .iter()
+            .map(|(name, value)| format!("{name}: {value}\n"))
+            .collect()
+    }
+}
+
+/// A `Seeder` which serves `/robots.txt`, `/.well-known/*` documents, and any other fixed paths.
+///
+/// Documents are answered for `GET` and `HEAD` requests; other methods are answered with
+/// `405 Method Not Allowed`.
+#[derive(Default)]
+pub struct WellKnown {
+    documents: HashMap<String, Document>,
+}
+
+impl WellKnown {
+    /// Constructs a new `WellKnown` without any documents.
+    pub fn new() -> WellKnown {
+        WellKnown::default()
+    }
+
+    /// Serves `/robots.txt` with the provided contents.
+    pub fn robots(self, robots: impl Into<String>) -> WellKnown {
+        self.path("/robots.txt", "text/plain; charset=utf-8", robots.into().into_bytes())
+    }
+
+    /// Serves `/.well-known/security.txt`.
+    pub fn security(self, security: &SecurityTxt) -> WellKnown {
+        self.well_known("security.txt", "text/plain; charset=utf-8", security.render().into_bytes())
+    }
+
+    /// Serves a static document at `/.well-known/<name>`.
+    pub fn well_known(
+        self,
+        name: &str,
+        content_type: &'static str,
+        body: impl Into<Box<[u8]>>,
+    ) -> WellKnown {
+        self.path(&format!("/.well-known/{}", name.trim_start_matches('/')), content_type, body)
+    }
+
+    /// Serves a document at `/.well-known/<name>`, rendered for each request.
+    pub fn well_known_with<F>(self, name: &str, content_type: &'static str, render: F) -> WellKnown
+    where
+        F: Fn(&HttpRequest<BoxBody>) -> BoxBody + Send + Sync + 'static,
+    {
+        self.path_with(&format!("/.well-known/{}", name.trim_start_matches('/')), content_type, render)
+    }
+
+    /// Serves a static document at any path.
+    pub fn path(mut self, path: &str, content_type: &'static str, body: impl Into<Box<[u8]>>) -> WellKnown {
+        self.documents.insert(path.to_owned(), Document {
+            content_type: HeaderValue::from_static(content_type),
+            body: Body::Static(Arc::from(body.into())),
+        });
+        self
+    }
+
+    /// Serves a document at any path, rendered for each request.
+    pub fn path_with<F>(mut self, path: &str, content_type: &'static str, render: F) -> WellKnown
+    where
+        F: Fn(&HttpRequest<BoxBody>) -> BoxBody + Send + Sync + 'static,
+    {
+        self.documents.insert(path.to_owned(), Document {
+            content_type: HeaderValue::from_static(content_type),
+            body: Body::Dynamic(Box::new(render)),
+        });
+        self
+    }
+
+    /// Builds the response for a request, if it is for one of this seeder's documents.
+    pub fn respond(&self, request: &HttpRequest<BoxBody>) -> Option<HttpResponse<BoxBody>> {
+        let document = self.documents.get(request.uri().path())?;
+
+        if request.method() != Method::GET && request.method() != Method::HEAD {
+            return Some(HttpResponse::builder()
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .header(ALLOW, "GET, HEAD")
+                .body(BoxBody::empty())
+                .unwrap());
+        }
+
+        let body = match &document.body {
+            Body::Static(bytes) => { BoxBody::new(bytes.as_ref().into()) }
+            Body::Dynamic(render) => { render(request) }
+        };
+
+        let length = body.raw_bytes().len();
+        let body = match request.method() == Method::HEAD {
+            true => { BoxBody::empty() }
+            false => { body }
+        };
+
+        Some(HttpResponse::builder()
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, document.content_type.clone())
+            .header(CONTENT_LENGTH, length)
+            .body(body)
+            .unwrap())
+    }
+}
+
+impl Seeder for WellKnown {
+    async fn seed<'a>(
+        &'a self,
+        input: Guard<'a, HttpRequest<BoxBody>>,
+    ) -> Guard<'a, HttpRequest<BoxBody>> {
+        let request = match input {
+            Guard::Accessible(request) => { request }
+            inaccessible => { return inaccessible; }
+        };
+
+        match self.respond(request) {
+            Some(response) => {
+                let status = response.status();
+
+                Guard::Inaccessible {
+                    request,
+                    respondent: Respondent::Respond(Box::new(response)),
+                    rejection: Rejection::new(status, "well_known", "Served a well-known document."),
+                }
+            }
+            None => { Guard::Accessible(request) }
+        }
+    }
+}